@@ -6,6 +6,48 @@ use syn::spanned::Spanned;
 pub(crate) struct Container {
     reconcile_with: Option<ReconcileWith>,
     hydrate_with: Option<HydrateWith>,
+    transparent: bool,
+    exhaustive: bool,
+    key_immutable: bool,
+    partial: bool,
+    digest: bool,
+    track: bool,
+    field_mask: bool,
+    deny_unknown_fields: bool,
+    key_fn: Option<KeyFn>,
+    repr: EnumRepr,
+    int_repr: bool,
+    bound: Bound,
+    rename_all: Option<RenameRule>,
+}
+
+/// Where clauses replacing the bounds the derives would generate, set with
+/// `#[autosurgeon(bound = "...")]` or `#[autosurgeon(bound(reconcile = "...", hydrate = "..."))]`
+#[derive(Clone, Default)]
+pub(crate) struct Bound {
+    reconcile: Option<Vec<syn::WherePredicate>>,
+    hydrate: Option<Vec<syn::WherePredicate>>,
+}
+
+/// A function computing the key of a container, set with `#[autosurgeon(key_fn = "...")]`
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct KeyFn {
+    pub(crate) key_fn: syn::Path,
+    /// Read the key out of the document, if not set we hydrate the whole value and apply `key_fn`
+    pub(crate) hydrate_key_fn: Option<syn::Path>,
+}
+
+/// How the variants of an enum are represented in the document
+#[derive(Clone, PartialEq, Eq, Default)]
+pub(crate) enum EnumRepr {
+    /// `"Variant"` for unit variants and `{"Variant": <fields>}` for everything else
+    #[default]
+    External,
+    /// `{<tag>: "Variant", <field>: <value>, ...}`, set with `#[autosurgeon(tag = "...")]`
+    Internal { tag: String },
+    /// `{<tag>: "Variant", <content>: <fields>}`, set with
+    /// `#[autosurgeon(tag = "...", content = "...")]`
+    Adjacent { tag: String, content: String },
 }
 
 impl Container {
@@ -16,9 +58,127 @@ impl Container {
         for attr in attrs {
             if attr.path().is_ident("autosurgeon") {
                 let attrs = AutosurgeonAttrs::from_attr(attr)?;
+                attrs.reject_rename()?;
+                attrs.reject_none_as_absent()?;
+                attrs.reject_polymorphic()?;
+                attrs.reject_map_as_entries()?;
+                attrs.reject_bytes()?;
+                attrs.reject_map_strategy()?;
+                attrs.reject_other()?;
+                attrs.reject_key()?;
+                if attrs.transparent
+                    && (attrs.with.is_some()
+                        || attrs.reconcile.is_some()
+                        || attrs.reconcile_with.is_some()
+                        || attrs.hydrate.is_some())
+                {
+                    return Err(syn::parse::Error::new(
+                        attrs.span,
+                        "cannot specify 'transparent' with 'with', 'reconcile', 'reconcile_with', or 'hydrate'",
+                    ));
+                }
+                if attrs.exhaustive
+                    && (attrs.transparent
+                        || attrs.with.is_some()
+                        || attrs.reconcile.is_some()
+                        || attrs.reconcile_with.is_some())
+                {
+                    return Err(syn::parse::Error::new(
+                        attrs.span,
+                        "cannot specify 'exhaustive' with 'transparent', 'with', 'reconcile', or 'reconcile_with'",
+                    ));
+                }
+                if attrs.tag.is_some()
+                    && (attrs.transparent
+                        || attrs.exhaustive
+                        || attrs.with.is_some()
+                        || attrs.reconcile.is_some()
+                        || attrs.reconcile_with.is_some()
+                        || attrs.hydrate.is_some())
+                {
+                    return Err(syn::parse::Error::new(
+                        attrs.span,
+                        "cannot specify 'tag' with 'transparent', 'exhaustive', 'with', 'reconcile', 'reconcile_with', or 'hydrate'",
+                    ));
+                }
+                if attrs.partial
+                    && (attrs.transparent || attrs.with.is_some() || attrs.hydrate.is_some())
+                {
+                    return Err(syn::parse::Error::new(
+                        attrs.span,
+                        "cannot specify 'partial' with 'transparent', 'with', or 'hydrate'",
+                    ));
+                }
+                if attrs.key_fn.is_some()
+                    && (attrs.transparent
+                        || attrs.with.is_some()
+                        || attrs.reconcile.is_some()
+                        || attrs.reconcile_with.is_some())
+                {
+                    return Err(syn::parse::Error::new(
+                        attrs.span,
+                        "cannot specify 'key_fn' with 'transparent', 'with', 'reconcile', or 'reconcile_with'",
+                    ));
+                }
+                let key_fn = match (attrs.key_fn.clone(), attrs.hydrate_key_fn.clone()) {
+                    (Some(key_fn), hydrate_key_fn) => Some(KeyFn {
+                        key_fn,
+                        hydrate_key_fn,
+                    }),
+                    (None, None) => None,
+                    (None, Some(_)) => {
+                        return Err(syn::parse::Error::new(
+                            attrs.span,
+                            "cannot specify 'hydrate_key_fn' without 'key_fn'",
+                        ));
+                    }
+                };
+                if attrs.int_repr
+                    && (attrs.tag.is_some()
+                        || attrs.transparent
+                        || attrs.with.is_some()
+                        || attrs.reconcile.is_some()
+                        || attrs.reconcile_with.is_some()
+                        || attrs.hydrate.is_some())
+                {
+                    return Err(syn::parse::Error::new(
+                        attrs.span,
+                        "cannot specify 'repr' with 'tag', 'transparent', 'with', 'reconcile', 'reconcile_with', or 'hydrate'",
+                    ));
+                }
+                let repr = match (attrs.tag.clone(), attrs.content.clone()) {
+                    (None, None) => EnumRepr::External,
+                    (Some(tag), None) => EnumRepr::Internal { tag },
+                    (Some(tag), Some(content)) if tag == content => {
+                        return Err(syn::parse::Error::new(
+                            attrs.span,
+                            "'tag' and 'content' must be different",
+                        ));
+                    }
+                    (Some(tag), Some(content)) => EnumRepr::Adjacent { tag, content },
+                    (None, Some(_)) => {
+                        return Err(syn::parse::Error::new(
+                            attrs.span,
+                            "cannot specify 'content' without 'tag'",
+                        ));
+                    }
+                };
                 result = Some(Container {
                     reconcile_with: ReconcileWith::from_attrs(&attrs)?,
                     hydrate_with: HydrateWith::from_attrs(&attrs)?,
+                    transparent: attrs.transparent,
+                    exhaustive: attrs.exhaustive,
+                    key_immutable: attrs.key_immutable,
+                    partial: attrs.partial,
+                    digest: attrs.digest,
+                    track: attrs.track,
+                    field_mask: attrs.field_mask,
+                    deny_unknown_fields: attrs.deny_unknown_fields,
+                    key_fn,
+                    repr,
+                    int_repr: attrs.int_repr,
+                    bound: attrs.bound.clone(),
+                    rename_all: attrs.rename_all,
                 });
             }
         }
@@ -32,6 +192,238 @@ impl Container {
     pub(crate) fn hydrate_with(&self) -> Option<TokenStream> {
         self.hydrate_with.as_ref().map(|h| h.hydrate_with())
     }
+
+    /// Whether the struct should be reconciled and hydrated as its only field
+    pub(crate) fn transparent(&self) -> bool {
+        self.transparent
+    }
+
+    /// Whether reconciling the struct should delete map entries which don't correspond to a field
+    pub(crate) fn exhaustive(&self) -> bool {
+        self.exhaustive
+    }
+
+    /// Whether reconciling should fail if the key of an item changes
+    pub(crate) fn key_immutable(&self) -> bool {
+        self.key_immutable
+    }
+
+    /// Whether to generate a companion type in which every field may be missing
+    pub(crate) fn partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Whether to implement `Reconcile::digest` by hashing the value
+    pub(crate) fn digest(&self) -> bool {
+        self.digest
+    }
+
+    /// Whether to reconcile only the fields which have been marked dirty and generate setters
+    /// which mark them
+    pub(crate) fn track(&self) -> bool {
+        self.track
+    }
+
+    /// Whether to generate a field mask type and implement `ReconcileMasked`
+    pub(crate) fn field_mask(&self) -> bool {
+        self.field_mask
+    }
+
+    /// Whether hydrating should fail if the map contains keys which don't correspond to a field
+    pub(crate) fn deny_unknown_fields(&self) -> bool {
+        self.deny_unknown_fields
+    }
+
+    /// The function computing the key, if the key isn't read from a field
+    pub(crate) fn key_fn(&self) -> Option<&KeyFn> {
+        self.key_fn.as_ref()
+    }
+
+    /// How the variants of an enum are represented
+    pub(crate) fn repr(&self) -> &EnumRepr {
+        &self.repr
+    }
+
+    /// Whether the variants of a fieldless enum are represented as their integer discriminants
+    pub(crate) fn int_repr(&self) -> bool {
+        self.int_repr
+    }
+
+    /// The bounds to use instead of `T: Reconcile` for every type parameter, if set
+    pub(crate) fn reconcile_bound(&self) -> Option<&[syn::WherePredicate]> {
+        self.bound.reconcile.as_deref()
+    }
+
+    /// The bounds to use instead of `T: Hydrate` for every type parameter, if set
+    pub(crate) fn hydrate_bound(&self) -> Option<&[syn::WherePredicate]> {
+        self.bound.hydrate.as_deref()
+    }
+
+    /// The case convention applied to the names of fields and variants which aren't renamed
+    pub(crate) fn rename_all(&self) -> Option<RenameRule> {
+        self.rename_all
+    }
+}
+
+/// A case convention for the names of fields and variants in the document, set with
+/// `#[autosurgeon(rename_all = "...")]`. The conversions are the same as serde's, so a type which
+/// derives both `Serialize` and `Reconcile` with the same rule uses the same names in both.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn from_lit(s: &syn::LitStr) -> syn::parse::Result<Self> {
+        Ok(match s.value().as_str() {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => {
+                return Err(syn::parse::Error::new(
+                    s.span(),
+                    "expected one of 'lowercase', 'UPPERCASE', 'PascalCase', 'camelCase', 'snake_case', 'SCREAMING_SNAKE_CASE', 'kebab-case', or 'SCREAMING-KEBAB-CASE'",
+                ))
+            }
+        })
+    }
+
+    /// Apply the rule to a variant name, which is assumed to be in `PascalCase`
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            Self::Pascal => variant.to_string(),
+            Self::Lower => variant.to_ascii_lowercase(),
+            Self::Upper => variant.to_ascii_uppercase(),
+            Self::Camel => {
+                let mut chars = variant.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            }
+            Self::Snake => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            }
+            Self::ScreamingSnake => Self::Snake.apply_to_variant(variant).to_ascii_uppercase(),
+            Self::Kebab => Self::Snake.apply_to_variant(variant).replace('_', "-"),
+            Self::ScreamingKebab => Self::ScreamingSnake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+
+    /// Apply the rule to a field name, which is assumed to be in `snake_case`
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            Self::Lower | Self::Snake => field.to_string(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            Self::Camel => {
+                let pascal = Self::Pascal.apply_to_field(field);
+                let mut chars = pascal.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            }
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+/// Add a `rename` to every named field and every variant in `data` which doesn't already have
+/// one, so the rest of the derives only have to deal with renames of individual fields and
+/// variants
+pub(crate) fn apply_rename_all(data: &mut syn::Data, rule: RenameRule) {
+    use syn::ext::IdentExt;
+
+    let rename_fields = |fields: &mut syn::Fields| {
+        if let syn::Fields::Named(fields) = fields {
+            for field in fields.named.iter_mut() {
+                if let Some(ident) = &field.ident {
+                    let name = rule.apply_to_field(&ident.unraw().to_string());
+                    add_rename(&mut field.attrs, name);
+                }
+            }
+        }
+    };
+    match data {
+        syn::Data::Struct(s) => rename_fields(&mut s.fields),
+        syn::Data::Enum(e) => {
+            for variant in e.variants.iter_mut() {
+                let name = rule.apply_to_variant(&variant.ident.unraw().to_string());
+                add_rename(&mut variant.attrs, name);
+                rename_fields(&mut variant.fields);
+            }
+        }
+        syn::Data::Union(_) => {}
+    }
+}
+
+/// Add `rename = "<name>"` to the `autosurgeon` attribute in `attrs`, unless it already has a
+/// `rename`. Attributes which fail to parse are left alone so the error is reported when they are
+/// parsed for real.
+fn add_rename(attrs: &mut Vec<syn::Attribute>, name: String) {
+    match attrs.iter_mut().find(|a| a.path().is_ident("autosurgeon")) {
+        Some(attr) => {
+            if !matches!(AutosurgeonAttrs::from_attr(attr), Ok(a) if a.rename.is_none()) {
+                return;
+            }
+            if let syn::Meta::List(list) = &mut attr.meta {
+                let ends_with_comma = matches!(
+                    list.tokens.clone().into_iter().last(),
+                    Some(proc_macro2::TokenTree::Punct(p)) if p.as_char() == ','
+                );
+                if !list.tokens.is_empty() && !ends_with_comma {
+                    list.tokens.extend(quote!(,));
+                }
+                list.tokens.extend(quote!(rename = #name));
+            }
+        }
+        None => attrs.push(syn::parse_quote!(#[autosurgeon(rename = #name)])),
+    }
+}
+
+/// What to do with keys in the document which are missing from a map, set with
+/// `#[autosurgeon(map_strategy = "...")]`
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MapStrategy {
+    DeleteAbsent,
+    KeepAbsent,
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -187,6 +579,9 @@ pub(crate) struct Field {
     reconcile_with: Option<ReconcileWith>,
     hydrate_with: Option<HydrateWith>,
     missing: Option<syn::Path>,
+    none_as_absent: bool,
+    key: bool,
+    rename: Option<String>,
 }
 
 impl Field {
@@ -200,11 +595,94 @@ impl Field {
                         "duplicate autosurgeon attribute",
                     ));
                 }
-                let attrs = AutosurgeonAttrs::from_attr(attr)?;
+                let mut attrs = AutosurgeonAttrs::from_attr(attr)?;
+                attrs.reject_container_attrs()?;
+                if field.ident.is_none() {
+                    attrs.reject_rename()?;
+                    attrs.reject_none_as_absent()?;
+                }
+                attrs.reject_other()?;
+                if attrs.none_as_absent {
+                    if attrs.with.is_some()
+                        || attrs.reconcile.is_some()
+                        || attrs.reconcile_with.is_some()
+                        || attrs.polymorphic.is_some()
+                        || attrs.map_as_entries
+                        || attrs.bytes
+                        || attrs.map_strategy.is_some()
+                    {
+                        return Err(syn::parse::Error::new(
+                            attrs.span,
+                            "cannot specify 'none_as_absent' with 'with', 'reconcile', 'reconcile_with', 'polymorphic', 'map_as_entries', 'bytes', or 'map_strategy'",
+                        ));
+                    }
+                    // An absent field hydrates as `None`
+                    if attrs.missing.is_none() {
+                        attrs.missing = Some(syn::parse_quote!(::std::default::Default::default));
+                    }
+                }
+                if let Some(registry) = attrs.polymorphic.take() {
+                    if attrs.with.is_some()
+                        || attrs.reconcile.is_some()
+                        || attrs.reconcile_with.is_some()
+                        || attrs.hydrate.is_some()
+                    {
+                        return Err(syn::parse::Error::new(
+                            attrs.span,
+                            "cannot specify 'polymorphic' with 'with', 'reconcile', 'reconcile_with', or 'hydrate'",
+                        ));
+                    }
+                    // A registry is just a module with `reconcile` and `hydrate` functions
+                    attrs.with = Some(registry);
+                }
+                if attrs.map_as_entries {
+                    if attrs.with.is_some()
+                        || attrs.reconcile.is_some()
+                        || attrs.reconcile_with.is_some()
+                        || attrs.hydrate.is_some()
+                    {
+                        return Err(syn::parse::Error::new(
+                            attrs.span,
+                            "cannot specify 'map_as_entries' with 'with', 'reconcile', 'reconcile_with', 'hydrate', or 'polymorphic'",
+                        ));
+                    }
+                    attrs.with = Some(syn::parse_quote!(::autosurgeon::map_entries));
+                }
+                if attrs.bytes {
+                    if attrs.with.is_some()
+                        || attrs.reconcile.is_some()
+                        || attrs.reconcile_with.is_some()
+                        || attrs.hydrate.is_some()
+                    {
+                        return Err(syn::parse::Error::new(
+                            attrs.span,
+                            "cannot specify 'bytes' with 'with', 'reconcile', 'reconcile_with', 'hydrate', 'polymorphic', or 'map_as_entries'",
+                        ));
+                    }
+                    attrs.with = Some(syn::parse_quote!(::autosurgeon::bytes::scalar));
+                }
+                if let Some(strategy) = attrs.map_strategy {
+                    if attrs.with.is_some()
+                        || attrs.reconcile.is_some()
+                        || attrs.reconcile_with.is_some()
+                    {
+                        return Err(syn::parse::Error::new(
+                            attrs.span,
+                            "cannot specify 'map_strategy' with 'with', 'reconcile', 'reconcile_with', 'polymorphic', 'map_as_entries', or 'bytes'",
+                        ));
+                    }
+                    if strategy == MapStrategy::KeepAbsent {
+                        attrs.reconcile =
+                            Some(syn::parse_quote!(::autosurgeon::map_strategy::keep_absent));
+                    }
+                }
                 result = Some(Field {
                     reconcile_with: ReconcileWith::from_attrs(&attrs)?,
                     hydrate_with: HydrateWith::from_attrs(&attrs)?,
                     missing: attrs.missing.clone(),
+                    none_as_absent: attrs.none_as_absent,
+                    key: attrs.key,
+                    rename: attrs.rename.clone(),
                 });
             }
         }
@@ -222,6 +700,21 @@ impl Field {
     pub(crate) fn missing(&self) -> Option<&syn::Path> {
         self.missing.as_ref()
     }
+
+    /// Whether `None` is reconciled by deleting the field from the map rather than writing null
+    pub(crate) fn none_as_absent(&self) -> bool {
+        self.none_as_absent
+    }
+
+    /// Whether this field is (part of) the key of the struct, an alternative to `#[key]`
+    pub(crate) fn key(&self) -> bool {
+        self.key
+    }
+
+    /// The name of the field in the document, if it isn't the field identifier
+    pub(crate) fn rename(&self) -> Option<&str> {
+        self.rename.as_deref()
+    }
 }
 
 // This is different to `Field` because we don't allow `reconcile=` on enum newtype fields. Why?,
@@ -276,6 +769,15 @@ impl EnumNewtypeAttrs {
                 ));
             }
             let attrs = AutosurgeonAttrs::from_attr(attr)?;
+            attrs.reject_container_attrs()?;
+            attrs.reject_rename()?;
+            attrs.reject_none_as_absent()?;
+            attrs.reject_polymorphic()?;
+            attrs.reject_map_as_entries()?;
+            attrs.reject_bytes()?;
+            attrs.reject_map_strategy()?;
+            attrs.reject_other()?;
+            attrs.reject_key()?;
             let hydrate_with = HydrateWith::from_attrs(&attrs)?;
             if attrs.reconcile.is_some() {
                 return Err(syn::parse::Error::new(
@@ -297,6 +799,82 @@ impl EnumNewtypeAttrs {
     }
 }
 
+/// Attributes on an enum variant itself, rather than on its fields
+#[derive(Default)]
+pub(crate) struct Variant {
+    rename: Option<String>,
+    /// Whether this is the variant to hydrate when the discriminant in the document isn't the
+    /// discriminant of any variant
+    other: bool,
+}
+
+impl Variant {
+    pub(crate) fn from_variant(variant: &syn::Variant) -> Result<Option<Self>, syn::parse::Error> {
+        let mut result = None;
+        for attr in &variant.attrs {
+            if attr.path().is_ident("autosurgeon") {
+                if result.is_some() {
+                    return Err(syn::parse::Error::new(
+                        attr.span(),
+                        "duplicate autosurgeon attribute",
+                    ));
+                }
+                let attrs = AutosurgeonAttrs::from_attr(attr)?;
+                attrs.reject_container_attrs()?;
+                if attrs.with.is_some()
+                    || attrs.reconcile.is_some()
+                    || attrs.reconcile_with.is_some()
+                    || attrs.hydrate.is_some()
+                    || attrs.missing.is_some()
+                    || attrs.none_as_absent
+                    || attrs.polymorphic.is_some()
+                    || attrs.map_as_entries
+                    || attrs.bytes
+                    || attrs.map_strategy.is_some()
+                    || attrs.key
+                {
+                    return Err(syn::parse::Error::new(
+                        attrs.span,
+                        "only 'rename' and 'other' are allowed on enum variants",
+                    ));
+                }
+                if attrs.other {
+                    match &variant.fields {
+                        syn::Fields::Unit => {}
+                        syn::Fields::Unnamed(f)
+                            if f.unnamed.len() == 1
+                                && !f.unnamed[0]
+                                    .attrs
+                                    .iter()
+                                    .any(|a| a.path().is_ident("autosurgeon")) => {}
+                        _ => {
+                            return Err(syn::parse::Error::new(
+                                attrs.span,
+                                "'other' is only allowed on unit variants and newtype variants without field attributes",
+                            ))
+                        }
+                    }
+                }
+                result = Some(Variant {
+                    rename: attrs.rename,
+                    other: attrs.other,
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    /// The name of the variant in the document, which is the variant identifier unless it was
+    /// renamed
+    pub(crate) fn discriminant(&self, ident: &syn::Ident) -> String {
+        self.rename.clone().unwrap_or_else(|| ident.to_string())
+    }
+
+    pub(crate) fn other(&self) -> bool {
+        self.other
+    }
+}
+
 struct AutosurgeonAttrs {
     span: proc_macro2::Span,
     reconcile: Option<syn::Path>,
@@ -304,6 +882,29 @@ struct AutosurgeonAttrs {
     with: Option<syn::Path>,
     hydrate: Option<syn::Path>,
     missing: Option<syn::Path>,
+    none_as_absent: bool,
+    polymorphic: Option<syn::Path>,
+    map_as_entries: bool,
+    bytes: bool,
+    map_strategy: Option<MapStrategy>,
+    transparent: bool,
+    exhaustive: bool,
+    key_immutable: bool,
+    partial: bool,
+    digest: bool,
+    track: bool,
+    field_mask: bool,
+    deny_unknown_fields: bool,
+    key: bool,
+    key_fn: Option<syn::Path>,
+    hydrate_key_fn: Option<syn::Path>,
+    rename: Option<String>,
+    other: bool,
+    tag: Option<String>,
+    content: Option<String>,
+    int_repr: bool,
+    bound: Bound,
+    rename_all: Option<RenameRule>,
 }
 
 impl AutosurgeonAttrs {
@@ -315,6 +916,29 @@ impl AutosurgeonAttrs {
             with: None,
             hydrate: None,
             missing: None,
+            none_as_absent: false,
+            polymorphic: None,
+            map_as_entries: false,
+            bytes: false,
+            map_strategy: None,
+            transparent: false,
+            exhaustive: false,
+            key_immutable: false,
+            partial: false,
+            digest: false,
+            track: false,
+            field_mask: false,
+            deny_unknown_fields: false,
+            key: false,
+            key_fn: None,
+            hydrate_key_fn: None,
+            rename: None,
+            other: false,
+            tag: None,
+            content: None,
+            int_repr: false,
+            bound: Bound::default(),
+            rename_all: None,
         };
         attr.parse_nested_meta(|meta| {
             if meta.path.is_ident("reconcile") {
@@ -337,6 +961,97 @@ impl AutosurgeonAttrs {
                 let value = meta.value()?;
                 let s: syn::LitStr = value.parse()?;
                 result.missing = Some(s.parse()?);
+            } else if meta.path.is_ident("none_as_absent") {
+                result.none_as_absent = true;
+            } else if meta.path.is_ident("polymorphic") {
+                let value = meta.value()?;
+                let s: syn::LitStr = value.parse()?;
+                result.polymorphic = Some(s.parse()?);
+            } else if meta.path.is_ident("map_as_entries") {
+                result.map_as_entries = true;
+            } else if meta.path.is_ident("bytes") {
+                result.bytes = true;
+            } else if meta.path.is_ident("map_strategy") {
+                let value = meta.value()?;
+                let s: syn::LitStr = value.parse()?;
+                result.map_strategy = Some(match s.value().as_str() {
+                    "delete_absent" => MapStrategy::DeleteAbsent,
+                    "keep_absent" => MapStrategy::KeepAbsent,
+                    _ => {
+                        return Err(syn::parse::Error::new(
+                            s.span(),
+                            "expected 'delete_absent' or 'keep_absent'",
+                        ))
+                    }
+                });
+            } else if meta.path.is_ident("transparent") {
+                result.transparent = true;
+            } else if meta.path.is_ident("exhaustive") {
+                result.exhaustive = true;
+            } else if meta.path.is_ident("key_immutable") {
+                result.key_immutable = true;
+            } else if meta.path.is_ident("partial") {
+                result.partial = true;
+            } else if meta.path.is_ident("digest") {
+                result.digest = true;
+            } else if meta.path.is_ident("track") {
+                result.track = true;
+            } else if meta.path.is_ident("field_mask") {
+                result.field_mask = true;
+            } else if meta.path.is_ident("deny_unknown_fields") {
+                result.deny_unknown_fields = true;
+            } else if meta.path.is_ident("key") {
+                result.key = true;
+            } else if meta.path.is_ident("key_fn") {
+                let value = meta.value()?;
+                let s: syn::LitStr = value.parse()?;
+                result.key_fn = Some(s.parse()?);
+            } else if meta.path.is_ident("hydrate_key_fn") {
+                let value = meta.value()?;
+                let s: syn::LitStr = value.parse()?;
+                result.hydrate_key_fn = Some(s.parse()?);
+            } else if meta.path.is_ident("rename") {
+                let value = meta.value()?;
+                let s: syn::LitStr = value.parse()?;
+                result.rename = Some(s.value());
+            } else if meta.path.is_ident("rename_all") {
+                let value = meta.value()?;
+                let s: syn::LitStr = value.parse()?;
+                result.rename_all = Some(RenameRule::from_lit(&s)?);
+            } else if meta.path.is_ident("other") {
+                result.other = true;
+            } else if meta.path.is_ident("tag") {
+                let value = meta.value()?;
+                let s: syn::LitStr = value.parse()?;
+                result.tag = Some(s.value());
+            } else if meta.path.is_ident("content") {
+                let value = meta.value()?;
+                let s: syn::LitStr = value.parse()?;
+                result.content = Some(s.value());
+            } else if meta.path.is_ident("repr") {
+                let value = meta.value()?;
+                let s: syn::LitStr = value.parse()?;
+                if s.value() != "int" {
+                    return Err(syn::parse::Error::new(s.span(), "expected 'int'"));
+                }
+                result.int_repr = true;
+            } else if meta.path.is_ident("bound") {
+                if meta.input.peek(syn::token::Paren) {
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("reconcile") {
+                            result.bound.reconcile = Some(parse_where_predicates(meta.value()?)?);
+                        } else if meta.path.is_ident("hydrate") {
+                            result.bound.hydrate = Some(parse_where_predicates(meta.value()?)?);
+                        } else {
+                            return Err(meta.error("expected 'reconcile' or 'hydrate'"));
+                        }
+                        Ok(())
+                    })?;
+                } else {
+                    let predicates = parse_where_predicates(meta.value()?)?;
+                    result.bound.reconcile = Some(predicates.clone());
+                    result.bound.hydrate = Some(predicates);
+                }
             } else {
                 return Err(meta.error("unknown attribute"));
             }
@@ -344,4 +1059,175 @@ impl AutosurgeonAttrs {
         })?;
         Ok(result)
     }
+
+    fn reject_rename(&self) -> syn::parse::Result<()> {
+        if self.rename.is_some() {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'rename' is only allowed on enum variants and named fields",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn reject_other(&self) -> syn::parse::Result<()> {
+        if self.other {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'other' is only allowed on enum variants",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn reject_key(&self) -> syn::parse::Result<()> {
+        if self.key {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'key' is only allowed on struct fields and enum variant fields",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn reject_none_as_absent(&self) -> syn::parse::Result<()> {
+        if self.none_as_absent {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'none_as_absent' is only allowed on named fields",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn reject_polymorphic(&self) -> syn::parse::Result<()> {
+        if self.polymorphic.is_some() {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'polymorphic' is not allowed on containers, variants, or newtype variant fields",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn reject_map_as_entries(&self) -> syn::parse::Result<()> {
+        if self.map_as_entries {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'map_as_entries' is not allowed on containers, variants, or newtype variant fields",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn reject_bytes(&self) -> syn::parse::Result<()> {
+        if self.bytes {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'bytes' is not allowed on containers, variants, or newtype variant fields",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn reject_map_strategy(&self) -> syn::parse::Result<()> {
+        if self.map_strategy.is_some() {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'map_strategy' is not allowed on containers, variants, or newtype variant fields",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reject attributes which only make sense on a container
+    fn reject_container_attrs(&self) -> syn::parse::Result<()> {
+        if self.transparent {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'transparent' is only allowed on structs",
+            ))
+        } else if self.exhaustive {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'exhaustive' is only allowed on structs",
+            ))
+        } else if self.key_immutable {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'key_immutable' is only allowed on structs and enums",
+            ))
+        } else if self.partial {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'partial' is only allowed on structs",
+            ))
+        } else if self.digest {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'digest' is only allowed on structs and enums",
+            ))
+        } else if self.track {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'track' is only allowed on structs",
+            ))
+        } else if self.field_mask {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'field_mask' is only allowed on structs",
+            ))
+        } else if self.deny_unknown_fields {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'deny_unknown_fields' is only allowed on structs",
+            ))
+        } else if self.key_fn.is_some() || self.hydrate_key_fn.is_some() {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'key_fn' and 'hydrate_key_fn' are only allowed on structs and enums",
+            ))
+        } else if self.tag.is_some() || self.content.is_some() {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'tag' and 'content' are only allowed on enums",
+            ))
+        } else if self.int_repr {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'repr' is only allowed on enums",
+            ))
+        } else if self.bound.reconcile.is_some() || self.bound.hydrate.is_some() {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'bound' is only allowed on structs and enums",
+            ))
+        } else if self.rename_all.is_some() {
+            Err(syn::parse::Error::new(
+                self.span,
+                "'rename_all' is only allowed on structs and enums",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Parse a string literal containing a comma separated list of where clause predicates
+fn parse_where_predicates(
+    input: syn::parse::ParseStream<'_>,
+) -> syn::parse::Result<Vec<syn::WherePredicate>> {
+    let s: syn::LitStr = input.parse()?;
+    let predicates = s.parse_with(
+        syn::punctuated::Punctuated::<syn::WherePredicate, syn::Token![,]>::parse_terminated,
+    )?;
+    Ok(predicates.into_iter().collect())
 }
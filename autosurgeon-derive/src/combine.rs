@@ -0,0 +1,81 @@
+/// An error which can absorb other errors of the same type
+///
+/// We use this to report every invalid field or variant in a derive input at once rather than
+/// stopping at the first one, which makes for a much shorter fix-compile loop.
+pub(crate) trait Combine {
+    fn combine(&mut self, other: Self);
+}
+
+impl Combine for syn::Error {
+    fn combine(&mut self, other: Self) {
+        syn::Error::combine(self, other)
+    }
+}
+
+/// Like `iter.collect::<Result<Vec<_>, _>>()` but rather than stopping at the first error this
+/// consumes the whole iterator and returns all the errors combined
+pub(crate) fn collect_all<T, E, I>(iter: I) -> Result<Vec<T>, E>
+where
+    E: Combine,
+    I: IntoIterator<Item = Result<T, E>>,
+{
+    let mut oks = Vec::new();
+    let mut err: Option<E> = None;
+    for result in iter {
+        match (result, &mut err) {
+            (Ok(t), _) => oks.push(t),
+            (Err(e), Some(existing)) => existing.combine(e),
+            (Err(e), None) => err = Some(e),
+        }
+    }
+    match err {
+        Some(e) => Err(e),
+        None => Ok(oks),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::{parse_quote, DeriveInput};
+
+    /// The messages of every `compile_error!` in `tokens`
+    fn compile_errors(tokens: proc_macro2::TokenStream) -> Vec<String> {
+        let file: syn::File = syn::parse2(tokens).unwrap();
+        file.items
+            .into_iter()
+            .filter_map(|item| match item {
+                syn::Item::Macro(m)
+                    if m.mac.path.segments.last().unwrap().ident == "compile_error" =>
+                {
+                    Some(m.mac.parse_body::<syn::LitStr>().unwrap().value())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported() {
+        let input: DeriveInput = parse_quote! {
+            struct Contact {
+                #[autosurgeon(bogus)]
+                name: String,
+                email: String,
+                #[autosurgeon(also_bogus)]
+                phone: String,
+            }
+        };
+        let expected = vec!["unknown attribute", "unknown attribute"];
+        assert_eq!(
+            compile_errors(crate::hydrate::expand(input.clone())),
+            expected
+        );
+        assert_eq!(
+            compile_errors(crate::reconcile::expand(
+                input,
+                crate::reconcile::Derive::Reconcile
+            )),
+            expected
+        );
+    }
+}
@@ -1,10 +1,10 @@
 use proc_macro2::{Span, TokenStream};
-use quote::{quote, quote_spanned};
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
     parse_macro_input, parse_quote, spanned::Spanned, DeriveInput, Fields, GenericParam, Generics,
 };
 
-use crate::attrs;
+use crate::{attrs, combine::collect_all};
 mod named_field;
 mod newtype_field;
 mod unnamed_field;
@@ -12,45 +12,100 @@ mod variant_fields;
 
 pub fn derive_hydrate(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    proc_macro::TokenStream::from(expand(input))
+}
 
+/// The implementation of `Hydrate` for `input`, or the compile errors explaining why there isn't
+/// one
+pub(crate) fn expand(mut input: DeriveInput) -> TokenStream {
     let container_attrs = match attrs::Container::from_attrs(input.attrs.iter()) {
         Ok(a) => a.unwrap_or_default(),
         Err(e) => {
-            return proc_macro::TokenStream::from(
-                syn::Error::new(input.span(), e.to_string()).into_compile_error(),
-            );
+            return syn::Error::new(input.span(), e.to_string()).into_compile_error();
         }
     };
+    if let Some(rule) = container_attrs.rename_all() {
+        attrs::apply_rename_all(&mut input.data, rule);
+    }
+
+    let generics = add_trait_bounds(
+        input.generics.clone(),
+        &input.data,
+        container_attrs.hydrate_bound(),
+    );
 
     if let Some(hydrate_with) = container_attrs.hydrate_with() {
-        return proc_macro::TokenStream::from(on_hydrate_with(&input, &hydrate_with));
+        return on_hydrate_with(&input, &generics, &hydrate_with);
     }
 
     let result = match &input.data {
-        syn::Data::Struct(datastruct) => on_struct(&input, datastruct),
-        syn::Data::Enum(dataenum) => on_enum(&input, dataenum),
+        syn::Data::Struct(_) if *container_attrs.repr() != attrs::EnumRepr::External => {
+            Err(error::DeriveError::Tag)
+        }
+        syn::Data::Struct(_) if container_attrs.int_repr() => Err(error::DeriveError::IntRepr),
+        syn::Data::Struct(_)
+            if container_attrs.transparent() && container_attrs.deny_unknown_fields() =>
+        {
+            Err(error::DeriveError::DenyUnknownFields)
+        }
+        syn::Data::Struct(datastruct) if container_attrs.transparent() => {
+            on_transparent_struct(&input, &generics, datastruct)
+        }
+        syn::Data::Struct(datastruct) => on_struct(
+            &input,
+            &generics,
+            datastruct,
+            container_attrs.partial(),
+            container_attrs.deny_unknown_fields(),
+        ),
+        _ if container_attrs.transparent() => Err(error::DeriveError::Transparent),
+        _ if container_attrs.partial() => Err(error::DeriveError::Partial),
+        _ if container_attrs.deny_unknown_fields() => Err(error::DeriveError::DenyUnknownFields),
+        syn::Data::Enum(dataenum) if container_attrs.int_repr() => {
+            on_int_enum(&input, &generics, dataenum)
+        }
+        syn::Data::Enum(dataenum) => match container_attrs.repr() {
+            attrs::EnumRepr::External => on_enum(&input, &generics, dataenum),
+            repr => on_tagged_enum(&input, &generics, dataenum, repr),
+        },
         _ => todo!(),
     };
-    let tokens = match result {
+    match result {
         Ok(t) => t,
-        Err(e) => syn::Error::new(e.span().unwrap_or_else(|| input.span()), e.to_string())
-            .to_compile_error(),
-    };
-
-    proc_macro::TokenStream::from(tokens)
+        Err(e) => e.to_compile_error(input.span()),
+    }
 }
 
-fn add_trait_bounds(mut generics: Generics) -> Generics {
+/// Add `T: Hydrate` to every type parameter which is used outside of a `PhantomData`, or if the user
+/// specified `bound = "..."` then add their bounds instead
+fn add_trait_bounds(
+    mut generics: Generics,
+    data: &syn::Data,
+    bound: Option<&[syn::WherePredicate]>,
+) -> Generics {
+    if let Some(bound) = bound {
+        generics
+            .make_where_clause()
+            .predicates
+            .extend(bound.iter().cloned());
+        return generics;
+    }
     for param in &mut generics.params {
         if let GenericParam::Type(ref mut type_param) = *param {
+            if !crate::used_outside_phantom_data(data, &type_param.ident) {
+                continue;
+            }
             type_param.bounds.push(parse_quote!(::autosurgeon::Hydrate));
         }
     }
     generics
 }
 
-fn on_hydrate_with(input: &DeriveInput, hydrate_with: &TokenStream) -> TokenStream {
-    let generics = add_trait_bounds(input.generics.clone());
+fn on_hydrate_with(
+    input: &DeriveInput,
+    generics: &Generics,
+    hydrate_with: &TokenStream,
+) -> TokenStream {
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     let name = &input.ident;
 
@@ -69,55 +124,77 @@ fn on_hydrate_with(input: &DeriveInput, hydrate_with: &TokenStream) -> TokenStre
 
 fn on_struct(
     input: &DeriveInput,
+    generics: &Generics,
     datastruct: &syn::DataStruct,
+    partial: bool,
+    deny_unknown_fields: bool,
 ) -> Result<TokenStream, error::DeriveError> {
     let name = &input.ident;
 
-    let generics = add_trait_bounds(input.generics.clone());
-
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     match datastruct.fields {
         Fields::Named(ref fields) => {
-            let fields = fields
-                .named
-                .iter()
-                .map(|field| named_field::NamedField::new(field, field.ident.as_ref().unwrap()))
-                .collect::<Result<Vec<_>, _>>()?;
-            let the_impl = gen_named_struct_impl(name, &fields);
+            let fields =
+                collect_all(fields.named.iter().map(|field| {
+                    named_field::NamedField::new(field, field.ident.as_ref().unwrap())
+                }))?;
+            let the_impl = gen_named_struct_impl(name, &fields, deny_unknown_fields);
+            let partial = if partial {
+                gen_partial_struct(input, generics, &fields)
+            } else {
+                quote!()
+            };
 
             Ok(quote! {
                 impl #impl_generics ::autosurgeon::Hydrate for #name #ty_generics #where_clause {
                     #the_impl
                 }
+
+                #partial
             })
         }
+        _ if partial => Err(error::DeriveError::Partial),
+        _ if deny_unknown_fields => Err(error::DeriveError::DenyUnknownFields),
         Fields::Unnamed(ref fields) => {
             if fields.unnamed.len() == 1 {
-                Ok(gen_newtype_struct_wrapper(input, fields, &generics)?)
+                let field = fields.unnamed.first().unwrap();
+                Ok(gen_newtype_struct_wrapper(input, field, generics)?)
             } else {
-                gen_tuple_struct_wrapper(input, fields, &generics)
+                gen_tuple_struct_wrapper(input, fields, generics)
             }
         }
         Fields::Unit => Err(error::DeriveError::HydrateForUnit),
     }
 }
 
+fn on_transparent_struct(
+    input: &DeriveInput,
+    generics: &Generics,
+    datastruct: &syn::DataStruct,
+) -> Result<TokenStream, error::DeriveError> {
+    let mut fields = datastruct.fields.iter();
+    match (fields.next(), fields.next()) {
+        (Some(field), None) => gen_newtype_struct_wrapper(input, field, generics),
+        _ => Err(error::DeriveError::Transparent),
+    }
+}
+
 fn on_enum(
     input: &DeriveInput,
+    generics: &Generics,
     enumstruct: &syn::DataEnum,
 ) -> Result<TokenStream, error::DeriveError> {
     let name = &input.ident;
 
-    let generics = add_trait_bounds(input.generics.clone());
-
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let unit_fields = EnumUnitFields::new(name, enumstruct);
-    let named_fields = EnumAsMapFields::new(name, enumstruct)?;
+    let other = OtherVariant::from_enum(enumstruct)?;
+    let unit_fields = EnumUnitFields::new(name, enumstruct)?;
+    let named_fields = EnumAsMapFields::new(name, enumstruct, other.as_ref())?;
 
-    let hydrate_string = unit_fields.hydrate_string();
-    let hydrate_map = named_fields.hydrate_map();
+    let hydrate_string = unit_fields.hydrate_string(other.as_ref());
+    let hydrate_map = named_fields.hydrate_map(other.as_ref());
 
     Ok(quote! {
         impl #impl_generics ::autosurgeon::Hydrate for #name #ty_generics
@@ -130,60 +207,213 @@ fn on_enum(
     })
 }
 
+/// Hydrate an enum with `#[autosurgeon(repr = "int")]` from the discriminant of one of its unit
+/// variants
+fn on_int_enum(
+    input: &DeriveInput,
+    generics: &Generics,
+    enumstruct: &syn::DataEnum,
+) -> Result<TokenStream, error::DeriveError> {
+    let name = &input.ident;
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    if enumstruct
+        .variants
+        .iter()
+        .any(|v| !matches!(v.fields, Fields::Unit))
+    {
+        return Err(error::DeriveError::IntRepr);
+    }
+    let other = OtherVariant::from_enum(enumstruct)?;
+    let value = syn::Ident::new("value", Span::mixed_site());
+    let arms = enumstruct.variants.iter().map(|v| {
+        let ident = &v.ident;
+        quote! {
+            #value if #value == #name::#ident as ::std::primitive::i64 => ::std::result::Result::Ok(#name::#ident),
+        }
+    });
+    let expected = format!(
+        "the discriminant of a variant of {}",
+        name.to_string().trim_start_matches("r#")
+    );
+    let fallback = match &other {
+        Some(other) => other.string_fallback_arm(name),
+        None => quote! {
+            other => ::std::result::Result::Err(::autosurgeon::HydrateError::unexpected(
+                #expected,
+                ::std::string::ToString::to_string(&other),
+            )),
+        },
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::autosurgeon::Hydrate for #name #ty_generics
+            #where_clause
+        {
+            fn hydrate_int(
+                #value: ::std::primitive::i64,
+            ) -> ::std::result::Result<Self, ::autosurgeon::HydrateError> {
+                match #value {
+                    #(#arms)*
+                    #fallback
+                }
+            }
+
+            fn hydrate_uint(
+                #value: ::std::primitive::u64,
+            ) -> ::std::result::Result<Self, ::autosurgeon::HydrateError> {
+                match <::std::primitive::i64 as ::std::convert::TryFrom<_>>::try_from(#value) {
+                    ::std::result::Result::Ok(#value) => Self::hydrate_int(#value),
+                    ::std::result::Result::Err(_) => ::std::result::Result::Err(
+                        ::autosurgeon::HydrateError::unexpected(
+                            #expected,
+                            ::std::string::ToString::to_string(&#value),
+                        ),
+                    ),
+                }
+            }
+        }
+    })
+}
+
+fn on_tagged_enum(
+    input: &DeriveInput,
+    generics: &Generics,
+    enumstruct: &syn::DataEnum,
+    repr: &attrs::EnumRepr,
+) -> Result<TokenStream, error::DeriveError> {
+    let name = &input.ident;
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (tag, content) = match repr {
+        attrs::EnumRepr::Internal { tag } => (tag, None),
+        attrs::EnumRepr::Adjacent { tag, content } => (tag, Some(content)),
+        attrs::EnumRepr::External => unreachable!(),
+    };
+
+    let other = OtherVariant::from_enum(enumstruct)?;
+    let variants = enumstruct
+        .variants
+        .iter()
+        .filter(|v| !other.as_ref().map(|o| o.is_newtype(v)).unwrap_or(false));
+    let arms = collect_all(variants.map(|v| {
+        let discriminant = attrs::Variant::from_variant(v)?
+            .unwrap_or_default()
+            .discriminant(&v.ident);
+        let ident = &v.ident;
+        let body = match variant_fields::Variant::from_variant(v)? {
+            None => quote!(::std::result::Result::Ok(#name::#ident)),
+            Some(variant) => match content {
+                Some(content) => variant.adjacent_tagged_hydrator(name, content),
+                None => variant.internal_tagged_hydrator(name)?,
+            },
+        };
+        Ok::<_, error::DeriveError>(quote!(#discriminant => { #body }))
+    }))?;
+
+    let discriminants = collect_all(enumstruct.variants.iter().map(|v| {
+        Ok::<_, error::DeriveError>(
+            attrs::Variant::from_variant(v)?
+                .unwrap_or_default()
+                .discriminant(&v.ident),
+        )
+    }))?;
+    let expected = format!("a {} of one of ({})", tag, discriminants.join(", "));
+    let fallback = match &other {
+        Some(other) => {
+            let fallback = other.map_fallback(name);
+            quote!(_ => #fallback,)
+        }
+        None => quote! {
+            other => ::std::result::Result::Err(::autosurgeon::HydrateError::unexpected(
+                #expected,
+                ::std::string::ToString::to_string(other),
+            )),
+        },
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::autosurgeon::Hydrate for #name #ty_generics
+            #where_clause
+        {
+            fn hydrate_map<D: ::autosurgeon::ReadDoc>(
+                doc: &D,
+                obj: &::automerge::ObjId,
+            ) -> ::std::result::Result<Self, ::autosurgeon::HydrateError> {
+                let tag: ::std::string::String = ::autosurgeon::hydrate_prop(doc, obj, #tag)?;
+                match tag.as_str() {
+                    #(#arms,)*
+                    #fallback
+                }
+            }
+        }
+    })
+}
+
 struct EnumUnitFields<'a> {
     ty: &'a syn::Ident,
-    fields: Vec<&'a syn::Ident>,
+    /// The identifier and discriminant of each unit variant
+    fields: Vec<(&'a syn::Ident, String)>,
 }
 
 impl<'a> EnumUnitFields<'a> {
-    fn new(ty: &'a syn::Ident, data: &'a syn::DataEnum) -> Self {
-        Self {
-            ty,
-            fields: data
-                .variants
+    fn new(ty: &'a syn::Ident, data: &'a syn::DataEnum) -> Result<Self, error::DeriveError> {
+        let fields = collect_all(
+            data.variants
                 .iter()
-                .filter_map(|f| match f.fields {
-                    Fields::Unit => Some(&f.ident),
-                    _ => None,
-                })
-                .collect(),
-        }
+                .filter(|v| matches!(v.fields, Fields::Unit))
+                .map(|v| {
+                    let discriminant = attrs::Variant::from_variant(v)?
+                        .unwrap_or_default()
+                        .discriminant(&v.ident);
+                    Ok::<_, error::DeriveError>((&v.ident, discriminant))
+                }),
+        )?;
+        Ok(Self { ty, fields })
     }
 
     fn branches(&self) -> TokenStream {
         let ty = self.ty;
-        let branches = self.fields.iter().map(|i| {
-            let branch_name = i.to_string();
-            quote!(#branch_name => ::std::result::Result::Ok(#ty::#i))
-        });
-        quote!(#(#branches),*)
+        let branches = self
+            .fields
+            .iter()
+            .map(|(i, branch_name)| quote!(#branch_name => ::std::result::Result::Ok(#ty::#i)));
+        quote!(#(#branches,)*)
     }
 
     fn expected(&self) -> TokenStream {
-        let names = self.fields.iter().map(|f| format!("{}", f));
+        let names = self.fields.iter().map(|(_, name)| name);
         let expected = quote!(One of (#(#names),*)).to_string();
         quote!(#expected)
     }
 
-    fn hydrate_string(&self) -> TokenStream {
-        if self.fields.is_empty() {
+    fn hydrate_string(&self, other: Option<&OtherVariant<'_>>) -> TokenStream {
+        if self.fields.is_empty() && other.is_none() {
             quote!()
         } else {
             let unit_branches = self.branches();
             let unit_error = self.expected();
+            let fallback = match other {
+                Some(other) => other.string_fallback_arm(self.ty),
+                None => quote! {
+                    other => ::std::result::Result::Err(
+                        ::autosurgeon::HydrateError::unexpected(
+                            #unit_error,
+                            ::std::string::ToString::to_string(other),
+                        ),
+                    ),
+                },
+            };
 
             quote! {
                 fn hydrate_string(
                     val: &'_ ::std::primitive::str
                 ) -> ::std::result::Result<Self, ::autosurgeon::HydrateError> {
                     match val {
-                        #unit_branches,
-                        other => ::std::result::Result::Err(
-                            ::autosurgeon::HydrateError::unexpected(
-                                #unit_error,
-                                ::std::string::ToString::to_string(other),
-                            ),
-                        ),
+                        #unit_branches
+                        #fallback
                     }
                 }
             }
@@ -191,45 +421,155 @@ impl<'a> EnumUnitFields<'a> {
     }
 }
 
+/// The variant marked with `#[autosurgeon(other)]`, which is hydrated when the discriminant in the
+/// document isn't the discriminant of any variant
+///
+/// A newtype variant is hydrated by hydrating its field from the whole value of the enum, so that
+/// the field can capture a variant written by a newer version of a schema. Reconciling the
+/// variant reconciles the field in place, which writes that variant back.
+enum OtherVariant<'a> {
+    Unit(&'a syn::Ident),
+    NewType(&'a syn::Ident, &'a syn::Type),
+}
+
+impl<'a> OtherVariant<'a> {
+    fn from_enum(data: &'a syn::DataEnum) -> Result<Option<Self>, error::DeriveError> {
+        let mut result = None;
+        for v in &data.variants {
+            let is_other = attrs::Variant::from_variant(v)?
+                .map(|a| a.other())
+                .unwrap_or(false);
+            if !is_other {
+                continue;
+            }
+            if result.is_some() {
+                return Err(syn::Error::new(
+                    v.span(),
+                    "only one variant can be marked with 'other'",
+                )
+                .into());
+            }
+            result = Some(match &v.fields {
+                Fields::Unnamed(f) => Self::NewType(&v.ident, &f.unnamed[0].ty),
+                _ => Self::Unit(&v.ident),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Whether `variant` is this variant and has a field, in which case it is never hydrated by
+    /// discriminant
+    fn is_newtype(&self, variant: &syn::Variant) -> bool {
+        matches!(self, Self::NewType(ident, _) if *ident == &variant.ident)
+    }
+
+    /// A match arm which evaluates to this variant, for a match on an unknown unit variant
+    /// discriminant
+    fn string_fallback_arm(&self, outer_ty: &syn::Ident) -> TokenStream {
+        match self {
+            Self::Unit(ident) => quote!(_ => ::std::result::Result::Ok(#outer_ty::#ident),),
+            Self::NewType(ident, ty) => quote! {
+                other => ::std::result::Result::Ok(#outer_ty::#ident(
+                    <#ty as ::autosurgeon::Hydrate>::hydrate_string(other)?,
+                )),
+            },
+        }
+    }
+
+    /// An expression which evaluates to this variant, for a map `obj` with an unknown
+    /// discriminant
+    fn map_fallback(&self, outer_ty: &syn::Ident) -> TokenStream {
+        match self {
+            Self::Unit(ident) => quote!(::std::result::Result::Ok(#outer_ty::#ident)),
+            Self::NewType(ident, ty) => quote! {
+                ::std::result::Result::Ok(#outer_ty::#ident(
+                    <#ty as ::autosurgeon::Hydrate>::hydrate_map(doc, obj)?,
+                ))
+            },
+        }
+    }
+}
+
 struct EnumAsMapFields<'a> {
     ty: &'a syn::Ident,
     variants: Vec<variant_fields::Variant<'a>>,
 }
 
 impl<'a> EnumAsMapFields<'a> {
-    fn new(ty: &'a syn::Ident, data: &'a syn::DataEnum) -> Result<Self, error::DeriveError> {
-        let variants = data
-            .variants
-            .iter()
-            .filter_map(|v| variant_fields::Variant::from_variant(v).transpose())
-            .collect::<Result<Vec<_>, _>>()?;
+    fn new(
+        ty: &'a syn::Ident,
+        data: &'a syn::DataEnum,
+        other: Option<&OtherVariant<'_>>,
+    ) -> Result<Self, error::DeriveError> {
+        let variants = collect_all(
+            data.variants
+                .iter()
+                .filter(|v| !other.map(|o| o.is_newtype(v)).unwrap_or(false))
+                .filter_map(|v| variant_fields::Variant::from_variant(v).transpose()),
+        )?;
         Ok(Self { ty, variants })
     }
 
-    fn hydrate_map(&self) -> TokenStream {
-        if self.variants.is_empty() {
+    fn hydrate_map(&self, other: Option<&OtherVariant<'_>>) -> TokenStream {
+        if self.variants.is_empty() && other.is_none() {
             quote!()
         } else {
-            let stanzas = self.variants.iter().map(|v| v.visitor_def(self.ty));
+            // Dispatch on the keys of the map rather than looking up the discriminant of every
+            // variant, so hydrating an enum with many variants is one pass over a (usually one
+            // key) map
+            let key = syn::Ident::new("key", Span::mixed_site());
+            let arms = self.variants.iter().map(|v| v.visitor_arm(self.ty));
+            let fallback = match other {
+                Some(other) => other.map_fallback(self.ty),
+                None => quote! {
+                    ::std::result::Result::Err(::autosurgeon::HydrateError::unexpected(
+                        "A map with one key",
+                        ::std::string::ToString::to_string("something else"),
+                    ))
+                },
+            };
             quote! {
                 fn hydrate_map<D: ::autosurgeon::ReadDoc>(
                     doc: &D,
                     obj: &::automerge::ObjId,
                 ) -> ::std::result::Result<Self, ::autosurgeon::HydrateError> {
-                    #(#stanzas)*
-                    ::std::result::Result::Err(::autosurgeon::HydrateError::unexpected(
-                        "A map with one key",
-                        ::std::string::ToString::to_string("something else"),
-                    ))
+                    for #key in ::autosurgeon::ReadDoc::keys(doc, obj) {
+                        match #key.as_str() {
+                            #(#arms)*
+                            _ => {}
+                        }
+                    }
+                    #fallback
                 }
             }
         }
     }
 }
 
-fn gen_named_struct_impl(name: &syn::Ident, fields: &[named_field::NamedField]) -> TokenStream {
+fn gen_named_struct_impl(
+    name: &syn::Ident,
+    fields: &[named_field::NamedField],
+    deny_unknown_fields: bool,
+) -> TokenStream {
     let obj_ident = syn::Ident::new("obj", Span::mixed_site());
     let field_hydrators = fields.iter().map(|f| f.hydrator(&obj_ident));
+    let check_unknown = deny_unknown_fields.then(|| {
+        let known = fields
+            .iter()
+            .filter(|f| f.is_stored())
+            .map(|f| f.string_name());
+        quote! {
+            let known: &[&::std::primitive::str] = &[#(#known),*];
+            let unknown = ::autosurgeon::ReadDoc::keys(doc, #obj_ident)
+                .filter(|k| !known.contains(&k.as_str()))
+                .collect::<::std::vec::Vec<_>>();
+            if !unknown.is_empty() {
+                return ::std::result::Result::Err(::autosurgeon::HydrateError::UnknownFields(
+                    unknown,
+                ));
+            }
+        }
+    });
 
     let field_initializers = fields.iter().map(|f| f.initializer());
 
@@ -238,6 +578,7 @@ fn gen_named_struct_impl(name: &syn::Ident, fields: &[named_field::NamedField])
             doc: &D,
             #obj_ident: &::automerge::ObjId,
         ) -> ::std::result::Result<Self, ::autosurgeon::HydrateError> {
+            #check_unknown
             #(#field_hydrators)*
             ::std::result::Result::Ok(#name {
                 #(#field_initializers),*
@@ -246,16 +587,131 @@ fn gen_named_struct_impl(name: &syn::Ident, fields: &[named_field::NamedField])
     }
 }
 
+/// Generate `<name>Partial`, a copy of the struct `input` in which every field is a
+/// `MaybeMissing`, along with its `Hydrate` impl
+fn gen_partial_struct(
+    input: &DeriveInput,
+    generics: &syn::Generics,
+    fields: &[named_field::NamedField],
+) -> TokenStream {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let partial_name = format_ident!("{}Partial", name);
+    let (def_generics, _, def_where_clause) = input.generics.split_for_impl();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let obj_ident = syn::Ident::new("obj", Span::mixed_site());
+    let field_defs = fields.iter().map(|f| f.partial_def());
+    let field_hydrators = fields.iter().map(|f| f.partial_hydrator(&obj_ident));
+    let errors_ident = syn::Ident::new("errors", Span::mixed_site());
+    let lossy_field_hydrators = fields
+        .iter()
+        .map(|f| f.lossy_partial_hydrator(&obj_ident, &errors_ident));
+    let field_names = fields.iter().map(|f| f.name()).collect::<Vec<_>>();
+    let missing_checks = fields.iter().map(|f| {
+        let field_name = f.name();
+        let string_name = f.string_name();
+        quote! {
+            if let ::autosurgeon::MaybeMissing::Missing = self.#field_name {
+                missing.push(#string_name);
+            }
+        }
+    });
+    let required = fields
+        .iter()
+        .filter(|f| f.missing().is_none())
+        .map(|f| f.name());
+    let completers = fields.iter().map(|f| {
+        let field_name = f.name();
+        let on_missing = match f.missing() {
+            Some(missing_fn) => quote!(#missing_fn()),
+            None => quote!(::std::unreachable!()),
+        };
+        quote! {
+            #field_name: match self.#field_name {
+                ::autosurgeon::MaybeMissing::Present(v) => v,
+                ::autosurgeon::MaybeMissing::Missing => #on_missing,
+            }
+        }
+    });
+
+    let doc = format!(
+        "A version of [`{}`] in which every field may be missing from the document",
+        name
+    );
+    let complete_doc = format!(
+        "Convert this into a [`{}`], returning `Err(self)` if any field without a `missing` \
+         function is missing",
+        name
+    );
+
+    quote! {
+        #[doc = #doc]
+        #vis struct #partial_name #def_generics #def_where_clause {
+            #(#field_defs),*
+        }
+
+        impl #impl_generics ::autosurgeon::Hydrate for #partial_name #ty_generics #where_clause {
+            fn hydrate_map<D: ::autosurgeon::ReadDoc>(
+                doc: &D,
+                #obj_ident: &::automerge::ObjId,
+            ) -> ::std::result::Result<Self, ::autosurgeon::HydrateError> {
+                #(#field_hydrators)*
+                ::std::result::Result::Ok(#partial_name {
+                    #(#field_names),*
+                })
+            }
+        }
+
+        impl #impl_generics #partial_name #ty_generics #where_clause {
+            /// The names of the fields which were missing from the document
+            pub fn missing_fields(&self) -> ::std::vec::Vec<&'static str> {
+                let mut missing = ::std::vec::Vec::new();
+                #(#missing_checks)*
+                missing
+            }
+
+            #[doc = #complete_doc]
+            pub fn complete(self) -> ::std::result::Result<#name #ty_generics, Self> {
+                if false #(|| ::std::matches!(self.#required, ::autosurgeon::MaybeMissing::Missing))* {
+                    return ::std::result::Result::Err(self);
+                }
+                ::std::result::Result::Ok(#name {
+                    #(#completers),*
+                })
+            }
+        }
+
+        impl #impl_generics ::autosurgeon::hydrate::HydratePartial for #name #ty_generics #where_clause {
+            type Partial = #partial_name #ty_generics;
+
+            fn hydrate_lossy<D: ::autosurgeon::ReadDoc>(
+                doc: &D,
+                #obj_ident: &::automerge::ObjId,
+                #errors_ident: &mut ::std::vec::Vec<::autosurgeon::HydrateError>,
+            ) -> Self::Partial {
+                #(#lossy_field_hydrators)*
+                #partial_name {
+                    #(#field_names),*
+                }
+            }
+        }
+    }
+}
+
 fn gen_newtype_struct_wrapper(
     input: &DeriveInput,
-    fields: &syn::FieldsUnnamed,
+    field: &syn::Field,
     generics: &syn::Generics,
 ) -> Result<TokenStream, error::DeriveError> {
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let field = fields.unnamed.first().unwrap();
     let attrs = attrs::Field::from_field(field)?.unwrap_or_default();
     let ty = &input.ident;
+    let construct = match &field.ident {
+        Some(name) => quote!(#ty { #name: inner }),
+        None => quote!(#ty(inner)),
+    };
 
     let inner_ty = &field.ty;
 
@@ -282,7 +738,7 @@ fn gen_newtype_struct_wrapper(
                     prop: ::autosurgeon::Prop<'a>,
                 ) -> ::std::result::Result<Self, ::autosurgeon::HydrateError> {
                     let inner = #hydrate_with(doc, obj, prop)?;
-                    ::std::result::Result::Ok(#ty(inner))
+                    ::std::result::Result::Ok(#construct)
                 }
             }
         })
@@ -307,7 +763,7 @@ fn gen_newtype_struct_wrapper(
                         obj,
                         prop,
                     )?#unwrap_missing;
-                    ::std::result::Result::Ok(#ty(inner))
+                    ::std::result::Result::Ok(#construct)
                 }
             }
         })
@@ -322,12 +778,15 @@ fn gen_tuple_struct_wrapper(
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     let name = &input.ident;
 
-    let fields = fields
-        .unnamed
-        .iter()
-        .enumerate()
-        .map(|(i, f)| unnamed_field::UnnamedField::new(f, i))
-        .collect::<Result<Vec<_>, _>>()?;
+    let prop_indices = crate::tuple_prop_indices(fields);
+    let fields = collect_all(
+        fields
+            .unnamed
+            .iter()
+            .zip(prop_indices)
+            .enumerate()
+            .map(|(i, (f, prop_index))| unnamed_field::UnnamedField::new(f, i, prop_index)),
+    )?;
 
     let obj_ident = syn::Ident::new("obj", Span::mixed_site());
     let field_hydrators = fields.iter().map(|f| f.hydrator(&obj_ident));
@@ -349,7 +808,9 @@ fn gen_tuple_struct_wrapper(
 }
 
 mod error {
-    use proc_macro2::Span;
+    use proc_macro2::{Span, TokenStream};
+
+    use crate::combine::Combine;
 
     #[derive(Debug, thiserror::Error)]
     pub(crate) enum DeriveError {
@@ -357,13 +818,53 @@ mod error {
         InvalidFieldAttrs(#[from] syn::parse::Error),
         #[error("cannot derive hydrate for unit struct")]
         HydrateForUnit,
+        #[error("#[autosurgeon(transparent)] requires a struct with exactly one field")]
+        Transparent,
+        #[error("#[autosurgeon(tag = \"...\")] is only allowed on enums")]
+        Tag,
+        #[error("#[autosurgeon(partial)] requires a struct with named fields")]
+        Partial,
+        #[error("#[autosurgeon(deny_unknown_fields)] requires a struct with named fields")]
+        DenyUnknownFields,
+        #[error("#[autosurgeon(repr = \"int\")] requires an enum with only unit variants")]
+        IntRepr,
+        #[error("multiple errors")]
+        Multiple(Vec<DeriveError>),
     }
 
     impl DeriveError {
-        pub(crate) fn span(&self) -> Option<Span> {
+        /// Emit this error as one or more compile errors, using `default_span` for errors which
+        /// don't have a span of their own
+        pub(crate) fn to_compile_error(&self, default_span: Span) -> TokenStream {
             match self {
-                Self::InvalidFieldAttrs(e) => Some(e.span()),
-                Self::HydrateForUnit => None,
+                Self::InvalidFieldAttrs(e) => e.to_compile_error(),
+                Self::HydrateForUnit
+                | Self::Transparent
+                | Self::Tag
+                | Self::Partial
+                | Self::DenyUnknownFields
+                | Self::IntRepr => {
+                    syn::Error::new(default_span, self.to_string()).to_compile_error()
+                }
+                Self::Multiple(errs) => errs
+                    .iter()
+                    .map(|e| e.to_compile_error(default_span))
+                    .collect(),
+            }
+        }
+    }
+
+    impl Combine for DeriveError {
+        fn combine(&mut self, other: Self) {
+            match (self, other) {
+                (Self::InvalidFieldAttrs(e1), Self::InvalidFieldAttrs(e2)) => e1.combine(e2),
+                (Self::Multiple(errs), Self::Multiple(others)) => errs.extend(others),
+                (Self::Multiple(errs), other) => errs.push(other),
+                (this, other) => {
+                    let first = std::mem::replace(this, Self::Multiple(Vec::new()));
+                    this.combine(first);
+                    this.combine(other);
+                }
             }
         }
     }
@@ -25,8 +25,12 @@ impl<'a> NamedField<'a> {
 
     pub(crate) fn hydrator(&self, obj_ident: &syn::Ident) -> TokenStream {
         let name = &self.name;
-        let string_name = format_ident!("{}", name).to_string();
-        if let Some(hydrate_with) = self.attrs.hydrate_with().map(|h| h.hydrate_with()) {
+        let string_name = self.string_name();
+        if crate::is_phantom_data(&self.field.ty) {
+            quote!(let #name = ::std::marker::PhantomData;)
+        } else if crate::is_dirty(&self.field.ty) {
+            quote!(let #name = ::autosurgeon::Dirty::clean();)
+        } else if let Some(hydrate_with) = self.attrs.hydrate_with().map(|h| h.hydrate_with()) {
             let span = self.field.span();
             let hydrate_with = if let Some(missing_fn) = self.attrs.missing() {
                 quote_spanned! {span=>
@@ -74,4 +78,113 @@ impl<'a> NamedField<'a> {
         let name = &self.name;
         quote!(#name)
     }
+
+    /// Like [`Self::hydrator`], but binds the field to a `MaybeMissing` which is `Missing` if the
+    /// field is not in the document, ignoring any `missing=` attribute
+    pub(crate) fn partial_hydrator(&self, obj_ident: &syn::Ident) -> TokenStream {
+        let name = &self.name;
+        let string_name = self.string_name();
+        let span = self.field.span();
+        if crate::is_phantom_data(&self.field.ty) {
+            return quote! {
+                let #name = ::autosurgeon::MaybeMissing::Present(::std::marker::PhantomData);
+            };
+        }
+        if crate::is_dirty(&self.field.ty) {
+            return quote! {
+                let #name = ::autosurgeon::MaybeMissing::Present(::autosurgeon::Dirty::clean());
+            };
+        }
+        let hydrate = self.partial_hydrate_fn();
+        quote_spanned! {span=>
+            let #name = match ::autosurgeon::ReadDoc::get(doc, &#obj_ident, #string_name)? {
+                ::std::option::Option::None => ::autosurgeon::MaybeMissing::Missing,
+                ::std::option::Option::Some(_) => ::autosurgeon::MaybeMissing::Present(
+                    #hydrate(doc, &#obj_ident, ::std::convert::Into::into(#string_name))
+                        .map_err(|e| e.in_prop(#string_name))?,
+                ),
+            };
+        }
+    }
+
+    /// Like [`Self::partial_hydrator`], but if the field fails to hydrate the error is pushed to
+    /// `errors_ident` and the field is `Missing`
+    pub(crate) fn lossy_partial_hydrator(
+        &self,
+        obj_ident: &syn::Ident,
+        errors_ident: &syn::Ident,
+    ) -> TokenStream {
+        let name = &self.name;
+        let string_name = self.string_name();
+        let span = self.field.span();
+        if crate::is_phantom_data(&self.field.ty) {
+            return quote! {
+                let #name = ::autosurgeon::MaybeMissing::Present(::std::marker::PhantomData);
+            };
+        }
+        if crate::is_dirty(&self.field.ty) {
+            return quote! {
+                let #name = ::autosurgeon::MaybeMissing::Present(::autosurgeon::Dirty::clean());
+            };
+        }
+        let hydrate = self.partial_hydrate_fn();
+        quote_spanned! {span=>
+            let #name = match ::autosurgeon::ReadDoc::get(doc, &#obj_ident, #string_name)
+                .map_err(::autosurgeon::HydrateError::from)
+                .and_then(|value| match value {
+                    ::std::option::Option::None => {
+                        ::std::result::Result::Ok(::autosurgeon::MaybeMissing::Missing)
+                    }
+                    ::std::option::Option::Some(_) => {
+                        #hydrate(doc, &#obj_ident, ::std::convert::Into::into(#string_name))
+                            .map(::autosurgeon::MaybeMissing::Present)
+                    }
+                }) {
+                ::std::result::Result::Ok(value) => value,
+                ::std::result::Result::Err(e) => {
+                    #errors_ident.push(e.in_prop(#string_name));
+                    ::autosurgeon::MaybeMissing::Missing
+                }
+            };
+        }
+    }
+
+    fn partial_hydrate_fn(&self) -> TokenStream {
+        match self.attrs.hydrate_with() {
+            Some(hydrate_with) => hydrate_with.hydrate_with(),
+            None => {
+                let ty = &self.field.ty;
+                quote_spanned!(self.field.span()=> <#ty as ::autosurgeon::Hydrate>::hydrate)
+            }
+        }
+    }
+
+    /// The definition of this field in the partial type
+    pub(crate) fn partial_def(&self) -> TokenStream {
+        let name = &self.name;
+        let vis = &self.field.vis;
+        let ty = &self.field.ty;
+        quote!(#vis #name: ::autosurgeon::MaybeMissing<#ty>)
+    }
+
+    pub(crate) fn name(&self) -> &syn::Ident {
+        self.name
+    }
+
+    /// Whether this field is stored in the document, rather than being a `PhantomData` or `Dirty`
+    pub(crate) fn is_stored(&self) -> bool {
+        !crate::is_phantom_data(&self.field.ty) && !crate::is_dirty(&self.field.ty)
+    }
+
+    /// The name of this field in the document
+    pub(crate) fn string_name(&self) -> String {
+        match self.attrs.rename() {
+            Some(rename) => rename.to_string(),
+            None => format_ident!("{}", self.name).to_string(),
+        }
+    }
+
+    pub(crate) fn missing(&self) -> Option<&syn::Path> {
+        self.attrs.missing()
+    }
 }
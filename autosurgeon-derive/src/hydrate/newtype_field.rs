@@ -56,4 +56,19 @@ impl<'a> NewtypeField<'a> {
             }
         }
     }
+
+    /// Generate a stream like `let #target = <hydration>` which hydrates the field from the map
+    /// `obj`, for newtype variants of internally tagged enums
+    pub(crate) fn hydrate_map_into(&self, target: &syn::Ident) -> Result<TokenStream, syn::Error> {
+        if self.attrs.hydrate_with().is_some() || self.attrs.missing().is_some() {
+            return Err(syn::Error::new(
+                self.field.span(),
+                "newtype variants of internally tagged enums cannot use 'hydrate', 'with', or 'missing'",
+            ));
+        }
+        let ty = &self.field.ty;
+        Ok(quote_spanned! {self.field.span()=>
+            let #target = <#ty as ::autosurgeon::Hydrate>::hydrate_map(doc, obj)?;
+        })
+    }
 }
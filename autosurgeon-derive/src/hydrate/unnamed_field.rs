@@ -8,22 +8,31 @@ pub(crate) struct UnnamedField {
     field: syn::Field,
     attrs: attrs::Field,
     index: usize,
+    /// The index of this field in the document, which skips `PhantomData` fields
+    prop_index: usize,
 }
 
 impl UnnamedField {
-    pub(crate) fn new(field: &syn::Field, index: usize) -> Result<Self, syn::parse::Error> {
+    pub(crate) fn new(
+        field: &syn::Field,
+        index: usize,
+        prop_index: usize,
+    ) -> Result<Self, syn::parse::Error> {
         let attrs = attrs::Field::from_field(field)?.unwrap_or_default();
         Ok(Self {
             field: field.clone(),
             attrs,
             index,
+            prop_index,
         })
     }
 
     pub(crate) fn hydrator(&self, obj_ident: &syn::Ident) -> TokenStream {
         let name = self.name();
-        let idx = self.index;
-        if let Some(hydrate_with) = self.attrs.hydrate_with().map(|h| h.hydrate_with()) {
+        let idx = self.prop_index;
+        if crate::is_phantom_data(&self.field.ty) {
+            quote!(let #name = ::std::marker::PhantomData;)
+        } else if let Some(hydrate_with) = self.attrs.hydrate_with().map(|h| h.hydrate_with()) {
             let span = self.field.span();
             let hydrate_with = if let Some(missing_fn) = self.attrs.missing() {
                 quote_spanned! {span=>
@@ -1,5 +1,7 @@
 use proc_macro2::{Span, TokenStream};
-use quote::{format_ident, quote};
+use quote::quote;
+
+use crate::{attrs, combine::collect_all};
 
 use super::{
     error::DeriveError, named_field::NamedField, newtype_field::NewtypeField,
@@ -8,41 +10,103 @@ use super::{
 
 pub(crate) struct Variant<'a> {
     ident: &'a syn::Ident,
+    discriminant: String,
     fields: VariantFields<'a>,
 }
 
 impl<'a> Variant<'a> {
-    pub(crate) fn visitor_def(&self, outer_ty: &syn::Ident) -> TokenStream {
-        self.fields.visitor_def(outer_ty, self.ident)
+    /// A match arm for the key of a map which hydrates this variant if the key is its
+    /// discriminant and the value has the right shape, and otherwise does nothing
+    pub(crate) fn visitor_arm(&self, outer_ty: &syn::Ident) -> TokenStream {
+        let discriminant = &self.discriminant;
+        let stanza = self
+            .fields
+            .visitor_def(outer_ty, self.ident, &self.discriminant);
+        quote!(#discriminant => { #stanza })
+    }
+
+    /// Hydrate the variant of an adjacently tagged enum, whose contents are at `content` in `obj`
+    pub(crate) fn adjacent_tagged_hydrator(
+        &self,
+        outer_ty: &syn::Ident,
+        content: &str,
+    ) -> TokenStream {
+        let stanza = self.fields.visitor_def(outer_ty, self.ident, content);
+        let expected = format!("a {} for the {} variant", content, self.discriminant);
+        quote! {
+            #stanza
+            ::std::result::Result::Err(::autosurgeon::HydrateError::unexpected(
+                #expected,
+                ::std::string::ToString::to_string("something else"),
+            ))
+        }
+    }
+
+    /// Hydrate the variant of an internally tagged enum, whose fields are in the same map as the
+    /// tag
+    pub(crate) fn internal_tagged_hydrator(
+        &self,
+        outer_ty: &syn::Ident,
+    ) -> Result<TokenStream, DeriveError> {
+        let variant_name = self.ident;
+        let obj_ident = syn::Ident::new("obj", Span::call_site());
+        match &self.fields {
+            VariantFields::Named(fields) => {
+                let field_hydrators = fields.iter().map(|f| f.hydrator(&obj_ident));
+                let field_initializers = fields.iter().map(|f| f.initializer());
+                Ok(quote! {
+                    #(#field_hydrators)*
+                    ::std::result::Result::Ok(#outer_ty::#variant_name {
+                        #(#field_initializers),*
+                    })
+                })
+            }
+            VariantFields::NewType(field) => {
+                let name = syn::Ident::new("field_0", Span::mixed_site());
+                let hydrator = field.hydrate_map_into(&name)?;
+                Ok(quote! {
+                    #hydrator
+                    ::std::result::Result::Ok(#outer_ty::#variant_name(#name))
+                })
+            }
+            VariantFields::Unnamed(_) => Err(DeriveError::InvalidFieldAttrs(syn::Error::new(
+                variant_name.span(),
+                "tuple variants cannot be internally tagged, add a 'content' attribute or use named fields",
+            ))),
+        }
     }
 
     pub(crate) fn from_variant(variant: &'a syn::Variant) -> Result<Option<Self>, DeriveError> {
+        let discriminant = attrs::Variant::from_variant(variant)?
+            .unwrap_or_default()
+            .discriminant(&variant.ident);
         let fields = match &variant.fields {
-            syn::Fields::Named(nf) => VariantFields::Named(
+            syn::Fields::Named(nf) => VariantFields::Named(collect_all(
                 nf.named
                     .iter()
-                    .map(|f| NamedField::new(f, f.ident.as_ref().unwrap()))
-                    .collect::<Result<Vec<_>, _>>()?,
-            ),
+                    .map(|f| NamedField::new(f, f.ident.as_ref().unwrap())),
+            )?),
             syn::Fields::Unnamed(uf) => {
                 if uf.unnamed.len() == 1 {
                     let f = uf.unnamed.first().unwrap();
                     let field = NewtypeField::from_field(f)?;
                     VariantFields::NewType(field)
                 } else {
-                    VariantFields::Unnamed(
+                    let prop_indices = crate::tuple_prop_indices(uf);
+                    VariantFields::Unnamed(collect_all(
                         uf.unnamed
                             .iter()
+                            .zip(prop_indices)
                             .enumerate()
-                            .map(|(i, f)| UnnamedField::new(f, i))
-                            .collect::<Result<Vec<_>, _>>()?,
-                    )
+                            .map(|(i, (f, prop_index))| UnnamedField::new(f, i, prop_index)),
+                    )?)
                 }
             }
             syn::Fields::Unit => return Ok(None),
         };
         Ok(Some(Self {
             ident: &variant.ident,
+            discriminant,
             fields,
         }))
     }
@@ -55,11 +119,22 @@ enum VariantFields<'a> {
 }
 
 impl<'a> VariantFields<'a> {
-    fn visitor_def(&self, outer_ty: &syn::Ident, variant_name: &'a syn::Ident) -> TokenStream {
+    fn visitor_def(
+        &self,
+        outer_ty: &syn::Ident,
+        variant_name: &'a syn::Ident,
+        variant_name_str: &str,
+    ) -> TokenStream {
         match self {
-            Self::Named(fields) => named_field_variant_stanza(outer_ty, variant_name, fields),
-            Self::Unnamed(fields) => unnamed_field_variant_stanza(outer_ty, variant_name, fields),
-            Self::NewType(field) => newtype_field_variant_stanza(outer_ty, variant_name, field),
+            Self::Named(fields) => {
+                named_field_variant_stanza(outer_ty, variant_name, variant_name_str, fields)
+            }
+            Self::Unnamed(fields) => {
+                unnamed_field_variant_stanza(outer_ty, variant_name, variant_name_str, fields)
+            }
+            Self::NewType(field) => {
+                newtype_field_variant_stanza(outer_ty, variant_name, variant_name_str, field)
+            }
         }
     }
 }
@@ -67,14 +142,14 @@ impl<'a> VariantFields<'a> {
 fn newtype_field_variant_stanza(
     outer_ty: &syn::Ident,
     variant_name: &syn::Ident,
+    variant_name_str: &str,
     field: &NewtypeField,
 ) -> TokenStream {
     let ty = outer_ty;
 
     let name = syn::Ident::new("field_0", proc_macro2::Span::mixed_site());
-    let variant_name_str = format_ident!("{}", variant_name).to_string();
 
-    let hydrator = field.hydrate_into(&name, &variant_name_str);
+    let hydrator = field.hydrate_into(&name, variant_name_str);
     quote! {
         if ::autosurgeon::ReadDoc::get(doc, obj, #variant_name_str)?.is_some() {
             #hydrator
@@ -87,11 +162,11 @@ fn newtype_field_variant_stanza(
 fn named_field_variant_stanza(
     outer_ty: &syn::Ident,
     variant_name: &syn::Ident,
+    variant_name_str: &str,
     fields: &[NamedField<'_>],
 ) -> TokenStream {
     let ty = outer_ty;
 
-    let variant_name_str = variant_name.to_string();
     let obj_ident = syn::Ident::new("id", Span::mixed_site());
     let field_hydrators = fields.iter().map(|f| f.hydrator(&obj_ident));
     let field_initializers = fields.iter().map(|f| f.initializer());
@@ -102,7 +177,12 @@ fn named_field_variant_stanza(
             obj,
             #variant_name_str,
         )? {
-            if ::std::matches!(val, ::automerge::Value::Object(::automerge::ObjType::Map)) {
+            if ::std::matches!(
+                val,
+                ::automerge::Value::Object(
+                    ::automerge::ObjType::Map | ::automerge::ObjType::Table
+                )
+            ) {
                 #(#field_hydrators)*
                 return ::std::result::Result::Ok(#ty::#variant_name {
                     #(#field_initializers),*
@@ -115,6 +195,7 @@ fn named_field_variant_stanza(
 fn unnamed_field_variant_stanza(
     outer_ty: &syn::Ident,
     variant_name: &syn::Ident,
+    variant_name_str: &str,
     fields: &[UnnamedField],
 ) -> TokenStream {
     let ty = outer_ty;
@@ -123,7 +204,6 @@ fn unnamed_field_variant_stanza(
     let hydrators = fields.iter().map(|f| f.hydrator(&obj_ident));
     let initializers = fields.iter().map(|f| f.initializer());
 
-    let variant_name_str = variant_name.to_string();
     quote! {
         if let ::std::option::Option::Some((val, #obj_ident)) = ::autosurgeon::ReadDoc::get(
             doc,
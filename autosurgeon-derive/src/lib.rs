@@ -1,6 +1,76 @@
 mod attrs;
+mod combine;
 mod hydrate;
 mod reconcile;
+mod schema;
+
+/// Whether `ty` is a `PhantomData`, fields of which are skipped by both derives
+fn is_phantom_data(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(p) => p
+            .path
+            .segments
+            .last()
+            .map(|s| s.ident == "PhantomData")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Whether `ty` is an `autosurgeon::Dirty`, which tracks the changed fields of a struct with
+/// named fields and is not stored in the document
+fn is_dirty(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(p) => p
+            .path
+            .segments
+            .last()
+            .map(|s| s.ident == "Dirty")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Whether the type parameter `param` is mentioned by any field which is not a `PhantomData`.
+/// Parameters which only appear in `PhantomData` fields don't need to implement `Reconcile` or
+/// `Hydrate`, so the derives don't add bounds for them.
+fn used_outside_phantom_data(data: &syn::Data, param: &syn::Ident) -> bool {
+    let fields: Box<dyn Iterator<Item = &syn::Field>> = match data {
+        syn::Data::Struct(s) => Box::new(s.fields.iter()),
+        syn::Data::Enum(e) => Box::new(e.variants.iter().flat_map(|v| v.fields.iter())),
+        syn::Data::Union(u) => Box::new(u.fields.named.iter()),
+    };
+    fields
+        .filter(|f| !is_phantom_data(&f.ty))
+        .any(|f| mentions(quote::ToTokens::to_token_stream(&f.ty), param))
+}
+
+/// Whether the identifier `param` appears anywhere in `tokens`
+fn mentions(tokens: proc_macro2::TokenStream, param: &syn::Ident) -> bool {
+    tokens.into_iter().any(|t| match t {
+        proc_macro2::TokenTree::Ident(i) => &i == param,
+        proc_macro2::TokenTree::Group(g) => mentions(g.stream(), param),
+        _ => false,
+    })
+}
+
+/// The index in the document of each of the fields of a tuple struct or variant, or `0` for
+/// `PhantomData` fields, which are not stored
+fn tuple_prop_indices(fields: &syn::FieldsUnnamed) -> Vec<usize> {
+    let mut next = 0;
+    fields
+        .unnamed
+        .iter()
+        .map(|f| {
+            if is_phantom_data(&f.ty) {
+                0
+            } else {
+                next += 1;
+                next - 1
+            }
+        })
+        .collect()
+}
 
 #[proc_macro_derive(Hydrate, attributes(autosurgeon))]
 pub fn derive_hydrate(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -11,3 +81,13 @@ pub fn derive_hydrate(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 pub fn derive_reconcile(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     reconcile::derive_reconcile(input)
 }
+
+#[proc_macro_derive(ReconcileKey, attributes(key, autosurgeon))]
+pub fn derive_reconcile_key(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    reconcile::derive_reconcile_key(input)
+}
+
+#[proc_macro_derive(DescribeSchema, attributes(key, autosurgeon))]
+pub fn derive_describe_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    schema::derive_describe_schema(input)
+}
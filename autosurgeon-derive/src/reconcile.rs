@@ -19,25 +19,84 @@ struct ReconcileImpl {
 }
 
 pub fn derive_reconcile(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive(input, Derive::Reconcile)
+}
+
+pub fn derive_reconcile_key(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    derive(input, Derive::ReconcileKey)
+}
+
+pub(crate) enum Derive {
+    Reconcile,
+    /// Only generate the key related items, as an implementation of `ReconcileKey`
+    ReconcileKey,
+}
+
+fn derive(input: proc_macro::TokenStream, derive: Derive) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    proc_macro::TokenStream::from(expand(input, derive))
+}
 
+/// The implementation of `derive` for `input`, or the compile errors explaining why there isn't one
+pub(crate) fn expand(mut input: DeriveInput, derive: Derive) -> TokenStream {
     let span = input.span();
 
     let name = &input.ident;
 
-    let generics = add_trait_bounds(input.generics.clone());
-
     let container_attrs = match attrs::Container::from_attrs(input.attrs.iter()) {
         Ok(c) => c.unwrap_or_default(),
         Err(e) => {
             let span = e.span();
-            return proc_macro::TokenStream::from(
-                syn::Error::new(span, e.to_string()).to_compile_error(),
-            );
+            return syn::Error::new(span, e.to_string()).to_compile_error();
         }
     };
+    if let Some(rule) = container_attrs.rename_all() {
+        attrs::apply_rename_all(&mut input.data, rule);
+    }
+
+    let generics = add_trait_bounds(
+        input.generics.clone(),
+        &input.data,
+        container_attrs.reconcile_bound(),
+    );
 
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let key_immutable = container_attrs.key_immutable().then(|| {
+        quote!(
+            const KEY_IMMUTABLE: bool = true;
+        )
+    });
+    let digest = container_attrs.digest().then(|| {
+        quote!(
+            fn digest(&self) -> ::std::option::Option<u64> {
+                ::std::option::Option::Some(::autosurgeon::reconcile::digest_of(self))
+            }
+        )
+    });
+    let setters = match (&input.data, &derive) {
+        (Data::Struct(s), Derive::Reconcile) if container_attrs.track() => match &s.fields {
+            Fields::Named(fields) => {
+                match struct_impl::tracked_setters(name, &input.generics, fields) {
+                    Ok(setters) => Some(setters),
+                    Err(e) => return e.to_compile_error(span),
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    let field_mask = match (&input.data, &derive) {
+        (Data::Struct(s), Derive::Reconcile) if container_attrs.field_mask() => match &s.fields {
+            Fields::Named(fields) => {
+                match struct_impl::field_mask(name, &input.vis, &generics, fields) {
+                    Ok(field_mask) => Some(field_mask),
+                    Err(e) => return e.to_compile_error(span),
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    };
     let reconciler_ident = syn::Ident::new("reconciler", Span::call_site());
 
     match reconcile_impl(
@@ -61,41 +120,111 @@ pub fn derive_reconcile(input: proc_macro::TokenStream) -> proc_macro::TokenStre
             let key_type = key_type.unwrap_or(quote! {
                 type Key<#key_lifetime> = ::autosurgeon::reconcile::NoKey;
             });
-            let expanded = quote! {
-                impl #impl_generics ::autosurgeon::Reconcile for #name #ty_generics #where_clause {
-                    #key_type
-                    fn reconcile<__R123: ::autosurgeon::Reconciler>(
-                        &self,
-                        mut #reconciler_ident: __R123,
-                    ) -> ::std::result::Result<(), __R123::Error> {
-                        #the_impl
-                    }
-                    #hydrate_key
-                    #get_key
-                }
-                #key_type_def
-            };
-
-            proc_macro::TokenStream::from(expanded)
+            // The implementation and the key type it uses are put in an anonymous constant so
+            // the key type can't collide with anything else in the module the derive is used in,
+            // whatever the types in it are called
+            match derive {
+                Derive::Reconcile => quote! {
+                    const _: () = {
+                        impl #impl_generics ::autosurgeon::Reconcile for #name #ty_generics #where_clause {
+                            #key_type
+                            #key_immutable
+                            fn reconcile<__R123: ::autosurgeon::Reconciler>(
+                                &self,
+                                mut #reconciler_ident: __R123,
+                            ) -> ::std::result::Result<(), __R123::Error> {
+                                #the_impl
+                            }
+                            #hydrate_key
+                            #get_key
+                            #digest
+                        }
+                        #key_type_def
+                    };
+                    #setters
+                    #field_mask
+                },
+                Derive::ReconcileKey => quote! {
+                    const _: () = {
+                        impl #impl_generics ::autosurgeon::reconcile::ReconcileKey for #name #ty_generics #where_clause {
+                            #key_type
+                            #key_immutable
+                            #hydrate_key
+                            #get_key
+                        }
+                        #key_type_def
+                    };
+                },
+            }
         }
-        Err(e) => proc_macro::TokenStream::from(
-            syn::Error::new(e.span().unwrap_or_else(|| input.span()), e.to_string())
-                .to_compile_error(),
-        ),
+        Err(e) => e.to_compile_error(input.span()),
     }
 }
 
-fn add_trait_bounds(mut generics: Generics) -> Generics {
+/// Add `T: Reconcile` to every type parameter which is used outside of a `PhantomData`, or if the user
+/// specified `bound = "..."` then add their bounds instead
+fn add_trait_bounds(
+    mut generics: Generics,
+    data: &syn::Data,
+    bound: Option<&[syn::WherePredicate]>,
+) -> Generics {
+    if let Some(bound) = bound {
+        generics
+            .make_where_clause()
+            .predicates
+            .extend(bound.iter().cloned());
+        return generics;
+    }
     for param in &mut generics.params {
         if let GenericParam::Type(ref mut type_param) = *param {
+            if !crate::used_outside_phantom_data(data, &type_param.ident) {
+                continue;
+            }
             type_param
                 .bounds
                 .push(parse_quote!(::autosurgeon::Reconcile));
         }
     }
+    add_key_bounds(&mut generics, data);
     generics
 }
 
+/// Add the bounds the key type needs for every `#[key]` field whose type mentions a type
+/// parameter
+///
+/// The key of such a field `T` is a `Cow<'k, T>`, which requires `T: Clone + PartialEq + 'static`,
+/// and the key is loaded from the document by hydrating a `T` unless the field has a custom
+/// hydrate function.
+fn add_key_bounds(generics: &mut Generics, data: &syn::Data) {
+    let params = generics
+        .type_params()
+        .map(|p| p.ident.clone())
+        .collect::<Vec<_>>();
+    let fields: Box<dyn Iterator<Item = &syn::Field>> = match data {
+        Data::Struct(s) => Box::new(s.fields.iter()),
+        Data::Enum(e) => Box::new(e.variants.iter().flat_map(|v| v.fields.iter())),
+        Data::Union(_) => return,
+    };
+    let mut predicates: Vec<syn::WherePredicate> = Vec::new();
+    for field in fields {
+        let attrs = attrs::Field::from_field(field).ok().flatten();
+        let is_key = field.attrs.iter().any(|a| a.path().is_ident("key"))
+            || attrs.as_ref().map(|a| a.key()).unwrap_or(false);
+        let ty = &field.ty;
+        let tokens = quote!(#ty);
+        if !is_key || !params.iter().any(|p| crate::mentions(tokens.clone(), p)) {
+            continue;
+        }
+        predicates.push(parse_quote!(
+            #ty: ::std::clone::Clone + ::std::cmp::PartialEq + 'static
+        ));
+        if attrs.as_ref().and_then(|a| a.hydrate_with()).is_none() {
+            predicates.push(parse_quote!(#ty: ::autosurgeon::Hydrate));
+        }
+    }
+    generics.make_where_clause().predicates.extend(predicates);
+}
+
 fn reconcile_impl(
     container_attrs: attrs::Container,
     _span: proc_macro2::Span,
@@ -105,24 +234,134 @@ fn reconcile_impl(
     data: &Data,
     vis: &syn::Visibility,
 ) -> Result<ReconcileImpl, error::DeriveError> {
+    if container_attrs.track()
+        && (!is_named_struct(data)
+            || container_attrs.reconcile_with().is_some()
+            || container_attrs.transparent())
+    {
+        return Err(error::DeriveError::Track);
+    }
+    if container_attrs.field_mask()
+        && (!is_named_struct(data)
+            || container_attrs.reconcile_with().is_some()
+            || container_attrs.transparent())
+    {
+        return Err(error::DeriveError::FieldMask);
+    }
     if let Some(reconcile) = container_attrs.reconcile_with() {
         return Ok(reconcile_with_impl(reconcile, reconciler_ident));
     }
-    match *data {
+    if container_attrs.transparent() {
+        return transparent_impl(data);
+    }
+    if container_attrs.key_fn().is_some() && has_key_fields(data) {
+        return Err(error::DeriveError::KeyFnWithKey);
+    }
+    let key_fn = container_attrs.key_fn().cloned();
+    let the_impl = match *data {
+        Data::Struct(_) if *container_attrs.repr() != attrs::EnumRepr::External => {
+            Err(error::DeriveError::Tag)
+        }
+        Data::Struct(_) if container_attrs.int_repr() => Err(error::DeriveError::IntRepr),
         Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => struct_impl::named_field_impl(reconciler_ident, fields),
+            Fields::Named(ref fields) => struct_impl::named_field_impl(
+                reconciler_ident,
+                fields,
+                container_attrs.exhaustive(),
+                container_attrs.track(),
+            ),
+            _ if container_attrs.exhaustive() => Err(error::DeriveError::Exhaustive),
             Fields::Unnamed(ref fields) => {
                 if fields.unnamed.len() == 1 {
                     let field = fields.unnamed.first().unwrap();
-                    newtype_struct_impl(field)
+                    newtype_struct_impl(field, quote!(self.0))
                 } else {
                     struct_impl::tuple_struct_impl(reconciler_ident, fields)
                 }
             }
             Fields::Unit => Err(error::DeriveError::Unit),
         },
-        Data::Enum(ref data) => enum_impl::enum_impl(vis, name, generics, reconciler_ident, data),
+        _ if container_attrs.exhaustive() => Err(error::DeriveError::Exhaustive),
+        Data::Enum(ref data) if container_attrs.int_repr() => {
+            enum_impl::int_enum_impl(reconciler_ident, data)
+        }
+        Data::Enum(ref data) => enum_impl::enum_impl(
+            vis,
+            name,
+            generics,
+            reconciler_ident,
+            data,
+            container_attrs.repr(),
+        ),
         Data::Union(_) => Err(error::DeriveError::Union),
+    }?;
+    Ok(match key_fn {
+        Some(key_fn) => key_fn_impl(the_impl.reconcile, &key_fn),
+        None => the_impl,
+    })
+}
+
+fn is_named_struct(data: &Data) -> bool {
+    matches!(data, Data::Struct(s) if matches!(s.fields, Fields::Named(_)))
+}
+
+/// Whether any field of `data` is marked as (part of) the key
+fn has_key_fields(data: &Data) -> bool {
+    let is_key = |field: &syn::Field| {
+        field.attrs.iter().any(|a| a.path().is_ident("key"))
+            || matches!(attrs::Field::from_field(field), Ok(Some(f)) if f.key())
+    };
+    match data {
+        Data::Struct(data) => data.fields.iter().any(is_key),
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|v| v.fields.iter())
+            .any(is_key),
+        Data::Union(_) => false,
+    }
+}
+
+fn key_fn_impl(reconcile: TokenStream, key_fn: &attrs::KeyFn) -> ReconcileImpl {
+    let key_lifetime = syn::Lifetime::new("'k", Span::mixed_site());
+    let key_fn_path = &key_fn.key_fn;
+    let load_key = match &key_fn.hydrate_key_fn {
+        Some(hydrate_key_fn) => quote!(#hydrate_key_fn(doc, obj, prop)),
+        None => quote! {
+            ::autosurgeon::reconcile::hydrate_computed_key(doc, obj, prop, |value: &Self| {
+                #key_fn_path(value)
+            })
+        },
+    };
+    ReconcileImpl {
+        key_type_def: None,
+        key_type: Some(quote! {
+            type Key<#key_lifetime> = ::autosurgeon::reconcile::ComputedKey;
+        }),
+        reconcile,
+        hydrate_key: Some(quote! {
+            fn hydrate_key<#key_lifetime, D: ::autosurgeon::ReadDoc>(
+                doc: &D,
+                obj: &::automerge::ObjId,
+                prop: ::autosurgeon::Prop<'_>,
+            ) -> ::std::result::Result<
+                ::autosurgeon::reconcile::LoadKey<Self::Key<#key_lifetime>>,
+                ::autosurgeon::ReconcileError,
+            > {
+                ::std::result::Result::Ok(
+                    #load_key?.map(::autosurgeon::reconcile::ComputedKey::new),
+                )
+            }
+        }),
+        get_key: Some(quote! {
+            fn key<#key_lifetime>(
+                &#key_lifetime self,
+            ) -> ::autosurgeon::reconcile::LoadKey<Self::Key<#key_lifetime>> {
+                ::autosurgeon::reconcile::LoadKey::Found(
+                    ::autosurgeon::reconcile::ComputedKey::new(#key_fn_path(self)),
+                )
+            }
+        }),
     }
 }
 
@@ -220,7 +459,27 @@ fn reconcile_with_impl(
     }
 }
 
-fn newtype_struct_impl(field: &syn::Field) -> Result<ReconcileImpl, error::DeriveError> {
+fn transparent_impl(data: &Data) -> Result<ReconcileImpl, error::DeriveError> {
+    let Data::Struct(data) = data else {
+        return Err(error::DeriveError::Transparent);
+    };
+    let mut fields = data.fields.iter();
+    match (fields.next(), fields.next()) {
+        (Some(field), None) => {
+            let accessor = match &field.ident {
+                Some(name) => quote!(self.#name),
+                None => quote!(self.0),
+            };
+            newtype_struct_impl(field, accessor)
+        }
+        _ => Err(error::DeriveError::Transparent),
+    }
+}
+
+fn newtype_struct_impl(
+    field: &syn::Field,
+    accessor: TokenStream,
+) -> Result<ReconcileImpl, error::DeriveError> {
     let field_ty = &field.ty;
     let fieldattrs = attrs::Field::from_field(field)?;
     let key_lifetime = syn::Lifetime::new("'k", Span::mixed_site());
@@ -231,16 +490,16 @@ fn newtype_struct_impl(field: &syn::Field) -> Result<ReconcileImpl, error::Deriv
         Ok(ReconcileImpl {
             reconcile: quote! {
                 #wrapper
-                #wrapper_tyname(&self.0).reconcile(reconciler)
+                #wrapper_tyname(&#accessor).reconcile(reconciler)
             },
             key_type: reconcile_with.key_type(),
             key_type_def: None,
             hydrate_key: reconcile_with.hydrate_key(),
-            get_key: reconcile_with.get_key(quote!(&self.0)),
+            get_key: reconcile_with.get_key(quote!(&#accessor)),
         })
     } else {
         Ok(ReconcileImpl {
-            reconcile: quote_spanned!(field.span()=> self.0.reconcile(reconciler)),
+            reconcile: quote_spanned!(field.span()=> #accessor.reconcile(reconciler)),
             key_type: Some(quote! {
                 type Key<#key_lifetime> =
                     <#field_ty as ::autosurgeon::Reconcile>::Key<#key_lifetime>;
@@ -262,7 +521,7 @@ fn newtype_struct_impl(field: &syn::Field) -> Result<ReconcileImpl, error::Deriv
                 fn key<#key_lifetime>(
                     &#key_lifetime self,
                 ) -> ::autosurgeon::reconcile::LoadKey<Self::Key<#key_lifetime>> {
-                    <#field_ty as ::autosurgeon::Reconcile>::key(&self.0)
+                    <#field_ty as ::autosurgeon::Reconcile>::key(&#accessor)
                 }
             }),
         })
@@ -270,7 +529,9 @@ fn newtype_struct_impl(field: &syn::Field) -> Result<ReconcileImpl, error::Deriv
 }
 
 mod error {
-    use proc_macro2::Span;
+    use proc_macro2::{Span, TokenStream};
+
+    use crate::combine::Combine;
 
     #[derive(Debug, thiserror::Error)]
     pub(crate) enum DeriveError {
@@ -280,17 +541,77 @@ mod error {
         Unit,
         #[error("cannot derive Reconcile for a Union")]
         Union,
+        #[error("#[autosurgeon(transparent)] requires a struct with exactly one field")]
+        Transparent,
+        #[error("#[autosurgeon(exhaustive)] requires a struct with named fields")]
+        Exhaustive,
+        #[error("#[autosurgeon(tag = \"...\")] is only allowed on enums")]
+        Tag,
+        #[error("cannot use #[autosurgeon(key_fn = \"...\")] together with #[key] fields")]
+        KeyFnWithKey,
+        #[error("#[autosurgeon(track)] requires a struct with named fields")]
+        Track,
+        #[error("#[autosurgeon(track)] requires exactly one field of type autosurgeon::Dirty")]
+        TrackDirtyField,
+        #[error("#[autosurgeon(track)] supports at most 64 fields besides the Dirty field")]
+        TooManyTracked,
+        #[error("#[autosurgeon(field_mask)] requires a struct with named fields")]
+        FieldMask,
+        #[error("#[autosurgeon(field_mask)] supports at most 64 fields")]
+        TooManyMasked,
+        #[error("#[autosurgeon(repr = \"int\")] requires an enum with only unit variants")]
+        IntRepr,
+        #[error("tuple variants cannot be internally tagged, add a 'content' attribute or use named fields")]
+        InternallyTaggedTuple,
         #[error(transparent)]
         Syn(#[from] syn::Error),
+        #[error("multiple errors")]
+        Multiple(Vec<DeriveError>),
     }
 
     impl DeriveError {
-        pub(super) fn span(&self) -> Option<Span> {
+        /// Emit this error as one or more compile errors, using `default_span` for errors which
+        /// don't have a span of their own
+        pub(super) fn to_compile_error(&self, default_span: Span) -> TokenStream {
             match self {
-                Self::InvalidKeyAttr(e) => e.span(),
-                Self::Unit => None,
-                Self::Union => None,
-                Self::Syn(s) => Some(s.span()),
+                Self::InvalidKeyAttr(InvalidKeyAttr::Parse(e)) | Self::Syn(e) => {
+                    e.to_compile_error()
+                }
+                Self::InvalidKeyAttr(InvalidKeyAttr::MultipleKey)
+                | Self::Unit
+                | Self::Union
+                | Self::Transparent
+                | Self::Exhaustive
+                | Self::Tag
+                | Self::KeyFnWithKey
+                | Self::Track
+                | Self::TrackDirtyField
+                | Self::TooManyTracked
+                | Self::FieldMask
+                | Self::TooManyMasked
+                | Self::IntRepr
+                | Self::InternallyTaggedTuple => {
+                    syn::Error::new(default_span, self.to_string()).to_compile_error()
+                }
+                Self::Multiple(errs) => errs
+                    .iter()
+                    .map(|e| e.to_compile_error(default_span))
+                    .collect(),
+            }
+        }
+    }
+
+    impl Combine for DeriveError {
+        fn combine(&mut self, other: Self) {
+            match (self, other) {
+                (Self::Syn(e1), Self::Syn(e2)) => e1.combine(e2),
+                (Self::Multiple(errs), Self::Multiple(others)) => errs.extend(others),
+                (Self::Multiple(errs), other) => errs.push(other),
+                (this, other) => {
+                    let first = std::mem::replace(this, Self::Multiple(Vec::new()));
+                    this.combine(first);
+                    this.combine(other);
+                }
             }
         }
     }
@@ -299,16 +620,7 @@ mod error {
     pub(crate) enum InvalidKeyAttr {
         #[error(transparent)]
         Parse(#[from] syn::Error),
-        #[error("multiple key attributes specified")]
+        #[error("multiple key attributes specified on one field")]
         MultipleKey,
     }
-
-    impl InvalidKeyAttr {
-        fn span(&self) -> Option<Span> {
-            match self {
-                Self::Parse(p) => Some(p.span()),
-                Self::MultipleKey => None,
-            }
-        }
-    }
 }
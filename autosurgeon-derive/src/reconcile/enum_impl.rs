@@ -4,32 +4,44 @@ use proc_macro2::{Span, TokenStream};
 use quote::{format_ident, quote, quote_spanned};
 use syn::spanned::Spanned;
 
-use crate::attrs;
+use crate::{attrs, combine::collect_all};
 
 use super::struct_impl::{
-    named_field_impl, tuple_struct_impl, Field, KeyField, NamedField, NamedFields, TupleField,
+    named_field_impl, tuple_struct_impl, Field, KeyFields, NamedField, NamedFields, TupleField,
     UnnamedFields,
 };
 use super::{error::DeriveError, ReconcileImpl};
 
 /// Represents a variant of an enum.
+///
+/// `discriminant` is the name of the variant in the document, which is different to `name` if the
+/// variant was renamed.
 enum Variant<'a> {
     /// A fieldless variant.
-    Unit { name: &'a syn::Ident },
+    Unit {
+        name: &'a syn::Ident,
+        discriminant: String,
+    },
     /// A variant with one unnamed field.
     NewType {
         name: &'a syn::Ident,
+        discriminant: String,
         inner_ty: &'a syn::Type,
         attrs: attrs::EnumNewtypeAttrs,
+        /// Whether this is the `#[autosurgeon(other)]` variant, whose field holds the whole value
+        /// of a variant this enum doesn't know about
+        other: bool,
     },
     /// A struct variant with named fields.
     Named {
         name: &'a syn::Ident,
+        discriminant: String,
         fields: &'a syn::FieldsNamed,
     },
     /// A tuple variant with unnamed fields.
     Unnamed {
         name: &'a syn::Ident,
+        discriminant: String,
         fields: &'a syn::FieldsUnnamed,
     },
 }
@@ -37,25 +49,34 @@ enum Variant<'a> {
 impl<'a> TryFrom<&'a syn::Variant> for Variant<'a> {
     type Error = DeriveError;
     fn try_from(v: &'a syn::Variant) -> Result<Self, DeriveError> {
+        let variant_attrs = attrs::Variant::from_variant(v)?.unwrap_or_default();
+        let discriminant = variant_attrs.discriminant(&v.ident);
         match &v.fields {
-            syn::Fields::Unit => Ok(Self::Unit { name: &v.ident }),
+            syn::Fields::Unit => Ok(Self::Unit {
+                name: &v.ident,
+                discriminant,
+            }),
             syn::Fields::Unnamed(fields) => {
                 if fields.unnamed.len() == 1 {
                     let field = fields.unnamed.first().unwrap();
                     Ok(Self::NewType {
                         name: &v.ident,
+                        discriminant,
                         inner_ty: &fields.unnamed.first().unwrap().ty,
                         attrs: attrs::EnumNewtypeAttrs::from_field(field)?.unwrap_or_default(),
+                        other: variant_attrs.other(),
                     })
                 } else {
                     Ok(Self::Unnamed {
                         name: &v.ident,
+                        discriminant,
                         fields,
                     })
                 }
             }
             syn::Fields::Named(fields) => Ok(Self::Named {
                 name: &v.ident,
+                discriminant,
                 fields,
             }),
         }
@@ -67,73 +88,213 @@ impl<'a> Variant<'a> {
         &self,
         reconciler_ident: &syn::Ident,
         generics: &syn::Generics,
+        repr: &attrs::EnumRepr,
     ) -> Result<proc_macro2::TokenStream, DeriveError> {
         match self {
-            Self::Unit { name } => {
-                let name_string = name.to_string();
-                Ok(quote! {
-                    Self::#name => ::autosurgeon::Reconciler::str(&mut reconciler, #name_string)
-                })
+            Self::Unit { name, discriminant } => {
+                if *repr == attrs::EnumRepr::External {
+                    Ok(quote! {
+                        Self::#name => ::autosurgeon::Reconciler::str(&mut reconciler, #discriminant)
+                    })
+                } else {
+                    let put = put_variant(reconciler_ident, repr, discriminant, None);
+                    Ok(quote! {
+                        Self::#name => {
+                            #put
+                            ::std::result::Result::Ok(())
+                        }
+                    })
+                }
             }
+            Self::NewType {
+                name, other: true, ..
+            } => Ok(quote! {
+                Self::#name(v) => ::autosurgeon::Reconcile::reconcile(v, #reconciler_ident)
+            }),
             Self::NewType {
                 name,
+                discriminant: name_string,
                 attrs,
                 inner_ty,
+                ..
             } => {
-                let name_string = name.to_string();
                 let ty = inner_ty;
-                let reconciler = attrs.reconcile_with().map(|reconcile_with| {
-                    quote! {
-                        struct ___EnumNewtypeVisitor<'a>(&'a #ty);
-                        impl<'a> ::autosurgeon::Reconcile for ___EnumNewtypeVisitor<'a> {
-                            type Key<'k> = #reconcile_with::Key<'a>;
-                            fn reconcile<R: ::autosurgeon::Reconciler>(
-                                &self,
-                                reconciler: R,
-                            ) -> ::std::result::Result<(), R::Error> {
-                                #reconcile_with::reconcile(self.0, reconciler)
-                            }
-                            fn hydrate_key<'k, D: ::autosurgeon::ReadDoc>(
-                                doc: &D,
-                                obj: &::automerge::ObjId,
-                                prop: ::autosurgeon::Prop<'_>,
-                            ) -> ::std::result::Result<
-                                ::autosurgeon::reconcile::LoadKey<Self::Key<'k>>,
-                                ::autosurgeon::ReconcileError,
-                            > {
-                                #reconcile_with::hydrate_key(doc, obj, prop)
-                            }
-                            fn key<'k>(
-                                &'k self,
-                            ) -> ::autosurgeon::reconcile::LoadKey<Self::Key<'k>> {
-                                #reconcile_with::key(self.0)
-                            }
-                        }
-                        ::autosurgeon::reconcile::MapReconciler::retain(&mut m, |k, _| k == #name_string)?;
-                        ::autosurgeon::reconcile::MapReconciler::put(&mut m, #name_string, ___EnumNewtypeVisitor(&v))?;
-                    }
-                }).unwrap_or_else(|| quote! {
-                    ::autosurgeon::reconcile::MapReconciler::retain(
-                        &mut m,
-                        |k, _| k == #name_string,
-                    )?;
-                    ::autosurgeon::reconcile::MapReconciler::put(&mut m, #name_string, v)?;
-                });
+                let (visitor, value) = attrs
+                    .reconcile_with()
+                    .map(|reconcile_with| {
+                        (
+                            quote! {
+                                struct ___EnumNewtypeVisitor<'a>(&'a #ty);
+                                impl<'a> ::autosurgeon::Reconcile for ___EnumNewtypeVisitor<'a> {
+                                    type Key<'k> = #reconcile_with::Key<'a>;
+                                    fn reconcile<R: ::autosurgeon::Reconciler>(
+                                        &self,
+                                        reconciler: R,
+                                    ) -> ::std::result::Result<(), R::Error> {
+                                        #reconcile_with::reconcile(self.0, reconciler)
+                                    }
+                                    fn hydrate_key<'k, D: ::autosurgeon::ReadDoc>(
+                                        doc: &D,
+                                        obj: &::automerge::ObjId,
+                                        prop: ::autosurgeon::Prop<'_>,
+                                    ) -> ::std::result::Result<
+                                        ::autosurgeon::reconcile::LoadKey<Self::Key<'k>>,
+                                        ::autosurgeon::ReconcileError,
+                                    > {
+                                        #reconcile_with::hydrate_key(doc, obj, prop)
+                                    }
+                                    fn key<'k>(
+                                        &'k self,
+                                    ) -> ::autosurgeon::reconcile::LoadKey<Self::Key<'k>> {
+                                        #reconcile_with::key(self.0)
+                                    }
+                                }
+                            },
+                            quote!(___EnumNewtypeVisitor(&v)),
+                        )
+                    })
+                    .unwrap_or_else(|| (quote!(), quote!(v)));
+                let put = put_variant(reconciler_ident, repr, name_string, Some(value));
                 Ok(quote! {
                      Self::#name(v) => {
-                        let mut m = ::autosurgeon::Reconciler::map(&mut #reconciler_ident)?;
-                        #reconciler
+                        #visitor
+                        #put
                         ::std::result::Result::Ok(())
                     }
                 })
             }
-            Self::Unnamed { name, fields } => {
-                enum_with_fields_variant(reconciler_ident, generics, name, *fields)
+            Self::Unnamed { .. } if matches!(repr, attrs::EnumRepr::Internal { .. }) => {
+                Err(DeriveError::InternallyTaggedTuple)
+            }
+            Self::Unnamed {
+                name,
+                discriminant,
+                fields,
+            } => enum_with_fields_variant(
+                reconciler_ident,
+                generics,
+                name,
+                discriminant,
+                *fields,
+                repr,
+            ),
+            Self::Named {
+                name,
+                discriminant,
+                fields,
+            } => enum_with_fields_variant(
+                reconciler_ident,
+                generics,
+                name,
+                discriminant,
+                *fields,
+                repr,
+            ),
+        }
+    }
+}
+
+/// Generate code which writes the variant `discriminant`, with contents `value` (if it is not a
+/// unit variant), to the document
+fn put_variant(
+    reconciler_ident: &syn::Ident,
+    repr: &attrs::EnumRepr,
+    discriminant: &str,
+    value: Option<TokenStream>,
+) -> TokenStream {
+    match repr {
+        attrs::EnumRepr::External => {
+            let variant_map = variant_map(reconciler_ident, discriminant);
+            quote! {
+                #variant_map
+                ::autosurgeon::reconcile::MapReconciler::put(&mut m, #discriminant, #value)?;
+            }
+        }
+        attrs::EnumRepr::Adjacent { tag, content } => {
+            let keep = if value.is_some() {
+                quote!(k == #tag || k == #content)
+            } else {
+                quote!(k == #tag)
+            };
+            let tagged_map = tagged_variant_map(reconciler_ident, tag, discriminant);
+            let put_content = value.map(|value| {
+                quote! {
+                    ::autosurgeon::reconcile::MapReconciler::put(&mut m, #content, #value)?;
+                }
+            });
+            quote! {
+                #tagged_map
+                ::autosurgeon::reconcile::MapReconciler::retain(&mut m, |k, _| #keep)?;
+                ::autosurgeon::reconcile::MapReconciler::put(&mut m, #tag, #discriminant)?;
+                #put_content
+            }
+        }
+        attrs::EnumRepr::Internal { tag } => {
+            let tagged_map = tagged_variant_map(reconciler_ident, tag, discriminant);
+            match value {
+                // The fields of the variant live alongside the tag, so reconcile them into the
+                // same map
+                Some(value) => quote! {
+                    {
+                        #tagged_map
+                        ::autosurgeon::reconcile::MapReconciler::put(&mut m, #tag, #discriminant)?;
+                    }
+                    ::autosurgeon::Reconcile::reconcile(&#value, #reconciler_ident)?;
+                },
+                None => quote! {
+                    #tagged_map
+                    ::autosurgeon::reconcile::MapReconciler::retain(&mut m, |k, _| k == #tag)?;
+                    ::autosurgeon::reconcile::MapReconciler::put(&mut m, #tag, #discriminant)?;
+                },
             }
-            Self::Named { name, fields } => {
-                enum_with_fields_variant(reconciler_ident, generics, name, *fields)
+        }
+    }
+}
+
+/// Generate code which binds `m` to a `MapReconciler` for the map containing the variant
+/// `variant_name`, with every other key removed
+///
+/// If the document currently holds a different variant then the whole map is replaced rather than
+/// just deleting the old variant's key. Otherwise concurrent changes which switch to different
+/// variants would merge into a map containing both of them.
+fn variant_map(reconciler_ident: &syn::Ident, variant_name: &str) -> TokenStream {
+    quote! {
+        let other_variant = {
+            let m = ::autosurgeon::Reconciler::map(&mut #reconciler_ident)?;
+            ::autosurgeon::reconcile::MapReconciler::entry(&m, #variant_name).is_none()
+                && ::autosurgeon::reconcile::MapReconciler::entries(&m).next().is_some()
+        };
+        if other_variant {
+            ::autosurgeon::Reconciler::replace_with_type(
+                &mut #reconciler_ident,
+                ::automerge::ObjType::Map,
+            )?;
+        }
+        let mut m = ::autosurgeon::Reconciler::map(&mut #reconciler_ident)?;
+        ::autosurgeon::reconcile::MapReconciler::retain(&mut m, |k, _| k == #variant_name)?;
+    }
+}
+
+/// Like [`variant_map`] but for tagged enums, where the variant is identified by the value of
+/// `tag`. Binds `m` without removing any keys.
+fn tagged_variant_map(reconciler_ident: &syn::Ident, tag: &str, variant_name: &str) -> TokenStream {
+    quote! {
+        let other_variant = {
+            let m = ::autosurgeon::Reconciler::map(&mut #reconciler_ident)?;
+            match ::autosurgeon::reconcile::MapReconciler::entry(&m, #tag) {
+                ::std::option::Option::Some(v) => v.to_str() != ::std::option::Option::Some(#variant_name),
+                ::std::option::Option::None => {
+                    ::autosurgeon::reconcile::MapReconciler::entries(&m).next().is_some()
+                }
             }
+        };
+        if other_variant {
+            ::autosurgeon::Reconciler::replace_with_type(
+                &mut #reconciler_ident,
+                ::automerge::ObjType::Map,
+            )?;
         }
+        let mut m = ::autosurgeon::Reconciler::map(&mut #reconciler_ident)?;
     }
 }
 
@@ -147,8 +308,8 @@ struct NewTypeKey<'a> {
 enum EnumKeyInnerType<'a> {
     Unit,
     NewType(NewTypeKey<'a>),
-    Tuple(KeyField<'static, TupleField<'static>>),
-    Struct(KeyField<'static, NamedField<'static>>),
+    Tuple(KeyFields<'static, TupleField<'static>>),
+    Struct(KeyFields<'static, NamedField<'static>>),
     // A struct variant with no #[key] attribute on any fields
     NoInnerKeyStruct,
     // A tuple variant with no #[key] attribute on any fields
@@ -183,21 +344,34 @@ impl<'a> EnumKeyInnerType<'a> {
                     }
                 }
             }
-            Self::Tuple(keyfield) => {
-                let before = (0..(keyfield.index())).map(|_| quote!("_"));
+            Self::Tuple(keyfields) => {
+                let indices = keyfields.indices();
+                let last = indices.iter().copied().max().unwrap_or_default();
+                let names = indices
+                    .iter()
+                    .map(|i| format_ident!("key_{}", i, span = Span::mixed_site()))
+                    .collect::<Vec<_>>();
+                let patterns = (0..=last).map(|i| {
+                    if indices.contains(&i) {
+                        let name = format_ident!("key_{}", i, span = Span::mixed_site());
+                        quote!(#name)
+                    } else {
+                        quote!(_)
+                    }
+                });
+                let found = keyfields.found(names.iter().map(|n| quote!(#n)));
                 quote! {
-                    Self::#variant_name(#(#before)* v, ..) => {
-                        ::autosurgeon::reconcile::LoadKey::Found(
-                            #key_type_name::#variant_name(::std::borrow::Cow::Borrowed(v)),
-                        )
+                    Self::#variant_name(#(#patterns,)* ..) => {
+                        ::autosurgeon::reconcile::LoadKey::Found(#key_type_name::#variant_name(#found))
                     }
                 }
             }
-            Self::Struct(keyfield) => {
-                let fieldname = keyfield.name();
+            Self::Struct(keyfields) => {
+                let fieldnames = keyfields.names();
+                let found = keyfields.found(fieldnames.iter().map(|n| quote!(#n)));
                 quote! {
-                    Self::#variant_name{#fieldname, ..} => ::autosurgeon::reconcile::LoadKey::Found(
-                        #key_type_name::#variant_name(::std::borrow::Cow::Borrowed(#fieldname)),
+                    Self::#variant_name{#(#fieldnames,)* ..} => ::autosurgeon::reconcile::LoadKey::Found(
+                        #key_type_name::#variant_name(#found),
                     )
                 }
             }
@@ -217,55 +391,55 @@ impl<'a> EnumKeyInnerType<'a> {
     ) -> Option<TokenStream> {
         match self {
             EnumKeyInnerType::Unit => Some(quote!(#variant_name)),
+            EnumKeyInnerType::NoInnerKeyStruct | EnumKeyInnerType::NoInnerKeyTuple => None,
+            _ => {
+                let inner = self.key_field_type(key_lifetime)?;
+                Some(quote! {
+                    #variant_name(#inner)
+                })
+            }
+        }
+    }
+
+    /// The type of the field of this variant of the key type, if it has one
+    fn key_field_type(&self, key_lifetime: &syn::Lifetime) -> Option<TokenStream> {
+        match self {
             EnumKeyInnerType::NewType(nt) => {
                 Some(if let Some(reconcile_with) = nt.attrs.reconcile_with() {
-                    quote! {
-                        #variant_name(#reconcile_with::Key<#key_lifetime>)
-                    }
+                    quote!(#reconcile_with::Key<#key_lifetime>)
                 } else {
                     let inner = nt.ty;
-                    quote! {
-                        #variant_name(<#inner as ::autosurgeon::Reconcile>::Key<#key_lifetime>)
-                    }
+                    quote!(<#inner as ::autosurgeon::Reconcile>::Key<#key_lifetime>)
                 })
             }
-            EnumKeyInnerType::Struct(keyfield) => {
-                let inner = keyfield.key_type();
-                Some(quote! {
-                    #variant_name(::std::borrow::Cow<#key_lifetime, #inner>)
-                })
-            }
-            EnumKeyInnerType::Tuple(keyfield) => {
-                let inner = keyfield.key_type();
-                Some(quote! {
-                    #variant_name(::std::borrow::Cow<#key_lifetime, #inner>)
-                })
-            }
-            EnumKeyInnerType::NoInnerKeyStruct | EnumKeyInnerType::NoInnerKeyTuple => None,
+            EnumKeyInnerType::Struct(keyfields) => Some(keyfields.key_type(key_lifetime)),
+            EnumKeyInnerType::Tuple(keyfields) => Some(keyfields.key_type(key_lifetime)),
+            EnumKeyInnerType::Unit
+            | EnumKeyInnerType::NoInnerKeyStruct
+            | EnumKeyInnerType::NoInnerKeyTuple => None,
         }
     }
 
+    /// Generate an expression which hydrates the key of this variant, where the contents of the
+    /// variant are at `prop` in `obj`
     fn hydrate_key(
         &self,
         key_type_name: &syn::Ident,
         variant_name: &syn::Ident,
-        obj_id_ident: &syn::Ident,
+        obj: &TokenStream,
+        prop: &TokenStream,
     ) -> TokenStream {
         match self {
             Self::Unit => quote! {
-                ::std::result::Result::Ok(::autosurgeon::reconcile::LoadKey::Found(#variant_name)),
+                ::std::result::Result::Ok(::autosurgeon::reconcile::LoadKey::Found(
+                    #key_type_name::#variant_name,
+                )),
             },
             Self::NewType(t) => {
-                let prop = variant_name.to_string();
                 if let Some(reconcile_with) = t.attrs.reconcile_with() {
                     quote! {
                         std::result::Result::Ok(
-                            #reconcile_with
-                                ::hydrate_key(
-                                    doc,
-                                    &#obj_id_ident,
-                                    ::std::convert::Into::into(#prop),
-                                )?
+                            #reconcile_with::hydrate_key(doc, #obj, #prop)?
                                 .map(#key_type_name::#variant_name),
                         ),
                     }
@@ -273,43 +447,26 @@ impl<'a> EnumKeyInnerType<'a> {
                     let t = t.ty;
                     quote! {
                         std::result::Result::Ok(
-                            <#t as ::autosurgeon::Reconcile>
-                                ::hydrate_key(
-                                    doc,
-                                    &#obj_id_ident,
-                                    ::std::convert::Into::into(#prop),
-                                )?
+                            <#t as ::autosurgeon::Reconcile>::hydrate_key(doc, #obj, #prop)?
                                 .map(#key_type_name::#variant_name),
                         ),
                     }
                 }
             }
-            Self::Struct(keyfield) => {
-                let prop = variant_name.to_string();
-                let key_prop = keyfield.prop();
+            Self::Struct(keyfields) => {
+                let hydrate = keyfields.hydrate(obj, prop);
                 quote! {
                     {
-                        let inner = ::autosurgeon::reconcile::hydrate_key(
-                            doc,
-                            &#obj_id_ident,
-                            ::std::convert::Into::into(#prop),
-                            ::std::convert::Into::into(#key_prop),
-                        )?;
+                        let inner = #hydrate?;
                         ::std::result::Result::Ok(inner.map(#key_type_name::#variant_name))
                     },
                 }
             }
-            Self::Tuple(keyfield) => {
-                let prop = variant_name.to_string();
-                let key_prop = keyfield.prop();
+            Self::Tuple(keyfields) => {
+                let hydrate = keyfields.hydrate(obj, prop);
                 quote! {
                     {
-                        let inner = ::autosurgeon::reconcile::hydrate_key(
-                            doc,
-                            &#obj_id_ident,
-                            ::std::convert::Into::into(#prop),
-                            ::std::convert::Into::into(#key_prop),
-                        )?;
+                        let inner = #hydrate?;
                         ::std::result::Result::Ok(inner.map(#key_type_name::#variant_name))
                     },
                 }
@@ -337,6 +494,7 @@ impl<'a> EnumKeyInnerType<'a> {
 
 struct EnumKeyVariant<'a> {
     name: &'a syn::Ident,
+    discriminant: &'a str,
     ty: EnumKeyInnerType<'a>,
 }
 
@@ -349,18 +507,37 @@ impl<'a> EnumKeyVariant<'a> {
         if EnumKeyInnerType::Unit == self.ty {
             None
         } else {
-            let name_str = self.name.to_string();
-            let hydrate = self.ty.hydrate_key(outer_name, self.name, obj_id_ident);
+            let name_str = self.discriminant;
+            let hydrate = self.ty.hydrate_key(
+                outer_name,
+                self.name,
+                &quote!(&#obj_id_ident),
+                &quote!(::std::convert::Into::into(#name_str)),
+            );
             Some(quote! {
                 #name_str => #hydrate
             })
         }
     }
 
+    /// A match arm for a tagged enum, matching the value of the tag
+    fn tagged_match_arm(
+        &self,
+        outer_name: &syn::Ident,
+        obj: &TokenStream,
+        prop: &TokenStream,
+    ) -> TokenStream {
+        let name_str = self.discriminant;
+        let hydrate = self.ty.hydrate_key(outer_name, self.name, obj, prop);
+        quote! {
+            ::std::option::Option::Some(#name_str) => #hydrate
+        }
+    }
+
     fn unit_match_arm(&self, outer_name: &syn::Ident) -> Option<TokenStream> {
         if EnumKeyInnerType::Unit == self.ty {
             let name = &self.name;
-            let name_str = self.name.to_string();
+            let name_str = self.discriminant;
             let variant_name = quote!(#outer_name::#name);
             Some(quote! {
                 #name_str => ::std::result::Result::Ok(
@@ -405,46 +582,57 @@ impl<'a> EnumKey<'a> {
             Vec::new(),
             move |mut variants, variant| {
                 let next = match variant {
-                    Variant::Unit { name } => EnumKeyVariant {
+                    Variant::Unit { name, discriminant } => EnumKeyVariant {
                         name,
+                        discriminant,
                         ty: EnumKeyInnerType::Unit,
                     },
                     Variant::NewType {
                         name,
+                        discriminant,
                         inner_ty,
                         attrs,
                         ..
                     } => EnumKeyVariant {
                         name,
+                        discriminant,
                         ty: EnumKeyInnerType::NewType(NewTypeKey {
                             ty: inner_ty,
                             attrs,
                         }),
                     },
-                    Variant::Named { name, fields } => {
-                        match NamedFields::try_from(*fields)?.key()? {
-                            Some(key) => EnumKeyVariant {
-                                name,
-                                ty: EnumKeyInnerType::Struct(key.into_owned()),
-                            },
-                            None => EnumKeyVariant {
-                                name,
-                                ty: EnumKeyInnerType::NoInnerKeyStruct,
-                            },
-                        }
-                    }
-                    Variant::Unnamed { name, fields } => {
-                        match UnnamedFields::try_from(*fields)?.key()? {
-                            Some(key) => EnumKeyVariant {
-                                name,
-                                ty: EnumKeyInnerType::Tuple(key.into_owned()),
-                            },
-                            None => EnumKeyVariant {
-                                name,
-                                ty: EnumKeyInnerType::NoInnerKeyTuple,
-                            },
-                        }
-                    }
+                    Variant::Named {
+                        name,
+                        discriminant,
+                        fields,
+                    } => match NamedFields::try_from(*fields)?.key()? {
+                        Some(key) => EnumKeyVariant {
+                            name,
+                            discriminant,
+                            ty: EnumKeyInnerType::Struct(key.into_owned()),
+                        },
+                        None => EnumKeyVariant {
+                            name,
+                            discriminant,
+                            ty: EnumKeyInnerType::NoInnerKeyStruct,
+                        },
+                    },
+                    Variant::Unnamed {
+                        name,
+                        discriminant,
+                        fields,
+                    } => match UnnamedFields::try_from(*fields)?.key()? {
+                        Some(key) => EnumKeyVariant {
+                            name,
+                            discriminant,
+                            ty: EnumKeyInnerType::Tuple(key.into_owned()),
+                        },
+                        None => EnumKeyVariant {
+                            name,
+                            discriminant,
+                            ty: EnumKeyInnerType::NoInnerKeyTuple,
+                        },
+                    },
                 };
                 variants.push(next);
                 Ok(variants)
@@ -468,7 +656,44 @@ impl<'a> EnumKey<'a> {
         self.variants.iter().any(|v| v.has_lifetime())
     }
 
-    fn type_def(&self, vis: &syn::Visibility) -> Option<TokenStream> {
+    /// The type parameters of the outer enum which the key type mentions, along with the where
+    /// predicates which only constrain those parameters
+    fn type_params(
+        &self,
+        generics: &syn::Generics,
+    ) -> (Vec<syn::TypeParam>, Vec<syn::WherePredicate>) {
+        let key_lifetime = syn::Lifetime::new("'k", Span::mixed_site());
+        let variant_defs = self
+            .variants
+            .iter()
+            .filter_map(|v| v.key_type_variant_def(&key_lifetime))
+            .collect::<TokenStream>();
+        let (used, unused): (Vec<_>, Vec<_>) = generics
+            .type_params()
+            .partition(|p| crate::mentions(variant_defs.clone(), &p.ident));
+        let params = used
+            .into_iter()
+            .map(|p| syn::TypeParam {
+                eq_token: None,
+                default: None,
+                ..p.clone()
+            })
+            .collect();
+        let predicates = generics
+            .where_clause
+            .iter()
+            .flat_map(|w| w.predicates.iter())
+            .filter(|pred| {
+                !unused
+                    .iter()
+                    .any(|p| crate::mentions(quote!(#pred), &p.ident))
+            })
+            .cloned()
+            .collect();
+        (params, predicates)
+    }
+
+    fn type_def(&self, vis: &syn::Visibility, generics: &syn::Generics) -> Option<TokenStream> {
         if !self.has_keyed_variants() {
             return None;
         }
@@ -478,18 +703,73 @@ impl<'a> EnumKey<'a> {
             .iter()
             .filter_map(|v| v.key_type_variant_def(&key_lifetime));
         let name = self.type_name();
-        let name_with_lifetime = if self.has_lifetime() {
-            quote!(#name<#key_lifetime>)
-        } else {
-            quote!(#name)
-        };
+        let (params, predicates) = self.type_params(generics);
+        let lifetime = self
+            .has_lifetime()
+            .then_some(&key_lifetime)
+            .into_iter()
+            .collect::<Vec<_>>();
+        let param_names = params.iter().map(|p| &p.ident).collect::<Vec<_>>();
+
+        // `Clone` and `PartialEq` are implemented by hand rather than derived so that they are
+        // bounded by the types of the fields of the key rather than by the type parameters,
+        // which needn't implement either trait
+        let keyed = self
+            .variants
+            .iter()
+            .filter(|v| v.has_key())
+            .map(|v| (v.name, v.ty.key_field_type(&key_lifetime)))
+            .collect::<Vec<_>>();
+        let field_tys = keyed.iter().filter_map(|(_, ty)| ty.as_ref());
+        let clone_bounds = field_tys
+            .clone()
+            .map(|ty| quote!(#ty: ::std::clone::Clone))
+            .collect::<Vec<_>>();
+        let eq_bounds = field_tys
+            .map(|ty| quote!(#ty: ::std::cmp::PartialEq))
+            .collect::<Vec<_>>();
+        let clone_arms = keyed.iter().map(|(variant, ty)| match ty {
+            Some(_) => quote!(Self::#variant(k) => Self::#variant(::std::clone::Clone::clone(k))),
+            None => quote!(Self::#variant => Self::#variant),
+        });
+        let eq_arms = keyed.iter().map(|(variant, ty)| match ty {
+            Some(_) => quote!((Self::#variant(a), Self::#variant(b)) => a == b),
+            None => quote!((Self::#variant, Self::#variant) => true),
+        });
+
         let span = Span::mixed_site();
         Some(quote_spanned! {span=>
-            #[derive(::std::clone::Clone, ::std::cmp::PartialEq)]
-            #[allow(clippy::derive_partial_eq_without_eq)]
-            #vis enum #name_with_lifetime {
+            #vis enum #name<#(#lifetime,)* #(#params),*> where #(#predicates),* {
                 #(#variant_defs),*
             }
+
+            impl<#(#lifetime,)* #(#params),*> ::std::clone::Clone
+                for #name<#(#lifetime,)* #(#param_names),*>
+            where
+                #(#predicates,)*
+                #(#clone_bounds,)*
+            {
+                fn clone(&self) -> Self {
+                    match self {
+                        #(#clone_arms),*
+                    }
+                }
+            }
+
+            impl<#(#lifetime,)* #(#params),*> ::std::cmp::PartialEq
+                for #name<#(#lifetime,)* #(#param_names),*>
+            where
+                #(#predicates,)*
+                #(#eq_bounds,)*
+            {
+                #[allow(unreachable_patterns)]
+                fn eq(&self, other: &Self) -> bool {
+                    match (self, other) {
+                        #(#eq_arms,)*
+                        _ => false,
+                    }
+                }
+            }
         })
     }
 
@@ -509,11 +789,70 @@ impl<'a> EnumKey<'a> {
         })
     }
 
-    fn hydrate_key(&self) -> Option<TokenStream> {
+    fn hydrate_key(&self, repr: &attrs::EnumRepr) -> Option<TokenStream> {
         if !self.has_keyed_variants() {
             return None;
         }
+        match repr {
+            attrs::EnumRepr::External => Some(self.hydrate_external_key()),
+            attrs::EnumRepr::Internal { tag } => Some(self.hydrate_tagged_key(tag, None)),
+            attrs::EnumRepr::Adjacent { tag, content } => {
+                Some(self.hydrate_tagged_key(tag, Some(content)))
+            }
+        }
+    }
+
+    /// `hydrate_key` for a tagged enum. If `content` is `None` then the contents of the variant
+    /// are in the same map as the tag.
+    fn hydrate_tagged_key(&self, tag: &str, content: Option<&String>) -> TokenStream {
+        let key_type_name = self.type_name();
+        let outer_id_ident = syn::Ident::new("outer_id", Span::mixed_site());
+        let (obj, prop) = match content {
+            Some(content) => (
+                quote!(&#outer_id_ident),
+                quote!(::std::convert::Into::into(#content)),
+            ),
+            None => (quote!(obj), quote!(::std::clone::Clone::clone(&prop))),
+        };
+        let match_arms = self
+            .variants
+            .iter()
+            .map(|v| v.tagged_match_arm(&key_type_name, &obj, &prop));
+        let k = syn::Lifetime::new("'k", Span::mixed_site());
+        quote! {
+            fn hydrate_key<#k, D: ::autosurgeon::ReadDoc>(
+                doc: &D,
+                obj: &::automerge::ObjId,
+                prop: ::autosurgeon::Prop<'_>,
+            ) -> ::std::result::Result<
+                ::autosurgeon::reconcile::LoadKey<Self::Key<#k>>,
+                ::autosurgeon::ReconcileError,
+            > {
+                let ::std::option::Option::Some((
+                    ::automerge::Value::Object(
+                        ::automerge::ObjType::Map | ::automerge::ObjType::Table
+                    ),
+                    #outer_id_ident,
+                )) = ::autosurgeon::ReadDoc::get(doc, obj, &prop)?
+                else {
+                    return ::std::result::Result::Ok(::autosurgeon::reconcile::LoadKey::KeyNotFound)
+                };
+                let ::std::option::Option::Some((tag, _)) =
+                    ::autosurgeon::ReadDoc::get(doc, &#outer_id_ident, #tag)?
+                else {
+                    return ::std::result::Result::Ok(::autosurgeon::reconcile::LoadKey::KeyNotFound)
+                };
+                match tag.to_str() {
+                    #(#match_arms)*
+                    _ => ::std::result::Result::Ok(
+                        ::autosurgeon::reconcile::LoadKey::KeyNotFound,
+                    ),
+                }
+            }
+        }
+    }
 
+    fn hydrate_external_key(&self) -> TokenStream {
         let key_type_name = self.type_name();
 
         let outer_id_ident = syn::Ident::new("outer_id", Span::mixed_site());
@@ -527,7 +866,7 @@ impl<'a> EnumKey<'a> {
             .iter()
             .filter_map(|v| v.unit_match_arm(&key_type_name));
         let k = syn::Lifetime::new("'k", Span::mixed_site());
-        Some(quote! {
+        quote! {
             fn hydrate_key<#k, D: ::autosurgeon::ReadDoc>(
                 doc: &D,
                 obj: &::automerge::ObjId,
@@ -557,7 +896,9 @@ impl<'a> EnumKey<'a> {
                             ::autosurgeon::reconcile::LoadKey::KeyNotFound,
                         )
                     },
-                    ::automerge::Value::Object(::automerge::ObjType::Map) => {
+                    ::automerge::Value::Object(
+                        ::automerge::ObjType::Map | ::automerge::ObjType::Table
+                    ) => {
                         let ::std::option::Option::Some(::automerge::iter::MapRangeItem {
                             key: discriminant_str,
                             ..
@@ -579,22 +920,18 @@ impl<'a> EnumKey<'a> {
                     )
                 }
             }
-        })
+        }
     }
 
-    fn key_type(&self) -> Option<TokenStream> {
+    fn key_type(&self, generics: &syn::Generics) -> Option<TokenStream> {
         if self.has_keyed_variants() {
             let key_type = self.type_name();
             let k = syn::Lifetime::new("'k", Span::mixed_site());
-            if self.has_lifetime() {
-                Some(quote! {
-                    type Key<#k> = #key_type<#k>;
-                })
-            } else {
-                Some(quote! {
-                    type Key<#k> = #key_type;
-                })
-            }
+            let lifetime = self.has_lifetime().then_some(&k).into_iter();
+            let params = self.type_params(generics).0.into_iter().map(|p| p.ident);
+            Some(quote! {
+                type Key<#k> = #key_type<#(#lifetime,)* #(#params),*>;
+            })
         } else {
             None
         }
@@ -607,18 +944,13 @@ pub(super) fn enum_impl(
     generics: &syn::Generics,
     reconciler_ident: &syn::Ident,
     data: &syn::DataEnum,
+    repr: &attrs::EnumRepr,
 ) -> Result<ReconcileImpl, DeriveError> {
-    let variants = data
-        .variants
-        .iter()
-        .map(Variant::try_from)
-        .collect::<Result<Vec<_>, _>>()?;
-    let matches = variants.iter().try_fold::<_, _, Result<_, DeriveError>>(
-        Vec::new(),
-        |mut results, v| {
-            results.push(v.match_arm(reconciler_ident, generics)?);
-            Ok(results)
-        },
+    let variants = collect_all(data.variants.iter().map(Variant::try_from))?;
+    let matches = collect_all(
+        variants
+            .iter()
+            .map(|v| v.match_arm(reconciler_ident, generics, repr)),
     )?;
     let enumkey = EnumKey::from_variants(name, variants.iter())?;
     let reconcile = quote! {
@@ -627,11 +959,43 @@ pub(super) fn enum_impl(
         }
     };
     Ok(ReconcileImpl {
-        key_type: enumkey.key_type(),
+        key_type: enumkey.key_type(generics),
         reconcile,
-        hydrate_key: enumkey.hydrate_key(),
+        hydrate_key: enumkey.hydrate_key(repr),
         get_key: enumkey.get_key(),
-        key_type_def: enumkey.type_def(vis),
+        key_type_def: enumkey.type_def(vis, generics),
+    })
+}
+
+/// Reconcile an enum with `#[autosurgeon(repr = "int")]`, all the variants of which are unit
+/// variants, as the discriminant of the variant
+pub(super) fn int_enum_impl(
+    reconciler_ident: &syn::Ident,
+    data: &syn::DataEnum,
+) -> Result<ReconcileImpl, DeriveError> {
+    let arms = data
+        .variants
+        .iter()
+        .map(|v| match v.fields {
+            syn::Fields::Unit => {
+                let ident = &v.ident;
+                Ok(quote!(Self::#ident => Self::#ident as ::std::primitive::i64))
+            }
+            _ => Err(DeriveError::IntRepr),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let reconcile = quote! {
+        let discriminant = match self {
+            #(#arms),*
+        };
+        ::autosurgeon::Reconciler::i64(&mut #reconciler_ident, discriminant)
+    };
+    Ok(ReconcileImpl {
+        key_type: None,
+        reconcile,
+        hydrate_key: None,
+        get_key: None,
+        key_type_def: None,
     })
 }
 
@@ -639,6 +1003,7 @@ pub(super) fn enum_impl(
 struct EnumUnnamedField<'a> {
     field: &'a syn::Field,
     idx: usize,
+    prop_idx: usize,
     attrs: attrs::Field,
 }
 
@@ -672,7 +1037,7 @@ impl<'a> Field for EnumUnnamedField<'a> {
     }
 
     fn as_prop(&self) -> TokenStream {
-        let idx = self.idx;
+        let idx = self.prop_idx;
         quote!(#idx)
     }
 
@@ -692,6 +1057,14 @@ impl<'a> Field for EnumUnnamedField<'a> {
     fn hydrate_with(&self) -> Option<&attrs::HydrateWith> {
         self.attrs.hydrate_with()
     }
+
+    fn is_key(&self) -> bool {
+        self.attrs.key()
+    }
+
+    fn none_as_absent(&self) -> bool {
+        self.attrs.none_as_absent()
+    }
 }
 
 struct EnumNamedField<'a> {
@@ -755,7 +1128,7 @@ impl<'a> VariantWithFields for &'a syn::FieldsNamed {
         fields: &[Self::Field],
     ) -> Result<ReconcileImpl, DeriveError> {
         let inner_fields = fields.iter().map(|f| f.named_field()).collect::<Vec<_>>();
-        named_field_impl(inner_reconciler_ident, inner_fields)
+        named_field_impl(inner_reconciler_ident, inner_fields, false, false)
     }
 
     fn variant_matcher<I: Iterator<Item = TokenStream>>(
@@ -773,14 +1146,18 @@ impl<'a> VariantWithFields for &'a syn::FieldsUnnamed {
     type Field = EnumUnnamedField<'a>;
 
     fn fields(&self) -> Result<Vec<Self::Field>, DeriveError> {
-        self.unnamed
-            .iter()
-            .enumerate()
-            .map(|(idx, field)| {
+        let prop_indices = crate::tuple_prop_indices(self);
+        collect_all(self.unnamed.iter().zip(prop_indices).enumerate().map(
+            |(idx, (field, prop_idx))| {
                 let attrs = attrs::Field::from_field(field)?.unwrap_or_default();
-                Ok(EnumUnnamedField { field, idx, attrs })
-            })
-            .collect::<Result<Vec<_>, _>>()
+                Ok(EnumUnnamedField {
+                    field,
+                    idx,
+                    prop_idx,
+                    attrs,
+                })
+            },
+        ))
     }
 
     fn inner_impl(
@@ -806,9 +1183,10 @@ fn enum_with_fields_variant<F: VariantWithFields>(
     reconciler_ident: &syn::Ident,
     generics: &syn::Generics,
     name: &syn::Ident,
+    variant_name_str: &str,
     variant: F,
+    repr: &attrs::EnumRepr,
 ) -> Result<TokenStream, DeriveError> {
-    let variant_name_str = name.to_string();
     let visitor_name = format_ident!("{}ReconcileVisitor", name);
 
     let fields = variant.fields()?;
@@ -816,7 +1194,10 @@ fn enum_with_fields_variant<F: VariantWithFields>(
     let field_defs = fields.iter().map(|f| {
         let name = f.name();
         let ty = f.ty();
+        // `PhantomData` fields are only stored to use the type parameters of the enum
+        let allow = crate::is_phantom_data(ty).then(|| quote!(#[allow(dead_code)]));
         quote! {
+            #allow
             #name: &'__reconcile_visitor #ty
         }
     });
@@ -832,6 +1213,16 @@ fn enum_with_fields_variant<F: VariantWithFields>(
         ..
     } = variant.inner_impl(&inner_reconciler_ident, &fields)?;
 
+    // The visitor is constructed with explicit generic arguments because they can't always be
+    // inferred from the field types (e.g. if a field is an associated type of a type parameter)
+    let lifetime_args = generics.lifetimes().map(|l| &l.lifetime);
+    let type_args = generics.params.iter().filter_map(|p| match p {
+        syn::GenericParam::Lifetime(_) => None,
+        syn::GenericParam::Type(t) => Some(&t.ident),
+        syn::GenericParam::Const(c) => Some(&c.ident),
+    });
+    let visitor_args = quote!(::<#(#lifetime_args,)* '_, #(#type_args),*>);
+
     let mut generics = generics.clone();
     generics
         .params
@@ -839,15 +1230,18 @@ fn enum_with_fields_variant<F: VariantWithFields>(
 
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     let variant_matcher = variant.variant_matcher(name, matchers);
+    let put = put_variant(reconciler_ident, repr, variant_name_str, Some(quote!(v)));
 
     Ok(quote! {
         #variant_matcher => {
-            struct #visitor_name #ty_generics
+            struct #visitor_name #impl_generics
             #where_clause
             {
                 #(#field_defs),*
             }
-            impl #impl_generics ::autosurgeon::Reconcile for #visitor_name #ty_generics {
+            impl #impl_generics ::autosurgeon::Reconcile for #visitor_name #ty_generics
+            #where_clause
+            {
                 type Key<'k> = ::autosurgeon::reconcile::NoKey;
                 fn reconcile<__R234: ::autosurgeon::Reconciler>(
                     &self,
@@ -856,12 +1250,10 @@ fn enum_with_fields_variant<F: VariantWithFields>(
                     #inner_reconcile
                 }
             }
-            let v = #visitor_name {
+            let v = #visitor_name #visitor_args {
                 #(#constructors),*
             };
-            let mut m = ::autosurgeon::Reconciler::map(&mut #reconciler_ident)?;
-            ::autosurgeon::reconcile::MapReconciler::retain(&mut m, |k, _| k == #variant_name_str)?;
-            ::autosurgeon::reconcile::MapReconciler::put(&mut m, #variant_name_str, v)?;
+            #put
             ::std::result::Result::Ok(())
         }
     })
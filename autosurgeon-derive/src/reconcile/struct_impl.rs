@@ -4,7 +4,10 @@ use proc_macro2::{Span, TokenStream};
 use quote::{format_ident, quote, quote_spanned};
 use syn::spanned::Spanned;
 
-use crate::attrs;
+use crate::{
+    attrs,
+    combine::{collect_all, Combine},
+};
 
 use super::{
     error::{DeriveError, InvalidKeyAttr},
@@ -40,6 +43,12 @@ pub(super) trait Field {
 
     fn hydrate_with(&self) -> Option<&attrs::HydrateWith>;
 
+    /// Whether this field has an `#[autosurgeon(key)]` attribute
+    fn is_key(&self) -> bool;
+
+    /// Whether this field has an `#[autosurgeon(none_as_absent)]` attribute
+    fn none_as_absent(&self) -> bool;
+
     fn upsert(&self, reconciler_ident: &syn::Ident, reconciler_ty: ReconcilerType) -> TokenStream {
         let prop = self.as_prop();
         let accessor = self.accessor();
@@ -54,6 +63,33 @@ pub(super) trait Field {
             }
             None => (quote!(), quote!(&#accessor)),
         };
+        // `None` deletes the key rather than writing null
+        if self.none_as_absent() && reconciler_ty == ReconcilerType::Map {
+            return quote_spanned! {self.span()=>
+                match &#accessor {
+                    ::std::option::Option::Some(value) => {
+                        ::autosurgeon::reconcile::MapReconciler::put(
+                            &mut #reconciler_ident,
+                            #prop,
+                            value,
+                        )?;
+                    }
+                    ::std::option::Option::None => {
+                        if ::autosurgeon::reconcile::MapReconciler::entry(
+                            &#reconciler_ident,
+                            #prop,
+                        )
+                        .is_some()
+                        {
+                            ::autosurgeon::reconcile::MapReconciler::delete(
+                                &mut #reconciler_ident,
+                                #prop,
+                            )?;
+                        }
+                    }
+                }
+            };
+        }
         let get = match reconciler_ty {
             ReconcilerType::Map => quote_spanned! {self.span()=>
                 ::autosurgeon::reconcile::MapReconciler::entry(&#reconciler_ident, #prop)
@@ -153,7 +189,10 @@ impl<'a> Field for NamedField<'a> {
     }
 
     fn as_prop(&self) -> TokenStream {
-        let propname = &self.name.to_string();
+        let propname = match self.attrs.rename() {
+            Some(rename) => rename.to_string(),
+            None => self.name.to_string(),
+        };
         quote!(#propname)
     }
 
@@ -173,20 +212,35 @@ impl<'a> Field for NamedField<'a> {
     fn hydrate_with(&self) -> Option<&attrs::HydrateWith> {
         self.attrs.hydrate_with()
     }
+
+    fn is_key(&self) -> bool {
+        self.attrs.key()
+    }
+
+    fn none_as_absent(&self) -> bool {
+        self.attrs.none_as_absent()
+    }
 }
 
 #[derive(Clone, Eq, PartialEq)]
 pub(super) struct TupleField<'a> {
     index: usize,
+    /// The index of this field in the document, which skips `PhantomData` fields
+    prop_index: usize,
     field: Cow<'a, syn::Field>,
     attrs: attrs::Field,
 }
 
 impl<'a> TupleField<'a> {
-    fn new(index: usize, field: Cow<'a, syn::Field>) -> Result<TupleField<'a>, syn::parse::Error> {
+    fn new(
+        index: usize,
+        prop_index: usize,
+        field: Cow<'a, syn::Field>,
+    ) -> Result<TupleField<'a>, syn::parse::Error> {
         let attrs = attrs::Field::from_field(&field)?.unwrap_or_default();
         Ok(Self {
             index,
+            prop_index,
             field,
             attrs,
         })
@@ -196,6 +250,7 @@ impl<'a> TupleField<'a> {
         let field: syn::Field = self.field.as_ref().clone();
         TupleField {
             index: self.index,
+            prop_index: self.prop_index,
             field: Cow::Owned(field),
             attrs: self.attrs.clone(),
         }
@@ -216,7 +271,7 @@ impl<'a> Field for TupleField<'a> {
     }
 
     fn as_prop(&self) -> TokenStream {
-        let idx = self.index;
+        let idx = self.prop_index;
         quote!(#idx)
     }
 
@@ -236,6 +291,14 @@ impl<'a> Field for TupleField<'a> {
     fn hydrate_with(&self) -> Option<&attrs::HydrateWith> {
         self.attrs.hydrate_with()
     }
+
+    fn is_key(&self) -> bool {
+        self.attrs.key()
+    }
+
+    fn none_as_absent(&self) -> bool {
+        self.attrs.none_as_absent()
+    }
 }
 
 #[derive(Clone, Eq, PartialEq)]
@@ -271,166 +334,177 @@ impl<'a> KeyField<'a, TupleField<'a>> {
 }
 
 impl<'a, F: Field + Clone> KeyField<'a, F> {
+    /// An expression which hydrates this key from inside the object at `prop` of `obj`,
+    /// evaluating to a `Result<LoadKey<Cow<'_, T>>, ReconcileError>`
+    fn hydrate(&self, obj: &TokenStream, prop: &TokenStream) -> TokenStream {
+        let key_prop = self.field.as_prop();
+        if let Some(hydrate_with) = self.field.hydrate_with() {
+            let hydrate_func = hydrate_with.hydrate_with();
+            quote! {
+                ::autosurgeon::reconcile::hydrate_key_with(
+                    doc,
+                    #obj,
+                    #prop,
+                    ::std::convert::Into::into(#key_prop),
+                    |doc, obj, prop| #hydrate_func(doc, obj, prop),
+                )
+                .map(|k| k.map(::std::borrow::Cow::Owned))
+            }
+        } else {
+            quote! {
+                ::autosurgeon::reconcile::hydrate_key::<_, ::std::borrow::Cow<'_, _>>(
+                    doc,
+                    #obj,
+                    #prop,
+                    ::std::convert::Into::into(#key_prop),
+                )
+            }
+        }
+    }
+}
+
+/// The fields marked with `#[key]` or `#[autosurgeon(key)]`
+///
+/// A single key field has a key of type `Cow<'k, T>`. If there are several then the key is a
+/// tuple of a `Cow` for each field, in the order the fields are declared.
+#[derive(Clone, Eq, PartialEq)]
+pub(super) struct KeyFields<'a, F: Clone>(Vec<KeyField<'a, F>>);
+
+impl<'a> KeyFields<'a, NamedField<'a>> {
+    pub(super) fn into_owned(self) -> KeyFields<'static, NamedField<'static>> {
+        KeyFields(self.0.into_iter().map(|k| k.into_owned()).collect())
+    }
+
+    pub(super) fn names(&self) -> Vec<&syn::Ident> {
+        self.0.iter().map(|k| k.name()).collect()
+    }
+}
+
+impl<'a> KeyFields<'a, TupleField<'a>> {
+    pub(super) fn into_owned(self) -> KeyFields<'static, TupleField<'static>> {
+        KeyFields(self.0.into_iter().map(|k| k.into_owned()).collect())
+    }
+
+    pub(super) fn indices(&self) -> Vec<usize> {
+        self.0.iter().map(|k| k.index()).collect()
+    }
+}
+
+impl<'a, F: Field + Clone> KeyFields<'a, F> {
     pub(super) fn from_fields<I: Iterator<Item = &'a F>>(
         fields: I,
-    ) -> Result<Option<KeyField<'a, F>>, InvalidKeyAttr> {
-        let mut key_field = None;
+    ) -> Result<Option<KeyFields<'a, F>>, InvalidKeyAttr> {
+        let mut key_fields = Vec::new();
         for field in fields {
-            for attr in field.attrs() {
-                if attr.path().is_ident("key") {
-                    if key_field.is_some() {
-                        return Err(InvalidKeyAttr::MultipleKey);
-                    } else {
-                        key_field = Some(KeyField {
-                            ty: Cow::Borrowed(field.ty()),
-                            field: Cow::Borrowed(field),
-                        });
-                    }
-                }
+            let key_attrs = field
+                .attrs()
+                .iter()
+                .filter(|attr| attr.path().is_ident("key"))
+                .count()
+                + usize::from(field.is_key());
+            if key_attrs > 1 {
+                return Err(InvalidKeyAttr::MultipleKey);
+            } else if key_attrs == 1 {
+                key_fields.push(KeyField {
+                    ty: Cow::Borrowed(field.ty()),
+                    field: Cow::Borrowed(field),
+                });
             }
         }
-        Ok(key_field)
+        Ok((!key_fields.is_empty()).then_some(KeyFields(key_fields)))
     }
 
-    fn key_type_def(&self) -> proc_macro2::TokenStream {
-        let ty = &self.ty;
-        let lifetime = syn::Lifetime::new("'k", Span::mixed_site());
-        quote! {
-            type Key<#lifetime> = ::std::borrow::Cow<#lifetime, #ty>;
+    /// The type of the key, borrowing from the value for `lifetime`
+    pub(super) fn key_type(&self, lifetime: &syn::Lifetime) -> TokenStream {
+        let tys = self.0.iter().map(|k| {
+            let ty = &k.ty;
+            quote!(::std::borrow::Cow<#lifetime, #ty>)
+        });
+        if self.0.len() == 1 {
+            quote!(#(#tys)*)
+        } else {
+            quote!((#(#tys),*))
         }
     }
 
-    pub(super) fn key_type(&self) -> &syn::Type {
-        self.ty.as_ref()
+    fn key_type_def(&self) -> TokenStream {
+        let lifetime = syn::Lifetime::new("'k", Span::mixed_site());
+        let key_type = self.key_type(&lifetime);
+        quote! {
+            type Key<#lifetime> = #key_type;
+        }
     }
 
-    fn hydrate_impl(&self) -> proc_macro2::TokenStream {
-        let key_prop = self.field.as_prop();
-        let key_lifetime = syn::Lifetime::new("'k", Span::mixed_site());
-        if let Some(hydrate_with) = self.field.hydrate_with() {
-            let hydrate_func = hydrate_with.hydrate_with();
-            quote! {
-                fn hydrate_key<#key_lifetime, D: ::autosurgeon::ReadDoc>(
-                    doc: &D,
-                    obj: &::automerge::ObjId,
-                    prop: ::autosurgeon::Prop<'_>,
-                ) -> ::std::result::Result<
-                    ::autosurgeon::reconcile::LoadKey<Self::Key<#key_lifetime>>,
-                    ::autosurgeon::ReconcileError,
-                > {
-                    let ::std::option::Option::Some(outer_type) =
-                        ::autosurgeon::ReadDoc::object_type(doc, &obj)
-                    else {
-                        return ::std::result::Result::Ok(
-                            ::autosurgeon::reconcile::LoadKey::KeyNotFound,
-                        )
-                    };
-                    let maybe_inner = match (outer_type, prop) {
-                        (
-                            ::automerge::ObjType::Map | ::automerge::ObjType::Table,
-                            ::autosurgeon::Prop::Key(k)
-                        ) => ::autosurgeon::ReadDoc::get(
-                            doc,
-                            &obj,
-                            ::std::convert::AsRef::as_ref(&k),
-                        )?,
-                        (
-                            ::automerge::ObjType::List | ::automerge::ObjType::Text,
-                            ::autosurgeon::Prop::Index(i)
-                        ) => ::autosurgeon::ReadDoc::get(doc, &obj, i as ::std::primitive::usize)?,
-                        _ => return ::std::result::Result::Ok(
-                            ::autosurgeon::reconcile::LoadKey::KeyNotFound,
-                        ),
-                    };
-                    let ::std::option::Option::Some((_, inner_obj)) = maybe_inner else {
-                        return ::std::result::Result::Ok(
-                            ::autosurgeon::reconcile::LoadKey::KeyNotFound,
-                        )
-                    };
-                    let ::std::option::Option::Some(inner_type) =
-                        ::autosurgeon::ReadDoc::object_type(doc, &inner_obj)
-                    else {
-                        return ::std::result::Result::Ok(
-                            ::autosurgeon::reconcile::LoadKey::KeyNotFound,
-                        )
-                    };
-                    let inner_val = match (inner_type, ::autosurgeon::Prop::from(#key_prop)) {
-                        (
-                            ::automerge::ObjType::Map | ::automerge::ObjType::Table,
-                            ::autosurgeon::Prop::Key(k)
-                        ) => ::autosurgeon::ReadDoc::get(
-                            doc,
-                            &inner_obj,
-                            ::std::convert::AsRef::as_ref(&k),
-                        )?,
-                        (
-                            ::automerge::ObjType::List | ::automerge::ObjType::Text,
-                            ::autosurgeon::Prop::Index(i)
-                        ) => ::autosurgeon::ReadDoc::get(
-                            doc,
-                            &inner_obj,
-                            i as ::std::primitive::usize,
-                        )?,
-                        _ => return ::std::result::Result::Ok(
-                            ::autosurgeon::reconcile::LoadKey::KeyNotFound,
-                        ),
-                    };
-                    if inner_val.is_none() {
-                        return ::std::result::Result::Ok(
-                            ::autosurgeon::reconcile::LoadKey::KeyNotFound,
+    /// An expression which hydrates the key from inside the object at `prop` of `obj`,
+    /// evaluating to a `Result<LoadKey<Key>, ReconcileError>`
+    ///
+    /// A composite key is only found if every one of its fields is found.
+    pub(super) fn hydrate(&self, obj: &TokenStream, prop: &TokenStream) -> TokenStream {
+        if let [key] = self.0.as_slice() {
+            return key.hydrate(obj, prop);
+        }
+        let prop_ident = syn::Ident::new("key_prop", Span::mixed_site());
+        let cloned_prop = quote!(::std::clone::Clone::clone(&#prop_ident));
+        let hydrators = self.0.iter().map(|k| k.hydrate(obj, &cloned_prop));
+        let names = (0..self.0.len())
+            .map(|i| format_ident!("key_{}", i, span = Span::mixed_site()))
+            .collect::<Vec<_>>();
+        quote! {
+            {
+                let #prop_ident: ::autosurgeon::Prop<'_> = #prop;
+                match (#(#hydrators?,)*) {
+                    (#(::autosurgeon::reconcile::LoadKey::Found(#names),)*) => {
+                        ::std::result::Result::<_, ::autosurgeon::ReconcileError>::Ok(
+                            ::autosurgeon::reconcile::LoadKey::Found((#(#names),*)),
                         )
-                    } else {
-                        match ::autosurgeon::hydrate::HydrateResultExt::strip_unexpected(
-                            #hydrate_func(doc, &inner_obj, ::std::convert::Into::into(#key_prop))
-                                .map(::std::option::Option::Some),
-                        )? {
-                            ::std::option::Option::Some(k) => ::std::result::Result::Ok(
-                                ::autosurgeon::reconcile::LoadKey::Found(
-                                    ::std::borrow::Cow::Owned(k),
-                                ),
-                            ),
-                            ::std::option::Option::None => ::std::result::Result::Ok(
-                                ::autosurgeon::reconcile::LoadKey::KeyNotFound,
-                            ),
-                        }
                     }
-
+                    _ => ::std::result::Result::Ok(::autosurgeon::reconcile::LoadKey::KeyNotFound),
                 }
             }
+        }
+    }
+
+    /// An expression for the key, given an expression which evaluates to a reference to each key
+    /// field
+    pub(super) fn found<I: IntoIterator<Item = TokenStream>>(&self, values: I) -> TokenStream {
+        let borrowed = values
+            .into_iter()
+            .map(|v| quote!(::std::borrow::Cow::Borrowed(#v)));
+        if self.0.len() == 1 {
+            quote!(#(#borrowed)*)
         } else {
-            quote! {
-                fn hydrate_key<#key_lifetime, D: ::autosurgeon::ReadDoc>(
-                    doc: &D,
-                    obj: &::automerge::ObjId,
-                    prop: ::autosurgeon::Prop<'_>,
-                ) -> ::std::result::Result<
-                    ::autosurgeon::reconcile::LoadKey<Self::Key<#key_lifetime>>,
-                    ::autosurgeon::ReconcileError,
-                > {
-                    ::autosurgeon::reconcile::hydrate_key::<_, ::std::borrow::Cow<'_, _>>(
-                        doc,
-                        obj,
-                        ::std::convert::Into::into(prop),
-                        ::std::convert::Into::into(#key_prop),
-                    )
-                }
-            }
+            quote!((#(#borrowed),*))
         }
     }
 
-    pub(super) fn prop(&self) -> TokenStream {
-        let key_prop = self.field.as_prop();
-        quote!(#key_prop)
+    fn hydrate_impl(&self) -> TokenStream {
+        let key_lifetime = syn::Lifetime::new("'k", Span::mixed_site());
+        let hydrate = self.hydrate(&quote!(obj), &quote!(prop));
+        quote! {
+            fn hydrate_key<#key_lifetime, D: ::autosurgeon::ReadDoc>(
+                doc: &D,
+                obj: &::automerge::ObjId,
+                prop: ::autosurgeon::Prop<'_>,
+            ) -> ::std::result::Result<
+                ::autosurgeon::reconcile::LoadKey<Self::Key<#key_lifetime>>,
+                ::autosurgeon::ReconcileError,
+            > {
+                #hydrate
+            }
+        }
     }
 
-    fn get_key(&self) -> proc_macro2::TokenStream {
-        let get_key = self.field.accessor();
+    fn get_key(&self) -> TokenStream {
+        let found = self.found(self.0.iter().map(|k| {
+            let accessor = k.field.accessor();
+            quote!(&#accessor)
+        }));
         let key_lifetime = syn::Lifetime::new("'k", Span::mixed_site());
         quote! {
             fn key<#key_lifetime>(
                 &#key_lifetime self,
             ) -> ::autosurgeon::reconcile::LoadKey<Self::Key<#key_lifetime>> {
-                ::autosurgeon::reconcile::LoadKey::Found(::std::borrow::Cow::Borrowed(&#get_key))
+                ::autosurgeon::reconcile::LoadKey::Found(#found)
             }
         }
     }
@@ -441,8 +515,8 @@ pub(super) struct NamedFields<'a>(Vec<NamedField<'a>>);
 impl<'a> NamedFields<'a> {
     pub(super) fn key(
         &'a self,
-    ) -> Result<Option<KeyField<'static, NamedField<'static>>>, InvalidKeyAttr> {
-        Ok(KeyField::from_fields(self.0.iter())?.map(|k| k.into_owned()))
+    ) -> Result<Option<KeyFields<'static, NamedField<'static>>>, InvalidKeyAttr> {
+        Ok(KeyFields::from_fields(self.0.iter())?.map(|k| k.into_owned()))
     }
 }
 
@@ -450,13 +524,9 @@ impl<'a> TryFrom<&'a syn::FieldsNamed> for NamedFields<'a> {
     type Error = DeriveError;
 
     fn try_from(fields: &'a syn::FieldsNamed) -> Result<Self, Self::Error> {
-        Ok(Self(
-            fields
-                .named
-                .iter()
-                .map(|f| NamedField::new(Cow::Borrowed(f.ident.as_ref().unwrap()), f))
-                .collect::<Result<Vec<_>, _>>()?,
-        ))
+        Ok(Self(collect_all(fields.named.iter().map(|f| {
+            NamedField::new(Cow::Borrowed(f.ident.as_ref().unwrap()), f)
+        }))?))
     }
 }
 
@@ -466,31 +536,82 @@ impl<'a> From<Vec<NamedField<'a>>> for NamedFields<'a> {
     }
 }
 
-impl<'a, E> TryFrom<Vec<Result<NamedField<'a>, E>>> for NamedFields<'a> {
+impl<'a, E: Combine> TryFrom<Vec<Result<NamedField<'a>, E>>> for NamedFields<'a> {
     type Error = E;
     fn try_from(f: Vec<Result<NamedField<'a>, E>>) -> Result<Self, Self::Error> {
-        Ok(Self(f.into_iter().collect::<Result<Vec<_>, _>>()?))
+        Ok(Self(collect_all(f)?))
     }
 }
 
 pub(super) fn named_field_impl<'a, F: TryInto<NamedFields<'a>, Error = DeriveError>>(
     reconciler_ident: &syn::Ident,
     fields: F,
+    exhaustive: bool,
+    track: bool,
 ) -> Result<ReconcileImpl, DeriveError> {
     let fields = fields.try_into()?.0;
+    let (dirty, fields) = split_dirty(fields, track)?;
 
     let inner_reconciler_ident = syn::Ident::new("m", Span::mixed_site());
 
+    let remove_stale = if exhaustive {
+        let props = fields
+            .iter()
+            .filter(|f| !crate::is_phantom_data(f.ty()))
+            .map(|f| f.as_prop());
+        quote! {
+            ::autosurgeon::reconcile::MapReconciler::retain(&mut #inner_reconciler_ident, |k, _| {
+                let fields: &[&::std::primitive::str] = &[#(#props),*];
+                fields.contains(&k)
+            })?;
+        }
+    } else {
+        quote!()
+    };
+
+    let stored = fields
+        .iter()
+        .filter(|f| !crate::is_phantom_data(f.ty()))
+        .cloned()
+        .collect::<Vec<_>>();
     let StructImpl {
-        field_impls,
+        mut field_impls,
         key_type,
         get_key,
         hydrate_key,
     } = struct_impl(fields, &inner_reconciler_ident, ReconcilerType::Map)?;
 
+    let clear_dirty = match dirty {
+        Some(dirty) => {
+            let dirty = dirty.accessor();
+            field_impls = field_impls
+                .into_iter()
+                .zip(stored.iter().enumerate())
+                .map(|(field_impl, (index, field))| {
+                    let prop = field.as_prop();
+                    quote! {
+                        if ::autosurgeon::Dirty::is_dirty(&#dirty, #index)
+                            || ::autosurgeon::reconcile::MapReconciler::entry(
+                                &#inner_reconciler_ident,
+                                #prop,
+                            )
+                            .is_none()
+                        {
+                            #field_impl
+                        }
+                    }
+                })
+                .collect();
+            quote!(::autosurgeon::Dirty::clear(&#dirty);)
+        }
+        None => quote!(),
+    };
+
     let the_impl = quote! {
         let mut #inner_reconciler_ident = ::autosurgeon::Reconciler::map(&mut #reconciler_ident)?;
         #( #field_impls)*
+        #remove_stale
+        #clear_dirty
         ::std::result::Result::Ok(())
     };
 
@@ -503,11 +624,221 @@ pub(super) fn named_field_impl<'a, F: TryInto<NamedFields<'a>, Error = DeriveErr
     })
 }
 
+/// Separate the `Dirty` field from the fields which are stored in the document, checking that a
+/// struct with `#[autosurgeon(track)]` has exactly one and not too many other fields
+fn split_dirty(
+    fields: Vec<NamedField<'_>>,
+    track: bool,
+) -> Result<(Option<NamedField<'_>>, Vec<NamedField<'_>>), DeriveError> {
+    let (dirty, fields): (Vec<_>, Vec<_>) =
+        fields.into_iter().partition(|f| crate::is_dirty(f.ty()));
+    if !track {
+        return Ok((None, fields));
+    }
+    let mut dirty = dirty.into_iter();
+    match (dirty.next(), dirty.next()) {
+        (Some(dirty), None) => {
+            let stored = fields
+                .iter()
+                .filter(|f| !crate::is_phantom_data(f.ty()))
+                .count();
+            if stored > 64 {
+                return Err(DeriveError::TooManyTracked);
+            }
+            Ok((Some(dirty), fields))
+        }
+        _ => Err(DeriveError::TrackDirtyField),
+    }
+}
+
+/// The `set_<field>` methods of a struct with `#[autosurgeon(track)]`, each of which sets a field
+/// and marks it dirty
+pub(super) fn tracked_setters(
+    name: &syn::Ident,
+    generics: &syn::Generics,
+    fields: &syn::FieldsNamed,
+) -> Result<TokenStream, DeriveError> {
+    let fields = NamedFields::try_from(fields)?.0;
+    let (dirty, fields) = split_dirty(fields, true)?;
+    let dirty = dirty
+        .expect("split_dirty checks there is a dirty field")
+        .accessor();
+    let setters = fields
+        .iter()
+        .filter(|f| !crate::is_phantom_data(f.ty()))
+        .enumerate()
+        .map(|(index, f)| {
+            let field_name = f.name();
+            let setter = format_ident!("set_{}", field_name);
+            let vis = &f.field.vis;
+            let ty = f.ty();
+            let doc = format!("Set `{}` and mark it dirty", field_name);
+            quote_spanned! {f.span()=>
+                #[doc = #doc]
+                #vis fn #setter(&mut self, value: #ty) -> &mut Self {
+                    self.#field_name = value;
+                    ::autosurgeon::Dirty::mark(&mut #dirty, #index);
+                    self
+                }
+            }
+        });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        // Not every field is necessarily set through its setter
+        #[allow(dead_code)]
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#setters)*
+        }
+    })
+}
+
+/// The `<Struct>FieldMask` type of a struct with `#[autosurgeon(field_mask)]`, which has a bit for
+/// every stored field, and the `ReconcileMasked` implementation which only writes the fields in
+/// the mask
+pub(super) fn field_mask(
+    name: &syn::Ident,
+    vis: &syn::Visibility,
+    generics: &syn::Generics,
+    fields: &syn::FieldsNamed,
+) -> Result<TokenStream, DeriveError> {
+    use syn::ext::IdentExt;
+
+    let fields = NamedFields::try_from(fields)?.0;
+    let (_, fields) = split_dirty(fields, false)?;
+    let stored = fields
+        .iter()
+        .filter(|f| !crate::is_phantom_data(f.ty()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if stored.len() > 64 {
+        return Err(DeriveError::TooManyMasked);
+    }
+    let mask_name = format_ident!("{}FieldMask", name);
+    let consts = stored
+        .iter()
+        .map(|f| format_ident!("{}", f.name().unraw().to_string().to_uppercase()))
+        .collect::<Vec<_>>();
+    let bits = (0..stored.len()).map(|i| 1_u64 << i);
+    let const_docs = stored.iter().map(|f| format!("The `{}` field", f.name()));
+    let props = stored.iter().map(|f| f.as_prop());
+    let all = if stored.len() == 64 {
+        u64::MAX
+    } else {
+        (1_u64 << stored.len()) - 1
+    };
+    let mask_doc = format!(
+        "A set of the fields of [`{}`], used to choose which fields `reconcile_masked` writes",
+        name
+    );
+
+    let inner_reconciler_ident = syn::Ident::new("m", Span::mixed_site());
+    let reconciler_ident = syn::Ident::new("reconciler", Span::mixed_site());
+    let mask_ident = syn::Ident::new("mask", Span::mixed_site());
+    let StructImpl { field_impls, .. } =
+        struct_impl(fields, &inner_reconciler_ident, ReconcilerType::Map)?;
+    let field_impls = field_impls
+        .into_iter()
+        .zip(consts.iter())
+        .map(|(field_impl, c)| {
+            quote! {
+                if #mask_name::contains(*#mask_ident, #mask_name::#c) {
+                    #field_impl
+                }
+            }
+        });
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #[doc = #mask_doc]
+        #[derive(
+            ::std::fmt::Debug,
+            ::std::clone::Clone,
+            ::std::marker::Copy,
+            ::std::cmp::PartialEq,
+            ::std::cmp::Eq,
+            ::std::hash::Hash,
+            ::std::default::Default,
+        )]
+        #vis struct #mask_name(u64);
+
+        // Not every field is necessarily used in a mask
+        #[allow(dead_code)]
+        impl #mask_name {
+            #(
+                #[doc = #const_docs]
+                pub const #consts: Self = Self(#bits);
+            )*
+
+            /// The mask containing no fields
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            /// The mask containing every field
+            pub const fn all() -> Self {
+                Self(#all)
+            }
+
+            /// Whether every field in `other` is also in `self`
+            pub const fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            /// Add the fields in `other` to `self`
+            pub fn insert(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+
+            /// Remove the fields in `other` from `self`
+            pub fn remove(&mut self, other: Self) {
+                self.0 &= !other.0;
+            }
+
+            /// The field which has the name `name` in the document
+            pub fn from_name(name: &::std::primitive::str) -> ::std::option::Option<Self> {
+                match name {
+                    #(#props => ::std::option::Option::Some(Self::#consts),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+
+        impl ::std::ops::BitOr for #mask_name {
+            type Output = Self;
+
+            fn bitor(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+        }
+
+        impl ::std::ops::BitOrAssign for #mask_name {
+            fn bitor_assign(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+        }
+
+        impl #impl_generics ::autosurgeon::ReconcileMasked for #name #ty_generics #where_clause {
+            type FieldMask = #mask_name;
+
+            fn reconcile_masked<__R123: ::autosurgeon::Reconciler>(
+                &self,
+                mut #reconciler_ident: __R123,
+                #mask_ident: &#mask_name,
+            ) -> ::std::result::Result<(), __R123::Error> {
+                let mut #inner_reconciler_ident =
+                    ::autosurgeon::Reconciler::map(&mut #reconciler_ident)?;
+                #(#field_impls)*
+                ::std::result::Result::Ok(())
+            }
+        }
+    })
+}
+
 pub(super) struct UnnamedFields<F>(Vec<F>);
 
 impl<F: Field + Clone> UnnamedFields<F> {
-    pub(super) fn key(&self) -> Result<Option<KeyField<'_, F>>, InvalidKeyAttr> {
-        KeyField::from_fields(self.0.iter())
+    pub(super) fn key(&self) -> Result<Option<KeyFields<'_, F>>, InvalidKeyAttr> {
+        KeyFields::from_fields(self.0.iter())
     }
 }
 
@@ -515,13 +846,16 @@ impl<'a> TryFrom<&'a syn::FieldsUnnamed> for UnnamedFields<TupleField<'a>> {
     type Error = DeriveError;
 
     fn try_from(f: &'a syn::FieldsUnnamed) -> Result<Self, Self::Error> {
-        Ok(UnnamedFields(
+        let prop_indices = crate::tuple_prop_indices(f);
+        Ok(UnnamedFields(collect_all(
             f.unnamed
                 .iter()
+                .zip(prop_indices)
                 .enumerate()
-                .map(|(index, f)| TupleField::new(index, Cow::Borrowed(f)))
-                .collect::<Result<Vec<_>, _>>()?,
-        ))
+                .map(|(index, (f, prop_index))| {
+                    TupleField::new(index, prop_index, Cow::Borrowed(f))
+                }),
+        )?))
     }
 }
 
@@ -578,9 +912,10 @@ fn struct_impl<F: Field + Clone>(
     reconciler_ident: &syn::Ident,
     reconciler_type: ReconcilerType,
 ) -> Result<StructImpl, DeriveError> {
-    let key_field = KeyField::from_fields(fields.iter())?;
+    let key_field = KeyFields::from_fields(fields.iter())?;
     let field_impls = fields
         .iter()
+        .filter(|f| !crate::is_phantom_data(f.ty()))
         .map(|f| f.upsert(reconciler_ident, reconciler_type))
         .collect();
     let key_type = key_field.as_ref().map(|k| k.key_type_def());
@@ -0,0 +1,223 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{parse_macro_input, parse_quote, spanned::Spanned, DeriveInput, Fields, GenericParam};
+
+use crate::attrs;
+
+pub fn derive_describe_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    let tokens = match describe(&mut input) {
+        Ok(t) => t,
+        Err(e) => e.into_compile_error(),
+    };
+    proc_macro::TokenStream::from(tokens)
+}
+
+fn describe(input: &mut DeriveInput) -> syn::Result<TokenStream> {
+    let container_attrs = attrs::Container::from_attrs(input.attrs.iter())
+        .map_err(|e| syn::Error::new(input.span(), e.to_string()))?
+        .unwrap_or_default();
+    if let Some(rule) = container_attrs.rename_all() {
+        attrs::apply_rename_all(&mut input.data, rule);
+    }
+
+    let mut generics = input.generics.clone();
+    for param in &mut generics.params {
+        if let GenericParam::Type(ref mut type_param) = *param {
+            if crate::used_outside_phantom_data(&input.data, &type_param.ident) {
+                type_param
+                    .bounds
+                    .push(parse_quote!(::autosurgeon::schema::DescribeSchema));
+            }
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let name = &input.ident;
+
+    let schema =
+        if container_attrs.reconcile_with().is_some() || container_attrs.hydrate_with().is_some() {
+            quote!(::autosurgeon::schema::Schema::Any)
+        } else {
+            match &input.data {
+                syn::Data::Struct(s) if container_attrs.transparent() => match s.fields.len() {
+                    1 => field_schema(s.fields.iter().next().unwrap())?,
+                    _ => {
+                        return Err(syn::Error::new(
+                            input.span(),
+                            "transparent structs must have exactly one field",
+                        ))
+                    }
+                },
+                syn::Data::Struct(s) => struct_schema(input, &s.fields)?,
+                syn::Data::Enum(_) if container_attrs.int_repr() => quote! {
+                    ::autosurgeon::schema::Schema::Scalar(::autosurgeon::schema::ScalarKind::Int)
+                },
+                syn::Data::Enum(e) => enum_schema(e, container_attrs.repr())?,
+                syn::Data::Union(_) => {
+                    return Err(syn::Error::new(
+                        input.span(),
+                        "cannot derive DescribeSchema for unions",
+                    ))
+                }
+            }
+        };
+
+    Ok(quote! {
+        impl #impl_generics ::autosurgeon::schema::DescribeSchema for #name #ty_generics #where_clause {
+            fn schema() -> ::autosurgeon::schema::Schema {
+                #schema
+            }
+        }
+    })
+}
+
+fn struct_schema(input: &DeriveInput, fields: &Fields) -> syn::Result<TokenStream> {
+    match fields {
+        Fields::Named(fields) => named_schema(fields),
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => field_schema(&fields.unnamed[0]),
+        Fields::Unnamed(fields) => tuple_schema(fields),
+        Fields::Unit => Err(syn::Error::new(
+            input.span(),
+            "cannot derive DescribeSchema for unit structs",
+        )),
+    }
+}
+
+fn enum_schema(data: &syn::DataEnum, repr: &attrs::EnumRepr) -> syn::Result<TokenStream> {
+    let mut options = Vec::with_capacity(data.variants.len());
+    for v in &data.variants {
+        let variant_attrs = attrs::Variant::from_variant(v)?.unwrap_or_default();
+        let discriminant = variant_attrs.discriminant(&v.ident);
+        // Anything which isn't one of the other variants hydrates as the `other` variant
+        if variant_attrs.other() {
+            options.push(match &v.fields {
+                Fields::Unit => quote!(::autosurgeon::schema::Schema::Any),
+                _ => fields_schema(&v.fields)?,
+            });
+            continue;
+        }
+        let option = match (repr, &v.fields) {
+            (attrs::EnumRepr::External, Fields::Unit) => quote! {
+                ::autosurgeon::schema::Schema::Literal(
+                    ::std::string::String::from(#discriminant),
+                )
+            },
+            (attrs::EnumRepr::External, fields) => {
+                let inner = fields_schema(fields)?;
+                quote! {
+                    ::autosurgeon::schema::Schema::Struct(::std::vec![
+                        ::autosurgeon::schema::Field::new(#discriminant, #inner),
+                    ])
+                }
+            }
+            (attrs::EnumRepr::Internal { tag }, Fields::Unit) => quote! {
+                ::autosurgeon::schema::Schema::tagged(
+                    #tag,
+                    #discriminant,
+                    ::autosurgeon::schema::Schema::Struct(::std::vec::Vec::new()),
+                )
+            },
+            (attrs::EnumRepr::Internal { tag }, fields) => {
+                let inner = fields_schema(fields)?;
+                quote!(::autosurgeon::schema::Schema::tagged(#tag, #discriminant, #inner))
+            }
+            (attrs::EnumRepr::Adjacent { tag, .. }, Fields::Unit) => quote! {
+                ::autosurgeon::schema::Schema::tagged(
+                    #tag,
+                    #discriminant,
+                    ::autosurgeon::schema::Schema::Struct(::std::vec::Vec::new()),
+                )
+            },
+            (attrs::EnumRepr::Adjacent { tag, content }, fields) => {
+                let inner = fields_schema(fields)?;
+                quote! {
+                    ::autosurgeon::schema::Schema::tagged(
+                        #tag,
+                        #discriminant,
+                        ::autosurgeon::schema::Schema::Struct(::std::vec![
+                            ::autosurgeon::schema::Field::new(#content, #inner),
+                        ]),
+                    )
+                }
+            }
+        };
+        options.push(option);
+    }
+    Ok(quote!(::autosurgeon::schema::Schema::OneOf(
+        ::std::vec![#(#options),*]
+    )))
+}
+
+/// The schema of the fields of an enum variant
+fn fields_schema(fields: &Fields) -> syn::Result<TokenStream> {
+    match fields {
+        Fields::Named(fields) => named_schema(fields),
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            let field = &fields.unnamed[0];
+            // Enum newtype fields only allow `reconcile_with` and `hydrate_with`
+            if field.attrs.iter().any(|a| a.path().is_ident("autosurgeon")) {
+                Ok(quote!(::autosurgeon::schema::Schema::Any))
+            } else {
+                let ty = &field.ty;
+                Ok(quote_spanned! {field.span()=>
+                    <#ty as ::autosurgeon::schema::DescribeSchema>::schema()
+                })
+            }
+        }
+        Fields::Unnamed(fields) => tuple_schema(fields),
+        Fields::Unit => Ok(quote!(::autosurgeon::schema::Schema::Struct(
+            ::std::vec::Vec::new()
+        ))),
+    }
+}
+
+fn named_schema(fields: &syn::FieldsNamed) -> syn::Result<TokenStream> {
+    let mut described = Vec::with_capacity(fields.named.len());
+    for field in &fields.named {
+        if crate::is_phantom_data(&field.ty) || crate::is_dirty(&field.ty) {
+            continue;
+        }
+        let attrs = attrs::Field::from_field(field)?.unwrap_or_default();
+        let name = match attrs.rename() {
+            Some(name) => name.to_string(),
+            None => field
+                .ident
+                .as_ref()
+                .unwrap()
+                .to_string()
+                .trim_start_matches("r#")
+                .to_string(),
+        };
+        let schema = field_schema(field)?;
+        let optional = attrs.missing().map(|_| quote!(.optional()));
+        described.push(quote!(::autosurgeon::schema::Field::new(#name, #schema)#optional));
+    }
+    Ok(quote!(::autosurgeon::schema::Schema::Struct(
+        ::std::vec![#(#described),*]
+    )))
+}
+
+fn tuple_schema(fields: &syn::FieldsUnnamed) -> syn::Result<TokenStream> {
+    let elems = fields
+        .unnamed
+        .iter()
+        .filter(|f| !crate::is_phantom_data(&f.ty))
+        .map(field_schema)
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(quote!(::autosurgeon::schema::Schema::Tuple(
+        ::std::vec![#(#elems),*]
+    )))
+}
+
+/// The schema of the value of a struct field, which is `Any` if the field is reconciled or
+/// hydrated by custom code
+fn field_schema(field: &syn::Field) -> syn::Result<TokenStream> {
+    let attrs = attrs::Field::from_field(field)?.unwrap_or_default();
+    if attrs.reconcile_with().is_some() || attrs.hydrate_with().is_some() {
+        return Ok(quote!(::autosurgeon::schema::Schema::Any));
+    }
+    let ty = &field.ty;
+    Ok(quote_spanned! {field.span()=>
+        <#ty as ::autosurgeon::schema::DescribeSchema>::schema()
+    })
+}
@@ -0,0 +1,57 @@
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+
+// `Store` itself is neither `Reconcile` nor `Hydrate`, only its item type is
+trait Store {
+    type Item;
+}
+
+#[derive(Debug, PartialEq)]
+struct Names;
+
+impl Store for Names {
+    type Item = String;
+}
+
+#[derive(Debug, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(bound = "S::Item: Reconcile + Hydrate")]
+struct Inventory<S: Store> {
+    items: Vec<S::Item>,
+}
+
+#[test]
+fn bound_replaces_generated_bounds() {
+    let inventory = Inventory::<Names> {
+        items: vec!["hammer".to_string(), "saw".to_string()],
+    };
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &inventory).unwrap();
+    let hydrated: Inventory<Names> = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, inventory);
+}
+
+#[derive(Debug, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(bound(reconcile = "S::Item: Reconcile", hydrate = "S::Item: Hydrate"))]
+enum Slot<S: Store> {
+    Empty,
+    Full { item: S::Item },
+}
+
+#[derive(Debug, PartialEq, Reconcile, Hydrate)]
+struct Slots {
+    first: Slot<Names>,
+    second: Slot<Names>,
+}
+
+#[test]
+fn separate_reconcile_and_hydrate_bounds() {
+    let slots = Slots {
+        first: Slot::Empty,
+        second: Slot::Full {
+            item: "hammer".to_string(),
+        },
+    };
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &slots).unwrap();
+    let hydrated: Slots = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, slots);
+}
@@ -0,0 +1,156 @@
+use automerge::ReadDoc;
+use autosurgeon::{
+    bytes::ByteArray, hydrate_prop, reconcile::LoadKey, reconcile_prop, Hydrate, Reconcile,
+};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Reconcile, Hydrate)]
+struct User {
+    #[key]
+    id: Uuid,
+    name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Reconcile, Hydrate)]
+struct Blob {
+    #[key]
+    #[autosurgeon(with = "autosurgeon::bytes::array")]
+    hash: [u8; 16],
+    size: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Reconcile, Hydrate)]
+struct Chunk {
+    #[key]
+    hash: ByteArray<4>,
+    offset: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Reconcile, Hydrate)]
+struct Keyed<K> {
+    #[key]
+    id: K,
+    value: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Reconcile, Hydrate)]
+enum Node<K, V> {
+    Leaf {
+        #[key]
+        id: K,
+        value: V,
+    },
+    Branch(Vec<V>),
+}
+
+/// Check that the key hydrated from each element of the list at `prop` matches the key of the
+/// corresponding element of `items`
+fn assert_keys_hydrate<T: Reconcile>(doc: &automerge::AutoCommit, prop: &str, items: &[T]) {
+    let (_, list) = doc.get(&automerge::ROOT, prop).unwrap().unwrap();
+    for (index, item) in items.iter().enumerate() {
+        match (
+            T::hydrate_key(doc, &list, index.into()).unwrap(),
+            item.key(),
+        ) {
+            (LoadKey::Found(hydrated), LoadKey::Found(key)) => assert!(hydrated == key),
+            _ => panic!("expected both keys to be found"),
+        }
+    }
+}
+
+#[test]
+fn uuid_keys() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut users = vec![
+        User {
+            id: Uuid::new_v4(),
+            name: "alice".to_string(),
+        },
+        User {
+            id: Uuid::new_v4(),
+            name: "bob".to_string(),
+        },
+    ];
+    reconcile_prop(&mut doc, automerge::ROOT, "users", &users).unwrap();
+    assert_keys_hydrate(&doc, "users", &users);
+
+    // Elements are matched by key, so swapping them keeps bob's object rather than updating both
+    // elements in place
+    let (_, list) = doc.get(&automerge::ROOT, "users").unwrap().unwrap();
+    let (_, bob) = doc.get(&list, 1).unwrap().unwrap();
+    users.swap(0, 1);
+    reconcile_prop(&mut doc, automerge::ROOT, "users", &users).unwrap();
+    let (_, first) = doc.get(&list, 0).unwrap().unwrap();
+    assert_eq!(first, bob);
+
+    let hydrated: Vec<User> = hydrate_prop(&doc, &automerge::ROOT, "users").unwrap();
+    assert_eq!(hydrated, users);
+}
+
+#[test]
+fn byte_array_keys() {
+    let mut doc = automerge::AutoCommit::new();
+    let blobs = vec![
+        Blob {
+            hash: [1; 16],
+            size: 10,
+        },
+        Blob {
+            hash: [2; 16],
+            size: 20,
+        },
+    ];
+    reconcile_prop(&mut doc, automerge::ROOT, "blobs", &blobs).unwrap();
+    assert_keys_hydrate(&doc, "blobs", &blobs);
+    let hydrated: Vec<Blob> = hydrate_prop(&doc, &automerge::ROOT, "blobs").unwrap();
+    assert_eq!(hydrated, blobs);
+
+    let chunks = vec![
+        Chunk {
+            hash: [1, 2, 3, 4].into(),
+            offset: 0,
+        },
+        Chunk {
+            hash: [5, 6, 7, 8].into(),
+            offset: 4,
+        },
+    ];
+    reconcile_prop(&mut doc, automerge::ROOT, "chunks", &chunks).unwrap();
+    assert_keys_hydrate(&doc, "chunks", &chunks);
+}
+
+#[test]
+fn generic_keys() {
+    let mut doc = automerge::AutoCommit::new();
+    let items = vec![
+        Keyed {
+            id: Uuid::new_v4(),
+            value: "one".to_string(),
+        },
+        Keyed {
+            id: Uuid::new_v4(),
+            value: "two".to_string(),
+        },
+    ];
+    reconcile_prop(&mut doc, automerge::ROOT, "items", &items).unwrap();
+    assert_keys_hydrate(&doc, "items", &items);
+
+    let nodes: Vec<Node<Uuid, String>> = vec![
+        Node::Leaf {
+            id: Uuid::new_v4(),
+            value: "leaf".to_string(),
+        },
+        Node::Branch(vec!["a".to_string(), "b".to_string()]),
+    ];
+    reconcile_prop(&mut doc, automerge::ROOT, "nodes", &nodes).unwrap();
+    let (_, list) = doc.get(&automerge::ROOT, "nodes").unwrap().unwrap();
+    match (
+        Node::<Uuid, String>::hydrate_key(&doc, &list, 0_usize.into()).unwrap(),
+        nodes[0].key(),
+    ) {
+        (LoadKey::Found(hydrated), LoadKey::Found(key)) => assert!(hydrated == key),
+        _ => panic!("expected both keys to be found"),
+    }
+    let hydrated: Vec<Node<Uuid, String>> = hydrate_prop(&doc, &automerge::ROOT, "nodes").unwrap();
+    assert_eq!(hydrated, nodes);
+}
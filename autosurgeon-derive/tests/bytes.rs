@@ -0,0 +1,48 @@
+use automerge::ScalarValue::{self, Bytes};
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+
+#[derive(Debug, PartialEq, Reconcile, Hydrate)]
+struct Rows {
+    #[autosurgeon(bytes)]
+    rows: Vec<Vec<u8>>,
+    #[autosurgeon(bytes)]
+    header: Vec<u8>,
+    #[autosurgeon(bytes)]
+    checksum: Option<[u8; 2]>,
+}
+
+#[test]
+fn bytes_round_trip() {
+    let mut rows = Rows {
+        rows: vec![vec![1, 2], vec![3], vec![4, 5]],
+        header: vec![0],
+        checksum: None,
+    };
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &rows).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "rows" => { list! { { Bytes(vec![1, 2]) }, { Bytes(vec![3]) }, { Bytes(vec![4, 5]) } } },
+            "header" => { Bytes(vec![0]) },
+            "checksum" => { ScalarValue::Null },
+        }
+    );
+
+    rows.rows.remove(0);
+    rows.rows.push(vec![6]);
+    rows.checksum = Some([7, 8]);
+    reconcile(&mut doc, &rows).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "rows" => { list! { { Bytes(vec![3]) }, { Bytes(vec![4, 5]) }, { Bytes(vec![6]) } } },
+            "header" => { Bytes(vec![0]) },
+            "checksum" => { Bytes(vec![7, 8]) },
+        }
+    );
+
+    let hydrated: Rows = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, rows);
+}
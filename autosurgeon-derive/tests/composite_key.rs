@@ -0,0 +1,112 @@
+use std::borrow::Cow;
+
+use automerge::ReadDoc;
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{reconcile::LoadKey, reconcile_prop, Reconcile};
+
+#[derive(Clone, Debug, PartialEq, Reconcile)]
+struct Item {
+    #[key]
+    tenant_id: String,
+    #[autosurgeon(key)]
+    item_id: u64,
+    name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Reconcile)]
+struct Pair(#[autosurgeon(key)] String, String, #[key] u64);
+
+#[derive(Clone, Debug, PartialEq, Reconcile)]
+enum Entry {
+    Named {
+        #[key]
+        tenant_id: String,
+        #[key]
+        item_id: u64,
+        name: String,
+    },
+    Tuple(String, #[key] u64, #[key] String),
+}
+
+fn item(tenant_id: &str, item_id: u64, name: &str) -> Item {
+    Item {
+        tenant_id: tenant_id.to_string(),
+        item_id,
+        name: name.to_string(),
+    }
+}
+
+#[test]
+fn composite_key_is_a_tuple_of_the_key_fields() {
+    let item = item("acme", 1, "widget");
+    assert!(matches!(
+        item.key(),
+        LoadKey::Found((Cow::Borrowed(tenant), Cow::Borrowed(1))) if tenant == "acme"
+    ));
+
+    let pair = Pair("a".to_string(), "b".to_string(), 3);
+    assert!(matches!(
+        pair.key(),
+        LoadKey::Found((Cow::Borrowed(a), Cow::Borrowed(3))) if a == "a"
+    ));
+}
+
+#[test]
+fn composite_key_is_hydrated_from_the_document() {
+    let mut doc = automerge::AutoCommit::new();
+    let entries = vec![
+        Entry::Named {
+            tenant_id: "acme".to_string(),
+            item_id: 1,
+            name: "widget".to_string(),
+        },
+        Entry::Tuple("ignored".to_string(), 2, "initech".to_string()),
+    ];
+    reconcile_prop(&mut doc, automerge::ROOT, "entries", &entries).unwrap();
+
+    let (_, list) = doc.get(&automerge::ROOT, "entries").unwrap().unwrap();
+    for (index, entry) in entries.iter().enumerate() {
+        let hydrated = Entry::hydrate_key(&doc, &list, index.into()).unwrap();
+        match (hydrated, entry.key()) {
+            (LoadKey::Found(hydrated), LoadKey::Found(key)) => assert!(hydrated == key),
+            _ => panic!("expected both keys to be found"),
+        }
+    }
+}
+
+#[test]
+fn items_with_the_same_composite_key_are_merged() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut items = vec![item("acme", 1, "widget"), item("initech", 1, "stapler")];
+    reconcile_prop(&mut doc, automerge::ROOT, "items", &items).unwrap();
+
+    let mut doc2 = doc.fork().with_actor(automerge::ActorId::random());
+    let mut items2 = items.clone();
+    items2.insert(0, item("acme", 2, "gadget"));
+    reconcile_prop(&mut doc2, automerge::ROOT, "items", &items2).unwrap();
+
+    // The removed item has the same `item_id` as the renamed one but a different `tenant_id`
+    items.remove(0);
+    items[0].name = "red stapler".to_string();
+    reconcile_prop(&mut doc, automerge::ROOT, "items", &items).unwrap();
+
+    doc.merge(&mut doc2).unwrap();
+
+    assert_doc!(
+        doc.document(),
+        map! {
+            "items" => { list! {
+                { map! {
+                    "tenant_id" => { "acme" },
+                    "item_id" => { 2_u64 },
+                    "name" => { "gadget" },
+                }},
+                { map! {
+                    "tenant_id" => { "initech" },
+                    "item_id" => { 1_u64 },
+                    "name" => { "red stapler" },
+                }},
+            }}
+        }
+    );
+}
@@ -0,0 +1,40 @@
+use std::borrow::Cow;
+
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+struct Article<'a> {
+    title: Cow<'a, str>,
+    tags: Cow<'a, [String]>,
+}
+
+#[test]
+fn borrowed_fields_round_trip_as_owned() {
+    let tags = vec!["rust".to_string(), "crdt".to_string()];
+    let article = Article {
+        title: Cow::Borrowed("Hello"),
+        tags: Cow::Borrowed(&tags),
+    };
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &article).unwrap();
+
+    let hydrated: Article<'static> = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, article);
+    assert!(matches!(hydrated.title, Cow::Owned(_)));
+    assert!(matches!(hydrated.tags, Cow::Owned(_)));
+}
+
+#[test]
+fn cow_slices_are_reconciled_like_vecs() {
+    #[derive(Debug, PartialEq, Reconcile, Hydrate)]
+    struct Tags {
+        tags: Vec<String>,
+    }
+
+    let mut doc = automerge::AutoCommit::new();
+    let tags = ["a".to_string(), "b".to_string()];
+    autosurgeon::reconcile_prop(&mut doc, automerge::ROOT, "tags", Cow::Borrowed(&tags[..]))
+        .unwrap();
+    let hydrated: Tags = hydrate(&doc).unwrap();
+    assert_eq!(hydrated.tags, tags);
+}
@@ -0,0 +1,70 @@
+use std::marker::PhantomData;
+
+use automerge::transaction::Transactable;
+use autosurgeon::{hydrate, Hydrate, HydrateError};
+
+#[derive(Debug, PartialEq, Hydrate)]
+#[autosurgeon(deny_unknown_fields)]
+struct Settings<T> {
+    theme: String,
+    font: Font,
+    marker: PhantomData<T>,
+}
+
+// Unknown keys in nested structs are only an error if they deny them too
+#[derive(Debug, PartialEq, Hydrate)]
+struct Font {
+    family: String,
+}
+
+fn doc() -> automerge::AutoCommit {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "theme", "dark").unwrap();
+    let font = doc
+        .put_object(automerge::ROOT, "font", automerge::ObjType::Map)
+        .unwrap();
+    doc.put(&font, "family", "serif").unwrap();
+    doc.put(&font, "size", 12).unwrap();
+    doc
+}
+
+#[test]
+fn known_fields_hydrate() {
+    let settings: Settings<u8> = hydrate(&doc()).unwrap();
+    assert_eq!(settings.theme, "dark");
+    assert_eq!(settings.font.family, "serif");
+}
+
+#[test]
+fn unknown_fields_are_listed() {
+    let mut doc = doc();
+    doc.put(automerge::ROOT, "zoom", 2).unwrap();
+    doc.put(automerge::ROOT, "language", "en").unwrap();
+    let err = hydrate::<_, Settings<u8>>(&doc).unwrap_err();
+    match err {
+        HydrateError::UnknownFields(keys) => assert_eq!(keys, vec!["language", "zoom"]),
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_fields_in_nested_structs_include_the_path() {
+    #[derive(Debug, Hydrate)]
+    struct Wrapper {
+        #[allow(dead_code)]
+        settings: Settings<u8>,
+    }
+
+    let mut doc = automerge::AutoCommit::new();
+    let settings = doc
+        .put_object(automerge::ROOT, "settings", automerge::ObjType::Map)
+        .unwrap();
+    doc.put(&settings, "theme", "dark").unwrap();
+    doc.put(&settings, "colour", "red").unwrap();
+    let err = hydrate::<_, Wrapper>(&doc).unwrap_err();
+    assert_eq!(err.path(), &autosurgeon::path!["settings"]);
+    assert!(matches!(
+        err.without_path(),
+        HydrateError::UnknownFields(keys) if keys == ["colour"]
+    ));
+}
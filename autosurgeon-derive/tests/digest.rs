@@ -0,0 +1,138 @@
+use automerge::{transaction::Transactable, ReadDoc};
+use autosurgeon::{
+    reconcile::{reconcile_with_scratch, ReconcileScratch},
+    Hydrate, Reconcile,
+};
+
+#[derive(Debug, Clone, PartialEq, Hash, Reconcile, Hydrate)]
+struct Doc {
+    title: String,
+    settings: Settings,
+    layers: Vec<Layer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, Reconcile, Hydrate)]
+#[autosurgeon(digest)]
+struct Settings {
+    theme: String,
+    zoom: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, Reconcile, Hydrate)]
+#[autosurgeon(digest)]
+enum Layer {
+    Image { url: String },
+    Group(Vec<String>),
+}
+
+fn doc() -> Doc {
+    Doc {
+        title: "drawing".to_string(),
+        settings: Settings {
+            theme: "dark".to_string(),
+            zoom: 100,
+        },
+        layers: vec![
+            Layer::Image {
+                url: "a.png".to_string(),
+            },
+            Layer::Group(vec!["b".to_string()]),
+        ],
+    }
+}
+
+#[test]
+fn derived_digests_are_equal_for_equal_values() {
+    assert_eq!(doc().settings.digest(), doc().settings.digest());
+    assert!(doc().settings.digest().is_some());
+    let mut other = doc().settings;
+    other.zoom = 200;
+    assert_ne!(doc().settings.digest(), other.digest());
+    assert_eq!(doc().digest(), None);
+}
+
+/// A value whose digest ignores `renders`, so that we can tell when reconciling it was skipped
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(digest)]
+struct Viewport {
+    zoom: u32,
+    renders: u32,
+}
+
+impl std::hash::Hash for Viewport {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.zoom.hash(state);
+    }
+}
+
+fn renders(am_doc: &automerge::AutoCommit) -> u32 {
+    autosurgeon::hydrate_prop::<_, Viewport, _, _>(am_doc, automerge::ROOT, "viewport")
+        .unwrap()
+        .renders
+}
+
+#[test]
+fn unchanged_values_are_skipped() {
+    let mut am_doc = automerge::AutoCommit::new();
+    let mut scratch = ReconcileScratch::new();
+    let mut value = std::collections::HashMap::from([(
+        "viewport",
+        Viewport {
+            zoom: 100,
+            renders: 1,
+        },
+    )]);
+    reconcile_with_scratch(&mut am_doc, &value, &mut scratch).unwrap();
+
+    // The digest hasn't changed so the value is skipped
+    value.get_mut("viewport").unwrap().renders = 2;
+    reconcile_with_scratch(&mut am_doc, &value, &mut scratch).unwrap();
+    assert_eq!(renders(&am_doc), 1);
+
+    // Changed values are still reconciled
+    value.get_mut("viewport").unwrap().zoom = 50;
+    reconcile_with_scratch(&mut am_doc, &value, &mut scratch).unwrap();
+    assert_eq!(renders(&am_doc), 2);
+}
+
+#[test]
+fn changes_to_the_document_forget_digests() {
+    let mut am_doc = automerge::AutoCommit::new();
+    let mut scratch = ReconcileScratch::new();
+    let value = doc();
+    reconcile_with_scratch(&mut am_doc, &value, &mut scratch).unwrap();
+
+    // An uncommitted change
+    let (_, settings) = am_doc.get(automerge::ROOT, "settings").unwrap().unwrap();
+    am_doc.put(&settings, "zoom", 50_u64).unwrap();
+    reconcile_with_scratch(&mut am_doc, &value, &mut scratch).unwrap();
+    let hydrated: Doc = autosurgeon::hydrate(&am_doc).unwrap();
+    assert_eq!(hydrated, value);
+
+    // A change merged from another peer
+    am_doc.commit();
+    let mut other = am_doc.fork();
+    other.put(&settings, "theme", "light").unwrap();
+    am_doc.merge(&mut other).unwrap();
+    reconcile_with_scratch(&mut am_doc, &value, &mut scratch).unwrap();
+    let hydrated: Doc = autosurgeon::hydrate(&am_doc).unwrap();
+    assert_eq!(hydrated, value);
+}
+
+#[test]
+fn forgetting_digests_reconciles_everything() {
+    let mut am_doc = automerge::AutoCommit::new();
+    let mut scratch = ReconcileScratch::new();
+    let value = doc();
+    reconcile_with_scratch(&mut am_doc, &value, &mut scratch).unwrap();
+
+    let (_, layers) = am_doc.get(automerge::ROOT, "layers").unwrap().unwrap();
+    let (_, image) = am_doc.get(&layers, 0).unwrap().unwrap();
+    let (_, image) = am_doc.get(&image, "Image").unwrap().unwrap();
+    am_doc.put(&image, "url", "z.png").unwrap();
+
+    scratch.forget_digests();
+    reconcile_with_scratch(&mut am_doc, &value, &mut scratch).unwrap();
+    let hydrated: Doc = autosurgeon::hydrate(&am_doc).unwrap();
+    assert_eq!(hydrated, value);
+}
@@ -0,0 +1,105 @@
+use automerge::transaction::Transactable;
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{reconcile, Reconcile};
+
+#[derive(Reconcile)]
+#[autosurgeon(exhaustive)]
+struct Item {
+    name: String,
+}
+
+#[derive(Reconcile)]
+#[autosurgeon(exhaustive)]
+struct Inventory {
+    owner: String,
+    items: Vec<Item>,
+}
+
+#[test]
+fn exhaustive_struct_deletes_stale_keys() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "owner", "alice").unwrap();
+    doc.put(automerge::ROOT, "removed_field", 1).unwrap();
+
+    reconcile(
+        &mut doc,
+        Inventory {
+            owner: "bob".to_string(),
+            items: Vec::new(),
+        },
+    )
+    .unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "owner" => { "bob" },
+            "items" => { list! {} },
+        }
+    );
+}
+
+#[test]
+fn exhaustive_struct_in_vec() {
+    let mut doc = automerge::AutoCommit::new();
+    let items = doc
+        .put_object(automerge::ROOT, "items", automerge::ObjType::List)
+        .unwrap();
+    for (idx, name) in ["one", "two"].into_iter().enumerate() {
+        let item = doc
+            .insert_object(&items, idx, automerge::ObjType::Map)
+            .unwrap();
+        doc.put(&item, "name", name).unwrap();
+        doc.put(&item, "quantity", 1).unwrap();
+    }
+
+    reconcile(
+        &mut doc,
+        Inventory {
+            owner: "alice".to_string(),
+            items: vec![
+                Item {
+                    name: "one".to_string(),
+                },
+                Item {
+                    name: "two".to_string(),
+                },
+            ],
+        },
+    )
+    .unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "owner" => { "alice" },
+            "items" => { list! {
+                { map! { "name" => { "one" } } },
+                { map! { "name" => { "two" } } },
+            }},
+        }
+    );
+}
+
+#[derive(Reconcile)]
+struct NotExhaustive {
+    name: String,
+}
+
+#[test]
+fn stale_keys_are_kept_by_default() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "removed_field", 1).unwrap();
+    reconcile(
+        &mut doc,
+        NotExhaustive {
+            name: "alice".to_string(),
+        },
+    )
+    .unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "name" => { "alice" },
+            "removed_field" => { 1 },
+        }
+    );
+}
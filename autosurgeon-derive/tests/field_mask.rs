@@ -0,0 +1,69 @@
+use automerge::ReadDoc;
+use autosurgeon::{hydrate, hydrate_prop, reconcile, reconcile_masked, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(field_mask, rename_all = "camelCase")]
+struct User {
+    display_name: String,
+    email: String,
+    tags: Vec<String>,
+}
+
+fn user() -> User {
+    User {
+        display_name: "Ada".to_string(),
+        email: "ada@example.com".to_string(),
+        tags: vec!["admin".to_string()],
+    }
+}
+
+#[test]
+fn only_masked_fields_are_written() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, user()).unwrap();
+
+    let update = User {
+        display_name: "Ada Lovelace".to_string(),
+        email: String::new(),
+        tags: Vec::new(),
+    };
+    reconcile_masked(&mut doc, &update, &UserFieldMask::DISPLAY_NAME).unwrap();
+
+    let hydrated: User = hydrate(&doc).unwrap();
+    assert_eq!(
+        hydrated,
+        User {
+            display_name: "Ada Lovelace".to_string(),
+            ..user()
+        }
+    );
+}
+
+#[test]
+fn masked_fields_missing_from_the_document_are_created() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut mask = UserFieldMask::empty();
+    mask |= UserFieldMask::EMAIL;
+    mask.insert(UserFieldMask::TAGS);
+    reconcile_masked(&mut doc, &user(), &mask).unwrap();
+
+    let mut keys = doc.keys(automerge::ROOT).collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, vec!["email", "tags"]);
+    let tags: Vec<String> = hydrate_prop(&doc, automerge::ROOT, "tags").unwrap();
+    assert_eq!(tags, vec!["admin"]);
+}
+
+#[test]
+fn masks_are_sets_of_fields() {
+    let mask = UserFieldMask::from_name("displayName").unwrap() | UserFieldMask::TAGS;
+    assert!(mask.contains(UserFieldMask::DISPLAY_NAME));
+    assert!(!mask.contains(UserFieldMask::EMAIL));
+    assert!(UserFieldMask::all().contains(mask));
+    assert_eq!(UserFieldMask::from_name("display_name"), None);
+
+    let mut mask = UserFieldMask::all();
+    mask.remove(UserFieldMask::EMAIL | UserFieldMask::TAGS);
+    assert_eq!(mask, UserFieldMask::DISPLAY_NAME);
+    assert_eq!(UserFieldMask::default(), UserFieldMask::empty());
+}
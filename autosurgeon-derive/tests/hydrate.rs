@@ -40,6 +40,21 @@ fn hydrate_struct() {
     );
 }
 
+#[test]
+fn hydrate_error_includes_field_path() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "name", "Microsoft").unwrap();
+    let emps = doc
+        .put_object(automerge::ROOT, "employees", ObjType::List)
+        .unwrap();
+    let satya = doc.insert_object(&emps, 0, ObjType::Map).unwrap();
+    doc.put(&satya, "name", "Satya Nadella").unwrap();
+    doc.put(&satya, "number", "one").unwrap();
+
+    let err = hydrate::<_, Company>(&doc).unwrap_err();
+    assert_eq!(err.to_string(), "at employees[0].number: unexpected string");
+}
+
 #[derive(Debug, Hydrate, PartialEq)]
 struct SpecialString(String);
 
@@ -52,6 +67,7 @@ fn hydrate_newtype_struct() {
 }
 
 // Just here to check that generics are propagated correctly
+#[allow(dead_code)]
 #[derive(Hydrate)]
 struct Wrapped<T>(T);
 
@@ -131,3 +147,49 @@ fn hydrate_multi_value_tuple_enum_variant() {
     let vec: Vector = hydrate(&doc).unwrap();
     assert_eq!(vec, Vector::ThreeD(1.2, 3.4, 5.6));
 }
+
+#[derive(Debug, Hydrate, PartialEq)]
+enum Shape {
+    Circle { radius: f64 },
+    Square(f64),
+    Polygon(f64, f64, f64),
+}
+
+#[test]
+fn hydrate_enum_variant_among_other_keys() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(&automerge::ROOT, "Aardvark", "not a variant")
+        .unwrap();
+    // A key with a variant's name but the wrong shape is skipped
+    doc.put(&automerge::ROOT, "Circle", 1.0).unwrap();
+    doc.put(&automerge::ROOT, "Square", 2.5).unwrap();
+    let shape: Shape = hydrate(&doc).unwrap();
+    assert_eq!(shape, Shape::Square(2.5));
+}
+
+#[test]
+fn hydrate_named_field_enum_from_table() {
+    // Automerge refuses to write into table objects, so write the variant as a map and then
+    // rewrite the change which created it to create a table instead
+    let mut doc = automerge::AutoCommit::new();
+    let latlng = doc
+        .put_object(&automerge::ROOT, "LatLng", ObjType::Map)
+        .unwrap();
+    doc.put(&latlng, "lat", 1.2).unwrap();
+    doc.put(&latlng, "lng", 2.3).unwrap();
+    let mut change = automerge::ExpandedChange::from(doc.get_changes(&[])[0]);
+
+    let mut table_doc = automerge::AutoCommit::new();
+    table_doc
+        .put_object(&automerge::ROOT, "LatLng", ObjType::Table)
+        .unwrap();
+    let make_table = automerge::ExpandedChange::from(table_doc.get_changes(&[])[0]);
+    change.operations[0].action = make_table.operations[0].action.clone();
+    change.hash = None;
+
+    let mut doc = automerge::AutoCommit::new();
+    doc.apply_changes([automerge::Change::from(change)])
+        .unwrap();
+    let coordinate: Coordinate = hydrate(&doc).unwrap();
+    assert_eq!(coordinate, Coordinate::LatLng { lat: 1.2, lng: 2.3 });
+}
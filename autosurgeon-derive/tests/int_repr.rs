@@ -0,0 +1,82 @@
+use automerge::{transaction::Transactable, ReadDoc, ScalarValue, Value};
+use autosurgeon::{hydrate, hydrate_prop, reconcile, Hydrate, HydrateError, Reconcile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(repr = "int")]
+enum Priority {
+    Low,
+    Medium = 5,
+    High,
+    Negative = -1,
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+struct Board {
+    priorities: Vec<Priority>,
+}
+
+#[test]
+fn variants_are_stored_as_their_discriminants() {
+    let mut doc = automerge::AutoCommit::new();
+    let board = Board {
+        priorities: vec![
+            Priority::Low,
+            Priority::Medium,
+            Priority::High,
+            Priority::Negative,
+        ],
+    };
+    reconcile(&mut doc, &board).unwrap();
+
+    let (_, list) = doc.get(automerge::ROOT, "priorities").unwrap().unwrap();
+    let values = (0..doc.length(&list))
+        .map(|i| match doc.get(&list, i).unwrap().unwrap().0 {
+            Value::Scalar(s) => s.into_owned(),
+            other => panic!("unexpected value {:?}", other),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec![
+            ScalarValue::Int(0),
+            ScalarValue::Int(5),
+            ScalarValue::Int(6),
+            ScalarValue::Int(-1)
+        ]
+    );
+
+    let hydrated: Board = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, board);
+}
+
+#[test]
+fn uints_hydrate_too() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "priority", ScalarValue::Uint(6))
+        .unwrap();
+    let priority: Priority = hydrate_prop(&doc, automerge::ROOT, "priority").unwrap();
+    assert_eq!(priority, Priority::High);
+}
+
+#[test]
+fn unknown_discriminants() {
+    #[derive(Debug, PartialEq, Reconcile, Hydrate)]
+    #[autosurgeon(repr = "int")]
+    enum Shape {
+        Circle = 1,
+        #[autosurgeon(other)]
+        Unknown,
+    }
+
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "priority", 3).unwrap();
+    doc.put(automerge::ROOT, "shape", 3).unwrap();
+
+    let err = hydrate_prop::<_, Priority, _, _>(&doc, automerge::ROOT, "priority").unwrap_err();
+    assert!(matches!(err.without_path(), HydrateError::Unexpected(_)));
+    let shape: Shape = hydrate_prop(&doc, automerge::ROOT, "shape").unwrap();
+    assert_eq!(shape, Shape::Unknown);
+    doc.put(automerge::ROOT, "shape", 1).unwrap();
+    let shape: Shape = hydrate_prop(&doc, automerge::ROOT, "shape").unwrap();
+    assert_eq!(shape, Shape::Circle);
+}
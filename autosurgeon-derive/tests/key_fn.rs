@@ -0,0 +1,150 @@
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{
+    reconcile::{ComputedKey, LoadKey},
+    reconcile_prop, Hydrate, Prop, ReadDoc, Reconcile, ReconcileError,
+};
+
+#[derive(Clone, Debug, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(key_fn = "normalized_email")]
+struct Contact {
+    email: String,
+    name: String,
+}
+
+fn normalized_email(contact: &Contact) -> String {
+    contact.email.to_lowercase()
+}
+
+#[derive(Clone, Debug, PartialEq, Reconcile)]
+#[autosurgeon(key_fn = "file_key", hydrate_key_fn = "hydrate_file_key")]
+struct File {
+    dir: String,
+    name: String,
+    size: u64,
+}
+
+fn file_key(file: &File) -> (String, String) {
+    (file.dir.clone(), file.name.clone())
+}
+
+fn hydrate_file_key<D: ReadDoc>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: Prop<'_>,
+) -> Result<LoadKey<(String, String)>, ReconcileError> {
+    let dir = autosurgeon::hydrate_key(doc, obj, prop.clone(), "dir".into())?;
+    let name = autosurgeon::hydrate_key(doc, obj, prop, "name".into())?;
+    Ok(match (dir, name) {
+        (LoadKey::Found(dir), LoadKey::Found(name)) => LoadKey::Found((dir, name)),
+        _ => LoadKey::KeyNotFound,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(key_fn = "shape_key")]
+enum Shape {
+    Circle { id: u64, radius: u64 },
+    Square { id: u64, side: u64 },
+}
+
+fn shape_key(shape: &Shape) -> u64 {
+    match shape {
+        Shape::Circle { id, .. } | Shape::Square { id, .. } => *id,
+    }
+}
+
+fn contact(email: &str, name: &str) -> Contact {
+    Contact {
+        email: email.to_string(),
+        name: name.to_string(),
+    }
+}
+
+fn found(key: LoadKey<ComputedKey>) -> ComputedKey {
+    match key {
+        LoadKey::Found(k) => k,
+        _ => panic!("expected the key to be found"),
+    }
+}
+
+#[test]
+fn key_is_computed_by_the_function() {
+    let lower = contact("alice@example.com", "Alice");
+    let upper = contact("ALICE@example.com", "Alice");
+    let other = contact("bob@example.com", "Bob");
+    assert_eq!(found(lower.key()), found(upper.key()));
+    assert_ne!(found(lower.key()), found(other.key()));
+}
+
+#[test]
+fn key_is_hydrated_from_the_document() {
+    let mut doc = automerge::AutoCommit::new();
+    let files = vec![File {
+        dir: "/tmp".to_string(),
+        name: "notes.txt".to_string(),
+        size: 3,
+    }];
+    let contacts = vec![contact("Alice@example.com", "Alice")];
+    reconcile_prop(&mut doc, automerge::ROOT, "files", &files).unwrap();
+    reconcile_prop(&mut doc, automerge::ROOT, "contacts", &contacts).unwrap();
+
+    let (_, list) = doc.get(&automerge::ROOT, "files").unwrap().unwrap();
+    let hydrated = found(File::hydrate_key(&doc, &list, 0_usize.into()).unwrap());
+    assert_eq!(hydrated, found(files[0].key()));
+
+    let (_, list) = doc.get(&automerge::ROOT, "contacts").unwrap().unwrap();
+    let hydrated = found(Contact::hydrate_key(&doc, &list, 0_usize.into()).unwrap());
+    assert_eq!(hydrated, found(contact("alice@EXAMPLE.com", "Alice").key()));
+
+    assert!(matches!(
+        Contact::hydrate_key(&doc, &list, 1_usize.into()).unwrap(),
+        LoadKey::KeyNotFound
+    ));
+}
+
+#[test]
+fn items_with_the_same_computed_key_are_merged() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut contacts = vec![contact("alice@example.com", "Alice")];
+    reconcile_prop(&mut doc, automerge::ROOT, "contacts", &contacts).unwrap();
+
+    let mut doc2 = doc.fork().with_actor(automerge::ActorId::random());
+    let mut contacts2 = contacts.clone();
+    contacts2.insert(0, contact("bob@example.com", "Bob"));
+    reconcile_prop(&mut doc2, automerge::ROOT, "contacts", &contacts2).unwrap();
+
+    // Same key after normalization, so this updates the existing contact
+    contacts[0].email = "ALICE@example.com".to_string();
+    reconcile_prop(&mut doc, automerge::ROOT, "contacts", &contacts).unwrap();
+
+    doc.merge(&mut doc2).unwrap();
+
+    assert_doc!(
+        doc.document(),
+        map! {
+            "contacts" => { list! {
+                { map! {
+                    "email" => { "bob@example.com" },
+                    "name" => { "Bob" },
+                }},
+                { map! {
+                    "email" => { "ALICE@example.com" },
+                    "name" => { "Alice" },
+                }},
+            }}
+        }
+    );
+}
+
+#[test]
+fn enum_key_is_computed_across_variants() {
+    let circle = Shape::Circle { id: 1, radius: 2 };
+    let square = Shape::Square { id: 1, side: 2 };
+    assert_eq!(found(circle.key()), found(square.key()));
+
+    let mut doc = automerge::AutoCommit::new();
+    reconcile_prop(&mut doc, automerge::ROOT, "shapes", vec![square.clone()]).unwrap();
+    let (_, list) = doc.get(&automerge::ROOT, "shapes").unwrap().unwrap();
+    let hydrated = found(Shape::hydrate_key(&doc, &list, 0_usize.into()).unwrap());
+    assert_eq!(hydrated, found(circle.key()));
+}
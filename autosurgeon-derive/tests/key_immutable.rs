@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{reconcile_prop, Reconcile, ReconcileError};
+
+#[derive(Debug, Clone, PartialEq, Reconcile)]
+#[autosurgeon(key_immutable)]
+struct Product {
+    #[key]
+    id: u64,
+    name: String,
+}
+
+fn product(id: u64, name: &str) -> Product {
+    Product {
+        id,
+        name: name.to_string(),
+    }
+}
+
+#[test]
+fn changing_key_of_map_entry_fails() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut products = HashMap::new();
+    products.insert("a".to_string(), product(1, "Lawnmower"));
+    reconcile_prop(&mut doc, automerge::ROOT, "products", &products).unwrap();
+
+    products.get_mut("a").unwrap().name = "Mower".to_string();
+    reconcile_prop(&mut doc, automerge::ROOT, "products", &products).unwrap();
+
+    products.get_mut("a").unwrap().id = 2;
+    let result = reconcile_prop(&mut doc, automerge::ROOT, "products", &products);
+    assert!(matches!(
+        result,
+        Err(ReconcileError::ImmutableKeyChanged(e)) if e.prop == "a".into()
+    ));
+}
+
+#[test]
+fn changing_key_of_seq_element_fails() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut products = vec![product(1, "Lawnmower"), product(2, "Strimmer")];
+    reconcile_prop(&mut doc, automerge::ROOT, "products", &products).unwrap();
+
+    products[1].id = 3;
+    let result = reconcile_prop(&mut doc, automerge::ROOT, "products", &products);
+    assert!(matches!(
+        result,
+        Err(ReconcileError::ImmutableKeyChanged(e)) if e.prop == 1_usize.into()
+    ));
+}
+
+#[test]
+fn inserting_and_removing_seq_elements_is_allowed() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut products = vec![product(1, "Lawnmower"), product(2, "Strimmer")];
+    reconcile_prop(&mut doc, automerge::ROOT, "products", &products).unwrap();
+
+    products.remove(0);
+    products.push(product(3, "Leafblower"));
+    reconcile_prop(&mut doc, automerge::ROOT, "products", &products).unwrap();
+    products.insert(0, product(4, "Hedge trimmer"));
+    reconcile_prop(&mut doc, automerge::ROOT, "products", &products).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "products" => { list! {
+                { map! { "id" => { 4_u64 }, "name" => { "Hedge trimmer" } }},
+                { map! { "id" => { 2_u64 }, "name" => { "Strimmer" } }},
+                { map! { "id" => { 3_u64 }, "name" => { "Leafblower" } }},
+            }}
+        }
+    );
+}
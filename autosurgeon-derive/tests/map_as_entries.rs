@@ -0,0 +1,37 @@
+use std::collections::{BTreeMap, HashMap};
+
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+
+#[derive(Debug, PartialEq, Reconcile, Hydrate)]
+struct Leaderboard {
+    #[autosurgeon(map_as_entries)]
+    scores: HashMap<String, u64>,
+    #[autosurgeon(map_as_entries)]
+    rounds: BTreeMap<u32, String>,
+}
+
+#[test]
+fn map_as_entries_round_trip() {
+    let board = Leaderboard {
+        scores: HashMap::from([("bob".to_string(), 2), ("alice".to_string(), 1)]),
+        rounds: BTreeMap::from([(2, "final".to_string()), (1, "heats".to_string())]),
+    };
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &board).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "scores" => { list! {
+                { map! { "key" => { "alice" }, "value" => { 1_u64 } } },
+                { map! { "key" => { "bob" }, "value" => { 2_u64 } } },
+            }},
+            "rounds" => { list! {
+                { map! { "key" => { "1" }, "value" => { "heats" } } },
+                { map! { "key" => { "2" }, "value" => { "final" } } },
+            }},
+        }
+    );
+    let hydrated: Leaderboard = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, board);
+}
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use automerge::ActorId;
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+
+#[derive(Debug, Default, PartialEq, Reconcile, Hydrate)]
+struct Room {
+    #[autosurgeon(map_strategy = "keep_absent")]
+    presence: HashMap<String, u64>,
+    #[autosurgeon(map_strategy = "delete_absent")]
+    scores: HashMap<String, u64>,
+}
+
+#[test]
+fn keep_absent_keeps_concurrently_inserted_keys() {
+    let mut doc1 = automerge::AutoCommit::new().with_actor(ActorId::from(vec![1]));
+    reconcile(&mut doc1, Room::default()).unwrap();
+    let mut doc2 = doc1.fork().with_actor(ActorId::from(vec![2]));
+
+    let mut room1: Room = hydrate(&doc1).unwrap();
+    room1.presence.insert("alice".to_string(), 1);
+    room1.scores.insert("alice".to_string(), 1);
+    reconcile(&mut doc1, &room1).unwrap();
+
+    let mut room2: Room = hydrate(&doc2).unwrap();
+    room2.presence.insert("bob".to_string(), 2);
+    room2.scores.insert("bob".to_string(), 2);
+    reconcile(&mut doc2, &room2).unwrap();
+
+    // Reconcile the stale state after merging, as a peer which hasn't rehydrated yet would
+    doc1.merge(&mut doc2).unwrap();
+    reconcile(&mut doc1, &room1).unwrap();
+
+    let room: Room = hydrate(&doc1).unwrap();
+    assert_eq!(
+        room.presence,
+        HashMap::from([("alice".to_string(), 1), ("bob".to_string(), 2)])
+    );
+    assert_eq!(room.scores, HashMap::from([("alice".to_string(), 1)]));
+}
@@ -64,3 +64,56 @@ fn hydrate_missing_on_named_field() {
         }
     );
 }
+
+#[derive(Debug, PartialEq, Hydrate)]
+enum Shape {
+    Circle {
+        radius: u64,
+        #[autosurgeon(missing = "Default::default")]
+        colour: Option<String>,
+    },
+    Line(u64, #[autosurgeon(missing = "Default::default")] u64),
+}
+
+#[test]
+fn hydrate_missing_in_enum_variant() {
+    let mut doc = automerge::AutoCommit::new();
+    let circle = doc
+        .put_object(&automerge::ROOT, "shape", automerge::ObjType::Map)
+        .unwrap();
+    let fields = doc
+        .put_object(&circle, "Circle", automerge::ObjType::Map)
+        .unwrap();
+    doc.put(&fields, "radius", 3_u64).unwrap();
+    let line = doc
+        .put_object(&automerge::ROOT, "line", automerge::ObjType::Map)
+        .unwrap();
+    let fields = doc
+        .put_object(&line, "Line", automerge::ObjType::List)
+        .unwrap();
+    doc.insert(&fields, 0, 5_u64).unwrap();
+
+    let shape: Shape = autosurgeon::hydrate_prop(&doc, &automerge::ROOT, "shape").unwrap();
+    assert_eq!(
+        shape,
+        Shape::Circle {
+            radius: 3,
+            colour: None
+        }
+    );
+    let line: Shape = autosurgeon::hydrate_prop(&doc, &automerge::ROOT, "line").unwrap();
+    assert_eq!(line, Shape::Line(5, 0));
+}
+
+#[test]
+fn missing_enum_variant_field_without_default_names_the_field() {
+    let mut doc = automerge::AutoCommit::new();
+    let circle = doc
+        .put_object(&automerge::ROOT, "shape", automerge::ObjType::Map)
+        .unwrap();
+    doc.put_object(&circle, "Circle", automerge::ObjType::Map)
+        .unwrap();
+    let err =
+        autosurgeon::hydrate_prop::<_, Shape, _, _>(&doc, &automerge::ROOT, "shape").unwrap_err();
+    assert!(err.to_string().contains("radius"), "{}", err);
+}
@@ -0,0 +1,65 @@
+use automerge::ReadDoc;
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+struct Profile {
+    name: String,
+    #[autosurgeon(none_as_absent)]
+    nickname: Option<String>,
+    bio: Option<String>,
+}
+
+#[test]
+fn none_deletes_the_key() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut profile = Profile {
+        name: "Margaret".to_string(),
+        nickname: Some("Peggy".to_string()),
+        bio: None,
+    };
+    reconcile(&mut doc, &profile).unwrap();
+    let mut keys = doc.keys(automerge::ROOT).collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, vec!["bio", "name", "nickname"]);
+
+    profile.nickname = None;
+    reconcile(&mut doc, &profile).unwrap();
+    let mut keys = doc.keys(automerge::ROOT).collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, vec!["bio", "name"]);
+
+    let hydrated: Profile = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, profile);
+}
+
+#[test]
+fn none_as_absent_in_enum_variants() {
+    #[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+    enum Event {
+        Meeting {
+            title: String,
+            #[autosurgeon(none_as_absent)]
+            room: Option<String>,
+        },
+    }
+
+    #[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+    struct Calendar {
+        next: Event,
+    }
+
+    let mut doc = automerge::AutoCommit::new();
+    let calendar = Calendar {
+        next: Event::Meeting {
+            title: "Standup".to_string(),
+            room: None,
+        },
+    };
+    reconcile(&mut doc, &calendar).unwrap();
+    let (_, next) = doc.get(automerge::ROOT, "next").unwrap().unwrap();
+    let (_, meeting) = doc.get(&next, "Meeting").unwrap().unwrap();
+    assert_eq!(doc.keys(&meeting).collect::<Vec<_>>(), vec!["title"]);
+
+    let hydrated: Calendar = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, calendar);
+}
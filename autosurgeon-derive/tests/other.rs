@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use automerge::{transaction::Transactable, ObjType};
+use automerge_test::{assert_doc, map};
+use autosurgeon::{hydrate_prop, reconcile_prop, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+enum Color {
+    Red,
+    Green,
+    #[autosurgeon(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+enum Status {
+    Active,
+    #[autosurgeon(other)]
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+enum Shape {
+    Circle {
+        radius: u64,
+    },
+    #[autosurgeon(other)]
+    Unknown(HashMap<String, String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(tag = "type")]
+enum Event {
+    Created {
+        id: String,
+    },
+    #[autosurgeon(other)]
+    Unknown,
+}
+
+#[test]
+fn unknown_unit_variant_hydrates_other() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "color", "Purple").unwrap();
+    let color: Color = hydrate_prop(&doc, &automerge::ROOT, "color").unwrap();
+    assert_eq!(color, Color::Unknown);
+
+    let unknown = doc
+        .put_object(automerge::ROOT, "color", ObjType::Map)
+        .unwrap();
+    doc.put_object(&unknown, "Gradient", ObjType::Map).unwrap();
+    let color: Color = hydrate_prop(&doc, &automerge::ROOT, "color").unwrap();
+    assert_eq!(color, Color::Unknown);
+
+    reconcile_prop(&mut doc, automerge::ROOT, "color", Color::Green).unwrap();
+    let color: Color = hydrate_prop(&doc, &automerge::ROOT, "color").unwrap();
+    assert_eq!(color, Color::Green);
+}
+
+#[test]
+fn unknown_variant_round_trips_through_newtype_other() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "status", "Suspended").unwrap();
+    let status: Status = hydrate_prop(&doc, &automerge::ROOT, "status").unwrap();
+    assert_eq!(status, Status::Other("Suspended".to_string()));
+    reconcile_prop(&mut doc, automerge::ROOT, "status", &status).unwrap();
+
+    let shape = doc
+        .put_object(automerge::ROOT, "shape", ObjType::Map)
+        .unwrap();
+    doc.put(&shape, "Square", "4").unwrap();
+    let shape: Shape = hydrate_prop(&doc, &automerge::ROOT, "shape").unwrap();
+    assert_eq!(
+        shape,
+        Shape::Unknown(HashMap::from([("Square".to_string(), "4".to_string())]))
+    );
+    reconcile_prop(&mut doc, automerge::ROOT, "shape", &shape).unwrap();
+
+    assert_doc!(
+        doc.document(),
+        map! {
+            "status" => { "Suspended" },
+            "shape" => { map! { "Square" => { "4" } } },
+        }
+    );
+
+    reconcile_prop(
+        &mut doc,
+        automerge::ROOT,
+        "shape",
+        Shape::Circle { radius: 2 },
+    )
+    .unwrap();
+    let shape: Shape = hydrate_prop(&doc, &automerge::ROOT, "shape").unwrap();
+    assert_eq!(shape, Shape::Circle { radius: 2 });
+}
+
+#[test]
+fn unknown_tag_hydrates_other() {
+    let mut doc = automerge::AutoCommit::new();
+    let event = doc
+        .put_object(automerge::ROOT, "event", ObjType::Map)
+        .unwrap();
+    doc.put(&event, "type", "Archived").unwrap();
+    doc.put(&event, "id", "1").unwrap();
+    let event: Event = hydrate_prop(&doc, &automerge::ROOT, "event").unwrap();
+    assert_eq!(event, Event::Unknown);
+
+    let created = Event::Created {
+        id: "2".to_string(),
+    };
+    reconcile_prop(&mut doc, automerge::ROOT, "event", &created).unwrap();
+    let event: Event = hydrate_prop(&doc, &automerge::ROOT, "event").unwrap();
+    assert_eq!(event, created);
+}
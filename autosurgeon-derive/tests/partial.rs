@@ -0,0 +1,125 @@
+use automerge::transaction::Transactable;
+use autosurgeon::{hydrate_lossy, hydrate_partial, reconcile, Hydrate, MaybeMissing, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(partial)]
+struct Settings {
+    theme: String,
+    font_size: u64,
+    #[autosurgeon(missing = "Vec::new")]
+    plugins: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(partial)]
+struct Labelled<T> {
+    label: String,
+    #[autosurgeon(hydrate = "hydrate_count")]
+    count: u64,
+    value: T,
+}
+
+fn hydrate_count<D: autosurgeon::ReadDoc>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: autosurgeon::Prop<'_>,
+) -> Result<u64, autosurgeon::HydrateError> {
+    Ok(u64::hydrate(doc, obj, prop)? * 2)
+}
+
+#[test]
+fn partial_hydrate_of_complete_document() {
+    let settings = Settings {
+        theme: "dark".to_string(),
+        font_size: 12,
+        plugins: vec!["spellcheck".to_string()],
+    };
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &settings).unwrap();
+
+    let partial = hydrate_partial::<_, Settings>(&doc).unwrap();
+    assert!(partial.missing_fields().is_empty());
+    assert_eq!(partial.complete().ok(), Some(settings));
+}
+
+#[test]
+fn partial_hydrate_of_incomplete_document() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "font_size", 14_u64).unwrap();
+
+    let partial = hydrate_partial::<_, Settings>(&doc).unwrap();
+    assert_eq!(partial.theme, MaybeMissing::Missing);
+    assert_eq!(partial.font_size, MaybeMissing::Present(14));
+    assert_eq!(partial.missing_fields(), vec!["theme", "plugins"]);
+    let partial = partial.complete().err().unwrap();
+    assert_eq!(partial.font_size, MaybeMissing::Present(14));
+
+    // Fields with a `missing` function don't have to be present
+    doc.put(automerge::ROOT, "theme", "light").unwrap();
+    let partial = hydrate_partial::<_, Settings>(&doc).unwrap();
+    assert_eq!(partial.missing_fields(), vec!["plugins"]);
+    assert_eq!(
+        partial.complete().ok(),
+        Some(Settings {
+            theme: "light".to_string(),
+            font_size: 14,
+            plugins: Vec::new(),
+        })
+    );
+}
+
+#[test]
+fn partial_hydrate_of_generic_struct_with_hydrate_fn() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "count", 2_u64).unwrap();
+    doc.put(automerge::ROOT, "value", true).unwrap();
+
+    let partial = hydrate_partial::<_, Labelled<bool>>(&doc).unwrap();
+    assert_eq!(partial.label, MaybeMissing::Missing);
+    assert_eq!(partial.count, MaybeMissing::Present(4));
+    assert_eq!(partial.value, MaybeMissing::Present(true));
+}
+
+#[test]
+fn partial_hydrate_still_fails_on_wrong_types() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "font_size", "large").unwrap();
+
+    let err = hydrate_partial::<_, Settings>(&doc).err().unwrap();
+    assert_eq!(err.to_string(), "at font_size: unexpected string");
+}
+
+#[test]
+fn lossy_hydrate_collects_every_error() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "theme", 1_u64).unwrap();
+    doc.put(automerge::ROOT, "font_size", "large").unwrap();
+    let plugins = doc
+        .put_object(automerge::ROOT, "plugins", automerge::ObjType::List)
+        .unwrap();
+    doc.insert(&plugins, 0, "spellcheck").unwrap();
+
+    let (partial, errors) = hydrate_lossy::<_, Settings>(&doc);
+    assert_eq!(partial.missing_fields(), vec!["theme", "font_size"]);
+    assert_eq!(
+        partial.plugins,
+        MaybeMissing::Present(vec!["spellcheck".to_string()])
+    );
+    assert_eq!(
+        errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+        vec![
+            "at theme: unexpected uint",
+            "at font_size: unexpected string"
+        ]
+    );
+
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "label", "clicks").unwrap();
+    doc.put(automerge::ROOT, "count", "many").unwrap();
+    doc.put(automerge::ROOT, "value", true).unwrap();
+    let (partial, errors) = hydrate_lossy::<_, Labelled<bool>>(&doc);
+    assert_eq!(partial.label, MaybeMissing::Present("clicks".to_string()));
+    assert_eq!(partial.count, MaybeMissing::Missing);
+    assert_eq!(partial.value, MaybeMissing::Present(true));
+    assert_eq!(errors.len(), 1);
+}
@@ -0,0 +1,84 @@
+use std::marker::PhantomData;
+
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+
+// Implements neither `Reconcile` nor `Hydrate`
+#[derive(Debug, PartialEq)]
+struct Meters;
+
+#[derive(Debug, PartialEq, Reconcile, Hydrate)]
+struct Distance<U> {
+    value: u64,
+    unit: PhantomData<U>,
+}
+
+#[test]
+fn phantom_fields_are_skipped() {
+    let distance = Distance::<Meters> {
+        value: 5,
+        unit: PhantomData,
+    };
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &distance).unwrap();
+    assert_doc!(doc.document(), map! { "value" => { 5_u64 } });
+    let hydrated: Distance<Meters> = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, distance);
+}
+
+#[derive(Debug, PartialEq, Reconcile, Hydrate)]
+struct Span<U>(u64, PhantomData<U>, u64);
+
+#[derive(Debug, PartialEq, Reconcile, Hydrate)]
+struct Route {
+    span: Span<Meters>,
+}
+
+#[test]
+fn phantom_tuple_fields_do_not_take_an_index() {
+    let route = Route {
+        span: Span(1, PhantomData, 2),
+    };
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &route).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! { "span" => { list! { { 1_u64 }, { 2_u64 } } } }
+    );
+    let hydrated: Route = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, route);
+}
+
+#[derive(Debug, PartialEq, Reconcile, Hydrate)]
+enum Measurement<U> {
+    Exact { value: u64, unit: PhantomData<U> },
+    Between(u64, u64, PhantomData<U>),
+}
+
+#[derive(Debug, PartialEq, Reconcile, Hydrate)]
+struct Survey {
+    first: Measurement<Meters>,
+    second: Measurement<Meters>,
+}
+
+#[test]
+fn phantom_variant_fields_are_skipped() {
+    let survey = Survey {
+        first: Measurement::Exact {
+            value: 3,
+            unit: PhantomData,
+        },
+        second: Measurement::Between(1, 2, PhantomData),
+    };
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &survey).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "first" => { map! { "Exact" => { map! { "value" => { 3_u64 } } } } },
+            "second" => { map! { "Between" => { list! { { 1_u64 }, { 2_u64 } } } } },
+        }
+    );
+    let hydrated: Survey = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, survey);
+}
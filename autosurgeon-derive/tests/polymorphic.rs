@@ -0,0 +1,137 @@
+use automerge_test::{assert_doc, map};
+use autosurgeon::{hydrate, polymorphic::AsAny, reconcile, Hydrate, Reconcile};
+
+trait Plugin: AsAny {
+    fn describe(&self) -> String;
+}
+
+#[derive(Reconcile, Hydrate)]
+struct Greeter {
+    greeting: String,
+}
+
+impl Plugin for Greeter {
+    fn describe(&self) -> String {
+        format!("greeter saying {}", self.greeting)
+    }
+}
+
+#[derive(Reconcile, Hydrate)]
+struct Repeater {
+    times: u64,
+    greeting: String,
+}
+
+impl Plugin for Repeater {
+    fn describe(&self) -> String {
+        format!("repeater saying {} {} times", self.greeting, self.times)
+    }
+}
+
+struct Unregistered;
+
+impl Plugin for Unregistered {
+    fn describe(&self) -> String {
+        "unregistered".to_string()
+    }
+}
+
+autosurgeon::polymorphic_registry! {
+    mod plugins for dyn Plugin {
+        "greeter" => Greeter,
+        "repeater" => Repeater,
+    }
+}
+
+#[derive(Reconcile, Hydrate)]
+struct Config {
+    name: String,
+    #[autosurgeon(polymorphic = "plugins")]
+    plugin: Box<dyn Plugin>,
+}
+
+#[test]
+fn round_trip_trait_object() {
+    let mut doc = automerge::AutoCommit::new();
+    let config = Config {
+        name: "test".to_string(),
+        plugin: Box::new(Greeter {
+            greeting: "hello".to_string(),
+        }),
+    };
+    reconcile(&mut doc, &config).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "name" => { "test" },
+            "plugin" => { map! {
+                "type" => { "greeter" },
+                "value" => { map! {
+                    "greeting" => { "hello" },
+                }},
+            }},
+        }
+    );
+
+    let hydrated: Config = hydrate(&doc).unwrap();
+    assert_eq!(hydrated.plugin.describe(), "greeter saying hello");
+}
+
+#[test]
+fn changing_type_replaces_value() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut config = Config {
+        name: "test".to_string(),
+        plugin: Box::new(Repeater {
+            times: 2,
+            greeting: "hello".to_string(),
+        }),
+    };
+    reconcile(&mut doc, &config).unwrap();
+
+    config.plugin = Box::new(Greeter {
+        greeting: "hi".to_string(),
+    });
+    reconcile(&mut doc, &config).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "name" => { "test" },
+            "plugin" => { map! {
+                "type" => { "greeter" },
+                "value" => { map! {
+                    "greeting" => { "hi" },
+                }},
+            }},
+        }
+    );
+}
+
+#[test]
+fn unknown_tag_fails_to_hydrate() {
+    let mut doc = automerge::AutoCommit::new();
+    let config = Config {
+        name: "test".to_string(),
+        plugin: Box::new(Greeter {
+            greeting: "hello".to_string(),
+        }),
+    };
+    reconcile(&mut doc, &config).unwrap();
+    let plugin = match automerge::ReadDoc::get(&doc, automerge::ROOT, "plugin").unwrap() {
+        Some((_, id)) => id,
+        None => panic!("no plugin"),
+    };
+    automerge::transaction::Transactable::put(&mut doc, &plugin, "type", "other").unwrap();
+    assert!(hydrate::<_, Config>(&doc).is_err());
+}
+
+#[test]
+#[should_panic(expected = "not registered with plugins")]
+fn reconciling_unregistered_type_panics() {
+    let mut doc = automerge::AutoCommit::new();
+    let config = Config {
+        name: "test".to_string(),
+        plugin: Box::new(Unregistered),
+    };
+    let _ = reconcile(&mut doc, &config);
+}
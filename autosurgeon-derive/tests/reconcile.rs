@@ -510,6 +510,42 @@ fn reconcile_between_enum_variants() {
     );
 }
 
+#[derive(Reconcile)]
+enum Shape {
+    Circle { radius: f64 },
+    Square(f64),
+    Line(f64, f64),
+}
+
+#[test]
+fn concurrent_switches_between_enum_variants() {
+    let mut doc1 = automerge::AutoCommit::new().with_actor("0000".as_bytes().into());
+    reconcile_prop(
+        &mut doc1,
+        automerge::ROOT,
+        "shape",
+        Shape::Circle { radius: 1.0 },
+    )
+    .unwrap();
+    let mut doc2 = doc1.fork().with_actor("ffff".as_bytes().into());
+
+    reconcile_prop(&mut doc1, automerge::ROOT, "shape", Shape::Square(2.0)).unwrap();
+    reconcile_prop(&mut doc2, automerge::ROOT, "shape", Shape::Line(3.0, 4.0)).unwrap();
+    doc1.merge(&mut doc2).unwrap();
+
+    // Both peers replaced the whole variant map so we end up with a conflict between the two maps
+    // rather than a single map containing both variants
+    assert_doc!(
+        doc1.document(),
+        map! {
+            "shape" => {
+                map! { "Square" => { 2.0 } },
+                map! { "Line" => { list! { { 3.0 }, { 4.0 } } } },
+            }
+        }
+    );
+}
+
 mod enumkeyvisibility {
     use autosurgeon::Reconcile;
 
@@ -528,3 +564,21 @@ pub enum Ports {
     Range(u16, u16),
     Collection(Vec<u16>),
 }
+
+mod generated_type_names {
+    use autosurgeon::Reconcile;
+
+    // The key type the derive generates for `Vehicle` can't collide with a type in the same module
+    #[allow(dead_code, non_camel_case_types)]
+    struct ___VehicleReconcileKeyType;
+
+    #[derive(Reconcile)]
+    #[allow(dead_code)]
+    pub enum Vehicle {
+        Car {
+            #[key]
+            id: String,
+        },
+        Bike,
+    }
+}
@@ -0,0 +1,104 @@
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{
+    reconcile::{LoadKey, MapReconciler},
+    reconcile_prop, Reconcile, ReconcileKey, Reconciler,
+};
+
+#[derive(Clone, ReconcileKey)]
+struct Cereal {
+    name: String,
+    #[key]
+    id: u64,
+}
+
+impl Reconcile for Cereal {
+    autosurgeon::delegate_key!();
+
+    fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        let mut m = reconciler.map()?;
+        m.put("id", self.id)?;
+        m.put("name", self.name.to_lowercase())?;
+        Ok(())
+    }
+}
+
+#[test]
+fn manual_reconcile_with_derived_key() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut cereals = vec![
+        Cereal {
+            name: "Weetabix".to_string(),
+            id: 1,
+        },
+        Cereal {
+            name: "Quavars".to_string(),
+            id: 2,
+        },
+    ];
+    reconcile_prop(&mut doc, automerge::ROOT, "cereals", &cereals).unwrap();
+
+    let mut doc2 = doc.fork().with_actor(automerge::ActorId::random());
+    let mut cereals2 = cereals.clone();
+    cereals2.insert(
+        0,
+        Cereal {
+            name: "Oats".to_string(),
+            id: 3,
+        },
+    );
+    reconcile_prop(&mut doc2, automerge::ROOT, "cereals", cereals2).unwrap();
+
+    cereals.remove(0);
+    reconcile_prop(&mut doc, automerge::ROOT, "cereals", &cereals).unwrap();
+
+    doc.merge(&mut doc2).unwrap();
+
+    assert_doc!(
+        doc.document(),
+        map! {
+            "cereals" => { list! {
+                { map! {
+                    "name" => { "oats" },
+                    "id" => { 3_u64 },
+                }},
+                { map! {
+                    "name" => { "quavars" },
+                    "id" => { 2_u64 },
+                }},
+            }}
+        }
+    );
+}
+
+#[test]
+fn derived_key_matches_field() {
+    let cereal = Cereal {
+        name: "Weetabix".to_string(),
+        id: 1,
+    };
+    assert!(matches!(
+        Reconcile::key(&cereal),
+        LoadKey::Found(k) if *k == 1
+    ));
+}
+
+#[derive(ReconcileKey)]
+struct Unkeyed {
+    name: String,
+}
+
+impl Reconcile for Unkeyed {
+    autosurgeon::delegate_key!();
+
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.name.reconcile(reconciler)
+    }
+}
+
+#[test]
+fn no_key_field_means_no_key() {
+    let value = Unkeyed {
+        name: "one".to_string(),
+    };
+    assert!(matches!(Reconcile::key(&value), LoadKey::NoKey));
+}
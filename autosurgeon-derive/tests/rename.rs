@@ -0,0 +1,129 @@
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{hydrate_prop, reconcile_prop, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+enum Event {
+    #[autosurgeon(rename = "started")]
+    Started,
+    #[autosurgeon(rename = "progress")]
+    Progress(u64),
+    #[autosurgeon(rename = "moved")]
+    Moved(i64, i64),
+    #[autosurgeon(rename = "finished")]
+    Finished { code: u64 },
+}
+
+#[test]
+fn renamed_variants_round_trip() {
+    let mut doc = automerge::AutoCommit::new();
+    let events = vec![
+        Event::Started,
+        Event::Progress(50),
+        Event::Moved(1, -1),
+        Event::Finished { code: 0 },
+    ];
+    reconcile_prop(&mut doc, automerge::ROOT, "events", &events).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "events" => { list! {
+                { "started" },
+                { map! { "progress" => { 50_u64 } } },
+                { map! { "moved" => { list! { { 1 }, { -1 } } } } },
+                { map! { "finished" => { map! { "code" => { 0_u64 } } } } },
+            }}
+        }
+    );
+
+    let hydrated: Vec<Event> = hydrate_prop(&doc, &automerge::ROOT, "events").unwrap();
+    assert_eq!(hydrated, events);
+}
+
+#[test]
+fn original_variant_names_are_not_hydrated() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile_prop(&mut doc, automerge::ROOT, "event", "Started").unwrap();
+    let result: Result<Event, _> = hydrate_prop(&doc, &automerge::ROOT, "event");
+    assert!(result.is_err());
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile)]
+struct Widget {
+    #[key]
+    id: u64,
+    name: String,
+}
+
+mod widget_by_id {
+    use std::borrow::Cow;
+
+    use autosurgeon::{reconcile::LoadKey, Prop, ReadDoc, ReconcileError, Reconciler};
+
+    pub type Key<'a> = Cow<'a, u64>;
+
+    pub fn reconcile<R: Reconciler>(widget: &super::Widget, reconciler: R) -> Result<(), R::Error> {
+        autosurgeon::Reconcile::reconcile(widget, reconciler)
+    }
+
+    pub fn hydrate_key<'k, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<LoadKey<Key<'k>>, ReconcileError> {
+        autosurgeon::reconcile::hydrate_key::<_, u64>(doc, obj, prop, "id".into())
+            .map(|k| k.map(Cow::Owned))
+    }
+
+    pub fn key(widget: &super::Widget) -> LoadKey<Key<'_>> {
+        LoadKey::Found(Cow::Borrowed(&widget.id))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile)]
+enum Part {
+    #[autosurgeon(rename = "widget")]
+    Widget(#[autosurgeon(reconcile_with = "widget_by_id")] Widget),
+}
+
+// The key of a renamed newtype variant must be hydrated from the renamed discriminant, otherwise
+// list elements can't be matched up by key
+#[test]
+fn renamed_newtype_variant_with_reconcile_with_uses_key() {
+    let mut parts = vec![
+        Part::Widget(Widget {
+            id: 1,
+            name: "one".to_string(),
+        }),
+        Part::Widget(Widget {
+            id: 2,
+            name: "two".to_string(),
+        }),
+    ];
+    let mut doc = automerge::AutoCommit::new();
+    reconcile_prop(&mut doc, automerge::ROOT, "parts", &parts).unwrap();
+
+    let mut doc2 = doc.fork().with_actor(automerge::ActorId::random());
+    let mut parts2 = parts.clone();
+    let Part::Widget(widget) = &mut parts2[1];
+    widget.name = "TWO".to_string();
+    reconcile_prop(&mut doc2, automerge::ROOT, "parts", &parts2).unwrap();
+
+    parts.remove(0);
+    reconcile_prop(&mut doc, automerge::ROOT, "parts", &parts).unwrap();
+
+    doc.merge(&mut doc2).unwrap();
+
+    assert_doc!(
+        doc.document(),
+        map! {
+            "parts" => { list! {
+                { map! {
+                    "widget" => { map! {
+                        "id" => { 2_u64 },
+                        "name" => { "TWO" },
+                    }}
+                }},
+            }}
+        }
+    );
+}
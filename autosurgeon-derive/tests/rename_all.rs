@@ -0,0 +1,97 @@
+use automerge::ReadDoc;
+use autosurgeon::{hydrate, hydrate_prop, reconcile, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(rename_all = "camelCase")]
+struct Project {
+    project_name: String,
+    #[autosurgeon(rename = "owner")]
+    owner_id: u64,
+    current_status: Status,
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(rename_all = "kebab-case")]
+enum Status {
+    NotStarted,
+    #[autosurgeon(rename = "wip")]
+    InProgress,
+    OnHold {
+        blocked_by: String,
+    },
+}
+
+fn project() -> Project {
+    Project {
+        project_name: "autosurgeon".to_string(),
+        owner_id: 7,
+        current_status: Status::OnHold {
+            blocked_by: "review".to_string(),
+        },
+    }
+}
+
+#[test]
+fn fields_and_variants_are_renamed() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, project()).unwrap();
+
+    let mut keys = doc.keys(automerge::ROOT).collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, vec!["currentStatus", "owner", "projectName"]);
+
+    let (_, status) = doc.get(automerge::ROOT, "currentStatus").unwrap().unwrap();
+    let blocked_by: String = hydrate_prop(&doc, &status, "on-hold")
+        .map(|h: std::collections::HashMap<String, String>| h["blocked-by"].clone())
+        .unwrap();
+    assert_eq!(blocked_by, "review");
+
+    let hydrated: Project = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, project());
+}
+
+#[test]
+fn explicit_renames_take_precedence() {
+    let mut doc = automerge::AutoCommit::new();
+    let mut project = project();
+    project.current_status = Status::InProgress;
+    reconcile(&mut doc, &project).unwrap();
+    let status: String = hydrate_prop(&doc, automerge::ROOT, "currentStatus").unwrap();
+    assert_eq!(status, "wip");
+
+    project.current_status = Status::NotStarted;
+    reconcile(&mut doc, &project).unwrap();
+    let status: String = hydrate_prop(&doc, automerge::ROOT, "currentStatus").unwrap();
+    assert_eq!(status, "not-started");
+    assert_eq!(hydrate::<_, Project>(&doc).unwrap(), project);
+}
+
+#[test]
+fn screaming_snake_case() {
+    #[derive(Debug, PartialEq, Reconcile, Hydrate)]
+    #[autosurgeon(rename_all = "SCREAMING_SNAKE_CASE")]
+    enum Level {
+        VeryHigh,
+        Low,
+    }
+
+    #[derive(Debug, PartialEq, Reconcile, Hydrate)]
+    #[autosurgeon(rename_all = "SCREAMING_SNAKE_CASE")]
+    struct Alarm {
+        alarm_level: Level,
+    }
+
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(
+        &mut doc,
+        Alarm {
+            alarm_level: Level::VeryHigh,
+        },
+    )
+    .unwrap();
+    let level: String = hydrate_prop(&doc, automerge::ROOT, "ALARM_LEVEL").unwrap();
+    assert_eq!(level, "VERY_HIGH");
+    let alarm: Alarm = hydrate(&doc).unwrap();
+    assert_eq!(alarm.alarm_level, Level::VeryHigh);
+    assert_ne!(alarm.alarm_level, Level::Low);
+}
@@ -0,0 +1,79 @@
+use automerge::ActorId;
+use automerge_test::{assert_doc, map};
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+struct Job {
+    outcome: Result<u64, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[allow(dead_code)]
+enum Outcome {
+    Ok(u64),
+    Err(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+struct DerivedJob {
+    outcome: Outcome,
+}
+
+#[test]
+fn result_is_stored_like_a_derived_enum() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &Job { outcome: Ok(3) }).unwrap();
+    let mut derived = automerge::AutoCommit::new();
+    reconcile(
+        &mut derived,
+        &DerivedJob {
+            outcome: Outcome::Ok(3),
+        },
+    )
+    .unwrap();
+    assert_doc!(
+        doc.document(),
+        map! { "outcome" => { map! { "Ok" => { 3_u64 } } } }
+    );
+    assert_doc!(
+        derived.document(),
+        map! { "outcome" => { map! { "Ok" => { 3_u64 } } } }
+    );
+
+    let job = Job {
+        outcome: Err("failed".to_string()),
+    };
+    reconcile(&mut doc, &job).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! { "outcome" => { map! { "Err" => { "failed" } } } }
+    );
+    assert_eq!(hydrate::<_, Job>(&doc).unwrap(), job);
+
+    let from_derived: DerivedJob = hydrate(&doc).unwrap();
+    assert_eq!(from_derived.outcome, Outcome::Err("failed".to_string()));
+}
+
+#[test]
+fn concurrent_switches_between_ok_and_err() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &Job { outcome: Ok(1) }).unwrap();
+
+    let mut doc2 = doc.fork().with_actor(ActorId::random());
+    reconcile(&mut doc2, &Job { outcome: Ok(2) }).unwrap();
+    reconcile(
+        &mut doc,
+        &Job {
+            outcome: Err("failed".to_string()),
+        },
+    )
+    .unwrap();
+    doc.merge(&mut doc2).unwrap();
+
+    let merged: Job = hydrate(&doc).unwrap();
+    assert!(
+        merged.outcome == Ok(2) || merged.outcome == Err("failed".to_string()),
+        "unexpected outcome {:?}",
+        merged.outcome
+    );
+}
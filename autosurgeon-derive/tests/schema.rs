@@ -0,0 +1,130 @@
+use automerge::transaction::Transactable;
+use autosurgeon::{
+    reconcile, reconcile_prop,
+    schema::{validate, DescribeSchema, Field, ScalarKind, Schema},
+    Reconcile, Text,
+};
+
+#[derive(Reconcile, DescribeSchema)]
+#[autosurgeon(rename_all = "camelCase")]
+struct Document {
+    title: Text,
+    page_count: u32,
+    #[autosurgeon(missing = "Vec::new")]
+    authors: Vec<Author>,
+    status: Status,
+    shapes: Vec<Shape>,
+}
+
+#[derive(Reconcile, DescribeSchema)]
+struct Author(String, Option<u64>);
+
+#[derive(Reconcile, DescribeSchema)]
+enum Status {
+    Draft,
+    #[autosurgeon(rename = "published")]
+    Published {
+        at: i64,
+    },
+}
+
+#[derive(Reconcile, DescribeSchema)]
+#[autosurgeon(tag = "type")]
+enum Shape {
+    Circle { radius: f64 },
+    Empty,
+}
+
+#[test]
+fn derived_schemas_describe_the_document_layout() {
+    assert_eq!(
+        Status::schema(),
+        Schema::OneOf(vec![
+            Schema::Literal("Draft".to_string()),
+            Schema::Struct(vec![Field::new(
+                "published",
+                Schema::Struct(vec![Field::new("at", Schema::Scalar(ScalarKind::Int))]),
+            )]),
+        ])
+    );
+    assert_eq!(
+        Shape::schema(),
+        Schema::OneOf(vec![
+            Schema::Struct(vec![
+                Field::new("type", Schema::Literal("Circle".to_string())),
+                Field::new("radius", Schema::Scalar(ScalarKind::F64)),
+            ]),
+            Schema::Struct(vec![Field::new(
+                "type",
+                Schema::Literal("Empty".to_string())
+            )]),
+        ])
+    );
+    let Schema::Struct(fields) = Document::schema() else {
+        panic!("expected a struct schema");
+    };
+    let names = fields
+        .iter()
+        .map(|f| (f.name(), f.is_required()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            ("title", true),
+            ("pageCount", true),
+            ("authors", false),
+            ("status", true),
+            ("shapes", true),
+        ]
+    );
+}
+
+#[test]
+fn reconciled_documents_are_valid() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(
+        &mut doc,
+        Document {
+            title: Text::with_value("Notes"),
+            page_count: 3,
+            authors: vec![Author("Ada".to_string(), None)],
+            status: Status::Published { at: 1 },
+            shapes: vec![Shape::Circle { radius: 1.0 }, Shape::Empty],
+        },
+    )
+    .unwrap();
+    assert_eq!(validate(&doc, &Document::schema()), Ok(()));
+
+    reconcile_prop(&mut doc, automerge::ROOT, "status", Status::Draft).unwrap();
+    assert_eq!(validate(&doc, &Document::schema()), Ok(()));
+}
+
+#[test]
+fn mismatches_have_paths() {
+    let mut doc = automerge::AutoCommit::new();
+    doc.put(automerge::ROOT, "title", "Notes").unwrap();
+    doc.put(automerge::ROOT, "pageCount", 3).unwrap();
+    doc.put(automerge::ROOT, "status", "Archived").unwrap();
+    let shapes = doc
+        .put_object(automerge::ROOT, "shapes", automerge::ObjType::List)
+        .unwrap();
+    let shape = doc
+        .insert_object(&shapes, 0, automerge::ObjType::Map)
+        .unwrap();
+    doc.put(&shape, "type", "Circle").unwrap();
+    doc.put(&shape, "radius", "big").unwrap();
+
+    let mismatches = validate(&doc, &Document::schema()).unwrap_err();
+    let messages = mismatches
+        .iter()
+        .map(|m| (m.path().to_string(), m.found().to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            ("/title".to_string(), "the string \"Notes\"".to_string()),
+            ("/status".to_string(), "the string \"Archived\"".to_string()),
+            ("/shapes/0".to_string(), "a map".to_string()),
+        ]
+    );
+}
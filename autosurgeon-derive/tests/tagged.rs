@@ -0,0 +1,221 @@
+use automerge::transaction::Transactable;
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{hydrate_prop, reconcile_prop, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(tag = "type", content = "data")]
+enum Shape {
+    Circle {
+        radius: u64,
+    },
+    Square(u64),
+    Line(i64, i64),
+    #[autosurgeon(rename = "empty")]
+    Empty,
+}
+
+#[test]
+fn adjacently_tagged_round_trip() {
+    let mut doc = automerge::AutoCommit::new();
+    let shapes = vec![
+        Shape::Circle { radius: 1 },
+        Shape::Square(2),
+        Shape::Line(-1, 1),
+        Shape::Empty,
+    ];
+    reconcile_prop(&mut doc, automerge::ROOT, "shapes", &shapes).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "shapes" => { list! {
+                { map! {
+                    "type" => { "Circle" },
+                    "data" => { map! { "radius" => { 1_u64 } } },
+                }},
+                { map! {
+                    "type" => { "Square" },
+                    "data" => { 2_u64 },
+                }},
+                { map! {
+                    "type" => { "Line" },
+                    "data" => { list! { { -1 }, { 1 } } },
+                }},
+                { map! {
+                    "type" => { "empty" },
+                }},
+            }}
+        }
+    );
+
+    let hydrated: Vec<Shape> = hydrate_prop(&doc, &automerge::ROOT, "shapes").unwrap();
+    assert_eq!(hydrated, shapes);
+}
+
+#[test]
+fn switching_adjacently_tagged_variant_removes_old_content() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile_prop(
+        &mut doc,
+        automerge::ROOT,
+        "shape",
+        Shape::Circle { radius: 1 },
+    )
+    .unwrap();
+    reconcile_prop(&mut doc, automerge::ROOT, "shape", Shape::Empty).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "shape" => { map! { "type" => { "empty" } } }
+        }
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+struct Rename {
+    name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(tag = "type")]
+enum Event {
+    #[autosurgeon(rename = "created")]
+    Created {
+        #[key]
+        id: String,
+        owner: String,
+    },
+    #[autosurgeon(rename = "renamed")]
+    Renamed(Rename),
+    #[autosurgeon(rename = "deleted")]
+    Deleted,
+}
+
+#[test]
+fn internally_tagged_round_trip() {
+    let mut doc = automerge::AutoCommit::new();
+    let events = vec![
+        Event::Created {
+            id: "1".to_string(),
+            owner: "alice".to_string(),
+        },
+        Event::Renamed(Rename {
+            name: "bob".to_string(),
+        }),
+        Event::Deleted,
+    ];
+    reconcile_prop(&mut doc, automerge::ROOT, "events", &events).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "events" => { list! {
+                { map! {
+                    "type" => { "created" },
+                    "id" => { "1" },
+                    "owner" => { "alice" },
+                }},
+                { map! {
+                    "type" => { "renamed" },
+                    "name" => { "bob" },
+                }},
+                { map! {
+                    "type" => { "deleted" },
+                }},
+            }}
+        }
+    );
+
+    let hydrated: Vec<Event> = hydrate_prop(&doc, &automerge::ROOT, "events").unwrap();
+    assert_eq!(hydrated, events);
+}
+
+#[test]
+fn hydrate_internally_tagged_from_foreign_document() {
+    let mut doc = automerge::AutoCommit::new();
+    let event = doc
+        .put_object(automerge::ROOT, "event", automerge::ObjType::Map)
+        .unwrap();
+    doc.put(&event, "type", "renamed").unwrap();
+    doc.put(&event, "name", "carol").unwrap();
+    doc.put(&event, "timestamp", 1234).unwrap();
+
+    let hydrated: Event = hydrate_prop(&doc, &automerge::ROOT, "event").unwrap();
+    assert_eq!(
+        hydrated,
+        Event::Renamed(Rename {
+            name: "carol".to_string()
+        })
+    );
+
+    doc.put(&event, "type", "unknown").unwrap();
+    let result: Result<Event, _> = hydrate_prop(&doc, &automerge::ROOT, "event");
+    assert!(result.is_err());
+}
+
+#[test]
+fn switching_internally_tagged_variant_removes_old_fields() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile_prop(
+        &mut doc,
+        automerge::ROOT,
+        "event",
+        Event::Created {
+            id: "1".to_string(),
+            owner: "alice".to_string(),
+        },
+    )
+    .unwrap();
+    reconcile_prop(
+        &mut doc,
+        automerge::ROOT,
+        "event",
+        Event::Renamed(Rename {
+            name: "bob".to_string(),
+        }),
+    )
+    .unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "event" => { map! {
+                "type" => { "renamed" },
+                "name" => { "bob" },
+            }}
+        }
+    );
+}
+
+#[test]
+fn internally_tagged_variants_are_matched_by_key() {
+    let mut events = vec![
+        Event::Created {
+            id: "1".to_string(),
+            owner: "alice".to_string(),
+        },
+        Event::Created {
+            id: "2".to_string(),
+            owner: "bob".to_string(),
+        },
+    ];
+    let mut doc = automerge::AutoCommit::new();
+    reconcile_prop(&mut doc, automerge::ROOT, "events", &events).unwrap();
+
+    let mut doc2 = doc.fork().with_actor(automerge::ActorId::random());
+    let mut events2 = events.clone();
+    if let Event::Created { owner, .. } = &mut events2[1] {
+        *owner = "charlie".to_string();
+    }
+    reconcile_prop(&mut doc2, automerge::ROOT, "events", &events2).unwrap();
+
+    events.remove(0);
+    reconcile_prop(&mut doc, automerge::ROOT, "events", &events).unwrap();
+
+    doc.merge(&mut doc2).unwrap();
+    let hydrated: Vec<Event> = hydrate_prop(&doc, &automerge::ROOT, "events").unwrap();
+    assert_eq!(
+        hydrated,
+        vec![Event::Created {
+            id: "2".to_string(),
+            owner: "charlie".to_string(),
+        }]
+    );
+}
@@ -0,0 +1,81 @@
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile, Text};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+struct Todo {
+    #[key]
+    title: Text,
+    done: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+struct Todos {
+    items: Vec<Todo>,
+}
+
+fn todo(title: &str) -> Todo {
+    Todo {
+        title: Text::with_value(title),
+        done: false,
+    }
+}
+
+#[test]
+fn text_fields_can_be_keys() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(
+        &mut doc,
+        Todos {
+            items: vec![todo("Milk"), todo("Eggs")],
+        },
+    )
+    .unwrap();
+
+    // Concurrently insert an item at the start and tick off "Eggs"
+    let mut doc2 = doc.fork().with_actor("actor2".as_bytes().into());
+    let mut todos2: Todos = hydrate(&doc2).unwrap();
+    todos2.items.insert(0, todo("Bread"));
+    reconcile(&mut doc2, &todos2).unwrap();
+
+    let mut todos: Todos = hydrate(&doc).unwrap();
+    todos.items[1].done = true;
+    reconcile(&mut doc, &todos).unwrap();
+
+    doc.merge(&mut doc2).unwrap();
+    let merged: Todos = hydrate(&doc).unwrap();
+    assert_eq!(
+        merged.items,
+        vec![
+            todo("Bread"),
+            todo("Milk"),
+            Todo {
+                done: true,
+                ..todo("Eggs")
+            }
+        ]
+    );
+}
+
+#[test]
+fn new_items_matching_existing_keys_do_not_duplicate_the_text() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(
+        &mut doc,
+        Todos {
+            items: vec![todo("Milk"), todo("Eggs")],
+        },
+    )
+    .unwrap();
+
+    let todos = Todos {
+        items: vec![
+            todo("Eggs"),
+            Todo {
+                done: true,
+                ..todo("Milk")
+            },
+        ],
+    };
+    reconcile(&mut doc, &todos).unwrap();
+    let hydrated: Todos = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, todos);
+}
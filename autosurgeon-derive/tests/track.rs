@@ -0,0 +1,84 @@
+use automerge::{transaction::Transactable, ReadDoc};
+use autosurgeon::{hydrate, hydrate_prop, reconcile, Dirty, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Default, Reconcile, Hydrate)]
+#[autosurgeon(track)]
+struct Contact {
+    name: String,
+    email: String,
+    tags: Vec<String>,
+    dirty: Dirty,
+}
+
+fn contact() -> Contact {
+    Contact {
+        name: "Sherlock Holmes".to_string(),
+        email: "sherlock@example.com".to_string(),
+        tags: vec!["detective".to_string()],
+        dirty: Dirty::default(),
+    }
+}
+
+#[test]
+fn new_values_are_written_in_full() {
+    let mut doc = automerge::AutoCommit::new();
+    let contact = contact();
+    reconcile(&mut doc, &contact).unwrap();
+    assert!(contact.dirty.is_clean());
+    assert!(doc.get(automerge::ROOT, "dirty").unwrap().is_none());
+
+    let hydrated: Contact = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, contact);
+    assert!(hydrated.dirty.is_clean());
+}
+
+#[test]
+fn only_dirty_fields_are_written() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, contact()).unwrap();
+
+    let mut contact: Contact = hydrate(&doc).unwrap();
+    doc.put(automerge::ROOT, "name", "S. Holmes").unwrap();
+    contact
+        .set_email("holmes@example.com".to_string())
+        .set_tags(vec!["consulting detective".to_string()]);
+    assert!(contact.dirty.is_dirty(1));
+    assert!(!contact.dirty.is_dirty(0));
+    reconcile(&mut doc, &contact).unwrap();
+    assert!(contact.dirty.is_clean());
+
+    let hydrated: Contact = hydrate(&doc).unwrap();
+    assert_eq!(hydrated.name, "S. Holmes");
+    assert_eq!(hydrated.email, "holmes@example.com");
+    assert_eq!(hydrated.tags, vec!["consulting detective"]);
+}
+
+#[test]
+fn clean_fields_missing_from_the_document_are_written() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, contact()).unwrap();
+    let contact: Contact = hydrate(&doc).unwrap();
+
+    let mut other = automerge::AutoCommit::new();
+    other
+        .put(automerge::ROOT, "name", "Mycroft Holmes")
+        .unwrap();
+    reconcile(&mut other, &contact).unwrap();
+    let email: String = hydrate_prop(&other, automerge::ROOT, "email").unwrap();
+    assert_eq!(email, "sherlock@example.com");
+    let name: String = hydrate_prop(&other, automerge::ROOT, "name").unwrap();
+    assert_eq!(name, "Mycroft Holmes");
+}
+
+#[test]
+fn mark_all_writes_every_field() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, contact()).unwrap();
+    let mut contact: Contact = hydrate(&doc).unwrap();
+    doc.put(automerge::ROOT, "name", "S. Holmes").unwrap();
+
+    contact.dirty.mark_all();
+    reconcile(&mut doc, &contact).unwrap();
+    let name: String = hydrate_prop(&doc, automerge::ROOT, "name").unwrap();
+    assert_eq!(name, "Sherlock Holmes");
+}
@@ -0,0 +1,102 @@
+use automerge_test::{assert_doc, list, map};
+use autosurgeon::{hydrate, hydrate_prop, reconcile, reconcile_prop, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(transparent)]
+struct Email {
+    address: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+struct Contact {
+    name: String,
+    email: Email,
+}
+
+#[test]
+fn transparent_struct_reconciles_as_inner_value() {
+    let contact = Contact {
+        name: "Sherlock".to_string(),
+        email: Email {
+            address: "sherlock@example.com".to_string(),
+        },
+    };
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, &contact).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "name" => { "Sherlock" },
+            "email" => { "sherlock@example.com" },
+        }
+    );
+
+    let hydrated: Contact = hydrate(&doc).unwrap();
+    assert_eq!(hydrated, contact);
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+struct Item {
+    #[key]
+    id: u64,
+    name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(transparent)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+#[test]
+fn transparent_struct_uses_inner_key() {
+    let items = vec![
+        Wrapper {
+            inner: Item {
+                id: 1,
+                name: "one".to_string(),
+            },
+        },
+        Wrapper {
+            inner: Item {
+                id: 2,
+                name: "two".to_string(),
+            },
+        },
+    ];
+    let mut doc = automerge::AutoCommit::new();
+    reconcile_prop(&mut doc, automerge::ROOT, "items", &items).unwrap();
+    let mut doc2 = doc.fork().with_actor(automerge::ActorId::random());
+
+    // Remove the first item in one doc and edit it in the other, the edit should be applied to
+    // the removed item (because keys are matched) rather than to the second item
+    reconcile_prop(&mut doc, automerge::ROOT, "items", &items[1..]).unwrap();
+    let mut edited = items.clone();
+    edited[1].inner.name = "deux".to_string();
+    reconcile_prop(&mut doc2, automerge::ROOT, "items", &edited).unwrap();
+    doc.merge(&mut doc2).unwrap();
+
+    assert_doc!(
+        doc.document(),
+        map! {
+            "items" => { list! {
+                { map! { "id" => { 2_u64 }, "name" => { "deux" } } },
+            }}
+        }
+    );
+    let hydrated: Vec<Wrapper<Item>> = hydrate_prop(&doc, &automerge::ROOT, "items").unwrap();
+    assert_eq!(hydrated, vec![edited[1].clone()]);
+}
+
+#[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+#[autosurgeon(transparent)]
+struct Celsius(f64);
+
+#[test]
+fn transparent_tuple_struct() {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile_prop(&mut doc, automerge::ROOT, "temp", Celsius(21.5)).unwrap();
+    assert_doc!(doc.document(), map! { "temp" => { 21.5 } });
+    let hydrated: Celsius = hydrate_prop(&doc, &automerge::ROOT, "temp").unwrap();
+    assert_eq!(hydrated, Celsius(21.5));
+}
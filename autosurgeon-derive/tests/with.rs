@@ -136,3 +136,46 @@ fn test_with_map_parseable_key() {
         }
     );
 }
+
+#[test]
+fn test_with_unique_list_of_unit_enums() {
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Reconcile, Hydrate)]
+    enum Flag {
+        Bold,
+        Italic,
+        Underline,
+    }
+
+    #[derive(Debug, PartialEq, Reconcile, Hydrate)]
+    struct Style {
+        #[autosurgeon(with = "autosurgeon::unique_list::sorted")]
+        flags: Vec<Flag>,
+    }
+
+    let mut doc = automerge::AutoCommit::new();
+    let style = Style {
+        flags: vec![Flag::Underline, Flag::Bold, Flag::Underline],
+    };
+    reconcile(&mut doc, &style).unwrap();
+    assert_doc!(
+        doc.document(),
+        map! {
+            "flags" => { list! { {"Bold"}, {"Underline"} } }
+        }
+    );
+
+    let flags = automerge::ReadDoc::get(&doc, &automerge::ROOT, "flags")
+        .unwrap()
+        .unwrap()
+        .1;
+    doc.insert(&flags, 0, "Italic").unwrap();
+    doc.insert(&flags, 0, "Bold").unwrap();
+
+    let style: Style = hydrate(&doc).unwrap();
+    assert_eq!(
+        style,
+        Style {
+            flags: vec![Flag::Bold, Flag::Italic, Flag::Underline]
+        }
+    );
+}
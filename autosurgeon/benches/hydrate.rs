@@ -0,0 +1,87 @@
+use automerge::{AutoCommit, ReadDoc};
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile, Text};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[derive(Reconcile, Hydrate)]
+struct Node {
+    value: i64,
+    child: Box<Option<Node>>,
+}
+
+#[derive(Reconcile, Hydrate)]
+struct Item {
+    #[key]
+    id: u64,
+    name: String,
+}
+
+#[derive(Reconcile, Hydrate)]
+struct Items {
+    items: Vec<Item>,
+}
+
+#[derive(Reconcile, Hydrate)]
+struct Document {
+    content: Text,
+}
+
+fn doc_with<R: Reconcile>(value: &R) -> AutoCommit {
+    let mut doc = AutoCommit::new();
+    reconcile(&mut doc, value).unwrap();
+    doc
+}
+
+fn bench_nested(c: &mut Criterion) {
+    let value = (0..100).fold(
+        Node {
+            value: 100,
+            child: Box::new(None),
+        },
+        |child, value| Node {
+            value,
+            child: Box::new(Some(child)),
+        },
+    );
+    let doc = doc_with(&value);
+    c.bench_function("hydrate nested 100 deep", |b| {
+        b.iter(|| hydrate::<_, Node>(black_box(&doc)).unwrap())
+    });
+}
+
+fn bench_vec(c: &mut Criterion) {
+    let value = Items {
+        items: (0..10_000)
+            .map(|id| Item {
+                id,
+                name: format!("item {}", id),
+            })
+            .collect(),
+    };
+    let doc = doc_with(&value);
+    c.bench_function("hydrate 10k keyed vec", |b| {
+        b.iter(|| hydrate::<_, Items>(black_box(&doc)).unwrap())
+    });
+
+    // The keys of every element are loaded when diffing a keyed list
+    let (_, list) = doc.get(automerge::ROOT, "items").unwrap().unwrap();
+    c.bench_function("hydrate keys of 10k keyed vec", |b| {
+        b.iter(|| {
+            for index in 0..doc.length(&list) {
+                black_box(Item::hydrate_key(&doc, &list, index.into()).unwrap());
+            }
+        })
+    });
+}
+
+fn bench_text(c: &mut Criterion) {
+    let value = Document {
+        content: Text::with_value("The quick brown fox jumps over the lazy dog. ".repeat(200)),
+    };
+    let doc = doc_with(&value);
+    c.bench_function("hydrate text", |b| {
+        b.iter(|| hydrate::<_, Document>(black_box(&doc)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_nested, bench_vec, bench_text);
+criterion_main!(benches);
@@ -0,0 +1,228 @@
+use automerge::AutoCommit;
+use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile, Text};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+#[derive(Clone, Reconcile, Hydrate)]
+struct Flat {
+    name: String,
+    description: String,
+    count: u64,
+    offset: i64,
+    ratio: f64,
+    enabled: bool,
+    tag: Option<String>,
+    a: u64,
+    b: u64,
+    c: u64,
+    d: u64,
+    e: u64,
+    f: String,
+    g: String,
+    h: String,
+    i: String,
+    j: i64,
+    k: i64,
+    l: i64,
+    m: bool,
+}
+
+fn flat() -> Flat {
+    Flat {
+        name: "flat".to_string(),
+        description: "a struct with a lot of fields".to_string(),
+        count: 1,
+        offset: -1,
+        ratio: 0.5,
+        enabled: true,
+        tag: None,
+        a: 1,
+        b: 2,
+        c: 3,
+        d: 4,
+        e: 5,
+        f: "f".to_string(),
+        g: "g".to_string(),
+        h: "h".to_string(),
+        i: "i".to_string(),
+        j: -1,
+        k: -2,
+        l: -3,
+        m: false,
+    }
+}
+
+#[derive(Clone, Reconcile, Hydrate)]
+struct Node {
+    value: i64,
+    child: Box<Option<Node>>,
+}
+
+fn nested(depth: i64) -> Node {
+    (0..depth).fold(
+        Node {
+            value: depth,
+            child: Box::new(None),
+        },
+        |child, value| Node {
+            value,
+            child: Box::new(Some(child)),
+        },
+    )
+}
+
+#[derive(Clone, Reconcile, Hydrate)]
+struct Item {
+    #[key]
+    id: u64,
+    name: String,
+}
+
+#[derive(Clone, Reconcile, Hydrate)]
+struct Items {
+    items: Vec<Item>,
+}
+
+fn items(n: u64) -> Items {
+    Items {
+        items: (0..n)
+            .map(|id| Item {
+                id,
+                name: format!("item {}", id),
+            })
+            .collect(),
+    }
+}
+
+#[derive(Clone, Reconcile, Hydrate)]
+struct Document {
+    content: Text,
+}
+
+fn doc_with<R: Reconcile>(value: &R) -> AutoCommit {
+    let mut doc = AutoCommit::new();
+    reconcile(&mut doc, value).unwrap();
+    doc.commit();
+    doc
+}
+
+fn bench_flat(c: &mut Criterion) {
+    let value = flat();
+    c.bench_function("reconcile flat struct into new doc", |b| {
+        b.iter(|| {
+            let mut doc = AutoCommit::new();
+            reconcile(&mut doc, black_box(&value)).unwrap();
+            doc
+        })
+    });
+
+    let doc = doc_with(&value);
+    let mut updated = value.clone();
+    updated.count += 1;
+    updated.tag = Some("updated".to_string());
+    c.bench_function("reconcile flat struct update", |b| {
+        b.iter_batched(
+            || doc.clone(),
+            |mut doc| {
+                reconcile(&mut doc, black_box(&updated)).unwrap();
+                doc
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_nested(c: &mut Criterion) {
+    let value = nested(100);
+    c.bench_function("reconcile nested 100 deep into new doc", |b| {
+        b.iter(|| {
+            let mut doc = AutoCommit::new();
+            reconcile(&mut doc, black_box(&value)).unwrap();
+            doc
+        })
+    });
+
+    let doc = doc_with(&value);
+    let mut updated = value.clone();
+    let mut node = &mut updated;
+    while let Some(child) = (*node.child).as_mut() {
+        node = child;
+    }
+    node.value += 1;
+    c.bench_function("reconcile nested 100 deep update innermost", |b| {
+        b.iter_batched(
+            || doc.clone(),
+            |mut doc| {
+                reconcile(&mut doc, black_box(&updated)).unwrap();
+                doc
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_keyed_vec(c: &mut Criterion) {
+    let value = items(10_000);
+    let doc = doc_with(&value);
+
+    // Remove some elements, insert some new ones, and move a block from the end to the start
+    let mut updated = value.clone();
+    let list = &mut updated.items;
+    list.retain(|item| item.id % 1000 != 0);
+    list.insert(5000, items(10_010).items.pop().unwrap());
+    let tail = list.split_off(list.len() - 100);
+    list.splice(0..0, tail);
+    c.bench_function("reconcile 10k keyed vec diff", |b| {
+        b.iter_batched(
+            || doc.clone(),
+            |mut doc| {
+                reconcile(&mut doc, black_box(&updated)).unwrap();
+                doc
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_text(c: &mut Criterion) {
+    let paragraph = "The quick brown fox jumps over the lazy dog. ".repeat(200);
+    let value = Document {
+        content: Text::with_value(&paragraph),
+    };
+    let doc = doc_with(&value);
+    let updated = paragraph.replacen("lazy", "sleepy", 1) + "The end.";
+    c.bench_function("reconcile text update", |b| {
+        b.iter_batched(
+            || (doc.clone(), hydrate::<_, Document>(&doc).unwrap()),
+            |(mut doc, mut value)| {
+                value.content.update(black_box(&updated));
+                reconcile(&mut doc, &value).unwrap();
+                doc
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_noop(c: &mut Criterion) {
+    let value = items(10_000);
+    let mut doc = doc_with(&value);
+    c.bench_function("reconcile 10k keyed vec no-op", |b| {
+        b.iter(|| reconcile(&mut doc, black_box(&value)).unwrap())
+    });
+
+    let value = flat();
+    let mut doc = doc_with(&value);
+    c.bench_function("reconcile flat struct no-op", |b| {
+        b.iter(|| reconcile(&mut doc, black_box(&value)).unwrap())
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_flat,
+    bench_nested,
+    bench_keyed_vec,
+    bench_text,
+    bench_noop
+);
+criterion_main!(benches);
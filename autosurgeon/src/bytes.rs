@@ -2,10 +2,43 @@
 //!
 //! This is necessary because otherwise we get conflicting implementations of `Reconcile` and
 //! `Hydrate` when we implement these traits for `u8` and `Vec<u8>`.
+//!
+//! Both newtypes use their contents as their [`Reconcile::Key`], so a list of them (or of structs
+//! with one of them as a `#[key]`) is matched up with the document by content, e.g. for a list of
+//! hashes:
+//!
+//! ```
+//! # use autosurgeon::{bytes::ByteVec, hydrate_prop, reconcile_prop};
+//! let mut doc = automerge::AutoCommit::new();
+//! let mut hashes: Vec<ByteVec> = vec![vec![1, 2].into(), vec![3, 4].into()];
+//! reconcile_prop(&mut doc, automerge::ROOT, "hashes", &hashes).unwrap();
+//!
+//! hashes.insert(0, vec![5, 6].into());
+//! reconcile_prop(&mut doc, automerge::ROOT, "hashes", &hashes).unwrap();
+//! let hydrated: Vec<ByteVec> = hydrate_prop(&doc, automerge::ROOT, "hashes").unwrap();
+//! assert_eq!(hydrated, hashes);
+//! ```
+
+use std::{borrow::Cow, ops::Deref};
 
-use std::ops::Deref;
+use automerge::{ScalarValue, Value};
 
-use crate::{Hydrate, HydrateError, Reconcile};
+use crate::{reconcile::LoadKey, Hydrate, HydrateError, Prop, ReadDoc, Reconcile, ReconcileError};
+
+/// Load the bytes at `prop` of `obj`, if there are any
+fn hydrate_bytes_key<D: ReadDoc>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: Prop<'_>,
+) -> Result<LoadKey<Vec<u8>>, ReconcileError> {
+    Ok(match doc.get(obj, &prop)? {
+        Some((Value::Scalar(s), _)) => match s.as_ref() {
+            ScalarValue::Bytes(b) => LoadKey::Found(b.clone()),
+            _ => LoadKey::KeyNotFound,
+        },
+        _ => LoadKey::KeyNotFound,
+    })
+}
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct ByteArray<const N: usize>([u8; N]);
@@ -29,11 +62,30 @@ impl<const N: usize> ByteArray<N> {
 }
 
 impl<const N: usize> Reconcile for ByteArray<N> {
-    type Key<'a> = crate::reconcile::NoKey;
+    type Key<'a> = Cow<'a, [u8; N]>;
 
     fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
         reconciler.bytes(self.0)
     }
+
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        LoadKey::Found(Cow::Borrowed(&self.0))
+    }
+
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, ReconcileError> {
+        Ok(match hydrate_bytes_key(doc, obj, prop)? {
+            LoadKey::Found(bytes) => match <[u8; N]>::try_from(bytes) {
+                Ok(array) => LoadKey::Found(Cow::Owned(array)),
+                Err(_) => LoadKey::KeyNotFound,
+            },
+            LoadKey::KeyNotFound => LoadKey::KeyNotFound,
+            LoadKey::NoKey => LoadKey::NoKey,
+        })
+    }
 }
 
 impl<const N: usize> Hydrate for ByteArray<N> {
@@ -82,11 +134,23 @@ impl From<ByteVec> for Vec<u8> {
 }
 
 impl Reconcile for ByteVec {
-    type Key<'a> = crate::reconcile::NoKey;
+    type Key<'a> = Cow<'a, [u8]>;
 
     fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
         reconciler.bytes(&self.0)
     }
+
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        LoadKey::Found(Cow::Borrowed(self.0.as_slice()))
+    }
+
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, ReconcileError> {
+        Ok(hydrate_bytes_key(doc, obj, prop)?.map(Cow::Owned))
+    }
 }
 
 impl Hydrate for ByteVec {
@@ -115,6 +179,216 @@ impl AsRef<Vec<u8>> for ByteVec {
     }
 }
 
+/// Derive macro adaptors for storing a `[u8; N]` field as [`automerge::ScalarValue::Bytes`]
+///
+/// `[u8; N]` implements [`Reconcile`] and [`Hydrate`] as a list of integers, a field of that type
+/// can use this module to store it as bytes instead. Such a field can also be a `#[key]`:
+///
+/// ```
+/// # use autosurgeon::{Reconcile, Hydrate};
+/// #[derive(Reconcile, Hydrate)]
+/// struct Blob {
+///     #[key]
+///     #[autosurgeon(with = "autosurgeon::bytes::array")]
+///     hash: [u8; 32],
+///     size: u64,
+/// }
+/// ```
+pub mod array {
+    use super::ByteArray;
+    use crate::{Hydrate, HydrateError, Prop, ReadDoc, Reconcile, Reconciler};
+
+    pub fn reconcile<R: Reconciler, const N: usize>(
+        bytes: &[u8; N],
+        reconciler: R,
+    ) -> Result<(), R::Error> {
+        ByteArray::from(*bytes).reconcile(reconciler)
+    }
+
+    pub fn hydrate<D: ReadDoc, const N: usize>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<[u8; N], HydrateError> {
+        ByteArray::<N>::hydrate(doc, obj, prop).map(|b| b.0)
+    }
+}
+
+/// A reference to some bytes which reconciles as [`automerge::ScalarValue::Bytes`] with the bytes as
+/// its key, for reconciling a list of byte vectors without cloning them into [`ByteVec`]s
+struct BytesRef<'a>(&'a [u8]);
+
+impl<'a> Reconcile for BytesRef<'a> {
+    type Key<'b> = Cow<'b, [u8]>;
+
+    fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        reconciler.bytes(self.0)
+    }
+
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        LoadKey::Found(Cow::Borrowed(self.0))
+    }
+
+    fn hydrate_key<'b, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'b>>, ReconcileError> {
+        Ok(hydrate_bytes_key(doc, obj, prop)?.map(Cow::Owned))
+    }
+}
+
+/// Derive macro adaptors for storing byte vectors as [`automerge::ScalarValue::Bytes`]
+///
+/// `Vec<u8>` implements [`Reconcile`] and [`Hydrate`] as a list of integers, which means that a
+/// `Vec<Vec<u8>>` is a list of lists and every change to it is diffed element by element. If the
+/// bytes are opaque (e.g. binary rows or hashes) this is wasted effort. The
+/// `#[autosurgeon(bytes)]` field attribute, which is shorthand for
+/// `#[autosurgeon(with = "autosurgeon::bytes::scalar")]`, stores each byte vector as a single
+/// bytes scalar instead. It can be used on any field whose type implements
+/// [`AsBytes`](crate::bytes::scalar::AsBytes),
+/// which includes `Vec<u8>`, `[u8; N]`, lists of either, and `Option`s of either:
+///
+/// ```
+/// # use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+/// #[derive(Debug, PartialEq, Reconcile, Hydrate)]
+/// struct Rows {
+///     #[autosurgeon(bytes)]
+///     rows: Vec<Vec<u8>>,
+///     #[autosurgeon(bytes)]
+///     checksum: Option<[u8; 4]>,
+/// }
+///
+/// let mut table = Rows {
+///     rows: vec![vec![1, 2], vec![3, 4], vec![5, 6]],
+///     checksum: None,
+/// };
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile(&mut doc, &table).unwrap();
+///
+/// table.rows.remove(0);
+/// table.checksum = Some([1, 2, 3, 4]);
+/// reconcile(&mut doc, &table).unwrap();
+/// assert_eq!(hydrate::<_, Rows>(&doc).unwrap(), table);
+/// ```
+///
+/// The elements of a list of byte vectors use their contents as their key, so removing or
+/// inserting rows only deletes or inserts those rows in the document.
+pub mod scalar {
+    use automerge::{ScalarValue, Value};
+
+    use super::{ByteArray, ByteVec, BytesRef};
+    use crate::{Hydrate, HydrateError, Prop, ReadDoc, Reconcile, Reconciler};
+
+    /// A type which the [`scalar`](crate::bytes::scalar) adaptors can store as one or more
+    /// [`automerge::ScalarValue::Bytes`]
+    pub trait AsBytes: Sized {
+        fn reconcile_as_bytes<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error>;
+
+        fn hydrate_as_bytes<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<Self, HydrateError>;
+    }
+
+    impl AsBytes for Vec<u8> {
+        fn reconcile_as_bytes<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            reconciler.bytes(self)
+        }
+
+        fn hydrate_as_bytes<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<Self, HydrateError> {
+            ByteVec::hydrate(doc, obj, prop).map(Vec::from)
+        }
+    }
+
+    impl<const N: usize> AsBytes for [u8; N] {
+        fn reconcile_as_bytes<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            reconciler.bytes(self)
+        }
+
+        fn hydrate_as_bytes<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<Self, HydrateError> {
+            ByteArray::<N>::hydrate(doc, obj, prop).map(|b| b.0)
+        }
+    }
+
+    impl AsBytes for Vec<Vec<u8>> {
+        fn reconcile_as_bytes<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+            self.iter()
+                .map(|b| BytesRef(b.as_slice()))
+                .collect::<Vec<_>>()
+                .reconcile(reconciler)
+        }
+
+        fn hydrate_as_bytes<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<Self, HydrateError> {
+            let rows: Vec<ByteVec> = Vec::hydrate(doc, obj, prop)?;
+            Ok(rows.into_iter().map(Vec::from).collect())
+        }
+    }
+
+    impl<const N: usize> AsBytes for Vec<[u8; N]> {
+        fn reconcile_as_bytes<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+            self.iter()
+                .map(|b| BytesRef(b.as_slice()))
+                .collect::<Vec<_>>()
+                .reconcile(reconciler)
+        }
+
+        fn hydrate_as_bytes<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<Self, HydrateError> {
+            let rows: Vec<ByteArray<N>> = Vec::hydrate(doc, obj, prop)?;
+            Ok(rows.into_iter().map(|b| b.0).collect())
+        }
+    }
+
+    impl<T: AsBytes> AsBytes for Option<T> {
+        fn reconcile_as_bytes<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            match self {
+                Some(value) => value.reconcile_as_bytes(reconciler),
+                None => reconciler.none(),
+            }
+        }
+
+        fn hydrate_as_bytes<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<Self, HydrateError> {
+            match doc.get(obj, &prop)? {
+                Some((Value::Scalar(s), _)) if matches!(s.as_ref(), ScalarValue::Null) => Ok(None),
+                _ => T::hydrate_as_bytes(doc, obj, prop).map(Some),
+            }
+        }
+    }
+
+    pub fn reconcile<T: AsBytes, R: Reconciler>(value: &T, reconciler: R) -> Result<(), R::Error> {
+        value.reconcile_as_bytes(reconciler)
+    }
+
+    pub fn hydrate<D: ReadDoc, T: AsBytes>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<T, HydrateError> {
+        T::hydrate_as_bytes(doc, obj, prop)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{ByteArray, ByteVec};
@@ -141,4 +415,40 @@ mod tests {
         let result: ByteVec = hydrate_prop(&doc, am::ROOT, "values").unwrap();
         assert_eq!(result, value);
     }
+
+    #[test]
+    fn keys() {
+        use crate::reconcile::LoadKey;
+        use crate::Reconcile;
+
+        let mut doc = am::AutoCommit::new();
+        let blobs: Vec<ByteVec> = vec![vec![1_u8].into(), vec![2_u8, 3].into()];
+        let hashes: Vec<ByteArray<2>> = vec![[1_u8, 2].into(), [3_u8, 4].into()];
+        reconcile_prop(&mut doc, am::ROOT, "blobs", &blobs).unwrap();
+        reconcile_prop(&mut doc, am::ROOT, "hashes", &hashes).unwrap();
+
+        let (_, list) = am::ReadDoc::get(&doc, &am::ROOT, "blobs").unwrap().unwrap();
+        match (
+            ByteVec::hydrate_key(&doc, &list, 1_usize.into()).unwrap(),
+            blobs[1].key(),
+        ) {
+            (LoadKey::Found(hydrated), LoadKey::Found(key)) => assert_eq!(hydrated, key),
+            _ => panic!("expected both keys to be found"),
+        }
+        let (_, list) = am::ReadDoc::get(&doc, &am::ROOT, "hashes")
+            .unwrap()
+            .unwrap();
+        match (
+            ByteArray::<2>::hydrate_key(&doc, &list, 0_usize.into()).unwrap(),
+            hashes[0].key(),
+        ) {
+            (LoadKey::Found(hydrated), LoadKey::Found(key)) => assert_eq!(hydrated, key),
+            _ => panic!("expected both keys to be found"),
+        }
+        // A byte array of the wrong length doesn't have a key
+        assert!(matches!(
+            ByteArray::<3>::hydrate_key(&doc, &list, 0_usize.into()).unwrap(),
+            LoadKey::KeyNotFound
+        ));
+    }
 }
@@ -0,0 +1,191 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
+
+use crate::{Doc, Hydrate, HydrateError, ReadDoc, Reconcile, ReconcileError};
+
+/// A root value which remembers what it looked like when it was last synchronised with a document
+///
+/// Reconciling a large value walks the whole document, even if nothing has changed. `Cached`
+/// records a hash of the value and the heads of the document when it is hydrated or reconciled,
+/// and [`Cached::reconcile`] skips reconciliation entirely if neither has changed since. This is
+/// useful for loops which periodically save the current state of an application.
+///
+/// Note that the heads of an [`automerge::AutoCommit`] or [`automerge::transaction::Transaction`]
+/// don't include changes which haven't been committed yet, so changes made to the document
+/// outside of `Cached` must be committed before calling [`Cached::reconcile`] for them to be
+/// noticed. Likewise committing the changes made by [`Cached::reconcile`] changes the heads, so
+/// call [`Cached::committed`] after committing them, otherwise the next call to
+/// [`Cached::reconcile`] will reconcile again.
+///
+/// # Example
+///
+/// ```rust
+/// # use autosurgeon::{reconcile, Cached, Hydrate, Reconcile};
+/// #[derive(Hash, Hydrate, Reconcile)]
+/// struct Todos {
+///     items: Vec<String>,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile(&mut doc, Todos { items: vec!["write docs".to_string()] }).unwrap();
+/// doc.commit();
+///
+/// let mut todos: Cached<Todos> = Cached::hydrate(&doc).unwrap();
+/// // Nothing has changed, so this doesn't touch the document
+/// assert!(!todos.reconcile(&mut doc).unwrap());
+///
+/// todos.items.push("write tests".to_string());
+/// assert!(todos.reconcile(&mut doc).unwrap());
+/// doc.commit();
+/// todos.committed(&doc);
+/// assert!(!todos.reconcile(&mut doc).unwrap());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cached<T> {
+    value: T,
+    snapshot: Option<Snapshot>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Snapshot {
+    heads: Vec<automerge::ChangeHash>,
+    hash: u64,
+}
+
+impl<T> Cached<T> {
+    /// Wrap a value which hasn't been hydrated from or reconciled with a document yet
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            snapshot: None,
+        }
+    }
+
+    /// Unwrap the value, discarding the record of when it was last synchronised
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Record that the changes made by the last call to [`Cached::reconcile`] have been committed
+    /// to `doc`
+    ///
+    /// This should be called straight after committing, as any other changes made to `doc`
+    /// between the reconcile and this call will be ignored by the next [`Cached::reconcile`].
+    pub fn committed<D: ReadDoc>(&mut self, doc: &D) {
+        if let Some(snapshot) = &mut self.snapshot {
+            snapshot.heads = doc.get_heads();
+        }
+    }
+}
+
+impl<T: Hash> Cached<T> {
+    fn snapshot<D: ReadDoc>(&self, doc: &D) -> Snapshot {
+        let mut hasher = DefaultHasher::new();
+        self.value.hash(&mut hasher);
+        Snapshot {
+            heads: doc.get_heads(),
+            hash: hasher.finish(),
+        }
+    }
+}
+
+impl<T: Hydrate + Hash> Cached<T> {
+    /// Hydrate a `T` from the root of `doc`, recording the heads of `doc`
+    pub fn hydrate<D: ReadDoc>(doc: &D) -> Result<Self, HydrateError> {
+        let mut result = Self::new(crate::hydrate(doc)?);
+        result.snapshot = Some(result.snapshot(doc));
+        Ok(result)
+    }
+}
+
+impl<T: Reconcile + Hash> Cached<T> {
+    /// Reconcile the value with the root of `doc`, unless neither it nor the document have
+    /// changed since it was last hydrated or reconciled
+    ///
+    /// Returns `true` if the value was reconciled and `false` if reconciliation was skipped.
+    pub fn reconcile<D: Doc>(&mut self, doc: &mut D) -> Result<bool, ReconcileError> {
+        if self.snapshot.as_ref() == Some(&self.snapshot(doc)) {
+            return Ok(false);
+        }
+        crate::reconcile(doc, &self.value)?;
+        self.snapshot = Some(self.snapshot(doc));
+        Ok(true)
+    }
+}
+
+impl<T> Deref for Cached<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Cached<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use automerge::transaction::Transactable;
+
+    use super::Cached;
+
+    #[test]
+    fn reconcile_skipped_when_nothing_changed() {
+        let mut doc = automerge::AutoCommit::new();
+        crate::reconcile(&mut doc, BTreeMap::from([("a".to_string(), 1)])).unwrap();
+        doc.commit();
+
+        let mut value: Cached<BTreeMap<String, i64>> = Cached::hydrate(&doc).unwrap();
+        assert!(!value.reconcile(&mut doc).unwrap());
+
+        // A local change is reconciled once
+        value.insert("b".to_string(), 2);
+        assert!(value.reconcile(&mut doc).unwrap());
+        doc.commit();
+        value.committed(&doc);
+        assert!(!value.reconcile(&mut doc).unwrap());
+    }
+
+    #[test]
+    fn reconcile_not_skipped_after_uncommitted_changes_are_committed() {
+        let mut doc = automerge::AutoCommit::new();
+        let mut value = Cached::new(BTreeMap::from([("a".to_string(), 1)]));
+        assert!(value.reconcile(&mut doc).unwrap());
+
+        // Without `committed` we can't tell the new heads apart from someone else's changes
+        doc.commit();
+        assert!(value.reconcile(&mut doc).unwrap());
+        assert!(!value.reconcile(&mut doc).unwrap());
+    }
+
+    #[test]
+    fn reconcile_not_skipped_when_document_changed() {
+        let mut doc = automerge::AutoCommit::new();
+        crate::reconcile(&mut doc, BTreeMap::from([("a".to_string(), 1)])).unwrap();
+        doc.commit();
+
+        let mut value: Cached<BTreeMap<String, i64>> = Cached::hydrate(&doc).unwrap();
+        doc.put(automerge::ROOT, "a", 5).unwrap();
+        doc.commit();
+
+        assert!(value.reconcile(&mut doc).unwrap());
+        let restored: BTreeMap<String, i64> = crate::hydrate(&doc).unwrap();
+        assert_eq!(restored, BTreeMap::from([("a".to_string(), 1)]));
+    }
+
+    #[test]
+    fn new_values_are_always_reconciled() {
+        let mut doc = automerge::AutoCommit::new();
+        let mut value = Cached::new(BTreeMap::from([("a".to_string(), 1)]));
+        assert!(value.reconcile(&mut doc).unwrap());
+    }
+}
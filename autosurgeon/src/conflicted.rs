@@ -0,0 +1,215 @@
+use automerge::{ActorId, ObjType, Value};
+
+use crate::{reconcile::LoadKey, Hydrate, HydrateError, Prop, ReadDoc, Reconcile, Reconciler};
+
+/// A value along with any values it conflicts with
+///
+/// When two peers concurrently set the same property automerge keeps both values and picks one
+/// of them as the winner, which is the one you get when hydrating most types. Hydrating a
+/// `Conflicted<T>` instead hydrates every one of the conflicting values, along with the ID of the
+/// operation which set it, so that you can show the conflict to the user.
+///
+/// ```rust
+/// # use autosurgeon::{hydrate_prop, reconcile_prop, Conflicted};
+/// # use automerge::ActorId;
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile_prop(&mut doc, automerge::ROOT, "title", "Draft").unwrap();
+///
+/// let mut doc2 = doc.fork().with_actor(ActorId::random());
+/// reconcile_prop(&mut doc2, automerge::ROOT, "title", "Final").unwrap();
+/// reconcile_prop(&mut doc, automerge::ROOT, "title", "Final draft").unwrap();
+/// doc.merge(&mut doc2).unwrap();
+///
+/// let title: Conflicted<String> = hydrate_prop(&doc, &automerge::ROOT, "title").unwrap();
+/// assert!(title.is_conflicted());
+/// let mut values: Vec<_> = title.values().iter().map(|c| c.value.as_str()).collect();
+/// values.sort();
+/// assert_eq!(values, vec!["Final", "Final draft"]);
+/// ```
+///
+/// The conflicting values are hydrated directly from their automerge value using the
+/// `hydrate_*` methods of [`Hydrate`], so a type which overrides [`Hydrate::hydrate`] will not
+/// hydrate correctly as anything other than the winner.
+///
+/// Reconciling a `Conflicted<T>` reconciles the winner. For scalar values this resolves the
+/// conflict, for objects this updates the winning object and leaves the conflict in place.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflicted<T> {
+    // In the order returned by automerge, so the winner is last. Never empty.
+    values: Vec<Conflict<T>>,
+}
+
+/// One of the values in a [`Conflicted`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict<T> {
+    pub value: T,
+    /// The ID of the operation which set this value
+    pub id: automerge::ObjId,
+}
+
+impl<T> Conflict<T> {
+    /// The actor which set this value
+    pub fn actor(&self) -> Option<&ActorId> {
+        match &self.id {
+            automerge::ObjId::Id(_, actor, _) => Some(actor),
+            automerge::ObjId::Root => None,
+        }
+    }
+
+    /// The counter of the operation which set this value
+    pub fn counter(&self) -> Option<u64> {
+        match &self.id {
+            automerge::ObjId::Id(counter, _, _) => Some(*counter),
+            automerge::ObjId::Root => None,
+        }
+    }
+}
+
+impl<T> Conflicted<T> {
+    /// The value automerge picked as the winner
+    pub fn winner(&self) -> &T {
+        &self.values[self.values.len() - 1].value
+    }
+
+    pub fn into_winner(self) -> T {
+        self.values.into_iter().last().unwrap().value
+    }
+
+    /// Whether there is more than one value
+    pub fn is_conflicted(&self) -> bool {
+        self.values.len() > 1
+    }
+
+    /// Every value, in the order automerge sorts them, so the winner is last
+    pub fn values(&self) -> &[Conflict<T>] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<Conflict<T>> {
+        self.values
+    }
+}
+
+impl<T: Reconcile> Reconcile for Conflicted<T> {
+    type Key<'a> = T::Key<'a>;
+
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.winner().reconcile(reconciler)
+    }
+
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+        T::hydrate_key(doc, obj, prop)
+    }
+
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        self.winner().key()
+    }
+}
+
+impl<T: Hydrate> Hydrate for Conflicted<T> {
+    fn hydrate<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<Self, HydrateError> {
+        let values = doc
+            .get_all(obj, &prop)?
+            .into_iter()
+            .map(|(value, id)| {
+                let value = match value {
+                    Value::Object(ObjType::Map | ObjType::Table) => T::hydrate_map(doc, &id),
+                    Value::Object(ObjType::List) => T::hydrate_seq(doc, &id),
+                    Value::Object(ObjType::Text) => T::hydrate_text(doc, &id),
+                    #[allow(unreachable_patterns)]
+                    Value::Object(obj_type) => T::hydrate_unknown_object(doc, &id, obj_type),
+                    Value::Scalar(v) => T::hydrate_scalar(v),
+                }?;
+                Ok(Conflict { value, id })
+            })
+            .collect::<Result<Vec<_>, HydrateError>>()?;
+        if values.is_empty() {
+            return Err(HydrateError::unexpected(
+                "a value",
+                "nothing at all".to_string(),
+            ));
+        }
+        Ok(Self { values })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::{transaction::Transactable, ActorId};
+
+    use super::Conflicted;
+    use crate::{hydrate_prop, reconcile_prop};
+
+    #[test]
+    fn hydrates_every_conflicting_value() {
+        let mut doc = automerge::AutoCommit::new().with_actor(ActorId::from(vec![1]));
+        reconcile_prop(&mut doc, automerge::ROOT, "value", vec![1_u64]).unwrap();
+
+        let mut doc2 = doc.fork().with_actor(ActorId::from(vec![2]));
+        reconcile_prop(&mut doc2, automerge::ROOT, "value", "two").unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "value", "one").unwrap();
+        doc.merge(&mut doc2).unwrap();
+
+        let value: Conflicted<String> = hydrate_prop(&doc, &automerge::ROOT, "value").unwrap();
+        assert!(value.is_conflicted());
+        assert_eq!(value.winner(), "two");
+        let values = value
+            .values()
+            .iter()
+            .map(|c| (c.value.as_str(), c.actor().unwrap().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                ("one", ActorId::from(vec![1])),
+                ("two", ActorId::from(vec![2]))
+            ]
+        );
+
+        // Reconciling the winner resolves the conflict
+        reconcile_prop(&mut doc, automerge::ROOT, "value", &value).unwrap();
+        let value: Conflicted<String> = hydrate_prop(&doc, &automerge::ROOT, "value").unwrap();
+        assert!(!value.is_conflicted());
+        assert_eq!(value.into_winner(), "two");
+    }
+
+    #[test]
+    fn hydrates_conflicting_objects() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "list", vec![0_u64]).unwrap();
+        let mut doc2 = doc.fork().with_actor(ActorId::random());
+        // Replacing a list with a fresh list conflicts with a concurrent fresh list
+        doc.put_object(automerge::ROOT, "list", automerge::ObjType::List)
+            .unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "list", vec![1_u64]).unwrap();
+        doc2.put_object(automerge::ROOT, "list", automerge::ObjType::List)
+            .unwrap();
+        reconcile_prop(&mut doc2, automerge::ROOT, "list", vec![2_u64]).unwrap();
+        doc.merge(&mut doc2).unwrap();
+
+        let list: Conflicted<Vec<u64>> = hydrate_prop(&doc, &automerge::ROOT, "list").unwrap();
+        let mut values = list
+            .into_values()
+            .into_iter()
+            .map(|c| c.value)
+            .collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(values, vec![vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn missing_value_is_an_error() {
+        let doc = automerge::AutoCommit::new();
+        assert!(
+            hydrate_prop::<_, Conflicted<String>, _, _>(&doc, &automerge::ROOT, "missing").is_err()
+        );
+    }
+}
@@ -0,0 +1,21 @@
+//! Small building blocks for persisting documents and passing them around
+//!
+//! Most applications which use autosurgeon end up writing the same glue: load a document from a
+//! file and save it whenever the value changes, accept a document in an HTTP request, or keep
+//! documents in a database keyed by some ID. The modules in here are tested versions of that glue,
+//! they are deliberately small so that if they don't quite fit your application you can copy them
+//! and change them.
+//!
+//! This module is only available with the `contrib` feature. The adapters for other crates each
+//! have their own feature:
+//!
+//! * [`file`] - Keep a value in a document on disk, saving it whenever it changes
+//! * `axum` (with the `axum` feature) - An extractor which hydrates a value from a posted
+//!   document and a response which sends a value as a document
+//! * `sqlite` (with the `rusqlite` feature) - A store of documents in a sqlite table, keyed by
+//!   document ID
+#[cfg(feature = "axum")]
+pub mod axum;
+pub mod file;
+#[cfg(feature = "rusqlite")]
+pub mod sqlite;
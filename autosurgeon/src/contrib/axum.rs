@@ -0,0 +1,81 @@
+//! Send and receive values as automerge documents in [`axum`] handlers
+//!
+//! [`Automerge<T>`] is both an extractor, which loads the request body as a document and hydrates
+//! a `T` from it, and a response, which reconciles a `T` into a new document and sends the saved
+//! document:
+//!
+//! ```rust
+//! # use autosurgeon::{contrib::axum::Automerge, Hydrate, Reconcile};
+//! #[derive(Hydrate, Reconcile)]
+//! struct Todo {
+//!     title: String,
+//!     done: bool,
+//! }
+//!
+//! async fn complete(Automerge(mut todo): Automerge<Todo>) -> Automerge<Todo> {
+//!     todo.done = true;
+//!     Automerge(todo)
+//! }
+//!
+//! let app: axum::Router = axum::Router::new().route("/complete", axum::routing::post(complete));
+//! ```
+//!
+//! The request body is read with axum's [`Bytes`] extractor, so the default body size limit
+//! applies. The content type of the request is not checked.
+use axum::{
+    body::Bytes,
+    extract::{rejection::BytesRejection, FromRequest, Request},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::{hydrate_from_bytes, to_new_document, Hydrate, HydrateError, Reconcile};
+
+/// The content type of responses containing a document
+pub const CONTENT_TYPE: &str = "application/octet-stream";
+
+/// An extractor and response for values stored in automerge documents
+///
+/// See the [module documentation](self).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Automerge<T>(pub T);
+
+/// Why an [`Automerge`] extractor rejected a request
+#[derive(Debug, thiserror::Error)]
+pub enum AutomergeRejection {
+    /// The body could not be read
+    #[error(transparent)]
+    Body(#[from] BytesRejection),
+    /// The body is not a document or the document does not contain a `T`
+    #[error("unable to hydrate the request body: {0}")]
+    Hydrate(#[from] HydrateError),
+}
+
+impl IntoResponse for AutomergeRejection {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Body(rejection) => rejection.into_response(),
+            Self::Hydrate(_) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()).into_response()
+            }
+        }
+    }
+}
+
+impl<T: Hydrate, S: Send + Sync> FromRequest<S> for Automerge<T> {
+    type Rejection = AutomergeRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let bytes = Bytes::from_request(req, state).await?;
+        Ok(Self(hydrate_from_bytes(&bytes)?))
+    }
+}
+
+impl<T: Reconcile> IntoResponse for Automerge<T> {
+    fn into_response(self) -> Response {
+        match to_new_document(&self.0) {
+            Ok(mut doc) => ([(header::CONTENT_TYPE, CONTENT_TYPE)], doc.save()).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Keep a value in a document on disk
+//!
+//! [`FileDocument`] loads a document from a file, hydrates a value from it and then writes the
+//! document back to the file every time an update changes it:
+//!
+//! ```rust
+//! # use autosurgeon::{contrib::file::FileDocument, Hydrate, Reconcile};
+//! #[derive(Debug, Default, Hydrate, Reconcile)]
+//! struct Settings {
+//!     theme: String,
+//!     font_size: u32,
+//! }
+//!
+//! # let dir = std::env::temp_dir().join(format!("autosurgeon-doc-{}", std::process::id()));
+//! # std::fs::create_dir_all(&dir).unwrap();
+//! let path = dir.join("settings.automerge");
+//! let mut settings = FileDocument::open_or_create(&path, Settings::default).unwrap();
+//! settings.update(|s| s.font_size = 14).unwrap();
+//!
+//! let settings = FileDocument::<Settings>::open(&path).unwrap();
+//! assert_eq!(settings.get().font_size, 14);
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+//!
+//! Files are written by writing a temporary file next to the target and renaming it over the
+//! target, so a crash part way through a save leaves the previous version of the file in place.
+use std::path::{Path, PathBuf};
+
+use automerge::AutoCommit;
+
+use crate::{hydrate, reconcile, Hydrate, HydrateError, Reconcile, ReconcileError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum FileError {
+    #[error("error reading or writing {}: {error}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+    #[error("unable to load the document: {0}")]
+    Load(#[from] automerge::AutomergeError),
+    #[error(transparent)]
+    Hydrate(#[from] HydrateError),
+    #[error(transparent)]
+    Reconcile(#[from] ReconcileError),
+}
+
+/// Load the document in the file at `path` and hydrate a `T` from it
+pub fn load<T: Hydrate, P: AsRef<Path>>(path: P) -> Result<(AutoCommit, T), FileError> {
+    let doc = read(path.as_ref())?;
+    let value = hydrate(&doc)?;
+    Ok((doc, value))
+}
+
+fn read(path: &Path) -> Result<AutoCommit, FileError> {
+    let bytes = std::fs::read(path).map_err(|error| FileError::Io {
+        path: path.to_owned(),
+        error,
+    })?;
+    Ok(AutoCommit::load(&bytes)?)
+}
+
+/// Save `doc` to the file at `path`, replacing whatever is there
+///
+/// The document is written to a temporary file in the same directory which is then renamed to
+/// `path`.
+pub fn save<P: AsRef<Path>>(doc: &mut AutoCommit, path: P) -> Result<(), FileError> {
+    let path = path.as_ref();
+    let io_err = |error| FileError::Io {
+        path: path.to_owned(),
+        error,
+    };
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    std::fs::write(&tmp, doc.save()).map_err(io_err)?;
+    std::fs::rename(&tmp, path).map_err(io_err)
+}
+
+/// A value which is kept in a document in a file
+///
+/// See the [module documentation](self).
+#[derive(Debug)]
+pub struct FileDocument<T> {
+    path: PathBuf,
+    doc: AutoCommit,
+    value: T,
+}
+
+impl<T: Hydrate + Reconcile> FileDocument<T> {
+    /// Open the document at `path`, which must exist
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let path = path.as_ref().to_owned();
+        let (doc, value) = load(&path)?;
+        Ok(Self { path, doc, value })
+    }
+
+    /// Open the document at `path`, or if there is no file there create one containing `init()`
+    pub fn open_or_create<P: AsRef<Path>, F: FnOnce() -> T>(
+        path: P,
+        init: F,
+    ) -> Result<Self, FileError> {
+        let path = path.as_ref();
+        if path.exists() {
+            return Self::open(path);
+        }
+        let value = init();
+        let mut doc = AutoCommit::new();
+        reconcile(&mut doc, &value)?;
+        save(&mut doc, path)?;
+        Ok(Self {
+            path: path.to_owned(),
+            doc,
+            value,
+        })
+    }
+
+    /// The current value
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// The document the value is stored in
+    pub fn doc(&self) -> &AutoCommit {
+        &self.doc
+    }
+
+    /// The file the document is saved to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Modify the value with `f`, then reconcile it and save the document if anything changed
+    ///
+    /// Afterwards the value is hydrated from the document again, so that values which track
+    /// their edits (like [`Text`](crate::Text)) can be updated again. If reconciling fails then
+    /// nothing is written and the changes made by `f` are discarded.
+    pub fn update<F: FnOnce(&mut T) -> R, R>(&mut self, f: F) -> Result<R, FileError> {
+        let result = f(&mut self.value);
+        if let Err(e) = reconcile(&mut self.doc, &self.value) {
+            self.doc.rollback();
+            self.value = hydrate(&self.doc)?;
+            return Err(e.into());
+        }
+        let changed = self.doc.commit().is_some();
+        self.value = hydrate(&self.doc)?;
+        if changed {
+            save(&mut self.doc, &self.path)?;
+        }
+        Ok(result)
+    }
+
+    /// Merge the changes in the file into this document, e.g. because another process wrote to it
+    ///
+    /// The value is hydrated again from the merged document.
+    pub fn reload(&mut self) -> Result<(), FileError> {
+        let mut on_disk = read(&self.path)?;
+        self.doc.merge(&mut on_disk)?;
+        self.value = hydrate(&self.doc)?;
+        Ok(())
+    }
+
+    /// The value, discarding the document
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::FileDocument;
+    use crate::{reconcile, Text};
+
+    // The derive macros can't be used inside this crate, so notes are a map from title to body
+    type Notes = BTreeMap<String, Text>;
+
+    struct TempDir(std::path::PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "autosurgeon-contrib-{}-{}",
+                name,
+                std::process::id()
+            ));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn body<'a>(notes: &'a FileDocument<Notes>, title: &str) -> &'a str {
+        notes.get()[title].as_str()
+    }
+
+    #[test]
+    fn updates_are_saved() {
+        let dir = TempDir::new("updates");
+        let path = dir.0.join("notes.automerge");
+        let mut notes = FileDocument::open_or_create(&path, Notes::new).unwrap();
+        notes
+            .update(|n| n.insert("shopping".to_string(), Text::with_value("eggs")))
+            .unwrap();
+        // Text tracks its edits so this only works if the value was hydrated after the last update
+        notes
+            .update(|n| n.get_mut("shopping").unwrap().splice(4, 0, ", milk"))
+            .unwrap();
+        notes
+            .update(|n| n.get_mut("shopping").unwrap().splice(0, 0, "bread, "))
+            .unwrap();
+
+        let reopened = FileDocument::<Notes>::open(&path).unwrap();
+        assert_eq!(body(&reopened, "shopping"), "bread, eggs, milk");
+    }
+
+    #[test]
+    fn unchanged_values_are_not_saved() {
+        let dir = TempDir::new("unchanged");
+        let path = dir.0.join("notes.automerge");
+        let mut notes = FileDocument::open_or_create(&path, Notes::new).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        notes.update(|n| n.retain(|_, _| true)).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn reload_merges_changes_from_the_file() {
+        let dir = TempDir::new("reload");
+        let path = dir.0.join("notes.automerge");
+        let mut notes = FileDocument::open_or_create(&path, Notes::new).unwrap();
+
+        // Another process forks the document and saves it after we have saved a change
+        let mut other = notes.doc().clone().with_actor(automerge::ActorId::random());
+        let mut value = notes.get().clone();
+        notes
+            .update(|n| n.insert("local".to_string(), Text::with_value("one")))
+            .unwrap();
+        value.insert("remote".to_string(), Text::with_value("two"));
+        reconcile(&mut other, &value).unwrap();
+        super::save(&mut other, &path).unwrap();
+
+        notes.reload().unwrap();
+        assert_eq!(body(&notes, "local"), "one");
+        assert_eq!(body(&notes, "remote"), "two");
+    }
+}
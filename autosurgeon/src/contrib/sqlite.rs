@@ -0,0 +1,162 @@
+//! Keep documents in a sqlite table, keyed by document ID
+//!
+//! [`DocumentStore`] stores each document as a row in a table called
+//! [`autosurgeon_documents`](DocumentStore::TABLE), creating the table if it doesn't exist. Values
+//! are written by reconciling them into the document which is already stored under their ID, so
+//! writing a value only records what changed and the stored document keeps its history:
+//!
+//! ```rust
+//! # use autosurgeon::{contrib::sqlite::DocumentStore, Hydrate, Reconcile};
+//! #[derive(Debug, PartialEq, Hydrate, Reconcile)]
+//! struct Profile {
+//!     name: String,
+//!     visits: u64,
+//! }
+//!
+//! let conn = rusqlite::Connection::open_in_memory().unwrap();
+//! let mut store = DocumentStore::new(conn).unwrap();
+//! store
+//!     .put("alex", &Profile { name: "Alex".to_string(), visits: 0 })
+//!     .unwrap();
+//! store.update("alex", |p: &mut Profile| p.visits += 1).unwrap();
+//!
+//! let profile: Profile = store.get("alex").unwrap().unwrap();
+//! assert_eq!(profile.visits, 1);
+//! ```
+//!
+//! Writes happen in an immediate transaction, so concurrent writers to the same database don't
+//! lose each other's changes.
+use automerge::AutoCommit;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+
+use crate::{hydrate, reconcile, Hydrate, HydrateError, Reconcile, ReconcileError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("unable to load the document: {0}")]
+    Load(#[from] automerge::AutomergeError),
+    #[error(transparent)]
+    Hydrate(#[from] HydrateError),
+    #[error(transparent)]
+    Reconcile(#[from] ReconcileError),
+}
+
+/// A store of documents in a sqlite table
+///
+/// See the [module documentation](self).
+#[derive(Debug)]
+pub struct DocumentStore {
+    conn: Connection,
+}
+
+impl DocumentStore {
+    /// The name of the table documents are stored in
+    pub const TABLE: &'static str = "autosurgeon_documents";
+
+    /// Store documents using `conn`, creating [`Self::TABLE`] if it doesn't exist
+    pub fn new(conn: Connection) -> Result<Self, StoreError> {
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY NOT NULL, bytes BLOB NOT NULL)",
+            Self::TABLE
+        ))?;
+        Ok(Self { conn })
+    }
+
+    /// The underlying connection
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn into_inner(self) -> Connection {
+        self.conn
+    }
+
+    /// Load the document stored under `id`, if there is one
+    pub fn load(&self, id: &str) -> Result<Option<AutoCommit>, StoreError> {
+        load(&self.conn, id)
+    }
+
+    /// Store `doc` under `id`, replacing any document which is already there
+    pub fn save(&self, id: &str, doc: &mut AutoCommit) -> Result<(), StoreError> {
+        save(&self.conn, id, doc)
+    }
+
+    /// Hydrate the value stored under `id`, if there is one
+    pub fn get<T: Hydrate>(&self, id: &str) -> Result<Option<T>, StoreError> {
+        match self.load(id)? {
+            Some(doc) => Ok(Some(hydrate(&doc)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Reconcile `value` into the document stored under `id`, creating the document if there
+    /// isn't one
+    pub fn put<T: Reconcile>(&mut self, id: &str, value: &T) -> Result<(), StoreError> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut doc = load(&tx, id)?.unwrap_or_default();
+        reconcile(&mut doc, value)?;
+        save(&tx, id, &mut doc)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Hydrate the value stored under `id`, modify it with `f` and reconcile it back
+    ///
+    /// Returns `None` without calling `f` if there is no document stored under `id`.
+    pub fn update<T, F, R>(&mut self, id: &str, f: F) -> Result<Option<R>, StoreError>
+    where
+        T: Hydrate + Reconcile,
+        F: FnOnce(&mut T) -> R,
+    {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let Some(mut doc) = load(&tx, id)? else {
+            return Ok(None);
+        };
+        let mut value: T = hydrate(&doc)?;
+        let result = f(&mut value);
+        reconcile(&mut doc, &value)?;
+        if doc.commit().is_some() {
+            save(&tx, id, &mut doc)?;
+        }
+        tx.commit()?;
+        Ok(Some(result))
+    }
+
+    /// Delete the document stored under `id`, returning whether there was one
+    pub fn delete(&self, id: &str) -> Result<bool, StoreError> {
+        let deleted = self.conn.execute(
+            &format!("DELETE FROM {} WHERE id = ?1", Self::TABLE),
+            params![id],
+        )?;
+        Ok(deleted > 0)
+    }
+}
+
+fn load(conn: &Connection, id: &str) -> Result<Option<AutoCommit>, StoreError> {
+    let bytes: Option<Vec<u8>> = conn
+        .query_row(
+            &format!("SELECT bytes FROM {} WHERE id = ?1", DocumentStore::TABLE),
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(bytes.map(|bytes| AutoCommit::load(&bytes)).transpose()?)
+}
+
+fn save(conn: &Connection, id: &str, doc: &mut AutoCommit) -> Result<(), StoreError> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (id, bytes) VALUES (?1, ?2) \
+             ON CONFLICT(id) DO UPDATE SET bytes = excluded.bytes",
+            DocumentStore::TABLE
+        ),
+        params![id, doc.save()],
+    )?;
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use crate::{reconcile::CounterReconciler, Hydrate, Reconcile};
+use automerge::{ScalarValue, Value};
+
+use crate::{reconcile::CounterReconciler, Hydrate, HydrateError, Prop, ReadDoc, Reconcile};
 
 /// A type which reconciles to an [`automerge::ScalarValue::Counter`]
 ///
@@ -33,6 +35,32 @@ use crate::{reconcile::CounterReconciler, Hydrate, Reconcile};
 /// let stats: Stats = hydrate(&doc).unwrap();
 /// assert_eq!(stats.num_clicks.value(), 8);
 /// ```
+///
+/// # Reconciliation
+///
+/// A `Counter` created with [`Counter::with_value`] (or [`Default`]) always sets the counter in
+/// the document to its value. A `Counter` which was hydrated from a document instead records the
+/// increments made to it, and reconciling it increments whatever counter is at the same location
+/// in the document. If there is no counter at that location, for example because the counter is
+/// being inserted into a new index in a sequence or the location currently holds some other
+/// value (such as the `null` of a `None`), the full value of the counter is written instead.
+///
+/// A peer which doesn't know that a value is a counter may overwrite it with a plain integer, after
+/// which concurrent increments from other peers would be lost. A `Counter` can be hydrated from
+/// an integer, and reconciling it (or calling [`CounterReconciler::increment`] on an integer)
+/// turns the integer back into a counter with the same value.
+///
+/// This means that `Option<Counter>` and `Vec<Counter>` behave as you would expect, bearing in
+/// mind that elements of a `Vec<Counter>` are matched up with the document by index: going from
+/// `None` to `Some` creates a new counter, and incrementing a `Some` or an element of a `Vec`
+/// increments the counter in the document.
+///
+/// A counter which is incremented after being hydrated must still be the counter it was hydrated
+/// from when it is reconciled. If it isn't, for example because an element was removed from the
+/// start of a `Vec<Counter>` after it was hydrated so that every later counter moved down one
+/// index, reconciling fails with a [`StaleHeads`](crate::reconcile::StaleHeads) error rather than
+/// incrementing the wrong counter. [`crate::reconcile()`] reports the path of the counter, such as
+/// `at counts[0]: the data to be reconciled is stale ...`.
 #[derive(Clone)]
 pub struct Counter(State);
 
@@ -50,10 +78,58 @@ impl std::default::Default for Counter {
     }
 }
 
+// Like `Text`, two counters are equal if they have the same value, regardless of where in a
+// document they were hydrated from
+impl std::cmp::PartialEq for Counter {
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()
+    }
+}
+
+impl std::cmp::Eq for Counter {}
+
+impl std::hash::Hash for Counter {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.value().hash(state)
+    }
+}
+
 #[derive(Clone)]
 enum State {
     Fresh(i64),
-    Rehydrated { original: i64, increment: i64 },
+    Rehydrated {
+        original: i64,
+        increment: i64,
+        origin: Option<Origin>,
+    },
+}
+
+/// The counter in the document a counter was hydrated from
+#[derive(Clone)]
+struct Origin {
+    /// The ID of the operation which created the counter
+    id: automerge::ObjId,
+    /// The heads of the document when the counter was hydrated
+    heads: Vec<automerge::ChangeHash>,
+}
+
+impl Origin {
+    /// The origin of the counter at `prop` of `obj`, if there is a counter there
+    fn of<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: &Prop<'_>,
+    ) -> Result<Option<Self>, HydrateError> {
+        Ok(match doc.get(obj, prop)? {
+            Some((Value::Scalar(s), id)) if matches!(s.as_ref(), ScalarValue::Counter(_)) => {
+                Some(Origin {
+                    id,
+                    heads: doc.get_heads(),
+                })
+            }
+            _ => None,
+        })
+    }
 }
 
 impl Counter {
@@ -74,6 +150,7 @@ impl Counter {
             State::Rehydrated {
                 original,
                 increment,
+                ..
             } => original + increment,
         }
     }
@@ -84,29 +161,76 @@ impl Reconcile for Counter {
 
     fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
         let mut c = reconciler.counter()?;
-        match self.0 {
-            State::Fresh(v) => c.set(v)?,
-            State::Rehydrated { increment, .. } => c.increment(increment)?,
+        match &self.0 {
+            State::Fresh(v) => c.set(*v)?,
+            State::Rehydrated {
+                original,
+                increment,
+                origin,
+            } => match c.current()? {
+                Some(_) if *increment == 0 => {}
+                Some(_) => {
+                    if let (Some(origin), Some(id)) = (origin, c.current_id()?) {
+                        if id != origin.id {
+                            return Err(crate::reconcile::StaleHeads {
+                                expected: origin.heads.clone(),
+                                found: c.heads().to_vec(),
+                            }
+                            .into());
+                        }
+                    }
+                    c.increment(*increment)?
+                }
+                None => c.set(original + increment)?,
+            },
         };
         Ok(())
     }
 }
 
 impl Hydrate for Counter {
+    // Hydrates as usual, but a counter also remembers which counter in the document it came from
+    fn hydrate<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<Self, HydrateError> {
+        let mut counter: Self = crate::hydrate::hydrate_by_type(doc, obj, &prop)?;
+        if let State::Rehydrated { origin, .. } = &mut counter.0 {
+            *origin = Origin::of(doc, obj, &prop)?;
+        }
+        Ok(counter)
+    }
+
     fn hydrate_counter(c: i64) -> Result<Self, crate::HydrateError> {
         Ok(Counter(State::Rehydrated {
             original: c,
             increment: 0,
+            origin: None,
         }))
     }
+
+    // A peer which doesn't know a field is a counter may have overwritten it with a plain integer.
+    // Reconciling a counter hydrated from an integer writes the full value, turning it back into a
+    // counter.
+    fn hydrate_int(i: i64) -> Result<Self, crate::HydrateError> {
+        Self::hydrate_counter(i)
+    }
+
+    fn hydrate_uint(u: u64) -> Result<Self, crate::HydrateError> {
+        Self::hydrate_counter(i64::try_from(u).unwrap_or(i64::MAX))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use automerge::ActorId;
+    use std::collections::HashMap;
+
+    use automerge::{transaction::Transactable, ActorId, ReadDoc};
+    use automerge_test::{assert_doc, list, map};
 
     use super::Counter;
-    use crate::{hydrate_prop, reconcile_prop};
+    use crate::{hydrate_prop, reconcile::CounterReconciler, reconcile_prop};
 
     #[test]
     fn simple_increment() {
@@ -128,4 +252,146 @@ mod tests {
         let counter: Counter = hydrate_prop(&doc, &automerge::ROOT, "counter").unwrap();
         assert_eq!(counter.value(), 8);
     }
+
+    #[test]
+    fn option_counter() {
+        let mut doc = automerge::AutoCommit::new();
+        let counter: Option<Counter> = None;
+        reconcile_prop(&mut doc, automerge::ROOT, "counter", &counter).unwrap();
+
+        let mut counter: Option<Counter> = hydrate_prop(&doc, &automerge::ROOT, "counter").unwrap();
+        assert!(counter.is_none());
+        counter = Some(Counter::with_value(2));
+        reconcile_prop(&mut doc, automerge::ROOT, "counter", &counter).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "counter" => { automerge::ScalarValue::counter(2) }
+            }
+        );
+
+        let mut doc2 = doc.fork().with_actor(ActorId::random());
+        let mut counter2: Option<Counter> =
+            hydrate_prop(&doc2, &automerge::ROOT, "counter").unwrap();
+        counter2.as_mut().unwrap().increment(3);
+        reconcile_prop(&mut doc2, automerge::ROOT, "counter", &counter2).unwrap();
+
+        let mut counter: Option<Counter> = hydrate_prop(&doc, &automerge::ROOT, "counter").unwrap();
+        counter.as_mut().unwrap().increment(4);
+        reconcile_prop(&mut doc, automerge::ROOT, "counter", &counter).unwrap();
+
+        doc.merge(&mut doc2).unwrap();
+        let counter: Option<Counter> = hydrate_prop(&doc, &automerge::ROOT, "counter").unwrap();
+        assert_eq!(counter.unwrap().value(), 9);
+    }
+
+    #[test]
+    fn rehydrated_counter_written_to_a_new_location_keeps_its_value() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "counters",
+            vec![Counter::with_value(5)],
+        )
+        .unwrap();
+
+        let mut counters: Vec<Counter> = hydrate_prop(&doc, &automerge::ROOT, "counters").unwrap();
+        counters[0].increment(1);
+        // The clone is inserted at a new index, it should not be inserted as just the increment
+        counters.push(counters[0].clone());
+        reconcile_prop(&mut doc, automerge::ROOT, "counters", &counters).unwrap();
+
+        // Overwriting a non counter value with a rehydrated counter also writes the whole value
+        reconcile_prop(&mut doc, automerge::ROOT, "other", "not a counter").unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "other", &counters[1]).unwrap();
+
+        assert_doc!(
+            doc.document(),
+            map! {
+                "counters" => { list! {
+                    { automerge::ScalarValue::counter(6) },
+                    { automerge::ScalarValue::counter(6) },
+                }},
+                "other" => { automerge::ScalarValue::counter(6) },
+            }
+        );
+    }
+
+    #[test]
+    fn counter_overwritten_with_int_is_repaired() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "counter", Counter::with_value(5)).unwrap();
+        // A peer which doesn't know this is a counter overwrites it with an int
+        doc.put(automerge::ROOT, "counter", 7_i64).unwrap();
+
+        let mut counter: Counter = hydrate_prop(&doc, &automerge::ROOT, "counter").unwrap();
+        assert_eq!(counter.value(), 7);
+        counter.increment(2);
+        reconcile_prop(&mut doc, automerge::ROOT, "counter", &counter).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "counter" => { automerge::ScalarValue::counter(9) }
+            }
+        );
+    }
+
+    #[test]
+    fn incrementing_an_int_keeps_its_value() {
+        struct Increment(i64);
+        impl crate::Reconcile for Increment {
+            type Key<'a> = crate::reconcile::NoKey;
+            fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+                reconciler.counter()?.increment(self.0)
+            }
+        }
+
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(automerge::ROOT, "signed", 4_i64).unwrap();
+        doc.put(automerge::ROOT, "unsigned", 4_u64).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "signed", Increment(3)).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "unsigned", Increment(3)).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "missing", Increment(3)).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "signed" => { automerge::ScalarValue::counter(7) },
+                "unsigned" => { automerge::ScalarValue::counter(7) },
+                "missing" => { automerge::ScalarValue::counter(3) },
+            }
+        );
+    }
+
+    #[test]
+    fn incrementing_a_counter_which_moved_is_stale() {
+        let mut doc = automerge::AutoCommit::new();
+        let counts = HashMap::from([(
+            "counts",
+            vec![Counter::with_value(1), Counter::with_value(2)],
+        )]);
+        crate::reconcile(&mut doc, &counts).unwrap();
+
+        let mut counts: HashMap<String, Vec<Counter>> = crate::hydrate(&doc).unwrap();
+        let mut doc2 = doc.fork().with_actor(ActorId::random());
+        let list = match doc2.get(automerge::ROOT, "counts").unwrap() {
+            Some((_, id)) => id,
+            None => panic!("no counts"),
+        };
+        doc2.delete(&list, 0).unwrap();
+        doc.merge(&mut doc2).unwrap();
+
+        for counter in counts.get_mut("counts").unwrap() {
+            counter.increment(1);
+        }
+        let err = crate::reconcile(&mut doc, &counts).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("at counts[0]: the data to be reconciled is stale"),
+            "{}",
+            err
+        );
+        let counts: HashMap<String, Vec<Counter>> = crate::hydrate(&doc).unwrap();
+        assert_eq!(counts["counts"], vec![Counter::with_value(2)]);
+    }
 }
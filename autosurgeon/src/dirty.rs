@@ -0,0 +1,117 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Which fields of a struct have changed since it was last hydrated or reconciled
+///
+/// A struct which derives `Reconcile` with `#[autosurgeon(track)]` must have exactly one field of
+/// type `Dirty`. The derive then generates a `set_<field>` method for every other field which
+/// sets the field and marks it dirty, and reconciling the struct only writes the fields which are
+/// dirty (or which aren't in the document at all) before marking every field clean again. The
+/// `Dirty` field itself is not stored in the document, a hydrated struct starts out clean.
+///
+/// Changes made by assigning to a field directly rather than through its setter are not noticed,
+/// so it's a good idea to keep the fields of a tracked struct private. Because clean fields are
+/// not written, a tracked struct should only be reconciled into the document (and the location in
+/// it) it was hydrated from, call [`Dirty::mark_all`] before reconciling it anywhere else.
+///
+/// # Example
+///
+/// ```rust
+/// # use autosurgeon::{hydrate, reconcile, Dirty, Hydrate, Reconcile};
+/// # use automerge::{transaction::Transactable, ReadDoc};
+/// #[derive(Hydrate, Reconcile)]
+/// #[autosurgeon(track)]
+/// struct Contact {
+///     name: String,
+///     email: String,
+///     dirty: Dirty,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// doc.put(automerge::ROOT, "name", "Sherlock Holmes").unwrap();
+/// doc.put(automerge::ROOT, "email", "sherlock@example.com").unwrap();
+///
+/// let mut contact: Contact = hydrate(&doc).unwrap();
+/// // Someone else changes the name in the meantime
+/// doc.put(automerge::ROOT, "name", "S. Holmes").unwrap();
+///
+/// contact.set_email("holmes@example.com".to_string());
+/// reconcile(&mut doc, &contact).unwrap();
+///
+/// // The name wasn't marked dirty, so it wasn't written
+/// let name: String = autosurgeon::hydrate_prop(&doc, automerge::ROOT, "name").unwrap();
+/// assert_eq!(name, "S. Holmes");
+/// let email: String = autosurgeon::hydrate_prop(&doc, automerge::ROOT, "email").unwrap();
+/// assert_eq!(email, "holmes@example.com");
+/// ```
+///
+/// A `Dirty` which was created with [`Default`] rather than hydrated has every field marked
+/// dirty, so a new value is written in full the first time it is reconciled. A tracked struct may
+/// have at most 64 fields besides its `Dirty` field. `Dirty` compares equal to and hashes the same
+/// as every other `Dirty`, so it doesn't affect derived `PartialEq` or `Hash` implementations.
+#[derive(Debug)]
+pub struct Dirty(AtomicU64);
+
+impl Dirty {
+    /// A `Dirty` with every field marked dirty
+    pub fn all() -> Self {
+        Self(AtomicU64::new(u64::MAX))
+    }
+
+    /// A `Dirty` with every field marked clean, this is what hydrating a tracked struct produces
+    pub fn clean() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    /// Whether the field at `index` (counting the fields of the struct in declaration order,
+    /// not including the `Dirty` field) has been marked dirty
+    pub fn is_dirty(&self, index: usize) -> bool {
+        self.0.load(Ordering::Relaxed) & (1 << index) != 0
+    }
+
+    /// Whether no field is marked dirty
+    pub fn is_clean(&self) -> bool {
+        self.0.load(Ordering::Relaxed) == 0
+    }
+
+    /// Mark the field at `index` dirty
+    pub fn mark(&mut self, index: usize) {
+        *self.0.get_mut() |= 1 << index;
+    }
+
+    /// Mark every field dirty, so the next reconcile writes the whole struct
+    pub fn mark_all(&mut self) {
+        *self.0.get_mut() = u64::MAX;
+    }
+
+    /// Mark every field clean
+    ///
+    /// This takes `&self` because it's called by the generated `Reconcile` implementation once
+    /// every dirty field has been written.
+    pub fn clear(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Default for Dirty {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl Clone for Dirty {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.0.load(Ordering::Relaxed)))
+    }
+}
+
+impl PartialEq for Dirty {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Dirty {}
+
+impl std::hash::Hash for Dirty {
+    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
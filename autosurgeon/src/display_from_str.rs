@@ -0,0 +1,60 @@
+//! Derive macro adaptors for storing any type implementing [`Display`] and [`FromStr`] as a string
+//!
+//! `autosurgeon` implements [`Reconcile`](crate::Reconcile) and [`Hydrate`] for
+//! the string-like types in the standard library such as [`std::net::IpAddr`] and
+//! [`std::path::PathBuf`], and for `url::Url` with the `url` feature. Other types which are
+//! naturally represented as a string, such as `semver::Version`, can be stored the same way using
+//! this module with `#[autosurgeon(with = "...")]`. Hydrating a string which fails to parse fails
+//! with [`HydrateError::Unexpected`].
+//!
+//! ```
+//! # use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+//! # #[derive(Debug, PartialEq)]
+//! # struct Version(String);
+//! # impl std::fmt::Display for Version {
+//! #     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+//! #         f.write_str(&self.0)
+//! #     }
+//! # }
+//! # impl std::str::FromStr for Version {
+//! #     type Err = std::convert::Infallible;
+//! #     fn from_str(s: &str) -> Result<Self, Self::Err> {
+//! #         Ok(Version(s.to_string()))
+//! #     }
+//! # }
+//! #[derive(Debug, PartialEq, Reconcile, Hydrate)]
+//! struct Server {
+//!     #[autosurgeon(with = "autosurgeon::display_from_str")]
+//!     version: Version,
+//! }
+//!
+//! let server = Server { version: "1.2.0".parse().unwrap() };
+//! let mut doc = automerge::AutoCommit::new();
+//! reconcile(&mut doc, &server).unwrap();
+//! assert_eq!(hydrate::<_, Server>(&doc).unwrap(), server);
+//! ```
+use std::{fmt::Display, str::FromStr};
+
+use crate::{Hydrate, HydrateError, Prop, ReadDoc, Reconciler};
+
+/// Reconcile `value` as the string it displays as
+pub fn reconcile<T: Display, R: Reconciler>(value: &T, mut reconciler: R) -> Result<(), R::Error> {
+    reconciler.str(value.to_string())
+}
+
+/// Hydrate a string and parse it with [`FromStr`]
+///
+/// Fails with [`HydrateError::Unexpected`] if the string doesn't parse as a `T`.
+pub fn hydrate<D: ReadDoc, T: FromStr>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: Prop<'_>,
+) -> Result<T, HydrateError> {
+    let s = String::hydrate(doc, obj, prop)?;
+    s.parse().map_err(|_| {
+        HydrateError::unexpected(
+            format!("a string which parses as {}", std::any::type_name::<T>()),
+            format!("{:?}", s),
+        )
+    })
+}
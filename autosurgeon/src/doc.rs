@@ -2,6 +2,9 @@ use std::ops::RangeBounds;
 
 use automerge::{self as am, AutomergeError, ObjId, Value};
 
+mod instrumented;
+pub use instrumented::{InstrumentedReadDoc, ReadAccess};
+
 /// An abstraction over the different ways of reading an automerge document
 pub trait ReadDoc {
     type Parents<'a>: Iterator<Item = am::Parent>
@@ -15,6 +18,19 @@ pub trait ReadDoc {
         prop: P,
     ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError>;
 
+    /// Get all the possibly conflicting values at `prop` of `obj`
+    ///
+    /// The values are in the same order automerge uses to choose a winner, so the last value is
+    /// the one returned by [`ReadDoc::get`]. The default implementation returns just that value,
+    /// documents which can see conflicts should override it.
+    fn get_all<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        Ok(self.get(obj, prop)?.into_iter().collect())
+    }
+
     fn object_type<O: AsRef<ObjId>>(&self, obj: O) -> Option<am::ObjType>;
     fn map_range<'a, O, R>(&'a self, obj: O, range: R) -> am::iter::MapRange<'a, R>
     where
@@ -30,8 +46,91 @@ pub trait ReadDoc {
 
     fn length<O: AsRef<ObjId>>(&self, obj: O) -> usize;
 
+    /// The keys of the map `obj`, in lexicographic order
+    fn keys<O: AsRef<ObjId>>(&self, obj: O) -> am::iter::Keys<'_>;
+
     fn text<O: AsRef<ObjId>>(&self, obj: O) -> Result<String, AutomergeError>;
     fn parents<O: AsRef<ObjId>>(&self, obj: O) -> Result<Self::Parents<'_>, AutomergeError>;
+
+    // The following methods are the same as the above but read the document as at `heads`. See
+    // [`AtHeads`] for a way to use these to hydrate from a past version of a document.
+
+    fn get_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError>;
+
+    /// The default implementation returns just the value returned by [`ReadDoc::get_at`], see
+    /// [`ReadDoc::get_all`]
+    fn get_all_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        Ok(self.get_at(obj, prop, heads)?.into_iter().collect())
+    }
+
+    fn map_range_at<'a, O, R>(
+        &'a self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::MapRange<'a, R>
+    where
+        R: RangeBounds<String> + 'a,
+        O: AsRef<ObjId>;
+
+    fn list_range_at<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::ListRange<'_, R>;
+
+    fn length_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> usize;
+
+    fn keys_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> am::iter::Keys<'_>;
+
+    fn text_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<String, AutomergeError>;
+
+    fn parents_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Self::Parents<'_>, AutomergeError>;
+
+    /// Get a cursor pointing at `position` in the sequence `obj`, as at `at` if it is given
+    fn get_cursor<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        position: usize,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<am::Cursor, AutomergeError>;
+
+    /// Get the current position of `cursor` in the sequence `obj`, as at `at` if it is given
+    fn get_cursor_position<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        cursor: &am::Cursor,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<usize, AutomergeError>;
+
+    /// Get the marks on the sequence `obj`
+    fn marks<O: AsRef<ObjId>>(&self, obj: O) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError>;
+
+    /// Get the marks on the sequence `obj` as at `heads`
+    fn marks_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError>;
 }
 
 /// An abstraction over the read + write operations we need from an automerge document
@@ -83,6 +182,30 @@ pub trait Doc: ReadDoc {
         del: isize,
         text: &str,
     ) -> Result<(), AutomergeError>;
+
+    fn mark<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        mark: am::marks::Mark<'_>,
+        expand: am::marks::ExpandMark,
+    ) -> Result<(), AutomergeError>;
+
+    fn unmark<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        key: &str,
+        start: usize,
+        end: usize,
+        expand: am::marks::ExpandMark,
+    ) -> Result<(), AutomergeError>;
+
+    /// The number of operations written which have not yet been committed
+    ///
+    /// This is only used to count the operations made by a reconcile, so documents which can't
+    /// tell can leave it as zero.
+    fn pending_ops(&self) -> usize {
+        0
+    }
 }
 
 impl ReadDoc for am::AutoCommit {
@@ -99,6 +222,14 @@ impl ReadDoc for am::AutoCommit {
         am::ReadDoc::get(self, obj, prop)
     }
 
+    fn get_all<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        am::ReadDoc::get_all(self, obj, prop)
+    }
+
     fn object_type<O: AsRef<ObjId>>(&self, obj: O) -> Option<am::ObjType> {
         am::ReadDoc::object_type(self, obj)
             .map(Some)
@@ -126,6 +257,10 @@ impl ReadDoc for am::AutoCommit {
         am::ReadDoc::length(self, obj)
     }
 
+    fn keys<O: AsRef<ObjId>>(&self, obj: O) -> am::iter::Keys<'_> {
+        am::ReadDoc::keys(self, obj)
+    }
+
     fn text<O: AsRef<ObjId>>(&self, obj: O) -> Result<String, AutomergeError> {
         am::ReadDoc::text(self, obj)
     }
@@ -133,6 +268,100 @@ impl ReadDoc for am::AutoCommit {
     fn parents<O: AsRef<ObjId>>(&self, obj: O) -> Result<Self::Parents<'_>, AutomergeError> {
         am::ReadDoc::parents(self, obj)
     }
+
+    fn get_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        am::ReadDoc::get_at(self, obj, prop, heads)
+    }
+
+    fn get_all_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        am::ReadDoc::get_all_at(self, obj, prop, heads)
+    }
+
+    fn map_range_at<'a, O, R>(
+        &'a self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::MapRange<'a, R>
+    where
+        R: RangeBounds<String> + 'a,
+        O: AsRef<ObjId>,
+    {
+        am::ReadDoc::map_range_at(self, obj, range, heads)
+    }
+
+    fn list_range_at<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::ListRange<'_, R> {
+        am::ReadDoc::list_range_at(self, obj, range, heads)
+    }
+
+    fn length_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> usize {
+        am::ReadDoc::length_at(self, obj, heads)
+    }
+
+    fn keys_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> am::iter::Keys<'_> {
+        am::ReadDoc::keys_at(self, obj, heads)
+    }
+
+    fn text_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<String, AutomergeError> {
+        am::ReadDoc::text_at(self, obj, heads)
+    }
+
+    fn parents_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Self::Parents<'_>, AutomergeError> {
+        am::ReadDoc::parents_at(self, obj, heads)
+    }
+
+    fn get_cursor<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        position: usize,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<am::Cursor, AutomergeError> {
+        am::ReadDoc::get_cursor(self, obj, position, at)
+    }
+
+    fn get_cursor_position<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        cursor: &am::Cursor,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<usize, AutomergeError> {
+        am::ReadDoc::get_cursor_position(self, obj, cursor, at)
+    }
+
+    fn marks<O: AsRef<ObjId>>(&self, obj: O) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        am::ReadDoc::marks(self, obj)
+    }
+
+    fn marks_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        am::ReadDoc::marks_at(self, obj, heads)
+    }
 }
 
 impl<'a> ReadDoc for am::transaction::Transaction<'a> {
@@ -149,6 +378,14 @@ impl<'a> ReadDoc for am::transaction::Transaction<'a> {
         am::ReadDoc::get(self, obj, prop)
     }
 
+    fn get_all<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        am::ReadDoc::get_all(self, obj, prop)
+    }
+
     fn object_type<O: AsRef<ObjId>>(&self, obj: O) -> Option<am::ObjType> {
         am::ReadDoc::object_type(self, obj)
             .map(Some)
@@ -174,6 +411,10 @@ impl<'a> ReadDoc for am::transaction::Transaction<'a> {
         am::ReadDoc::length(self, obj)
     }
 
+    fn keys<O: AsRef<ObjId>>(&self, obj: O) -> am::iter::Keys<'_> {
+        am::ReadDoc::keys(self, obj)
+    }
+
     fn text<O: AsRef<ObjId>>(&self, obj: O) -> Result<String, AutomergeError> {
         am::ReadDoc::text(self, obj)
     }
@@ -181,6 +422,100 @@ impl<'a> ReadDoc for am::transaction::Transaction<'a> {
     fn parents<O: AsRef<ObjId>>(&self, obj: O) -> Result<Self::Parents<'_>, AutomergeError> {
         am::ReadDoc::parents(self, obj)
     }
+
+    fn get_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        am::ReadDoc::get_at(self, obj, prop, heads)
+    }
+
+    fn get_all_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        am::ReadDoc::get_all_at(self, obj, prop, heads)
+    }
+
+    fn map_range_at<'b, O, R>(
+        &'b self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+    {
+        am::ReadDoc::map_range_at(self, obj, range, heads)
+    }
+
+    fn list_range_at<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::ListRange<'_, R> {
+        am::ReadDoc::list_range_at(self, obj, range, heads)
+    }
+
+    fn length_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> usize {
+        am::ReadDoc::length_at(self, obj, heads)
+    }
+
+    fn keys_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> am::iter::Keys<'_> {
+        am::ReadDoc::keys_at(self, obj, heads)
+    }
+
+    fn text_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<String, AutomergeError> {
+        am::ReadDoc::text_at(self, obj, heads)
+    }
+
+    fn parents_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Self::Parents<'_>, AutomergeError> {
+        am::ReadDoc::parents_at(self, obj, heads)
+    }
+
+    fn get_cursor<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        position: usize,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<am::Cursor, AutomergeError> {
+        am::ReadDoc::get_cursor(self, obj, position, at)
+    }
+
+    fn get_cursor_position<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        cursor: &am::Cursor,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<usize, AutomergeError> {
+        am::ReadDoc::get_cursor_position(self, obj, cursor, at)
+    }
+
+    fn marks<O: AsRef<ObjId>>(&self, obj: O) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        am::ReadDoc::marks(self, obj)
+    }
+
+    fn marks_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        am::ReadDoc::marks_at(self, obj, heads)
+    }
 }
 
 impl ReadDoc for am::Automerge {
@@ -197,6 +532,14 @@ impl ReadDoc for am::Automerge {
         am::ReadDoc::get(self, obj, prop)
     }
 
+    fn get_all<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        am::ReadDoc::get_all(self, obj, prop)
+    }
+
     fn object_type<O: AsRef<ObjId>>(&self, obj: O) -> Option<am::ObjType> {
         am::ReadDoc::object_type(self, obj)
             .map(Some)
@@ -223,6 +566,10 @@ impl ReadDoc for am::Automerge {
         am::ReadDoc::length(self, obj)
     }
 
+    fn keys<O: AsRef<ObjId>>(&self, obj: O) -> am::iter::Keys<'_> {
+        am::ReadDoc::keys(self, obj)
+    }
+
     fn text<O: AsRef<ObjId>>(&self, obj: O) -> Result<String, AutomergeError> {
         am::ReadDoc::text(self, obj)
     }
@@ -230,6 +577,300 @@ impl ReadDoc for am::Automerge {
     fn parents<O: AsRef<ObjId>>(&self, obj: O) -> Result<Self::Parents<'_>, AutomergeError> {
         am::ReadDoc::parents(self, obj)
     }
+
+    fn get_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        am::ReadDoc::get_at(self, obj, prop, heads)
+    }
+
+    fn get_all_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        am::ReadDoc::get_all_at(self, obj, prop, heads)
+    }
+
+    fn map_range_at<'a, O, R>(
+        &'a self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::MapRange<'a, R>
+    where
+        R: RangeBounds<String> + 'a,
+        O: AsRef<ObjId>,
+    {
+        am::ReadDoc::map_range_at(self, obj, range, heads)
+    }
+
+    fn list_range_at<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::ListRange<'_, R> {
+        am::ReadDoc::list_range_at(self, obj, range, heads)
+    }
+
+    fn length_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> usize {
+        am::ReadDoc::length_at(self, obj, heads)
+    }
+
+    fn keys_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> am::iter::Keys<'_> {
+        am::ReadDoc::keys_at(self, obj, heads)
+    }
+
+    fn text_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<String, AutomergeError> {
+        am::ReadDoc::text_at(self, obj, heads)
+    }
+
+    fn parents_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Self::Parents<'_>, AutomergeError> {
+        am::ReadDoc::parents_at(self, obj, heads)
+    }
+
+    fn get_cursor<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        position: usize,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<am::Cursor, AutomergeError> {
+        am::ReadDoc::get_cursor(self, obj, position, at)
+    }
+
+    fn get_cursor_position<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        cursor: &am::Cursor,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<usize, AutomergeError> {
+        am::ReadDoc::get_cursor_position(self, obj, cursor, at)
+    }
+
+    fn marks<O: AsRef<ObjId>>(&self, obj: O) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        am::ReadDoc::marks(self, obj)
+    }
+
+    fn marks_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        am::ReadDoc::marks_at(self, obj, heads)
+    }
+}
+
+/// A [`ReadDoc`] which reads `doc` as at `heads`
+///
+/// This is what [`crate::hydrate_at`] and [`crate::hydrate_prop_at`] use under the hood, but it can
+/// be passed anywhere a [`ReadDoc`] is expected in order to read a past version of a document
+/// without forking it.
+///
+/// Every read is made as at `heads` except [`ReadDoc::object_type`], because automerge has no
+/// way to look up the type of an object as at some heads. The type of an object never changes
+/// so this only matters for an object which was created after `heads`: its type is still
+/// returned rather than `None`. Objects are only found by reading their parents though, and
+/// those reads don't see objects created after `heads`.
+///
+/// ```rust
+/// # use automerge::transaction::Transactable;
+/// # use autosurgeon::{hydrate, AtHeads};
+/// # use std::collections::HashMap;
+/// let mut doc = automerge::AutoCommit::new();
+/// doc.put(automerge::ROOT, "name", "old").unwrap();
+/// let heads = doc.get_heads();
+/// doc.put(automerge::ROOT, "name", "new").unwrap();
+///
+/// let old: HashMap<String, String> = hydrate(&AtHeads::new(&doc, &heads)).unwrap();
+/// assert_eq!(old.get("name").map(String::as_str), Some("old"));
+/// ```
+#[derive(Debug)]
+pub struct AtHeads<'a, D: ?Sized> {
+    doc: &'a D,
+    heads: &'a [am::ChangeHash],
+}
+
+impl<'a, D: ?Sized> AtHeads<'a, D> {
+    pub fn new(doc: &'a D, heads: &'a [am::ChangeHash]) -> Self {
+        Self { doc, heads }
+    }
+}
+
+impl<'a, D: ?Sized> Clone for AtHeads<'a, D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, D: ?Sized> Copy for AtHeads<'a, D> {}
+
+impl<'a, D: ReadDoc + ?Sized> ReadDoc for AtHeads<'a, D> {
+    type Parents<'b> = D::Parents<'a> where Self: 'b;
+    fn get_heads(&self) -> Vec<am::ChangeHash> {
+        self.heads.to_vec()
+    }
+
+    fn get<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_at(obj, prop, self.heads)
+    }
+
+    fn get_all<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_all_at(obj, prop, self.heads)
+    }
+
+    /// The type of `obj`, which is returned even if `obj` was created after the heads, see
+    /// [`AtHeads`]
+    fn object_type<O: AsRef<ObjId>>(&self, obj: O) -> Option<am::ObjType> {
+        self.doc.object_type(obj)
+    }
+
+    fn map_range<'b, O, R>(&'b self, obj: O, range: R) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+    {
+        self.doc.map_range_at(obj, range, self.heads)
+    }
+
+    fn list_range<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+    ) -> am::iter::ListRange<'_, R> {
+        self.doc.list_range_at(obj, range, self.heads)
+    }
+
+    fn length<O: AsRef<ObjId>>(&self, obj: O) -> usize {
+        self.doc.length_at(obj, self.heads)
+    }
+
+    fn keys<O: AsRef<ObjId>>(&self, obj: O) -> am::iter::Keys<'_> {
+        self.doc.keys_at(obj, self.heads)
+    }
+
+    fn text<O: AsRef<ObjId>>(&self, obj: O) -> Result<String, AutomergeError> {
+        self.doc.text_at(obj, self.heads)
+    }
+
+    fn parents<O: AsRef<ObjId>>(&self, obj: O) -> Result<Self::Parents<'_>, AutomergeError> {
+        self.doc.parents_at(obj, self.heads)
+    }
+
+    fn get_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_at(obj, prop, heads)
+    }
+
+    fn get_all_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_all_at(obj, prop, heads)
+    }
+
+    fn map_range_at<'b, O, R>(
+        &'b self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+    {
+        self.doc.map_range_at(obj, range, heads)
+    }
+
+    fn list_range_at<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::ListRange<'_, R> {
+        self.doc.list_range_at(obj, range, heads)
+    }
+
+    fn length_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> usize {
+        self.doc.length_at(obj, heads)
+    }
+
+    fn keys_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> am::iter::Keys<'_> {
+        self.doc.keys_at(obj, heads)
+    }
+
+    fn text_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<String, AutomergeError> {
+        self.doc.text_at(obj, heads)
+    }
+
+    fn parents_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Self::Parents<'_>, AutomergeError> {
+        self.doc.parents_at(obj, heads)
+    }
+
+    fn get_cursor<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        position: usize,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<am::Cursor, AutomergeError> {
+        self.doc.get_cursor(obj, position, at.or(Some(self.heads)))
+    }
+
+    fn get_cursor_position<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        cursor: &am::Cursor,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<usize, AutomergeError> {
+        self.doc
+            .get_cursor_position(obj, cursor, at.or(Some(self.heads)))
+    }
+
+    fn marks<O: AsRef<ObjId>>(&self, obj: O) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        self.doc.marks_at(obj, self.heads)
+    }
+
+    fn marks_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        self.doc.marks_at(obj, heads)
+    }
 }
 
 impl<T: am::transaction::Transactable + ReadDoc> Doc for T {
@@ -295,4 +936,87 @@ impl<T: am::transaction::Transactable + ReadDoc> Doc for T {
     ) -> Result<(), AutomergeError> {
         am::transaction::Transactable::splice_text(self, obj, pos, del, text)
     }
+
+    fn mark<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        mark: am::marks::Mark<'_>,
+        expand: am::marks::ExpandMark,
+    ) -> Result<(), AutomergeError> {
+        am::transaction::Transactable::mark(self, obj, mark, expand)
+    }
+
+    fn unmark<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        key: &str,
+        start: usize,
+        end: usize,
+        expand: am::marks::ExpandMark,
+    ) -> Result<(), AutomergeError> {
+        am::transaction::Transactable::unmark(self, obj, key, start, end, expand)
+    }
+
+    fn pending_ops(&self) -> usize {
+        am::transaction::Transactable::pending_ops(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::{
+        marks::{ExpandMark, Mark},
+        ScalarValue,
+    };
+
+    use super::{Doc, ReadDoc};
+
+    fn bold_first_word<D: Doc>(doc: &mut D, text: &automerge::ObjId) {
+        let mark = Mark::new("bold".to_string(), ScalarValue::Boolean(true), 0, 5);
+        doc.mark(text, mark, ExpandMark::After).unwrap();
+    }
+
+    #[test]
+    fn mark_and_unmark() {
+        let mut doc = automerge::AutoCommit::new();
+        let text = doc
+            .put_object(automerge::ROOT, "text", automerge::ObjType::Text)
+            .unwrap();
+        doc.splice_text(&text, 0, 0, "hello world").unwrap();
+
+        bold_first_word(&mut doc, &text);
+        doc.commit();
+        let marks = ReadDoc::marks(&doc, &text).unwrap();
+        assert_eq!(marks.len(), 1);
+        assert_eq!(
+            (marks[0].name(), marks[0].start, marks[0].end),
+            ("bold", 0, 5)
+        );
+
+        let heads = doc.get_heads();
+        let mut doc = doc.document().clone();
+        let mut tx = doc.transaction();
+        Doc::unmark(&mut tx, &text, "bold", 0, 5, ExpandMark::After).unwrap();
+        assert!(ReadDoc::marks(&tx, &text).unwrap().is_empty());
+        assert_eq!(ReadDoc::marks_at(&tx, &text, &heads).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn keys_and_keys_at() {
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(automerge::ROOT, "b", 1).unwrap();
+        doc.commit();
+        let heads = doc.get_heads();
+        doc.put(automerge::ROOT, "a", 2).unwrap();
+
+        let keys = ReadDoc::keys(&doc, automerge::ROOT).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["a", "b"]);
+        let keys = ReadDoc::keys_at(&doc, automerge::ROOT, &heads).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["b"]);
+        let at_heads = super::AtHeads::new(&doc, &heads);
+        assert_eq!(
+            at_heads.keys(automerge::ROOT).collect::<Vec<_>>(),
+            vec!["b"]
+        );
+    }
 }
@@ -0,0 +1,347 @@
+use std::{cell::RefCell, collections::BTreeSet, ops::RangeBounds};
+
+use automerge::{self as am, AutomergeError, ObjId, Value};
+
+use super::ReadDoc;
+
+/// A read of a document recorded by [`InstrumentedReadDoc`]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReadAccess {
+    /// The value at `prop` of `obj` was read
+    Prop { obj: ObjId, prop: am::Prop },
+    /// Something which depends on the whole of the object was read, such as its keys, its length,
+    /// or its text
+    Object(ObjId),
+}
+
+impl ReadAccess {
+    /// The object which was read
+    pub fn obj(&self) -> &ObjId {
+        match self {
+            Self::Prop { obj, .. } => obj,
+            Self::Object(obj) => obj,
+        }
+    }
+
+    /// Whether `patch` may have changed the result of this read
+    ///
+    /// Changes to a map property only affect reads of that property or of the whole map. Any
+    /// change to a sequence affects every read of it, because inserting or deleting an element
+    /// moves the elements after it.
+    pub fn affected_by(&self, patch: &am::Patch) -> bool {
+        if self.obj() != &patch.obj {
+            return false;
+        }
+        let changed_key = match &patch.action {
+            am::PatchAction::PutMap { key, .. } | am::PatchAction::DeleteMap { key } => key,
+            am::PatchAction::Increment {
+                prop: am::Prop::Map(key),
+                ..
+            }
+            | am::PatchAction::Conflict {
+                prop: am::Prop::Map(key),
+            } => key,
+            _ => return true,
+        };
+        match self {
+            Self::Prop {
+                prop: am::Prop::Map(read_key),
+                ..
+            } => read_key == changed_key,
+            _ => true,
+        }
+    }
+}
+
+/// A [`ReadDoc`] which records every read made through it
+///
+/// Hydrating through an `InstrumentedReadDoc` tells you exactly which parts of the document the
+/// hydrated value depends on. This is useful for reactive invalidation: when changes arrive, only
+/// rehydrate the values whose accesses overlap the objects and properties touched by the
+/// incoming patches.
+///
+/// ```rust
+/// # use autosurgeon::{hydrate_prop, reconcile_prop, InstrumentedReadDoc, ReadAccess};
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile_prop(&mut doc, automerge::ROOT, "title", "Minutes").unwrap();
+/// reconcile_prop(&mut doc, automerge::ROOT, "attendees", vec!["alice", "bob"]).unwrap();
+///
+/// let instrumented = InstrumentedReadDoc::new(&doc);
+/// let title: String = hydrate_prop(&instrumented, &automerge::ROOT, "title").unwrap();
+/// assert_eq!(
+///     instrumented.into_accesses().into_iter().collect::<Vec<_>>(),
+///     vec![ReadAccess::Prop {
+///         obj: automerge::ROOT,
+///         prop: "title".into()
+///     }]
+/// );
+/// ```
+///
+/// Reads made via the `*_at` methods are recorded in the same way as reads of the current state.
+#[derive(Debug)]
+pub struct InstrumentedReadDoc<'a, D: ?Sized> {
+    doc: &'a D,
+    accesses: RefCell<BTreeSet<ReadAccess>>,
+}
+
+impl<'a, D: ?Sized> InstrumentedReadDoc<'a, D> {
+    pub fn new(doc: &'a D) -> Self {
+        Self {
+            doc,
+            accesses: RefCell::new(BTreeSet::new()),
+        }
+    }
+
+    /// The reads made so far
+    pub fn accesses(&self) -> BTreeSet<ReadAccess> {
+        self.accesses.borrow().clone()
+    }
+
+    /// Return the reads made so far and start recording afresh
+    pub fn take_accesses(&self) -> BTreeSet<ReadAccess> {
+        std::mem::take(&mut *self.accesses.borrow_mut())
+    }
+
+    pub fn into_accesses(self) -> BTreeSet<ReadAccess> {
+        self.accesses.into_inner()
+    }
+
+    fn record_prop<P: Into<am::Prop>>(&self, obj: &ObjId, prop: P) -> am::Prop {
+        let prop = prop.into();
+        self.accesses.borrow_mut().insert(ReadAccess::Prop {
+            obj: obj.clone(),
+            prop: prop.clone(),
+        });
+        prop
+    }
+
+    fn record_obj<O: AsRef<ObjId>>(&self, obj: O) -> O {
+        self.accesses
+            .borrow_mut()
+            .insert(ReadAccess::Object(obj.as_ref().clone()));
+        obj
+    }
+}
+
+impl<'a, D: ReadDoc + ?Sized> ReadDoc for InstrumentedReadDoc<'a, D> {
+    type Parents<'b>
+        = D::Parents<'a>
+    where
+        Self: 'b;
+
+    fn get_heads(&self) -> Vec<am::ChangeHash> {
+        self.doc.get_heads()
+    }
+
+    fn get<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get(obj, self.record_prop(obj, prop))
+    }
+
+    fn get_all<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_all(obj, self.record_prop(obj, prop))
+    }
+
+    fn object_type<O: AsRef<ObjId>>(&self, obj: O) -> Option<am::ObjType> {
+        self.doc.object_type(self.record_obj(obj))
+    }
+
+    fn map_range<'b, O, R>(&'b self, obj: O, range: R) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+        R: RangeBounds<String>,
+    {
+        self.doc.map_range(self.record_obj(obj), range)
+    }
+
+    fn list_range<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+    ) -> am::iter::ListRange<'_, R> {
+        self.doc.list_range(self.record_obj(obj), range)
+    }
+
+    fn length<O: AsRef<ObjId>>(&self, obj: O) -> usize {
+        self.doc.length(self.record_obj(obj))
+    }
+
+    fn keys<O: AsRef<ObjId>>(&self, obj: O) -> am::iter::Keys<'_> {
+        self.doc.keys(self.record_obj(obj))
+    }
+
+    fn text<O: AsRef<ObjId>>(&self, obj: O) -> Result<String, AutomergeError> {
+        self.doc.text(self.record_obj(obj))
+    }
+
+    fn parents<O: AsRef<ObjId>>(&self, obj: O) -> Result<Self::Parents<'_>, AutomergeError> {
+        self.doc.parents(self.record_obj(obj))
+    }
+
+    fn get_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_at(obj, self.record_prop(obj, prop), heads)
+    }
+
+    fn get_all_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_all_at(obj, self.record_prop(obj, prop), heads)
+    }
+
+    fn map_range_at<'b, O, R>(
+        &'b self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+    {
+        self.doc.map_range_at(self.record_obj(obj), range, heads)
+    }
+
+    fn list_range_at<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::ListRange<'_, R> {
+        self.doc.list_range_at(self.record_obj(obj), range, heads)
+    }
+
+    fn length_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> usize {
+        self.doc.length_at(self.record_obj(obj), heads)
+    }
+
+    fn keys_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> am::iter::Keys<'_> {
+        self.doc.keys_at(self.record_obj(obj), heads)
+    }
+
+    fn text_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<String, AutomergeError> {
+        self.doc.text_at(self.record_obj(obj), heads)
+    }
+
+    fn parents_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Self::Parents<'_>, AutomergeError> {
+        self.doc.parents_at(self.record_obj(obj), heads)
+    }
+
+    fn get_cursor<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        position: usize,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<am::Cursor, AutomergeError> {
+        self.doc.get_cursor(self.record_obj(obj), position, at)
+    }
+
+    fn get_cursor_position<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        cursor: &am::Cursor,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<usize, AutomergeError> {
+        self.doc
+            .get_cursor_position(self.record_obj(obj), cursor, at)
+    }
+
+    fn marks<O: AsRef<ObjId>>(&self, obj: O) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        self.doc.marks(self.record_obj(obj))
+    }
+
+    fn marks_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        self.doc.marks_at(self.record_obj(obj), heads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{InstrumentedReadDoc, ReadAccess};
+    use crate::{hydrate, reconcile, Hydrate, HydrateError, ReadDoc, Reconcile, Reconciler};
+
+    #[derive(Debug, PartialEq)]
+    struct Meeting {
+        title: String,
+        attendees: Vec<String>,
+    }
+
+    impl Reconcile for Meeting {
+        type Key<'a> = crate::reconcile::NoKey;
+        fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            use crate::reconcile::MapReconciler;
+            let mut m = reconciler.map()?;
+            m.put("title", &self.title)?;
+            m.put("attendees", &self.attendees)?;
+            Ok(())
+        }
+    }
+
+    impl Hydrate for Meeting {
+        fn hydrate_map<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+            Ok(Meeting {
+                title: crate::hydrate_prop(doc, obj, "title")?,
+                attendees: crate::hydrate_prop(doc, obj, "attendees")?,
+            })
+        }
+    }
+
+    #[test]
+    fn records_reads() {
+        let mut doc = automerge::AutoCommit::new();
+        let meeting = Meeting {
+            title: "Standup".to_string(),
+            attendees: vec!["alice".to_string()],
+        };
+        reconcile(&mut doc, &meeting).unwrap();
+        let attendees = doc.get(&automerge::ROOT, "attendees").unwrap().unwrap().1;
+
+        let instrumented = InstrumentedReadDoc::new(&doc);
+        let hydrated: Meeting = hydrate(&instrumented).unwrap();
+        assert_eq!(hydrated, meeting);
+        let accesses = instrumented.take_accesses();
+        assert!(accesses.contains(&ReadAccess::Prop {
+            obj: automerge::ROOT,
+            prop: "title".into()
+        }));
+        assert!(accesses.contains(&ReadAccess::Prop {
+            obj: automerge::ROOT,
+            prop: "attendees".into()
+        }));
+        assert!(accesses.contains(&ReadAccess::Object(attendees.clone())));
+        assert!(accesses
+            .iter()
+            .all(|a| a.obj() == &automerge::ROOT || a.obj() == &attendees));
+
+        assert_eq!(instrumented.into_accesses(), BTreeSet::new());
+    }
+}
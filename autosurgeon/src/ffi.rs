@@ -0,0 +1,296 @@
+//! Alternative encodings for [`OsString`](std::ffi::OsString), [`CString`](std::ffi::CString) and
+//! [`PathBuf`](std::path::PathBuf)
+//!
+//! By default `OsString`, `OsStr`, `CString`, `CStr`, `PathBuf` and `Path` are reconciled as
+//! strings, and reconciling
+//! a value which is not valid UTF-8 fails with [`NotUtf8`](crate::reconcile::NotUtf8). The
+//! modules here can be used with `#[autosurgeon(with = "...")]` to choose a different encoding:
+//!
+//! * The `lossy` modules replace any invalid UTF-8 with U+FFFD REPLACEMENT CHARACTER. Reconciling
+//!   never fails, but a value which is not valid UTF-8 will not survive a round trip.
+//! * The `bytes` modules store the raw bytes as a [`automerge::ScalarValue::Bytes`]. Every value
+//!   survives a round trip, but other clients can't read the value as a string. They hydrate from
+//!   either bytes or a string, so a field can be switched from the default encoding without
+//!   migrating the document.
+//!
+//! [`os_string::bytes`] and [`path_buf::bytes`] are only available on Unix, where an `OsString` is
+//! an arbitrary sequence of bytes.
+//!
+//! ```
+//! # use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile};
+//! # use std::ffi::{CString, OsString};
+//! #[derive(Debug, PartialEq, Reconcile, Hydrate)]
+//! struct File {
+//!     #[autosurgeon(with = "autosurgeon::ffi::os_string::lossy")]
+//!     name: OsString,
+//!     #[autosurgeon(with = "autosurgeon::ffi::c_string::bytes")]
+//!     label: CString,
+//! }
+//!
+//! let file = File {
+//!     name: OsString::from("notes.txt"),
+//!     label: CString::new(vec![0xff, 0x01]).unwrap(),
+//! };
+//! let mut doc = automerge::AutoCommit::new();
+//! reconcile(&mut doc, &file).unwrap();
+//! let hydrated: File = hydrate(&doc).unwrap();
+//! assert_eq!(hydrated, file);
+//! ```
+
+/// Encodings for [`OsString`](std::ffi::OsString)
+pub mod os_string {
+    /// Store an `OsString` as a string, replacing invalid UTF-8
+    pub mod lossy {
+        use std::ffi::OsString;
+
+        use crate::{Hydrate, HydrateError, Prop, ReadDoc, Reconciler};
+
+        pub fn reconcile<R: Reconciler>(
+            value: &OsString,
+            mut reconciler: R,
+        ) -> Result<(), R::Error> {
+            reconciler.str(value.to_string_lossy())
+        }
+
+        pub fn hydrate<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<OsString, HydrateError> {
+            OsString::hydrate(doc, obj, prop)
+        }
+    }
+
+    /// Store an `OsString` as its raw bytes
+    #[cfg(unix)]
+    pub mod bytes {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+        use crate::{Hydrate, HydrateError, Prop, ReadDoc, Reconciler};
+
+        pub fn reconcile<R: Reconciler>(
+            value: &OsString,
+            mut reconciler: R,
+        ) -> Result<(), R::Error> {
+            reconciler.bytes(value.as_bytes())
+        }
+
+        pub fn hydrate<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<OsString, HydrateError> {
+            let super::super::RawBytes(bytes) = Hydrate::hydrate(doc, obj, prop)?;
+            Ok(OsString::from_vec(bytes))
+        }
+    }
+}
+
+/// Encodings for [`CString`](std::ffi::CString)
+pub mod c_string {
+    /// Store a `CString` as a string, replacing invalid UTF-8
+    pub mod lossy {
+        use std::ffi::CString;
+
+        use crate::{Hydrate, HydrateError, Prop, ReadDoc, Reconciler};
+
+        pub fn reconcile<R: Reconciler>(
+            value: &CString,
+            mut reconciler: R,
+        ) -> Result<(), R::Error> {
+            reconciler.str(value.to_string_lossy())
+        }
+
+        pub fn hydrate<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<CString, HydrateError> {
+            CString::hydrate(doc, obj, prop)
+        }
+    }
+
+    /// Store a `CString` as its raw bytes, without the trailing NUL
+    pub mod bytes {
+        use std::ffi::CString;
+
+        use crate::{Hydrate, HydrateError, Prop, ReadDoc, Reconciler};
+
+        pub fn reconcile<R: Reconciler>(
+            value: &CString,
+            mut reconciler: R,
+        ) -> Result<(), R::Error> {
+            reconciler.bytes(value.as_bytes())
+        }
+
+        pub fn hydrate<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<CString, HydrateError> {
+            let super::super::RawBytes(bytes) = Hydrate::hydrate(doc, obj, prop)?;
+            CString::new(bytes).map_err(|_| {
+                HydrateError::unexpected(
+                    "bytes without NUL bytes",
+                    "bytes containing a NUL byte".to_string(),
+                )
+            })
+        }
+    }
+}
+
+/// Encodings for [`PathBuf`](std::path::PathBuf)
+pub mod path_buf {
+    /// Store a `PathBuf` as a string, replacing invalid UTF-8
+    pub mod lossy {
+        use std::path::PathBuf;
+
+        use crate::{Hydrate, HydrateError, Prop, ReadDoc, Reconciler};
+
+        pub fn reconcile<R: Reconciler>(
+            value: &std::path::Path,
+            mut reconciler: R,
+        ) -> Result<(), R::Error> {
+            reconciler.str(value.to_string_lossy())
+        }
+
+        pub fn hydrate<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<PathBuf, HydrateError> {
+            PathBuf::hydrate(doc, obj, prop)
+        }
+    }
+
+    /// Store a `PathBuf` as its raw bytes
+    #[cfg(unix)]
+    pub mod bytes {
+        use std::os::unix::ffi::OsStrExt;
+        use std::path::PathBuf;
+
+        use crate::{HydrateError, Prop, ReadDoc, Reconciler};
+
+        pub fn reconcile<R: Reconciler>(
+            value: &std::path::Path,
+            mut reconciler: R,
+        ) -> Result<(), R::Error> {
+            reconciler.bytes(value.as_os_str().as_bytes())
+        }
+
+        pub fn hydrate<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<PathBuf, HydrateError> {
+            super::super::os_string::bytes::hydrate(doc, obj, prop).map(PathBuf::from)
+        }
+    }
+}
+
+/// Bytes hydrated from either a bytes scalar or a string
+struct RawBytes(Vec<u8>);
+
+impl crate::Hydrate for RawBytes {
+    fn hydrate_bytes(bytes: &[u8]) -> Result<Self, crate::HydrateError> {
+        Ok(Self(bytes.to_vec()))
+    }
+
+    fn hydrate_string(s: &'_ str) -> Result<Self, crate::HydrateError> {
+        Ok(Self(s.as_bytes().to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use automerge_test::{assert_doc, map};
+
+    use crate::{hydrate_prop, reconcile_prop, Hydrate, ReadDoc, Reconcile};
+
+    macro_rules! encoded {
+        ($name:ident, $ty:ty, $($module:ident)::+) => {
+            struct $name($ty);
+
+            impl Reconcile for $name {
+                type Key<'a> = crate::reconcile::NoKey;
+
+                fn reconcile<R: crate::Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+                    $($module)::+::reconcile(&self.0, reconciler)
+                }
+            }
+
+            impl Hydrate for $name {
+                fn hydrate<D: ReadDoc>(
+                    doc: &D,
+                    obj: &automerge::ObjId,
+                    prop: crate::Prop<'_>,
+                ) -> Result<Self, crate::HydrateError> {
+                    $($module)::+::hydrate(doc, obj, prop).map(Self)
+                }
+            }
+        };
+    }
+
+    encoded!(LossyCString, CString, super::c_string::lossy);
+    encoded!(BytesCString, CString, super::c_string::bytes);
+
+    #[test]
+    fn lossy_and_bytes_encodings() {
+        let invalid = CString::new(vec![b'a', 0xff]).unwrap();
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "lossy",
+            LossyCString(invalid.clone()),
+        )
+        .unwrap();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "bytes",
+            BytesCString(invalid.clone()),
+        )
+        .unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "string", "valid").unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "lossy" => { "a\u{fffd}" },
+                "bytes" => { vec![b'a', 0xff] },
+                "string" => { "valid" },
+            }
+        );
+
+        let BytesCString(bytes) = hydrate_prop(&doc, &automerge::ROOT, "bytes").unwrap();
+        assert_eq!(bytes, invalid);
+        let BytesCString(string) = hydrate_prop(&doc, &automerge::ROOT, "string").unwrap();
+        assert_eq!(string.to_str(), Ok("valid"));
+        let LossyCString(lossy) = hydrate_prop(&doc, &automerge::ROOT, "lossy").unwrap();
+        assert_eq!(lossy.to_str(), Ok("a\u{fffd}"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn os_string_bytes_round_trip() {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+
+        encoded!(BytesOsString, OsString, super::os_string::bytes);
+
+        let invalid = OsString::from_vec(vec![b'a', 0xff]);
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "path",
+            BytesOsString(invalid.clone()),
+        )
+        .unwrap();
+        let BytesOsString(hydrated) = hydrate_prop(&doc, &automerge::ROOT, "path").unwrap();
+        assert_eq!(hydrated, invalid);
+    }
+}
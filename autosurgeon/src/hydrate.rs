@@ -5,6 +5,11 @@ use crate::{Prop, ReadDoc};
 
 mod impls;
 pub(crate) mod map;
+mod resolve;
+pub use resolve::{
+    hydrate_prop_with_resolver, hydrate_with_resolver, ConflictResolver, PreferActor, PreferMax,
+    ResolvingReadDoc,
+};
 
 /// A type which can be hydrated from an automerge document
 ///
@@ -27,14 +32,7 @@ pub trait Hydrate: Sized {
         obj: &automerge::ObjId,
         prop: Prop<'_>,
     ) -> Result<Self, HydrateError> {
-        match doc.get(obj, &prop)? {
-            None => Self::hydrate_none(),
-            Some((Value::Object(ObjType::Map), id)) => Self::hydrate_map(doc, &id),
-            Some((Value::Object(ObjType::Table), id)) => Self::hydrate_map(doc, &id),
-            Some((Value::Object(ObjType::List), id)) => Self::hydrate_seq(doc, &id),
-            Some((Value::Object(ObjType::Text), id)) => Self::hydrate_text(doc, &id),
-            Some((Value::Scalar(v), _)) => Self::hydrate_scalar(v),
-        }
+        hydrate_by_type(doc, obj, &prop)
     }
 
     fn hydrate_scalar(s: Cow<'_, automerge::ScalarValue>) -> Result<Self, HydrateError> {
@@ -103,11 +101,98 @@ pub trait Hydrate: Sized {
     fn hydrate_none() -> Result<Self, HydrateError> {
         Err(HydrateError::Unexpected(Unexpected::None))
     }
+
+    /// Hydrate an object of a type which this version of `autosurgeon` doesn't know about
+    ///
+    /// This is only called for object types which are added by versions of automerge newer than
+    /// the one this crate was written against. See [`crate::PreservedObject`] for a way to keep
+    /// such objects intact.
+    fn hydrate_unknown_object<D: ReadDoc>(
+        _doc: &D,
+        _obj: &automerge::ObjId,
+        _obj_type: ObjType,
+    ) -> Result<Self, HydrateError> {
+        Err(HydrateError::Unexpected(Unexpected::UnknownObject))
+    }
+}
+
+/// The default implementation of [`Hydrate::hydrate`], which calls the `hydrate_*` method for the
+/// type of the value at `prop`
+///
+/// This is for implementations of `hydrate` which need to do something extra, such as remember
+/// where the value came from, but otherwise hydrate as usual.
+pub(crate) fn hydrate_by_type<D: ReadDoc, H: Hydrate>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: &Prop<'_>,
+) -> Result<H, HydrateError> {
+    match doc.get(obj, prop)? {
+        None => H::hydrate_none(),
+        Some((Value::Object(ObjType::Map), id)) => H::hydrate_map(doc, &id),
+        Some((Value::Object(ObjType::Table), id)) => H::hydrate_map(doc, &id),
+        Some((Value::Object(ObjType::List), id)) => H::hydrate_seq(doc, &id),
+        Some((Value::Object(ObjType::Text), id)) => H::hydrate_text(doc, &id),
+        // Object types added in versions of automerge newer than this crate knows about
+        #[allow(unreachable_patterns)]
+        Some((Value::Object(obj_type), id)) => H::hydrate_unknown_object(doc, &id, obj_type),
+        Some((Value::Scalar(v), _)) => H::hydrate_scalar(v),
+    }
 }
 
 /// Hydrate an instance of `H` from `doc`
 pub fn hydrate<D: ReadDoc, H: Hydrate>(doc: &D) -> Result<H, HydrateError> {
-    H::hydrate_map(doc, &automerge::ROOT)
+    crate::trace::hydrate(doc, &automerge::ROOT, || {
+        H::hydrate_map(doc, &automerge::ROOT)
+    })
+}
+
+/// Hydrate whichever fields of `H` are present in `doc`
+///
+/// Unlike [`hydrate`] this succeeds when fields are missing, which is useful for reading documents
+/// written by older or newer versions of a schema. See [`HydratePartial`].
+pub fn hydrate_partial<D: ReadDoc, H: HydratePartial>(doc: &D) -> Result<H::Partial, HydrateError> {
+    crate::trace::hydrate(doc, &automerge::ROOT, || {
+        H::Partial::hydrate_map(doc, &automerge::ROOT)
+    })
+}
+
+/// Hydrate whichever fields of `H` are present in `doc` and valid, along with the error for every
+/// field which failed to hydrate
+///
+/// Where [`hydrate_partial`] fails on the first field which can't be hydrated, this carries on
+/// and treats each such field as missing, so that tools which diagnose corrupted documents can
+/// report every problem at once. The errors are in the same order as the fields of `H` and each
+/// includes the path of its field. A field whose type is itself a struct is either hydrated
+/// completely or reported as a single error.
+///
+/// ```rust
+/// # use autosurgeon::{hydrate_lossy, Hydrate, MaybeMissing};
+/// # use automerge::transaction::Transactable;
+/// #[derive(Hydrate)]
+/// #[autosurgeon(partial)]
+/// struct Settings {
+///     theme: String,
+///     font_size: u64,
+///     line_height: u64,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// doc.put(&automerge::ROOT, "theme", 3_u64).unwrap();
+/// doc.put(&automerge::ROOT, "font_size", "large").unwrap();
+/// doc.put(&automerge::ROOT, "line_height", 2_u64).unwrap();
+///
+/// let (partial, errors) = hydrate_lossy::<_, Settings>(&doc);
+/// assert_eq!(partial.line_height, MaybeMissing::Present(2));
+/// assert_eq!(partial.missing_fields(), vec!["theme", "font_size"]);
+/// assert_eq!(errors[0].to_string(), "at theme: unexpected uint");
+/// assert_eq!(errors[1].to_string(), "at font_size: unexpected string");
+/// ```
+pub fn hydrate_lossy<D: ReadDoc, H: HydratePartial>(doc: &D) -> (H::Partial, Vec<HydrateError>) {
+    crate::trace::hydrate(doc, &automerge::ROOT, || {
+        let mut errors = Vec::new();
+        let partial = H::hydrate_lossy(doc, &automerge::ROOT, &mut errors);
+        (partial, errors)
+    })
 }
 
 /// Hydrate an instance of `H` located at property `prop` of object `obj`
@@ -116,13 +201,76 @@ pub fn hydrate_prop<'a, D: ReadDoc, H: Hydrate, P: Into<Prop<'a>>, O: AsRef<auto
     obj: O,
     prop: P,
 ) -> Result<H, HydrateError> {
-    H::hydrate(doc, obj.as_ref(), prop.into())
+    let prop = prop.into();
+    H::hydrate(doc, obj.as_ref(), prop.clone()).map_err(|e| e.in_prop(&prop))
+}
+
+/// Hydrate an instance of `H` from `doc` as at `heads`
+///
+/// This is useful for displaying the history of a document without having to fork it. See
+/// [`crate::AtHeads`] if you need to read a past version of the document in some other way.
+pub fn hydrate_at<D: ReadDoc, H: Hydrate>(
+    doc: &D,
+    heads: &[automerge::ChangeHash],
+) -> Result<H, HydrateError> {
+    hydrate(&crate::AtHeads::new(doc, heads))
+}
+
+/// Hydrate an instance of `H` located at property `prop` of object `obj` as at `heads`
+pub fn hydrate_prop_at<
+    'a,
+    D: ReadDoc,
+    H: Hydrate,
+    P: Into<Prop<'a>>,
+    O: AsRef<automerge::ObjId>,
+>(
+    doc: &D,
+    obj: O,
+    prop: P,
+    heads: &[automerge::ChangeHash],
+) -> Result<H, HydrateError> {
+    hydrate_prop(&crate::AtHeads::new(doc, heads), obj, prop)
+}
+
+/// Load a document from its serialized form and hydrate an instance of `H` from it
+///
+/// This is a shortcut for [`automerge::Automerge::load`] followed by [`hydrate`], for when you
+/// only need to read the document. Errors loading the document are returned as
+/// [`HydrateError::Automerge`].
+///
+/// ```rust
+/// # use autosurgeon::{hydrate_from_bytes, reconcile, Hydrate, Reconcile};
+/// #[derive(Debug, PartialEq, Reconcile, Hydrate)]
+/// struct Config {
+///     name: String,
+/// }
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile(&mut doc, &Config { name: "server".to_string() }).unwrap();
+/// let bytes = doc.save();
+///
+/// let config: Config = hydrate_from_bytes(&bytes).unwrap();
+/// assert_eq!(config.name, "server");
+/// ```
+pub fn hydrate_from_bytes<H: Hydrate>(bytes: &[u8]) -> Result<H, HydrateError> {
+    let doc = automerge::Automerge::load(bytes)?;
+    hydrate(&doc)
+}
+
+/// Load a document from its serialized form and hydrate an instance of `H` from it as at `heads`
+///
+/// See [`hydrate_from_bytes`] and [`hydrate_at`].
+pub fn hydrate_from_bytes_at<H: Hydrate>(
+    bytes: &[u8],
+    heads: &[automerge::ChangeHash],
+) -> Result<H, HydrateError> {
+    let doc = automerge::Automerge::load(bytes)?;
+    hydrate_at(&doc, heads)
 }
 
 /// Hydrate an instance of `H` located at a path in the document
 ///
-/// The path must be an iterator of properties which start at `obj`. If any of the properties does
-/// not exist this will return `Ok(None)`
+/// The path must be an iterator of properties which start at `obj`, such as a reference to a
+/// [`Path`](crate::Path). If any of the properties does not exist this will return `Ok(None)`
 pub fn hydrate_path<'a, D: ReadDoc, H: Hydrate, P: IntoIterator<Item = Prop<'a>>>(
     doc: &D,
     obj: &automerge::ObjId,
@@ -195,6 +343,19 @@ pub enum HydrateError {
     Unexpected(Unexpected),
     #[error("map key parse error: {0}")]
     ParseMapKey(Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// The map contained keys which don't correspond to any field of a struct with
+    /// `#[autosurgeon(deny_unknown_fields)]`
+    #[error("unknown fields: {}", .0.join(", "))]
+    UnknownFields(Vec<String>),
+    /// An error which occurred while hydrating the value at `path`
+    ///
+    /// `path` is relative to the object which was being hydrated when the error occurred, so for
+    /// errors returned by [`hydrate_prop`] it starts with the `prop` which was passed in.
+    #[error("at {}: {error}", crate::prop::DisplayPath(.path))]
+    AtPath {
+        path: crate::Path,
+        error: Box<HydrateError>,
+    },
 }
 
 impl HydrateError {
@@ -218,6 +379,40 @@ impl HydrateError {
             found,
         })
     }
+
+    /// Record that this error occurred while hydrating the value at `prop`
+    ///
+    /// The property is added to the front of the path of the error, so this should be called as
+    /// the error propagates up out of nested objects.
+    pub fn in_prop<P: Into<automerge::Prop>>(self, prop: P) -> Self {
+        match self {
+            HydrateError::AtPath { mut path, error } => {
+                path.push_front(prop.into());
+                HydrateError::AtPath { path, error }
+            }
+            other => HydrateError::AtPath {
+                path: crate::path![prop],
+                error: Box::new(other),
+            },
+        }
+    }
+
+    /// The path to the value which failed to hydrate, empty if the path is not known
+    pub fn path(&self) -> &crate::Path {
+        static EMPTY: crate::Path = crate::Path::new();
+        match self {
+            HydrateError::AtPath { path, .. } => path,
+            _ => &EMPTY,
+        }
+    }
+
+    /// The underlying error, without any path information
+    pub fn without_path(self) -> HydrateError {
+        match self {
+            HydrateError::AtPath { error, .. } => *error,
+            other => other,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -234,6 +429,7 @@ pub enum Unexpected {
     String,
     Timestamp,
     Unknown,
+    UnknownObject,
     None,
     Other { expected: String, found: String },
 }
@@ -253,6 +449,7 @@ impl std::fmt::Display for Unexpected {
             Self::String => write!(f, "string"),
             Self::Timestamp => write!(f, "timestamp"),
             Self::Unknown => write!(f, "unknown"),
+            Self::UnknownObject => write!(f, "unknown object type"),
             Self::None => write!(f, "None"),
             Self::Other { expected, found } => write!(f, "{}, expected {}", found, expected),
         }
@@ -269,7 +466,8 @@ impl<T> HydrateResultExt<Option<T>> for Result<Option<T>, HydrateError> {
             Ok(v) => Ok(v),
             Err(HydrateError::Unexpected(_)) => Ok(None),
             Err(HydrateError::Automerge(e)) => Err(e),
-            Err(HydrateError::ParseMapKey(_)) => Ok(None),
+            Err(HydrateError::ParseMapKey(_) | HydrateError::UnknownFields(_)) => Ok(None),
+            Err(HydrateError::AtPath { error, .. }) => Err(*error).strip_unexpected(),
         }
     }
 }
@@ -332,8 +530,10 @@ impl<T: Hydrate> Hydrate for MaybeMissing<T> {
     }
 }
 
+/// Reconciling `Missing` does nothing, so whatever is in the document is left there
 impl<T: crate::Reconcile> crate::Reconcile for MaybeMissing<T> {
     type Key<'a> = T::Key<'a>;
+    const KEY_IMMUTABLE: bool = T::KEY_IMMUTABLE;
 
     fn reconcile<R: crate::Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
         match self {
@@ -341,9 +541,87 @@ impl<T: crate::Reconcile> crate::Reconcile for MaybeMissing<T> {
             Self::Present(val) => val.reconcile(reconciler),
         }
     }
+
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<crate::reconcile::LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+        match doc.get(obj, &prop)? {
+            None => Ok(crate::reconcile::LoadKey::KeyNotFound),
+            Some(_) => T::hydrate_key(doc, obj, prop),
+        }
+    }
+
+    fn key(&self) -> crate::reconcile::LoadKey<Self::Key<'_>> {
+        match self {
+            Self::Missing => crate::reconcile::LoadKey::KeyNotFound,
+            Self::Present(val) => val.key(),
+        }
+    }
+
+    fn digest(&self) -> Option<u64> {
+        match self {
+            Self::Missing => None,
+            Self::Present(val) => val.digest(),
+        }
+    }
 }
 
 impl<T> MaybeMissing<T> {
+    /// Whether this is [`MaybeMissing::Missing`]
+    pub fn is_missing(&self) -> bool {
+        matches!(self, MaybeMissing::Missing)
+    }
+
+    /// Whether this is [`MaybeMissing::Present`]
+    pub fn is_present(&self) -> bool {
+        matches!(self, MaybeMissing::Present(_))
+    }
+
+    /// The value, if it is present
+    pub fn present(self) -> Option<T> {
+        match self {
+            MaybeMissing::Present(x) => Some(x),
+            MaybeMissing::Missing => None,
+        }
+    }
+
+    pub fn as_ref(&self) -> MaybeMissing<&T> {
+        match self {
+            MaybeMissing::Present(x) => MaybeMissing::Present(x),
+            MaybeMissing::Missing => MaybeMissing::Missing,
+        }
+    }
+
+    pub fn as_mut(&mut self) -> MaybeMissing<&mut T> {
+        match self {
+            MaybeMissing::Present(x) => MaybeMissing::Present(x),
+            MaybeMissing::Missing => MaybeMissing::Missing,
+        }
+    }
+
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> MaybeMissing<U> {
+        match self {
+            MaybeMissing::Present(x) => MaybeMissing::Present(f(x)),
+            MaybeMissing::Missing => MaybeMissing::Missing,
+        }
+    }
+
+    pub fn and_then<U, F: FnOnce(T) -> MaybeMissing<U>>(self, f: F) -> MaybeMissing<U> {
+        match self {
+            MaybeMissing::Present(x) => f(x),
+            MaybeMissing::Missing => MaybeMissing::Missing,
+        }
+    }
+
+    pub fn unwrap_or(self, default: T) -> T {
+        match self {
+            MaybeMissing::Present(x) => x,
+            MaybeMissing::Missing => default,
+        }
+    }
+
     pub fn unwrap_or_else<F>(self, f: F) -> T
     where
         F: FnOnce() -> T,
@@ -353,6 +631,70 @@ impl<T> MaybeMissing<T> {
             MaybeMissing::Missing => f(),
         }
     }
+
+    pub fn unwrap_or_default(self) -> T
+    where
+        T: Default,
+    {
+        self.unwrap_or_else(T::default)
+    }
+}
+
+/// `None` is `Missing`
+impl<T> From<Option<T>> for MaybeMissing<T> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(x) => MaybeMissing::Present(x),
+            None => MaybeMissing::Missing,
+        }
+    }
+}
+
+/// `Missing` is `None`
+impl<T> From<MaybeMissing<T>> for Option<T> {
+    fn from(value: MaybeMissing<T>) -> Self {
+        value.present()
+    }
+}
+
+/// A struct with a companion type in which every field may be missing
+///
+/// This is implemented by `#[derive(Hydrate)]` for structs with the `#[autosurgeon(partial)]`
+/// attribute. The generated type is named after the struct with a `Partial` suffix, and each of
+/// its fields is a [`MaybeMissing`] of the corresponding field of the struct. It also has a
+/// `missing_fields` method which returns the names of the missing fields, and a `complete` method
+/// which converts it into the struct if nothing required is missing.
+///
+/// ```rust
+/// # use autosurgeon::{hydrate_partial, Hydrate, MaybeMissing};
+/// # use automerge::transaction::Transactable;
+/// #[derive(Hydrate)]
+/// #[autosurgeon(partial)]
+/// struct Settings {
+///     theme: String,
+///     font_size: u64,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// doc.put(&automerge::ROOT, "theme", "dark").unwrap();
+///
+/// let partial = hydrate_partial::<_, Settings>(&doc).unwrap();
+/// assert_eq!(partial.theme, MaybeMissing::Present("dark".to_string()));
+/// assert_eq!(partial.missing_fields(), vec!["font_size"]);
+/// assert!(partial.complete().is_err());
+/// ```
+pub trait HydratePartial {
+    type Partial: Hydrate;
+
+    /// Hydrate the partial type from the map `obj`, pushing the error for each field which fails
+    /// to hydrate to `errors` and treating that field as missing
+    ///
+    /// See [`hydrate_lossy`]
+    fn hydrate_lossy<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        errors: &mut Vec<HydrateError>,
+    ) -> Self::Partial;
 }
 
 #[cfg(test)]
@@ -537,4 +879,238 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn hydrate_at_heads() {
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(automerge::ROOT, "name", "Unseen University")
+            .unwrap();
+        let employees = doc
+            .put_object(automerge::ROOT, "employees", ObjType::List)
+            .unwrap();
+        let ridcully = doc.insert_object(&employees, 0, ObjType::Map).unwrap();
+        doc.put(&ridcully, "name", "Mustrum Ridcully").unwrap();
+        doc.put(&ridcully, "number", 1_u64).unwrap();
+        doc.commit();
+        let before = doc.get_heads();
+
+        doc.put(automerge::ROOT, "name", "UU").unwrap();
+        doc.put(&ridcully, "number", 2_u64).unwrap();
+        let stibbons = doc.insert_object(&employees, 1, ObjType::Map).unwrap();
+        doc.put(&stibbons, "name", "Ponder Stibbons").unwrap();
+        doc.put(&stibbons, "number", 3_u64).unwrap();
+
+        let old: Company = hydrate_at(&doc, &before).unwrap();
+        assert_eq!(
+            old,
+            Company {
+                name: "Unseen University".to_string(),
+                employees: vec![Employee {
+                    name: "Mustrum Ridcully".to_string(),
+                    number: 1,
+                }],
+            }
+        );
+
+        let old_ridcully: Employee = hydrate_prop_at(&doc, &employees, 0_usize, &before).unwrap();
+        assert_eq!(old_ridcully.number, 1);
+
+        let now: Company = hydrate(&doc).unwrap();
+        assert_eq!(now.name, "UU");
+        assert_eq!(now.employees.len(), 2);
+    }
+
+    #[test]
+    fn hydrate_from_saved_bytes() {
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(automerge::ROOT, "name", "Unseen University")
+            .unwrap();
+        doc.put_object(automerge::ROOT, "employees", ObjType::List)
+            .unwrap();
+        doc.commit();
+        let before = doc.get_heads();
+        doc.put(automerge::ROOT, "name", "UU").unwrap();
+        let bytes = doc.save();
+
+        let now: Company = hydrate_from_bytes(&bytes).unwrap();
+        assert_eq!(now.name, "UU");
+        let old: Company = hydrate_from_bytes_at(&bytes, &before).unwrap();
+        assert_eq!(old.name, "Unseen University");
+
+        assert!(matches!(
+            hydrate_from_bytes::<Company>(b"not a document"),
+            Err(HydrateError::Automerge(_))
+        ));
+    }
+
+    #[test]
+    fn hydrate_nonzero_and_wrapping() {
+        use std::num::{NonZeroI16, NonZeroU32, Wrapping};
+
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(automerge::ROOT, "count", 3_u64).unwrap();
+        doc.put(automerge::ROOT, "offset", -2_i64).unwrap();
+        doc.put(automerge::ROOT, "zero", 0_u64).unwrap();
+
+        let count: NonZeroU32 = hydrate_prop(&doc, &automerge::ROOT, "count").unwrap();
+        assert_eq!(count.get(), 3);
+        let offset: NonZeroI16 = hydrate_prop(&doc, &automerge::ROOT, "offset").unwrap();
+        assert_eq!(offset.get(), -2);
+        let zero: Result<NonZeroU32, _> = hydrate_prop(&doc, &automerge::ROOT, "zero");
+        assert!(zero.is_err());
+        let wrapping: Wrapping<u8> = hydrate_prop(&doc, &automerge::ROOT, "count").unwrap();
+        assert_eq!(wrapping, Wrapping(3));
+    }
+
+    #[test]
+    fn hydrate_atomics_and_cells() {
+        use std::cell::{Cell, RefCell};
+        use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(automerge::ROOT, "offset", -2_i64).unwrap();
+        doc.put(automerge::ROOT, "enabled", true).unwrap();
+        doc.put(automerge::ROOT, "name", "sam").unwrap();
+
+        let offset: AtomicI32 = hydrate_prop(&doc, &automerge::ROOT, "offset").unwrap();
+        assert_eq!(offset.load(Ordering::SeqCst), -2);
+        let enabled: AtomicBool = hydrate_prop(&doc, &automerge::ROOT, "enabled").unwrap();
+        assert!(enabled.load(Ordering::SeqCst));
+        let cell: Cell<i64> = hydrate_prop(&doc, &automerge::ROOT, "offset").unwrap();
+        assert_eq!(cell.get(), -2);
+        let name: RefCell<String> = hydrate_prop(&doc, &automerge::ROOT, "name").unwrap();
+        assert_eq!(*name.borrow(), "sam");
+    }
+
+    #[test]
+    fn rc_and_arc_round_trip() {
+        use std::{rc::Rc, sync::Arc};
+
+        let mut doc = automerge::AutoCommit::new();
+        let shared = Rc::new(vec!["a".to_string(), "b".to_string()]);
+        let name: Arc<str> = Arc::from("sam");
+        crate::reconcile_prop(&mut doc, automerge::ROOT, "shared", &shared).unwrap();
+        crate::reconcile_prop(&mut doc, automerge::ROOT, "name", &name).unwrap();
+
+        let hydrated: Rc<Vec<String>> = hydrate_prop(&doc, &automerge::ROOT, "shared").unwrap();
+        assert_eq!(hydrated, shared);
+        let hydrated: Arc<String> = hydrate_prop(&doc, &automerge::ROOT, "name").unwrap();
+        assert_eq!(*hydrated, "sam");
+    }
+
+    #[test]
+    fn hydrate_os_and_c_strings() {
+        use std::ffi::{CString, OsString};
+
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(automerge::ROOT, "path", "a.txt").unwrap();
+        doc.put(automerge::ROOT, "nul", "a\0b").unwrap();
+
+        let path: OsString = hydrate_prop(&doc, &automerge::ROOT, "path").unwrap();
+        assert_eq!(path, "a.txt");
+        let label: CString = hydrate_prop(&doc, &automerge::ROOT, "path").unwrap();
+        assert_eq!(label.to_str(), Ok("a.txt"));
+        let nul: Result<CString, _> = hydrate_prop(&doc, &automerge::ROOT, "nul");
+        assert!(nul.is_err());
+    }
+
+    #[test]
+    fn errors_include_path() {
+        let mut doc = automerge::AutoCommit::new();
+        let products = doc
+            .put_object(automerge::ROOT, "products", ObjType::List)
+            .unwrap();
+        let first = doc.insert_object(&products, 0, ObjType::Map).unwrap();
+        doc.put(&first, "price", 1_u64).unwrap();
+        let second = doc.insert_object(&products, 1, ObjType::Map).unwrap();
+        doc.put(&second, "price", "free").unwrap();
+
+        let err =
+            hydrate_prop::<_, Vec<HashMap<String, u64>>, _, _>(&doc, &automerge::ROOT, "products")
+                .unwrap_err();
+        assert_eq!(err.to_string(), "at products[1].price: unexpected string");
+        assert_eq!(err.path(), &crate::path!["products", 1_usize, "price"]);
+        assert!(matches!(
+            err.without_path(),
+            HydrateError::Unexpected(Unexpected::String)
+        ));
+    }
+
+    #[test]
+    fn fixed_size_arrays() {
+        let mut doc = automerge::AutoCommit::new();
+        let position = [1.0, 2.5, -3.0];
+        crate::reconcile_prop(&mut doc, automerge::ROOT, "position", position).unwrap();
+        let hydrated: [f64; 3] = hydrate_prop(&doc, &automerge::ROOT, "position").unwrap();
+        assert_eq!(hydrated, position);
+
+        crate::reconcile_prop(&mut doc, automerge::ROOT, "position", [0.0, 1.0]).unwrap();
+        let err =
+            hydrate_prop::<_, [f64; 3], _, _>(&doc, &automerge::ROOT, "position").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at position: unexpected a list of length 2, expected a list of length 3"
+        );
+    }
+
+    #[test]
+    fn chars_and_wide_ints() {
+        let mut doc = automerge::AutoCommit::new();
+        crate::reconcile_prop(&mut doc, automerge::ROOT, "char", 'é').unwrap();
+        crate::reconcile_prop(&mut doc, automerge::ROOT, "big", u128::MAX).unwrap();
+        crate::reconcile_prop(&mut doc, automerge::ROOT, "small", i128::MIN).unwrap();
+        doc.put(automerge::ROOT, "int", -5_i64).unwrap();
+
+        assert_eq!(
+            doc.get(&automerge::ROOT, "big").unwrap().unwrap().0,
+            automerge::Value::str("340282366920938463463374607431768211455")
+        );
+        assert_eq!(
+            hydrate_prop::<_, char, _, _>(&doc, &automerge::ROOT, "char").unwrap(),
+            'é'
+        );
+        assert_eq!(
+            hydrate_prop::<_, u128, _, _>(&doc, &automerge::ROOT, "big").unwrap(),
+            u128::MAX
+        );
+        assert_eq!(
+            hydrate_prop::<_, i128, _, _>(&doc, &automerge::ROOT, "small").unwrap(),
+            i128::MIN
+        );
+        assert_eq!(
+            hydrate_prop::<_, i128, _, _>(&doc, &automerge::ROOT, "int").unwrap(),
+            -5
+        );
+        assert!(hydrate_prop::<_, u128, _, _>(&doc, &automerge::ROOT, "int").is_err());
+        assert!(hydrate_prop::<_, char, _, _>(&doc, &automerge::ROOT, "big").is_err());
+    }
+
+    #[test]
+    fn maybe_missing_replaces_option() {
+        assert_eq!(MaybeMissing::from(Some(1)), MaybeMissing::Present(1));
+        assert_eq!(MaybeMissing::<u8>::from(None), MaybeMissing::Missing);
+        assert_eq!(Option::from(MaybeMissing::Present(1)), Some(1));
+        assert_eq!(MaybeMissing::Present(2).map(|x| x * 2).unwrap_or(0), 4);
+        assert_eq!(
+            MaybeMissing::Present(2).and_then(|_| MaybeMissing::<u8>::Missing),
+            MaybeMissing::Missing
+        );
+        assert_eq!(MaybeMissing::<String>::Missing.unwrap_or_default(), "");
+
+        // Missing values leave the document alone, present values are written
+        let mut doc = automerge::AutoCommit::new();
+        let names = HashMap::from([
+            ("a", MaybeMissing::Present("one")),
+            ("b", MaybeMissing::Present("two")),
+        ]);
+        crate::reconcile(&mut doc, &names).unwrap();
+        let names = HashMap::from([
+            ("a", MaybeMissing::Missing),
+            ("b", MaybeMissing::Present("three")),
+        ]);
+        crate::reconcile(&mut doc, &names).unwrap();
+        let hydrated: HashMap<String, String> = hydrate(&doc).unwrap();
+        assert_eq!(hydrated["a"], "one");
+        assert_eq!(hydrated["b"], "three");
+    }
 }
@@ -8,6 +8,47 @@ impl Hydrate for String {
     }
 }
 
+impl Hydrate for std::ffi::OsString {
+    fn hydrate_string(s: &'_ str) -> Result<Self, HydrateError> {
+        Ok(s.into())
+    }
+}
+
+impl Hydrate for std::path::PathBuf {
+    fn hydrate_string(s: &'_ str) -> Result<Self, HydrateError> {
+        Ok(s.into())
+    }
+}
+
+macro_rules! parse_impl {
+    ($ty:ty, $expected:literal) => {
+        impl Hydrate for $ty {
+            fn hydrate_string(s: &'_ str) -> Result<Self, HydrateError> {
+                s.parse()
+                    .map_err(|_| HydrateError::unexpected($expected, format!("{:?}", s)))
+            }
+        }
+    };
+}
+
+parse_impl!(std::net::IpAddr, "an IP address");
+parse_impl!(std::net::Ipv4Addr, "an IPv4 address");
+parse_impl!(std::net::Ipv6Addr, "an IPv6 address");
+parse_impl!(std::net::SocketAddr, "a socket address");
+parse_impl!(std::net::SocketAddrV4, "an IPv4 socket address");
+parse_impl!(std::net::SocketAddrV6, "an IPv6 socket address");
+
+impl Hydrate for std::ffi::CString {
+    fn hydrate_string(s: &'_ str) -> Result<Self, HydrateError> {
+        std::ffi::CString::new(s).map_err(|_| {
+            HydrateError::unexpected(
+                "a string without NUL bytes",
+                "a string containing a NUL byte".to_string(),
+            )
+        })
+    }
+}
+
 impl<T> Hydrate for Vec<T>
 where
     T: Hydrate,
@@ -22,6 +63,60 @@ where
     }
 }
 
+impl<T, const N: usize> Hydrate for [T; N]
+where
+    T: Hydrate,
+{
+    fn hydrate_seq<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        let len = doc.length(obj);
+        if len != N {
+            return Err(HydrateError::unexpected(
+                format!("a list of length {}", N),
+                format!("a list of length {}", len),
+            ));
+        }
+        let items = Vec::hydrate_seq(doc, obj)?;
+        Ok(items
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("the length was checked above")))
+    }
+}
+
+impl<T> Hydrate for std::collections::VecDeque<T>
+where
+    T: Hydrate,
+{
+    fn hydrate_seq<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        Ok(Vec::hydrate_seq(doc, obj)?.into())
+    }
+}
+
+impl<T> Hydrate for std::collections::LinkedList<T>
+where
+    T: Hydrate,
+{
+    fn hydrate_seq<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        (0..doc.length(obj))
+            .map(|idx| hydrate_prop(doc, obj, idx))
+            .collect()
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A> Hydrate for smallvec::SmallVec<A>
+where
+    A: smallvec::Array,
+    A::Item: Hydrate,
+{
+    fn hydrate_seq<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        let mut result = smallvec::SmallVec::with_capacity(doc.length(obj));
+        for idx in 0..doc.length(obj) {
+            result.push(hydrate_prop(doc, obj, idx)?);
+        }
+        Ok(result)
+    }
+}
+
 macro_rules! int_impl {
     ($ty:ident, $hydrator: ident, $from_ty:ident) => {
         impl Hydrate for $ty {
@@ -46,6 +141,130 @@ int_impl!(i16, hydrate_int, i64);
 int_impl!(i32, hydrate_int, i64);
 int_impl!(i64, hydrate_int, i64);
 
+// Reconciled as decimal strings, but also hydrated from integers so that a field can be widened
+// from a 64 bit integer without migrating the document
+macro_rules! wide_int_impl {
+    ($ty:ident) => {
+        impl Hydrate for $ty {
+            fn hydrate_string(s: &'_ str) -> Result<Self, HydrateError> {
+                s.parse().map_err(|_| {
+                    HydrateError::unexpected(
+                        concat!("a string containing a ", stringify!($ty)),
+                        format!("{:?}", s),
+                    )
+                })
+            }
+
+            fn hydrate_int(i: i64) -> Result<Self, HydrateError> {
+                i.try_into().map_err(|_| {
+                    HydrateError::unexpected(
+                        concat!("a ", stringify!($ty)),
+                        "a negative integer".to_string(),
+                    )
+                })
+            }
+
+            fn hydrate_uint(u: u64) -> Result<Self, HydrateError> {
+                Ok(u.into())
+            }
+        }
+    };
+}
+
+wide_int_impl!(u128);
+wide_int_impl!(i128);
+
+impl Hydrate for char {
+    fn hydrate_string(s: &'_ str) -> Result<Self, HydrateError> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(HydrateError::unexpected(
+                "a single character",
+                format!("{:?}", s),
+            )),
+        }
+    }
+}
+
+macro_rules! nonzero_impl {
+    ($ty:ident, $inner:ident, $hydrator: ident, $from_ty:ident) => {
+        impl Hydrate for std::num::$ty {
+            fn $hydrator(u: $from_ty) -> Result<Self, HydrateError> {
+                std::num::$ty::new($inner::$hydrator(u)?).ok_or_else(|| {
+                    HydrateError::unexpected(
+                        concat!("a non-zero ", stringify!($inner)),
+                        "zero".to_string(),
+                    )
+                })
+            }
+        }
+    };
+}
+
+nonzero_impl!(NonZeroU8, u8, hydrate_uint, u64);
+nonzero_impl!(NonZeroU16, u16, hydrate_uint, u64);
+nonzero_impl!(NonZeroU32, u32, hydrate_uint, u64);
+nonzero_impl!(NonZeroU64, u64, hydrate_uint, u64);
+nonzero_impl!(NonZeroI8, i8, hydrate_int, i64);
+nonzero_impl!(NonZeroI16, i16, hydrate_int, i64);
+nonzero_impl!(NonZeroI32, i32, hydrate_int, i64);
+nonzero_impl!(NonZeroI64, i64, hydrate_int, i64);
+
+impl<T: Hydrate> Hydrate for std::num::Wrapping<T> {
+    fn hydrate<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<Self, HydrateError> {
+        Ok(std::num::Wrapping(T::hydrate(doc, obj, prop)?))
+    }
+}
+
+macro_rules! atomic_impl {
+    ($ty:ident, $inner:ident) => {
+        impl Hydrate for std::sync::atomic::$ty {
+            fn hydrate<D: ReadDoc>(
+                doc: &D,
+                obj: &automerge::ObjId,
+                prop: crate::Prop<'_>,
+            ) -> Result<Self, HydrateError> {
+                Ok(Self::new($inner::hydrate(doc, obj, prop)?))
+            }
+        }
+    };
+}
+
+atomic_impl!(AtomicBool, bool);
+atomic_impl!(AtomicU8, u8);
+atomic_impl!(AtomicU16, u16);
+atomic_impl!(AtomicU32, u32);
+atomic_impl!(AtomicU64, u64);
+atomic_impl!(AtomicI8, i8);
+atomic_impl!(AtomicI16, i16);
+atomic_impl!(AtomicI32, i32);
+atomic_impl!(AtomicI64, i64);
+
+impl<T: Hydrate + Copy> Hydrate for std::cell::Cell<T> {
+    fn hydrate<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<Self, HydrateError> {
+        Ok(std::cell::Cell::new(T::hydrate(doc, obj, prop)?))
+    }
+}
+
+impl<T: Hydrate> Hydrate for std::cell::RefCell<T> {
+    fn hydrate<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<Self, HydrateError> {
+        Ok(std::cell::RefCell::new(T::hydrate(doc, obj, prop)?))
+    }
+}
+
 impl Hydrate for bool {
     fn hydrate_bool(b: bool) -> Result<Self, HydrateError> {
         Ok(b)
@@ -100,13 +319,35 @@ impl<T: Hydrate> Hydrate for Option<T> {
     }
 }
 
-impl<'a, T: Hydrate + Clone> Hydrate for Cow<'a, T> {
+/// `Result` is hydrated from a map with a single key, `"Ok"` or `"Err"`, like a derived externally
+/// tagged enum
+impl<T: Hydrate, E: Hydrate> Hydrate for Result<T, E> {
+    fn hydrate_map<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        if doc.get(obj, "Ok")?.is_some() {
+            return Ok(Ok(hydrate_prop(doc, obj, "Ok")?));
+        }
+        if doc.get(obj, "Err")?.is_some() {
+            return Ok(Err(hydrate_prop(doc, obj, "Err")?));
+        }
+        Err(HydrateError::unexpected(
+            "A map with an \"Ok\" or \"Err\" key",
+            "something else".to_string(),
+        ))
+    }
+}
+
+/// A `Cow` is always hydrated as `Cow::Owned`, so this works for `Cow<'_, str>` (hydrated via
+/// `String`) and `Cow<'_, [T]>` (hydrated via `Vec<T>`) as well as `Cow<'_, T>` for `T: Clone`
+impl<'a, B: ToOwned + ?Sized> Hydrate for Cow<'a, B>
+where
+    B::Owned: Hydrate,
+{
     fn hydrate<D: ReadDoc>(
         doc: &D,
         obj: &automerge::ObjId,
         prop: crate::Prop<'_>,
     ) -> Result<Self, HydrateError> {
-        Ok(Cow::Owned(T::hydrate(doc, obj, prop)?))
+        Ok(Cow::Owned(B::Owned::hydrate(doc, obj, prop)?))
     }
 }
 
@@ -119,3 +360,23 @@ impl<T: Hydrate> Hydrate for Box<T> {
         Ok(Box::new(T::hydrate(doc, obj, prop)?))
     }
 }
+
+impl<T: Hydrate> Hydrate for std::rc::Rc<T> {
+    fn hydrate<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<Self, HydrateError> {
+        Ok(std::rc::Rc::new(T::hydrate(doc, obj, prop)?))
+    }
+}
+
+impl<T: Hydrate> Hydrate for std::sync::Arc<T> {
+    fn hydrate<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<Self, HydrateError> {
+        Ok(std::sync::Arc::new(T::hydrate(doc, obj, prop)?))
+    }
+}
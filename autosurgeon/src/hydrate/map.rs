@@ -5,44 +5,65 @@ use std::{
 
 use automerge::{self as am, ObjType};
 
-use crate::{Hydrate, HydrateError};
+use crate::{FromPropKey, Hydrate, HydrateError};
 
 impl<K, V> Hydrate for HashMap<K, V>
 where
-    K: From<String> + Hash + Eq,
+    K: FromPropKey + Hash + Eq,
     V: Hydrate,
 {
     fn hydrate_map<D: crate::ReadDoc>(
         doc: &D,
         obj: &automerge::ObjId,
     ) -> Result<Self, crate::HydrateError> {
-        hydrate_map_impl(doc, obj, |k| Ok(K::from(k.to_string())))
+        // Allocate up front rather than going through `FromIterator`, which can't see the number
+        // of entries through the `Result`s and so would rehash as the map grows
+        let mut result = HashMap::with_capacity(doc.length(obj));
+        for entry in map_entries(doc, obj, K::from_prop_key)? {
+            let (key, val) = entry?;
+            result.insert(key, val);
+        }
+        Ok(result)
     }
 }
 
 impl<K, V> Hydrate for BTreeMap<K, V>
 where
-    K: From<String> + Ord,
+    K: FromPropKey + Ord,
     V: Hydrate,
 {
     fn hydrate_map<D: crate::ReadDoc>(
         doc: &D,
         obj: &automerge::ObjId,
     ) -> Result<Self, crate::HydrateError> {
-        hydrate_map_impl(doc, obj, |k| Ok(K::from(k.to_string())))
+        hydrate_map_impl(doc, obj, K::from_prop_key)
     }
 }
 
 pub(crate) fn hydrate_map_impl<'a, F, D, K, V, M>(
     doc: &'a D,
-    obj: &automerge::ObjId,
+    obj: &'a automerge::ObjId,
     extract_key: F,
 ) -> Result<M, crate::HydrateError>
 where
-    F: Fn(&'a str) -> Result<K, crate::HydrateError>,
+    F: Fn(&'a str) -> Result<K, crate::HydrateError> + 'a,
     D: crate::ReadDoc,
     V: Hydrate,
     M: FromIterator<(K, V)>,
+{
+    map_entries(doc, obj, extract_key)?.collect()
+}
+
+/// Hydrate each entry of the map `obj` in document order, which is sorted by key
+pub(crate) fn map_entries<'a, F, D, K, V>(
+    doc: &'a D,
+    obj: &'a automerge::ObjId,
+    extract_key: F,
+) -> Result<impl Iterator<Item = Result<(K, V), crate::HydrateError>> + 'a, crate::HydrateError>
+where
+    F: Fn(&'a str) -> Result<K, crate::HydrateError> + 'a,
+    D: crate::ReadDoc,
+    V: Hydrate,
 {
     let Some(obj_type) = doc.object_type(obj) else {
         return Err(HydrateError::unexpected(
@@ -51,14 +72,15 @@ where
         ));
     };
     match obj_type {
-        ObjType::Map | ObjType::Table => doc
-            .map_range(obj.clone(), ..)
-            .map(move |am::iter::MapRangeItem { key, .. }| {
-                let val = V::hydrate(doc, obj, key.into())?;
-                let key_parsed: K = extract_key(key)?;
-                Ok((key_parsed, val))
-            })
-            .collect(),
+        ObjType::Map | ObjType::Table => {
+            Ok(doc
+                .map_range(obj.clone(), ..)
+                .map(move |am::iter::MapRangeItem { key, .. }| {
+                    let val = V::hydrate(doc, obj, key.into()).map_err(|e| e.in_prop(key))?;
+                    let key_parsed: K = extract_key(key)?;
+                    Ok((key_parsed, val))
+                }))
+        }
         ObjType::Text => Err(HydrateError::unexpected(
             "a map",
             "a text object".to_string(),
@@ -76,7 +98,7 @@ mod tests {
     use automerge as am;
     use std::collections::HashMap;
 
-    use crate::{hydrate, Hydrate};
+    use crate::{hydrate, Hydrate, HydrateError};
 
     #[derive(Debug, PartialEq)]
     struct User {
@@ -96,9 +118,10 @@ mod tests {
 
     #[derive(Debug, PartialEq, Eq, Hash)]
     struct UserName(String);
-    impl From<String> for UserName {
-        fn from(s: String) -> Self {
-            UserName(s)
+    impl std::str::FromStr for UserName {
+        type Err = std::convert::Infallible;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Ok(UserName(s.to_string()))
         }
     }
     impl<'a> From<&'a str> for UserName {
@@ -137,4 +160,57 @@ mod tests {
         let result: HashMap<UserName, User> = hydrate(&doc).unwrap();
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn hydrate_map_with_integer_keys() {
+        let mut doc = am::AutoCommit::new();
+        doc.put(am::ROOT, "1", "one").unwrap();
+        doc.put(am::ROOT, "2", "two").unwrap();
+
+        let result: std::collections::BTreeMap<u16, String> = hydrate(&doc).unwrap();
+        assert_eq!(
+            result.into_iter().collect::<Vec<_>>(),
+            vec![(1, "one".to_string()), (2, "two".to_string())]
+        );
+
+        doc.put(am::ROOT, "three", "three").unwrap();
+        let result: Result<HashMap<u16, String>, _> = hydrate(&doc);
+        assert!(matches!(result, Err(crate::HydrateError::ParseMapKey(_))));
+    }
+
+    #[test]
+    fn hydrate_map_with_char_and_bool_keys() {
+        let letters = HashMap::from([('a', 1_u64), ('é', 2)]);
+        let mut doc = am::AutoCommit::new();
+        crate::reconcile(&mut doc, &letters).unwrap();
+        let result: HashMap<char, u64> = hydrate(&doc).unwrap();
+        assert_eq!(result, letters);
+
+        doc.put(am::ROOT, "ab", 3_u64).unwrap();
+        let err = hydrate::<_, HashMap<char, u64>>(&doc).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "map key parse error: \"ab\" is not a valid char"
+        );
+        let HydrateError::ParseMapKey(err) = err else {
+            panic!("expected a ParseMapKey error, got {:?}", err);
+        };
+        assert_eq!(
+            std::error::Error::source(&*err).unwrap().to_string(),
+            "too many characters in string"
+        );
+
+        let flags = std::collections::BTreeMap::from([(false, "off"), (true, "on")]);
+        let mut doc = am::AutoCommit::new();
+        crate::reconcile(&mut doc, &flags).unwrap();
+        let result: std::collections::BTreeMap<bool, String> = hydrate(&doc).unwrap();
+        assert_eq!(
+            result.into_iter().collect::<Vec<_>>(),
+            vec![(false, "off".to_string()), (true, "on".to_string())]
+        );
+
+        doc.put(am::ROOT, "maybe", "?").unwrap();
+        let err = hydrate::<_, HashMap<bool, String>>(&doc).unwrap_err();
+        assert!(err.to_string().contains("\"maybe\" is not a valid bool"));
+    }
 }
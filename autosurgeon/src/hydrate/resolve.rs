@@ -0,0 +1,398 @@
+use std::{cmp::Ordering, ops::RangeBounds};
+
+use automerge::{self as am, ActorId, AutomergeError, ObjId, ScalarValue, Value};
+
+use super::{hydrate, hydrate_prop, Hydrate, HydrateError};
+use crate::{Prop, ReadDoc};
+
+/// Chooses which of several conflicting values to hydrate
+///
+/// When two peers concurrently set the same property automerge keeps both values and picks one
+/// of them as the winner, which is the one [`ReadDoc::get`] returns. A `ConflictResolver` lets you
+/// pick a different value instead, e.g. the largest one with [`PreferMax`] or the one written by a
+/// particular peer with [`PreferActor`]. Resolvers are used by hydrating through a
+/// [`ResolvingReadDoc`], usually via [`hydrate_with_resolver`] or [`hydrate_prop_with_resolver`].
+///
+/// `ConflictResolver` is implemented for closures which take the conflicting values and return
+/// the index of the one to use:
+///
+/// ```rust
+/// # use autosurgeon::{hydrate_prop_with_resolver, reconcile_prop};
+/// # use automerge::ActorId;
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile_prop(&mut doc, automerge::ROOT, "name", "ann").unwrap();
+/// let mut doc2 = doc.fork().with_actor(ActorId::random());
+/// reconcile_prop(&mut doc2, automerge::ROOT, "name", "Annabel").unwrap();
+/// reconcile_prop(&mut doc, automerge::ROOT, "name", "Ann").unwrap();
+/// doc.merge(&mut doc2).unwrap();
+///
+/// // Prefer the longest name
+/// let longest = |values: &[(automerge::Value<'_>, automerge::ObjId)]| {
+///     (0..values.len()).max_by_key(|i| values[*i].0.to_str().map(str::len))
+/// };
+/// let name: String = hydrate_prop_with_resolver(&doc, automerge::ROOT, "name", longest).unwrap();
+/// assert_eq!(name, "Annabel");
+/// ```
+pub trait ConflictResolver {
+    /// Choose one of `values`
+    ///
+    /// `values` contains at least two values, in the order automerge sorts them, so the last
+    /// value is automerge's winner. Return the index of the value to use, or `None` to use
+    /// automerge's winner. Returning an index which is out of bounds will panic.
+    fn resolve(&self, values: &[(Value<'_>, ObjId)]) -> Option<usize>;
+}
+
+impl<F> ConflictResolver for F
+where
+    F: Fn(&[(Value<'_>, ObjId)]) -> Option<usize>,
+{
+    fn resolve(&self, values: &[(Value<'_>, ObjId)]) -> Option<usize> {
+        self(values)
+    }
+}
+
+/// A [`ConflictResolver`] which picks the largest value
+///
+/// Numbers (including counters) are compared by value, strings and bytes lexicographically, and
+/// timestamps and booleans in the obvious way. If none of the values is larger than automerge's
+/// winner, including when the values can't be compared with each other (e.g. objects, or a
+/// string and a number), automerge's winner is used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PreferMax;
+
+impl ConflictResolver for PreferMax {
+    fn resolve(&self, values: &[(Value<'_>, ObjId)]) -> Option<usize> {
+        let mut best = values.len().checked_sub(1)?;
+        for (idx, (value, _)) in values.iter().enumerate().rev().skip(1) {
+            if compare_values(value, &values[best].0) == Some(Ordering::Greater) {
+                best = idx;
+            }
+        }
+        Some(best)
+    }
+}
+
+/// A [`ConflictResolver`] which picks the value written by a particular actor
+///
+/// If none of the values was written by the actor automerge's winner is used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreferActor(pub ActorId);
+
+impl ConflictResolver for PreferActor {
+    fn resolve(&self, values: &[(Value<'_>, ObjId)]) -> Option<usize> {
+        values
+            .iter()
+            .rposition(|(_, id)| matches!(id, ObjId::Id(_, actor, _) if actor == &self.0))
+    }
+}
+
+fn compare_values(left: &Value<'_>, right: &Value<'_>) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Scalar(left), Value::Scalar(right)) => compare_scalars(left, right),
+        _ => None,
+    }
+}
+
+fn compare_scalars(left: &ScalarValue, right: &ScalarValue) -> Option<Ordering> {
+    match (left, right) {
+        (ScalarValue::Str(l), ScalarValue::Str(r)) => Some(l.as_str().cmp(r.as_str())),
+        (ScalarValue::Bytes(l), ScalarValue::Bytes(r)) => Some(l.cmp(r)),
+        (ScalarValue::Boolean(l), ScalarValue::Boolean(r)) => Some(l.cmp(r)),
+        (ScalarValue::Timestamp(l), ScalarValue::Timestamp(r)) => Some(l.cmp(r)),
+        (ScalarValue::F64(_), _) | (_, ScalarValue::F64(_)) => {
+            as_f64(left)?.partial_cmp(&as_f64(right)?)
+        }
+        _ => Some(as_i128(left)?.cmp(&as_i128(right)?)),
+    }
+}
+
+fn as_i128(value: &ScalarValue) -> Option<i128> {
+    match value {
+        ScalarValue::Int(i) => Some(i128::from(*i)),
+        ScalarValue::Uint(u) => Some(i128::from(*u)),
+        ScalarValue::Counter(c) => Some(i128::from(i64::from(c))),
+        _ => None,
+    }
+}
+
+fn as_f64(value: &ScalarValue) -> Option<f64> {
+    match value {
+        ScalarValue::F64(f) => Some(*f),
+        other => as_i128(other).map(|i| i as f64),
+    }
+}
+
+/// A [`ReadDoc`] which uses a [`ConflictResolver`] to choose between conflicting values
+///
+/// Every read of a single property, via [`ReadDoc::get`] or [`ReadDoc::get_at`], returns the value
+/// chosen by the resolver rather than automerge's winner. As every [`Hydrate`] implementation
+/// reads properties this way, this changes the result of hydrating anything through it. Reads
+/// of every value of a property, via [`ReadDoc::get_all`], are unchanged, so e.g.
+/// [`crate::Conflicted`] still sees every value.
+#[derive(Debug)]
+pub struct ResolvingReadDoc<'a, D: ?Sized, R> {
+    doc: &'a D,
+    resolver: R,
+}
+
+impl<'a, D: ?Sized, R: ConflictResolver> ResolvingReadDoc<'a, D, R> {
+    pub fn new(doc: &'a D, resolver: R) -> Self {
+        Self { doc, resolver }
+    }
+
+    fn resolve<'b>(&self, mut values: Vec<(Value<'b>, ObjId)>) -> Option<(Value<'b>, ObjId)> {
+        if values.len() > 1 {
+            if let Some(idx) = self.resolver.resolve(&values) {
+                return Some(values.swap_remove(idx));
+            }
+        }
+        values.pop()
+    }
+}
+
+impl<'a, D: ReadDoc + ?Sized, R: ConflictResolver> ReadDoc for ResolvingReadDoc<'a, D, R> {
+    type Parents<'b>
+        = D::Parents<'a>
+    where
+        Self: 'b;
+
+    fn get_heads(&self) -> Vec<am::ChangeHash> {
+        self.doc.get_heads()
+    }
+
+    fn get<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        Ok(self.resolve(self.doc.get_all(obj, prop)?))
+    }
+
+    fn get_all<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_all(obj, prop)
+    }
+
+    fn object_type<O: AsRef<ObjId>>(&self, obj: O) -> Option<am::ObjType> {
+        self.doc.object_type(obj)
+    }
+
+    fn map_range<'b, O, Rng>(&'b self, obj: O, range: Rng) -> am::iter::MapRange<'b, Rng>
+    where
+        Rng: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+        Rng: RangeBounds<String>,
+    {
+        self.doc.map_range(obj, range)
+    }
+
+    fn list_range<O: AsRef<ObjId>, Rng: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: Rng,
+    ) -> am::iter::ListRange<'_, Rng> {
+        self.doc.list_range(obj, range)
+    }
+
+    fn length<O: AsRef<ObjId>>(&self, obj: O) -> usize {
+        self.doc.length(obj)
+    }
+
+    fn keys<O: AsRef<ObjId>>(&self, obj: O) -> am::iter::Keys<'_> {
+        self.doc.keys(obj)
+    }
+
+    fn text<O: AsRef<ObjId>>(&self, obj: O) -> Result<String, AutomergeError> {
+        self.doc.text(obj)
+    }
+
+    fn parents<O: AsRef<ObjId>>(&self, obj: O) -> Result<Self::Parents<'_>, AutomergeError> {
+        self.doc.parents(obj)
+    }
+
+    fn get_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        Ok(self.resolve(self.doc.get_all_at(obj, prop, heads)?))
+    }
+
+    fn get_all_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_all_at(obj, prop, heads)
+    }
+
+    fn map_range_at<'b, O, Rng>(
+        &'b self,
+        obj: O,
+        range: Rng,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::MapRange<'b, Rng>
+    where
+        Rng: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+    {
+        self.doc.map_range_at(obj, range, heads)
+    }
+
+    fn list_range_at<O: AsRef<ObjId>, Rng: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: Rng,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::ListRange<'_, Rng> {
+        self.doc.list_range_at(obj, range, heads)
+    }
+
+    fn length_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> usize {
+        self.doc.length_at(obj, heads)
+    }
+
+    fn keys_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> am::iter::Keys<'_> {
+        self.doc.keys_at(obj, heads)
+    }
+
+    fn text_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<String, AutomergeError> {
+        self.doc.text_at(obj, heads)
+    }
+
+    fn parents_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Self::Parents<'_>, AutomergeError> {
+        self.doc.parents_at(obj, heads)
+    }
+
+    fn get_cursor<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        position: usize,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<am::Cursor, AutomergeError> {
+        self.doc.get_cursor(obj, position, at)
+    }
+
+    fn get_cursor_position<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        cursor: &am::Cursor,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<usize, AutomergeError> {
+        self.doc.get_cursor_position(obj, cursor, at)
+    }
+
+    fn marks<O: AsRef<ObjId>>(&self, obj: O) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        self.doc.marks(obj)
+    }
+
+    fn marks_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        self.doc.marks_at(obj, heads)
+    }
+}
+
+/// Hydrate an instance of `H` from `doc`, using `resolver` to choose between conflicting values
+///
+/// See [`ConflictResolver`].
+pub fn hydrate_with_resolver<D: ReadDoc, H: Hydrate, R: ConflictResolver>(
+    doc: &D,
+    resolver: R,
+) -> Result<H, HydrateError> {
+    hydrate(&ResolvingReadDoc::new(doc, resolver))
+}
+
+/// Hydrate an instance of `H` located at property `prop` of object `obj`, using `resolver` to
+/// choose between conflicting values
+///
+/// See [`ConflictResolver`].
+pub fn hydrate_prop_with_resolver<
+    'a,
+    D: ReadDoc,
+    H: Hydrate,
+    P: Into<Prop<'a>>,
+    O: AsRef<automerge::ObjId>,
+    R: ConflictResolver,
+>(
+    doc: &D,
+    obj: O,
+    prop: P,
+    resolver: R,
+) -> Result<H, HydrateError> {
+    hydrate_prop(&ResolvingReadDoc::new(doc, resolver), obj, prop)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use automerge::ActorId;
+
+    use super::{hydrate_prop_with_resolver, hydrate_with_resolver, PreferActor, PreferMax};
+    use crate::{hydrate_prop, reconcile_prop, Conflicted};
+
+    fn conflicting_doc(left: u64, right: u64) -> automerge::AutoCommit {
+        let mut doc = automerge::AutoCommit::new().with_actor(ActorId::from(vec![1]));
+        reconcile_prop(&mut doc, automerge::ROOT, "value", 0_u64).unwrap();
+        let mut doc2 = doc.fork().with_actor(ActorId::from(vec![2]));
+        reconcile_prop(&mut doc, automerge::ROOT, "value", left).unwrap();
+        reconcile_prop(&mut doc2, automerge::ROOT, "value", right).unwrap();
+        doc.merge(&mut doc2).unwrap();
+        doc
+    }
+
+    #[test]
+    fn prefer_max() {
+        // Actor 2 wins by default, whichever value it wrote
+        let doc = conflicting_doc(5, 3);
+        assert_eq!(
+            hydrate_prop::<_, u64, _, _>(&doc, automerge::ROOT, "value").unwrap(),
+            3
+        );
+        let value: u64 =
+            hydrate_prop_with_resolver(&doc, automerge::ROOT, "value", PreferMax).unwrap();
+        assert_eq!(value, 5);
+
+        let doc = conflicting_doc(3, 5);
+        let value: u64 =
+            hydrate_prop_with_resolver(&doc, automerge::ROOT, "value", PreferMax).unwrap();
+        assert_eq!(value, 5);
+    }
+
+    #[test]
+    fn prefer_actor() {
+        let doc = conflicting_doc(1, 2);
+        let values: HashMap<String, u64> =
+            hydrate_with_resolver(&doc, PreferActor(ActorId::from(vec![1]))).unwrap();
+        assert_eq!(values.get("value"), Some(&1));
+
+        // An actor which didn't write any of the values leaves automerge's winner
+        let values: HashMap<String, u64> =
+            hydrate_with_resolver(&doc, PreferActor(ActorId::from(vec![3]))).unwrap();
+        assert_eq!(values.get("value"), Some(&2));
+    }
+
+    #[test]
+    fn get_all_is_unchanged() {
+        let doc = conflicting_doc(5, 3);
+        let value: Conflicted<u64> =
+            hydrate_prop_with_resolver(&doc, automerge::ROOT, "value", PreferMax).unwrap();
+        assert_eq!(value.into_winner(), 3);
+    }
+}
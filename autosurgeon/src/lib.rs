@@ -9,7 +9,8 @@
 //! macros to do this work mechanically.
 //!
 //! Additionally `autosurgeon` provides the [`Counter`] and [`Text`] data types which implement
-//! [`Reconcile`] and [`Hydrate`] for counters and text respectively.
+//! [`Reconcile`] and [`Hydrate`] for counters and text respectively. For very large texts use
+//! [`RichText`], which stores the text as a rope so that edits stay fast.
 //!
 //! Currently this library does not handle incremental updates, that means that every time you
 //! receive concurrent changes from other documents you will need to re-`hydrate` your data
@@ -18,7 +19,35 @@
 //! ## Feature Flags
 //!
 //! * `uuid` - Includes implementations of `Reconcile` and `Hydrate` for the [`Uuid`](https://docs.rs/uuid/latest/uuid/) crate which will
-//! reconcile to a [`automerge::ScalarValue::Bytes`]
+//!   reconcile to a [`automerge::ScalarValue::Bytes`]
+//! * `serde_json` - Includes implementations of `Reconcile` and `Hydrate` for
+//!   [`serde_json::Value`](https://docs.rs/serde_json/latest/serde_json/enum.Value.html), which
+//!   maps JSON objects and arrays to automerge maps and lists and JSON scalars to the
+//!   corresponding automerge scalars. This is useful for storing parts of a document whose schema
+//!   is dynamic alongside typed data. Text objects hydrate as strings and counters and timestamps
+//!   as numbers.
+//! * `smallvec` - Includes implementations of `Reconcile` and `Hydrate` for
+//!   [`smallvec::SmallVec`](https://docs.rs/smallvec/latest/smallvec/struct.SmallVec.html), which
+//!   reconciles to a list just like `Vec`
+//! * `url` - Includes implementations of `Reconcile` and `Hydrate` for
+//!   [`url::Url`](https://docs.rs/url/latest/url/struct.Url.html), which is stored as a string
+//! * `serde` - Includes implementations of `Serialize` and `Deserialize` for [`Text`], [`Counter`]
+//!   and [`MaybeMissing`], for example to snapshot application state. `Text` and `Counter`
+//!   serialize as their current value, and a deserialized `Text` or `Counter` reconciles as if it
+//!   were created with `with_value`.
+//! * `contrib` - Includes the `contrib` module of helpers for persisting documents, such as
+//!   keeping a value in a document on disk
+//! * `axum` - Includes `contrib::axum`, an [`axum`](https://docs.rs/axum/latest/axum/) extractor
+//!   and response for values sent as documents. Implies `contrib`
+//! * `rusqlite` - Includes `contrib::sqlite`, a store of documents in a sqlite table using
+//!   [`rusqlite`](https://docs.rs/rusqlite/latest/rusqlite/). Implies `contrib`
+//! * `tracing` - Emits [`tracing`](https://docs.rs/tracing/latest/tracing/) spans around calls
+//!   to [`reconcile()`], [`reconcile_prop`], [`reconcile_insert`], [`hydrate()`],
+//!   [`hydrate_partial`] and [`hydrate_lossy`] (and the functions built on them), recording the
+//!   path and type of the object and, for reconciles, the number of operations made. A debug
+//!   event is emitted whenever a reconcile replaces a value with a new object because the value
+//!   was not an object of the type being reconciled, which is usually why concurrent changes to
+//!   that part of the document were lost.
 //!
 //! ## Example
 //!
@@ -187,6 +216,150 @@
 //! let z = E::Z;                // Represented as `"Z"`
 //! ```
 //!
+//! A struct with a single named field can be represented as just that field, like a newtype
+//! struct, using the `transparent` attribute
+//!
+//! ```rust,no_run
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! #[derive(Reconcile, Hydrate)]
+//! #[autosurgeon(transparent)]
+//! struct Email {
+//!     address: String,
+//! }
+//! // Represented as just the inner value `"someone@example.com"`
+//! let e = Email { address: "someone@example.com".to_string() };
+//! ```
+//!
+//! The name used for an enum variant in the document can be changed with the `rename` attribute
+//!
+//! ```rust,no_run
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! #[derive(Reconcile, Hydrate)]
+//! enum Status {
+//!     #[autosurgeon(rename = "in-progress")]
+//!     InProgress { percent: u64 },
+//!     #[autosurgeon(rename = "done")]
+//!     Done,
+//! }
+//! let s = Status::InProgress { percent: 50 }; // Represented as `{"in-progress":{"percent":50}}`
+//! let d = Status::Done;                       // Represented as `"done"`
+//! ```
+//!
+//! Named struct fields can be renamed in the same way. To rename every field and variant of a
+//! type at once use `rename_all` on the type, which takes one of `"lowercase"`, `"UPPERCASE"`,
+//! `"PascalCase"`, `"camelCase"`, `"snake_case"`, `"SCREAMING_SNAKE_CASE"`, `"kebab-case"`, or
+//! `"SCREAMING-KEBAB-CASE"` and converts names the same way as serde. A `rename` on an individual
+//! field or variant takes precedence.
+//!
+//! ```rust,no_run
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! #[derive(Reconcile, Hydrate)]
+//! #[autosurgeon(rename_all = "camelCase")]
+//! struct Task {
+//!     due_date: String,
+//!     #[autosurgeon(rename = "who")]
+//!     assigned_to: String,
+//! }
+//! // Represented as `{"dueDate": ..., "who": ...}`
+//! ```
+//!
+//! An enum which only has unit variants can be stored as the integer discriminants of its variants
+//! rather than as their names with `#[autosurgeon(repr = "int")]`, which makes long lists of them
+//! much smaller. Hydrating an integer which isn't the discriminant of any variant fails unless
+//! one of the variants is marked `other`.
+//!
+//! ```rust,no_run
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! #[derive(Reconcile, Hydrate)]
+//! #[autosurgeon(repr = "int")]
+//! enum Priority {
+//!     Low,            // Represented as `0`
+//!     High = 10,      // Represented as `10`
+//! }
+//! ```
+//!
+//! Enums can also be stored with the variant name in a separate field, as is common in documents
+//! written by other languages. The `tag` attribute stores the fields of a variant alongside the
+//! variant name, and adding a `content` attribute stores them under a separate key instead.
+//! Tuple variants can't be used with `tag` unless `content` is also specified, and newtype
+//! variants with just `tag` must contain a type which is represented as a map.
+//!
+//! ```rust,no_run
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! #[derive(Reconcile, Hydrate)]
+//! #[autosurgeon(tag = "type")]
+//! enum Internal {
+//!     W { a: i32, b: i32 },
+//!     Z,
+//! }
+//! let w = Internal::W { a: 0, b: 0 }; // Represented as `{"type":"W","a":0,"b":0}`
+//! let z = Internal::Z;                // Represented as `{"type":"Z"}`
+//!
+//! #[derive(Reconcile, Hydrate)]
+//! #[autosurgeon(tag = "type", content = "data")]
+//! enum Adjacent {
+//!     W { a: i32, b: i32 },
+//!     X(i32, i32),
+//!     Z,
+//! }
+//! let w = Adjacent::W { a: 0, b: 0 }; // Represented as `{"type":"W","data":{"a":0,"b":0}}`
+//! let x = Adjacent::X(0, 0);          // Represented as `{"type":"X","data":[0,0]}`
+//! let z = Adjacent::Z;                // Represented as `{"type":"Z"}`
+//! ```
+//!
+//! Hydrating an enum fails if the variant name in the document isn't the name of any variant,
+//! for example because the document was written by a newer version of your application. Marking
+//! a unit variant with the `other` attribute hydrates it instead. Marking a newtype variant with
+//! `other` hydrates its field from the whole value of the unknown variant, and reconciling it
+//! writes that value back unchanged, so the unknown variant survives a round trip.
+//!
+//! ```rust,no_run
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! # use std::collections::HashMap;
+//! #[derive(Reconcile, Hydrate)]
+//! enum Priority {
+//!     Low,
+//!     High,
+//!     #[autosurgeon(other)]
+//!     Unknown(String), // Hydrated from e.g. `"Urgent"`
+//! }
+//! ```
+//!
+//! By default reconciling a struct only touches the keys corresponding to its fields, so if you
+//! remove a field from a struct the old key stays in the document. The `exhaustive` attribute
+//! makes reconciling a struct with named fields delete any other keys in the map
+//!
+//! ```rust,no_run
+//! # use autosurgeon::Reconcile;
+//! #[derive(Reconcile)]
+//! #[autosurgeon(exhaustive)]
+//! struct Settings {
+//!     theme: String,
+//! }
+//! // Reconciling into `{"theme":"light","font":"serif"}` produces `{"theme":"dark"}`
+//! let s = Settings { theme: "dark".to_string() };
+//! ```
+//!
+//! Similarly hydrating a struct ignores any keys in the map which don't correspond to a field.
+//! The `deny_unknown_fields` attribute makes hydrating fail with
+//! [`HydrateError::UnknownFields`] listing the other keys instead, which is useful for noticing
+//! when the documents you are reading have drifted from your schema
+//!
+//! ```rust
+//! # use autosurgeon::{hydrate, Hydrate, HydrateError};
+//! # use automerge::transaction::Transactable;
+//! #[derive(Debug, Hydrate)]
+//! #[autosurgeon(deny_unknown_fields)]
+//! struct Settings {
+//!     theme: String,
+//! }
+//! let mut doc = automerge::AutoCommit::new();
+//! doc.put(automerge::ROOT, "theme", "dark").unwrap();
+//! doc.put(automerge::ROOT, "font", "serif").unwrap();
+//! let err = hydrate::<_, Settings>(&doc).unwrap_err();
+//! assert!(matches!(err, HydrateError::UnknownFields(keys) if keys == ["font"]));
+//! ```
+//!
 //! ### The `key` attribute
 //!
 //! `autosurgeon` will generally do its best to generate smart diffs. But sometimes you know
@@ -332,6 +505,75 @@
 //! );
 //! ```
 //!
+//! A key field can be of any type which implements [`Hydrate`], `Clone` and `PartialEq`, including
+//! [`Text`], in which case two items are the same if their text is the same. A field with a
+//! `with=` or `hydrate=` attribute is hydrated with that function when it is used as a key.
+//!
+//! `#[autosurgeon(key)]` can be used instead of `#[key]`. If more than one field is marked as a
+//! key then the key is composite: two items are only the same if every one of those fields
+//! matches. The key type is then a tuple of the individual keys, in the order the fields are
+//! declared.
+//!
+//! ```rust,no_run
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! #[derive(Reconcile, Hydrate)]
+//! struct Item {
+//!     #[key]
+//!     tenant_id: String,
+//!     #[key]
+//!     item_id: u64,
+//!     name: String,
+//! }
+//! ```
+//!
+//! If the key isn't a field, for example because it is a normalized version of a field or a
+//! combination of several of them, you can compute it with `#[autosurgeon(key_fn = "...")]`
+//! instead. The function takes `&Self` and can return any type which implements `PartialEq`. By
+//! default the key of an item in the document is computed by hydrating the whole item and calling
+//! the key function, which requires that the type implements [`Hydrate`]. To avoid that you can
+//! also pass `hydrate_key_fn`, a function with the same signature as [`Reconcile::hydrate_key`]
+//! which returns the same type as the key function.
+//!
+//! ```rust,no_run
+//! # use autosurgeon::{Reconcile, Hydrate, Prop, ReadDoc, ReconcileError, reconcile::LoadKey};
+//! #[derive(Reconcile, Hydrate)]
+//! #[autosurgeon(key_fn = "normalized_email", hydrate_key_fn = "hydrate_normalized_email")]
+//! struct Contact {
+//!     email: String,
+//!     name: String,
+//! }
+//!
+//! fn normalized_email(contact: &Contact) -> String {
+//!     contact.email.to_lowercase()
+//! }
+//!
+//! fn hydrate_normalized_email<D: ReadDoc>(
+//!     doc: &D,
+//!     obj: &automerge::ObjId,
+//!     prop: Prop<'_>,
+//! ) -> Result<LoadKey<String>, ReconcileError> {
+//!     let email: LoadKey<String> = autosurgeon::hydrate_key(doc, obj, prop, "email".into())?;
+//!     Ok(email.map(|e| e.to_lowercase()))
+//! }
+//! ```
+//!
+//! If the key of an item changes then the item is treated as a different item, which means the
+//! old object and its history are replaced. This is usually a bug, so you can make it an error to
+//! change the key with the `key_immutable` attribute. Reconciling then fails with
+//! [`ReconcileError::ImmutableKeyChanged`] if a map entry, or an element at the same index in a
+//! sequence, is replaced with an item with a different key.
+//!
+//! ```rust,no_run
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! #[derive(Reconcile, Hydrate)]
+//! #[autosurgeon(key_immutable)]
+//! struct Product {
+//!     #[key]
+//!     id: u64,
+//!     name: String,
+//! }
+//! ```
+//!
 //! ### Providing Implementations for foreign types
 //!
 //! Deriving `Hydrate` and `Reconcile` is fine for your own types, but sometimes you are using a
@@ -417,6 +659,31 @@
 //! }
 //! ```
 //!
+//! #### `polymorphic=`
+//!
+//! Fields containing trait objects (`Box<dyn Trait>`) can be reconciled and hydrated using a
+//! registry of the concrete types which implement the trait, declared with
+//! [`polymorphic_registry!`]. See the [`polymorphic`] module for details.
+//!
+//! #### `map_as_entries`
+//!
+//! `#[autosurgeon(map_as_entries)]` stores a map-typed field as a list of
+//! `{"key": ..., "value": ...}` objects sorted by key, rather than as a map. This is shorthand for
+//! `#[autosurgeon(with = "autosurgeon::map_entries")]`, see the [`map_entries`] module for
+//! details.
+//!
+//! #### `bytes`
+//!
+//! `#[autosurgeon(bytes)]` stores a `Vec<u8>` or `[u8; N]` field (or a list or `Option` of them)
+//! as bytes scalars rather than as lists of integers. This is shorthand for
+//! `#[autosurgeon(with = "autosurgeon::bytes::scalar")]`, see [`bytes::scalar`].
+//!
+//! #### `map_strategy`
+//!
+//! Reconciling a map deletes any keys in the document which are not in the map, which can lose
+//! keys inserted concurrently by other peers. `#[autosurgeon(map_strategy = "keep_absent")]` on a
+//! map-typed field leaves those keys in place instead. See the [`map_strategy`] module.
+//!
 //! #### Providing default values with `missing=`
 //!
 //! Occasionally you may want to provide a default value for a field which
@@ -447,32 +714,187 @@
 //! assert_eq!(contact.visibility, Visibility::Public);
 //!
 //! ```
+//!
+//! `missing` can also be used on the fields of enum variants, which is useful when
+//! a variant gains a field after documents have been written with it.
+//!
+//! #### Leaving out `None` with `none_as_absent`
+//!
+//! An `Option` is reconciled as null when it is `None`, and hydrating it from a key which isn't
+//! in the document is an error. Some documents, particularly those shared with JavaScript, use a
+//! missing key instead of null. `#[autosurgeon(none_as_absent)]` on a named `Option` field
+//! deletes the key when the field is `None` and hydrates a missing key as `None`.
+//!
+//! ```rust
+//! # use autosurgeon::{reconcile, Reconcile, Hydrate, ReadDoc};
+//! #[derive(Reconcile, Hydrate)]
+//! struct Contact {
+//!     name: String,
+//!     #[autosurgeon(none_as_absent)]
+//!     phone: Option<String>,
+//! }
+//!
+//! let mut doc = automerge::AutoCommit::new();
+//! let contact = Contact {
+//!     name: "Sherlock Holmes".to_string(),
+//!     phone: None,
+//! };
+//! reconcile(&mut doc, &contact).unwrap();
+//! assert_eq!(doc.keys(&automerge::ROOT).collect::<Vec<_>>(), vec!["name"]);
+//! ```
+//!
+//! #### Hydrating whatever is present with `partial`
+//!
+//! If a document may be missing any number of fields, for example because it was written by an
+//! older version of your application, you can add `#[autosurgeon(partial)]` to a struct. As well
+//! as the normal `Hydrate` implementation the derive macro then generates a `<Struct>Partial`
+//! type in which every field is a [`MaybeMissing`], which you can hydrate with
+//! [`hydrate_partial`]. See [`HydratePartial`] for details.
+//!
+//! #### Skipping unchanged values with `digest`
+//!
+//! Adding `#[autosurgeon(digest)]` to a struct or enum which implements `Hash` implements
+//! [`Reconcile::digest`] by hashing the value. When the value is reconciled with a
+//! [`ReconcileScratch`](reconcile::ReconcileScratch) which already reconciled an equal value into
+//! the same object, the value is skipped rather than being compared with the document field by
+//! field. See [`Reconcile::digest`] for the caveats.
+//!
+//! #### Tracking changed fields with `track`
+//!
+//! Adding `#[autosurgeon(track)]` to a struct with named fields and a field of type [`Dirty`]
+//! generates a `set_<field>` method for each of the other fields, which sets the field and marks
+//! it dirty. Reconciling the struct then only writes the fields which have been set since it was
+//! hydrated, leaving the rest of the object untouched. See [`Dirty`] for the details.
+//!
+//! #### Writing some of the fields with `field_mask`
+//!
+//! Adding `#[autosurgeon(field_mask)]` to a struct with named fields generates a
+//! `<Struct>FieldMask` type, a set of fields with a constant for each field, and implements
+//! [`ReconcileMasked`]. [`reconcile_masked`] then writes only the fields in a mask and leaves the
+//! other fields in the document alone, which is what a partial update API expects.
+//!
+//! ### Generic types and `bound=`
+//!
+//! Fields of type `PhantomData` are not written to the document and hydrate to `PhantomData`,
+//! so type parameters which are only used in `PhantomData` fields don't need to implement
+//! `Reconcile` or `Hydrate`. The derive macros require every other type parameter to implement
+//! `Reconcile` (or `Hydrate`). This is wrong if a parameter is only used behind a `with=` or
+//! through one of its associated types. In these cases you can replace the generated bounds with
+//! your own using `#[autosurgeon(bound = "...")]`, or give separate bounds for each
+//! derive with `#[autosurgeon(bound(reconcile = "...", hydrate = "..."))]`.
+//!
+//! ```rust
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! trait Store {
+//!     type Item;
+//! }
+//!
+//! #[derive(Reconcile, Hydrate)]
+//! #[autosurgeon(bound = "S::Item: Reconcile + Hydrate")]
+//! struct Inventory<S: Store> {
+//!     items: Vec<S::Item>,
+//! }
+//! ```
+//!
+//! ### What the derive macros generate
+//!
+//! Besides the trait implementations, the only items the derives add to the module they're used
+//! in are the ones you ask for by name: the `<Struct>Partial` type of `partial`, the
+//! `set_<field>` methods of `track`, and the `<Struct>FieldMask` type of `field_mask`. Helper types,
+//! such as the key type of an enum with `#[key]` fields, are defined inside an anonymous
+//! `const _: () = { ... }` block along with the implementation that uses them, so they can't
+//! collide with other types in the module however they're named. The output of the derives
+//! depends only on the item they're applied to, so it's the same from one build to the next.
 
 #[doc = include_str!("../../README.md")]
 #[cfg(doctest)]
 pub struct ReadmeDoctests;
 
+mod cached;
+pub use cached::Cached;
+mod conflicted;
+pub use conflicted::{Conflict, Conflicted};
 mod counter;
 pub use counter::Counter;
+mod dirty;
+pub use dirty::Dirty;
 pub mod bytes;
+pub mod display_from_str;
 mod doc;
-pub use doc::{Doc, ReadDoc};
+pub mod ffi;
+pub use doc::{AtHeads, Doc, InstrumentedReadDoc, ReadAccess, ReadDoc};
 pub mod hydrate;
 #[doc(inline)]
-pub use hydrate::{hydrate, hydrate_path, hydrate_prop, Hydrate, HydrateError, MaybeMissing};
+pub use hydrate::{
+    hydrate, hydrate_at, hydrate_from_bytes, hydrate_from_bytes_at, hydrate_lossy, hydrate_partial,
+    hydrate_path, hydrate_prop, hydrate_prop_at, hydrate_prop_with_resolver, hydrate_with_resolver,
+    Hydrate, HydrateError, HydratePartial, MaybeMissing,
+};
 pub mod reconcile;
 #[doc(inline)]
 pub use reconcile::{
-    hydrate_key, reconcile, reconcile_insert, reconcile_prop, Reconcile, ReconcileError, Reconciler,
+    hydrate_key, reconcile, reconcile_at, reconcile_clear, reconcile_delete, reconcile_insert,
+    reconcile_masked, reconcile_observed, reconcile_prop, to_new_document,
+    to_new_document_with_actor, Reconcile, ReconcileError, ReconcileKey, ReconcileMasked,
+    Reconciler,
 };
+mod lww;
+pub use lww::Lww;
+mod table;
+pub use table::Table;
+mod rope;
 mod text;
-pub use text::Text;
+pub use text::{Cursor, CursorError, Mark, Splice, Text};
+mod text_ref;
+pub use text_ref::TextRef;
+mod rich_text;
+pub use rich_text::RichText;
+mod preserved;
+pub use preserved::{PreserveUnknown, PreservedObject};
+pub mod map_entries;
+pub mod map_pairs;
+pub mod map_strategy;
 pub mod map_with_parseable_keys;
+pub mod migrate;
+pub mod paths;
+pub mod polymorphic;
+pub mod schema;
+mod selector;
+pub use selector::Selector;
+pub mod transactional;
+pub mod unique_list;
+#[doc(inline)]
+pub use paths::paths_of;
+#[doc(inline)]
+pub use transactional::{reconcile_atomic, reconcile_with_options, TransactionalReconciler};
 
+mod path;
+pub use path::{ParsePathError, Path};
 mod prop;
-pub use prop::Prop;
+pub use prop::{FromPropKey, Prop, PropKey};
+mod trace;
 
-pub use autosurgeon_derive::{Hydrate, Reconcile};
+pub use autosurgeon_derive::{Hydrate, Reconcile, ReconcileKey};
 
 #[cfg(feature = "uuid")]
 mod uuid;
+
+#[cfg(feature = "url")]
+mod url;
+
+#[cfg(feature = "serde_json")]
+mod serde_json;
+
+#[cfg(feature = "serde")]
+mod serde;
+
+#[cfg(feature = "contrib")]
+pub mod contrib;
+
+// These types are commonly held in application state which is shared between threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Counter>();
+    assert_send_sync::<Text>();
+    assert_send_sync::<MaybeMissing<()>>();
+};
@@ -0,0 +1,295 @@
+use automerge::{ActorId, ObjType};
+
+use crate::{
+    reconcile::{MapReconciler, NoKey},
+    Hydrate, HydrateError, Prop, ReadDoc, Reconcile, Reconciler,
+};
+
+const VALUE: &str = "value";
+const TIMESTAMP: &str = "timestamp";
+const ACTOR: &str = "actor";
+
+/// A last writer wins register
+///
+/// Concurrent changes to a value in automerge are resolved by picking a winner based on the
+/// internal IDs of the changes, which are unrelated to when the changes were made. `Lww` instead
+/// stores a timestamp and the ID of the actor which wrote the value alongside the value, as a map
+/// of the form `{"value": ..., "timestamp": ..., "actor": ...}`, and the value with the highest
+/// timestamp wins. Ties are broken by comparing the actors, so every peer picks the same value.
+///
+/// The timestamp can be anything which increases, such as the time in milliseconds or a Lamport
+/// clock. [`Lww::set`] uses a Lamport clock, setting the timestamp to one more than the timestamp
+/// of the current value.
+///
+/// # Example
+///
+/// ```rust
+/// # use autosurgeon::{hydrate, reconcile, Hydrate, Lww, Reconcile};
+/// # use automerge::ActorId;
+/// #[derive(Reconcile, Hydrate)]
+/// struct Settings {
+///     theme: Lww<String>,
+/// }
+///
+/// let alice = ActorId::random();
+/// let bob = ActorId::random();
+/// let mut doc = automerge::AutoCommit::new().with_actor(alice.clone());
+/// let settings = Settings { theme: Lww::new("light".to_string(), alice.clone()) };
+/// reconcile(&mut doc, &settings).unwrap();
+///
+/// // Bob changes the theme twice
+/// let mut doc2 = doc.fork().with_actor(bob.clone());
+/// let mut settings2: Settings = hydrate(&doc2).unwrap();
+/// settings2.theme.set("dark".to_string(), bob.clone());
+/// settings2.theme.set("solarized".to_string(), bob);
+/// reconcile(&mut doc2, &settings2).unwrap();
+///
+/// // Concurrently Alice changes it once
+/// let mut settings: Settings = hydrate(&doc).unwrap();
+/// settings.theme.set("high contrast".to_string(), alice);
+/// reconcile(&mut doc, &settings).unwrap();
+///
+/// // Bob's change has the highest timestamp so it wins, whichever way round we merge
+/// doc.merge(&mut doc2).unwrap();
+/// let settings: Settings = hydrate(&doc).unwrap();
+/// assert_eq!(settings.theme.value(), "solarized");
+/// ```
+///
+/// # Reconciliation
+///
+/// If the value in the document has a higher timestamp than `self` then reconciling does nothing,
+/// the newer value in the document wins. If the timestamps are the same then the value is
+/// reconciled in place, so modifying a hydrated value without calling [`Lww::set`] behaves like
+/// modifying any other value. Otherwise the whole register is replaced, which means that
+/// concurrent changes are stored as conflicting maps and hydrating picks the one with the highest
+/// timestamp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lww<T> {
+    value: T,
+    timestamp: u64,
+    actor: ActorId,
+}
+
+impl<T> Lww<T> {
+    /// Create a register with a timestamp of `0`
+    pub fn new(value: T, actor: ActorId) -> Self {
+        Self::with_timestamp(value, 0, actor)
+    }
+
+    pub fn with_timestamp(value: T, timestamp: u64, actor: ActorId) -> Self {
+        Self {
+            value,
+            timestamp,
+            actor,
+        }
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// The actor which wrote the current value
+    pub fn actor(&self) -> &ActorId {
+        &self.actor
+    }
+
+    /// Set the value, incrementing the timestamp
+    pub fn set(&mut self, value: T, actor: ActorId) {
+        self.set_with_timestamp(value, self.timestamp + 1, actor)
+    }
+
+    /// Set the value with a timestamp of your choosing, e.g. the current time
+    pub fn set_with_timestamp(&mut self, value: T, timestamp: u64, actor: ActorId) {
+        self.value = value;
+        self.timestamp = timestamp;
+        self.actor = actor;
+    }
+
+    fn version(&self) -> (u64, &ActorId) {
+        (self.timestamp, &self.actor)
+    }
+}
+
+impl<T: Reconcile> Reconcile for Lww<T> {
+    type Key<'a> = NoKey;
+
+    fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        let (existing, empty) = {
+            let m = reconciler.map()?;
+            let empty = m.entries().next().is_none();
+            (stored_version(&m), empty)
+        };
+        match existing {
+            Some((timestamp, actor)) if (timestamp, &actor) > self.version() => Ok(()),
+            Some((timestamp, actor)) if (timestamp, &actor) == self.version() => {
+                reconciler.map()?.put(VALUE, &self.value)
+            }
+            _ => {
+                if !empty {
+                    reconciler.replace_with_type(ObjType::Map)?;
+                }
+                let mut m = reconciler.map()?;
+                m.put(VALUE, &self.value)?;
+                m.put(TIMESTAMP, self.timestamp)?;
+                m.put(ACTOR, ActorBytes(self.actor.clone()))?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The timestamp and actor of the register in `m`, if it is one
+fn stored_version<M: MapReconciler>(m: &M) -> Option<(u64, ActorId)> {
+    use automerge::{ScalarValue, Value};
+    let timestamp = match m.entry(TIMESTAMP)? {
+        Value::Scalar(s) => match s.as_ref() {
+            ScalarValue::Uint(t) => *t,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let actor = match m.entry(ACTOR)? {
+        Value::Scalar(s) => match s.as_ref() {
+            ScalarValue::Bytes(b) => ActorId::from(b.as_slice()),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some((timestamp, actor))
+}
+
+impl<T: Hydrate> Hydrate for Lww<T> {
+    fn hydrate<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<Self, HydrateError> {
+        // If there are concurrent writes then every one of them is a conflicting value here
+        let mut winner: Option<(u64, ActorId, automerge::ObjId)> = None;
+        for (value, id) in doc.get_all(obj, &prop)? {
+            if !matches!(
+                value,
+                automerge::Value::Object(ObjType::Map | ObjType::Table)
+            ) {
+                continue;
+            }
+            let timestamp: u64 =
+                u64::hydrate(doc, &id, TIMESTAMP.into()).map_err(|e| e.in_prop(TIMESTAMP))?;
+            let ActorBytes(actor) =
+                ActorBytes::hydrate(doc, &id, ACTOR.into()).map_err(|e| e.in_prop(ACTOR))?;
+            if winner
+                .as_ref()
+                .map_or(true, |(t, a, _)| (timestamp, &actor) > (*t, a))
+            {
+                winner = Some((timestamp, actor, id));
+            }
+        }
+        let Some((timestamp, actor, id)) = winner else {
+            return Err(HydrateError::unexpected(
+                "a map containing a last writer wins register",
+                "something else".to_string(),
+            ));
+        };
+        let value = T::hydrate(doc, &id, VALUE.into()).map_err(|e| e.in_prop(VALUE))?;
+        Ok(Self {
+            value,
+            timestamp,
+            actor,
+        })
+    }
+}
+
+/// An actor ID stored as bytes
+struct ActorBytes(ActorId);
+
+impl Reconcile for ActorBytes {
+    type Key<'a> = NoKey;
+
+    fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        reconciler.bytes(self.0.to_bytes())
+    }
+}
+
+impl Hydrate for ActorBytes {
+    fn hydrate_bytes(bytes: &[u8]) -> Result<Self, HydrateError> {
+        Ok(Self(ActorId::from(bytes)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::ActorId;
+
+    use super::Lww;
+    use crate::{hydrate_prop, reconcile_prop};
+
+    fn actor(b: u8) -> ActorId {
+        ActorId::from(vec![b])
+    }
+
+    #[test]
+    fn highest_timestamp_wins_on_merge() {
+        let mut doc = automerge::AutoCommit::new().with_actor(actor(1));
+        let register = Lww::new("initial".to_string(), actor(1));
+        reconcile_prop(&mut doc, automerge::ROOT, "register", &register).unwrap();
+
+        let mut doc2 = doc.fork().with_actor(actor(2));
+        let mut newer: Lww<String> = hydrate_prop(&doc2, &automerge::ROOT, "register").unwrap();
+        newer.set_with_timestamp("newer".to_string(), 10, actor(2));
+        reconcile_prop(&mut doc2, automerge::ROOT, "register", &newer).unwrap();
+
+        // The change with the highest actor ID wins the automerge conflict, make sure that
+        // isn't the one with the highest timestamp
+        let mut older: Lww<String> = hydrate_prop(&doc, &automerge::ROOT, "register").unwrap();
+        older.set_with_timestamp("older".to_string(), 5, actor(3));
+        reconcile_prop(&mut doc, automerge::ROOT, "register", &older).unwrap();
+
+        let mut merged = doc.clone();
+        merged.merge(&mut doc2).unwrap();
+        let mut merged2 = doc2.clone();
+        merged2.merge(&mut doc).unwrap();
+        for doc in [&merged, &merged2] {
+            let hydrated: Lww<String> = hydrate_prop(doc, &automerge::ROOT, "register").unwrap();
+            assert_eq!(hydrated, newer);
+        }
+    }
+
+    #[test]
+    fn equal_timestamps_are_broken_by_actor() {
+        let mut doc = automerge::AutoCommit::new().with_actor(actor(9));
+        let register = Lww::new(1_u64, actor(9));
+        reconcile_prop(&mut doc, automerge::ROOT, "register", &register).unwrap();
+
+        let mut doc2 = doc.fork().with_actor(actor(8));
+        let mut one = register.clone();
+        one.set(2, actor(1));
+        reconcile_prop(&mut doc, automerge::ROOT, "register", &one).unwrap();
+        let mut two = register;
+        two.set(3, actor(2));
+        reconcile_prop(&mut doc2, automerge::ROOT, "register", &two).unwrap();
+
+        doc.merge(&mut doc2).unwrap();
+        let hydrated: Lww<u64> = hydrate_prop(&doc, &automerge::ROOT, "register").unwrap();
+        assert_eq!(hydrated, two);
+    }
+
+    #[test]
+    fn reconciling_an_older_value_does_nothing() {
+        let mut doc = automerge::AutoCommit::new();
+        let mut register = Lww::new("first".to_string(), actor(1));
+        let stale = register.clone();
+        register.set("second".to_string(), actor(1));
+        reconcile_prop(&mut doc, automerge::ROOT, "register", &register).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "register", &stale).unwrap();
+        let hydrated: Lww<String> = hydrate_prop(&doc, &automerge::ROOT, "register").unwrap();
+        assert_eq!(hydrated, register);
+    }
+}
@@ -0,0 +1,198 @@
+//! Derive macro adaptors for storing a map as a sorted list of entries
+//!
+//! Some consumers of a document, particularly ones written in JavaScript, expect ordered data to
+//! be a list of `{"key": ..., "value": ...}` objects rather than a map. This module stores any
+//! map (anything which iterates as `(&K, &V)` and can be collected from `(K, V)`, such as a
+//! [`HashMap`](std::collections::HashMap) or a [`BTreeMap`](std::collections::BTreeMap)) in that
+//! form. The entries are sorted by their encoded key, so the resulting list is the same no matter
+//! which language or map implementation produced it.
+//!
+//! Rather than using `with=` directly you can use the `map_as_entries` attribute:
+//!
+//! ```
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! # use std::collections::HashMap;
+//! #[derive(Reconcile, Hydrate)]
+//! struct Scores {
+//!     #[autosurgeon(map_as_entries)]
+//!     by_player: HashMap<String, u64>,
+//! }
+//! ```
+//!
+//! Keys are encoded using [`PropKey`] and [`FromPropKey`], just as for a map. The key of each
+//! entry is also used as its key when reconciling, so updating the value for a key updates the
+//! existing entry rather than replacing it, and inserting a key inserts a new entry at the
+//! appropriate index.
+//!
+//! If two peers concurrently insert the same key the list will contain it twice, in which case
+//! the value which is later in the list wins when hydrating.
+use std::borrow::Cow;
+
+use crate::{
+    reconcile::{LoadKey, MapReconciler},
+    FromPropKey, Hydrate, HydrateError, Prop, PropKey, ReadDoc, Reconcile, ReconcileError,
+    Reconciler,
+};
+
+pub fn reconcile<'a, K, V, I, R>(items: I, reconciler: R) -> Result<(), R::Error>
+where
+    K: PropKey + 'a,
+    V: Reconcile + 'a,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+    R: Reconciler,
+{
+    let mut entries = items
+        .into_iter()
+        .map(|(key, value)| Entry {
+            key: key.to_prop_key(),
+            value,
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    entries.reconcile(reconciler)
+}
+
+pub fn hydrate<'a, D, K, V, C>(
+    doc: &'a D,
+    obj: &automerge::ObjId,
+    prop: Prop<'a>,
+) -> Result<C, HydrateError>
+where
+    D: ReadDoc,
+    K: FromPropKey,
+    V: Hydrate,
+    C: FromIterator<(K, V)>,
+{
+    let entries: Vec<HydratedEntry<V>> = crate::hydrate_prop(doc, obj, prop)?;
+    entries
+        .into_iter()
+        .map(|e| Ok((K::from_prop_key(&e.key)?, e.value)))
+        .collect()
+}
+
+struct Entry<'a, V> {
+    key: String,
+    value: &'a V,
+}
+
+impl<'a, V: Reconcile> Reconcile for Entry<'a, V> {
+    type Key<'b> = Cow<'b, str>;
+
+    fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        let mut m = reconciler.map()?;
+        m.put("key", self.key.as_str())?;
+        m.put("value", self.value)?;
+        Ok(())
+    }
+
+    fn hydrate_key<'b, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'b>>, ReconcileError> {
+        let key = crate::reconcile::hydrate_key::<_, String>(doc, obj, prop, "key".into())?;
+        Ok(key.map(Cow::Owned))
+    }
+
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        LoadKey::Found(Cow::Borrowed(&self.key))
+    }
+}
+
+struct HydratedEntry<V> {
+    key: String,
+    value: V,
+}
+
+impl<V: Hydrate> Hydrate for HydratedEntry<V> {
+    fn hydrate_map<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        Ok(Self {
+            key: crate::hydrate_prop(doc, obj, "key")?,
+            value: crate::hydrate_prop(doc, obj, "value")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use automerge::{ActorId, ReadDoc};
+    use automerge_test::{assert_doc, list, map};
+
+    use crate::reconcile_prop;
+
+    struct Scores(HashMap<String, u64>);
+
+    impl crate::Reconcile for Scores {
+        type Key<'a> = crate::reconcile::NoKey;
+        fn reconcile<R: crate::Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+            super::reconcile(&self.0, reconciler)
+        }
+    }
+
+    #[test]
+    fn round_trip_as_sorted_entries() {
+        let mut doc = automerge::AutoCommit::new();
+        let scores = Scores(HashMap::from([
+            ("charlie".to_string(), 3),
+            ("alice".to_string(), 1),
+            ("bob".to_string(), 2),
+        ]));
+        reconcile_prop(&mut doc, automerge::ROOT, "scores", &scores).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "scores" => { list! {
+                    { map! { "key" => { "alice" }, "value" => { 1_u64 } } },
+                    { map! { "key" => { "bob" }, "value" => { 2_u64 } } },
+                    { map! { "key" => { "charlie" }, "value" => { 3_u64 } } },
+                }}
+            }
+        );
+
+        let hydrated: BTreeMap<String, u64> =
+            super::hydrate(&doc, &automerge::ROOT, "scores".into()).unwrap();
+        assert_eq!(
+            hydrated.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("alice".to_string(), 1),
+                ("bob".to_string(), 2),
+                ("charlie".to_string(), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn updates_entries_by_key() {
+        let mut doc = automerge::AutoCommit::new().with_actor(ActorId::from(vec![1]));
+        let mut scores = Scores(HashMap::from([
+            ("alice".to_string(), 1),
+            ("bob".to_string(), 2),
+        ]));
+        reconcile_prop(&mut doc, automerge::ROOT, "scores", &scores).unwrap();
+
+        // Concurrently update bob's score and insert charlie, both changes should survive
+        let mut doc2 = doc.fork().with_actor(ActorId::from(vec![2]));
+        let mut scores2 = Scores(scores.0.clone());
+        scores2.0.insert("bob".to_string(), 5);
+        reconcile_prop(&mut doc2, automerge::ROOT, "scores", &scores2).unwrap();
+
+        scores.0.insert("charlie".to_string(), 3);
+        reconcile_prop(&mut doc, automerge::ROOT, "scores", &scores).unwrap();
+        doc.merge(&mut doc2).unwrap();
+
+        let entries = doc.get(automerge::ROOT, "scores").unwrap().unwrap().1;
+        assert_eq!(doc.length(&entries), 3);
+        let hydrated: HashMap<String, u64> =
+            super::hydrate(&doc, &automerge::ROOT, "scores".into()).unwrap();
+        assert_eq!(
+            hydrated,
+            HashMap::from([
+                ("alice".to_string(), 1),
+                ("bob".to_string(), 5),
+                ("charlie".to_string(), 3),
+            ])
+        );
+    }
+}
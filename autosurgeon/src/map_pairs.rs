@@ -0,0 +1,130 @@
+//! Derive macro adaptors for storing a `Vec<(K, V)>` as a map
+//!
+//! Automerge maps are ordered by key, so hydrating one into a `HashMap` loses that ordering. If
+//! you need to iterate over the entries in document order but don't want to pull in an ordered
+//! map implementation you can use this module to hydrate into a `Vec<(K, V)>` instead. Keys are
+//! encoded using [`PropKey`] and [`FromPropKey`], just as for
+//! [`HashMap`][std::collections::HashMap].
+//!
+//! ```
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! #[derive(Reconcile, Hydrate)]
+//! struct Scores {
+//!     #[autosurgeon(with = "autosurgeon::map_pairs")]
+//!     by_player: Vec<(String, u64)>,
+//! }
+//! ```
+//!
+//! When reconciling, entries with duplicate keys overwrite earlier entries with the same key.
+use automerge::{ObjType, Value};
+
+use crate::{FromPropKey, Hydrate, HydrateError, Prop, PropKey, ReadDoc, Reconcile, Reconciler};
+
+pub fn reconcile<'a, K, V, I, R>(items: I, reconciler: R) -> Result<(), R::Error>
+where
+    K: PropKey + 'a,
+    V: Reconcile + 'a,
+    I: IntoIterator<Item = &'a (K, V)>,
+    R: Reconciler,
+{
+    crate::reconcile::map::reconcile_map_impl(
+        items.into_iter().map(|(k, v)| (k.to_prop_key(), v)),
+        reconciler,
+    )
+}
+
+pub fn hydrate<'a, D, K, V>(
+    doc: &'a D,
+    obj: &automerge::ObjId,
+    prop: Prop<'a>,
+) -> Result<Vec<(K, V)>, HydrateError>
+where
+    D: ReadDoc,
+    K: FromPropKey,
+    V: Hydrate,
+{
+    let obj = match doc.get(obj, &prop)? {
+        Some((Value::Object(ObjType::Map | ObjType::Table), id)) => id,
+        _ => {
+            return Err(HydrateError::unexpected(
+                "a map",
+                "something else".to_string(),
+            ))
+        }
+    };
+    let mut result = Vec::with_capacity(doc.length(&obj));
+    for entry in crate::hydrate::map::map_entries(doc, &obj, K::from_prop_key)? {
+        result.push(entry?);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge_test::{assert_doc, map};
+
+    use crate::reconcile_prop;
+
+    struct Scores(Vec<(String, u64)>);
+
+    impl crate::Reconcile for Scores {
+        type Key<'a> = crate::reconcile::NoKey;
+        fn reconcile<R: crate::Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+            super::reconcile(&self.0, reconciler)
+        }
+    }
+
+    #[test]
+    fn round_trip_in_document_order() {
+        let mut doc = automerge::AutoCommit::new();
+        let scores = Scores(vec![
+            ("charlie".to_string(), 3),
+            ("alice".to_string(), 1),
+            ("bob".to_string(), 2),
+        ]);
+        reconcile_prop(&mut doc, automerge::ROOT, "scores", &scores).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "scores" => { map! {
+                    "alice" => { 1_u64 },
+                    "bob" => { 2_u64 },
+                    "charlie" => { 3_u64 },
+                }}
+            }
+        );
+
+        let hydrated: Vec<(String, u64)> =
+            super::hydrate(&doc, &automerge::ROOT, "scores".into()).unwrap();
+        assert_eq!(
+            hydrated,
+            vec![
+                ("alice".to_string(), 1),
+                ("bob".to_string(), 2),
+                ("charlie".to_string(), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn hydrate_parses_keys() {
+        let mut doc = automerge::AutoCommit::new();
+        let scores = std::collections::HashMap::from([(2_u16, "two"), (1_u16, "one")]);
+        reconcile_prop(&mut doc, automerge::ROOT, "scores", &scores).unwrap();
+        let hydrated: Vec<(u16, String)> =
+            super::hydrate(&doc, &automerge::ROOT, "scores".into()).unwrap();
+        assert_eq!(
+            hydrated,
+            vec![(1, "one".to_string()), (2, "two".to_string())]
+        );
+    }
+
+    #[test]
+    fn hydrate_rejects_non_maps() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "scores", vec![1_u64]).unwrap();
+        let result: Result<Vec<(String, u64)>, _> =
+            super::hydrate(&doc, &automerge::ROOT, "scores".into());
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,78 @@
+//! Derive macro adaptors controlling what happens to keys which are in the document but not in
+//! the map being reconciled
+//!
+//! By default reconciling a map deletes every key in the document which is not in the map. This
+//! is usually what you want, but it means that if one peer inserts a key concurrently with
+//! another peer reconciling a map which doesn't contain it yet, the key is deleted again when the
+//! second peer next reconciles. For maps which many peers insert into concurrently, such as
+//! presence information or caches, use the `map_strategy` attribute to keep those keys instead:
+//!
+//! ```
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! # use std::collections::HashMap;
+//! #[derive(Reconcile, Hydrate)]
+//! struct Room {
+//!     #[autosurgeon(map_strategy = "keep_absent")]
+//!     presence: HashMap<String, u64>,
+//! }
+//! ```
+//!
+//! The strategies are
+//!
+//! * `"delete_absent"` - delete keys which are not in the map, the default
+//! * `"keep_absent"` - leave keys which are not in the map as they are, see [`keep_absent`]
+//!
+//! With `keep_absent` you remove a key by deleting it from the document directly, e.g. with
+//! [`crate::reconcile_delete`].
+use crate::{PropKey, Reconcile, Reconciler};
+
+/// Reconcile a map without deleting keys which are in the document but not in `items`
+pub fn keep_absent<'a, K, V, I, R>(items: I, reconciler: R) -> Result<(), R::Error>
+where
+    K: PropKey + 'a,
+    V: Reconcile + 'a,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+    R: Reconciler,
+{
+    crate::reconcile::map::reconcile_map_entries(
+        items.into_iter().map(|(k, v)| (k.to_prop_key(), v)),
+        reconciler,
+        false,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use automerge_test::{assert_doc, map};
+
+    use crate::reconcile_prop;
+
+    struct Presence(HashMap<String, u64>);
+
+    impl crate::Reconcile for Presence {
+        type Key<'a> = crate::reconcile::NoKey;
+        fn reconcile<R: crate::Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+            super::keep_absent(&self.0, reconciler)
+        }
+    }
+
+    #[test]
+    fn keeps_keys_missing_from_the_map() {
+        let mut doc = automerge::AutoCommit::new();
+        let presence = Presence(HashMap::from([("alice".to_string(), 1)]));
+        reconcile_prop(&mut doc, automerge::ROOT, "presence", &presence).unwrap();
+        let presence = Presence(HashMap::from([("bob".to_string(), 2)]));
+        reconcile_prop(&mut doc, automerge::ROOT, "presence", &presence).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "presence" => { map! {
+                    "alice" => { 1_u64 },
+                    "bob" => { 2_u64 },
+                }}
+            }
+        );
+    }
+}
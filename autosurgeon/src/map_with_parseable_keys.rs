@@ -2,10 +2,9 @@
 //!
 //! The default implementations of [`Reconcile`] and [`Hydrate`] for
 //! [`HashMap`][std::collections::HashMap] and [`BTreeMap`][std::collections::BTreeMap]
-//! require the key to implement [`AsRef<str>`] and [`From<String>`], respectively.  This is not
-//! always possible: for example, a hash map with integer keys cannot be represented that way.
-//! As a solution, this module offers `with`-adaptors for derive macros, which rely on [`ToString`]
-//! and [`FromStr`]:
+//! encode keys using [`crate::PropKey`] and [`crate::FromPropKey`], which already cover any key
+//! implementing [`ToString`] and [`FromStr`]. For other map-like collections this module offers
+//! `with`-adaptors for derive macros, which rely on the same traits:
 //!
 //! ```
 //! # use autosurgeon::{Reconcile, Hydrate};
@@ -51,7 +50,7 @@ where
     M: FromIterator<(K, V)>,
 {
     let obj = match doc.get(obj, &prop)? {
-        Some((Value::Object(ObjType::Map), id)) => id,
+        Some((Value::Object(ObjType::Map | ObjType::Table), id)) => id,
         _ => {
             return Err(HydrateError::unexpected(
                 "a map",
@@ -59,8 +58,5 @@ where
             ))
         }
     };
-    crate::hydrate::map::hydrate_map_impl(doc, &obj, |k| {
-        k.parse::<K>()
-            .map_err(|e| HydrateError::ParseMapKey(e.into()))
-    })
+    crate::hydrate::map::hydrate_map_impl(doc, &obj, crate::prop::parse_map_key)
 }
@@ -0,0 +1,368 @@
+//! Versioned schemas and migrations between them
+//!
+//! As an application evolves the shape of the data it stores in a document changes. This module
+//! provides a way to describe each version of the schema as a separate type and to upgrade
+//! documents written with an older version of the schema to the latest one.
+//!
+//! Each version implements [`Versioned`], which gives it a version number, and every version but
+//! the first implements [`Migrate`] from the version before it. A chain of versions is then
+//! expressed as a tuple, oldest first, and passed to [`hydrate_versioned`]:
+//!
+//! ```rust
+//! # use autosurgeon::{Hydrate, Reconcile, reconcile, hydrate};
+//! # use autosurgeon::migrate::{hydrate_versioned, reconcile_versioned, Migrate, Versioned};
+//! # use automerge::ReadDoc;
+//! #[derive(Hydrate, Reconcile)]
+//! struct V1 {
+//!     name: String,
+//! }
+//! impl Versioned for V1 {
+//!     const VERSION: u64 = 1;
+//! }
+//!
+//! #[derive(Debug, PartialEq, Hydrate, Reconcile)]
+//! struct V2 {
+//!     first_name: String,
+//!     last_name: String,
+//! }
+//! impl Versioned for V2 {
+//!     const VERSION: u64 = 2;
+//! }
+//! impl Migrate<V1> for V2 {
+//!     fn migrate(previous: V1) -> Self {
+//!         let (first, last) = previous.name.split_once(' ').unwrap_or((&previous.name, ""));
+//!         V2 {
+//!             first_name: first.to_string(),
+//!             last_name: last.to_string(),
+//!         }
+//!     }
+//! }
+//!
+//! let mut doc = automerge::AutoCommit::new();
+//! reconcile_versioned(&mut doc, &V1 { name: "Sam Vimes".to_string() }).unwrap();
+//!
+//! let latest = hydrate_versioned::<(V1, V2), _>(&mut doc).unwrap();
+//! assert_eq!(
+//!     latest,
+//!     V2 {
+//!         first_name: "Sam".to_string(),
+//!         last_name: "Vimes".to_string()
+//!     }
+//! );
+//! // The document has been upgraded, and the old "name" key has been removed
+//! assert!(doc.get(&automerge::ROOT, "name").unwrap().is_none());
+//! ```
+//!
+//! The version number is stored in the root of the document under [`VERSION_KEY`]. Documents
+//! which don't have a version number (e.g. because they were written before the application
+//! started using versioned schemas) are treated as being the oldest version in the chain.
+use std::collections::HashSet;
+
+use automerge::{ScalarValue, Value};
+
+use crate::{Doc, Hydrate, HydrateError, ReadDoc, Reconcile, ReconcileError};
+
+/// The key in the root of the document which holds the schema version
+pub const VERSION_KEY: &str = "schema_version";
+
+/// A version of a schema
+pub trait Versioned: Hydrate {
+    /// The version number of this schema, this must be unique within a chain of versions
+    const VERSION: u64;
+}
+
+/// Upgrade from the previous version of a schema
+pub trait Migrate<Previous>: Versioned {
+    fn migrate(previous: Previous) -> Self;
+}
+
+/// A chain of schema versions, oldest first
+///
+/// This is implemented for tuples `(V1, V2, .., Vn)` (up to a length of 8) where `V1:
+/// Versioned` and every subsequent version implements [`Migrate`] from the version before it.
+pub trait Versions {
+    /// The latest version in the chain
+    type Latest: Versioned;
+
+    /// Hydrate the root of `doc` as the version `version`, then migrate it to [`Self::Latest`]
+    ///
+    /// Returns `Ok(None)` if `version` is not part of this chain
+    fn hydrate_version<D: ReadDoc>(
+        doc: &D,
+        version: u64,
+    ) -> Result<Option<Self::Latest>, HydrateError>;
+
+    /// The version number of the oldest version in the chain
+    fn oldest() -> u64;
+}
+
+impl<V1: Versioned> Versions for (V1,) {
+    type Latest = V1;
+
+    fn hydrate_version<D: ReadDoc>(doc: &D, version: u64) -> Result<Option<V1>, HydrateError> {
+        if version == V1::VERSION {
+            Ok(Some(crate::hydrate(doc)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn oldest() -> u64 {
+        V1::VERSION
+    }
+}
+
+macro_rules! versions_impl {
+    ($($prev:ident),+; $last:ident) => {
+        impl<$($prev,)+ $last> Versions for ($($prev,)+ $last,)
+        where
+            ($($prev,)+): Versions,
+            $last: Migrate<<($($prev,)+) as Versions>::Latest>,
+        {
+            type Latest = $last;
+
+            fn hydrate_version<D: ReadDoc>(
+                doc: &D,
+                version: u64,
+            ) -> Result<Option<$last>, HydrateError> {
+                if version == $last::VERSION {
+                    return Ok(Some(crate::hydrate(doc)?));
+                }
+                Ok(<($($prev,)+) as Versions>::hydrate_version(doc, version)?.map($last::migrate))
+            }
+
+            fn oldest() -> u64 {
+                <($($prev,)+) as Versions>::oldest()
+            }
+        }
+    };
+}
+
+versions_impl!(V1; V2);
+versions_impl!(V1, V2; V3);
+versions_impl!(V1, V2, V3; V4);
+versions_impl!(V1, V2, V3, V4; V5);
+versions_impl!(V1, V2, V3, V4, V5; V6);
+versions_impl!(V1, V2, V3, V4, V5, V6; V7);
+versions_impl!(V1, V2, V3, V4, V5, V6, V7; V8);
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrateError {
+    #[error(transparent)]
+    Hydrate(#[from] HydrateError),
+    #[error(transparent)]
+    Reconcile(#[from] ReconcileError),
+    #[error("unknown schema version {0}")]
+    UnknownVersion(u64),
+    #[error("invalid schema version, expected an unsigned integer")]
+    InvalidVersion,
+}
+
+/// Read the schema version of `doc`, returning `None` if there is no version
+pub fn version<D: ReadDoc>(doc: &D) -> Result<Option<u64>, MigrateError> {
+    match doc
+        .get(&automerge::ROOT, VERSION_KEY)
+        .map_err(HydrateError::from)?
+    {
+        None => Ok(None),
+        Some((Value::Scalar(s), _)) => match s.as_ref() {
+            ScalarValue::Uint(v) => Ok(Some(*v)),
+            ScalarValue::Int(v) if *v >= 0 => Ok(Some(*v as u64)),
+            _ => Err(MigrateError::InvalidVersion),
+        },
+        Some(_) => Err(MigrateError::InvalidVersion),
+    }
+}
+
+/// Hydrate the latest version of `V` from `doc`, upgrading the document if necessary
+///
+/// This reads the version number from the document, hydrates the corresponding version in `V`
+/// and then runs each [`Migrate`] implementation between that version and the latest one. If any
+/// migrations were run the upgraded value is reconciled back into the document along with the new
+/// version number, and any keys in the root of the document which the latest version does not
+/// write are deleted.
+pub fn hydrate_versioned<V, D>(doc: &mut D) -> Result<V::Latest, MigrateError>
+where
+    V: Versions,
+    V::Latest: Reconcile,
+    D: Doc,
+{
+    let version = version(&*doc)?.unwrap_or_else(V::oldest);
+    let latest =
+        V::hydrate_version(&*doc, version)?.ok_or(MigrateError::UnknownVersion(version))?;
+    if version != V::Latest::VERSION {
+        reconcile_versioned(doc, &latest)?;
+        remove_stale_keys(doc, &latest)?;
+    }
+    Ok(latest)
+}
+
+/// Reconcile `value` with the root of `doc` and record its version number in the document
+pub fn reconcile_versioned<V, D>(doc: &mut D, value: &V) -> Result<(), ReconcileError>
+where
+    V: Versioned + Reconcile,
+    D: Doc,
+{
+    crate::reconcile(doc, value)?;
+    crate::reconcile_prop(doc, automerge::ROOT, VERSION_KEY, V::VERSION)
+}
+
+// Delete any keys in the root of `doc` which reconciling `value` would not write. We figure out
+// which keys `value` writes by reconciling it into an empty document.
+fn remove_stale_keys<V: Reconcile, D: Doc>(doc: &mut D, value: &V) -> Result<(), ReconcileError> {
+    let mut scratch = automerge::AutoCommit::new();
+    crate::reconcile(&mut scratch, value)?;
+    let written = automerge::ReadDoc::keys(&scratch, automerge::ROOT).collect::<HashSet<_>>();
+    let stale = doc
+        .map_range(automerge::ROOT, ..)
+        .map(|item| item.key.to_string())
+        .filter(|k| k != VERSION_KEY && !written.contains(k))
+        .collect::<Vec<_>>();
+    for key in stale {
+        doc.delete(automerge::ROOT, key)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge_test::{assert_doc, map};
+
+    use super::*;
+    use crate::{Hydrate, Reconcile};
+
+    #[derive(Debug, PartialEq)]
+    struct V1 {
+        count: String,
+    }
+
+    impl Versioned for V1 {
+        const VERSION: u64 = 1;
+    }
+
+    impl Hydrate for V1 {
+        fn hydrate_map<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+            Ok(V1 {
+                count: crate::hydrate_prop(doc, obj, "count")?,
+            })
+        }
+    }
+
+    impl Reconcile for V1 {
+        type Key<'a> = crate::reconcile::NoKey;
+        fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            use crate::reconcile::MapReconciler;
+            let mut m = reconciler.map()?;
+            m.put("count", &self.count)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct V2 {
+        count: u64,
+    }
+
+    impl Versioned for V2 {
+        const VERSION: u64 = 2;
+    }
+
+    impl Migrate<V1> for V2 {
+        fn migrate(previous: V1) -> Self {
+            V2 {
+                count: previous.count.parse().unwrap_or(0),
+            }
+        }
+    }
+
+    impl Hydrate for V2 {
+        fn hydrate_map<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+            Ok(V2 {
+                count: crate::hydrate_prop(doc, obj, "count")?,
+            })
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct V3 {
+        total: u64,
+    }
+
+    impl Versioned for V3 {
+        const VERSION: u64 = 3;
+    }
+
+    impl Migrate<V2> for V3 {
+        fn migrate(previous: V2) -> Self {
+            V3 {
+                total: previous.count,
+            }
+        }
+    }
+
+    impl Hydrate for V3 {
+        fn hydrate_map<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+            Ok(V3 {
+                total: crate::hydrate_prop(doc, obj, "total")?,
+            })
+        }
+    }
+
+    impl Reconcile for V3 {
+        type Key<'a> = crate::reconcile::NoKey;
+        fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            use crate::reconcile::MapReconciler;
+            let mut m = reconciler.map()?;
+            m.put("total", self.total)
+        }
+    }
+
+    #[test]
+    fn migrates_through_every_version() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_versioned(
+            &mut doc,
+            &V1 {
+                count: "5".to_string(),
+            },
+        )
+        .unwrap();
+
+        let latest = hydrate_versioned::<(V1, V2, V3), _>(&mut doc).unwrap();
+        assert_eq!(latest, V3 { total: 5 });
+        assert_doc!(
+            doc.document(),
+            map! {
+                "total" => { 5_u64 },
+                "schema_version" => { 3_u64 },
+            }
+        );
+
+        // Hydrating again doesn't need to migrate
+        let latest = hydrate_versioned::<(V1, V2, V3), _>(&mut doc).unwrap();
+        assert_eq!(latest, V3 { total: 5 });
+    }
+
+    #[test]
+    fn unversioned_documents_are_the_oldest_version() {
+        let mut doc = automerge::AutoCommit::new();
+        crate::reconcile(
+            &mut doc,
+            &V1 {
+                count: "2".to_string(),
+            },
+        )
+        .unwrap();
+        let latest = hydrate_versioned::<(V1, V2, V3), _>(&mut doc).unwrap();
+        assert_eq!(latest, V3 { total: 2 });
+    }
+
+    #[test]
+    fn unknown_versions_are_an_error() {
+        let mut doc = automerge::AutoCommit::new();
+        crate::reconcile_prop(&mut doc, automerge::ROOT, VERSION_KEY, 7_u64).unwrap();
+        assert!(matches!(
+            hydrate_versioned::<(V1, V2, V3), _>(&mut doc),
+            Err(MigrateError::UnknownVersion(7))
+        ));
+    }
+}
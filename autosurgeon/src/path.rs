@@ -0,0 +1,218 @@
+use std::{fmt, ops::Deref, str::FromStr};
+
+use automerge as am;
+
+use crate::Prop;
+
+/// A path from an object (usually the root) of a document to a value in it
+///
+/// A `Path` is a list of map keys and list indices. The easiest way to build one is with the
+/// [`path!`](crate::path!) macro, which converts each of its arguments into an
+/// [`automerge::Prop`]. A path can be passed to [`hydrate_path`](crate::hydrate_path) and
+/// [`reconcile_at`](crate::reconcile_at):
+///
+/// ```rust
+/// # use autosurgeon::{hydrate_path, path, reconcile, reconcile_at, Path};
+/// # use std::collections::HashMap;
+/// let mut doc = automerge::AutoCommit::new();
+/// let catalogue = HashMap::from([("products", vec![HashMap::from([("name", "chair")])])]);
+/// reconcile(&mut doc, &catalogue).unwrap();
+///
+/// let name: Path = path!["products", 0, "name"];
+/// reconcile_at(&mut doc, &name, "stool").unwrap();
+/// let renamed: Option<String> = hydrate_path(&doc, &automerge::ROOT, &name).unwrap();
+/// assert_eq!(renamed.as_deref(), Some("stool"));
+/// ```
+///
+/// Paths are displayed, and parsed with [`FromStr`], in the style of a JSON pointer: each
+/// property is preceded by a `/`, and `~` and `/` in map keys are written as `~0` and `~1`. The
+/// empty path is the empty string. A property made up only of digits is parsed as a list index,
+/// so a path through a map key like `"3"` does not survive a round trip through a string.
+///
+/// ```rust
+/// # use autosurgeon::{path, Path};
+/// let name = path!["products", 3, "name"];
+/// assert_eq!(name.to_string(), "/products/3/name");
+/// assert_eq!("/products/3/name".parse::<Path>().unwrap(), name);
+/// ```
+///
+/// `Path` is also how the rest of the crate reports paths: the errors returned when hydrating or
+/// reconciling fails record the path of the value which failed, and [`paths_of`](crate::paths_of),
+/// [`plan`](crate::reconcile::plan) and [`reconcile_observed`](crate::reconcile::reconcile_observed)
+/// report the paths they write to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Path(Vec<am::Prop>);
+
+impl Path {
+    /// The empty path, which refers to the object the path starts at
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Add `prop` to the end of the path
+    pub fn push<P: Into<am::Prop>>(&mut self, prop: P) {
+        self.0.push(prop.into());
+    }
+
+    /// Add `prop` to the start of the path
+    pub(crate) fn push_front(&mut self, prop: am::Prop) {
+        self.0.insert(0, prop);
+    }
+
+    /// The path without its last property, or `None` if the path is empty
+    pub fn parent(&self) -> Option<Path> {
+        self.0.split_last().map(|(_, parent)| Path(parent.to_vec()))
+    }
+
+    /// The properties which make up the path
+    pub fn into_vec(self) -> Vec<am::Prop> {
+        self.0
+    }
+}
+
+impl Deref for Path {
+    type Target = [am::Prop];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<am::Prop>> for Path {
+    fn from(props: Vec<am::Prop>) -> Self {
+        Self(props)
+    }
+}
+
+impl From<&[am::Prop]> for Path {
+    fn from(props: &[am::Prop]) -> Self {
+        Self(props.to_vec())
+    }
+}
+
+impl From<Path> for Vec<am::Prop> {
+    fn from(path: Path) -> Self {
+        path.0
+    }
+}
+
+impl<P: Into<am::Prop>> FromIterator<P> for Path {
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        Self(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a Path {
+    type Item = Prop<'a>;
+    type IntoIter = std::iter::Map<std::slice::Iter<'a, am::Prop>, fn(&'a am::Prop) -> Prop<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter().map(Prop::from)
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for prop in &self.0 {
+            match prop {
+                am::Prop::Map(k) => write!(f, "/{}", k.replace('~', "~0").replace('/', "~1"))?,
+                am::Prop::Seq(idx) => write!(f, "/{}", idx)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The string passed to [`Path::from_str`] was not a valid path
+#[derive(Debug, thiserror::Error)]
+#[error("{0:?} is not a valid path, expected something like \"/products/3/name\"")]
+pub struct ParsePathError(String);
+
+impl FromStr for Path {
+    type Err = ParsePathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self::new());
+        }
+        let err = || ParsePathError(s.to_string());
+        let rest = s.strip_prefix('/').ok_or_else(err)?;
+        rest.split('/')
+            .map(|segment| {
+                if !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+                    return segment.parse().map(am::Prop::Seq).map_err(|_| err());
+                }
+                let mut key = String::with_capacity(segment.len());
+                let mut chars = segment.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '~' => match chars.next() {
+                            Some('0') => key.push('~'),
+                            Some('1') => key.push('/'),
+                            _ => return Err(err()),
+                        },
+                        c => key.push(c),
+                    }
+                }
+                Ok(am::Prop::Map(key))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+/// Build a [`Path`] from a list of map keys and list indices
+///
+/// Each argument can be anything which converts into an [`automerge::Prop`], such as a `&str`,
+/// a `String`, or a `usize`. See [`Path`] for an example.
+#[macro_export]
+macro_rules! path {
+    ($($prop:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut path = $crate::Path::new();
+        $(path.push($prop);)*
+        path
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::Prop;
+
+    use super::Path;
+
+    #[test]
+    fn paths_round_trip_through_strings() {
+        let paths = [
+            Path::new(),
+            path!["products", 3, "name"],
+            path!["a/b", "~c", ""],
+            path![0, 1],
+        ];
+        for path in paths {
+            let s = path.to_string();
+            assert_eq!(s.parse::<Path>().unwrap(), path, "{}", s);
+        }
+        assert_eq!(path!["a/b", "~c"].to_string(), "/a~1b/~0c");
+    }
+
+    #[test]
+    fn digits_are_parsed_as_indices() {
+        let path: Path = "/products/03/x1".parse().unwrap();
+        assert_eq!(
+            path.into_vec(),
+            vec![
+                Prop::Map("products".to_string()),
+                Prop::Seq(3),
+                Prop::Map("x1".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_paths_are_rejected() {
+        for s in ["products", "/a~2", "/a~"] {
+            assert!(s.parse::<Path>().is_err(), "{}", s);
+        }
+    }
+}
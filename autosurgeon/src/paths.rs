@@ -0,0 +1,981 @@
+//! Find out, or restrict, which parts of a document a value writes to
+//!
+//! [`paths_of`] reconciles a value without a document and records the path of every map key and
+//! list index it would write. This is useful when you need to know which subtrees of a document
+//! belong to which part of your application, for example to decide who is allowed to edit them.
+//!
+//! ```rust
+//! # use autosurgeon::{path, paths_of, Reconcile};
+//! #[derive(Reconcile)]
+//! struct Settings {
+//!     theme: String,
+//!     recent: Vec<String>,
+//! }
+//!
+//! let settings = Settings {
+//!     theme: "dark".to_string(),
+//!     recent: vec!["notes.txt".to_string()],
+//! };
+//! let paths = paths_of(&settings).unwrap();
+//! assert_eq!(
+//!     paths,
+//!     vec![path!["theme"], path!["recent"], path!["recent", 0_usize]]
+//! );
+//! ```
+//!
+//! The value is reconciled as if into an empty document, so anything which depends on what is
+//! already in the document (such as deleting stale keys) is not recorded. To stop a reconcile
+//! from modifying anything outside of a given path, including deletions, use
+//! [`reconcile_scoped`].
+use std::{cell::RefCell, collections::BTreeSet, marker::PhantomData};
+
+use crate::{
+    reconcile::{
+        CounterReconciler, LoadKey, MapReconciler, OutOfScope, SeqReconciler, TextReconciler,
+    },
+    Doc, Path, Prop, ReadDoc, Reconcile, ReconcileError, Reconciler,
+};
+
+/// Every path `value` would write to, in the order it would write them
+///
+/// The root of the document is not included. Each path is only included once, even if it would
+/// be written more than once.
+///
+/// # Errors
+///
+/// Returns an error if reconciling `value` fails for reasons which don't depend on the document,
+/// such as an `OsString` which is not valid UTF-8.
+pub fn paths_of<T: Reconcile + ?Sized>(value: &T) -> Result<Vec<Path>, ReconcileError> {
+    let mut paths = Vec::new();
+    value.reconcile(PathRecorder {
+        path: Path::new(),
+        paths: &mut paths,
+    })?;
+    let mut seen = BTreeSet::new();
+    paths.retain(|p| seen.insert(p.clone()));
+    Ok(paths)
+}
+
+/// A [`Reconciler`] which doesn't write anything but records the path of every node it visits
+struct PathRecorder<'a> {
+    path: Path,
+    paths: &'a mut Vec<Path>,
+}
+
+impl<'a> PathRecorder<'a> {
+    fn child<R: Reconcile>(
+        path: &Path,
+        paths: &mut Vec<Path>,
+        prop: automerge::Prop,
+        value: R,
+    ) -> Result<(), ReconcileError> {
+        let mut path = path.clone();
+        path.push(prop);
+        paths.push(path.clone());
+        value.reconcile(PathRecorder { path, paths })
+    }
+}
+
+impl<'a> Reconciler for PathRecorder<'a> {
+    type Error = ReconcileError;
+    type Map<'b>
+        = RecordMap<'b>
+    where
+        Self: 'b;
+    type Seq<'b>
+        = RecordSeq<'b>
+    where
+        Self: 'b;
+    type Text<'b>
+        = Leaf
+    where
+        Self: 'b;
+    type Counter<'b>
+        = Leaf
+    where
+        Self: 'b;
+
+    fn none(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn bytes<B: AsRef<[u8]>>(&mut self, _value: B) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn timestamp(&mut self, _value: i64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn unknown(&mut self, _type_code: u8, _bytes: &[u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn boolean(&mut self, _value: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn str<S: AsRef<str>>(&mut self, _value: S) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn u64(&mut self, _value: u64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn i64(&mut self, _value: i64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn f64(&mut self, _value: f64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn map(&mut self) -> Result<Self::Map<'_>, Self::Error> {
+        Ok(RecordMap {
+            path: &self.path,
+            paths: self.paths,
+        })
+    }
+
+    fn seq(&mut self) -> Result<Self::Seq<'_>, Self::Error> {
+        Ok(RecordSeq {
+            path: &self.path,
+            paths: self.paths,
+            len: 0,
+        })
+    }
+
+    fn text(&mut self) -> Result<Self::Text<'_>, Self::Error> {
+        Ok(Leaf)
+    }
+
+    fn counter(&mut self) -> Result<Self::Counter<'_>, Self::Error> {
+        Ok(Leaf)
+    }
+
+    fn replace_with_type(&mut self, _obj_type: automerge::ObjType) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn heads(&self) -> &[automerge::ChangeHash] {
+        &[]
+    }
+}
+
+struct RecordMap<'a> {
+    path: &'a Path,
+    paths: &'a mut Vec<Path>,
+}
+
+impl<'a> MapReconciler for RecordMap<'a> {
+    type Error = ReconcileError;
+    type EntriesIter<'b>
+        = std::iter::Empty<(&'b str, automerge::Value<'b>)>
+    where
+        Self: 'b;
+
+    fn entries(&self) -> Self::EntriesIter<'_> {
+        std::iter::empty()
+    }
+
+    fn entry<P: AsRef<str>>(&self, _prop: P) -> Option<automerge::Value<'_>> {
+        None
+    }
+
+    fn put<R: Reconcile, P: AsRef<str>>(&mut self, prop: P, value: R) -> Result<(), Self::Error> {
+        let prop = automerge::Prop::Map(prop.as_ref().to_string());
+        PathRecorder::child(self.path, self.paths, prop, value)
+    }
+
+    fn delete<P: AsRef<str>>(&mut self, _prop: P) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn hydrate_entry_key<'b, R: Reconcile, P: AsRef<str>>(
+        &self,
+        _prop: P,
+    ) -> Result<LoadKey<R::Key<'b>>, Self::Error> {
+        Ok(LoadKey::KeyNotFound)
+    }
+}
+
+struct RecordSeq<'a> {
+    path: &'a Path,
+    paths: &'a mut Vec<Path>,
+    len: usize,
+}
+
+impl<'a> SeqReconciler for RecordSeq<'a> {
+    type Error = ReconcileError;
+    type ItemIter<'b>
+        = std::iter::Empty<(usize, automerge::Value<'b>, automerge::ObjId)>
+    where
+        Self: 'b;
+
+    fn items(&self) -> Self::ItemIter<'_> {
+        std::iter::empty()
+    }
+
+    fn get(&self, _index: usize) -> Result<Option<automerge::Value<'_>>, Self::Error> {
+        Ok(None)
+    }
+
+    fn hydrate_item_key<'b, R: Reconcile>(
+        &self,
+        _index: usize,
+    ) -> Result<LoadKey<R::Key<'b>>, Self::Error> {
+        Ok(LoadKey::KeyNotFound)
+    }
+
+    fn insert<R: Reconcile>(&mut self, index: usize, value: R) -> Result<(), Self::Error> {
+        self.len += 1;
+        PathRecorder::child(self.path, self.paths, automerge::Prop::Seq(index), value)
+    }
+
+    fn set<R: Reconcile>(&mut self, index: usize, value: R) -> Result<(), Self::Error> {
+        PathRecorder::child(self.path, self.paths, automerge::Prop::Seq(index), value)
+    }
+
+    fn delete(&mut self, _index: usize) -> Result<(), Self::Error> {
+        self.len = self.len.saturating_sub(1);
+        Ok(())
+    }
+
+    fn len(&self) -> Result<usize, Self::Error> {
+        Ok(self.len)
+    }
+}
+
+/// Text and counters have no children so there is nothing to record
+struct Leaf;
+
+impl TextReconciler for Leaf {
+    type Error = ReconcileError;
+
+    fn splice<S: AsRef<str>>(
+        &mut self,
+        _pos: usize,
+        _delete: isize,
+        _insert: S,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn append<S: AsRef<str>>(&mut self, _text: S) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn mark(
+        &mut self,
+        _mark: &crate::text::Mark,
+        _expand: automerge::marks::ExpandMark,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn unmark(
+        &mut self,
+        _name: &str,
+        _start: usize,
+        _end: usize,
+        _expand: automerge::marks::ExpandMark,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn heads(&self) -> &[automerge::ChangeHash] {
+        &[]
+    }
+}
+
+impl CounterReconciler for Leaf {
+    type Error = ReconcileError;
+
+    fn increment(&mut self, _by: i64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set(&mut self, _value: i64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn current(&self) -> Result<Option<i64>, Self::Error> {
+        Ok(None)
+    }
+}
+
+/// Reconcile `value` with the root of `doc`, skipping any change outside of `allowed_prefix`
+///
+/// This guards parts of a document which don't belong to the code doing the reconciling, for
+/// example the data of other tenants in a multi-tenant document. `value` is reconciled as normal,
+/// except that anything which would change the document outside of `allowed_prefix` is skipped.
+/// Writing a value which is already in the document is not a change, so `value` can contain
+/// (unchanged) data outside of the prefix. Objects on the way to the prefix are created if they
+/// don't exist, but inserting into or deleting from a list above the prefix is always skipped
+/// because it would move the elements after it.
+///
+/// # Errors
+///
+/// If any changes were skipped this returns [`ReconcileError::OutOfScope`] listing their paths.
+/// Everything inside `allowed_prefix` has still been reconciled, so if you want all or nothing
+/// then reconcile into a transaction and roll it back on error.
+///
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use autosurgeon::{path, paths::reconcile_scoped, reconcile, ReconcileError};
+/// let mut doc = automerge::AutoCommit::new();
+/// let mut tenants = HashMap::new();
+/// tenants.insert("acme", HashMap::from([("plan", "free")]));
+/// tenants.insert("initech", HashMap::from([("plan", "free")]));
+/// reconcile(&mut doc, HashMap::from([("tenants", &tenants)])).unwrap();
+///
+/// let acme = path!["tenants", "acme"];
+/// tenants.insert("acme", HashMap::from([("plan", "pro")]));
+/// reconcile_scoped(&mut doc, &acme, HashMap::from([("tenants", &tenants)])).unwrap();
+///
+/// // A buggy component which only owns "acme" but removes another tenant
+/// tenants.remove("initech");
+/// let result = reconcile_scoped(&mut doc, &acme, HashMap::from([("tenants", &tenants)]));
+/// let Err(ReconcileError::OutOfScope(e)) = result else { panic!() };
+/// assert_eq!(e.skipped, vec![path!["tenants", "initech"]]);
+/// ```
+pub fn reconcile_scoped<D: Doc, R: Reconcile>(
+    doc: &mut D,
+    allowed_prefix: &Path,
+    value: R,
+) -> Result<(), ReconcileError> {
+    let scope = Scope {
+        prefix: allowed_prefix.clone(),
+        skipped: RefCell::new(Vec::new()),
+    };
+    crate::reconcile(
+        doc,
+        Scoped {
+            value,
+            path: Path::new(),
+            current: Current::Missing,
+            scope: &scope,
+        },
+    )?;
+    let mut skipped = scope.skipped.into_inner();
+    if skipped.is_empty() {
+        return Ok(());
+    }
+    let mut seen = BTreeSet::new();
+    skipped.retain(|p| seen.insert(p.clone()));
+    Err(OutOfScope {
+        prefix: scope.prefix,
+        skipped,
+    }
+    .into())
+}
+
+struct Scope {
+    prefix: Path,
+    skipped: RefCell<Vec<Path>>,
+}
+
+enum Relation {
+    /// The path is the prefix or is below it
+    Inside,
+    /// The path is above the prefix
+    Ancestor,
+    Outside,
+}
+
+impl Scope {
+    fn relation(&self, path: &[automerge::Prop]) -> Relation {
+        if path.starts_with(&self.prefix) {
+            Relation::Inside
+        } else if self.prefix.starts_with(path) {
+            Relation::Ancestor
+        } else {
+            Relation::Outside
+        }
+    }
+
+    fn skip(&self, path: &[automerge::Prop]) {
+        self.skipped.borrow_mut().push(Path::from(path));
+    }
+}
+
+fn child(path: &[automerge::Prop], prop: automerge::Prop) -> Path {
+    let mut path = Path::from(path);
+    path.push(prop);
+    path
+}
+
+/// What is currently in the document at the path being reconciled
+enum Current {
+    Missing,
+    Scalar(automerge::ScalarValue),
+    Object(automerge::ObjType),
+}
+
+impl Current {
+    fn of(value: Option<automerge::Value<'_>>) -> Self {
+        match value {
+            None => Self::Missing,
+            Some(automerge::Value::Scalar(s)) => Self::Scalar(s.into_owned()),
+            Some(automerge::Value::Object(o)) => Self::Object(o),
+        }
+    }
+
+    fn is_map(&self) -> bool {
+        matches!(
+            self,
+            Self::Object(automerge::ObjType::Map | automerge::ObjType::Table)
+        )
+    }
+}
+
+/// A value which is reconciled at `path`, guarding any changes it makes if `path` is not inside
+/// the prefix
+struct Scoped<'s, T> {
+    value: T,
+    path: Path,
+    current: Current,
+    scope: &'s Scope,
+}
+
+impl<'s, T: Reconcile> Reconcile for Scoped<'s, T> {
+    type Key<'a> = T::Key<'a>;
+    const KEY_IMMUTABLE: bool = T::KEY_IMMUTABLE;
+
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        match self.scope.relation(&self.path) {
+            Relation::Inside => self.value.reconcile(reconciler),
+            Relation::Ancestor | Relation::Outside => self.value.reconcile(Guard {
+                inner: reconciler,
+                path: &self.path,
+                current: &self.current,
+                scope: self.scope,
+            }),
+        }
+    }
+
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, ReconcileError> {
+        T::hydrate_key(doc, obj, prop)
+    }
+
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        self.value.key()
+    }
+}
+
+/// A [`Reconciler`] for a node which is not inside the prefix
+///
+/// Objects are only entered if they already exist (or are above the prefix) and scalars are only
+/// compared with what is in the document, nothing is ever written.
+struct Guard<'s, R> {
+    inner: R,
+    path: &'s [automerge::Prop],
+    current: &'s Current,
+    scope: &'s Scope,
+}
+
+impl<'s, R: Reconciler> Guard<'s, R> {
+    fn scalar<V: Into<automerge::ScalarValue>>(&mut self, value: V) -> Result<(), R::Error> {
+        match self.current {
+            Current::Scalar(s) if *s == value.into() => {}
+            _ => self.scope.skip(self.path),
+        }
+        Ok(())
+    }
+
+    /// Whether we can enter the object here, skipping it if not
+    fn enter(&self, exists: bool) -> bool {
+        match self.scope.relation(self.path) {
+            Relation::Ancestor => true,
+            _ if exists => true,
+            _ => {
+                self.scope.skip(self.path);
+                false
+            }
+        }
+    }
+
+    fn leaf(&self, obj_type: Option<automerge::ObjType>) -> Skipped<'_, R> {
+        let exists = match (self.current, obj_type) {
+            (Current::Object(o), Some(obj_type)) => *o == obj_type,
+            (Current::Scalar(automerge::ScalarValue::Counter(_)), None) => true,
+            _ => false,
+        };
+        if !exists {
+            self.scope.skip(self.path);
+        }
+        let counter = match self.current {
+            Current::Scalar(automerge::ScalarValue::Counter(c)) => Some(c.into()),
+            _ => None,
+        };
+        Skipped {
+            heads: self.inner.heads().to_vec(),
+            counter,
+            path: self.path,
+            scope: self.scope,
+            _reconciler: PhantomData,
+        }
+    }
+}
+
+impl<'s, R: Reconciler> Reconciler for Guard<'s, R> {
+    type Error = R::Error;
+    type Map<'b>
+        = GuardMap<'b, R::Map<'b>>
+    where
+        Self: 'b;
+    type Seq<'b>
+        = GuardSeq<'b, R::Seq<'b>>
+    where
+        Self: 'b;
+    type Text<'b>
+        = Skipped<'b, R>
+    where
+        Self: 'b;
+    type Counter<'b>
+        = Skipped<'b, R>
+    where
+        Self: 'b;
+
+    fn none(&mut self) -> Result<(), Self::Error> {
+        self.scalar(automerge::ScalarValue::Null)
+    }
+
+    fn bytes<B: AsRef<[u8]>>(&mut self, value: B) -> Result<(), Self::Error> {
+        self.scalar(value.as_ref().to_vec())
+    }
+
+    fn timestamp(&mut self, value: i64) -> Result<(), Self::Error> {
+        self.scalar(automerge::ScalarValue::Timestamp(value))
+    }
+
+    fn unknown(&mut self, type_code: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.scalar(automerge::ScalarValue::Unknown {
+            type_code,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    fn boolean(&mut self, value: bool) -> Result<(), Self::Error> {
+        self.scalar(value)
+    }
+
+    fn str<S: AsRef<str>>(&mut self, value: S) -> Result<(), Self::Error> {
+        self.scalar(value.as_ref())
+    }
+
+    fn u64(&mut self, value: u64) -> Result<(), Self::Error> {
+        self.scalar(value)
+    }
+
+    fn i64(&mut self, value: i64) -> Result<(), Self::Error> {
+        self.scalar(value)
+    }
+
+    fn f64(&mut self, value: f64) -> Result<(), Self::Error> {
+        self.scalar(value)
+    }
+
+    fn map(&mut self) -> Result<Self::Map<'_>, Self::Error> {
+        let inner = if self.enter(self.current.is_map()) {
+            Some(self.inner.map()?)
+        } else {
+            None
+        };
+        Ok(GuardMap {
+            inner,
+            path: self.path,
+            scope: self.scope,
+        })
+    }
+
+    fn seq(&mut self) -> Result<Self::Seq<'_>, Self::Error> {
+        let exists = matches!(self.current, Current::Object(automerge::ObjType::List));
+        let inner = if self.enter(exists) {
+            Some(self.inner.seq()?)
+        } else {
+            None
+        };
+        Ok(GuardSeq {
+            inner,
+            path: self.path,
+            scope: self.scope,
+        })
+    }
+
+    fn text(&mut self) -> Result<Self::Text<'_>, Self::Error> {
+        Ok(self.leaf(Some(automerge::ObjType::Text)))
+    }
+
+    fn counter(&mut self) -> Result<Self::Counter<'_>, Self::Error> {
+        Ok(self.leaf(None))
+    }
+
+    fn replace_with_type(&mut self, _obj_type: automerge::ObjType) -> Result<(), Self::Error> {
+        self.scope.skip(self.path);
+        Ok(())
+    }
+
+    fn heads(&self) -> &[automerge::ChangeHash] {
+        self.inner.heads()
+    }
+}
+
+/// An iterator which is empty if there is no object to iterate over
+struct MaybeIter<I>(Option<I>);
+
+impl<I: Iterator> Iterator for MaybeIter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.as_mut()?.next()
+    }
+}
+
+/// A map which is not inside the prefix, `inner` is `None` if the map doesn't exist
+struct GuardMap<'s, M> {
+    inner: Option<M>,
+    path: &'s [automerge::Prop],
+    scope: &'s Scope,
+}
+
+impl<'s, M: MapReconciler> MapReconciler for GuardMap<'s, M> {
+    type Error = M::Error;
+    type EntriesIter<'b>
+        = MaybeIter<M::EntriesIter<'b>>
+    where
+        Self: 'b;
+
+    fn entries(&self) -> Self::EntriesIter<'_> {
+        MaybeIter(self.inner.as_ref().map(|m| m.entries()))
+    }
+
+    fn entry<P: AsRef<str>>(&self, prop: P) -> Option<automerge::Value<'_>> {
+        self.inner.as_ref()?.entry(prop)
+    }
+
+    fn put<R: Reconcile, P: AsRef<str>>(&mut self, prop: P, value: R) -> Result<(), Self::Error> {
+        let Some(inner) = &mut self.inner else {
+            return Ok(());
+        };
+        let path = child(self.path, automerge::Prop::Map(prop.as_ref().to_string()));
+        let current = Current::of(inner.entry(&prop));
+        inner.put(
+            prop,
+            Scoped {
+                value,
+                path,
+                current,
+                scope: self.scope,
+            },
+        )
+    }
+
+    fn delete<P: AsRef<str>>(&mut self, prop: P) -> Result<(), Self::Error> {
+        let Some(inner) = &mut self.inner else {
+            return Ok(());
+        };
+        let path = child(self.path, automerge::Prop::Map(prop.as_ref().to_string()));
+        match self.scope.relation(&path) {
+            Relation::Inside => inner.delete(prop),
+            _ => {
+                if inner.entry(&prop).is_some() {
+                    self.scope.skip(&path);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn hydrate_entry_key<'b, R: Reconcile, P: AsRef<str>>(
+        &self,
+        prop: P,
+    ) -> Result<LoadKey<R::Key<'b>>, Self::Error> {
+        match &self.inner {
+            Some(inner) => inner.hydrate_entry_key::<R, P>(prop),
+            None => Ok(LoadKey::KeyNotFound),
+        }
+    }
+}
+
+/// A list which is not inside the prefix, `inner` is `None` if the list doesn't exist
+struct GuardSeq<'s, S> {
+    inner: Option<S>,
+    path: &'s [automerge::Prop],
+    scope: &'s Scope,
+}
+
+impl<'s, S: SeqReconciler> SeqReconciler for GuardSeq<'s, S> {
+    type Error = S::Error;
+    type ItemIter<'b>
+        = MaybeIter<S::ItemIter<'b>>
+    where
+        Self: 'b;
+
+    fn items(&self) -> Self::ItemIter<'_> {
+        MaybeIter(self.inner.as_ref().map(|s| s.items()))
+    }
+
+    fn get(&self, index: usize) -> Result<Option<automerge::Value<'_>>, Self::Error> {
+        match &self.inner {
+            Some(inner) => inner.get(index),
+            None => Ok(None),
+        }
+    }
+
+    fn hydrate_item_key<'b, R: Reconcile>(
+        &self,
+        index: usize,
+    ) -> Result<LoadKey<R::Key<'b>>, Self::Error> {
+        match &self.inner {
+            Some(inner) => inner.hydrate_item_key::<R>(index),
+            None => Ok(LoadKey::KeyNotFound),
+        }
+    }
+
+    // Inserting or deleting moves the elements after it, which may be inside the prefix, so
+    // neither is allowed in a list which isn't inside the prefix
+    fn insert<R: Reconcile>(&mut self, index: usize, _value: R) -> Result<(), Self::Error> {
+        if self.inner.is_some() {
+            self.scope
+                .skip(&child(self.path, automerge::Prop::Seq(index)));
+        }
+        Ok(())
+    }
+
+    fn set<R: Reconcile>(&mut self, index: usize, value: R) -> Result<(), Self::Error> {
+        let Some(inner) = &mut self.inner else {
+            return Ok(());
+        };
+        let path = child(self.path, automerge::Prop::Seq(index));
+        let current = Current::of(inner.get(index)?);
+        inner.set(
+            index,
+            Scoped {
+                value,
+                path,
+                current,
+                scope: self.scope,
+            },
+        )
+    }
+
+    fn delete(&mut self, index: usize) -> Result<(), Self::Error> {
+        if self.inner.is_some() {
+            self.scope
+                .skip(&child(self.path, automerge::Prop::Seq(index)));
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> Result<usize, Self::Error> {
+        match &self.inner {
+            Some(inner) => inner.len(),
+            None => Ok(0),
+        }
+    }
+}
+
+/// Text or a counter which is not inside the prefix, every change to which is skipped
+struct Skipped<'s, R> {
+    heads: Vec<automerge::ChangeHash>,
+    counter: Option<i64>,
+    path: &'s [automerge::Prop],
+    scope: &'s Scope,
+    _reconciler: PhantomData<fn() -> R>,
+}
+
+impl<'s, R: Reconciler> TextReconciler for Skipped<'s, R> {
+    type Error = R::Error;
+
+    fn splice<S: AsRef<str>>(
+        &mut self,
+        _pos: usize,
+        _delete: isize,
+        _insert: S,
+    ) -> Result<(), Self::Error> {
+        self.scope.skip(self.path);
+        Ok(())
+    }
+
+    fn append<S: AsRef<str>>(&mut self, _text: S) -> Result<(), Self::Error> {
+        self.scope.skip(self.path);
+        Ok(())
+    }
+
+    fn mark(
+        &mut self,
+        _mark: &crate::text::Mark,
+        _expand: automerge::marks::ExpandMark,
+    ) -> Result<(), Self::Error> {
+        self.scope.skip(self.path);
+        Ok(())
+    }
+
+    fn unmark(
+        &mut self,
+        _name: &str,
+        _start: usize,
+        _end: usize,
+        _expand: automerge::marks::ExpandMark,
+    ) -> Result<(), Self::Error> {
+        self.scope.skip(self.path);
+        Ok(())
+    }
+
+    fn heads(&self) -> &[automerge::ChangeHash] {
+        &self.heads
+    }
+}
+
+impl<'s, R: Reconciler> CounterReconciler for Skipped<'s, R> {
+    type Error = R::Error;
+
+    fn increment(&mut self, by: i64) -> Result<(), Self::Error> {
+        if by != 0 || self.counter.is_none() {
+            self.scope.skip(self.path);
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, value: i64) -> Result<(), Self::Error> {
+        if self.counter != Some(value) {
+            self.scope.skip(self.path);
+        }
+        Ok(())
+    }
+
+    fn current(&self) -> Result<Option<i64>, Self::Error> {
+        Ok(self.counter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use automerge_test::{assert_doc, list, map};
+
+    use super::{paths_of, reconcile_scoped};
+    use crate::{path, reconcile, Path, ReconcileError, Text};
+
+    #[test]
+    fn nested_collections() {
+        let mut value = HashMap::new();
+        value.insert("lists".to_string(), vec![vec![1_u64], vec![]]);
+        assert_eq!(
+            paths_of(&value).unwrap(),
+            vec![
+                path!["lists"],
+                path!["lists", 0_usize],
+                path!["lists", 0_usize, 0_usize],
+                path!["lists", 1_usize],
+            ]
+        );
+    }
+
+    #[test]
+    fn text_and_scalars_have_no_children() {
+        let mut value = HashMap::new();
+        value.insert("text".to_string(), Text::with_value("hello"));
+        assert_eq!(paths_of(&value).unwrap(), vec![path!["text"]]);
+        assert_eq!(paths_of(&1_u64).unwrap(), Vec::<Path>::new());
+    }
+
+    fn tenants(acme: &str, initech: &str) -> HashMap<&'static str, HashMap<&'static str, String>> {
+        let mut tenants = HashMap::new();
+        tenants.insert("acme", acme.to_string());
+        tenants.insert("initech", initech.to_string());
+        HashMap::from([("tenants", tenants)])
+    }
+
+    #[test]
+    fn scoped_reconcile_writes_inside_the_prefix() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile(&mut doc, tenants("free", "free")).unwrap();
+        let acme = path!["tenants", "acme"];
+        reconcile_scoped(&mut doc, &acme, tenants("pro", "free")).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "tenants" => { map! {
+                    "acme" => { "pro" },
+                    "initech" => { "free" },
+                }}
+            }
+        );
+    }
+
+    #[test]
+    fn scoped_reconcile_skips_writes_outside_the_prefix() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile(&mut doc, tenants("free", "free")).unwrap();
+        let acme = path!["tenants", "acme"];
+        let mut value = tenants("pro", "enterprise");
+        value.insert("admin", HashMap::new());
+        let Err(ReconcileError::OutOfScope(e)) = reconcile_scoped(&mut doc, &acme, value) else {
+            panic!("expected an OutOfScope error");
+        };
+        let mut skipped = e.skipped;
+        skipped.sort();
+        assert_eq!(skipped, vec![path!["admin"], path!["tenants", "initech"]]);
+        assert_doc!(
+            doc.document(),
+            map! {
+                "tenants" => { map! {
+                    "acme" => { "pro" },
+                    "initech" => { "free" },
+                }}
+            }
+        );
+
+        // Removing a tenant deletes it from the map, which is also outside the prefix
+        let mut value = tenants("pro", "free");
+        value.get_mut("tenants").unwrap().remove("initech");
+        let Err(ReconcileError::OutOfScope(e)) = reconcile_scoped(&mut doc, &acme, value) else {
+            panic!("expected an OutOfScope error");
+        };
+        assert_eq!(e.skipped, vec![path!["tenants", "initech"]]);
+        assert_doc!(
+            doc.document(),
+            map! {
+                "tenants" => { map! {
+                    "acme" => { "pro" },
+                    "initech" => { "free" },
+                }}
+            }
+        );
+    }
+
+    #[test]
+    fn scoped_reconcile_does_not_shift_lists_above_the_prefix() {
+        let items = |ns: &[u64]| {
+            let items: Vec<_> = ns.iter().map(|n| HashMap::from([("n", *n)])).collect();
+            HashMap::from([("items", items)])
+        };
+        let mut doc = automerge::AutoCommit::new();
+        reconcile(&mut doc, items(&[1, 2])).unwrap();
+        let second = path!["items", 1_usize];
+
+        reconcile_scoped(&mut doc, &second, items(&[1, 3])).unwrap();
+        let Err(ReconcileError::OutOfScope(e)) = reconcile_scoped(&mut doc, &second, items(&[1]))
+        else {
+            panic!("expected an OutOfScope error");
+        };
+        assert_eq!(e.skipped, vec![path!["items", 1_usize]]);
+        assert_doc!(
+            doc.document(),
+            map! {
+                "items" => { list! {
+                    { map! { "n" => { 1_u64 } } },
+                    { map! { "n" => { 3_u64 } } },
+                }}
+            }
+        );
+    }
+}
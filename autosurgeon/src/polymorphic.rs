@@ -0,0 +1,191 @@
+//! Reconciling and hydrating trait objects
+//!
+//! Fields of type `Box<dyn Trait>` can't be reconciled or hydrated directly because there's no
+//! way to know which concrete type to construct when hydrating. Instead you can declare a
+//! registry of the concrete types which implement the trait using [`polymorphic_registry!`] and
+//! use the `polymorphic` attribute to refer to it. Each value is stored as a map containing the
+//! tag of its concrete type under `"type"` and the value itself under `"value"`.
+//!
+//! The trait must have [`AsAny`] as a supertrait so that the registry can find the concrete type
+//! of a value.
+//!
+//! ```
+//! # use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile, polymorphic::AsAny};
+//! trait Shape: AsAny {
+//!     fn area(&self) -> f64;
+//! }
+//!
+//! #[derive(Reconcile, Hydrate)]
+//! struct Circle {
+//!     radius: f64,
+//! }
+//!
+//! impl Shape for Circle {
+//!     fn area(&self) -> f64 {
+//!         std::f64::consts::PI * self.radius * self.radius
+//!     }
+//! }
+//!
+//! #[derive(Reconcile, Hydrate)]
+//! struct Square {
+//!     side: f64,
+//! }
+//!
+//! impl Shape for Square {
+//!     fn area(&self) -> f64 {
+//!         self.side * self.side
+//!     }
+//! }
+//!
+//! autosurgeon::polymorphic_registry! {
+//!     mod shapes for dyn Shape {
+//!         "circle" => Circle,
+//!         "square" => Square,
+//!     }
+//! }
+//!
+//! #[derive(Reconcile, Hydrate)]
+//! struct Drawing {
+//!     #[autosurgeon(polymorphic = "shapes")]
+//!     shape: Box<dyn Shape>,
+//! }
+//!
+//! # fn main() {
+//! let mut doc = automerge::AutoCommit::new();
+//! let drawing = Drawing { shape: Box::new(Square { side: 2.0 }) };
+//! reconcile(&mut doc, &drawing).unwrap();
+//!
+//! let hydrated: Drawing = hydrate(&doc).unwrap();
+//! assert_eq!(hydrated.shape.area(), 4.0);
+//! # }
+//! ```
+//!
+//! [`polymorphic_registry!`]: crate::polymorphic_registry
+use std::any::Any;
+
+use automerge::ObjType;
+
+use crate::{
+    reconcile::MapReconciler, Hydrate, HydrateError, Prop, ReadDoc, Reconcile, Reconciler,
+};
+
+/// The key of the type tag in the map a polymorphic value is stored in
+pub const TAG: &str = "type";
+/// The key of the value itself in the map a polymorphic value is stored in
+pub const VALUE: &str = "value";
+
+/// Convert a trait object to `&dyn Any` so it can be downcast to its concrete type
+///
+/// This is implemented for every `'static` type, you just need to make it a supertrait of the
+/// trait you want to use with [`polymorphic_registry!`](crate::polymorphic_registry).
+pub trait AsAny: Any {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Reconcile `value` as a map containing `tag` and the value
+///
+/// If the document contains a value with a different tag then it is replaced rather than
+/// updated, so that none of the old value is left behind.
+pub fn reconcile_tagged<R: Reconciler, T: Reconcile + ?Sized>(
+    mut reconciler: R,
+    tag: &str,
+    value: &T,
+) -> Result<(), R::Error> {
+    let other_type = {
+        let m = reconciler.map()?;
+        match m.entry(TAG) {
+            Some(v) => v.to_str() != Some(tag),
+            None => m.entries().next().is_some(),
+        }
+    };
+    if other_type {
+        reconciler.replace_with_type(ObjType::Map)?;
+    }
+    let mut m = reconciler.map()?;
+    m.put(TAG, tag)?;
+    m.put(VALUE, value)?;
+    Ok(())
+}
+
+/// Hydrate the tag of the polymorphic value at `prop` of `obj`
+///
+/// Returns the tag along with the ID of the map the value is stored in.
+pub fn hydrate_tag<D: ReadDoc>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: Prop<'_>,
+) -> Result<(String, automerge::ObjId), HydrateError> {
+    match doc.get(obj, &prop)? {
+        Some((automerge::Value::Object(ObjType::Map | ObjType::Table), id)) => {
+            let tag = String::hydrate(doc, &id, TAG.into()).map_err(|e| e.in_prop(TAG))?;
+            Ok((tag, id))
+        }
+        _ => Err(HydrateError::unexpected(
+            "a map containing a type tag",
+            "something else".to_string(),
+        )),
+    }
+}
+
+/// Declare a module which reconciles and hydrates `Box<dyn Trait>`
+///
+/// The module contains `reconcile` and `hydrate` functions, so it can be used with
+/// `#[autosurgeon(polymorphic = "...")]` (or `with = "..."`). Each registered type is identified
+/// in the document by its tag. See the [module documentation](crate::polymorphic) for an example.
+///
+/// # Panics
+///
+/// The generated `reconcile` function panics if the value is not one of the registered types.
+#[macro_export]
+macro_rules! polymorphic_registry {
+    ($vis:vis mod $name:ident for dyn $trait:path { $($tag:literal => $ty:ty),* $(,)? }) => {
+        $vis mod $name {
+            use super::*;
+
+            pub fn reconcile<R: $crate::Reconciler>(
+                value: &::std::boxed::Box<dyn $trait>,
+                reconciler: R,
+            ) -> ::std::result::Result<(), R::Error> {
+                let any = $crate::polymorphic::AsAny::as_any(&**value);
+                $(
+                    if let ::std::option::Option::Some(v) = any.downcast_ref::<$ty>() {
+                        return $crate::polymorphic::reconcile_tagged(reconciler, $tag, v);
+                    }
+                )*
+                panic!(concat!(
+                    "the type of the value is not registered with ",
+                    stringify!($name)
+                ))
+            }
+
+            pub fn hydrate<D: $crate::ReadDoc>(
+                doc: &D,
+                obj: &::automerge::ObjId,
+                prop: $crate::Prop<'_>,
+            ) -> ::std::result::Result<::std::boxed::Box<dyn $trait>, $crate::HydrateError> {
+                let (tag, value_obj) = $crate::polymorphic::hydrate_tag(doc, obj, prop)?;
+                match tag.as_str() {
+                    $(
+                        $tag => ::std::result::Result::Ok(::std::boxed::Box::new(
+                            $crate::hydrate_prop::<_, $ty, _, _>(
+                                doc,
+                                &value_obj,
+                                $crate::polymorphic::VALUE,
+                            )?,
+                        )),
+                    )*
+                    other => ::std::result::Result::Err($crate::HydrateError::unexpected(
+                        concat!("one of the types registered with ", stringify!($name)),
+                        ::std::format!("the unknown type {:?}", other),
+                    )),
+                }
+            }
+        }
+    };
+}
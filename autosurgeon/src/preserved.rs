@@ -0,0 +1,303 @@
+use automerge::{ObjType, ScalarValue, Value};
+
+use crate::{
+    hydrate::Unexpected,
+    reconcile::{LoadKey, NoKey},
+    Hydrate, HydrateError, Prop, ReadDoc, Reconcile, ReconcileError,
+};
+
+/// A reference to an object in the document which should be left untouched
+///
+/// Hydrating a `PreservedObject` succeeds for any object in the document, whatever its type,
+/// including object types added in versions of automerge newer than this crate knows about.
+/// Reconciling it does nothing at all, so the object it was hydrated from is left exactly as it
+/// was. This is useful for parts of a document which your application doesn't understand (for
+/// example because they were written by a newer version of it) but which you don't want to lose
+/// when reconciling the rest of the document.
+///
+/// Because reconciling does nothing, a `PreservedObject` can't be used to copy an object to
+/// another location: if it is reconciled somewhere other than where it was hydrated from (for
+/// example because it has moved to a new index in a `Vec`) then nothing is written.
+///
+/// # Example
+///
+/// ```rust
+/// # use autosurgeon::{hydrate, reconcile, Hydrate, PreservedObject, Reconcile};
+/// # use automerge::{transaction::Transactable, ReadDoc};
+/// #[derive(Hydrate, Reconcile)]
+/// struct Settings {
+///     theme: String,
+///     plugins: PreservedObject,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// doc.put(automerge::ROOT, "theme", "dark").unwrap();
+/// let plugins = doc
+///     .put_object(automerge::ROOT, "plugins", automerge::ObjType::Map)
+///     .unwrap();
+/// doc.put(&plugins, "spellcheck", true).unwrap();
+///
+/// let mut settings: Settings = hydrate(&doc).unwrap();
+/// assert_eq!(settings.plugins.obj_type(), automerge::ObjType::Map);
+/// settings.theme = "light".to_string();
+/// reconcile(&mut doc, &settings).unwrap();
+///
+/// let (_, id) = doc.get(automerge::ROOT, "plugins").unwrap().unwrap();
+/// assert_eq!(&id, settings.plugins.id());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreservedObject {
+    id: automerge::ObjId,
+    obj_type: ObjType,
+}
+
+impl PreservedObject {
+    /// The ID of the object this was hydrated from
+    pub fn id(&self) -> &automerge::ObjId {
+        &self.id
+    }
+
+    /// The type of the object this was hydrated from
+    pub fn obj_type(&self) -> ObjType {
+        self.obj_type
+    }
+}
+
+impl Hydrate for PreservedObject {
+    fn hydrate<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<Self, HydrateError> {
+        match doc.get(obj, &prop)? {
+            Some((Value::Object(obj_type), id)) => Ok(Self { id, obj_type }),
+            Some((Value::Scalar(_), _)) => Err(HydrateError::unexpected(
+                "an object",
+                "a scalar value".to_string(),
+            )),
+            None => Err(HydrateError::Unexpected(Unexpected::None)),
+        }
+    }
+}
+
+impl Reconcile for PreservedObject {
+    type Key<'a> = NoKey;
+
+    fn reconcile<R: crate::Reconciler>(&self, _reconciler: R) -> Result<(), R::Error> {
+        Ok(())
+    }
+}
+
+/// A value which may be a scalar of a type this version of automerge doesn't know about
+///
+/// Newer versions of automerge may add new scalar types, which older versions read as
+/// [`ScalarValue::Unknown`]. No ordinary `Hydrate` implementation accepts an unknown scalar, so
+/// a document containing one fails to hydrate, and even if it did hydrate reconciling would
+/// overwrite the unknown value with something else. Wrapping a field in `PreserveUnknown` makes
+/// hydrating an unknown scalar succeed with [`PreserveUnknown::Unknown`], which reconciles back to
+/// exactly the same scalar.
+///
+/// # Example
+///
+/// ```rust
+/// # use autosurgeon::{hydrate, reconcile, Hydrate, PreserveUnknown, Reconcile};
+/// # use automerge::{transaction::Transactable, ReadDoc, ScalarValue};
+/// #[derive(Hydrate, Reconcile)]
+/// struct Event {
+///     name: String,
+///     at: PreserveUnknown<i64>,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// doc.put(automerge::ROOT, "name", "launch").unwrap();
+/// // Some scalar type added by a newer automerge
+/// let future = ScalarValue::Unknown { type_code: 12, bytes: vec![1, 2, 3] };
+/// doc.put(automerge::ROOT, "at", future.clone()).unwrap();
+///
+/// let mut event: Event = hydrate(&doc).unwrap();
+/// assert!(matches!(event.at, PreserveUnknown::Unknown { type_code: 12, .. }));
+/// event.name = "liftoff".to_string();
+/// reconcile(&mut doc, &event).unwrap();
+///
+/// let (at, _) = doc.get(automerge::ROOT, "at").unwrap().unwrap();
+/// assert_eq!(at, automerge::Value::Scalar(std::borrow::Cow::Owned(future)));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PreserveUnknown<T> {
+    /// A value which `T` could hydrate
+    Known(T),
+    /// A [`ScalarValue::Unknown`]
+    Unknown { type_code: u8, bytes: Vec<u8> },
+}
+
+impl<T> PreserveUnknown<T> {
+    /// The known value, if there is one
+    pub fn known(&self) -> Option<&T> {
+        match self {
+            Self::Known(t) => Some(t),
+            Self::Unknown { .. } => None,
+        }
+    }
+
+    /// Consume `self`, returning the known value if there is one
+    pub fn into_known(self) -> Option<T> {
+        match self {
+            Self::Known(t) => Some(t),
+            Self::Unknown { .. } => None,
+        }
+    }
+}
+
+impl<T> From<T> for PreserveUnknown<T> {
+    fn from(t: T) -> Self {
+        Self::Known(t)
+    }
+}
+
+fn unknown_scalar<D: ReadDoc>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: &Prop<'_>,
+) -> Result<Option<(u8, Vec<u8>)>, automerge::AutomergeError> {
+    Ok(match doc.get(obj, prop)? {
+        Some((Value::Scalar(s), _)) => match s.as_ref() {
+            ScalarValue::Unknown { type_code, bytes } => Some((*type_code, bytes.clone())),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+impl<T: Hydrate> Hydrate for PreserveUnknown<T> {
+    fn hydrate<D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<Self, HydrateError> {
+        match unknown_scalar(doc, obj, &prop)? {
+            Some((type_code, bytes)) => Ok(Self::Unknown { type_code, bytes }),
+            None => T::hydrate(doc, obj, prop).map(Self::Known),
+        }
+    }
+}
+
+impl<T: Reconcile> Reconcile for PreserveUnknown<T> {
+    type Key<'a> = T::Key<'a>;
+    const KEY_IMMUTABLE: bool = T::KEY_IMMUTABLE;
+
+    fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        match self {
+            Self::Known(t) => t.reconcile(reconciler),
+            Self::Unknown { type_code, bytes } => reconciler.unknown(*type_code, bytes),
+        }
+    }
+
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, ReconcileError> {
+        match unknown_scalar(doc, obj, &prop)? {
+            Some(_) => Ok(LoadKey::KeyNotFound),
+            None => T::hydrate_key(doc, obj, prop),
+        }
+    }
+
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        match self {
+            Self::Known(t) => t.key(),
+            Self::Unknown { .. } => LoadKey::KeyNotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::{transaction::Transactable, ReadDoc, ScalarValue};
+    use automerge_test::{assert_doc, list, map};
+
+    use crate::{hydrate_prop, reconcile_prop};
+
+    use super::{PreserveUnknown, PreservedObject};
+
+    #[test]
+    fn preserved_object_round_trips() {
+        let mut doc = automerge::AutoCommit::new();
+        let extra = doc
+            .put_object(automerge::ROOT, "extra", automerge::ObjType::List)
+            .unwrap();
+        doc.insert(&extra, 0, 1).unwrap();
+        let text = doc
+            .insert_object(&extra, 1, automerge::ObjType::Text)
+            .unwrap();
+        doc.splice_text(&text, 0, 0, "hello").unwrap();
+
+        let preserved: PreservedObject = hydrate_prop(&doc, &automerge::ROOT, "extra").unwrap();
+        assert_eq!(preserved.obj_type(), automerge::ObjType::List);
+        assert_eq!(preserved.id(), &extra);
+        reconcile_prop(&mut doc, automerge::ROOT, "extra", &preserved).unwrap();
+
+        assert_doc!(
+            doc.document(),
+            map! {
+                "extra" => { list! { { 1 }, { "hello" } } },
+            }
+        );
+    }
+
+    #[test]
+    fn preserved_object_requires_an_object() {
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(automerge::ROOT, "extra", "scalar").unwrap();
+        assert!(hydrate_prop::<_, PreservedObject, _, _>(&doc, &automerge::ROOT, "extra").is_err());
+        assert!(
+            hydrate_prop::<_, PreservedObject, _, _>(&doc, &automerge::ROOT, "missing").is_err()
+        );
+    }
+
+    #[test]
+    fn unknown_scalars_survive_a_round_trip() {
+        let mut doc = automerge::AutoCommit::new();
+        let list = doc
+            .put_object(automerge::ROOT, "list", automerge::ObjType::List)
+            .unwrap();
+        let unknown = ScalarValue::Unknown {
+            type_code: 12,
+            bytes: vec![7, 8],
+        };
+        doc.insert(&list, 0, 1_i64).unwrap();
+        doc.insert(&list, 1, unknown.clone()).unwrap();
+        doc.commit();
+        let heads = doc.get_heads();
+
+        assert!(hydrate_prop::<_, Vec<i64>, _, _>(&doc, &automerge::ROOT, "list").is_err());
+        let mut values: Vec<PreserveUnknown<i64>> =
+            hydrate_prop(&doc, &automerge::ROOT, "list").unwrap();
+        assert_eq!(
+            values,
+            vec![
+                PreserveUnknown::Known(1),
+                PreserveUnknown::Unknown {
+                    type_code: 12,
+                    bytes: vec![7, 8],
+                },
+            ]
+        );
+
+        // Reconciling the hydrated values changes nothing
+        reconcile_prop(&mut doc, automerge::ROOT, "list", &values).unwrap();
+        doc.commit();
+        assert_eq!(doc.get_heads(), heads);
+
+        values[0] = PreserveUnknown::Known(2);
+        values.push(PreserveUnknown::Known(3));
+        reconcile_prop(&mut doc, automerge::ROOT, "list", &values).unwrap();
+        let (value, _) = doc.get(&list, 1).unwrap().unwrap();
+        assert_eq!(
+            value,
+            automerge::Value::Scalar(std::borrow::Cow::Owned(unknown))
+        );
+        let (value, _) = doc.get(&list, 2).unwrap().unwrap();
+        assert_eq!(value, automerge::Value::from(3_i64));
+    }
+}
@@ -1,6 +1,8 @@
 use automerge as am;
 use std::borrow::Cow;
 
+use crate::HydrateError;
+
 #[derive(Clone, Debug)]
 pub enum Prop<'a> {
     Key(Cow<'a, str>),
@@ -25,6 +27,22 @@ impl<'a> From<&Prop<'a>> for automerge::Prop {
     }
 }
 
+/// Renders a path like `products[3].price`
+pub(crate) struct DisplayPath<'a>(pub(crate) &'a [am::Prop]);
+
+impl<'a> std::fmt::Display for DisplayPath<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, prop) in self.0.iter().enumerate() {
+            match prop {
+                am::Prop::Map(k) if i == 0 => write!(f, "{}", k)?,
+                am::Prop::Map(k) => write!(f, ".{}", k)?,
+                am::Prop::Seq(idx) => write!(f, "[{}]", idx)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl From<u32> for Prop<'static> {
     fn from(v: u32) -> Self {
         Prop::Index(v)
@@ -66,3 +84,85 @@ impl<'a> From<&'a am::Prop> for Prop<'a> {
         }
     }
 }
+
+/// A type which can be used as the key of a map in the document
+///
+/// Automerge maps are always keyed by strings, so when reconciling a map such as a
+/// `HashMap<K, V>` each key is encoded into the string which is used as the property of the
+/// corresponding entry in the document. This is implemented for every type which implements
+/// [`ToString`], which means that maps with integer, `char` or `bool` keys,
+/// [`Uuid`](https://docs.rs/uuid) keys, or keys which are newtypes implementing
+/// [`std::fmt::Display`] can all be reconciled. A key type which only implements [`AsRef<str>`]
+/// must also implement `Display` to be used as the key of a map.
+///
+/// The inverse operation, used when hydrating, is [`FromPropKey`].
+pub trait PropKey {
+    /// Encode this key as the property of a map entry
+    fn to_prop_key(&self) -> String;
+}
+
+impl<K: ToString + ?Sized> PropKey for K {
+    fn to_prop_key(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// A type which can be decoded from the property of a map entry
+///
+/// This is the inverse of [`PropKey`] and is implemented for every type which implements
+/// [`std::str::FromStr`]. Failure to parse a key results in a [`HydrateError::ParseMapKey`]
+/// whose message includes the key and the type it couldn't be parsed as.
+///
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use autosurgeon::{hydrate, reconcile};
+/// let mut scores = HashMap::new();
+/// scores.insert(1_u64, "one".to_string());
+/// scores.insert(2_u64, "two".to_string());
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile(&mut doc, &scores).unwrap();
+///
+/// let hydrated: HashMap<u64, String> = hydrate(&doc).unwrap();
+/// assert_eq!(hydrated, scores);
+/// ```
+pub trait FromPropKey: Sized {
+    /// Decode a key from the property of a map entry
+    fn from_prop_key(key: &str) -> Result<Self, HydrateError>;
+}
+
+impl<K> FromPropKey for K
+where
+    K: std::str::FromStr,
+    K::Err: std::error::Error + Send + Sync + 'static,
+{
+    fn from_prop_key(key: &str) -> Result<Self, HydrateError> {
+        parse_map_key(key)
+    }
+}
+
+/// Parse `key` as a `K`, failing with a [`HydrateError::ParseMapKey`] which names the key and
+/// the type it was parsed as
+pub(crate) fn parse_map_key<K>(key: &str) -> Result<K, HydrateError>
+where
+    K: std::str::FromStr,
+    K::Err: std::error::Error + Send + Sync + 'static,
+{
+    key.parse().map_err(|e: K::Err| {
+        HydrateError::ParseMapKey(Box::new(InvalidMapKey {
+            key: key.to_string(),
+            ty: std::any::type_name::<K>(),
+            source: Box::new(e),
+        }))
+    })
+}
+
+/// The error in a [`HydrateError::ParseMapKey`] when a key fails to parse, whose
+/// [`source`](std::error::Error::source) is the error from the key type's parser
+#[derive(Debug, thiserror::Error)]
+#[error("{key:?} is not a valid {ty}")]
+struct InvalidMapKey {
+    key: String,
+    ty: &'static str,
+    source: Box<dyn std::error::Error + Send + Sync + 'static>,
+}
@@ -4,9 +4,20 @@ use automerge::ScalarValue;
 
 use crate::{Doc, Prop, ReadDoc};
 
+mod float;
+pub use float::{CanonicalFloats, FloatKey};
 mod impls;
 pub(crate) mod map;
+mod masked;
+mod observe;
+mod plan;
+mod read_cache;
+mod scratch;
 mod seq;
+pub use masked::{reconcile_masked, ReconcileMasked};
+pub use observe::{reconcile_observed, ReconcileOp};
+pub use plan::{plan, PlannedAction, PlannedChange};
+pub use scratch::ReconcileScratch;
 
 /// A node in the document we are reconciling with.
 ///
@@ -20,7 +31,7 @@ mod seq;
 /// implementation of a trait representing that structure after first updating the document to
 /// match the implied structure.
 pub trait Reconciler {
-    type Error: std::error::Error + From<StaleHeads>;
+    type Error: std::error::Error + From<StaleHeads> + From<ImmutableKeyChanged> + From<NotUtf8>;
 
     /// The type returned from [`Self::map`]
     type Map<'a>: MapReconciler<Error = Self::Error>
@@ -66,10 +77,17 @@ pub trait Reconciler {
     /// Set the current node to a [`automerge::ScalarValue::F64`]
     fn f64(&mut self, value: f64) -> Result<(), Self::Error>;
 
+    /// Set the current node to a [`automerge::ScalarValue::Unknown`]
+    ///
+    /// Unknown scalars are scalar types added by versions of automerge newer than the one in use,
+    /// this writes one back exactly as it was read. See [`crate::PreserveUnknown`].
+    fn unknown(&mut self, type_code: u8, bytes: &[u8]) -> Result<(), Self::Error>;
+
     /// Set the current node to a [`automerge::ObjType::Map`]
     ///
     /// The [`MapReconciler`] which is returned can be used to reconcile the state of the resulting
-    /// map
+    /// map. If the current node is an [`automerge::ObjType::Table`] (which some other tools create)
+    /// then it is replaced by a new map, as automerge does not allow writing into tables.
     fn map(&mut self) -> Result<Self::Map<'_>, Self::Error>;
 
     /// Set the current node to a [`automerge::ObjType::List`]
@@ -90,8 +108,28 @@ pub trait Reconciler {
     /// text
     fn counter(&mut self) -> Result<Self::Counter<'_>, Self::Error>;
 
+    /// Replace the current node with a new, empty object of type `obj_type`
+    ///
+    /// [`Self::map`], [`Self::seq`], and [`Self::text`] reconcile into the existing object if it
+    /// already has the right type. This method instead always discards the current value, along
+    /// with any values it conflicts with and (if it is an object) all of its children. Subsequent
+    /// calls to `map`, `seq`, or `text` will reconcile into the new object.
+    ///
+    /// This is useful when the value being reconciled has nothing in common with what is in the
+    /// document, for example when switching between enum variants, as it avoids merging the new
+    /// value with stale data.
+    fn replace_with_type(&mut self, obj_type: automerge::ObjType) -> Result<(), Self::Error>;
+
     /// Get the heads of the document this reconciler is pointing at
     fn heads(&self) -> &[automerge::ChangeHash];
+
+    /// Buffers which implementations of [`Reconcile`] can use for temporary collections
+    ///
+    /// The default implementation returns `None`, in which case implementations should allocate
+    /// whatever they need. See [`ReconcileScratch`].
+    fn scratch(&self) -> Option<&ReconcileScratch> {
+        None
+    }
 }
 
 /// A node in the document which is a map.
@@ -99,7 +137,7 @@ pub trait Reconciler {
 /// A `MapReconciler` is obtained from [`Reconciler::map`] so once you have a `MapReconciler` it is
 /// pointing at an already existing map in the underlying document or transaction.
 pub trait MapReconciler {
-    type Error: std::error::Error + From<StaleHeads>;
+    type Error: std::error::Error + From<StaleHeads> + From<ImmutableKeyChanged> + From<NotUtf8>;
     type EntriesIter<'a>: Iterator<Item = (&'a str, automerge::Value<'a>)>
     where
         Self: 'a;
@@ -139,6 +177,11 @@ pub trait MapReconciler {
         Ok(())
     }
 
+    /// Delete every entry in the map
+    fn clear(&mut self) -> Result<(), Self::Error> {
+        self.retain(|_, _| false)
+    }
+
     /// Remove any entries that do not satisfy the given predicate.
     fn retain<F: FnMut(&str, automerge::Value) -> bool>(
         &mut self,
@@ -161,12 +204,15 @@ pub trait MapReconciler {
 
 /// A node in the document which is an `automerge::List`
 pub trait SeqReconciler {
-    type Error: std::error::Error + From<StaleHeads>;
-    type ItemIter<'a>: Iterator<Item = automerge::Value<'a>>
+    type Error: std::error::Error + From<StaleHeads> + From<ImmutableKeyChanged> + From<NotUtf8>;
+    type ItemIter<'a>: Iterator<Item = (usize, automerge::Value<'a>, automerge::ObjId)>
     where
         Self: 'a;
 
     /// An iterator over the items currently in this node in the document
+    ///
+    /// Each item is the index of the item, its value, and the ID of the operation which inserted
+    /// it. For items which are objects this ID is also the ID of the object.
     fn items(&self) -> Self::ItemIter<'_>;
 
     /// Get a single item from the document
@@ -187,6 +233,16 @@ pub trait SeqReconciler {
     /// Insert the given value at the given index in the document
     fn insert<R: Reconcile>(&mut self, index: usize, value: R) -> Result<(), Self::Error>;
 
+    /// Insert the given value at the end of the sequence
+    ///
+    /// This is the same as inserting at [`Self::len`], but appending doesn't move any of the
+    /// existing elements, so implementations can avoid the bookkeeping an insert in the middle of
+    /// the sequence needs.
+    fn push<R: Reconcile>(&mut self, value: R) -> Result<(), Self::Error> {
+        let len = self.len()?;
+        self.insert(len, value)
+    }
+
     /// Reconcile the value of an index with some `R`
     fn set<R: Reconcile>(&mut self, index: usize, value: R) -> Result<(), Self::Error>;
 
@@ -203,22 +259,77 @@ pub trait SeqReconciler {
 
 /// A node in the document which is an `automerge::ScalarValue::Counter`
 pub trait CounterReconciler {
-    type Error: std::error::Error + From<StaleHeads>;
+    type Error: std::error::Error + From<StaleHeads> + From<ImmutableKeyChanged> + From<NotUtf8>;
 
+    /// Increment the counter by `by`
+    ///
+    /// If there is no counter here then one is created with the value `by`, unless the value here
+    /// is an integer (e.g. because a peer which didn't know this was a counter overwrote it) in
+    /// which case it is converted back into a counter, keeping its value.
     fn increment(&mut self, by: i64) -> Result<(), Self::Error>;
     fn set(&mut self, value: i64) -> Result<(), Self::Error>;
+
+    /// The value of the counter currently in the document
+    ///
+    /// Returns `None` if there is nothing here yet (e.g. because we are inserting into a sequence)
+    /// or if the value here is not a counter.
+    fn current(&self) -> Result<Option<i64>, Self::Error>;
+
+    /// The ID of the operation which created the counter currently in the document
+    ///
+    /// Two counters at the same location are the same counter if they have the same ID. Returns
+    /// `None` if there is no counter here or this reconciler can't tell.
+    fn current_id(&self) -> Result<Option<automerge::ObjId>, Self::Error> {
+        Ok(None)
+    }
+
+    /// The heads of the document this reconciler is pointing at
+    fn heads(&self) -> &[automerge::ChangeHash] {
+        &[]
+    }
 }
 
 /// A node in the document which is an `automerge::ObjType::Text`
 pub trait TextReconciler {
-    type Error: std::error::Error + From<StaleHeads>;
+    type Error: std::error::Error + From<StaleHeads> + From<ImmutableKeyChanged> + From<NotUtf8>;
     fn splice<S: AsRef<str>>(
         &mut self,
         pos: usize,
         delete: isize,
         insert: S,
     ) -> Result<(), Self::Error>;
+
+    /// Insert `text` at the end of the text
+    ///
+    /// The default implementation splices `text` in after the last `char` of
+    /// [`Self::current`], or at the start if this reconciler can't read the current text.
+    fn append<S: AsRef<str>>(&mut self, text: S) -> Result<(), Self::Error> {
+        let end = self.current().map(|t| t.chars().count()).unwrap_or(0);
+        self.splice(end, 0, text)
+    }
+
+    /// Add `mark` to the text
+    fn mark(
+        &mut self,
+        mark: &crate::text::Mark,
+        expand: automerge::marks::ExpandMark,
+    ) -> Result<(), Self::Error>;
+
+    /// Remove the mark called `name` from the `char`s `start..end`
+    fn unmark(
+        &mut self,
+        name: &str,
+        start: usize,
+        end: usize,
+        expand: automerge::marks::ExpandMark,
+    ) -> Result<(), Self::Error>;
+
     fn heads(&self) -> &[automerge::ChangeHash];
+
+    /// The current contents of the text object, or `None` if this reconciler can't read them
+    fn current(&self) -> Option<String> {
+        None
+    }
 }
 
 /// Placeholder type to be used for types which do not have a key
@@ -226,7 +337,19 @@ pub trait TextReconciler {
 pub struct NoKey;
 
 /// The result of either loading a key from the document or from a `R: Reconcile`
-#[derive(Debug)]
+///
+/// When implementing [`Reconcile::hydrate_key`] and [`Reconcile::key`] by hand the helpers here
+/// save matching on every variant, for example [`LoadKey::and_then`] for loading a key which is
+/// computed from a value in the document:
+///
+/// ```rust
+/// # use autosurgeon::reconcile::LoadKey;
+/// let loaded: LoadKey<String> = LoadKey::Found("42".to_string());
+/// let id = loaded.and_then(|s| LoadKey::from_option(s.parse::<u64>().ok()));
+/// assert_eq!(id, LoadKey::Found(42));
+/// assert_eq!(id.ok(), Some(42));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LoadKey<K> {
     /// This data type does not have a key
     NoKey,
@@ -237,6 +360,14 @@ pub enum LoadKey<K> {
 }
 
 impl<K> LoadKey<K> {
+    /// `LoadKey::Found` if `key` is `Some`, otherwise `LoadKey::KeyNotFound`
+    pub fn from_option(key: Option<K>) -> Self {
+        match key {
+            Some(k) => Self::Found(k),
+            None => Self::KeyNotFound,
+        }
+    }
+
     /// If this is a `LoadKey::Found`, map `f` over the contents
     pub fn map<L, F: FnOnce(K) -> L>(self, f: F) -> LoadKey<L> {
         match self {
@@ -245,6 +376,43 @@ impl<K> LoadKey<K> {
             Self::Found(k) => LoadKey::Found(f(k)),
         }
     }
+
+    /// If this is a `LoadKey::Found`, replace it with the result of calling `f` on the contents
+    pub fn and_then<L, F: FnOnce(K) -> LoadKey<L>>(self, f: F) -> LoadKey<L> {
+        match self {
+            Self::NoKey => LoadKey::NoKey,
+            Self::KeyNotFound => LoadKey::KeyNotFound,
+            Self::Found(k) => f(k),
+        }
+    }
+
+    /// Borrow the key, if there is one
+    pub fn as_ref(&self) -> LoadKey<&K> {
+        match self {
+            Self::NoKey => LoadKey::NoKey,
+            Self::KeyNotFound => LoadKey::KeyNotFound,
+            Self::Found(k) => LoadKey::Found(k),
+        }
+    }
+
+    /// The key if this is a `LoadKey::Found`, otherwise `None`
+    pub fn ok(self) -> Option<K> {
+        match self {
+            Self::Found(k) => Some(k),
+            Self::NoKey | Self::KeyNotFound => None,
+        }
+    }
+
+    /// Whether this is a `LoadKey::Found`
+    pub fn is_found(&self) -> bool {
+        matches!(self, Self::Found(_))
+    }
+}
+
+impl<K> From<Option<K>> for LoadKey<K> {
+    fn from(key: Option<K>) -> Self {
+        Self::from_option(key)
+    }
 }
 
 /// A data type which can be reconciled
@@ -252,6 +420,11 @@ impl<K> LoadKey<K> {
 /// The required method is `reconcile`. This allows you to update the state of a document based on
 /// the state of the implementor.
 ///
+/// `Reconcile` is implemented for `&T` wherever it is implemented for `T`, so every function which
+/// takes a value to reconcile (such as [`reconcile`], [`reconcile_prop`], [`reconcile_insert`],
+/// and [`MapReconciler::put`]) also accepts a reference. Reconciling only ever reads the value, so
+/// pass a reference rather than moving or cloning a value which you want to keep using.
+///
 /// As well as the `reconcile` method, `Reconcile` also allows you to specify the "key" of the
 /// type. This is used by autosurgeon to determine how to merge with existing data in the document.
 /// Imagine that you have a list of items like this:
@@ -315,6 +488,18 @@ pub trait Reconcile {
     /// should be `Cow<'a, String>`.
     type Key<'a>: PartialEq;
 
+    /// Whether it is an error for the key of this item to change
+    ///
+    /// Usually if the key of an item in the document differs from the key of the item being
+    /// reconciled then the item is treated as a new item, which means the old object (and its
+    /// history) is replaced with a new one. If the key is not supposed to change this is almost
+    /// always a bug, so if this is `true` reconciling will instead fail with
+    /// [`ReconcileError::ImmutableKeyChanged`]. This applies to map entries and to sequence
+    /// elements which are replaced by an element with a different key at the same index.
+    ///
+    /// The derive macros set this with `#[autosurgeon(key_immutable)]`.
+    const KEY_IMMUTABLE: bool = false;
+
     /// Reconcile this item with the document
     ///
     /// See the documentation of `reconciler` for more details. Typically though there are two
@@ -389,6 +574,115 @@ pub trait Reconcile {
     fn key(&self) -> LoadKey<Self::Key<'_>> {
         LoadKey::NoKey
     }
+
+    /// A hash of the contents of `self`, used to skip reconciling values which haven't changed
+    ///
+    /// Reconciling an unchanged value still reads every object and key beneath it, which for a
+    /// large value that is reconciled often (e.g. on every frame) adds up. If this returns
+    /// `Some` then reconciling with [`reconcile_with_scratch`] remembers the digest of the object
+    /// the value was reconciled into, and the next time a value with the same digest is
+    /// reconciled into that object with the same [`ReconcileScratch`] it is skipped.
+    ///
+    /// Two values with the same digest must reconcile to the same thing. [`digest_of`] computes a
+    /// digest for any type implementing `Hash`, and the derive macros implement this method using
+    /// it with `#[autosurgeon(digest)]`.
+    ///
+    /// The digests only describe what was written by reconciling, so they are only used if the
+    /// value is reconciled into an object, and the scratch forgets them as soon as the document
+    /// is changed in any other way, including by committing. See
+    /// [`ReconcileScratch::forget_digests`].
+    fn digest(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// A digest of `value` for implementing [`Reconcile::digest`]
+///
+/// Digests are only ever compared with other digests computed in the same process, so this uses
+/// the standard library's default hasher.
+pub fn digest_of<T: std::hash::Hash + ?Sized>(value: &T) -> u64 {
+    use std::hash::Hasher;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The key related parts of [`Reconcile`]
+///
+/// This is implemented by `#[derive(ReconcileKey)]`, which generates the same key type,
+/// [`Reconcile::hydrate_key`] and [`Reconcile::key`] as `#[derive(Reconcile)]` would (e.g. using
+/// a `#[key]` field) but no [`Reconcile::reconcile`]. This allows you to write the body of
+/// `reconcile` by hand whilst still using a generated key. Use [`delegate_key!`](crate::delegate_key)
+/// in the `Reconcile` implementation to forward the key items to this trait.
+///
+/// ```rust
+/// # use autosurgeon::{Reconcile, ReconcileKey, Reconciler, reconcile::MapReconciler};
+/// #[derive(ReconcileKey)]
+/// struct User {
+///     #[key]
+///     id: u64,
+///     name: String,
+/// }
+///
+/// impl Reconcile for User {
+///     autosurgeon::delegate_key!();
+///
+///     fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+///         let mut m = reconciler.map()?;
+///         m.put("id", self.id)?;
+///         m.put("name", self.name.to_uppercase())?;
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait ReconcileKey {
+    /// See [`Reconcile::Key`]
+    type Key<'a>: PartialEq;
+
+    /// See [`Reconcile::KEY_IMMUTABLE`]
+    const KEY_IMMUTABLE: bool = false;
+
+    /// See [`Reconcile::hydrate_key`]
+    fn hydrate_key<'a, D: ReadDoc>(
+        #[allow(unused_variables)] doc: &D,
+        #[allow(unused_variables)] obj: &automerge::ObjId,
+        #[allow(unused_variables)] prop: Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, ReconcileError> {
+        Ok(LoadKey::NoKey)
+    }
+
+    /// See [`Reconcile::key`]
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        LoadKey::NoKey
+    }
+}
+
+/// Implement the key items of [`Reconcile`] by forwarding to [`ReconcileKey`]
+///
+/// This should be used inside an `impl Reconcile` block, see [`ReconcileKey`] for an example.
+#[macro_export]
+macro_rules! delegate_key {
+    () => {
+        type Key<'a> = <Self as $crate::reconcile::ReconcileKey>::Key<'a>;
+        const KEY_IMMUTABLE: bool = <Self as $crate::reconcile::ReconcileKey>::KEY_IMMUTABLE;
+
+        fn hydrate_key<'a, D: $crate::ReadDoc>(
+            doc: &D,
+            obj: &::automerge::ObjId,
+            prop: $crate::Prop<'_>,
+        ) -> ::std::result::Result<
+            $crate::reconcile::LoadKey<<Self as $crate::reconcile::ReconcileKey>::Key<'a>>,
+            $crate::ReconcileError,
+        > {
+            <Self as $crate::reconcile::ReconcileKey>::hydrate_key(doc, obj, prop)
+        }
+
+        fn key(
+            &self,
+        ) -> $crate::reconcile::LoadKey<<Self as $crate::reconcile::ReconcileKey>::Key<'_>> {
+            <Self as $crate::reconcile::ReconcileKey>::key(self)
+        }
+    };
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -399,6 +693,62 @@ pub enum ReconcileError {
     TopLevelNotMap,
     #[error(transparent)]
     StaleHeads(#[from] StaleHeads),
+    #[error(transparent)]
+    ImmutableKeyChanged(#[from] ImmutableKeyChanged),
+    #[error(transparent)]
+    NotUtf8(#[from] NotUtf8),
+    #[error(transparent)]
+    OutOfScope(#[from] OutOfScope),
+    #[error(transparent)]
+    MaxDepthExceeded(#[from] MaxDepthExceeded),
+    /// [`reconcile_at`] was given a path through something which is not an object in the
+    /// document
+    #[error("there is no object at {0}")]
+    NoObject(crate::Path),
+    /// An error which occurred while reconciling the value at `path`
+    ///
+    /// `path` is relative to the object passed to [`reconcile`] or [`reconcile_prop`]
+    #[error("at {}: {error}", crate::prop::DisplayPath(.path))]
+    AtPath {
+        path: crate::Path,
+        error: Box<ReconcileError>,
+    },
+}
+
+impl ReconcileError {
+    /// Record that this error occurred while reconciling the value at `prop`
+    ///
+    /// The property is added to the front of the path of the error, so this should be called as
+    /// the error propagates up out of nested objects.
+    pub fn in_prop<P: Into<automerge::Prop>>(self, prop: P) -> Self {
+        match self {
+            ReconcileError::AtPath { mut path, error } => {
+                path.push_front(prop.into());
+                ReconcileError::AtPath { path, error }
+            }
+            other => ReconcileError::AtPath {
+                path: crate::path![prop],
+                error: Box::new(other),
+            },
+        }
+    }
+
+    /// The path to the value which failed to reconcile, empty if the path is not known
+    pub fn path(&self) -> &crate::Path {
+        static EMPTY: crate::Path = crate::Path::new();
+        match self {
+            ReconcileError::AtPath { path, .. } => path,
+            _ => &EMPTY,
+        }
+    }
+
+    /// The underlying error, without any path information
+    pub fn without_path(self) -> ReconcileError {
+        match self {
+            ReconcileError::AtPath { error, .. } => *error,
+            other => other,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -408,9 +758,56 @@ pub struct StaleHeads {
     pub found: Vec<automerge::ChangeHash>,
 }
 
+/// The key of an item whose [`Reconcile::KEY_IMMUTABLE`] is `true` was changed
+#[derive(Debug, thiserror::Error)]
+#[error("the key of the item at {prop} was changed, but the key is immutable")]
+pub struct ImmutableKeyChanged {
+    /// The property of the item in its parent map or sequence
+    pub prop: automerge::Prop,
+}
+
+/// A value which must be reconciled as a string, such as an [`std::ffi::OsString`], was not valid
+/// UTF-8
+#[derive(Debug, thiserror::Error)]
+#[error("{lossy:?} is not valid UTF-8")]
+pub struct NotUtf8 {
+    /// The value with any invalid UTF-8 replaced by U+FFFD REPLACEMENT CHARACTER
+    pub lossy: String,
+}
+
+/// [`crate::paths::reconcile_scoped`] skipped writes outside of the allowed prefix
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "skipped {} writes outside of the allowed prefix {:?}",
+    .skipped.len(),
+    crate::prop::DisplayPath(.prefix).to_string()
+)]
+pub struct OutOfScope {
+    /// The prefix which writes were restricted to
+    pub prefix: crate::Path,
+    /// The path of every write which was skipped
+    pub skipped: Vec<crate::Path>,
+}
+
+/// The value being reconciled nests objects more deeply than
+/// [`ReconcileScratch::max_depth`]
+///
+/// This is usually wrapped in a [`ReconcileError::AtPath`] whose path is the object which would
+/// have been too deep.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "objects are nested more than {limit} deep, if this is intended use \
+    ReconcileScratch::with_max_depth to raise the limit"
+)]
+pub struct MaxDepthExceeded {
+    /// The maximum depth which was exceeded
+    pub limit: usize,
+}
+
 struct RootReconciler<'a, D> {
     heads: Vec<automerge::ChangeHash>,
     doc: &'a mut D,
+    scratch: &'a ReconcileScratch,
 }
 
 impl<'a, D: Doc> Reconciler for RootReconciler<'a, D> {
@@ -440,6 +837,10 @@ impl<'a, D: Doc> Reconciler for RootReconciler<'a, D> {
         Err(ReconcileError::TopLevelNotMap)
     }
 
+    fn unknown(&mut self, _type_code: u8, _bytes: &[u8]) -> Result<(), Self::Error> {
+        Err(ReconcileError::TopLevelNotMap)
+    }
+
     fn str<S: AsRef<str>>(&mut self, _value: S) -> Result<(), Self::Error> {
         Err(ReconcileError::TopLevelNotMap)
     }
@@ -461,6 +862,8 @@ impl<'a, D: Doc> Reconciler for RootReconciler<'a, D> {
             heads: &self.heads,
             current_obj: automerge::ROOT,
             doc: self.doc,
+            depth: 0,
+            scratch: self.scratch,
         })
     }
 
@@ -476,9 +879,22 @@ impl<'a, D: Doc> Reconciler for RootReconciler<'a, D> {
         Err(ReconcileError::TopLevelNotMap)
     }
 
+    fn replace_with_type(&mut self, obj_type: automerge::ObjType) -> Result<(), Self::Error> {
+        use automerge::ObjType;
+        // The root can't be replaced so the closest we can get is removing everything in it
+        match obj_type {
+            ObjType::Map | ObjType::Table => self.map()?.clear(),
+            _ => Err(ReconcileError::TopLevelNotMap),
+        }
+    }
+
     fn heads(&self) -> &[automerge::ChangeHash] {
         &self.heads
     }
+
+    fn scratch(&self) -> Option<&ReconcileScratch> {
+        Some(self.scratch)
+    }
 }
 
 enum PropAction<'a> {
@@ -487,14 +903,54 @@ enum PropAction<'a> {
 }
 
 impl<'a> PropAction<'a> {
-    fn get_target<'b, D: Doc>(
+    /// Find or create the object of type `objtype` to reconcile into
+    ///
+    /// If the target holds conflicting values (e.g. after merging concurrent changes which wrote
+    /// different types to the same property) then we inspect all of them. The only base we ever
+    /// reconcile into is the winner, which every peer agrees on, as changes to a losing object
+    /// would not be visible. If the winner is an object of type `objtype` we reconcile into it,
+    /// which leaves the losing values in place so that concurrent changes to the winning object
+    /// still merge, unless `clear_conflicts` is set. Otherwise we create a new object, which
+    /// replaces all of the conflicting values. In particular a `Table` is replaced by a new `Map`,
+    /// because automerge does not allow writing into tables.
+    fn target_obj<D: Doc>(
         &self,
-        doc: &'b D,
+        doc: &mut D,
         obj: &automerge::ObjId,
-    ) -> Result<Option<(automerge::Value<'b>, automerge::ObjId)>, automerge::AutomergeError> {
-        match self {
-            Self::Put(prop) => doc.get(obj, prop),
-            Self::Insert(_idx) => Ok(None),
+        objtype: automerge::ObjType,
+        clear_conflicts: bool,
+    ) -> Result<automerge::ObjId, automerge::AutomergeError> {
+        use automerge::Value;
+        let mut values = match self {
+            Self::Put(prop) => doc.get_all(obj, prop)?,
+            Self::Insert(_idx) => Vec::new(),
+        };
+        let conflicted = values.len() > 1;
+        match values.pop() {
+            Some((Value::Object(existing), id)) if existing == objtype => {
+                if !(conflicted && clear_conflicts) {
+                    return Ok(id);
+                }
+                if let Self::Put(prop) = self {
+                    crate::trace::clearing_conflicts(doc, obj, prop, values.len());
+                }
+                self.create_target_obj(doc, obj, objtype)
+            }
+            Some((found, _)) => {
+                if let Self::Put(prop) = self {
+                    crate::trace::replacing(doc, obj, prop, objtype, &found);
+                }
+                self.create_target_obj(doc, obj, objtype)
+            }
+            None => self.create_target_obj(doc, obj, objtype),
+        }
+    }
+
+    /// Called after creating an object, so that if we were inserting then subsequent calls to
+    /// `map`, `seq`, or `text` update the inserted object rather than inserting another one
+    fn inserted(&mut self) {
+        if let Self::Insert(idx) = self {
+            *self = Self::Put(Prop::Index(*idx));
         }
     }
 
@@ -528,6 +984,20 @@ struct PropReconciler<'a, D> {
     doc: &'a mut D,
     current_obj: automerge::ObjId,
     action: PropAction<'a>,
+    // The depth of any object created by this reconciler
+    depth: usize,
+    scratch: &'a ReconcileScratch,
+}
+
+impl<'a, D> PropReconciler<'a, D> {
+    fn check_depth(&self) -> Result<(), ReconcileError> {
+        let limit = self.scratch.max_depth();
+        if self.depth > limit {
+            Err(MaxDepthExceeded { limit }.into())
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl<'a, D: Doc> Reconciler for PropReconciler<'a, D> {
@@ -565,6 +1035,16 @@ impl<'a, D: Doc> Reconciler for PropReconciler<'a, D> {
             .map_err(ReconcileError::from)
     }
 
+    fn unknown(&mut self, type_code: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        let value = ScalarValue::Unknown {
+            type_code,
+            bytes: bytes.to_vec(),
+        };
+        self.action
+            .create_primitive(self.doc, &self.current_obj, value)
+            .map_err(ReconcileError::from)
+    }
+
     fn str<S: AsRef<str>>(&mut self, value: S) -> Result<(), Self::Error> {
         self.action
             .create_primitive(self.doc, &self.current_obj, value.as_ref())
@@ -584,55 +1064,73 @@ impl<'a, D: Doc> Reconciler for PropReconciler<'a, D> {
     }
 
     fn f64(&mut self, value: f64) -> Result<(), Self::Error> {
+        let value = self.scratch.canonical_floats().apply(value);
+        // Automerge doesn't write a value which is equal to the one already there, but NaN isn't
+        // equal to itself, so check for an identical NaN here
+        if let (true, PropAction::Put(prop)) = (value.is_nan(), &self.action) {
+            let existing = self.doc.get_all(&self.current_obj, prop)?;
+            if let [(automerge::Value::Scalar(existing), _)] = existing.as_slice() {
+                if let ScalarValue::F64(f) = existing.as_ref() {
+                    if f.to_bits() == value.to_bits() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
         self.action
             .create_primitive(self.doc, &self.current_obj, value)
             .map_err(ReconcileError::from)
     }
 
     fn map(&mut self) -> Result<InMap<'_, D>, Self::Error> {
-        use automerge::{ObjType, Value};
-        let map_id = if let Some((Value::Object(ObjType::Map), id)) =
-            self.action.get_target(self.doc, &self.current_obj)?
-        {
-            id
-        } else {
-            self.action
-                .create_target_obj(self.doc, &self.current_obj, ObjType::Map)?
-        };
+        use automerge::ObjType;
+        self.check_depth()?;
+        let map_id = self.action.target_obj(
+            self.doc,
+            &self.current_obj,
+            ObjType::Map,
+            self.scratch.clear_conflicts(),
+        )?;
+        self.action.inserted();
         Ok(InMap {
             heads: self.heads,
             current_obj: map_id,
             doc: self.doc,
+            depth: self.depth,
+            scratch: self.scratch,
         })
     }
 
     fn seq(&mut self) -> Result<InSeq<'_, D>, Self::Error> {
-        use automerge::{ObjType, Value};
-        let seq_id = if let Some((Value::Object(ObjType::List), id)) =
-            self.action.get_target(self.doc, &self.current_obj)?
-        {
-            id
-        } else {
-            self.action
-                .create_target_obj(self.doc, &self.current_obj, ObjType::List)?
-        };
+        use automerge::ObjType;
+        self.check_depth()?;
+        let seq_id = self.action.target_obj(
+            self.doc,
+            &self.current_obj,
+            ObjType::List,
+            self.scratch.clear_conflicts(),
+        )?;
+        self.action.inserted();
         Ok(InSeq {
             heads: self.heads,
             obj: seq_id,
             doc: self.doc,
+            cache: Some(self.scratch.take_read_cache()),
+            depth: self.depth,
+            scratch: self.scratch,
         })
     }
 
     fn text(&mut self) -> Result<Self::Text<'_>, Self::Error> {
-        use automerge::{ObjType, Value};
-        let text_id = if let Some((Value::Object(ObjType::Text), id)) =
-            self.action.get_target(self.doc, &self.current_obj)?
-        {
-            id
-        } else {
-            self.action
-                .create_target_obj(self.doc, &self.current_obj, ObjType::Text)?
-        };
+        use automerge::ObjType;
+        self.check_depth()?;
+        let text_id = self.action.target_obj(
+            self.doc,
+            &self.current_obj,
+            ObjType::Text,
+            self.scratch.clear_conflicts(),
+        )?;
+        self.action.inserted();
         Ok(InText {
             heads: self.heads,
             obj: text_id,
@@ -642,18 +1140,32 @@ impl<'a, D: Doc> Reconciler for PropReconciler<'a, D> {
 
     fn counter(&mut self) -> Result<Self::Counter<'_>, Self::Error> {
         Ok(AtCounter {
+            heads: self.heads,
             doc: self.doc,
             current_obj: &self.current_obj,
             action: &self.action,
         })
     }
 
+    fn replace_with_type(&mut self, obj_type: automerge::ObjType) -> Result<(), Self::Error> {
+        self.check_depth()?;
+        self.action
+            .create_target_obj(self.doc, &self.current_obj, obj_type)?;
+        self.action.inserted();
+        Ok(())
+    }
+
     fn heads(&self) -> &[automerge::ChangeHash] {
         self.heads
     }
+
+    fn scratch(&self) -> Option<&ReconcileScratch> {
+        Some(self.scratch)
+    }
 }
 
 struct AtCounter<'a, D> {
+    heads: &'a [automerge::ChangeHash],
     doc: &'a mut D,
     current_obj: &'a automerge::ObjId,
     action: &'a PropAction<'a>,
@@ -666,14 +1178,25 @@ impl<'a, D: Doc> CounterReconciler for AtCounter<'a, D> {
         use automerge::Value;
         match &self.action {
             PropAction::Put(prop) => {
-                if let Some((Value::Scalar(s), _)) = self.doc.get(self.current_obj, prop)? {
-                    if let ScalarValue::Counter(_) = s.as_ref() {
-                        self.doc.increment(self.current_obj, prop, by)?;
-                        return Ok(());
-                    }
-                }
-                self.doc
-                    .put(self.current_obj, prop, ScalarValue::Counter(by.into()))?;
+                let current = match self.doc.get(self.current_obj, prop)? {
+                    Some((Value::Scalar(s), _)) => match s.as_ref() {
+                        ScalarValue::Counter(_) => {
+                            self.doc.increment(self.current_obj, prop, by)?;
+                            return Ok(());
+                        }
+                        // A peer which doesn't know this is a counter overwrote it with a plain
+                        // integer, turn it back into a counter without losing the value
+                        ScalarValue::Int(i) => *i,
+                        ScalarValue::Uint(u) => i64::try_from(*u).unwrap_or(i64::MAX),
+                        _ => 0,
+                    },
+                    _ => 0,
+                };
+                self.doc.put(
+                    self.current_obj,
+                    prop,
+                    ScalarValue::Counter((current + by).into()),
+                )?;
                 Ok(())
             }
             PropAction::Insert(idx) => {
@@ -695,12 +1218,45 @@ impl<'a, D: Doc> CounterReconciler for AtCounter<'a, D> {
         )?;
         Ok(())
     }
+
+    fn current(&self) -> Result<Option<i64>, Self::Error> {
+        use automerge::Value;
+        match &self.action {
+            PropAction::Put(prop) => match self.doc.get(self.current_obj, prop)? {
+                Some((Value::Scalar(s), _)) => match s.as_ref() {
+                    ScalarValue::Counter(c) => Ok(Some(c.into())),
+                    _ => Ok(None),
+                },
+                _ => Ok(None),
+            },
+            PropAction::Insert(_) => Ok(None),
+        }
+    }
+
+    fn current_id(&self) -> Result<Option<automerge::ObjId>, Self::Error> {
+        use automerge::Value;
+        match &self.action {
+            PropAction::Put(prop) => match self.doc.get(self.current_obj, prop)? {
+                Some((Value::Scalar(s), id)) if matches!(s.as_ref(), ScalarValue::Counter(_)) => {
+                    Ok(Some(id))
+                }
+                _ => Ok(None),
+            },
+            PropAction::Insert(_) => Ok(None),
+        }
+    }
+
+    fn heads(&self) -> &[automerge::ChangeHash] {
+        self.heads
+    }
 }
 
 struct InMap<'a, D> {
     heads: &'a [automerge::ChangeHash],
     doc: &'a mut D,
     current_obj: automerge::ObjId,
+    depth: usize,
+    scratch: &'a ReconcileScratch,
 }
 
 impl<'a, D: Doc> MapReconciler for InMap<'a, D> {
@@ -723,20 +1279,34 @@ impl<'a, D: Doc> MapReconciler for InMap<'a, D> {
     }
 
     fn put<R: Reconcile, P: AsRef<str>>(&mut self, prop: P, value: R) -> Result<(), Self::Error> {
+        let digest = value.digest();
+        let target: Prop<'_> = prop.as_ref().into();
+        if self
+            .scratch
+            .digest_unchanged(&*self.doc, &self.current_obj, &target, digest)?
+        {
+            return Ok(());
+        }
         let reconciler = PropReconciler {
             heads: self.heads,
             current_obj: self.current_obj.clone(),
             doc: self.doc,
             action: PropAction::Put(prop.as_ref().into()),
+            depth: self.depth + 1,
+            scratch: self.scratch,
         };
-        value.reconcile(reconciler)?;
+        value
+            .reconcile(reconciler)
+            .map_err(|e| e.in_prop(prop.as_ref()))?;
+        self.scratch
+            .remember_digest(&*self.doc, &self.current_obj, &target, digest)?;
         Ok(())
     }
 
     fn delete<P: AsRef<str>>(&mut self, prop: P) -> Result<(), Self::Error> {
         self.doc
             .delete(&self.current_obj, prop.as_ref())
-            .map_err(ReconcileError::from)
+            .map_err(|e| ReconcileError::from(e).in_prop(prop.as_ref()))
     }
 
     fn hydrate_entry_key<'b, R: Reconcile, P: AsRef<str>>(
@@ -765,6 +1335,30 @@ struct InSeq<'a, D> {
     heads: &'a [automerge::ChangeHash],
     doc: &'a mut D,
     obj: automerge::ObjId,
+    // Only `None` once we have been dropped
+    cache: Option<read_cache::ReadCache>,
+    depth: usize,
+    scratch: &'a ReconcileScratch,
+}
+
+impl<'a, D> InSeq<'a, D> {
+    fn cache(&self) -> &read_cache::ReadCache {
+        self.cache.as_ref().expect("cache is only taken on drop")
+    }
+
+    fn clear_cache(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            cache.clear();
+        }
+    }
+}
+
+impl<'a, D> Drop for InSeq<'a, D> {
+    fn drop(&mut self) {
+        if let Some(cache) = self.cache.take() {
+            self.scratch.recycle_read_cache(cache);
+        }
+    }
 }
 
 struct ItemsInSeq<'a> {
@@ -772,10 +1366,12 @@ struct ItemsInSeq<'a> {
 }
 
 impl<'a> Iterator for ItemsInSeq<'a> {
-    type Item = automerge::Value<'a>;
+    type Item = (usize, automerge::Value<'a>, automerge::ObjId);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.list_range.next().map(|i| i.value)
+        self.list_range
+            .next()
+            .map(|item| (item.index, item.value, item.id))
     }
 }
 
@@ -791,36 +1387,72 @@ impl<'a, D: Doc> SeqReconciler for InSeq<'a, D> {
     }
 
     fn get(&'_ self, index: usize) -> Result<Option<automerge::Value<'_>>, Self::Error> {
-        Ok(self.doc.get(&self.obj, index)?.map(|(v, _)| v))
+        Ok(self
+            .cache()
+            .get(&*self.doc, &self.obj, index)?
+            .map(|(v, _)| v))
     }
 
     fn insert<R: Reconcile>(&mut self, index: usize, value: R) -> Result<(), Self::Error> {
+        self.clear_cache();
         let reconciler = PropReconciler {
             heads: self.heads,
             doc: self.doc,
             current_obj: self.obj.clone(),
             action: PropAction::Insert(index as u32),
+            depth: self.depth + 1,
+            scratch: self.scratch,
         };
-        value.reconcile(reconciler)?;
-        Ok(())
+        value.reconcile(reconciler).map_err(|e| e.in_prop(index))
     }
 
-    fn set<R: Reconcile>(&mut self, index: usize, value: R) -> Result<(), Self::Error> {
+    fn push<R: Reconcile>(&mut self, value: R) -> Result<(), Self::Error> {
+        let index = self.doc.length(&self.obj);
+        // Nothing before the end of the sequence moves so only the read of the new index is stale
+        if let Some(cache) = &mut self.cache {
+            cache.forget(&self.obj, index);
+        }
         let reconciler = PropReconciler {
             heads: self.heads,
             doc: self.doc,
             current_obj: self.obj.clone(),
-            action: PropAction::Put(index.into()),
+            action: PropAction::Insert(index as u32),
+            depth: self.depth + 1,
+            scratch: self.scratch,
         };
-        value.reconcile(reconciler)?;
-        Ok(())
+        value.reconcile(reconciler).map_err(|e| e.in_prop(index))
     }
 
-    fn delete<'b>(&mut self, index: usize) -> Result<(), Self::Error> {
-        self.doc
-            .delete(&self.obj, index)
-            .map_err(ReconcileError::from)
-    }
+    fn set<R: Reconcile>(&mut self, index: usize, value: R) -> Result<(), Self::Error> {
+        let digest = value.digest();
+        let target = Prop::Index(index as u32);
+        if self
+            .scratch
+            .digest_unchanged(&*self.doc, &self.obj, &target, digest)?
+        {
+            return Ok(());
+        }
+        self.clear_cache();
+        let reconciler = PropReconciler {
+            heads: self.heads,
+            doc: self.doc,
+            current_obj: self.obj.clone(),
+            action: PropAction::Put(index.into()),
+            depth: self.depth + 1,
+            scratch: self.scratch,
+        };
+        value.reconcile(reconciler).map_err(|e| e.in_prop(index))?;
+        self.scratch
+            .remember_digest(&*self.doc, &self.obj, &target, digest)?;
+        Ok(())
+    }
+
+    fn delete<'b>(&mut self, index: usize) -> Result<(), Self::Error> {
+        self.clear_cache();
+        self.doc
+            .delete(&self.obj, index)
+            .map_err(|e| ReconcileError::from(e).in_prop(index))
+    }
 
     fn len(&self) -> Result<usize, Self::Error> {
         Ok(self.doc.length(&self.obj))
@@ -830,8 +1462,9 @@ impl<'a, D: Doc> SeqReconciler for InSeq<'a, D> {
         &self,
         index: usize,
     ) -> Result<LoadKey<R::Key<'b>>, Self::Error> {
-        if self.doc.get(&self.obj, index)?.is_some() {
-            R::hydrate_key(self.doc, &self.obj, index.into())
+        let doc = self.cache().reader(&*self.doc);
+        if doc.get(&self.obj, index)?.is_some() {
+            R::hydrate_key(&doc, &self.obj, index.into())
         } else {
             Ok(LoadKey::KeyNotFound)
         }
@@ -858,9 +1491,45 @@ impl<'a, D: Doc> TextReconciler for InText<'a, D> {
         Ok(())
     }
 
+    fn append<S: AsRef<str>>(&mut self, text: S) -> Result<(), Self::Error> {
+        let end = self.doc.length(&self.obj);
+        self.doc.splice_text(&self.obj, end, 0, text.as_ref())?;
+        Ok(())
+    }
+
+    fn mark(
+        &mut self,
+        mark: &crate::text::Mark,
+        expand: automerge::marks::ExpandMark,
+    ) -> Result<(), Self::Error> {
+        let mark = automerge::marks::Mark::new(
+            mark.name.clone(),
+            mark.value.clone(),
+            mark.start,
+            mark.end,
+        );
+        self.doc.mark(&self.obj, mark, expand)?;
+        Ok(())
+    }
+
+    fn unmark(
+        &mut self,
+        name: &str,
+        start: usize,
+        end: usize,
+        expand: automerge::marks::ExpandMark,
+    ) -> Result<(), Self::Error> {
+        self.doc.unmark(&self.obj, name, start, end, expand)?;
+        Ok(())
+    }
+
     fn heads(&self) -> &[automerge::ChangeHash] {
         self.heads
     }
+
+    fn current(&self) -> Option<String> {
+        self.doc.text(&self.obj).ok()
+    }
 }
 
 /// Reconcile `value` with `doc`
@@ -868,12 +1537,66 @@ impl<'a, D: Doc> TextReconciler for InText<'a, D> {
 /// This will throw an error if the implementation of `Reconcile` for `R` does anything except call
 /// `Reconciler::map` because only a map is a valid object for the root of an automerge document.
 pub fn reconcile<R: Reconcile, D: Doc>(doc: &mut D, value: R) -> Result<(), ReconcileError> {
-    let reconciler = RootReconciler {
-        heads: doc.get_heads(),
-        doc,
-    };
-    value.reconcile(reconciler)?;
-    Ok(())
+    reconcile_with_scratch(doc, value, &mut ReconcileScratch::new())
+}
+
+/// Create a new document containing `value`
+///
+/// This is shorthand for creating an [`automerge::AutoCommit`] and then calling [`reconcile`] on
+/// it. As with [`reconcile`], `value` must reconcile to a map.
+///
+/// ```rust
+/// # use autosurgeon::{hydrate, to_new_document, Hydrate, Reconcile};
+/// #[derive(Debug, PartialEq, Reconcile, Hydrate)]
+/// struct Record {
+///     name: String,
+/// }
+/// let record = Record { name: "first".to_string() };
+/// let doc = to_new_document(&record).unwrap();
+/// assert_eq!(hydrate::<_, Record>(&doc).unwrap(), record);
+/// ```
+pub fn to_new_document<R: Reconcile>(value: R) -> Result<automerge::AutoCommit, ReconcileError> {
+    let mut doc = automerge::AutoCommit::new();
+    reconcile(&mut doc, value)?;
+    Ok(doc)
+}
+
+/// Create a new document with actor ID `actor` containing `value`
+///
+/// See [`to_new_document`]
+pub fn to_new_document_with_actor<R: Reconcile>(
+    value: R,
+    actor: automerge::ActorId,
+) -> Result<automerge::AutoCommit, ReconcileError> {
+    let mut doc = automerge::AutoCommit::new().with_actor(actor);
+    reconcile(&mut doc, value)?;
+    Ok(doc)
+}
+
+/// Reconcile `value` with `doc`, reusing the temporary buffers in `scratch`
+///
+/// This is the same as [`reconcile`] but avoids allocating temporary buffers which were
+/// allocated by a previous call with the same `scratch`. See [`ReconcileScratch`].
+pub fn reconcile_with_scratch<R: Reconcile, D: Doc>(
+    doc: &mut D,
+    value: R,
+    scratch: &mut ReconcileScratch,
+) -> Result<(), ReconcileError> {
+    scratch.forget_digests_if_changed(&*doc);
+    let result = crate::trace::reconcile(doc, &automerge::ROOT, None, |doc| {
+        let reconciler = RootReconciler {
+            heads: doc.get_heads(),
+            doc,
+            scratch,
+        };
+        value.reconcile(reconciler)
+    });
+    if result.is_err() {
+        // We may have written some objects without recording their digests
+        scratch.forget_digests();
+    }
+    scratch.digests_written(&*doc);
+    result
 }
 
 /// Reconcile `value` with `(obj, prop)` in `doc`
@@ -903,14 +1626,117 @@ pub fn reconcile_prop<'a, D: Doc, R: Reconcile, O: AsRef<automerge::ObjId>, P: I
     prop: P,
     value: R,
 ) -> Result<(), ReconcileError> {
-    let heads = doc.get_heads();
-    let reconciler = PropReconciler {
-        heads: &heads,
-        doc,
-        action: PropAction::Put(prop.into()),
-        current_obj: obj.as_ref().clone(),
+    let prop = prop.into();
+    crate::trace::reconcile(doc, obj.as_ref(), Some(&prop), |doc| {
+        let heads = doc.get_heads();
+        let scratch = ReconcileScratch::new();
+        let reconciler = PropReconciler {
+            heads: &heads,
+            doc,
+            action: PropAction::Put(prop.clone()),
+            current_obj: obj.as_ref().clone(),
+            depth: 1,
+            scratch: &scratch,
+        };
+        value.reconcile(reconciler)
+    })?;
+    Ok(())
+}
+
+/// Reconcile `value` with the location `path` points to in `doc`
+///
+/// Every property of `path` except the last must already refer to an object in the document, if
+/// one doesn't then this fails with [`ReconcileError::NoObject`] and nothing is written. The empty
+/// path reconciles `value` with the root of the document, like [`reconcile`]. The paths recorded
+/// in errors start at the root of the document.
+///
+/// ```rust
+/// # use autosurgeon::{path, reconcile, reconcile_at};
+/// # use automerge_test::{assert_doc, list, map};
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile(&mut doc, std::collections::HashMap::from([("numbers", vec![1, 2, 3])])).unwrap();
+/// reconcile_at(&mut doc, &path!["numbers", 1], 5).unwrap();
+/// assert_doc!(
+///     doc.document(),
+///     map! { "numbers" => { list! { {1}, {5}, {3} } } }
+/// );
+/// assert!(reconcile_at(&mut doc, &path!["missing", 0], 5).is_err());
+/// ```
+pub fn reconcile_at<D: Doc, R: Reconcile>(
+    doc: &mut D,
+    path: &crate::Path,
+    value: R,
+) -> Result<(), ReconcileError> {
+    let Some((last, parents)) = path.split_last() else {
+        return reconcile(doc, value);
     };
-    value.reconcile(reconciler)?;
+    let mut obj = automerge::ROOT;
+    for (i, prop) in parents.iter().enumerate() {
+        match doc.get(&obj, prop.clone())? {
+            Some((automerge::Value::Object(_), id)) => obj = id,
+            _ => return Err(ReconcileError::NoObject(crate::Path::from(&path[..=i]))),
+        }
+    }
+    reconcile_prop(doc, obj, last, value)
+        .map_err(|e| parents.iter().rev().fold(e, |e, p| e.in_prop(p.clone())))
+}
+
+/// Delete the value at `prop` of `obj` in `doc`
+///
+/// For a map this removes the key, for a sequence this removes the element (and so shifts every
+/// later element down by one). Deleting a property which doesn't exist does nothing.
+///
+/// ```rust
+/// # use autosurgeon::{reconcile_delete, reconcile_prop};
+/// # use automerge_test::{assert_doc, map};
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile_prop(&mut doc, automerge::ROOT, "name", "Alice").unwrap();
+/// reconcile_prop(&mut doc, automerge::ROOT, "numbers", vec![1, 2, 3]).unwrap();
+/// reconcile_delete(&mut doc, automerge::ROOT, "numbers").unwrap();
+/// assert_doc!(doc.document(), map! { "name" => { "Alice" } });
+/// ```
+pub fn reconcile_delete<'a, D: Doc, O: AsRef<automerge::ObjId>, P: Into<Prop<'a>>>(
+    doc: &mut D,
+    obj: O,
+    prop: P,
+) -> Result<(), ReconcileError> {
+    let prop = automerge::Prop::from(&prop.into());
+    if doc.get(obj.as_ref(), prop.clone())?.is_some() {
+        doc.delete(obj, prop)?;
+    }
+    Ok(())
+}
+
+/// Remove everything from the object `obj` in `doc`
+///
+/// This deletes every key of a map, every element of a sequence, or all the text in a text
+/// object, leaving the (now empty) object itself in place.
+pub fn reconcile_clear<D: Doc, O: AsRef<automerge::ObjId>>(
+    doc: &mut D,
+    obj: O,
+) -> Result<(), ReconcileError> {
+    let obj = obj.as_ref();
+    match doc.object_type(obj) {
+        Some(automerge::ObjType::Map | automerge::ObjType::Table) => {
+            let keys = doc
+                .map_range(obj, ..)
+                .map(|item| item.key.to_string())
+                .collect::<Vec<_>>();
+            for key in keys {
+                doc.delete(obj, key)?;
+            }
+        }
+        Some(automerge::ObjType::List) => {
+            for idx in (0..doc.length(obj)).rev() {
+                doc.delete(obj, idx)?;
+            }
+        }
+        Some(automerge::ObjType::Text) => {
+            let len = doc.length(obj);
+            doc.splice_text(obj, 0, len as isize, "")?;
+        }
+        None => return Err(automerge::AutomergeError::NotAnObject.into()),
+    }
     Ok(())
 }
 
@@ -918,20 +1744,25 @@ pub fn reconcile_prop<'a, D: Doc, R: Reconcile, O: AsRef<automerge::ObjId>, P: I
 ///
 /// This is useful when you specifically want to insert an object which does not implement
 /// `Reconcile::key` into a sequence
-pub fn reconcile_insert<R: Reconcile>(
-    doc: &mut automerge::AutoCommit,
-    obj: automerge::ObjId,
+pub fn reconcile_insert<D: Doc, R: Reconcile, O: AsRef<automerge::ObjId>>(
+    doc: &mut D,
+    obj: O,
     idx: usize,
     value: R,
 ) -> Result<(), ReconcileError> {
-    let heads = doc.get_heads();
-    let reconciler = PropReconciler {
-        heads: &heads,
-        doc,
-        action: PropAction::Insert(idx as u32),
-        current_obj: obj,
-    };
-    value.reconcile(reconciler)?;
+    crate::trace::reconcile(doc, obj.as_ref(), None, |doc| {
+        let heads = doc.get_heads();
+        let scratch = ReconcileScratch::new();
+        let reconciler = PropReconciler {
+            heads: &heads,
+            doc,
+            action: PropAction::Insert(idx as u32),
+            current_obj: obj.as_ref().clone(),
+            depth: 1,
+            scratch: &scratch,
+        };
+        value.reconcile(reconciler)
+    })?;
     Ok(())
 }
 
@@ -965,12 +1796,158 @@ pub fn hydrate_key<'a, D: ReadDoc, H: crate::Hydrate + Clone>(
     inner: Prop<'a>,
 ) -> Result<LoadKey<H>, ReconcileError> {
     use crate::hydrate::HydrateResultExt;
-    Ok(
-        crate::hydrate::hydrate_path(doc, obj, vec![outer, inner].into_iter())
-            .strip_unexpected()?
-            .map(LoadKey::Found)
-            .unwrap_or(LoadKey::KeyNotFound),
-    )
+    Ok(crate::hydrate::hydrate_path(doc, obj, vec![outer, inner])
+        .strip_unexpected()?
+        .map(LoadKey::Found)
+        .unwrap_or(LoadKey::KeyNotFound))
+}
+
+/// Hydrate the key `inner` from inside the object `outer` using a custom hydrate function
+///
+/// This is the same as [`hydrate_key`] except that the key is hydrated using `hydrate` rather than
+/// [`crate::Hydrate`]. This is useful when implementing [`Reconcile::hydrate_key`] for a type whose
+/// key is hydrated using a function such as those passed to `#[autosurgeon(hydrate = "..")]`.
+///
+/// If either `outer` or `inner` do not exist, or if `hydrate` fails with
+/// [`crate::HydrateError::Unexpected`], this returns [`LoadKey::KeyNotFound`].
+pub fn hydrate_key_with<'a, D, K, F>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    outer: Prop<'_>,
+    inner: Prop<'a>,
+    hydrate: F,
+) -> Result<LoadKey<K>, ReconcileError>
+where
+    D: ReadDoc,
+    F: FnOnce(&D, &automerge::ObjId, Prop<'a>) -> Result<K, crate::HydrateError>,
+{
+    use crate::hydrate::HydrateResultExt;
+    let Some((automerge::Value::Object(_), outer_obj)) = get_prop(doc, obj, &outer)? else {
+        return Ok(LoadKey::KeyNotFound);
+    };
+    if get_prop(doc, &outer_obj, &inner)?.is_none() {
+        return Ok(LoadKey::KeyNotFound);
+    }
+    Ok(hydrate(doc, &outer_obj, inner)
+        .map(Some)
+        .strip_unexpected()?
+        .map(LoadKey::Found)
+        .unwrap_or(LoadKey::KeyNotFound))
+}
+
+/// Hydrate the key `inner` from inside the element at `index` of the list `list`
+///
+/// See [`hydrate_key_with`]
+pub fn hydrate_list_item_key<'a, D, K, F>(
+    doc: &D,
+    list: &automerge::ObjId,
+    index: usize,
+    inner: Prop<'a>,
+    hydrate: F,
+) -> Result<LoadKey<K>, ReconcileError>
+where
+    D: ReadDoc,
+    F: FnOnce(&D, &automerge::ObjId, Prop<'a>) -> Result<K, crate::HydrateError>,
+{
+    hydrate_key_with(doc, list, index.into(), inner, hydrate)
+}
+
+/// Hydrate the key `inner` from inside the value at `key` of the map `map`
+///
+/// See [`hydrate_key_with`]
+pub fn hydrate_map_entry_key<'a, D, K, F>(
+    doc: &D,
+    map: &automerge::ObjId,
+    key: &str,
+    inner: Prop<'a>,
+    hydrate: F,
+) -> Result<LoadKey<K>, ReconcileError>
+where
+    D: ReadDoc,
+    F: FnOnce(&D, &automerge::ObjId, Prop<'a>) -> Result<K, crate::HydrateError>,
+{
+    hydrate_key_with(doc, map, key.into(), inner, hydrate)
+}
+
+/// The key of a type whose key is computed by `#[autosurgeon(key_fn = "...")]`
+///
+/// The derive macro can't name the type returned by the key function, so the key is stored as a
+/// trait object. Two `ComputedKey`s are equal if they contain the same type and the values are
+/// equal.
+pub struct ComputedKey(Box<dyn DynKey>);
+
+impl ComputedKey {
+    pub fn new<K: PartialEq + 'static>(key: K) -> Self {
+        Self(Box::new(key))
+    }
+}
+
+impl PartialEq for ComputedKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.dyn_eq(other.0.as_any())
+    }
+}
+
+impl std::fmt::Debug for ComputedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ComputedKey(..)")
+    }
+}
+
+trait DynKey {
+    fn as_any(&self) -> &dyn std::any::Any;
+    fn dyn_eq(&self, other: &dyn std::any::Any) -> bool;
+}
+
+impl<K: PartialEq + 'static> DynKey for K {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn dyn_eq(&self, other: &dyn std::any::Any) -> bool {
+        other.downcast_ref::<K>() == Some(self)
+    }
+}
+
+/// Hydrate the value at `prop` of `obj` and compute its key with `key_fn`
+///
+/// This is what `#[autosurgeon(key_fn = "...")]` does when no `hydrate_key_fn` is given. If the
+/// value can't be hydrated as a `T` this returns [`LoadKey::KeyNotFound`].
+pub fn hydrate_computed_key<D, T, K, F>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: Prop<'_>,
+    key_fn: F,
+) -> Result<LoadKey<K>, ReconcileError>
+where
+    D: ReadDoc,
+    T: crate::Hydrate,
+    F: FnOnce(&T) -> K,
+{
+    use crate::hydrate::HydrateResultExt;
+    Ok(T::hydrate(doc, obj, prop)
+        .map(Some)
+        .strip_unexpected()?
+        .map(|value| LoadKey::Found(key_fn(&value)))
+        .unwrap_or(LoadKey::KeyNotFound))
+}
+
+// Get the value at `prop` in `obj`, returning `None` if `prop` is the wrong kind of property for
+// the type of `obj`
+fn get_prop<'a, D: ReadDoc>(
+    doc: &'a D,
+    obj: &automerge::ObjId,
+    prop: &Prop<'_>,
+) -> Result<Option<(automerge::Value<'a>, automerge::ObjId)>, automerge::AutomergeError> {
+    match (doc.object_type(obj), prop) {
+        (Some(automerge::ObjType::Map | automerge::ObjType::Table), Prop::Key(k)) => {
+            doc.get(obj, k.as_ref())
+        }
+        (Some(automerge::ObjType::List | automerge::ObjType::Text), Prop::Index(i)) => {
+            doc.get(obj, *i as usize)
+        }
+        _ => Ok(None),
+    }
 }
 
 #[cfg(test)]
@@ -1010,6 +1987,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn reconcile_into_new_document() {
+        let bob = Contact {
+            name: "bob".to_string(),
+            id: 1,
+            addresses: Vec::new(),
+        };
+        let actor = automerge::ActorId::from(vec![1, 2, 3]);
+        let mut doc = to_new_document_with_actor(&bob, actor.clone()).unwrap();
+        assert_eq!(doc.get_actor(), &actor);
+        assert_doc!(
+            doc.document(),
+            map! {
+                "name" => { "bob" },
+                "id" => { 1_u64 },
+                "addresses" => { list!{} },
+            }
+        );
+
+        assert!(to_new_document("not a map").is_err());
+    }
+
     #[test]
     fn basic_reconciliation() {
         let mut bob = Contact {
@@ -1138,4 +2137,748 @@ mod tests {
         let val = doc.get(&automerge::ROOT, "bar").unwrap();
         assert!(val.is_none());
     }
+
+    #[test]
+    fn hydrate_list_item_and_map_entry_keys() {
+        fn hydrate_upper<D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: Prop<'_>,
+        ) -> Result<String, crate::HydrateError> {
+            let s: String = crate::hydrate_prop(doc, obj, prop)?;
+            Ok(s.to_uppercase())
+        }
+
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "contacts",
+            vec![Contact {
+                name: "bob".to_string(),
+                addresses: Vec::new(),
+                id: 1,
+            }],
+        )
+        .unwrap();
+        let contacts = doc.get(&automerge::ROOT, "contacts").unwrap().unwrap().1;
+
+        let key = hydrate_list_item_key(&doc, &contacts, 0, "name".into(), hydrate_upper).unwrap();
+        assert!(matches!(key, LoadKey::Found(k) if k == "BOB"));
+        let key = hydrate_list_item_key(&doc, &contacts, 1, "name".into(), hydrate_upper).unwrap();
+        assert!(matches!(key, LoadKey::KeyNotFound));
+        let key = hydrate_list_item_key(&doc, &contacts, 0, "nope".into(), hydrate_upper).unwrap();
+        assert!(matches!(key, LoadKey::KeyNotFound));
+
+        let key = hydrate_map_entry_key(
+            &doc,
+            &automerge::ROOT,
+            "contacts",
+            0_usize.into(),
+            |doc, obj, prop| crate::hydrate_prop::<_, NameOnly, _, _>(doc, obj, prop),
+        )
+        .unwrap();
+        assert!(matches!(key, LoadKey::Found(NameOnly(n)) if n == "bob"));
+
+        struct NameOnly(String);
+        impl crate::Hydrate for NameOnly {
+            fn hydrate_map<D: ReadDoc>(
+                doc: &D,
+                obj: &automerge::ObjId,
+            ) -> Result<Self, crate::HydrateError> {
+                Ok(NameOnly(crate::hydrate_prop(doc, obj, "name")?))
+            }
+        }
+    }
+
+    #[test]
+    fn reconcile_into_mixed_type_conflicts() {
+        use std::collections::HashMap;
+
+        // A map which won a conflict with a scalar is reconciled into rather than replaced
+        let mut doc1 = automerge::AutoCommit::new().with_actor("0000".as_bytes().into());
+        let mut doc2 = doc1.fork().with_actor("ffff".as_bytes().into());
+        doc1.put(automerge::ROOT, "value", 1).unwrap();
+        let map = doc2
+            .put_object(automerge::ROOT, "value", automerge::ObjType::Map)
+            .unwrap();
+        doc2.put(&map, "a", 1).unwrap();
+        doc1.merge(&mut doc2).unwrap();
+        assert_eq!(doc1.get_all(&automerge::ROOT, "value").unwrap().len(), 2);
+
+        let mut value = HashMap::new();
+        value.insert("b".to_string(), 2);
+        reconcile_prop(&mut doc1, automerge::ROOT, "value", &value).unwrap();
+        let (_, id) = doc1.get(&automerge::ROOT, "value").unwrap().unwrap();
+        assert_eq!(id, map);
+        // The losing scalar is left in place
+        assert_doc!(
+            doc1.document(),
+            map! { "value" => { map! { "b" => { 2 } }, 1 } }
+        );
+
+        // A scalar which won a conflict with a map is replaced by a new map, which clears the
+        // conflict
+        let mut doc1 = automerge::AutoCommit::new().with_actor("ffff".as_bytes().into());
+        let mut doc2 = doc1.fork().with_actor("0000".as_bytes().into());
+        doc1.put(automerge::ROOT, "value", 1).unwrap();
+        let map = doc2
+            .put_object(automerge::ROOT, "value", automerge::ObjType::Map)
+            .unwrap();
+        doc1.merge(&mut doc2).unwrap();
+        reconcile_prop(&mut doc1, automerge::ROOT, "value", &value).unwrap();
+        let conflicts = doc1.get_all(&automerge::ROOT, "value").unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_ne!(conflicts[0].1, map);
+        assert_doc!(
+            doc1.document(),
+            map! { "value" => { map! { "b" => { 2 } } } }
+        );
+    }
+
+    #[test]
+    fn reconcile_clearing_mixed_type_conflicts() {
+        use std::collections::HashMap;
+
+        // A map which won a conflict with a scalar is replaced along with the scalar
+        let mut doc1 = automerge::AutoCommit::new().with_actor("0000".as_bytes().into());
+        let mut doc2 = doc1.fork().with_actor("ffff".as_bytes().into());
+        doc1.put(automerge::ROOT, "value", 1).unwrap();
+        let map = doc2
+            .put_object(automerge::ROOT, "value", automerge::ObjType::Map)
+            .unwrap();
+        doc2.put(&map, "a", 1).unwrap();
+        doc1.merge(&mut doc2).unwrap();
+
+        let mut scratch = ReconcileScratch::new().with_clear_conflicts(true);
+        let value = HashMap::from([("value", HashMap::from([("b", 2)]))]);
+        reconcile_with_scratch(&mut doc1, &value, &mut scratch).unwrap();
+        let conflicts = doc1.get_all(&automerge::ROOT, "value").unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_ne!(conflicts[0].1, map);
+        assert_doc!(
+            doc1.document(),
+            map! { "value" => { map! { "b" => { 2 } } } }
+        );
+
+        // A list which won a conflict with a text object is replaced along with the text, and
+        // every peer which reconciles the same value ends up with a list
+        let mut doc1 = automerge::AutoCommit::new().with_actor("0000".as_bytes().into());
+        let mut doc2 = doc1.fork().with_actor("ffff".as_bytes().into());
+        let text = doc1
+            .put_object(automerge::ROOT, "value", automerge::ObjType::Text)
+            .unwrap();
+        doc1.splice_text(&text, 0, 0, "hello").unwrap();
+        let list = doc2
+            .put_object(automerge::ROOT, "value", automerge::ObjType::List)
+            .unwrap();
+        doc2.insert(&list, 0, 1).unwrap();
+        doc1.merge(&mut doc2).unwrap();
+        doc2.merge(&mut doc1).unwrap();
+
+        let value = HashMap::from([("value", vec![1, 2])]);
+        for doc in [&mut doc1, &mut doc2] {
+            reconcile_with_scratch(doc, &value, &mut scratch).unwrap();
+            assert_eq!(doc.get_all(&automerge::ROOT, "value").unwrap().len(), 1);
+            assert_doc!(
+                doc.document(),
+                map! { "value" => { list! { { 1 }, { 2 } } } }
+            );
+        }
+
+        // A property without conflicts is still reconciled in place
+        let (_, list) = doc1.get(&automerge::ROOT, "value").unwrap().unwrap();
+        reconcile_with_scratch(&mut doc1, &value, &mut scratch).unwrap();
+        let (_, id) = doc1.get(&automerge::ROOT, "value").unwrap().unwrap();
+        assert_eq!(id, list);
+    }
+
+    /// Reconciles `T` into a fresh object of type `.0`
+    struct Replacing<T>(automerge::ObjType, T);
+
+    impl<T: Reconcile> Reconcile for Replacing<T> {
+        type Key<'a> = NoKey;
+        fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            reconciler.replace_with_type(self.0)?;
+            self.1.reconcile(reconciler)
+        }
+    }
+
+    #[test]
+    fn replace_with_type_transitions() {
+        use automerge::ObjType;
+        use std::collections::HashMap;
+
+        let mut doc = automerge::AutoCommit::new();
+        let map = HashMap::from([("a", 1)]);
+        reconcile_prop(&mut doc, automerge::ROOT, "value", &map).unwrap();
+        let (_, old_map) = doc.get(&automerge::ROOT, "value").unwrap().unwrap();
+
+        // map -> map discards the old children and the old object
+        let replacement = HashMap::from([("b", 2)]);
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "value",
+            Replacing(ObjType::Map, &replacement),
+        )
+        .unwrap();
+        let (_, new_map) = doc.get(&automerge::ROOT, "value").unwrap().unwrap();
+        assert_ne!(old_map, new_map);
+        assert_doc!(
+            doc.document(),
+            map! { "value" => { map! { "b" => { 2 } } } }
+        );
+
+        // map -> list
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "value",
+            Replacing(ObjType::List, vec![1, 2]),
+        )
+        .unwrap();
+        assert_doc!(
+            doc.document(),
+            map! { "value" => { list! { { 1 }, { 2 } } } }
+        );
+
+        // list -> text
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "value",
+            Replacing(ObjType::Text, crate::Text::with_value("hello")),
+        )
+        .unwrap();
+        assert_eq!(
+            doc.text(doc.get(&automerge::ROOT, "value").unwrap().unwrap().1)
+                .unwrap(),
+            "hello"
+        );
+
+        // text -> map
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "value",
+            Replacing(ObjType::Map, &map),
+        )
+        .unwrap();
+        assert_doc!(
+            doc.document(),
+            map! { "value" => { map! { "a" => { 1 } } } }
+        );
+    }
+
+    #[test]
+    fn replace_with_type_discards_conflicts() {
+        use automerge::ObjType;
+        use std::collections::HashMap;
+
+        let mut doc1 = automerge::AutoCommit::new().with_actor("0000".as_bytes().into());
+        let mut doc2 = doc1.fork().with_actor("ffff".as_bytes().into());
+        doc1.put(automerge::ROOT, "value", 1).unwrap();
+        let map = doc2
+            .put_object(automerge::ROOT, "value", ObjType::Map)
+            .unwrap();
+        doc2.put(&map, "a", 1).unwrap();
+        doc1.merge(&mut doc2).unwrap();
+
+        let value = HashMap::from([("b", 2)]);
+        reconcile_prop(
+            &mut doc1,
+            automerge::ROOT,
+            "value",
+            Replacing(ObjType::Map, &value),
+        )
+        .unwrap();
+        assert_doc!(
+            doc1.document(),
+            map! { "value" => { map! { "b" => { 2 } } } }
+        );
+    }
+
+    #[test]
+    fn replace_with_type_when_inserting() {
+        use automerge::ObjType;
+
+        let mut doc = automerge::AutoCommit::new();
+        let list = doc
+            .put_object(automerge::ROOT, "list", ObjType::List)
+            .unwrap();
+        reconcile_insert(&mut doc, list, 0, Replacing(ObjType::List, vec![1])).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! { "list" => { list! { { list! { { 1 } } } } } }
+        );
+    }
+
+    #[test]
+    fn replace_root() {
+        use automerge::ObjType;
+        use std::collections::HashMap;
+
+        let mut doc = automerge::AutoCommit::new();
+        reconcile(&mut doc, HashMap::from([("a", 1)])).unwrap();
+        reconcile(&mut doc, Replacing(ObjType::Map, HashMap::from([("b", 2)]))).unwrap();
+        assert_doc!(doc.document(), map! { "b" => { 2 } });
+
+        assert!(matches!(
+            reconcile(&mut doc, Replacing(ObjType::List, Vec::<u64>::new())),
+            Err(ReconcileError::TopLevelNotMap)
+        ));
+    }
+
+    #[test]
+    fn reconcile_nonzero_and_wrapping() {
+        use std::num::{NonZeroI8, NonZeroU64, Wrapping};
+
+        let mut doc = automerge::AutoCommit::new();
+        let ids = vec![NonZeroU64::new(1).unwrap(), NonZeroU64::new(2).unwrap()];
+        reconcile_prop(&mut doc, automerge::ROOT, "ids", &ids).unwrap();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "delta",
+            NonZeroI8::new(-1).unwrap(),
+        )
+        .unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "hash", Wrapping(5_u32)).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "ids" => { list! { { 1_u64 }, { 2_u64 } } },
+                "delta" => { -1 },
+                "hash" => { 5_u64 },
+            }
+        );
+
+        let (_, ids_obj) = doc.get(&automerge::ROOT, "ids").unwrap().unwrap();
+        let key = <NonZeroU64 as Reconcile>::hydrate_key(&doc, &ids_obj, 1_usize.into()).unwrap();
+        assert!(matches!(key, LoadKey::Found(k) if k.get() == 2));
+    }
+
+    #[test]
+    fn reconcile_atomics_and_cells() {
+        use std::cell::{Cell, RefCell};
+        use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+        let mut doc = automerge::AutoCommit::new();
+        let hits = AtomicU64::new(3);
+        let enabled = AtomicBool::new(true);
+        let ratio = Cell::new(0.5);
+        let name = RefCell::new("old".to_string());
+        reconcile_prop(&mut doc, automerge::ROOT, "hits", &hits).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "enabled", &enabled).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "ratio", &ratio).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "name", &name).unwrap();
+
+        hits.fetch_add(1, Ordering::SeqCst);
+        ratio.set(0.25);
+        *name.borrow_mut() = "new".to_string();
+        reconcile_prop(&mut doc, automerge::ROOT, "hits", &hits).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "ratio", &ratio).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "name", &name).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "hits" => { 4_u64 },
+                "enabled" => { true },
+                "ratio" => { 0.25 },
+                "name" => { "new" },
+            }
+        );
+    }
+
+    #[test]
+    fn reconcile_os_and_c_strings() {
+        use std::ffi::{CString, OsString};
+
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "path", OsString::from("a.txt")).unwrap();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "label",
+            CString::new("label").unwrap(),
+        )
+        .unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "path" => { "a.txt" },
+                "label" => { "label" },
+            }
+        );
+
+        let invalid = CString::new(vec![b'a', 0xff]).unwrap();
+        let err = reconcile_prop(&mut doc, automerge::ROOT, "label", invalid).unwrap_err();
+        assert!(matches!(
+            err.without_path(),
+            ReconcileError::NotUtf8(NotUtf8 { lossy }) if lossy == "a\u{fffd}"
+        ));
+    }
+
+    #[test]
+    fn reconcile_by_reference() {
+        let numbers = vec![1, 2, 3];
+        let mut doc = automerge::AutoCommit::new();
+        let mut by_name = std::collections::HashMap::new();
+        by_name.insert("numbers", &numbers);
+        reconcile(&mut doc, &by_name).unwrap();
+
+        let lists = doc
+            .put_object(automerge::ROOT, "lists", automerge::ObjType::List)
+            .unwrap();
+        reconcile_insert(&mut doc, &lists, 0, &numbers).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "first", &numbers[..1]).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "name", Box::<str>::from("boxed")).unwrap();
+
+        assert_doc!(
+            doc.document(),
+            map! {
+                "numbers" => { list! { {1}, {2}, {3} } },
+                "lists" => { list! { { list! { {1}, {2}, {3} } } } },
+                "first" => { list! { {1} } },
+                "name" => { "boxed" },
+            }
+        );
+    }
+
+    #[test]
+    fn reconcile_string_like_types() {
+        use std::net::{IpAddr, SocketAddr};
+        use std::path::PathBuf;
+
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let addr: SocketAddr = "[::1]:8080".parse().unwrap();
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "ip", ip).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "addr", addr).unwrap();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "path",
+            PathBuf::from("/etc/hosts"),
+        )
+        .unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "ip" => { "10.0.0.1" },
+                "addr" => { "[::1]:8080" },
+                "path" => { "/etc/hosts" },
+            }
+        );
+
+        let hydrated: IpAddr = crate::hydrate_prop(&doc, &automerge::ROOT, "ip").unwrap();
+        assert_eq!(hydrated, ip);
+        let hydrated: SocketAddr = crate::hydrate_prop(&doc, &automerge::ROOT, "addr").unwrap();
+        assert_eq!(hydrated, addr);
+        let hydrated: PathBuf = crate::hydrate_prop(&doc, &automerge::ROOT, "path").unwrap();
+        assert_eq!(hydrated, PathBuf::from("/etc/hosts"));
+
+        let err =
+            crate::hydrate_prop::<_, IpAddr, _, _>(&doc, &automerge::ROOT, "path").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at path: unexpected \"/etc/hosts\", expected an IP address"
+        );
+    }
+
+    #[test]
+    fn errors_include_path() {
+        struct Stale;
+
+        impl Reconcile for Stale {
+            type Key<'a> = NoKey;
+
+            fn reconcile<R: Reconciler>(&self, _reconciler: R) -> Result<(), R::Error> {
+                Err(StaleHeads {
+                    expected: Vec::new(),
+                    found: Vec::new(),
+                }
+                .into())
+            }
+        }
+
+        let mut value = std::collections::HashMap::new();
+        value.insert("items", vec![None, Some(Stale)]);
+        let mut doc = automerge::AutoCommit::new();
+        let err = reconcile(&mut doc, &value).unwrap_err();
+        assert_eq!(err.path(), &crate::path!["items", 1_usize]);
+        assert!(err
+            .to_string()
+            .starts_with("at items[1]: the data to be reconciled is stale"));
+        assert!(matches!(err.without_path(), ReconcileError::StaleHeads(_)));
+    }
+
+    #[test]
+    fn max_depth() {
+        struct Nested(usize);
+
+        impl Reconcile for Nested {
+            type Key<'a> = NoKey;
+
+            fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+                let mut m = reconciler.map()?;
+                if self.0 > 0 {
+                    m.put("child", Nested(self.0 - 1))?;
+                }
+                Ok(())
+            }
+        }
+
+        let mut doc = automerge::AutoCommit::new();
+        let mut scratch = ReconcileScratch::with_max_depth(5);
+        reconcile_with_scratch(&mut doc, Nested(5), &mut scratch).unwrap();
+
+        let mut doc = automerge::AutoCommit::new();
+        let err = reconcile_with_scratch(&mut doc, Nested(6), &mut scratch).unwrap_err();
+        assert_eq!(err.path().len(), 6);
+        assert!(err
+            .to_string()
+            .starts_with("at child.child.child.child.child.child: objects are nested more than 5"));
+        assert!(matches!(
+            err.without_path(),
+            ReconcileError::MaxDepthExceeded(MaxDepthExceeded { limit: 5 })
+        ));
+
+        let mut doc = automerge::AutoCommit::new();
+        reconcile(&mut doc, Nested(100)).unwrap();
+    }
+
+    #[test]
+    fn delete_and_clear() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "name", "Alice").unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "numbers", vec![1, 2, 3]).unwrap();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "text",
+            crate::Text::with_value("hello"),
+        )
+        .unwrap();
+        let mut nested = std::collections::HashMap::new();
+        nested.insert("a", 1);
+        nested.insert("b", 2);
+        reconcile_prop(&mut doc, automerge::ROOT, "nested", nested).unwrap();
+
+        reconcile_delete(&mut doc, automerge::ROOT, "name").unwrap();
+        reconcile_delete(&mut doc, automerge::ROOT, "missing").unwrap();
+
+        let (_, numbers) = doc.get(&automerge::ROOT, "numbers").unwrap().unwrap();
+        reconcile_delete(&mut doc, &numbers, 1_usize).unwrap();
+        reconcile_delete(&mut doc, &numbers, 5_usize).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "numbers" => { list! { { 1 }, { 3 } } },
+                "text" => { "hello" },
+                "nested" => { map! { "a" => { 1 }, "b" => { 2 } } },
+            }
+        );
+
+        for prop in ["numbers", "text", "nested"] {
+            let (_, obj) = doc.get(&automerge::ROOT, prop).unwrap().unwrap();
+            reconcile_clear(&mut doc, obj).unwrap();
+        }
+        assert_doc!(
+            doc.document(),
+            map! {
+                "numbers" => { list! {} },
+                "text" => { "" },
+                "nested" => { map! {} },
+            }
+        );
+    }
+
+    #[test]
+    fn seq_items_include_index_and_id() {
+        // Records what `items` sees when reconciling over an existing list
+        struct Recorder(std::cell::RefCell<Vec<(usize, automerge::ObjId)>>);
+
+        impl Reconcile for Recorder {
+            type Key<'a> = NoKey;
+            fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+                let seq = reconciler.seq()?;
+                *self.0.borrow_mut() = seq.items().map(|(index, _, id)| (index, id)).collect();
+                Ok(())
+            }
+        }
+
+        let mut doc = automerge::AutoCommit::new();
+        let list = doc
+            .put_object(automerge::ROOT, "list", automerge::ObjType::List)
+            .unwrap();
+        let first = doc
+            .insert_object(&list, 0, automerge::ObjType::Map)
+            .unwrap();
+        let second = doc
+            .insert_object(&list, 1, automerge::ObjType::Map)
+            .unwrap();
+
+        let recorder = Recorder(std::cell::RefCell::new(Vec::new()));
+        reconcile_prop(&mut doc, automerge::ROOT, "list", &recorder).unwrap();
+        assert_eq!(recorder.0.into_inner(), vec![(0, first), (1, second)]);
+    }
+
+    // Automerge refuses to create or write into table objects, so to get a populated table we
+    // write a map and then rewrite the change which created it to create a table instead
+    fn doc_with_table(prop: &str, entries: &[(&str, i64)]) -> automerge::AutoCommit {
+        let mut doc = automerge::AutoCommit::new();
+        let map = doc
+            .put_object(automerge::ROOT, prop, automerge::ObjType::Map)
+            .unwrap();
+        for (key, value) in entries {
+            doc.put(&map, *key, *value).unwrap();
+        }
+        let mut change = automerge::ExpandedChange::from(doc.get_changes(&[])[0]);
+
+        let mut table_doc = automerge::AutoCommit::new();
+        table_doc
+            .put_object(automerge::ROOT, prop, automerge::ObjType::Table)
+            .unwrap();
+        let make_table = automerge::ExpandedChange::from(table_doc.get_changes(&[])[0]);
+
+        change.operations[0].action = make_table.operations[0].action.clone();
+        change.hash = None;
+        let mut doc = automerge::AutoCommit::new();
+        doc.apply_changes([automerge::Change::from(change)])
+            .unwrap();
+        doc
+    }
+
+    #[test]
+    fn reconcile_map_into_existing_table_replaces_it_with_a_map() {
+        use std::collections::HashMap;
+
+        let mut doc = doc_with_table("rows", &[("a", 1), ("b", 2)]);
+        let (value, table) = doc.get(&automerge::ROOT, "rows").unwrap().unwrap();
+        assert_eq!(value, automerge::Value::Object(automerge::ObjType::Table));
+        let hydrated: HashMap<String, i64> =
+            crate::hydrate_prop(&doc, automerge::ROOT, "rows").unwrap();
+        assert_eq!(
+            hydrated,
+            HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+        );
+
+        let rows = HashMap::from([("b", 3), ("c", 4)]);
+        reconcile_prop(&mut doc, automerge::ROOT, "rows", &rows).unwrap();
+        let (value, id) = doc.get(&automerge::ROOT, "rows").unwrap().unwrap();
+        assert_ne!(id, table);
+        assert_eq!(value, automerge::Value::Object(automerge::ObjType::Map));
+        let hydrated: HashMap<String, i64> =
+            crate::hydrate_prop(&doc, automerge::ROOT, "rows").unwrap();
+        assert_eq!(
+            hydrated,
+            HashMap::from([("b".to_string(), 3), ("c".to_string(), 4)])
+        );
+    }
+
+    #[test]
+    fn reconcile_clear_empties_a_table() {
+        let mut doc = doc_with_table("rows", &[("a", 1), ("b", 2)]);
+        let (_, table) = doc.get(&automerge::ROOT, "rows").unwrap().unwrap();
+        reconcile_clear(&mut doc, &table).unwrap();
+        assert_eq!(doc.length(&table), 0);
+    }
+
+    #[test]
+    fn floats_which_are_not_equal_to_themselves_are_not_rewritten() {
+        let mut doc = automerge::AutoCommit::new();
+        let floats = vec![1.0, f64::NAN, -0.0];
+        reconcile_prop(&mut doc, automerge::ROOT, "floats", &floats).unwrap();
+        doc.commit();
+        let heads = doc.get_heads();
+
+        // Another peer writes the same list with a positive zero and a different NaN
+        let floats = vec![1.0, -f64::NAN, 0.0];
+        reconcile_prop(&mut doc, automerge::ROOT, "floats", &floats).unwrap();
+        let floats = vec![1.0, -f64::NAN, 0.0];
+        reconcile_prop(&mut doc, automerge::ROOT, "floats", &floats).unwrap();
+        doc.commit();
+        let (_, list) = doc.get(&automerge::ROOT, "floats").unwrap().unwrap();
+        assert_eq!(doc.length(&list), 3);
+        // The NaN was rewritten once, but the elements weren't deleted and reinserted
+        assert_eq!(doc.get_changes(&heads).len(), 1);
+        assert_eq!(doc.get_changes(&heads)[0].len(), 1);
+    }
+
+    #[test]
+    fn write_canonical_floats() {
+        let mut doc = automerge::AutoCommit::new();
+        let mut scratch = ReconcileScratch::new().with_canonical_floats(CanonicalFloats::ALL);
+        let value = std::collections::HashMap::from([("floats", vec![-0.0, -f64::NAN])]);
+        reconcile_with_scratch(&mut doc, &value, &mut scratch).unwrap();
+        doc.commit();
+        let heads = doc.get_heads();
+        reconcile_with_scratch(&mut doc, &value, &mut scratch).unwrap();
+        doc.commit();
+        assert_eq!(doc.get_heads(), heads);
+
+        let (_, list) = doc.get(&automerge::ROOT, "floats").unwrap().unwrap();
+        let bits = (0..2)
+            .map(|i| match doc.get(&list, i).unwrap().unwrap().0 {
+                automerge::Value::Scalar(s) => match s.as_ref() {
+                    automerge::ScalarValue::F64(f) => f.to_bits(),
+                    other => panic!("expected a float, got {:?}", other),
+                },
+                other => panic!("expected a scalar, got {:?}", other),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(bits, vec![0.0_f64.to_bits(), f64::NAN.to_bits()]);
+    }
+
+    #[test]
+    fn push_and_append() {
+        // Appends a line to a log, which is a list of numbers and a text
+        struct Append<'a>(&'a str);
+
+        impl<'a> Reconcile for Append<'a> {
+            type Key<'b> = NoKey;
+            fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+                let mut map = reconciler.map()?;
+                map.put("lines", Lines(self.0))?;
+                map.put("text", AppendText(self.0))?;
+                Ok(())
+            }
+        }
+
+        struct Lines<'a>(&'a str);
+
+        impl<'a> Reconcile for Lines<'a> {
+            type Key<'b> = NoKey;
+            fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+                let mut seq = reconciler.seq()?;
+                // Reading the end of the list before pushing mustn't leave a stale read behind
+                let len = seq.len()?;
+                assert!(seq.get(len)?.is_none());
+                seq.push(self.0)?;
+                assert!(seq.get(len)?.is_some());
+                Ok(())
+            }
+        }
+
+        struct AppendText<'a>(&'a str);
+
+        impl<'a> Reconcile for AppendText<'a> {
+            type Key<'b> = NoKey;
+            fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+                reconciler.text()?.append(self.0)
+            }
+        }
+
+        let mut doc = automerge::AutoCommit::new();
+        reconcile(&mut doc, Append("one")).unwrap();
+        reconcile(&mut doc, Append("two")).unwrap();
+        let (_, lines) = doc.get(&automerge::ROOT, "lines").unwrap().unwrap();
+        assert_eq!(doc.length(&lines), 2);
+        let (two, _) = doc.get(&lines, 1).unwrap().unwrap();
+        assert_eq!(two.into_string().unwrap(), "two");
+        let (_, text) = doc.get(&automerge::ROOT, "text").unwrap().unwrap();
+        assert_eq!(doc.text(&text).unwrap(), "onetwo");
+    }
 }
@@ -0,0 +1,143 @@
+/// Rules for canonicalizing floats before they are written to a document
+///
+/// `-0.0` and `0.0` compare equal but are different values, as are NaNs with different payloads.
+/// If peers write the "same" float with different bits then a document can end up with values
+/// which look the same but aren't, which matters when the floats are used as keys or compared
+/// by other tools. Canonicalizing floats on every peer makes them write identical values.
+///
+/// By default nothing is canonicalized, use
+/// [`ReconcileScratch::with_canonical_floats`](super::ReconcileScratch::with_canonical_floats)
+/// to turn the rules on:
+///
+/// ```rust
+/// # use autosurgeon::reconcile::{reconcile_with_scratch, CanonicalFloats, ReconcileScratch};
+/// # use automerge::{ReadDoc, ScalarValue, Value};
+/// # use std::collections::HashMap;
+/// let mut scratch = ReconcileScratch::new().with_canonical_floats(CanonicalFloats::ALL);
+/// let mut doc = automerge::AutoCommit::new();
+/// let value = HashMap::from([("zero", -0.0)]);
+/// reconcile_with_scratch(&mut doc, &value, &mut scratch).unwrap();
+/// let (zero, _) = doc.get(automerge::ROOT, "zero").unwrap().unwrap();
+/// let Value::Scalar(zero) = zero else { panic!("expected a scalar") };
+/// let ScalarValue::F64(zero) = zero.as_ref() else { panic!("expected a float") };
+/// assert!(zero.is_sign_positive());
+/// ```
+///
+/// Keys of floats are always canonicalized, see [`FloatKey`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CanonicalFloats {
+    /// Write `-0.0` as `0.0`
+    pub negative_zero: bool,
+    /// Write every NaN as [`f64::NAN`]
+    pub nan: bool,
+}
+
+impl CanonicalFloats {
+    /// Write floats exactly as they are
+    pub const NONE: Self = Self {
+        negative_zero: false,
+        nan: false,
+    };
+
+    /// Apply every rule
+    pub const ALL: Self = Self {
+        negative_zero: true,
+        nan: true,
+    };
+
+    /// Canonicalize `value` according to these rules
+    pub fn apply(&self, value: f64) -> f64 {
+        if self.nan && value.is_nan() {
+            f64::NAN
+        } else if self.negative_zero && value == 0.0 {
+            0.0
+        } else {
+            value
+        }
+    }
+}
+
+/// The [`Reconcile::Key`](super::Reconcile::Key) of `f64` and `f32`
+///
+/// Keys are canonicalized with [`CanonicalFloats::ALL`] and then compared bit for bit. This means
+/// that `-0.0` is the same key as `0.0` and every NaN is the same key as every other NaN (unlike
+/// `==` for floats, for which NaN is not even equal to itself). So a list of floats which one
+/// peer wrote with `-0.0` and another with `0.0`, or which contains NaNs, is matched up element
+/// by element when it is reconciled rather than the elements being deleted and reinserted.
+///
+/// Note that fields of derived types marked with `#[key]` use the field type itself as the key,
+/// so `f64` key fields are compared with `==`.
+#[derive(Clone, Copy, Debug)]
+pub struct FloatKey(f64);
+
+impl FloatKey {
+    pub fn new(value: f64) -> Self {
+        Self(CanonicalFloats::ALL.apply(value))
+    }
+
+    /// The canonicalized value
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+}
+
+impl From<f64> for FloatKey {
+    fn from(value: f64) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<f32> for FloatKey {
+    fn from(value: f32) -> Self {
+        Self::new(value as f64)
+    }
+}
+
+impl PartialEq for FloatKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for FloatKey {}
+
+impl std::hash::Hash for FloatKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CanonicalFloats, FloatKey};
+
+    #[test]
+    fn canonical_keys() {
+        assert_eq!(FloatKey::new(-0.0), FloatKey::new(0.0));
+        assert_eq!(FloatKey::new(f64::NAN), FloatKey::new(-f64::NAN));
+        assert_eq!(
+            FloatKey::new(f64::from_bits(f64::NAN.to_bits() | 1)),
+            FloatKey::new(f64::NAN)
+        );
+        assert_ne!(FloatKey::new(1.0), FloatKey::new(f64::NAN));
+        assert_ne!(FloatKey::new(1.0), FloatKey::new(-1.0));
+        assert_eq!(FloatKey::from(1.5_f32), FloatKey::new(1.5));
+    }
+
+    #[test]
+    fn rules_are_applied_independently() {
+        let zero = CanonicalFloats {
+            negative_zero: true,
+            nan: false,
+        };
+        assert!(zero.apply(-0.0).is_sign_positive());
+        assert!(zero.apply(-f64::NAN).is_sign_negative());
+        let nan = CanonicalFloats {
+            negative_zero: false,
+            nan: true,
+        };
+        assert!(nan.apply(-0.0).is_sign_negative());
+        assert_eq!(nan.apply(-f64::NAN).to_bits(), f64::NAN.to_bits());
+        assert!(CanonicalFloats::NONE.apply(-0.0).is_sign_negative());
+    }
+}
@@ -1,7 +1,7 @@
-use automerge::{ScalarValue, Value};
+use automerge::{ObjType, ScalarValue, Value};
 use std::borrow::Cow;
 
-use super::{LoadKey, Reconcile, Reconciler};
+use super::{FloatKey, LoadKey, MapReconciler, NoKey, NotUtf8, Reconcile, Reconciler};
 use crate::ReadDoc;
 
 impl Reconcile for String {
@@ -56,8 +56,9 @@ impl Reconcile for str {
     }
 }
 
-impl<'a, T: Reconcile + ?Sized> Reconcile for &'a T {
+impl<T: Reconcile + ?Sized> Reconcile for &T {
     type Key<'b> = T::Key<'b>;
+    const KEY_IMMUTABLE: bool = T::KEY_IMMUTABLE;
     fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
         (*self).reconcile(reconciler)
     }
@@ -73,15 +74,18 @@ impl<'a, T: Reconcile + ?Sized> Reconcile for &'a T {
     fn key(&self) -> LoadKey<Self::Key<'_>> {
         T::key(self)
     }
+    fn digest(&self) -> Option<u64> {
+        T::digest(self)
+    }
 }
 
 impl Reconcile for f64 {
-    type Key<'a> = f64;
+    type Key<'a> = FloatKey;
     fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
         reconciler.f64(*self)
     }
     fn key(&self) -> LoadKey<Self::Key<'_>> {
-        LoadKey::Found(*self)
+        LoadKey::Found(FloatKey::from(*self))
     }
     fn hydrate_key<'a, D: ReadDoc>(
         doc: &D,
@@ -91,7 +95,7 @@ impl Reconcile for f64 {
         Ok(match doc.get(obj, &prop)? {
             Some((Value::Scalar(s), _)) => {
                 if let ScalarValue::F64(f) = s.as_ref() {
-                    LoadKey::Found(*f)
+                    LoadKey::Found(FloatKey::from(*f))
                 } else {
                     LoadKey::KeyNotFound
                 }
@@ -102,12 +106,12 @@ impl Reconcile for f64 {
 }
 
 impl Reconcile for f32 {
-    type Key<'a> = f32;
+    type Key<'a> = FloatKey;
     fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
         reconciler.f64(*self as f64)
     }
     fn key(&self) -> LoadKey<Self::Key<'_>> {
-        LoadKey::Found(*self)
+        LoadKey::Found(FloatKey::from(*self))
     }
     fn hydrate_key<'a, D: ReadDoc>(
         doc: &D,
@@ -117,7 +121,7 @@ impl Reconcile for f32 {
         Ok(match doc.get(obj, &prop)? {
             Some((Value::Scalar(s), _)) => {
                 if let ScalarValue::F64(f) = s.as_ref() {
-                    LoadKey::Found(*f as f32)
+                    LoadKey::Found(FloatKey::from(*f as f32))
                 } else {
                     LoadKey::KeyNotFound
                 }
@@ -163,6 +167,7 @@ macro_rules! int_impl {
             fn key(&self) -> LoadKey<Self::Key<'_>> {
                 LoadKey::Found(*self)
             }
+            #[allow(irrefutable_let_patterns)]
             fn hydrate_key<'a, D: ReadDoc>(
                 doc: &D,
                 obj: &automerge::ObjId,
@@ -196,8 +201,321 @@ int_impl!(i16, Int, i64);
 int_impl!(i32, Int, i64);
 int_impl!(i64, Int, i64);
 
-impl<T: Reconcile> Reconcile for Box<T> {
+macro_rules! wide_int_impl {
+    ($ty:ident) => {
+        /// Reconciled as a decimal string because automerge integers are at most 64 bits
+        impl Reconcile for $ty {
+            type Key<'a> = $ty;
+            fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+                reconciler.str(self.to_string())
+            }
+            fn key(&self) -> LoadKey<Self::Key<'_>> {
+                LoadKey::Found(*self)
+            }
+            fn hydrate_key<'a, D: ReadDoc>(
+                doc: &D,
+                obj: &automerge::ObjId,
+                prop: crate::Prop<'_>,
+            ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+                Ok(match doc.get(obj, &prop)? {
+                    Some((Value::Scalar(s), _)) => {
+                        let parsed = match s.as_ref() {
+                            ScalarValue::Str(s) => s.parse().ok(),
+                            ScalarValue::Int(i) => $ty::try_from(*i).ok(),
+                            ScalarValue::Uint(u) => $ty::try_from(*u).ok(),
+                            _ => None,
+                        };
+                        parsed.map(LoadKey::Found).unwrap_or(LoadKey::KeyNotFound)
+                    }
+                    _ => LoadKey::KeyNotFound,
+                })
+            }
+        }
+    };
+}
+
+wide_int_impl!(u128);
+wide_int_impl!(i128);
+
+/// Reconciled as a string containing the single character
+impl Reconcile for char {
+    type Key<'a> = char;
+    fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        reconciler.str(self.encode_utf8(&mut [0; 4]))
+    }
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        LoadKey::Found(*self)
+    }
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+        Ok(match doc.get(obj, &prop)? {
+            Some((Value::Scalar(s), _)) => match s.as_ref() {
+                ScalarValue::Str(s) => {
+                    let mut chars = s.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => LoadKey::Found(c),
+                        _ => LoadKey::KeyNotFound,
+                    }
+                }
+                _ => LoadKey::KeyNotFound,
+            },
+            _ => LoadKey::KeyNotFound,
+        })
+    }
+}
+
+macro_rules! nonzero_impl {
+    ($ty:ident, $inner:ident) => {
+        impl Reconcile for std::num::$ty {
+            type Key<'a> = std::num::$ty;
+            fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+                self.get().reconcile(reconciler)
+            }
+            fn key(&self) -> LoadKey<Self::Key<'_>> {
+                LoadKey::Found(*self)
+            }
+            fn hydrate_key<'a, D: ReadDoc>(
+                doc: &D,
+                obj: &automerge::ObjId,
+                prop: crate::Prop<'_>,
+            ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+                Ok(match $inner::hydrate_key(doc, obj, prop)? {
+                    LoadKey::Found(v) => std::num::$ty::new(v)
+                        .map(LoadKey::Found)
+                        .unwrap_or(LoadKey::KeyNotFound),
+                    LoadKey::KeyNotFound => LoadKey::KeyNotFound,
+                    LoadKey::NoKey => LoadKey::NoKey,
+                })
+            }
+        }
+    };
+}
+
+nonzero_impl!(NonZeroU8, u8);
+nonzero_impl!(NonZeroU16, u16);
+nonzero_impl!(NonZeroU32, u32);
+nonzero_impl!(NonZeroU64, u64);
+nonzero_impl!(NonZeroI8, i8);
+nonzero_impl!(NonZeroI16, i16);
+nonzero_impl!(NonZeroI32, i32);
+nonzero_impl!(NonZeroI64, i64);
+
+/// Reconciled as a string. Reconciling a value which is not valid UTF-8 fails with
+/// [`NotUtf8`], see [`crate::ffi`] for other encodings.
+impl Reconcile for std::ffi::OsStr {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        match self.to_str() {
+            Some(s) => reconciler.str(s),
+            None => Err(NotUtf8 {
+                lossy: self.to_string_lossy().into_owned(),
+            }
+            .into()),
+        }
+    }
+}
+
+impl Reconcile for std::ffi::OsString {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.as_os_str().reconcile(reconciler)
+    }
+}
+
+/// Reconciled as a string. Reconciling a value which is not valid UTF-8 fails with
+/// [`NotUtf8`], see [`crate::ffi`] for other encodings.
+impl Reconcile for std::ffi::CStr {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        match self.to_str() {
+            Ok(s) => reconciler.str(s),
+            Err(_) => Err(NotUtf8 {
+                lossy: self.to_string_lossy().into_owned(),
+            }
+            .into()),
+        }
+    }
+}
+
+impl Reconcile for std::ffi::CString {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.as_c_str().reconcile(reconciler)
+    }
+}
+
+/// Reconciled as a string. Reconciling a path which is not valid UTF-8 fails with
+/// [`NotUtf8`], see [`crate::ffi::path_buf`] for other encodings.
+impl Reconcile for std::path::Path {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.as_os_str().reconcile(reconciler)
+    }
+}
+
+impl Reconcile for std::path::PathBuf {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.as_path().reconcile(reconciler)
+    }
+}
+
+macro_rules! display_impl {
+    ($ty:ty) => {
+        /// Reconciled as a string
+        impl Reconcile for $ty {
+            type Key<'a> = NoKey;
+            fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+                reconciler.str(self.to_string())
+            }
+        }
+    };
+}
+
+display_impl!(std::net::IpAddr);
+display_impl!(std::net::Ipv4Addr);
+display_impl!(std::net::Ipv6Addr);
+display_impl!(std::net::SocketAddr);
+display_impl!(std::net::SocketAddrV4);
+display_impl!(std::net::SocketAddrV6);
+
+impl<T: Reconcile> Reconcile for std::num::Wrapping<T> {
     type Key<'a> = T::Key<'a>;
+    const KEY_IMMUTABLE: bool = T::KEY_IMMUTABLE;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.0.reconcile(reconciler)
+    }
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+        T::hydrate_key(doc, obj, prop)
+    }
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        self.0.key()
+    }
+}
+
+macro_rules! atomic_impl {
+    ($ty:ident, $inner:ident) => {
+        impl Reconcile for std::sync::atomic::$ty {
+            type Key<'a> = $inner;
+            fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+                self.load(std::sync::atomic::Ordering::SeqCst)
+                    .reconcile(reconciler)
+            }
+            fn key(&self) -> LoadKey<Self::Key<'_>> {
+                LoadKey::Found(self.load(std::sync::atomic::Ordering::SeqCst))
+            }
+            fn hydrate_key<'a, D: ReadDoc>(
+                doc: &D,
+                obj: &automerge::ObjId,
+                prop: crate::Prop<'_>,
+            ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+                $inner::hydrate_key(doc, obj, prop)
+            }
+        }
+    };
+}
+
+atomic_impl!(AtomicBool, bool);
+atomic_impl!(AtomicU8, u8);
+atomic_impl!(AtomicU16, u16);
+atomic_impl!(AtomicU32, u32);
+atomic_impl!(AtomicU64, u64);
+atomic_impl!(AtomicI8, i8);
+atomic_impl!(AtomicI16, i16);
+atomic_impl!(AtomicI32, i32);
+atomic_impl!(AtomicI64, i64);
+
+// The key of a `Cell` or `RefCell` would have to borrow from a temporary copy or guard, so these
+// types have no key.
+impl<T: Reconcile + Copy> Reconcile for std::cell::Cell<T> {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.get().reconcile(reconciler)
+    }
+}
+
+impl<T: Reconcile + ?Sized> Reconcile for std::cell::RefCell<T> {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.borrow().reconcile(reconciler)
+    }
+}
+
+impl<T: Reconcile + ?Sized> Reconcile for Box<T> {
+    type Key<'a> = T::Key<'a>;
+    const KEY_IMMUTABLE: bool = T::KEY_IMMUTABLE;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        T::reconcile(self, reconciler)
+    }
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+        T::hydrate_key(doc, obj, prop)
+    }
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        T::key(self)
+    }
+    fn digest(&self) -> Option<u64> {
+        T::digest(self)
+    }
+}
+
+impl<T: Reconcile + ?Sized> Reconcile for std::rc::Rc<T> {
+    type Key<'a> = T::Key<'a>;
+    const KEY_IMMUTABLE: bool = T::KEY_IMMUTABLE;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        T::reconcile(self, reconciler)
+    }
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+        T::hydrate_key(doc, obj, prop)
+    }
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        T::key(self)
+    }
+    fn digest(&self) -> Option<u64> {
+        T::digest(self)
+    }
+}
+
+impl<T: Reconcile + ?Sized> Reconcile for std::sync::Arc<T> {
+    type Key<'a> = T::Key<'a>;
+    const KEY_IMMUTABLE: bool = T::KEY_IMMUTABLE;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        T::reconcile(self, reconciler)
+    }
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+        T::hydrate_key(doc, obj, prop)
+    }
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        T::key(self)
+    }
+    fn digest(&self) -> Option<u64> {
+        T::digest(self)
+    }
+}
+
+/// Reconciles the borrowed or owned value, so a `Cow<'_, str>` is stored like a `String` and a
+/// `Cow<'_, [T]>` like a `Vec<T>`
+impl<'c, T: Reconcile + ToOwned + ?Sized> Reconcile for Cow<'c, T> {
+    type Key<'a> = T::Key<'a>;
+    const KEY_IMMUTABLE: bool = T::KEY_IMMUTABLE;
     fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
         T::reconcile(self, reconciler)
     }
@@ -211,15 +529,22 @@ impl<T: Reconcile> Reconcile for Box<T> {
     fn key(&self) -> LoadKey<Self::Key<'_>> {
         T::key(self)
     }
+    fn digest(&self) -> Option<u64> {
+        T::digest(self)
+    }
 }
 
 impl<T: Reconcile> Reconcile for Option<T> {
     type Key<'a> = T::Key<'a>;
+    const KEY_IMMUTABLE: bool = T::KEY_IMMUTABLE;
     fn key(&self) -> LoadKey<Self::Key<'_>> {
         self.as_ref()
             .map(|s| T::key(s))
             .unwrap_or(LoadKey::KeyNotFound)
     }
+    fn digest(&self) -> Option<u64> {
+        self.as_ref().and_then(T::digest)
+    }
     fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
         match self {
             Some(s) => s.reconcile(reconciler),
@@ -240,3 +565,59 @@ impl<T: Reconcile> Reconcile for Option<T> {
         }
     }
 }
+
+/// `Result` is reconciled in the same way as a derived externally tagged enum, i.e. as a map with
+/// a single key, `"Ok"` or `"Err"`, containing the value
+impl<T: Reconcile, E: Reconcile> Reconcile for Result<T, E> {
+    type Key<'a> = Result<T::Key<'a>, E::Key<'a>>;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        match self {
+            Ok(v) => reconcile_variant(reconciler, "Ok", v),
+            Err(e) => reconcile_variant(reconciler, "Err", e),
+        }
+    }
+    fn key(&self) -> LoadKey<Self::Key<'_>> {
+        match self {
+            Ok(v) => v.key().map(Ok),
+            Err(e) => e.key().map(Err),
+        }
+    }
+    fn hydrate_key<'a, D: ReadDoc>(
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: crate::Prop<'_>,
+    ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+        let id = match doc.get(obj, &prop)? {
+            Some((Value::Object(ObjType::Map | ObjType::Table), id)) => id,
+            _ => return Ok(LoadKey::KeyNotFound),
+        };
+        if doc.get(&id, "Ok")?.is_some() {
+            Ok(T::hydrate_key(doc, &id, "Ok".into())?.map(Ok))
+        } else if doc.get(&id, "Err")?.is_some() {
+            Ok(E::hydrate_key(doc, &id, "Err".into())?.map(Err))
+        } else {
+            Ok(LoadKey::KeyNotFound)
+        }
+    }
+}
+
+/// Reconcile `value` into a map containing only the key `variant`
+///
+/// If the map contains some other variant then it is replaced, so that concurrent changes to
+/// different variants don't merge into a map containing both of them.
+fn reconcile_variant<R: Reconciler, V: Reconcile>(
+    mut reconciler: R,
+    variant: &str,
+    value: &V,
+) -> Result<(), R::Error> {
+    let other_variant = {
+        let m = reconciler.map()?;
+        m.entry(variant).is_none() && m.entries().next().is_some()
+    };
+    if other_variant {
+        reconciler.replace_with_type(ObjType::Map)?;
+    }
+    let mut m = reconciler.map()?;
+    m.retain(|k, _| k == variant)?;
+    m.put(variant, value)
+}
@@ -1,30 +1,30 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 
-use crate::Reconcile;
+use crate::{PropKey, Reconcile};
 
-use super::{LoadKey, MapReconciler};
+use super::{ImmutableKeyChanged, LoadKey, MapReconciler};
 
 impl<K, V> Reconcile for HashMap<K, V>
 where
-    K: AsRef<str>,
+    K: PropKey,
     V: Reconcile,
 {
     type Key<'a> = super::NoKey;
 
     fn reconcile<R: crate::Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
-        reconcile_map_impl(self.iter(), reconciler)
+        reconcile_map_impl(self.iter().map(|(k, v)| (k.to_prop_key(), v)), reconciler)
     }
 }
 
 impl<K, V> Reconcile for BTreeMap<K, V>
 where
-    K: AsRef<str>,
+    K: PropKey,
     V: Reconcile,
 {
     type Key<'a> = super::NoKey;
 
     fn reconcile<R: crate::Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
-        reconcile_map_impl(self.iter(), reconciler)
+        reconcile_map_impl(self.iter().map(|(k, v)| (k.to_prop_key(), v)), reconciler)
     }
 }
 
@@ -34,21 +34,42 @@ pub(crate) fn reconcile_map_impl<
     V: Reconcile + 'a,
     I: Iterator<Item = (K, &'a V)>,
     R: crate::Reconciler,
+>(
+    items: I,
+    reconciler: R,
+) -> Result<(), R::Error> {
+    reconcile_map_entries(items, reconciler, true)
+}
+
+/// Reconcile `items` with a map, deleting keys which are not in `items` if `delete_absent` is set
+pub(crate) fn reconcile_map_entries<
+    'a,
+    K: AsRef<str> + 'a,
+    V: Reconcile + 'a,
+    I: Iterator<Item = (K, &'a V)>,
+    R: crate::Reconciler,
 >(
     items: I,
     mut reconciler: R,
+    delete_absent: bool,
 ) -> Result<(), R::Error> {
+    let (mut old_keys, mut incoming_keys) = match reconciler.scratch() {
+        Some(scratch) => (scratch.take_key_set(), scratch.take_key_set()),
+        None => (HashSet::new(), HashSet::new()),
+    };
     let mut m = reconciler.map()?;
-    let old_keys = m
-        .entries()
-        .map(|(k, _)| k.to_string())
-        .collect::<HashSet<_>>();
-    let mut incoming_keys = HashSet::new();
+    old_keys.extend(m.entries().map(|(k, _)| k.to_string()));
     for (k, val) in items {
         incoming_keys.insert(k.as_ref().to_string());
         if let LoadKey::Found(new_key) = val.key() {
             if let LoadKey::Found(existing_key) = m.hydrate_entry_key::<V, _>(&k)? {
                 if existing_key != new_key {
+                    if V::KEY_IMMUTABLE {
+                        return Err(ImmutableKeyChanged {
+                            prop: k.as_ref().into(),
+                        }
+                        .into());
+                    }
                     m.replace(k, val)?;
                     continue;
                 }
@@ -56,9 +77,16 @@ pub(crate) fn reconcile_map_impl<
         }
         m.put(k.as_ref(), val)?;
     }
-    let delenda = old_keys.difference(&incoming_keys);
-    for k in delenda {
-        m.delete(k)?;
+    if delete_absent {
+        let delenda = old_keys.difference(&incoming_keys);
+        for k in delenda {
+            m.delete(k)?;
+        }
+    }
+    drop(m);
+    if let Some(scratch) = reconciler.scratch() {
+        scratch.recycle_key_set(old_keys);
+        scratch.recycle_key_set(incoming_keys);
     }
     Ok(())
 }
@@ -170,4 +198,29 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn reconcile_map_with_integer_keys() {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert(1_u64, "one");
+        map.insert(20_u64, "twenty");
+        let mut doc = automerge::AutoCommit::new();
+        reconcile(&mut doc, &map).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "1" => { "one" },
+                "20" => { "twenty" },
+            }
+        );
+
+        map.remove(&1);
+        reconcile(&mut doc, &map).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "20" => { "twenty" },
+            }
+        );
+    }
 }
@@ -0,0 +1,74 @@
+use super::NoKey;
+use crate::{Doc, Reconcile, ReconcileError, Reconciler};
+
+/// A type which can reconcile just some of its fields
+///
+/// This is implemented by `#[derive(Reconcile)]` for structs with named fields which have the
+/// `#[autosurgeon(field_mask)]` attribute. The derive generates a `<Struct>FieldMask` type, a set
+/// of fields with a constant for each field (the field name in upper case), which is used as the
+/// [`ReconcileMasked::FieldMask`]. See [`reconcile_masked`].
+pub trait ReconcileMasked: Reconcile {
+    /// The set of fields to reconcile
+    type FieldMask;
+
+    /// Reconcile the fields in `mask` with the document and leave every other field in the
+    /// document as it is
+    fn reconcile_masked<R: Reconciler>(
+        &self,
+        reconciler: R,
+        mask: &Self::FieldMask,
+    ) -> Result<(), R::Error>;
+}
+
+/// Reconcile only the fields of `value` which are in `mask` with the root of `doc`
+///
+/// This is for partial updates, such as those made by services which accept a value along with a
+/// protobuf `FieldMask`: the fields outside the mask are left as they are in the document rather
+/// than being overwritten with whatever `value` happens to contain. As with [`crate::reconcile`],
+/// `value` must reconcile to a map.
+///
+/// ```rust
+/// # use autosurgeon::{hydrate, reconcile, reconcile_masked, Hydrate, Reconcile};
+/// #[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+/// #[autosurgeon(field_mask)]
+/// struct Profile {
+///     name: String,
+///     bio: String,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile(&mut doc, Profile { name: "Ada".to_string(), bio: "Mathematician".to_string() }).unwrap();
+///
+/// // Only the bio is part of the update, the empty name is not written
+/// let update = Profile { name: String::new(), bio: "Programmer".to_string() };
+/// reconcile_masked(&mut doc, &update, &ProfileFieldMask::BIO).unwrap();
+///
+/// let profile: Profile = hydrate(&doc).unwrap();
+/// assert_eq!(profile.name, "Ada");
+/// assert_eq!(profile.bio, "Programmer");
+///
+/// // Masks can also be built from the names of the fields in the document
+/// let mask = ProfileFieldMask::from_name("name").unwrap() | ProfileFieldMask::BIO;
+/// assert_eq!(mask, ProfileFieldMask::all());
+/// ```
+pub fn reconcile_masked<T: ReconcileMasked, D: Doc>(
+    doc: &mut D,
+    value: &T,
+    mask: &T::FieldMask,
+) -> Result<(), ReconcileError> {
+    crate::reconcile(doc, Masked { value, mask })
+}
+
+/// Reconciles `value` with [`ReconcileMasked::reconcile_masked`]
+struct Masked<'a, T: ReconcileMasked> {
+    value: &'a T,
+    mask: &'a T::FieldMask,
+}
+
+impl<'a, T: ReconcileMasked> Reconcile for Masked<'a, T> {
+    type Key<'k> = NoKey;
+
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.value.reconcile_masked(reconciler, self.mask)
+    }
+}
@@ -0,0 +1,498 @@
+use std::ops::RangeBounds;
+
+use automerge::{self as am, AutomergeError, ObjId, Value};
+
+use super::PlannedAction;
+use crate::{Doc, ReadDoc, Reconcile, ReconcileError};
+
+/// Reconcile `value` with `doc`, calling `observer` for every operation the reconcile makes
+///
+/// The observer is called just before each operation is applied, so it sees the operations in
+/// the order they are made. Puts of the value a property already has are skipped, because
+/// automerge doesn't record them. This is useful for logging, for metrics, or for invalidating
+/// caches of values derived from the parts of the document which changed.
+///
+/// ```rust
+/// # use autosurgeon::{reconcile, reconcile_observed, Reconcile};
+/// #[derive(Reconcile)]
+/// struct Settings {
+///     theme: String,
+///     font_size: u32,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// let mut settings = Settings {
+///     theme: "light".to_string(),
+///     font_size: 12,
+/// };
+/// reconcile(&mut doc, &settings).unwrap();
+///
+/// settings.theme = "dark".to_string();
+/// let mut log = Vec::new();
+/// reconcile_observed(&mut doc, &settings, |op| log.push(op.to_string())).unwrap();
+/// assert_eq!(log, vec!["put theme = \"dark\" (was \"light\")"]);
+/// ```
+pub fn reconcile_observed<D, R, F>(doc: &mut D, value: R, observer: F) -> Result<(), ReconcileError>
+where
+    D: Doc,
+    R: Reconcile,
+    F: FnMut(ReconcileOp),
+{
+    let mut observed = Observed { doc, observer };
+    crate::reconcile(&mut observed, value)
+}
+
+/// An operation made by a reconcile, see [`reconcile_observed`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReconcileOp {
+    /// The path from the root of the document to what is changed
+    ///
+    /// This is the same as [`super::PlannedChange::path`]
+    pub path: crate::Path,
+    pub action: PlannedAction,
+    /// The value which a put, delete, or increment replaced, if there was one
+    ///
+    /// This is always `None` for inserts, text splices and marks.
+    pub old: Option<Value<'static>>,
+}
+
+impl std::fmt::Display for ReconcileOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let change = super::PlannedChange {
+            path: self.path.clone(),
+            action: self.action.clone(),
+        };
+        match &self.old {
+            Some(old) => write!(f, "{} (was {})", change, old),
+            None => write!(f, "{}", change),
+        }
+    }
+}
+
+/// A [`Doc`] which passes every operation made through it to an observer
+struct Observed<'a, D, F> {
+    doc: &'a mut D,
+    observer: F,
+}
+
+impl<'a, D: Doc, F: FnMut(ReconcileOp)> Observed<'a, D, F> {
+    fn observe<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        prop: Option<am::Prop>,
+        action: PlannedAction,
+    ) -> Result<(), AutomergeError> {
+        let old = match (&prop, &action) {
+            (
+                Some(prop),
+                PlannedAction::Put(_)
+                | PlannedAction::PutObject(_)
+                | PlannedAction::Delete
+                | PlannedAction::Increment(_),
+            ) => self
+                .doc
+                .get(obj.as_ref(), prop.clone())?
+                .map(|(v, _)| v.into_owned()),
+            _ => None,
+        };
+        if let (Some(prop), PlannedAction::Put(new), Some(Value::Scalar(old))) =
+            (&prop, &action, &old)
+        {
+            // Automerge doesn't record a put of the value a property already has, so neither do we
+            if new == old.as_ref() && self.doc.get_all(obj.as_ref(), prop.clone())?.len() == 1 {
+                return Ok(());
+            }
+        }
+        let mut path = self
+            .doc
+            .parents(obj.as_ref())?
+            .map(|p| p.prop)
+            .collect::<Vec<_>>();
+        path.reverse();
+        path.extend(prop);
+        (self.observer)(ReconcileOp {
+            path: path.into(),
+            action,
+            old,
+        });
+        Ok(())
+    }
+}
+
+impl<'a, D: Doc, F: FnMut(ReconcileOp)> ReadDoc for Observed<'a, D, F> {
+    type Parents<'b>
+        = D::Parents<'b>
+    where
+        Self: 'b;
+
+    fn get_heads(&self) -> Vec<am::ChangeHash> {
+        self.doc.get_heads()
+    }
+
+    fn get<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get(obj, prop)
+    }
+
+    fn get_all<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_all(obj, prop)
+    }
+
+    fn object_type<O: AsRef<ObjId>>(&self, obj: O) -> Option<am::ObjType> {
+        self.doc.object_type(obj)
+    }
+
+    fn map_range<'b, O, R>(&'b self, obj: O, range: R) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+        R: RangeBounds<String>,
+    {
+        self.doc.map_range(obj, range)
+    }
+
+    fn list_range<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+    ) -> am::iter::ListRange<'_, R> {
+        self.doc.list_range(obj, range)
+    }
+
+    fn length<O: AsRef<ObjId>>(&self, obj: O) -> usize {
+        self.doc.length(obj)
+    }
+
+    fn keys<O: AsRef<ObjId>>(&self, obj: O) -> am::iter::Keys<'_> {
+        self.doc.keys(obj)
+    }
+
+    fn text<O: AsRef<ObjId>>(&self, obj: O) -> Result<String, AutomergeError> {
+        self.doc.text(obj)
+    }
+
+    fn parents<O: AsRef<ObjId>>(&self, obj: O) -> Result<Self::Parents<'_>, AutomergeError> {
+        self.doc.parents(obj)
+    }
+
+    fn get_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_at(obj, prop, heads)
+    }
+
+    fn get_all_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_all_at(obj, prop, heads)
+    }
+
+    fn map_range_at<'b, O, R>(
+        &'b self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+    {
+        self.doc.map_range_at(obj, range, heads)
+    }
+
+    fn list_range_at<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::ListRange<'_, R> {
+        self.doc.list_range_at(obj, range, heads)
+    }
+
+    fn length_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> usize {
+        self.doc.length_at(obj, heads)
+    }
+
+    fn keys_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> am::iter::Keys<'_> {
+        self.doc.keys_at(obj, heads)
+    }
+
+    fn text_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<String, AutomergeError> {
+        self.doc.text_at(obj, heads)
+    }
+
+    fn parents_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Self::Parents<'_>, AutomergeError> {
+        self.doc.parents_at(obj, heads)
+    }
+
+    fn get_cursor<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        position: usize,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<am::Cursor, AutomergeError> {
+        self.doc.get_cursor(obj, position, at)
+    }
+
+    fn get_cursor_position<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        cursor: &am::Cursor,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<usize, AutomergeError> {
+        self.doc.get_cursor_position(obj, cursor, at)
+    }
+
+    fn marks<O: AsRef<ObjId>>(&self, obj: O) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        self.doc.marks(obj)
+    }
+
+    fn marks_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        self.doc.marks_at(obj, heads)
+    }
+}
+
+impl<'a, D: Doc, F: FnMut(ReconcileOp)> Doc for Observed<'a, D, F> {
+    fn put<O: AsRef<ObjId>, P: Into<am::Prop>, V: Into<am::ScalarValue>>(
+        &mut self,
+        obj: O,
+        prop: P,
+        value: V,
+    ) -> Result<(), AutomergeError> {
+        let (prop, value) = (prop.into(), value.into());
+        self.observe(&obj, Some(prop.clone()), PlannedAction::Put(value.clone()))?;
+        self.doc.put(obj, prop, value)
+    }
+
+    fn put_object<O: AsRef<am::ObjId>, P: Into<am::Prop>>(
+        &mut self,
+        obj: O,
+        prop: P,
+        value: am::ObjType,
+    ) -> Result<ObjId, AutomergeError> {
+        let prop = prop.into();
+        self.observe(&obj, Some(prop.clone()), PlannedAction::PutObject(value))?;
+        self.doc.put_object(obj, prop, value)
+    }
+
+    fn insert<O: AsRef<ObjId>, V: Into<am::ScalarValue>>(
+        &mut self,
+        obj: O,
+        index: usize,
+        value: V,
+    ) -> Result<(), AutomergeError> {
+        let value = value.into();
+        self.observe(
+            &obj,
+            Some(am::Prop::Seq(index)),
+            PlannedAction::Insert(value.clone()),
+        )?;
+        self.doc.insert(obj, index, value)
+    }
+
+    fn insert_object<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        index: usize,
+        value: am::ObjType,
+    ) -> Result<ObjId, AutomergeError> {
+        self.observe(
+            &obj,
+            Some(am::Prop::Seq(index)),
+            PlannedAction::InsertObject(value),
+        )?;
+        self.doc.insert_object(obj, index, value)
+    }
+
+    fn increment<O: AsRef<ObjId>, P: Into<am::Prop>>(
+        &mut self,
+        obj: O,
+        prop: P,
+        value: i64,
+    ) -> Result<(), AutomergeError> {
+        let prop = prop.into();
+        self.observe(&obj, Some(prop.clone()), PlannedAction::Increment(value))?;
+        self.doc.increment(obj, prop, value)
+    }
+
+    fn delete<O: AsRef<ObjId>, P: Into<am::Prop>>(
+        &mut self,
+        obj: O,
+        prop: P,
+    ) -> Result<(), AutomergeError> {
+        let prop = prop.into();
+        self.observe(&obj, Some(prop.clone()), PlannedAction::Delete)?;
+        self.doc.delete(obj, prop)
+    }
+
+    fn splice_text<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        pos: usize,
+        del: isize,
+        text: &str,
+    ) -> Result<(), AutomergeError> {
+        self.observe(
+            &obj,
+            None,
+            PlannedAction::SpliceText {
+                pos,
+                delete: del,
+                insert: text.to_string(),
+            },
+        )?;
+        self.doc.splice_text(obj, pos, del, text)
+    }
+
+    fn mark<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        mark: am::marks::Mark<'_>,
+        expand: am::marks::ExpandMark,
+    ) -> Result<(), AutomergeError> {
+        self.observe(
+            &obj,
+            None,
+            PlannedAction::Mark {
+                name: mark.name().to_string(),
+                value: mark.value().clone(),
+                start: mark.start,
+                end: mark.end,
+            },
+        )?;
+        self.doc.mark(obj, mark, expand)
+    }
+
+    fn unmark<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        key: &str,
+        start: usize,
+        end: usize,
+        expand: am::marks::ExpandMark,
+    ) -> Result<(), AutomergeError> {
+        self.observe(
+            &obj,
+            None,
+            PlannedAction::Unmark {
+                name: key.to_string(),
+                start,
+                end,
+            },
+        )?;
+        self.doc.unmark(obj, key, start, end, expand)
+    }
+
+    fn pending_ops(&self) -> usize {
+        self.doc.pending_ops()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::{ObjType, ScalarValue, Value};
+
+    use super::{reconcile_observed, ReconcileOp};
+    use crate::{
+        reconcile::{MapReconciler, PlannedAction},
+        Reconcile, Reconciler,
+    };
+
+    struct Contact {
+        name: String,
+        emails: Vec<String>,
+    }
+
+    impl Reconcile for Contact {
+        type Key<'a> = crate::reconcile::NoKey;
+        fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            let mut m = reconciler.map()?;
+            m.put("name", &self.name)?;
+            m.put("emails", &self.emails)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn observes_every_operation() {
+        let mut doc = automerge::AutoCommit::new();
+        let mut ops = Vec::new();
+        let mut contact = Contact {
+            name: "alice".to_string(),
+            emails: vec!["alice@example.com".to_string()],
+        };
+        reconcile_observed(&mut doc, &contact, |op| ops.push(op)).unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                ReconcileOp {
+                    path: crate::path!["name"],
+                    action: PlannedAction::Put("alice".into()),
+                    old: None,
+                },
+                ReconcileOp {
+                    path: crate::path!["emails"],
+                    action: PlannedAction::PutObject(ObjType::List),
+                    old: None,
+                },
+                ReconcileOp {
+                    path: crate::path!["emails", 0_usize],
+                    action: PlannedAction::Insert("alice@example.com".into()),
+                    old: None,
+                },
+            ]
+        );
+
+        contact.name = "Alice".to_string();
+        contact.emails.clear();
+        ops.clear();
+        reconcile_observed(&mut doc, &contact, |op| ops.push(op)).unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                ReconcileOp {
+                    path: crate::path!["name"],
+                    action: PlannedAction::Put("Alice".into()),
+                    old: Some(Value::Scalar(std::borrow::Cow::Owned(ScalarValue::from(
+                        "alice"
+                    )))),
+                },
+                ReconcileOp {
+                    path: crate::path!["emails", 0_usize],
+                    action: PlannedAction::Delete,
+                    old: Some(Value::Scalar(std::borrow::Cow::Owned(ScalarValue::from(
+                        "alice@example.com"
+                    )))),
+                },
+            ]
+        );
+        // The operations were applied as well as observed
+        let hydrated: Vec<String> = crate::hydrate_prop(&doc, &automerge::ROOT, "emails").unwrap();
+        assert!(hydrated.is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+use automerge::{self as am, ObjType, ScalarValue};
+
+use crate::{prop::DisplayPath, Reconcile, ReconcileError};
+
+/// Work out what reconciling `value` with `doc` would change, without changing `doc`
+///
+/// This reconciles `value` with a copy of `doc` and records every operation which the reconcile
+/// makes, along with the path to the property or object it changes. This is useful for showing
+/// a preview of what saving will do, or for asserting on the changes a reconcile makes in tests.
+///
+/// ```rust
+/// # use autosurgeon::{reconcile, reconcile::{plan, PlannedAction}, Reconcile};
+/// #[derive(Reconcile)]
+/// struct Profile {
+///     name: String,
+///     tags: Vec<String>,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// let mut profile = Profile {
+///     name: "alice".to_string(),
+///     tags: vec!["admin".to_string()],
+/// };
+/// reconcile(&mut doc, &profile).unwrap();
+///
+/// profile.name = "Alice".to_string();
+/// profile.tags.clear();
+/// let changes = plan(&doc, &profile).unwrap();
+/// let changes = changes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+/// assert_eq!(changes, vec!["put name = \"Alice\"", "delete tags[0]"]);
+/// ```
+///
+/// Copying the document takes time proportional to its size, so this is intended for previews
+/// and tests rather than for every save.
+pub fn plan<R: Reconcile>(
+    doc: &am::AutoCommit,
+    value: R,
+) -> Result<Vec<PlannedChange>, ReconcileError> {
+    let mut changes = Vec::new();
+    super::reconcile_observed(&mut doc.clone(), value, |op| {
+        changes.push(PlannedChange {
+            path: op.path,
+            action: op.action,
+        })
+    })?;
+    Ok(changes)
+}
+
+/// A change which reconciling a value would make, see [`plan`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlannedChange {
+    /// The path from the root of the document to what is changed
+    ///
+    /// For puts, deletes and increments this is the path to the property, for inserts it is the
+    /// path to the inserted element, and for text splices and marks it is the path to the text.
+    pub path: crate::Path,
+    pub action: PlannedAction,
+}
+
+/// What a [`PlannedChange`] does
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlannedAction {
+    Put(ScalarValue),
+    /// Replace the property with a new, empty object
+    PutObject(ObjType),
+    Insert(ScalarValue),
+    /// Insert a new, empty object
+    InsertObject(ObjType),
+    Delete,
+    Increment(i64),
+    SpliceText {
+        pos: usize,
+        delete: isize,
+        insert: String,
+    },
+    Mark {
+        name: String,
+        value: ScalarValue,
+        start: usize,
+        end: usize,
+    },
+    Unmark {
+        name: String,
+        start: usize,
+        end: usize,
+    },
+}
+
+impl std::fmt::Display for PlannedChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = DisplayPath(&self.path);
+        match &self.action {
+            PlannedAction::Put(v) => write!(f, "put {} = {}", path, v),
+            PlannedAction::PutObject(t) => write!(f, "put {} = new {}", path, t),
+            PlannedAction::Insert(v) => write!(f, "insert {} = {}", path, v),
+            PlannedAction::InsertObject(t) => write!(f, "insert {} = new {}", path, t),
+            PlannedAction::Delete => write!(f, "delete {}", path),
+            PlannedAction::Increment(by) => write!(f, "increment {} by {}", path, by),
+            PlannedAction::SpliceText {
+                pos,
+                delete,
+                insert,
+            } => write!(
+                f,
+                "splice {} at {} deleting {} inserting {:?}",
+                path, pos, delete, insert
+            ),
+            PlannedAction::Mark {
+                name,
+                value,
+                start,
+                end,
+            } => write!(f, "mark {}[{}..{}] {} = {}", path, start, end, name, value),
+            PlannedAction::Unmark { name, start, end } => {
+                write!(f, "unmark {}[{}..{}] {}", path, start, end, name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::ObjType;
+
+    use super::{plan, PlannedAction, PlannedChange};
+    use crate::{reconcile, reconcile::MapReconciler, Counter, Reconcile, Reconciler, Text};
+
+    struct Note {
+        title: Text,
+        views: Counter,
+        tags: Vec<Vec<String>>,
+    }
+
+    impl Reconcile for Note {
+        type Key<'a> = crate::reconcile::NoKey;
+        fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            let mut m = reconciler.map()?;
+            m.put("title", &self.title)?;
+            m.put("views", &self.views)?;
+            m.put("tags", &self.tags)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn plan_does_not_change_the_document() {
+        let mut doc = automerge::AutoCommit::new();
+        let mut note = Note {
+            title: Text::with_value("hello"),
+            views: Counter::default(),
+            tags: Vec::new(),
+        };
+        reconcile(&mut doc, &note).unwrap();
+        let heads = doc.get_heads();
+
+        note.title = crate::hydrate_prop(&doc, &automerge::ROOT, "title").unwrap();
+        note.title.splice(5, 0, " world");
+        note.views = crate::hydrate_prop(&doc, &automerge::ROOT, "views").unwrap();
+        note.views.increment(2);
+        note.tags.push(vec!["draft".to_string()]);
+        let changes = plan(&doc, &note).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                PlannedChange {
+                    path: crate::path!["title"],
+                    action: PlannedAction::SpliceText {
+                        pos: 5,
+                        delete: 0,
+                        insert: " world".to_string()
+                    },
+                },
+                PlannedChange {
+                    path: crate::path!["views"],
+                    action: PlannedAction::Increment(2),
+                },
+                PlannedChange {
+                    path: crate::path!["tags", 0_usize],
+                    action: PlannedAction::InsertObject(ObjType::List),
+                },
+                PlannedChange {
+                    path: crate::path!["tags", 0_usize, 0_usize],
+                    action: PlannedAction::Insert("draft".into()),
+                },
+            ]
+        );
+        assert_eq!(doc.get_heads(), heads);
+        assert_eq!(
+            changes[0].to_string(),
+            "splice title at 5 deleting 0 inserting \" world\""
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::{cell::RefCell, collections::HashMap, ops::RangeBounds};
+
+use automerge::{self as am, AutomergeError, ObjId, Value};
+
+use crate::ReadDoc;
+
+type Entry = Option<(Value<'static>, ObjId)>;
+
+// `automerge::Prop` doesn't implement `Hash`
+#[derive(PartialEq, Eq, Hash)]
+enum CacheProp {
+    Map(String),
+    Seq(usize),
+}
+
+impl From<&am::Prop> for CacheProp {
+    fn from(prop: &am::Prop) -> Self {
+        match prop {
+            am::Prop::Map(k) => Self::Map(k.clone()),
+            am::Prop::Seq(i) => Self::Seq(*i),
+        }
+    }
+}
+
+/// The results of [`ReadDoc::get`] calls made during one reconcile pass
+///
+/// When reconciling a sequence we hydrate the key of every element in the document before making
+/// any changes, and the same element may be examined more than once (e.g. when trimming the common
+/// prefix and suffix and then again when diffing the remainder). Each lookup also reads the
+/// element itself before reading the key inside it. Caching the reads means each `(obj, prop)`
+/// is only read from the document once.
+///
+/// The cache must be cleared whenever the document is modified.
+#[derive(Default)]
+pub(super) struct ReadCache {
+    gets: RefCell<HashMap<(ObjId, CacheProp), Entry>>,
+}
+
+impl ReadCache {
+    pub(super) fn clear(&mut self) {
+        self.gets.get_mut().clear();
+    }
+
+    /// Forget the read of `index` in the sequence `obj`, e.g. because an element was appended there
+    pub(super) fn forget(&mut self, obj: &ObjId, index: usize) {
+        self.gets
+            .get_mut()
+            .remove(&(obj.clone(), CacheProp::Seq(index)));
+    }
+
+    /// Read `(obj, prop)` from `doc`, or from the cache if it has already been read
+    pub(super) fn get<D: ReadDoc, P: Into<am::Prop>>(
+        &self,
+        doc: &D,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Entry, AutomergeError> {
+        let prop = prop.into();
+        let key = (obj.clone(), CacheProp::from(&prop));
+        if let Some(entry) = self.gets.borrow().get(&key) {
+            return Ok(entry.clone());
+        }
+        let entry = doc.get(obj, prop)?.map(|(v, id)| (v.into_owned(), id));
+        self.gets.borrow_mut().insert(key, entry.clone());
+        Ok(entry)
+    }
+
+    /// A [`ReadDoc`] which reads `doc` through this cache
+    pub(super) fn reader<'a, D: ReadDoc>(&'a self, doc: &'a D) -> CachedReads<'a, D> {
+        CachedReads { doc, cache: self }
+    }
+
+    #[cfg(test)]
+    pub(super) fn len(&self) -> usize {
+        self.gets.borrow().len()
+    }
+}
+
+pub(super) struct CachedReads<'a, D> {
+    doc: &'a D,
+    cache: &'a ReadCache,
+}
+
+impl<'a, D: ReadDoc> ReadDoc for CachedReads<'a, D> {
+    type Parents<'b>
+        = D::Parents<'a>
+    where
+        Self: 'b;
+
+    fn get_heads(&self) -> Vec<am::ChangeHash> {
+        self.doc.get_heads()
+    }
+
+    fn get<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        self.cache.get(self.doc, obj, prop)
+    }
+
+    fn get_all<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_all(obj, prop)
+    }
+
+    fn object_type<O: AsRef<ObjId>>(&self, obj: O) -> Option<am::ObjType> {
+        self.doc.object_type(obj)
+    }
+
+    fn map_range<'b, O, R>(&'b self, obj: O, range: R) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+        R: RangeBounds<String>,
+    {
+        self.doc.map_range(obj, range)
+    }
+
+    fn list_range<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+    ) -> am::iter::ListRange<'_, R> {
+        self.doc.list_range(obj, range)
+    }
+
+    fn length<O: AsRef<ObjId>>(&self, obj: O) -> usize {
+        self.doc.length(obj)
+    }
+
+    fn keys<O: AsRef<ObjId>>(&self, obj: O) -> am::iter::Keys<'_> {
+        self.doc.keys(obj)
+    }
+
+    fn text<O: AsRef<ObjId>>(&self, obj: O) -> Result<String, AutomergeError> {
+        self.doc.text(obj)
+    }
+
+    fn parents<O: AsRef<ObjId>>(&self, obj: O) -> Result<Self::Parents<'_>, AutomergeError> {
+        self.doc.parents(obj)
+    }
+
+    fn get_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_at(obj, prop, heads)
+    }
+
+    fn get_all_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        self.doc.get_all_at(obj, prop, heads)
+    }
+
+    fn map_range_at<'b, O, R>(
+        &'b self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+    {
+        self.doc.map_range_at(obj, range, heads)
+    }
+
+    fn list_range_at<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::ListRange<'_, R> {
+        self.doc.list_range_at(obj, range, heads)
+    }
+
+    fn length_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> usize {
+        self.doc.length_at(obj, heads)
+    }
+
+    fn keys_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> am::iter::Keys<'_> {
+        self.doc.keys_at(obj, heads)
+    }
+
+    fn text_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<String, AutomergeError> {
+        self.doc.text_at(obj, heads)
+    }
+
+    fn parents_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Self::Parents<'_>, AutomergeError> {
+        self.doc.parents_at(obj, heads)
+    }
+
+    fn get_cursor<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        position: usize,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<am::Cursor, AutomergeError> {
+        self.doc.get_cursor(obj, position, at)
+    }
+
+    fn get_cursor_position<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        cursor: &am::Cursor,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<usize, AutomergeError> {
+        self.doc.get_cursor_position(obj, cursor, at)
+    }
+
+    fn marks<O: AsRef<ObjId>>(&self, obj: O) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        self.doc.marks(obj)
+    }
+
+    fn marks_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        self.doc.marks_at(obj, heads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::transaction::Transactable;
+
+    use super::ReadCache;
+    use crate::ReadDoc;
+
+    #[test]
+    fn reads_are_cached_until_cleared() {
+        let mut doc = automerge::AutoCommit::new();
+        let list = doc
+            .put_object(automerge::ROOT, "list", automerge::ObjType::List)
+            .unwrap();
+        doc.insert(&list, 0, "one").unwrap();
+
+        let mut cache = ReadCache::default();
+        let reader = cache.reader(&doc);
+        assert_eq!(
+            reader.get(&list, 0_usize).unwrap().unwrap().0.to_str(),
+            Some("one")
+        );
+        assert!(reader.get(&list, 1_usize).unwrap().is_none());
+        assert_eq!(
+            reader.get(&list, 0_usize).unwrap().unwrap().0.to_str(),
+            Some("one")
+        );
+        assert_eq!(cache.len(), 2);
+
+        doc.put(&list, 0, "two").unwrap();
+        assert_eq!(
+            cache.get(&doc, &list, 0_usize).unwrap().unwrap().0.to_str(),
+            Some("one")
+        );
+        cache.clear();
+        assert_eq!(
+            cache.get(&doc, &list, 0_usize).unwrap().unwrap().0.to_str(),
+            Some("two")
+        );
+    }
+}
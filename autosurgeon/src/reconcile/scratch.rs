@@ -0,0 +1,357 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
+
+use super::{read_cache::ReadCache, CanonicalFloats};
+use crate::{Doc, Prop, ReadDoc};
+
+/// Buffers which are reused across calls to [`reconcile_with_scratch`](super::reconcile_with_scratch)
+///
+/// Reconciling allocates some temporary collections for every map and sequence it visits, such
+/// as the set of keys already in a map or the cache of elements read from a sequence. If you are
+/// reconciling the same state many times a second (e.g. on every frame of a game) then keeping a
+/// `ReconcileScratch` around and passing it to `reconcile_with_scratch` means these collections
+/// are allocated once and then reused, rather than being allocated and dropped every time.
+///
+/// ```rust
+/// # use autosurgeon::{Reconcile, reconcile::{reconcile_with_scratch, ReconcileScratch}};
+/// # use std::collections::HashMap;
+/// #[derive(Reconcile)]
+/// struct Cursors {
+///     positions: HashMap<String, u64>,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// let mut scratch = ReconcileScratch::new();
+/// let mut cursors = Cursors { positions: HashMap::new() };
+/// for frame in 0..10 {
+///     cursors.positions.insert("alice".to_string(), frame);
+///     reconcile_with_scratch(&mut doc, &cursors, &mut scratch).unwrap();
+/// }
+/// ```
+///
+/// Temporary collections whose type depends on the type being reconciled, such as the keys of
+/// the elements of a sequence, are not pooled.
+///
+/// A custom [`Reconciler`](super::Reconciler) can make its scratch buffers available to
+/// [`Reconcile`](super::Reconcile) implementations by implementing
+/// [`Reconciler::scratch`](super::Reconciler::scratch).
+///
+/// The scratch also holds the maximum depth of nested objects a reconcile may create, see
+/// [`Self::with_max_depth`], the rules for canonicalizing floats, see
+/// [`Self::with_canonical_floats`], and whether to clear conflicts when reconciling into an
+/// object, see [`Self::with_clear_conflicts`]. Finally it remembers the [`digest`](super::Reconcile::digest)
+/// of values which have been reconciled into objects so that they can be skipped if they haven't
+/// changed, see [`Self::forget_digests`].
+pub struct ReconcileScratch {
+    key_sets: RefCell<Vec<HashSet<String>>>,
+    read_caches: RefCell<Vec<ReadCache>>,
+    digests: RefCell<HashMap<automerge::ObjId, u64>>,
+    /// The heads and number of uncommitted operations of the document after `digests` were last
+    /// written
+    digests_written_at: Option<(Vec<automerge::ChangeHash>, usize)>,
+    max_depth: usize,
+    canonical_floats: CanonicalFloats,
+    clear_conflicts: bool,
+}
+
+impl Default for ReconcileScratch {
+    fn default() -> Self {
+        Self {
+            key_sets: RefCell::default(),
+            read_caches: RefCell::default(),
+            digests: RefCell::default(),
+            digests_written_at: None,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            canonical_floats: CanonicalFloats::NONE,
+            clear_conflicts: false,
+        }
+    }
+}
+
+impl ReconcileScratch {
+    /// The maximum depth of nested objects used by [`Self::new`]
+    pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail with [`MaxDepthExceeded`](super::MaxDepthExceeded) when reconciling a value which
+    /// nests objects more than `max_depth` deep
+    ///
+    /// Depth is counted from the object being reconciled into, so the fields of the value passed
+    /// to [`reconcile`](super::reconcile) are at depth 1. Recursive data structures which are
+    /// accidentally cyclic or very deep otherwise overflow the stack or produce documents which
+    /// are expensive to load, the limit turns this into an error which says where it happened.
+    ///
+    /// ```rust
+    /// # use autosurgeon::path;
+    /// # use autosurgeon::reconcile::{reconcile_with_scratch, ReconcileError, ReconcileScratch};
+    /// # use std::collections::HashMap;
+    /// let mut value = HashMap::new();
+    /// value.insert("a", HashMap::from([("b", HashMap::from([("c", 1)]))]));
+    /// let mut doc = automerge::AutoCommit::new();
+    /// let err = reconcile_with_scratch(&mut doc, &value, &mut ReconcileScratch::with_max_depth(1))
+    ///     .unwrap_err();
+    /// assert_eq!(err.path(), &path!["a", "b"]);
+    /// assert!(matches!(err.without_path(), ReconcileError::MaxDepthExceeded(_)));
+    /// ```
+    pub fn with_max_depth(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            ..Self::default()
+        }
+    }
+
+    /// The maximum depth of nested objects, see [`Self::with_max_depth`]
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Canonicalize every float written to the document according to `rules`
+    ///
+    /// See [`CanonicalFloats`] for why this matters.
+    pub fn with_canonical_floats(mut self, rules: CanonicalFloats) -> Self {
+        self.canonical_floats = rules;
+        self
+    }
+
+    /// The rules for canonicalizing floats, see [`Self::with_canonical_floats`]
+    pub fn canonical_floats(&self) -> CanonicalFloats {
+        self.canonical_floats
+    }
+
+    /// Replace objects which conflict with other values rather than reconciling into them
+    ///
+    /// After merging concurrent changes a property can hold several conflicting values, for
+    /// example a map written by one peer and a string written by another. Reconciling a map,
+    /// sequence, or text into such a property reconciles into the winning value if it is an
+    /// object of the right type, leaving the losing values in place so that concurrent changes to
+    /// the winning object still merge. With `clear_conflicts` set the conflicting values are
+    /// instead all replaced by a new object, at the cost of the identity of the winning object.
+    ///
+    /// ```rust
+    /// # use autosurgeon::reconcile::{reconcile_with_scratch, ReconcileScratch};
+    /// # use automerge::{transaction::Transactable, ReadDoc};
+    /// # use std::collections::HashMap;
+    /// let mut doc = automerge::AutoCommit::new();
+    /// let mut doc2 = doc.fork();
+    /// doc.put_object(automerge::ROOT, "settings", automerge::ObjType::Map).unwrap();
+    /// doc2.put(automerge::ROOT, "settings", "none").unwrap();
+    /// doc.merge(&mut doc2).unwrap();
+    /// assert_eq!(doc.get_all(automerge::ROOT, "settings").unwrap().len(), 2);
+    ///
+    /// let state = HashMap::from([("settings", HashMap::from([("theme", "dark")]))]);
+    /// let mut scratch = ReconcileScratch::new().with_clear_conflicts(true);
+    /// reconcile_with_scratch(&mut doc, &state, &mut scratch).unwrap();
+    /// assert_eq!(doc.get_all(automerge::ROOT, "settings").unwrap().len(), 1);
+    /// ```
+    pub fn with_clear_conflicts(mut self, clear_conflicts: bool) -> Self {
+        self.clear_conflicts = clear_conflicts;
+        self
+    }
+
+    /// Whether to replace conflicted objects, see [`Self::with_clear_conflicts`]
+    pub fn clear_conflicts(&self) -> bool {
+        self.clear_conflicts
+    }
+
+    /// Take an empty set of keys from the pool, allocating one if the pool is empty
+    pub fn take_key_set(&self) -> HashSet<String> {
+        self.key_sets.borrow_mut().pop().unwrap_or_default()
+    }
+
+    /// Return a set of keys obtained from [`Self::take_key_set`] to the pool
+    pub fn recycle_key_set(&self, mut keys: HashSet<String>) {
+        keys.clear();
+        self.key_sets.borrow_mut().push(keys);
+    }
+
+    /// Forget the digests of every value reconciled with this scratch
+    ///
+    /// A digest records what a value looked like when it was last reconciled into an object, and
+    /// reconciling a value with the same digest into the same object again is skipped. The
+    /// scratch also records the heads of the document, and the number of operations which haven't
+    /// been committed yet, after every reconcile. If either is different at the start of the
+    /// next reconcile then the document was changed by something else (including by committing
+    /// the changes made by the previous reconcile), so the digests are forgotten automatically.
+    ///
+    /// This method is for documents which can't report their uncommitted operations (see
+    /// [`Doc::pending_ops`]) and have been edited without committing.
+    ///
+    /// ```rust
+    /// # use autosurgeon::{reconcile::{reconcile_with_scratch, ReconcileScratch}, Reconcile};
+    /// # use automerge::{transaction::Transactable, ReadDoc};
+    /// #[derive(Hash, Reconcile)]
+    /// struct Config {
+    ///     inner: Inner,
+    /// }
+    ///
+    /// #[derive(Hash, Reconcile)]
+    /// #[autosurgeon(digest)]
+    /// struct Inner {
+    ///     name: String,
+    /// }
+    ///
+    /// let mut doc = automerge::AutoCommit::new();
+    /// let mut scratch = ReconcileScratch::new();
+    /// let config = Config { inner: Inner { name: "one".to_string() } };
+    /// reconcile_with_scratch(&mut doc, &config, &mut scratch).unwrap();
+    ///
+    /// let (_, inner) = doc.get(automerge::ROOT, "inner").unwrap().unwrap();
+    /// doc.put(&inner, "name", "two").unwrap();
+    ///
+    /// scratch.forget_digests();
+    /// reconcile_with_scratch(&mut doc, &config, &mut scratch).unwrap();
+    /// let (name, _) = doc.get(&inner, "name").unwrap().unwrap();
+    /// assert_eq!(name.into_string().unwrap(), "one");
+    /// ```
+    pub fn forget_digests(&self) {
+        self.digests.borrow_mut().clear();
+    }
+
+    /// Forget the digests if `doc` has changed since they were last written
+    pub(super) fn forget_digests_if_changed<D: Doc + ?Sized>(&self, doc: &D) {
+        if self.digests_written_at != Some((doc.get_heads(), doc.pending_ops())) {
+            self.forget_digests();
+        }
+    }
+
+    /// Record the state of `doc` after reconciling into it, see [`Self::forget_digests`]
+    pub(super) fn digests_written<D: Doc + ?Sized>(&mut self, doc: &D) {
+        self.digests_written_at = Some((doc.get_heads(), doc.pending_ops()));
+    }
+
+    /// Whether the object at `(obj, prop)` was last reconciled from a value with `digest`
+    pub(super) fn digest_unchanged<D: ReadDoc + ?Sized>(
+        &self,
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: &Prop<'_>,
+        digest: Option<u64>,
+    ) -> Result<bool, automerge::AutomergeError> {
+        let Some(digest) = digest else {
+            return Ok(false);
+        };
+        if self.digests.borrow().is_empty() {
+            return Ok(false);
+        }
+        Ok(match single_object(doc, obj, prop)? {
+            Some(id) => self.digests.borrow().get(&id) == Some(&digest),
+            None => false,
+        })
+    }
+
+    /// Record that the object at `(obj, prop)` was reconciled from a value with `digest`
+    pub(super) fn remember_digest<D: ReadDoc + ?Sized>(
+        &self,
+        doc: &D,
+        obj: &automerge::ObjId,
+        prop: &Prop<'_>,
+        digest: Option<u64>,
+    ) -> Result<(), automerge::AutomergeError> {
+        let Some(digest) = digest else {
+            return Ok(());
+        };
+        if let Some(id) = single_object(doc, obj, prop)? {
+            self.digests.borrow_mut().insert(id, digest);
+        }
+        Ok(())
+    }
+
+    pub(super) fn take_read_cache(&self) -> ReadCache {
+        self.read_caches.borrow_mut().pop().unwrap_or_default()
+    }
+
+    pub(super) fn recycle_read_cache(&self, mut cache: ReadCache) {
+        cache.clear();
+        self.read_caches.borrow_mut().push(cache);
+    }
+}
+
+impl std::fmt::Debug for ReconcileScratch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReconcileScratch")
+            .field("key_sets", &self.key_sets.borrow().len())
+            .field("read_caches", &self.read_caches.borrow().len())
+            .field("digests", &self.digests.borrow().len())
+            .field("max_depth", &self.max_depth)
+            .field("canonical_floats", &self.canonical_floats)
+            .field("clear_conflicts", &self.clear_conflicts)
+            .finish()
+    }
+}
+
+/// The ID of the object at `(obj, prop)`, if there is exactly one value there and it is an object
+///
+/// Conflicting values are resolved by reconciling, so a digest can't describe a property with
+/// more than one value.
+fn single_object<D: ReadDoc + ?Sized>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: &Prop<'_>,
+) -> Result<Option<automerge::ObjId>, automerge::AutomergeError> {
+    let mut values = doc.get_all(obj, prop)?;
+    if values.len() != 1 {
+        return Ok(None);
+    }
+    Ok(match values.pop() {
+        Some((automerge::Value::Object(_), id)) => Some(id),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use automerge_test::{assert_doc, list, map};
+
+    use super::ReconcileScratch;
+    use crate::{
+        reconcile::{reconcile_with_scratch, MapReconciler, NoKey},
+        Reconcile, Reconciler,
+    };
+
+    struct State {
+        scores: HashMap<String, u64>,
+        history: Vec<u64>,
+    }
+
+    impl Reconcile for State {
+        type Key<'a> = NoKey;
+        fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            let mut map = reconciler.map()?;
+            map.put("scores", &self.scores)?;
+            map.put("history", &self.history)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn buffers_are_reused_between_calls() {
+        let mut doc = automerge::AutoCommit::new();
+        let mut scratch = ReconcileScratch::new();
+        let mut state = State {
+            scores: HashMap::new(),
+            history: Vec::new(),
+        };
+        for i in 0..3 {
+            state.scores.insert("alice".to_string(), i);
+            state.history.push(i);
+            reconcile_with_scratch(&mut doc, &state, &mut scratch).unwrap();
+            // Reconciling `scores` borrows two key sets and `history` borrows a cache
+            assert_eq!(scratch.key_sets.borrow().len(), 2);
+            assert_eq!(scratch.read_caches.borrow().len(), 1);
+        }
+        assert_doc!(
+            doc.document(),
+            map! {
+                "scores" => { map! { "alice" => { 2_u64 } } },
+                "history" => { list! { { 0_u64 }, { 1_u64 }, { 2_u64 } } },
+            }
+        );
+    }
+}
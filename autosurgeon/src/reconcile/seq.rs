@@ -1,4 +1,4 @@
-use super::{LoadKey, NoKey, Reconcile, Reconciler, SeqReconciler};
+use super::{ImmutableKeyChanged, LoadKey, NoKey, Reconcile, Reconciler, SeqReconciler};
 
 // This module implements an LCS diff for sequences when reconciling. Currently the implementation
 // of the Hunt-Szymanski diff is from the `similar` crate. Consequenctly most of the types in this
@@ -19,6 +19,39 @@ impl<T: Reconcile> Reconcile for Vec<T> {
     }
 }
 
+impl<T: Reconcile, const N: usize> Reconcile for [T; N] {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        reconcile_seq(self, reconciler)
+    }
+}
+
+impl<T: Reconcile> Reconcile for std::collections::VecDeque<T> {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        reconcile_seq(&self.iter().collect::<Vec<_>>(), reconciler)
+    }
+}
+
+impl<T: Reconcile> Reconcile for std::collections::LinkedList<T> {
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        reconcile_seq(&self.iter().collect::<Vec<_>>(), reconciler)
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A> Reconcile for smallvec::SmallVec<A>
+where
+    A: smallvec::Array,
+    A::Item: Reconcile,
+{
+    type Key<'a> = NoKey;
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        reconcile_seq(self, reconciler)
+    }
+}
+
 // Represents a key of an element in the document, we don't represent the actual element here
 // because we don't want to hydrate the entire element from the document, just the key
 struct OldElem<K> {
@@ -52,13 +85,14 @@ impl<'a: 'b, 'b, T: Reconcile> PartialEq<OldElem<LoadKey<T::Key<'b>>>> for NewEl
     }
 }
 
-struct Hook<'a, T, S> {
+struct Hook<'a, T, S, K> {
     idx: usize,
     seq: &'a mut S,
     items: &'a [T],
+    old_keys: &'a [OldElem<LoadKey<K>>],
 }
 
-impl<'a, T, S> similar::algorithms::DiffHook for Hook<'a, T, S>
+impl<'a, T, S, K> similar::algorithms::DiffHook for Hook<'a, T, S, K>
 where
     T: Reconcile,
     S: SeqReconciler,
@@ -101,6 +135,31 @@ where
         }
         Ok(())
     }
+
+    fn replace(
+        &mut self,
+        old_index: usize,
+        old_len: usize,
+        new_index: usize,
+        new_len: usize,
+    ) -> Result<(), Self::Error> {
+        // Replacing a keyed element with a differently keyed element at the same index is what
+        // changing the key of an element looks like
+        if T::KEY_IMMUTABLE {
+            let old = &self.old_keys[old_index..(old_index + old_len)];
+            let new = &self.items[new_index..(new_index + new_len)];
+            for (offset, (old, new)) in old.iter().zip(new).enumerate() {
+                if matches!(old.key, LoadKey::Found(_)) && matches!(new.key(), LoadKey::Found(_)) {
+                    return Err(ImmutableKeyChanged {
+                        prop: (self.idx + offset).into(),
+                    }
+                    .into());
+                }
+            }
+        }
+        self.delete(old_index, old_len, new_index)?;
+        self.insert(old_index, new_index, new_len)
+    }
 }
 
 fn reconcile_seq<T, R>(items: &[T], mut reconciler: R) -> Result<(), R::Error>
@@ -112,8 +171,50 @@ where
     let mut seq = reconciler.seq()?;
 
     let old_len = seq.len()?;
-    let old_keys = (0..old_len).try_fold::<_, _, Result<_, R::Error>>(
-        Vec::with_capacity(old_len),
+    let new_len = items.len();
+
+    let matches = |seq: &R::Seq<'_>, old_index: usize, new_index: usize| {
+        let old = OldElem {
+            key: seq.hydrate_item_key::<T>(old_index)?,
+            index: old_index,
+        };
+        let new = NewElem {
+            elem: &items[new_index],
+            index: new_index,
+        };
+        Ok::<_, R::Error>(new == old)
+    };
+
+    // Appending to or prepending to a long list is very common, so before running the diff we
+    // skip over any matching elements at the start and end of the sequence. The diff (and the
+    // buffer of old keys it needs) then only covers the elements in the middle, which is where the
+    // changes are.
+    let mut prefix = 0;
+    while prefix < old_len && prefix < new_len && matches(&seq, prefix, prefix)? {
+        prefix += 1;
+    }
+
+    // Every element in the document matched, so the rest of the new elements are appended
+    if prefix == old_len {
+        for (index, elem) in items.iter().enumerate() {
+            if index < prefix {
+                seq.set(index, elem)?;
+            } else {
+                seq.push(elem)?;
+            }
+        }
+        return Ok(());
+    }
+    let mut suffix = 0;
+    while suffix < old_len - prefix
+        && suffix < new_len - prefix
+        && matches(&seq, old_len - 1 - suffix, new_len - 1 - suffix)?
+    {
+        suffix += 1;
+    }
+
+    let old_keys = (prefix..(old_len - suffix)).try_fold::<_, _, Result<_, R::Error>>(
+        Vec::with_capacity(old_len - suffix - prefix),
         |mut items, i| {
             items.push(OldElem {
                 key: seq.hydrate_item_key::<T>(i)?,
@@ -123,19 +224,40 @@ where
         },
     )?;
 
-    let new = items
+    let new = items[prefix..(new_len - suffix)]
         .iter()
         .enumerate()
-        .map(|(i, e)| NewElem { elem: e, index: i })
+        .map(|(i, e)| NewElem {
+            elem: e,
+            index: prefix + i,
+        })
         .collect::<Vec<_>>();
 
     let mut hook = Hook {
         idx: 0,
-        items,
+        items: &items[prefix..(new_len - suffix)],
         seq: &mut seq,
+        old_keys: &old_keys,
     };
 
-    similar::algorithms::lcs::diff(&mut hook, &old_keys, 0..old_len, &new, 0..items.len())?;
+    for elem in &items[..prefix] {
+        hook.seq.set(hook.idx, elem)?;
+        hook.idx += 1;
+    }
+
+    let mut replace = similar::algorithms::Replace::new(&mut hook);
+    similar::algorithms::lcs::diff(
+        &mut replace,
+        &old_keys,
+        0..old_keys.len(),
+        &new,
+        0..new.len(),
+    )?;
+
+    for elem in &items[(new_len - suffix)..] {
+        hook.seq.set(hook.idx, elem)?;
+        hook.idx += 1;
+    }
     Ok(())
 }
 
@@ -261,4 +383,128 @@ mod tests {
             }
         )
     }
+
+    // A keyed element which counts how many times its key is hydrated
+    #[derive(Clone)]
+    struct Counted(u64);
+
+    static KEYS_HYDRATED: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    impl crate::Reconcile for Counted {
+        type Key<'a> = u64;
+
+        fn reconcile<R: crate::Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+            self.0.reconcile(reconciler)
+        }
+
+        fn hydrate_key<'a, D: ReadDoc>(
+            doc: &D,
+            obj: &automerge::ObjId,
+            prop: crate::prop::Prop<'_>,
+        ) -> Result<LoadKey<Self::Key<'a>>, crate::ReconcileError> {
+            KEYS_HYDRATED.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            u64::hydrate_key(doc, obj, prop)
+        }
+
+        fn key(&self) -> LoadKey<Self::Key<'_>> {
+            LoadKey::Found(self.0)
+        }
+    }
+
+    #[test]
+    fn append_and_prepend_hydrate_each_key_once() {
+        use std::sync::atomic::Ordering;
+
+        let mut vals = (0..100).map(Counted).collect::<Vec<_>>();
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "vals", &vals).unwrap();
+
+        vals.push(Counted(100));
+        KEYS_HYDRATED.store(0, Ordering::SeqCst);
+        reconcile_prop(&mut doc, automerge::ROOT, "vals", &vals).unwrap();
+        // The whole prefix matches, then there are no old elements left to diff
+        assert_eq!(KEYS_HYDRATED.load(Ordering::SeqCst), 100);
+
+        vals.insert(0, Counted(101));
+        KEYS_HYDRATED.store(0, Ordering::SeqCst);
+        reconcile_prop(&mut doc, automerge::ROOT, "vals", &vals).unwrap();
+        // One failed comparison at the start, then the whole suffix matches
+        assert_eq!(KEYS_HYDRATED.load(Ordering::SeqCst), 102);
+
+        let hydrated: Vec<u64> = crate::hydrate_prop(&doc, &automerge::ROOT, "vals").unwrap();
+        let expected = std::iter::once(101).chain(0..=100).collect::<Vec<_>>();
+        assert_eq!(hydrated, expected);
+    }
+
+    #[test]
+    fn reconcile_vecdeque_and_linkedlist() {
+        use std::collections::{LinkedList, VecDeque};
+
+        let mut doc = automerge::AutoCommit::new();
+        let mut queue = VecDeque::from(vec![1, 2, 3]);
+        reconcile_prop(&mut doc, automerge::ROOT, "queue", &queue).unwrap();
+        queue.pop_front();
+        queue.push_back(4);
+        reconcile_prop(&mut doc, automerge::ROOT, "queue", &queue).unwrap();
+
+        let list = LinkedList::from(["a", "b"]);
+        reconcile_prop(&mut doc, automerge::ROOT, "list", &list).unwrap();
+
+        assert_doc!(
+            doc.document(),
+            map! {
+                "queue" => { list! { { 2 }, { 3 }, { 4 } } },
+                "list" => { list! { { "a" }, { "b" } } },
+            }
+        );
+
+        let hydrated: VecDeque<i64> = crate::hydrate_prop(&doc, &automerge::ROOT, "queue").unwrap();
+        assert_eq!(hydrated, VecDeque::from(vec![2, 3, 4]));
+        let hydrated: LinkedList<String> =
+            crate::hydrate_prop(&doc, &automerge::ROOT, "list").unwrap();
+        assert_eq!(
+            hydrated,
+            LinkedList::from(["a".to_string(), "b".to_string()])
+        );
+    }
+
+    #[test]
+    fn remove_outer_elements_of_nested_vecs() {
+        let mut rows: Vec<Vec<u8>> = vec![vec![1, 2], vec![3], vec![4, 5, 6], vec![7]];
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "rows", &rows).unwrap();
+
+        for index in [0, 1, 1] {
+            rows.remove(index);
+            reconcile_prop(&mut doc, automerge::ROOT, "rows", &rows).unwrap();
+            let hydrated: Vec<Vec<u8>> =
+                crate::hydrate_prop(&doc, &automerge::ROOT, "rows").unwrap();
+            assert_eq!(hydrated, rows);
+        }
+        assert_doc!(
+            doc.document(),
+            map! {
+                "rows" => { list! { { list! { { 3_u64 } } } } },
+            }
+        );
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[test]
+    fn reconcile_smallvec() {
+        use smallvec::{smallvec, SmallVec};
+
+        let mut doc = automerge::AutoCommit::new();
+        let vals: SmallVec<[u64; 2]> = smallvec![1, 2, 3];
+        reconcile_prop(&mut doc, automerge::ROOT, "vals", &vals).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "vals" => { list! { { 1_u64 }, { 2_u64 }, { 3_u64 } } },
+            }
+        );
+        let hydrated: SmallVec<[u64; 2]> =
+            crate::hydrate_prop(&doc, &automerge::ROOT, "vals").unwrap();
+        assert_eq!(hydrated, vals);
+    }
 }
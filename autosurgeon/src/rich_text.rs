@@ -0,0 +1,283 @@
+use crate::{
+    reconcile::{NoKey, TextReconciler},
+    rope::Rope,
+    text::{splice_range, Splice},
+    Hydrate, HydrateError, ReadDoc, Reconcile,
+};
+
+/// A type which reconciles to an [`automerge::ObjType::Text`] and stays fast for large texts
+///
+/// `RichText` is used in the same way as [`crate::Text`]: hydrate it, make calls to
+/// [`RichText::splice`], and reconcile it. The difference is that [`crate::Text`] stores its value
+/// as a `String`, so every splice copies the text after the splice point, whereas `RichText`
+/// stores the value as a rope, so finding the position of a splice takes O(log n) time and the
+/// splice itself only touches the part of the text it changes. Use `RichText` for texts of many
+/// megabytes, such as whole books or source files.
+///
+/// Positions are always measured in `char`s, which is how automerge indexes text, because
+/// converting a byte offset into a `char` offset would mean scanning the text.
+///
+/// As with [`crate::Text`] reconciling a `RichText` with a document whose heads have changed
+/// since it was hydrated returns a [`crate::reconcile::ReconcileError::StaleHeads`] error.
+///
+/// ```rust
+/// # use autosurgeon::{hydrate_prop, reconcile_prop, RichText};
+/// let mut doc = automerge::AutoCommit::new();
+/// let text = RichText::with_value("It was a dark night");
+/// reconcile_prop(&mut doc, automerge::ROOT, "book", &text).unwrap();
+///
+/// let mut text: RichText = hydrate_prop(&doc, &automerge::ROOT, "book").unwrap();
+/// text.splice(9, 0, "and stormy ");
+/// reconcile_prop(&mut doc, automerge::ROOT, "book", &text).unwrap();
+///
+/// let text: RichText = hydrate_prop(&doc, &automerge::ROOT, "book").unwrap();
+/// assert_eq!(text.to_string(), "It was a and stormy dark night");
+/// ```
+#[derive(Clone)]
+pub struct RichText(State);
+
+#[derive(Clone)]
+enum State {
+    Fresh(Rope),
+    Rehydrated {
+        value: Rope,
+        edits: Vec<Splice>,
+        from_heads: Vec<automerge::ChangeHash>,
+    },
+}
+
+impl RichText {
+    pub fn with_value<S: AsRef<str>>(value: S) -> RichText {
+        Self(State::Fresh(Rope::from(value.as_ref())))
+    }
+
+    /// Delete `del` `char`s starting at `pos` and insert `insert` in their place
+    ///
+    /// `del` can be negative to indicate deleting `del` characters preceding `pos`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start or end of the splice is greater than [`Self::len_chars`]
+    pub fn splice<S: AsRef<str>>(&mut self, pos: usize, del: isize, insert: S) {
+        let (start, end) = splice_range(pos, del);
+        match &mut self.0 {
+            State::Fresh(v) => v.splice(start, end - start, insert.as_ref()),
+            State::Rehydrated { value, edits, .. } => {
+                value.splice(start, end - start, insert.as_ref());
+                edits.push(Splice {
+                    pos: start,
+                    delete: end - start,
+                    insert: insert.as_ref().to_string(),
+                });
+            }
+        }
+    }
+
+    /// Replace the value of the text with `new_value`
+    ///
+    /// Unlike [`crate::Text::update`] this doesn't diff the two values, which would be too slow
+    /// for a large text. Instead the part of the text between the common prefix and suffix of
+    /// the old and new values is replaced with a single splice.
+    pub fn update<S: AsRef<str>>(&mut self, new_value: S) {
+        let new_value = new_value.as_ref();
+        let (old_len, prefix, suffix) = {
+            let old = self.value();
+            let prefix = old
+                .chars()
+                .zip(new_value.chars())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let max_suffix = old.len_chars().min(new_value.chars().count()) - prefix;
+            let suffix = old
+                .chars()
+                .rev()
+                .zip(new_value.chars().rev())
+                .take(max_suffix)
+                .take_while(|(a, b)| a == b)
+                .count();
+            (old.len_chars(), prefix, suffix)
+        };
+        let insert = new_value
+            .chars()
+            .skip(prefix)
+            .take(new_value.chars().count() - prefix - suffix)
+            .collect::<String>();
+        let delete = old_len - prefix - suffix;
+        if delete > 0 || !insert.is_empty() {
+            self.splice(prefix, delete as isize, insert);
+        }
+    }
+
+    /// The length of the text in `char`s
+    pub fn len_chars(&self) -> usize {
+        self.value().len_chars()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len_chars() == 0
+    }
+
+    /// The text as a sequence of string slices, in order
+    ///
+    /// Use this rather than [`ToString::to_string`] to avoid copying the whole text.
+    pub fn chunks(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.value().chunks()
+    }
+
+    /// The `char`s of the text
+    pub fn chars(&self) -> impl DoubleEndedIterator<Item = char> + '_ {
+        self.value().chars()
+    }
+
+    /// The splices which have been made since this text was hydrated
+    ///
+    /// See [`crate::Text::edits`]
+    pub fn edits(&self) -> &[Splice] {
+        match &self.0 {
+            State::Fresh(_) => &[],
+            State::Rehydrated { edits, .. } => edits,
+        }
+    }
+
+    fn value(&self) -> &Rope {
+        match &self.0 {
+            State::Fresh(v) => v,
+            State::Rehydrated { value, .. } => value,
+        }
+    }
+}
+
+impl std::default::Default for RichText {
+    fn default() -> Self {
+        RichText::with_value("")
+    }
+}
+
+impl std::fmt::Debug for RichText {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RichText")
+            .field("len_chars", &self.len_chars())
+            .finish()
+    }
+}
+
+impl std::fmt::Display for RichText {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value().fmt(f)
+    }
+}
+
+impl<S: AsRef<str>> From<S> for RichText {
+    fn from(s: S) -> Self {
+        RichText::with_value(s)
+    }
+}
+
+impl std::cmp::PartialEq for RichText {
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()
+    }
+}
+
+impl std::cmp::Eq for RichText {}
+
+impl Reconcile for RichText {
+    type Key<'a> = NoKey;
+
+    fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        let mut t = reconciler.text()?;
+        match &self.0 {
+            State::Fresh(v) => {
+                let mut pos = 0;
+                for chunk in v.chunks() {
+                    t.splice(pos, 0, chunk)?;
+                    pos += chunk.chars().count();
+                }
+            }
+            State::Rehydrated {
+                edits, from_heads, ..
+            } => {
+                let to_heads = t.heads();
+                if to_heads != from_heads {
+                    return Err(crate::reconcile::StaleHeads {
+                        expected: from_heads.to_vec(),
+                        found: to_heads.to_vec(),
+                    }
+                    .into());
+                }
+                for edit in edits {
+                    t.splice(edit.pos, edit.delete as isize, &edit.insert)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Hydrate for RichText {
+    fn hydrate_text<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        let value = doc.text(obj)?;
+        Ok(RichText(State::Rehydrated {
+            value: Rope::from(value.as_str()),
+            edits: Vec::new(),
+            from_heads: doc.get_heads(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::ActorId;
+
+    use super::RichText;
+    use crate::{hydrate_prop, reconcile_prop, Text};
+
+    #[test]
+    fn merge_large_text() {
+        let mut doc1 = automerge::AutoCommit::new();
+        let text = RichText::with_value("lorem ipsum ".repeat(10_000));
+        reconcile_prop(&mut doc1, automerge::ROOT, "text", &text).unwrap();
+        let mut doc2 = doc1.fork().with_actor(ActorId::random());
+
+        let mut text1: RichText = hydrate_prop(&doc1, &automerge::ROOT, "text").unwrap();
+        text1.splice(0, 0, "Start: ");
+        text1.splice(60_007, 12, "");
+        reconcile_prop(&mut doc1, automerge::ROOT, "text", &text1).unwrap();
+
+        let mut text2: RichText = hydrate_prop(&doc2, &automerge::ROOT, "text").unwrap();
+        let end = text2.len_chars();
+        text2.splice(end, 0, "The end");
+        reconcile_prop(&mut doc2, automerge::ROOT, "text", &text2).unwrap();
+
+        doc1.merge(&mut doc2).unwrap();
+        let expected = format!("Start: {}The end", "lorem ipsum ".repeat(9_999));
+        let merged: RichText = hydrate_prop(&doc1, &automerge::ROOT, "text").unwrap();
+        assert_eq!(merged.to_string(), expected);
+        // `RichText` and `Text` are stored in the same way
+        let merged: Text = hydrate_prop(&doc1, &automerge::ROOT, "text").unwrap();
+        assert_eq!(merged.as_str(), expected);
+    }
+
+    #[test]
+    fn update_splices_between_common_prefix_and_suffix() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "text",
+            RichText::with_value("some value"),
+        )
+        .unwrap();
+        let mut text: RichText = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        text.update("some other value");
+        assert_eq!(text.edits().len(), 1);
+        assert_eq!(text.edits()[0].pos, 5);
+        assert_eq!(text.edits()[0].insert, "other ");
+        text.update("aaa");
+        text.update("aa");
+        assert_eq!(text.to_string(), "aa");
+        reconcile_prop(&mut doc, automerge::ROOT, "text", &text).unwrap();
+        let text: RichText = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        assert_eq!(text.to_string(), "aa");
+    }
+}
@@ -0,0 +1,249 @@
+//! A chunked string used by [`crate::RichText`]
+//!
+//! The text is stored as a list of chunks of up to `MAX_CHUNK` chars, along with a Fenwick tree
+//! over the length of each chunk. Finding the chunk which contains a given char index is
+//! O(log n) and an edit only touches the chunks it overlaps, so splicing stays cheap no matter
+//! how large the text is. The index is rebuilt from scratch when chunks are added or removed,
+//! which only happens once per `TARGET_CHUNK` chars inserted or when a splice spans chunks.
+
+const TARGET_CHUNK: usize = 1024;
+const MAX_CHUNK: usize = 2 * TARGET_CHUNK;
+
+#[derive(Clone, Default)]
+pub(crate) struct Rope {
+    chunks: Vec<Chunk>,
+    // A Fenwick tree over the length in chars of each chunk, 1-based so `index[0]` is unused
+    index: Vec<usize>,
+    len_chars: usize,
+}
+
+#[derive(Clone)]
+struct Chunk {
+    text: String,
+    chars: usize,
+}
+
+impl Chunk {
+    fn byte_offset(&self, char_offset: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_offset)
+            .map(|(i, _)| i)
+            .unwrap_or(self.text.len())
+    }
+}
+
+impl Rope {
+    pub(crate) fn len_chars(&self) -> usize {
+        self.len_chars
+    }
+
+    pub(crate) fn chunks(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.chunks.iter().map(|c| c.text.as_str())
+    }
+
+    pub(crate) fn chars(&self) -> impl DoubleEndedIterator<Item = char> + '_ {
+        self.chunks().flat_map(str::chars)
+    }
+
+    /// Delete `del` chars at `pos` and insert `insert` in their place
+    ///
+    /// # Panics
+    ///
+    /// If `pos + del` is greater than the length of the rope
+    pub(crate) fn splice(&mut self, pos: usize, del: usize, insert: &str) {
+        assert!(
+            pos + del <= self.len_chars,
+            "index {} out of bounds of text of length {}",
+            pos + del,
+            self.len_chars
+        );
+        self.delete(pos, del);
+        self.insert(pos, insert);
+    }
+
+    fn delete(&mut self, pos: usize, del: usize) {
+        if del == 0 {
+            return;
+        }
+        self.len_chars -= del;
+        let (first, offset) = self.find(pos);
+        let chunk = &mut self.chunks[first];
+        if offset + del <= chunk.chars {
+            let start = chunk.byte_offset(offset);
+            let end = chunk.byte_offset(offset + del);
+            chunk.text.replace_range(start..end, "");
+            chunk.chars -= del;
+            self.add(first, -(del as isize));
+            return;
+        }
+        let start = chunk.byte_offset(offset);
+        chunk.text.truncate(start);
+        chunk.chars = offset;
+        let (last, last_offset) = self.find(pos + del);
+        let chunk = &mut self.chunks[last];
+        let end = chunk.byte_offset(last_offset);
+        chunk.text.replace_range(..end, "");
+        chunk.chars -= last_offset;
+        self.chunks.drain(first + 1..last);
+        self.rebuild();
+    }
+
+    fn insert(&mut self, pos: usize, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let count = text.chars().count();
+        self.len_chars += count;
+        if self.chunks.is_empty() {
+            self.chunks = split(text);
+            self.rebuild();
+            return;
+        }
+        let (i, offset) = self.find(pos);
+        let chunk = &mut self.chunks[i];
+        let byte = chunk.byte_offset(offset);
+        chunk.text.insert_str(byte, text);
+        chunk.chars += count;
+        if chunk.chars > MAX_CHUNK {
+            let chunk = self.chunks.remove(i);
+            self.chunks.splice(i..i, split(&chunk.text));
+            self.rebuild();
+        } else {
+            self.add(i, count as isize);
+        }
+    }
+
+    // The chunk containing char `pos` and the offset of `pos` within it. If `pos` is the length
+    // of the rope this is the end of the last chunk.
+    fn find(&self, pos: usize) -> (usize, usize) {
+        let n = self.chunks.len();
+        // Find the largest `idx` such that the first `idx` chunks contain at most `pos` chars
+        let mut idx = 0;
+        let mut remaining = pos;
+        let mut step = n.next_power_of_two();
+        while step > 0 {
+            if idx + step <= n && self.index[idx + step] <= remaining {
+                idx += step;
+                remaining -= self.index[idx];
+            }
+            step >>= 1;
+        }
+        if idx == n {
+            (n - 1, self.chunks[n - 1].chars)
+        } else {
+            (idx, remaining)
+        }
+    }
+
+    fn add(&mut self, chunk: usize, delta: isize) {
+        let mut i = chunk + 1;
+        while i < self.index.len() {
+            self.index[i] = (self.index[i] as isize + delta) as usize;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    fn rebuild(&mut self) {
+        self.chunks.retain(|c| c.chars > 0);
+        let n = self.chunks.len();
+        self.index = vec![0; n + 1];
+        for i in 1..=n {
+            self.index[i] += self.chunks[i - 1].chars;
+            let parent = i + (i & i.wrapping_neg());
+            if parent <= n {
+                self.index[parent] += self.index[i];
+            }
+        }
+    }
+}
+
+impl From<&str> for Rope {
+    fn from(s: &str) -> Self {
+        let mut rope = Rope {
+            chunks: split(s),
+            index: Vec::new(),
+            len_chars: s.chars().count(),
+        };
+        rope.rebuild();
+        rope
+    }
+}
+
+impl std::fmt::Display for Rope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for chunk in self.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for Rope {
+    fn eq(&self, other: &Self) -> bool {
+        self.len_chars == other.len_chars && self.chars().eq(other.chars())
+    }
+}
+
+impl Eq for Rope {}
+
+// Split `s` into chunks of `TARGET_CHUNK` chars
+fn split(s: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut chars = 0;
+    for (i, _) in s.char_indices() {
+        if chars == TARGET_CHUNK {
+            chunks.push(Chunk {
+                text: s[start..i].to_string(),
+                chars,
+            });
+            start = i;
+            chars = 0;
+        }
+        chars += 1;
+    }
+    if chars > 0 {
+        chunks.push(Chunk {
+            text: s[start..].to_string(),
+            chars,
+        });
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rope, MAX_CHUNK};
+
+    #[test]
+    fn splices_match_string() {
+        let mut expected: Vec<char> = "déjà vu ".repeat(1000).chars().collect();
+        let mut rope = Rope::from(expected.iter().collect::<String>().as_str());
+        // A simple linear congruential generator so the test is deterministic
+        let mut seed: u64 = 42;
+        let mut next = |max: usize| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            ((seed >> 33) as usize) % (max + 1)
+        };
+        for round in 0..500 {
+            let pos = next(expected.len());
+            let del = next((expected.len() - pos).min(if round % 50 == 0 { 5000 } else { 20 }));
+            let insert = "ü".repeat(next(if round % 40 == 0 { 3000 } else { 10 }));
+            expected.splice(pos..pos + del, insert.chars());
+            rope.splice(pos, del, &insert);
+            assert_eq!(rope.len_chars(), expected.len());
+            assert!(rope.chunks.iter().all(|c| c.chars <= MAX_CHUNK));
+        }
+        assert_eq!(rope.to_string(), expected.iter().collect::<String>());
+    }
+
+    #[test]
+    fn delete_everything() {
+        let mut rope = Rope::from("a".repeat(5000).as_str());
+        rope.splice(0, 5000, "");
+        assert_eq!(rope.len_chars(), 0);
+        rope.splice(0, 0, "b");
+        assert_eq!(rope.to_string(), "b");
+    }
+}
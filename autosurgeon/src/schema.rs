@@ -0,0 +1,579 @@
+//! Descriptions of how a type is laid out in a document, and validating documents against them
+//!
+//! A [`Schema`] describes the shape of the data a type reconciles to and hydrates from: which
+//! maps have which keys, what the elements of lists look like, and which kinds of scalars are
+//! expected. Schemas can be derived with `#[derive(DescribeSchema)]`, which understands the same
+//! attributes as the `Reconcile` and `Hydrate` derives, and [`validate`] checks a document
+//! against one, reporting every part of the document which doesn't match along with its path.
+//! This is mostly useful for debugging documents written by other implementations of a schema,
+//! such as a JavaScript application editing the same document.
+//!
+//! ```rust
+//! # use autosurgeon::schema::{validate, DescribeSchema};
+//! # use automerge::transaction::Transactable;
+//! #[derive(DescribeSchema)]
+//! struct Contact {
+//!     name: String,
+//!     emails: Vec<String>,
+//!     age: Option<u64>,
+//! }
+//!
+//! let mut doc = automerge::AutoCommit::new();
+//! doc.put(automerge::ROOT, "name", 42).unwrap();
+//! let emails = doc.put_object(automerge::ROOT, "emails", automerge::ObjType::List).unwrap();
+//! doc.insert(&emails, 0, "sherlock@example.com").unwrap();
+//! doc.insert(&emails, 1, true).unwrap();
+//!
+//! let mismatches = validate(&doc, &Contact::schema()).unwrap_err();
+//! let messages = mismatches.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+//! assert_eq!(
+//!     messages,
+//!     vec![
+//!         "/name: expected a string, found an integer",
+//!         "/emails/1: expected a string, found a boolean",
+//!         "/age: expected null or an integer, found nothing",
+//!     ]
+//! );
+//! ```
+//!
+//! Fields which have a `missing` function may be absent, every other field of a struct must be
+//! present, including `Option`s, which hydrate from null. Keys in a map which don't correspond to
+//! a field are ignored. When a property has conflicting values only the value which hydrating
+//! would see is validated. Fields with custom `reconcile=`, `hydrate=` or `with=` implementations
+//! are described as [`Schema::Any`], as are types which don't implement [`DescribeSchema`]
+//! themselves if you implement it for them that way.
+use std::{borrow::Cow, fmt};
+
+use automerge::{self as am, ObjType, ScalarValue, Value};
+
+use crate::{Path, ReadDoc};
+
+pub use autosurgeon_derive::DescribeSchema;
+
+/// The layout of a value in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schema {
+    /// Anything at all, for values whose layout isn't known
+    Any,
+    /// A scalar value of the given kind
+    Scalar(ScalarKind),
+    /// A string with exactly this value, such as the name of a unit enum variant
+    Literal(String),
+    /// A counter
+    Counter,
+    /// A text object
+    Text,
+    /// A list every element of which matches the schema
+    List(Box<Schema>),
+    /// A list with exactly one element matching each of the schemas, in order
+    Tuple(Vec<Schema>),
+    /// A map with any keys, the values of which match the schema
+    Map(Box<Schema>),
+    /// A map with the given fields
+    Struct(Vec<Field>),
+    /// Either null or a value matching the schema
+    Nullable(Box<Schema>),
+    /// A value matching any of the schemas, such as one of the variants of an enum
+    OneOf(Vec<Schema>),
+}
+
+/// A field of a [`Schema::Struct`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    name: String,
+    schema: Schema,
+    required: bool,
+}
+
+impl Field {
+    /// A field called `name` which must match `schema`. The field is required unless `schema` is
+    /// [`Schema::Any`].
+    pub fn new<S: Into<String>>(name: S, schema: Schema) -> Self {
+        let required = !matches!(schema, Schema::Any);
+        Self {
+            name: name.into(),
+            schema,
+            required,
+        }
+    }
+
+    /// Allow the field to be missing from the map
+    pub fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+
+    /// The key of the field in the map
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The schema of the value of the field
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Whether it's a mismatch for the field to be missing
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+}
+
+/// The kinds of scalar a [`Schema::Scalar`] can require
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScalarKind {
+    /// A string
+    Str,
+    /// A signed or unsigned integer
+    Int,
+    /// A floating point number
+    F64,
+    /// A boolean
+    Bool,
+    /// A byte array
+    Bytes,
+    /// A timestamp
+    Timestamp,
+}
+
+impl Schema {
+    /// The schema of a variant of an enum with `#[autosurgeon(tag = "...")]`, which is `inner`
+    /// with an extra field `tag` containing `variant`
+    pub fn tagged<S: Into<String>>(tag: &str, variant: S, inner: Schema) -> Self {
+        let tag = Field::new(tag, Schema::Literal(variant.into()));
+        match inner {
+            Schema::Struct(mut fields) => {
+                fields.insert(0, tag);
+                Schema::Struct(fields)
+            }
+            _ => Schema::Struct(vec![tag]),
+        }
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => write!(f, "anything"),
+            Self::Scalar(kind) => write!(f, "{}", kind),
+            Self::Literal(s) => write!(f, "{:?}", s),
+            Self::Counter => write!(f, "a counter"),
+            Self::Text => write!(f, "a text object"),
+            Self::List(_) => write!(f, "a list"),
+            Self::Tuple(elems) => write!(f, "a list of {} elements", elems.len()),
+            Self::Map(_) => write!(f, "a map"),
+            Self::Struct(fields) => match fields.as_slice() {
+                [only] => write!(f, "a map with the key {:?}", only.name),
+                _ => write!(f, "a map"),
+            },
+            Self::Nullable(inner) => write!(f, "null or {}", inner),
+            Self::OneOf(options) => {
+                write!(f, "one of (")?;
+                for (i, option) in options.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", option)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for ScalarKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Self::Str => "a string",
+            Self::Int => "an integer",
+            Self::F64 => "a float",
+            Self::Bool => "a boolean",
+            Self::Bytes => "bytes",
+            Self::Timestamp => "a timestamp",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// A type which can describe its layout in a document
+///
+/// This is implemented for the types in the standard library which implement `Reconcile` and
+/// `Hydrate`, and can be derived for your own types with `#[derive(DescribeSchema)]`.
+pub trait DescribeSchema {
+    /// The layout of values of this type
+    fn schema() -> Schema;
+}
+
+/// A part of a document which doesn't match a [`Schema`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{}: expected {expected}, found {found}", DisplayPath(.path))]
+pub struct Mismatch {
+    path: Path,
+    expected: String,
+    found: String,
+}
+
+impl Mismatch {
+    /// The path from the object which was validated to the value which didn't match
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A description of the schema the value should have matched
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    /// A description of the value which was found, `"nothing"` if the value was missing
+    pub fn found(&self) -> &str {
+        &self.found
+    }
+}
+
+/// Displays the empty path as `/` rather than as an empty string
+struct DisplayPath<'a>(&'a Path);
+
+impl<'a> fmt::Display for DisplayPath<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            write!(f, "/")
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+/// Check that the root of `doc` matches `schema`, returning every mismatch if it doesn't
+pub fn validate<D: ReadDoc>(doc: &D, schema: &Schema) -> Result<(), Vec<Mismatch>> {
+    validate_at(doc, &am::ROOT, schema)
+}
+
+/// Check that the object `obj` in `doc` matches `schema`, returning every mismatch if it doesn't
+///
+/// The paths of the mismatches are relative to `obj`.
+pub fn validate_at<D: ReadDoc>(
+    doc: &D,
+    obj: &am::ObjId,
+    schema: &Schema,
+) -> Result<(), Vec<Mismatch>> {
+    let value = doc
+        .object_type(obj)
+        .map(|obj_type| (Value::Object(obj_type), obj.clone()));
+    let mut mismatches = Vec::new();
+    check(doc, value, schema, &Path::new(), &mut mismatches);
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches)
+    }
+}
+
+fn check<D: ReadDoc>(
+    doc: &D,
+    value: Option<(Value<'_>, am::ObjId)>,
+    schema: &Schema,
+    path: &Path,
+    mismatches: &mut Vec<Mismatch>,
+) {
+    let mismatch = |path: &Path, found: String| Mismatch {
+        path: path.clone(),
+        expected: schema.to_string(),
+        found,
+    };
+    let (value, id) = match (schema, value) {
+        (Schema::Any, _) => return,
+        (_, None) => return mismatches.push(mismatch(path, "nothing".to_string())),
+        (Schema::Nullable(_), Some((Value::Scalar(s), _))) if s.is_null() => return,
+        (Schema::Nullable(inner), value) => return check(doc, value, inner, path, mismatches),
+        (_, Some(value)) => value,
+    };
+    match (schema, &value) {
+        (Schema::Scalar(kind), Value::Scalar(s)) if scalar_matches(*kind, s.as_ref()) => {}
+        (Schema::Literal(expected), Value::Scalar(s)) if s.to_str() == Some(&**expected) => {}
+        (Schema::Counter, Value::Scalar(s))
+            if matches!(
+                s.as_ref(),
+                ScalarValue::Counter(_) | ScalarValue::Int(_) | ScalarValue::Uint(_)
+            ) => {}
+        (Schema::Text, Value::Object(ObjType::Text)) => {}
+        (Schema::List(elem), Value::Object(ObjType::List)) => {
+            for i in 0..doc.length(&id) {
+                check(doc, get(doc, &id, i), elem, &child(path, i), mismatches);
+            }
+        }
+        (Schema::Tuple(elems), Value::Object(ObjType::List)) if doc.length(&id) == elems.len() => {
+            for (i, elem) in elems.iter().enumerate() {
+                check(doc, get(doc, &id, i), elem, &child(path, i), mismatches);
+            }
+        }
+        (Schema::Map(value_schema), Value::Object(ObjType::Map | ObjType::Table)) => {
+            for key in doc.keys(&id) {
+                let value = get(doc, &id, key.as_str());
+                check(doc, value, value_schema, &child(path, key), mismatches);
+            }
+        }
+        (Schema::Struct(fields), Value::Object(ObjType::Map | ObjType::Table)) => {
+            for field in fields {
+                let value = get(doc, &id, field.name.as_str());
+                if value.is_some() || field.required {
+                    let path = child(path, field.name.as_str());
+                    check(doc, value, &field.schema, &path, mismatches);
+                }
+            }
+        }
+        (Schema::OneOf(options), _) => {
+            let matches_option = options.iter().any(|option| {
+                let mut option_mismatches = Vec::new();
+                check(
+                    doc,
+                    Some((value.clone(), id.clone())),
+                    option,
+                    path,
+                    &mut option_mismatches,
+                );
+                option_mismatches.is_empty()
+            });
+            if !matches_option {
+                mismatches.push(mismatch(path, describe(&value)));
+            }
+        }
+        _ => mismatches.push(mismatch(path, describe(&value))),
+    }
+}
+
+fn child<P: Into<am::Prop>>(path: &Path, prop: P) -> Path {
+    let mut child = path.clone();
+    child.push(prop);
+    child
+}
+
+fn get<D: ReadDoc, P: Into<am::Prop>>(
+    doc: &D,
+    obj: &am::ObjId,
+    prop: P,
+) -> Option<(Value<'static>, am::ObjId)> {
+    doc.get(obj, prop)
+        .ok()
+        .flatten()
+        .map(|(value, id)| (value.to_owned(), id))
+}
+
+fn scalar_matches(kind: ScalarKind, value: &ScalarValue) -> bool {
+    matches!(
+        (kind, value),
+        (ScalarKind::Str, ScalarValue::Str(_))
+            | (ScalarKind::Int, ScalarValue::Int(_) | ScalarValue::Uint(_))
+            | (ScalarKind::F64, ScalarValue::F64(_))
+            | (ScalarKind::Bool, ScalarValue::Boolean(_))
+            | (ScalarKind::Bytes, ScalarValue::Bytes(_))
+            | (ScalarKind::Timestamp, ScalarValue::Timestamp(_))
+    )
+}
+
+fn describe(value: &Value<'_>) -> String {
+    match value {
+        Value::Object(ObjType::Map | ObjType::Table) => "a map",
+        Value::Object(ObjType::List) => "a list",
+        Value::Object(ObjType::Text) => "a text object",
+        // Object types added in versions of automerge newer than this crate knows about
+        #[allow(unreachable_patterns)]
+        Value::Object(_) => "an unknown object",
+        Value::Scalar(s) => match s.as_ref() {
+            ScalarValue::Str(s) => return format!("the string {:?}", s.as_str()),
+            ScalarValue::Int(_) | ScalarValue::Uint(_) => "an integer",
+            ScalarValue::F64(_) => "a float",
+            ScalarValue::Counter(_) => "a counter",
+            ScalarValue::Timestamp(_) => "a timestamp",
+            ScalarValue::Boolean(_) => "a boolean",
+            ScalarValue::Bytes(_) => "bytes",
+            ScalarValue::Null => "null",
+            ScalarValue::Unknown { .. } => "an unknown scalar",
+        },
+    }
+    .to_string()
+}
+
+macro_rules! scalar_schema {
+    ($kind:ident, $($ty:ty),*) => {
+        $(
+            impl DescribeSchema for $ty {
+                fn schema() -> Schema {
+                    Schema::Scalar(ScalarKind::$kind)
+                }
+            }
+        )*
+    };
+}
+
+scalar_schema!(
+    Str,
+    String,
+    str,
+    char,
+    std::path::PathBuf,
+    std::ffi::OsString
+);
+scalar_schema!(Int, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+scalar_schema!(F64, f32, f64);
+scalar_schema!(Bool, bool);
+
+macro_rules! forward_schema {
+    ($($ty:ident),*) => {
+        $(
+            impl<T: DescribeSchema + ?Sized> DescribeSchema for $ty<T> {
+                fn schema() -> Schema {
+                    T::schema()
+                }
+            }
+        )*
+    };
+}
+
+use std::{rc::Rc, sync::Arc};
+forward_schema!(Box, Rc, Arc);
+
+impl<T: DescribeSchema + ?Sized> DescribeSchema for &T {
+    fn schema() -> Schema {
+        T::schema()
+    }
+}
+
+impl<B: DescribeSchema + ToOwned + ?Sized> DescribeSchema for Cow<'_, B> {
+    fn schema() -> Schema {
+        B::schema()
+    }
+}
+
+impl<T: DescribeSchema> DescribeSchema for Option<T> {
+    fn schema() -> Schema {
+        Schema::Nullable(Box::new(T::schema()))
+    }
+}
+
+macro_rules! list_schema {
+    ($($ty:ty),*) => {
+        $(
+            impl<T: DescribeSchema> DescribeSchema for $ty {
+                fn schema() -> Schema {
+                    Schema::List(Box::new(T::schema()))
+                }
+            }
+        )*
+    };
+}
+
+list_schema!(
+    [T],
+    Vec<T>,
+    std::collections::VecDeque<T>,
+    std::collections::LinkedList<T>
+);
+
+impl<T: DescribeSchema, const N: usize> DescribeSchema for [T; N] {
+    fn schema() -> Schema {
+        Schema::Tuple((0..N).map(|_| T::schema()).collect())
+    }
+}
+
+impl<K, V: DescribeSchema, S> DescribeSchema for std::collections::HashMap<K, V, S> {
+    fn schema() -> Schema {
+        Schema::Map(Box::new(V::schema()))
+    }
+}
+
+impl<K, V: DescribeSchema> DescribeSchema for std::collections::BTreeMap<K, V> {
+    fn schema() -> Schema {
+        Schema::Map(Box::new(V::schema()))
+    }
+}
+
+impl DescribeSchema for crate::Text {
+    fn schema() -> Schema {
+        Schema::Text
+    }
+}
+
+impl DescribeSchema for crate::Counter {
+    fn schema() -> Schema {
+        Schema::Counter
+    }
+}
+
+impl<T> DescribeSchema for std::marker::PhantomData<T> {
+    fn schema() -> Schema {
+        Schema::Any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::transaction::Transactable;
+    use std::collections::HashMap;
+
+    use super::{validate, DescribeSchema, Field, ScalarKind, Schema};
+    use crate::path;
+
+    #[test]
+    fn nested_mismatches_are_all_reported() {
+        let schema = Schema::Struct(vec![
+            Field::new("scores", HashMap::<String, Vec<u64>>::schema()),
+            Field::new("owner", String::schema()),
+            Field::new("note", Option::<String>::schema()),
+        ]);
+        let mut doc = automerge::AutoCommit::new();
+        let scores = doc
+            .put_object(automerge::ROOT, "scores", automerge::ObjType::Map)
+            .unwrap();
+        let alice = doc
+            .put_object(&scores, "alice", automerge::ObjType::List)
+            .unwrap();
+        doc.insert(&alice, 0, 1_u64).unwrap();
+        doc.insert(&alice, 1, "two").unwrap();
+        doc.put(&scores, "bob", 3_u64).unwrap();
+
+        let mismatches = validate(&doc, &schema).unwrap_err();
+        let found = mismatches
+            .iter()
+            .map(|m| (m.path().clone(), m.expected().to_string(), m.found()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    path!["scores", "alice", 1],
+                    "an integer".to_string(),
+                    "the string \"two\""
+                ),
+                (path!["scores", "bob"], "a list".to_string(), "an integer"),
+                (path!["owner"], "a string".to_string(), "nothing"),
+                (path!["note"], "null or a string".to_string(), "nothing"),
+            ]
+        );
+
+        doc.put(automerge::ROOT, "note", automerge::ScalarValue::Null)
+            .unwrap();
+        assert_eq!(validate(&doc, &schema).unwrap_err().len(), 3);
+    }
+
+    #[test]
+    fn one_of_matches_any_option() {
+        let schema = Schema::Struct(vec![Field::new(
+            "status",
+            Schema::OneOf(vec![
+                Schema::Literal("done".to_string()),
+                Schema::Scalar(ScalarKind::Int),
+            ]),
+        )]);
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(automerge::ROOT, "status", "done").unwrap();
+        assert!(validate(&doc, &schema).is_ok());
+        doc.put(automerge::ROOT, "status", 3).unwrap();
+        assert!(validate(&doc, &schema).is_ok());
+        doc.put(automerge::ROOT, "status", "todo").unwrap();
+        let mismatches = validate(&doc, &schema).unwrap_err();
+        assert_eq!(
+            mismatches[0].to_string(),
+            "/status: expected one of (\"done\", an integer), found the string \"todo\""
+        );
+    }
+}
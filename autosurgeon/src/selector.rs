@@ -0,0 +1,131 @@
+use std::{collections::BTreeSet, marker::PhantomData};
+
+use crate::{HydrateError, InstrumentedReadDoc, ReadAccess, ReadDoc};
+
+/// A memoized query over a document
+///
+/// A `Selector` wraps a function which hydrates some value from a document. The first call to
+/// [`Selector::get`] runs the function through an [`InstrumentedReadDoc`] and caches both the
+/// result and the parts of the document it read. Subsequent calls return the cached value until
+/// [`Selector::apply_patches`] is given a patch which touches one of those parts.
+///
+/// ```rust
+/// # use autosurgeon::{hydrate_prop, reconcile_prop, Selector};
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile_prop(&mut doc, automerge::ROOT, "title", "Groceries").unwrap();
+/// reconcile_prop(&mut doc, automerge::ROOT, "items", vec!["eggs"]).unwrap();
+///
+/// let mut title: Selector<_, String, _> =
+///     Selector::new(|doc| hydrate_prop(doc, &automerge::ROOT, "title"));
+/// assert_eq!(title.get(&doc).unwrap(), "Groceries");
+///
+/// // Changing the items doesn't affect the title, so it isn't recomputed
+/// doc.update_diff_cursor();
+/// reconcile_prop(&mut doc, automerge::ROOT, "items", vec!["eggs", "milk"]).unwrap();
+/// assert!(!title.apply_patches(&doc.diff_incremental()));
+///
+/// reconcile_prop(&mut doc, automerge::ROOT, "title", "Shopping").unwrap();
+/// assert!(title.apply_patches(&doc.diff_incremental()));
+/// assert_eq!(title.get(&doc).unwrap(), "Shopping");
+/// ```
+///
+/// The selector relies on being given every patch applied to the document since the value was
+/// cached. If you can't guarantee that, call [`Selector::invalidate`].
+pub struct Selector<D: ?Sized, T, F> {
+    select: F,
+    cached: Option<(T, BTreeSet<ReadAccess>)>,
+    _doc: PhantomData<fn(&D)>,
+}
+
+impl<D, T, F> Selector<D, T, F>
+where
+    D: ReadDoc + ?Sized,
+    F: for<'a> Fn(&InstrumentedReadDoc<'a, D>) -> Result<T, HydrateError>,
+{
+    pub fn new(select: F) -> Self {
+        Self {
+            select,
+            cached: None,
+            _doc: PhantomData,
+        }
+    }
+
+    /// The value of the selector, computing it from `doc` if it isn't cached
+    pub fn get(&mut self, doc: &D) -> Result<&T, HydrateError> {
+        if self.cached.is_none() {
+            let instrumented = InstrumentedReadDoc::new(doc);
+            let value = (self.select)(&instrumented)?;
+            self.cached = Some((value, instrumented.into_accesses()));
+        }
+        Ok(&self.cached.as_ref().unwrap().0)
+    }
+
+    /// Discard the cached value if any of `patches` could have changed it
+    ///
+    /// Returns `true` if the cached value was discarded
+    pub fn apply_patches<'p, I: IntoIterator<Item = &'p automerge::Patch>>(
+        &mut self,
+        patches: I,
+    ) -> bool {
+        let Some((_, accesses)) = &self.cached else {
+            return false;
+        };
+        let affected = patches
+            .into_iter()
+            .any(|patch| accesses.iter().any(|a| a.affected_by(patch)));
+        if affected {
+            self.cached = None;
+        }
+        affected
+    }
+
+    /// Discard the cached value
+    pub fn invalidate(&mut self) {
+        self.cached = None;
+    }
+
+    pub fn is_cached(&self) -> bool {
+        self.cached.is_some()
+    }
+
+    /// The parts of the document the cached value was hydrated from, if there is one
+    pub fn accesses(&self) -> Option<&BTreeSet<ReadAccess>> {
+        self.cached.as_ref().map(|(_, accesses)| accesses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::{transaction::Transactable, ActorId, ReadDoc};
+
+    use super::Selector;
+    use crate::{hydrate_prop, reconcile_prop};
+
+    #[test]
+    fn recomputes_when_read_objects_change() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "todos", vec!["write tests"]).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "owner", "alice").unwrap();
+        doc.update_diff_cursor();
+
+        let mut todos: Selector<_, Vec<String>, _> =
+            Selector::new(|doc| hydrate_prop(doc, &automerge::ROOT, "todos"));
+        assert_eq!(todos.get(&doc).unwrap(), &vec!["write tests".to_string()]);
+
+        reconcile_prop(&mut doc, automerge::ROOT, "owner", "bob").unwrap();
+        assert!(!todos.apply_patches(&doc.diff_incremental()));
+        assert!(todos.is_cached());
+
+        // A change from another peer to an element of the list
+        let mut other = doc.fork().with_actor(ActorId::random());
+        let list = other.get(&automerge::ROOT, "todos").unwrap().unwrap().1;
+        other.insert(&list, 1, "ship it").unwrap();
+        doc.merge(&mut other).unwrap();
+        assert!(todos.apply_patches(&doc.diff_incremental()));
+        assert!(!todos.is_cached());
+        assert_eq!(
+            todos.get(&doc).unwrap(),
+            &vec!["write tests".to_string(), "ship it".to_string()]
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Counter, MaybeMissing, Text};
+
+// `Text` and `Counter` serialize as their current value. The information about where in a
+// document they were hydrated from is not serialized, so a deserialized value reconciles as if it
+// were created with `with_value`.
+
+impl Serialize for Text {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Text {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Text::with_value)
+    }
+}
+
+impl Serialize for Counter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.value())
+    }
+}
+
+impl<'de> Deserialize<'de> for Counter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        i64::deserialize(deserializer).map(Counter::with_value)
+    }
+}
+
+// `MaybeMissing` serializes `Present` values transparently and `Missing` as a unit. To round trip
+// a `Missing` field skip it when serializing and default it when deserializing, i.e.
+// `#[serde(default, skip_serializing_if = "MaybeMissing::is_missing")]`.
+
+impl<T: Serialize> Serialize for MaybeMissing<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            MaybeMissing::Missing => serializer.serialize_unit(),
+            MaybeMissing::Present(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for MaybeMissing<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(MaybeMissing::Present)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{hydrate_prop, reconcile_prop, Counter, MaybeMissing, Text};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Snapshot {
+        title: Text,
+        views: Counter,
+        #[serde(default, skip_serializing_if = "MaybeMissing::is_missing")]
+        subtitle: MaybeMissing<Option<String>>,
+    }
+
+    #[test]
+    fn round_trip_through_json() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "title",
+            Text::with_value("hello"),
+        )
+        .unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "views", Counter::with_value(3)).unwrap();
+        let snapshot = Snapshot {
+            title: hydrate_prop(&doc, &automerge::ROOT, "title").unwrap(),
+            views: hydrate_prop(&doc, &automerge::ROOT, "views").unwrap(),
+            subtitle: hydrate_prop(&doc, &automerge::ROOT, "subtitle").unwrap(),
+        };
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(json, r#"{"title":"hello","views":3}"#);
+        let deserialized: Snapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, snapshot);
+
+        let deserialized: Snapshot =
+            serde_json::from_str(r#"{"title":"hello","views":3,"subtitle":null}"#).unwrap();
+        assert_eq!(deserialized.subtitle, MaybeMissing::Present(None));
+    }
+}
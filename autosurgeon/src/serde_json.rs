@@ -0,0 +1,190 @@
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    hydrate::{map::hydrate_map_impl, Unexpected},
+    reconcile::{map::reconcile_map_impl, NoKey},
+    Hydrate, HydrateError, ReadDoc, Reconcile, Reconciler,
+};
+
+impl Reconcile for Value {
+    type Key<'a> = NoKey;
+
+    fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        match self {
+            Value::Null => reconciler.none(),
+            Value::Bool(b) => reconciler.boolean(*b),
+            Value::Number(n) => n.reconcile(reconciler),
+            Value::String(s) => reconciler.str(s),
+            Value::Array(items) => items.reconcile(reconciler),
+            Value::Object(entries) => entries.reconcile(reconciler),
+        }
+    }
+}
+
+impl Reconcile for Number {
+    type Key<'a> = NoKey;
+
+    fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        if let Some(i) = self.as_i64() {
+            reconciler.i64(i)
+        } else if let Some(u) = self.as_u64() {
+            reconciler.u64(u)
+        } else {
+            // Every `Number` is representable as one of i64, u64, or f64
+            reconciler.f64(self.as_f64().unwrap_or(f64::NAN))
+        }
+    }
+}
+
+impl Reconcile for Map<String, Value> {
+    type Key<'a> = NoKey;
+
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        reconcile_map_impl(self.iter(), reconciler)
+    }
+}
+
+impl Hydrate for Value {
+    fn hydrate_none() -> Result<Self, HydrateError> {
+        Ok(Value::Null)
+    }
+
+    fn hydrate_bool(b: bool) -> Result<Self, HydrateError> {
+        Ok(Value::Bool(b))
+    }
+
+    fn hydrate_int(i: i64) -> Result<Self, HydrateError> {
+        Ok(Value::Number(i.into()))
+    }
+
+    fn hydrate_uint(u: u64) -> Result<Self, HydrateError> {
+        Ok(Value::Number(u.into()))
+    }
+
+    fn hydrate_f64(f: f64) -> Result<Self, HydrateError> {
+        Number::from_f64(f)
+            .map(Value::Number)
+            .ok_or_else(|| HydrateError::unexpected("a finite number", f.to_string()))
+    }
+
+    fn hydrate_counter(c: i64) -> Result<Self, HydrateError> {
+        Ok(Value::Number(c.into()))
+    }
+
+    fn hydrate_timestamp(t: i64) -> Result<Self, HydrateError> {
+        Ok(Value::Number(t.into()))
+    }
+
+    fn hydrate_string(s: &'_ str) -> Result<Self, HydrateError> {
+        Ok(Value::String(s.to_string()))
+    }
+
+    fn hydrate_text<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        Ok(Value::String(doc.text(obj)?))
+    }
+
+    fn hydrate_seq<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        Vec::hydrate_seq(doc, obj).map(Value::Array)
+    }
+
+    fn hydrate_map<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        Map::hydrate_map(doc, obj).map(Value::Object)
+    }
+}
+
+impl Hydrate for Number {
+    fn hydrate_int(i: i64) -> Result<Self, HydrateError> {
+        Ok(i.into())
+    }
+
+    fn hydrate_uint(u: u64) -> Result<Self, HydrateError> {
+        Ok(u.into())
+    }
+
+    fn hydrate_f64(f: f64) -> Result<Self, HydrateError> {
+        Number::from_f64(f)
+            .ok_or_else(|| HydrateError::unexpected("a finite number", f.to_string()))
+    }
+
+    fn hydrate_counter(c: i64) -> Result<Self, HydrateError> {
+        Ok(c.into())
+    }
+
+    fn hydrate_timestamp(t: i64) -> Result<Self, HydrateError> {
+        Ok(t.into())
+    }
+}
+
+impl Hydrate for Map<String, Value> {
+    fn hydrate_map<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        hydrate_map_impl(doc, obj, |k| Ok(k.to_string()))
+    }
+
+    fn hydrate_none() -> Result<Self, HydrateError> {
+        Err(HydrateError::Unexpected(Unexpected::None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::transaction::Transactable;
+    use automerge_test::{assert_doc, list, map};
+    use serde_json::{json, Value};
+
+    use crate::{hydrate_prop, reconcile_prop, Text};
+
+    #[test]
+    fn round_trip_json() {
+        let value = json!({
+            "name": "Ridcully",
+            "age": 64,
+            "height": 1.8,
+            "big": u64::MAX,
+            "wizard": true,
+            "hat": null,
+            "staff": ["oak", 1, {"magic": false}],
+        });
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "person", &value).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "person" => { map! {
+                    "name" => { "Ridcully" },
+                    "age" => { 64 },
+                    "height" => { 1.8 },
+                    "big" => { u64::MAX },
+                    "wizard" => { true },
+                    "hat" => { automerge::ScalarValue::Null },
+                    "staff" => { list! { { "oak" }, { 1 }, { map! { "magic" => { false } } } } },
+                }}
+            }
+        );
+        let hydrated: Value = hydrate_prop(&doc, &automerge::ROOT, "person").unwrap();
+        assert_eq!(hydrated, value);
+    }
+
+    #[test]
+    fn reconcile_json_removes_stale_keys() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "v", json!({"a": 1, "b": 2})).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "v", json!({"a": 3})).unwrap();
+        let hydrated: Value = hydrate_prop(&doc, &automerge::ROOT, "v").unwrap();
+        assert_eq!(hydrated, json!({"a": 3}));
+    }
+
+    #[test]
+    fn hydrate_automerge_types_as_json() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "text", Text::with_value("hi")).unwrap();
+        doc.put(automerge::ROOT, "count", automerge::ScalarValue::counter(3))
+            .unwrap();
+        doc.put(automerge::ROOT, "bytes", vec![1_u8, 2]).unwrap();
+
+        let text: Value = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        assert_eq!(text, json!("hi"));
+        let count: Value = hydrate_prop(&doc, &automerge::ROOT, "count").unwrap();
+        assert_eq!(count, json!(3));
+        assert!(hydrate_prop::<_, Value, _, _>(&doc, &automerge::ROOT, "bytes").is_err());
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    reconcile::NoKey, FromPropKey, Hydrate, HydrateError, PropKey, ReadDoc, Reconcile, Reconciler,
+};
+
+/// A collection of rows, each identified by a unique row ID
+///
+/// A `Table` is stored as a map from row ID (encoded using [`PropKey`]) to row, where each row is
+/// typically a struct. Because every row lives under its own key, rows which are inserted or
+/// removed concurrently merge without interfering with each other, as do concurrent changes to
+/// different rows. If the row type has a key (see [`Reconcile::key`]) and a row is replaced by a
+/// row with a different key then the old row object is replaced rather than being updated in
+/// place.
+///
+/// Concurrent inserts only merge if the rows have different IDs, so row IDs should be generated
+/// in a way which makes collisions unlikely, for example by using a random
+/// [`Uuid`](https://docs.rs/uuid) (with the `uuid` feature enabled) or some other unique value.
+///
+/// `Table` hydrates from both maps and legacy [`automerge::ObjType::Table`] objects. New tables
+/// are written as maps.
+///
+/// # Example
+///
+/// ```rust
+/// # use autosurgeon::{hydrate, reconcile, Hydrate, Reconcile, Table};
+/// #[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+/// struct Task {
+///     title: String,
+///     done: bool,
+/// }
+///
+/// #[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+/// struct Project {
+///     tasks: Table<String, Task>,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// let mut project = Project { tasks: Table::new() };
+/// project.tasks.insert(
+///     "task-1".to_string(),
+///     Task { title: "Write docs".to_string(), done: false },
+/// );
+/// reconcile(&mut doc, &project).unwrap();
+///
+/// let mut doc2 = doc.fork();
+/// let mut project2: Project = hydrate(&doc2).unwrap();
+/// project2.tasks.insert(
+///     "task-2".to_string(),
+///     Task { title: "Write tests".to_string(), done: false },
+/// );
+/// reconcile(&mut doc2, &project2).unwrap();
+///
+/// project.tasks.get_mut("task-1").unwrap().done = true;
+/// reconcile(&mut doc, &project).unwrap();
+///
+/// doc.merge(&mut doc2).unwrap();
+/// let merged: Project = hydrate(&doc).unwrap();
+/// assert_eq!(merged.tasks.len(), 2);
+/// assert!(merged.tasks.get("task-1").unwrap().done);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table<K, V> {
+    rows: BTreeMap<K, V>,
+}
+
+impl<K, V> Table<K, V> {
+    pub fn new() -> Self {
+        Self {
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// The number of rows in the table
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Iterate over the rows in the table in order of their IDs
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.rows.iter()
+    }
+
+    /// Iterate over mutable references to the rows in the table in order of their IDs
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.rows.iter_mut()
+    }
+}
+
+impl<K: Ord, V> Table<K, V> {
+    /// Insert a row, returning the row it replaced if there was already a row with the same ID
+    pub fn insert(&mut self, id: K, row: V) -> Option<V> {
+        self.rows.insert(id, row)
+    }
+
+    /// Remove a row, returning it if it existed
+    pub fn remove<Q>(&mut self, id: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.rows.remove(id)
+    }
+
+    pub fn get<Q>(&self, id: &Q) -> Option<&V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.rows.get(id)
+    }
+
+    pub fn get_mut<Q>(&mut self, id: &Q) -> Option<&mut V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.rows.get_mut(id)
+    }
+
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.rows.contains_key(id)
+    }
+}
+
+impl<K, V> Default for Table<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for Table<K, V> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self {
+            rows: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<K, V> IntoIterator for Table<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::collections::btree_map::IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+impl<K, V> From<BTreeMap<K, V>> for Table<K, V> {
+    fn from(rows: BTreeMap<K, V>) -> Self {
+        Self { rows }
+    }
+}
+
+impl<K, V> From<Table<K, V>> for BTreeMap<K, V> {
+    fn from(table: Table<K, V>) -> Self {
+        table.rows
+    }
+}
+
+impl<K: PropKey, V: Reconcile> Reconcile for Table<K, V> {
+    type Key<'a> = NoKey;
+
+    fn reconcile<R: Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        crate::reconcile::map::reconcile_map_impl(
+            self.rows.iter().map(|(k, v)| (k.to_prop_key(), v)),
+            reconciler,
+        )
+    }
+}
+
+impl<K: FromPropKey + Ord, V: Hydrate> Hydrate for Table<K, V> {
+    fn hydrate_map<D: ReadDoc>(doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        crate::hydrate::map::hydrate_map_impl(doc, obj, K::from_prop_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::ActorId;
+    use automerge_test::{assert_doc, map};
+
+    use super::Table;
+    use crate::{hydrate_prop, reconcile_prop};
+
+    #[test]
+    fn round_trip() {
+        let mut doc = automerge::AutoCommit::new();
+        let table: Table<u64, String> = [(2, "two".to_string()), (1, "one".to_string())]
+            .into_iter()
+            .collect();
+        reconcile_prop(&mut doc, automerge::ROOT, "table", &table).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "table" => { map! {
+                    "1" => { "one" },
+                    "2" => { "two" },
+                }}
+            }
+        );
+        let hydrated: Table<u64, String> = hydrate_prop(&doc, &automerge::ROOT, "table").unwrap();
+        assert_eq!(hydrated, table);
+    }
+
+    #[test]
+    fn concurrent_inserts_and_removes_merge() {
+        let mut table: Table<String, Vec<u64>> = Table::new();
+        table.insert("a".to_string(), vec![1]);
+        table.insert("b".to_string(), vec![2]);
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "table", &table).unwrap();
+
+        let mut doc2 = doc.fork().with_actor(ActorId::random());
+        let mut table2 = table.clone();
+        table2.insert("c".to_string(), vec![3]);
+        table2.get_mut("b").unwrap().push(4);
+        reconcile_prop(&mut doc2, automerge::ROOT, "table", &table2).unwrap();
+
+        table.remove("a");
+        table.insert("d".to_string(), vec![5]);
+        reconcile_prop(&mut doc, automerge::ROOT, "table", &table).unwrap();
+
+        doc.merge(&mut doc2).unwrap();
+        let merged: Table<String, Vec<u64>> =
+            hydrate_prop(&doc, &automerge::ROOT, "table").unwrap();
+        let expected: Table<String, Vec<u64>> = [
+            ("b".to_string(), vec![2, 4]),
+            ("c".to_string(), vec![3]),
+            ("d".to_string(), vec![5]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(merged, expected);
+    }
+}
@@ -1,6 +1,10 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use automerge::marks::ExpandMark;
+
 use crate::{
     reconcile::{NoKey, TextReconciler},
-    Hydrate, ReadDoc, Reconcile,
+    Hydrate, HydrateError, ReadDoc, Reconcile,
 };
 
 /// A type which reconciles to an [`automerge::ObjType::Text`]
@@ -12,6 +16,12 @@ use crate::{
 ///
 /// **important** Attempting to reconcile this struct with a document whose heads have changed
 /// since the struct was rehydrated will throw a [`crate::reconcile::ReconcileError::StaleHeads`] error.
+/// Use [`Text::rebase`] to update the pending edits to apply to the current state of the
+/// document first.
+///
+/// The text also carries its [`Mark`]s (formatting spans such as bold or links), see
+/// [`Text::marks`], [`Text::add_mark`] and [`Text::remove_mark`]. Marks move along with the text
+/// as it is spliced.
 ///
 /// # Example
 ///
@@ -49,7 +59,14 @@ use crate::{
 /// assert_eq!(quote.text.as_str(), "All that glitters is not gold");
 /// ```
 #[derive(Clone)]
-pub struct Text(State);
+pub struct Text {
+    state: State,
+    // The marks on the text as it currently is
+    marks: Vec<Mark>,
+    // The calls to `add_mark` and `remove_mark` to apply once the splices have been applied, with
+    // positions updated to refer to the current text
+    mark_edits: Vec<MarkEdit>,
+}
 
 impl std::default::Default for Text {
     fn default() -> Self {
@@ -67,7 +84,11 @@ impl std::fmt::Debug for Text {
 
 impl Text {
     pub fn with_value<S: AsRef<str>>(value: S) -> Text {
-        Self(State::Fresh(value.as_ref().to_string()))
+        Self {
+            state: State::Fresh(value.as_ref().to_string()),
+            marks: Vec::new(),
+            mark_edits: Vec::new(),
+        }
     }
 
     /// Update the value of the `Text`
@@ -76,7 +97,7 @@ impl Text {
     ///
     /// * pos - The index to start the splice at
     /// * del - The number of characters to delete. This can be negative to indicate deleting `del`
-    ///         characters preceding `pos`
+    ///   characters preceding `pos`
     /// * insert - The characters to insert
     ///
     /// The `pos` index uses the same logic as [`String::replace_range`]. This means
@@ -104,21 +125,290 @@ impl Text {
         } else {
             pos
         };
-        match &mut self.0 {
-            State::Fresh(v) => {
-                v.replace_range(start..(start + del.unsigned_abs()), insert.as_ref())
-            }
+        let end = start + del.unsigned_abs();
+        // Automerge indexes text by `char` so we record the edit in those units rather than bytes
+        let value = self.as_str();
+        let char_start = value[..start].chars().count();
+        let char_delete = value[start..end].chars().count();
+        self.move_marks(char_start, char_delete, insert.as_ref().chars().count());
+        match &mut self.state {
+            State::Fresh(v) => v.replace_range(start..end, insert.as_ref()),
             State::Rehydrated { value, edits, .. } => {
-                value.replace_range(start..(start + del.unsigned_abs()), insert.as_ref());
+                value.replace_range(start..end, insert.as_ref());
                 edits.push(Splice {
-                    pos,
-                    delete: del,
+                    pos: char_start,
+                    delete: char_delete,
                     insert: insert.as_ref().to_string(),
                 });
             }
         }
     }
 
+    /// The same as [`Self::splice`] except that `pos` and `del` are measured in `char`s rather
+    /// than bytes
+    ///
+    /// This is the unit automerge uses to index text, and so the unit which [`Cursor`] positions
+    /// are measured in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start or end of the splice is greater than [`Self::len_chars`]
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use autosurgeon::Text;
+    /// let mut value = Text::with_value("día uno");
+    /// value.splice_chars(4, 3, "dos");
+    /// assert_eq!(value.as_str(), "día dos");
+    /// ```
+    pub fn splice_chars<S: AsRef<str>>(&mut self, pos: usize, del: isize, insert: S) {
+        let (start, end) = splice_range(pos, del);
+        let value = self.as_str();
+        let start = byte_offset(value.char_indices().map(|(i, _)| i), value.len(), start);
+        let end = byte_offset(value.char_indices().map(|(i, _)| i), value.len(), end);
+        self.splice(start, (end - start) as isize, insert)
+    }
+
+    /// The same as [`Self::splice`] except that `pos` and `del` are measured in extended grapheme
+    /// clusters rather than bytes
+    ///
+    /// Grapheme clusters are what a user would think of as a single character. For example "é"
+    /// may be made up of two `char`s (an "e" and a combining accent) but is one grapheme.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start or end of the splice is greater than [`Self::len_graphemes`]
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use autosurgeon::Text;
+    /// let mut value = Text::with_value("cafe\u{301} time");
+    /// value.splice_graphemes(0, 4, "tea");
+    /// assert_eq!(value.as_str(), "tea time");
+    /// ```
+    pub fn splice_graphemes<S: AsRef<str>>(&mut self, pos: usize, del: isize, insert: S) {
+        let (start, end) = splice_range(pos, del);
+        let value = self.as_str();
+        let start = byte_offset(
+            value.grapheme_indices(true).map(|(i, _)| i),
+            value.len(),
+            start,
+        );
+        let end = byte_offset(
+            value.grapheme_indices(true).map(|(i, _)| i),
+            value.len(),
+            end,
+        );
+        self.splice(start, (end - start) as isize, insert)
+    }
+
+    /// The length of the text in `char`s
+    pub fn len_chars(&self) -> usize {
+        self.as_str().chars().count()
+    }
+
+    /// The length of the text in extended grapheme clusters
+    pub fn len_graphemes(&self) -> usize {
+        self.as_str().graphemes(true).count()
+    }
+
+    /// Create a [`Cursor`] pointing at the `char` index `pos` in this text
+    ///
+    /// `pos` is an index into the current value of this `Text`, including any splices made since
+    /// it was hydrated. The cursor tracks the character at that position, so after concurrent
+    /// changes have been merged you can use [`Self::cursor_position`] on a freshly hydrated `Text`
+    /// to find out where that character has moved to.
+    ///
+    /// `doc` must be the document (or a descendant of the document) this text was hydrated from.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CursorError::NotInDocument`] if this text was not hydrated from a document, or if
+    /// `pos` points at a character which was inserted since it was hydrated (and so does not
+    /// exist in the document yet).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use autosurgeon::{hydrate_prop, reconcile_prop, Text};
+    /// let mut doc = automerge::AutoCommit::new();
+    /// reconcile_prop(&mut doc, automerge::ROOT, "text", Text::with_value("world")).unwrap();
+    ///
+    /// let text: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+    /// let cursor = text.cursor(&doc, 0).unwrap();
+    ///
+    /// let mut text: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+    /// text.splice_chars(0, 0, "hello ");
+    /// reconcile_prop(&mut doc, automerge::ROOT, "text", &text).unwrap();
+    ///
+    /// let text: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+    /// assert_eq!(text.cursor_position(&doc, &cursor).unwrap(), 6);
+    /// ```
+    pub fn cursor<D: ReadDoc>(&self, doc: &D, pos: usize) -> Result<Cursor, CursorError> {
+        let State::Rehydrated {
+            edits,
+            from_heads,
+            obj,
+            ..
+        } = &self.state
+        else {
+            return Err(CursorError::NotInDocument);
+        };
+        let mut pos = pos;
+        for edit in edits.iter().rev() {
+            let inserted = edit.insert.chars().count();
+            if pos >= edit.pos + inserted {
+                pos = pos - inserted + edit.delete;
+            } else if pos >= edit.pos {
+                return Err(CursorError::NotInDocument);
+            }
+        }
+        Ok(Cursor(doc.get_cursor(
+            obj,
+            pos,
+            at_heads(doc, from_heads),
+        )?))
+    }
+
+    /// Find the `char` index in this text of the character `cursor` points at
+    ///
+    /// The returned index takes into account any splices made since this text was hydrated. If
+    /// the character the cursor points at has been deleted then the index of the next surviving
+    /// character is returned.
+    pub fn cursor_position<D: ReadDoc>(
+        &self,
+        doc: &D,
+        cursor: &Cursor,
+    ) -> Result<usize, CursorError> {
+        let State::Rehydrated {
+            edits,
+            from_heads,
+            obj,
+            ..
+        } = &self.state
+        else {
+            return Err(CursorError::NotInDocument);
+        };
+        let mut pos = doc.get_cursor_position(obj, &cursor.0, at_heads(doc, from_heads))?;
+        for edit in edits {
+            if pos >= edit.pos + edit.delete {
+                pos = pos - edit.delete + edit.insert.chars().count();
+            } else if pos >= edit.pos {
+                pos = edit.pos + edit.insert.chars().count();
+            }
+        }
+        Ok(pos)
+    }
+
+    /// The splices made to this text since it was hydrated, in the order they were made
+    ///
+    /// These are the edits which will be applied to the document when this text is reconciled.
+    /// A `Text` which was not hydrated from a document has no edits.
+    pub fn edits(&self) -> &[Splice] {
+        match &self.state {
+            State::Fresh(_) => &[],
+            State::Rehydrated { edits, .. } => edits,
+        }
+    }
+
+    /// Transform the edits made since this text was hydrated so that they apply to the current
+    /// state of `doc`
+    ///
+    /// Normally reconciling a `Text` with a document which has changed since the text was
+    /// hydrated fails with [`crate::reconcile::ReconcileError::StaleHeads`], as the positions of
+    /// the edits may no longer refer to the same characters. This method uses the changes made
+    /// to the document since the text was hydrated to work out where each edit should now go,
+    /// and then updates this text so that it looks as though it was hydrated from the current
+    /// state of `doc` and the edits were made to that. This means you can keep a `Text` around as
+    /// a long lived edit buffer and rebase it whenever you want to reconcile it.
+    ///
+    /// Characters which were deleted locally and concurrently are only deleted once. Characters
+    /// which were inserted concurrently within a range which was deleted locally are kept.
+    ///
+    /// This does nothing if the text was not hydrated from a document or the document has not
+    /// changed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use automerge::ActorId;
+    /// # use autosurgeon::{hydrate_prop, reconcile_prop, Text};
+    /// let mut doc = automerge::AutoCommit::new();
+    /// reconcile_prop(&mut doc, automerge::ROOT, "text", Text::with_value("glitters")).unwrap();
+    /// doc.commit();
+    ///
+    /// let mut buffer: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+    /// buffer.splice_chars(8, 0, " is not gold");
+    ///
+    /// // Meanwhile, someone else edits the text
+    /// let mut text: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+    /// text.splice_chars(0, 0, "all that ");
+    /// reconcile_prop(&mut doc, automerge::ROOT, "text", &text).unwrap();
+    /// doc.commit();
+    ///
+    /// buffer.rebase(&doc).unwrap();
+    /// assert_eq!(buffer.as_str(), "all that glitters is not gold");
+    /// reconcile_prop(&mut doc, automerge::ROOT, "text", &buffer).unwrap();
+    /// ```
+    pub fn rebase<D: ReadDoc>(&mut self, doc: &D) -> Result<(), HydrateError> {
+        let State::Rehydrated {
+            value,
+            edits,
+            from_heads,
+            obj,
+        } = &self.state
+        else {
+            return Ok(());
+        };
+        if doc.get_heads() == *from_heads {
+            return Ok(());
+        }
+        let anchors = Anchors {
+            doc,
+            obj,
+            from_heads,
+            len: doc.text_at(obj, from_heads)?.chars().count(),
+        };
+        let old_value = value.clone();
+        let mut rebased = Text::hydrate_text(doc, obj)?;
+        // Apply the changes last first so that the positions of the earlier ones are unaffected
+        for change in net_changes(value, edits, anchors.len).iter().rev() {
+            let pos = anchors.position(change.start)?;
+            for original in (change.start..change.end).rev() {
+                if let Some(current) = anchors.visible_position(original)? {
+                    rebased.splice_chars(current, 1, "");
+                }
+            }
+            if !change.insert.is_empty() {
+                rebased.splice_chars(pos, 0, &change.insert);
+            }
+        }
+        let mark_edits = std::mem::take(&mut self.mark_edits);
+        *self = rebased;
+        // Reapply the pending mark edits, mapping their positions into the rebased text
+        let positions = PositionMap::new(&old_value, self.as_str());
+        for edit in mark_edits {
+            match edit {
+                MarkEdit::Add { mark, expand } => {
+                    let (start, end) = positions.range(mark.start, mark.end);
+                    self.add_mark(Mark { start, end, ..mark }, expand);
+                }
+                MarkEdit::Remove {
+                    name,
+                    start,
+                    end,
+                    expand,
+                } => {
+                    let (start, end) = positions.range(start, end);
+                    self.remove_mark(&name, start, end, expand);
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Update the value of the text field by diffing it with a new string
     ///
     /// This is useful if you can't capture the edits to a text field as they happen (i.e. the
@@ -166,7 +456,7 @@ impl Text {
     /// assert_eq!(start3.content.as_str(), "another day");
     /// ```
     pub fn update<S: AsRef<str>>(&mut self, new_value: S) {
-        match &mut self.0 {
+        match &mut self.state {
             State::Fresh(v) => *v = new_value.as_ref().to_string(),
             State::Rehydrated { value, .. } => {
                 let mut idx = 0;
@@ -193,11 +483,123 @@ impl Text {
     }
 
     pub fn as_str(&self) -> &str {
-        match &self.0 {
+        match &self.state {
             State::Fresh(v) => v,
             State::Rehydrated { value, .. } => value,
         }
     }
+
+    /// The marks (formatting spans such as bold or links) on this text
+    ///
+    /// These are the marks which were on the text when it was hydrated, updated to reflect any
+    /// splices and calls to [`Self::add_mark`] and [`Self::remove_mark`] made since. Marks with
+    /// the same name which overlap are not merged together.
+    pub fn marks(&self) -> &[Mark] {
+        &self.marks
+    }
+
+    /// Add `mark` to the text
+    ///
+    /// `expand` controls whether text which is later inserted at either end of the mark is
+    /// included in it, see [`automerge::marks::ExpandMark`].
+    ///
+    /// # Panics
+    ///
+    /// If the start of the mark is after its end, or the end is greater than [`Self::len_chars`]
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use autosurgeon::{hydrate_prop, reconcile_prop, Mark, Text};
+    /// # use automerge::marks::ExpandMark;
+    /// let mut doc = automerge::AutoCommit::new();
+    /// let mut text = Text::with_value("hello world");
+    /// text.add_mark(Mark::new("bold", true, 0, 5), ExpandMark::After);
+    /// reconcile_prop(&mut doc, automerge::ROOT, "text", &text).unwrap();
+    ///
+    /// let text: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+    /// assert_eq!(text.marks(), &[Mark::new("bold", true, 0, 5)]);
+    /// ```
+    pub fn add_mark(&mut self, mark: Mark, expand: ExpandMark) {
+        assert!(
+            mark.start <= mark.end && mark.end <= self.len_chars(),
+            "mark {}..{} out of bounds of text of length {}",
+            mark.start,
+            mark.end,
+            self.len_chars()
+        );
+        self.marks.push(mark.clone());
+        self.mark_edits.push(MarkEdit::Add { mark, expand });
+    }
+
+    /// Remove the mark called `name` from the `char`s `start..end`
+    ///
+    /// Any mark called `name` which extends outside of `start..end` is shortened or split in two.
+    ///
+    /// # Panics
+    ///
+    /// If `start` is after `end`, or `end` is greater than [`Self::len_chars`]
+    pub fn remove_mark(&mut self, name: &str, start: usize, end: usize, expand: ExpandMark) {
+        assert!(
+            start <= end && end <= self.len_chars(),
+            "range {}..{} out of bounds of text of length {}",
+            start,
+            end,
+            self.len_chars()
+        );
+        let mut marks = Vec::with_capacity(self.marks.len());
+        for mark in self.marks.drain(..) {
+            if mark.name != name || mark.end <= start || mark.start >= end {
+                marks.push(mark);
+                continue;
+            }
+            if mark.start < start {
+                marks.push(Mark {
+                    end: start,
+                    ..mark.clone()
+                });
+            }
+            if mark.end > end {
+                marks.push(Mark { start: end, ..mark });
+            }
+        }
+        self.marks = marks;
+        self.mark_edits.push(MarkEdit::Remove {
+            name: name.to_string(),
+            start,
+            end,
+            expand,
+        });
+    }
+
+    // Update the positions of the marks and pending mark edits to account for deleting `delete`
+    // chars at `pos` and inserting `insert` chars in their place
+    fn move_marks(&mut self, pos: usize, delete: usize, insert: usize) {
+        let move_range = |start: &mut usize, end: &mut usize| {
+            // Text inserted at the start of a range goes before it, at the end goes after it
+            *start = if *start < pos {
+                *start
+            } else if *start >= pos + delete {
+                *start - delete + insert
+            } else {
+                pos + insert
+            };
+            *end = if *end <= pos {
+                *end
+            } else if *end >= pos + delete {
+                *end - delete + insert
+            } else {
+                pos
+            };
+            *start < *end
+        };
+        self.marks
+            .retain_mut(|mark| move_range(&mut mark.start, &mut mark.end));
+        self.mark_edits.retain_mut(|edit| match edit {
+            MarkEdit::Add { mark, .. } => move_range(&mut mark.start, &mut mark.end),
+            MarkEdit::Remove { start, end, .. } => move_range(start, end),
+        });
+    }
 }
 
 impl<S: AsRef<str>> From<S> for Text {
@@ -214,6 +616,12 @@ impl std::cmp::PartialEq for Text {
 
 impl std::cmp::Eq for Text {}
 
+impl std::hash::Hash for Text {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
 #[derive(Clone)]
 enum State {
     Fresh(String),
@@ -221,24 +629,272 @@ enum State {
         value: String,
         edits: Vec<Splice>,
         from_heads: Vec<automerge::ChangeHash>,
+        obj: automerge::ObjId,
     },
 }
 
+/// A change made to a [`Text`] since it was hydrated, see [`Text::edits`]
+///
+/// Positions are in `char`s, which is what automerge uses to index text, and refer to the text
+/// as it was after all the preceding splices were applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Splice {
+    /// The index at which the splice starts
+    pub pos: usize,
+    /// The number of characters deleted
+    pub delete: usize,
+    /// The text inserted at `pos`
+    pub insert: String,
+}
+
+/// A formatting span on a [`Text`], such as bold or a link
+///
+/// `start` and `end` are indices in `char`s, the mark covers `start..end`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mark {
+    pub name: String,
+    pub value: automerge::ScalarValue,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Mark {
+    pub fn new<S: Into<String>, V: Into<automerge::ScalarValue>>(
+        name: S,
+        value: V,
+        start: usize,
+        end: usize,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            start,
+            end,
+        }
+    }
+}
+
+impl<'a> From<automerge::marks::Mark<'a>> for Mark {
+    fn from(mark: automerge::marks::Mark<'a>) -> Self {
+        Self {
+            name: mark.name().to_string(),
+            value: mark.value().clone(),
+            start: mark.start,
+            end: mark.end,
+        }
+    }
+}
+
 #[derive(Clone)]
-struct Splice {
-    pos: usize,
-    delete: isize,
+enum MarkEdit {
+    Add {
+        mark: Mark,
+        expand: ExpandMark,
+    },
+    Remove {
+        name: String,
+        start: usize,
+        end: usize,
+        expand: ExpandMark,
+    },
+}
+
+impl MarkEdit {
+    fn apply<T: TextReconciler>(&self, t: &mut T) -> Result<(), T::Error> {
+        match self {
+            Self::Add { mark, expand } => t.mark(mark, *expand),
+            Self::Remove {
+                name,
+                start,
+                end,
+                expand,
+            } => t.unmark(name, *start, *end, *expand),
+        }
+    }
+}
+
+// The net effect of a sequence of splices on the original text: the characters `start..end` of
+// the original text are deleted and `insert` is inserted in their place
+struct Change {
+    start: usize,
+    end: usize,
     insert: String,
 }
 
+// Collapse the splices which turned a text of `original_len` chars into `value` into a list of
+// non overlapping changes to the original text, sorted by position
+fn net_changes(value: &str, edits: &[Splice], original_len: usize) -> Vec<Change> {
+    // For each char in `value`, the index of the char in the original text it came from or `None`
+    // if it was inserted by one of the edits
+    let mut origins: Vec<Option<usize>> = (0..original_len).map(Some).collect();
+    for edit in edits {
+        origins.splice(
+            edit.pos..(edit.pos + edit.delete),
+            std::iter::repeat(None).take(edit.insert.chars().count()),
+        );
+    }
+    let mut changes = Vec::new();
+    let mut next = 0;
+    let mut insert = String::new();
+    for (c, origin) in value.chars().zip(origins) {
+        match origin {
+            None => insert.push(c),
+            Some(original) => {
+                if original > next || !insert.is_empty() {
+                    changes.push(Change {
+                        start: next,
+                        end: original,
+                        insert: std::mem::take(&mut insert),
+                    });
+                }
+                next = original + 1;
+            }
+        }
+    }
+    if next < original_len || !insert.is_empty() {
+        changes.push(Change {
+            start: next,
+            end: original_len,
+            insert,
+        });
+    }
+    changes
+}
+
+// Maps indices into a text as at `from_heads` to indices into the current state of the text
+struct Anchors<'a, D> {
+    doc: &'a D,
+    obj: &'a automerge::ObjId,
+    from_heads: &'a [automerge::ChangeHash],
+    len: usize,
+}
+
+impl<'a, D: ReadDoc> Anchors<'a, D> {
+    fn cursor(&self, original: usize) -> Result<automerge::Cursor, automerge::AutomergeError> {
+        self.doc
+            .get_cursor(self.obj, original, Some(self.from_heads))
+    }
+
+    // The current index of the original char `original`, or of the next char which is still
+    // there if it has been deleted. `original` may be the length of the original text, in which
+    // case this is the index just after the last original char.
+    fn position(&self, original: usize) -> Result<usize, automerge::AutomergeError> {
+        if original < self.len {
+            self.doc
+                .get_cursor_position(self.obj, &self.cursor(original)?, None)
+        } else if original == 0 {
+            Ok(0)
+        } else {
+            match self.visible_position(original - 1)? {
+                Some(pos) => Ok(pos + 1),
+                None => self.position(original - 1),
+            }
+        }
+    }
+
+    // The current index of the original char `original`, or `None` if it has been deleted
+    fn visible_position(
+        &self,
+        original: usize,
+    ) -> Result<Option<usize>, automerge::AutomergeError> {
+        let cursor = self.cursor(original)?;
+        let pos = self.doc.get_cursor_position(self.obj, &cursor, None)?;
+        if pos < self.doc.length(self.obj) && self.doc.get_cursor(self.obj, pos, None)? == cursor {
+            Ok(Some(pos))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+// The heads to read `doc` at in order to see the text as it was when it was hydrated. If the
+// document hasn't changed we read the current state, which also includes any uncommitted changes
+// in an open transaction.
+fn at_heads<'a, D: ReadDoc>(
+    doc: &D,
+    from_heads: &'a [automerge::ChangeHash],
+) -> Option<&'a [automerge::ChangeHash]> {
+    if doc.get_heads() == from_heads {
+        None
+    } else {
+        Some(from_heads)
+    }
+}
+
+// Maps `char` indices in one version of a text to indices in another, based on a diff of the two
+struct PositionMap {
+    ops: Vec<similar::DiffOp>,
+    new_len: usize,
+}
+
+impl PositionMap {
+    fn new(old: &str, new: &str) -> Self {
+        let old = old.chars().collect::<Vec<_>>();
+        let new = new.chars().collect::<Vec<_>>();
+        Self {
+            ops: similar::capture_diff_slices(similar::Algorithm::Myers, &old, &new),
+            new_len: new.len(),
+        }
+    }
+
+    // The position of the old char `pos` in the new text. Deleted chars map to the position
+    // where they were deleted.
+    fn position(&self, pos: usize) -> usize {
+        for op in &self.ops {
+            let (tag, old, new) = op.as_tag_tuple();
+            if old.contains(&pos) {
+                return match tag {
+                    similar::DiffTag::Equal => new.start + (pos - old.start),
+                    _ => new.start,
+                };
+            }
+        }
+        self.new_len
+    }
+
+    fn range(&self, start: usize, end: usize) -> (usize, usize) {
+        let start = self.position(start);
+        (start, self.position(end).max(start))
+    }
+}
+
+// Normalise a (pos, del) pair where `del` may be negative into a start and end index
+pub(crate) fn splice_range(pos: usize, del: isize) -> (usize, usize) {
+    if del < 0 {
+        let start = pos.saturating_sub(del.unsigned_abs());
+        (start, start + del.unsigned_abs())
+    } else {
+        (pos, pos + del.unsigned_abs())
+    }
+}
+
+// Convert an index into the units yielded by `offsets` (e.g. chars or graphemes) into a byte
+// offset into a string of length `len`
+fn byte_offset<I: Iterator<Item = usize>>(mut offsets: I, len: usize, index: usize) -> usize {
+    let mut count = 0;
+    loop {
+        match offsets.next() {
+            Some(offset) if count == index => return offset,
+            Some(_) => count += 1,
+            None if count == index => return len,
+            None => panic!("index {} out of bounds of text of length {}", index, count),
+        }
+    }
+}
+
 impl Reconcile for Text {
     type Key<'a> = NoKey;
 
     fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
         let mut t = reconciler.text()?;
-        match &self.0 {
+        match &self.state {
             State::Fresh(v) => {
-                t.splice(0, 0, v)?;
+                // The text may already be in the document, for example because it is the key of
+                // an element of a sequence which matched, in which case inserting it again would
+                // duplicate it
+                if t.current().as_deref() != Some(v.as_str()) {
+                    t.splice(0, 0, v)?;
+                }
             }
             State::Rehydrated {
                 edits, from_heads, ..
@@ -252,11 +908,14 @@ impl Reconcile for Text {
                     .into());
                 } else {
                     for edit in edits {
-                        t.splice(edit.pos, edit.delete, &edit.insert)?;
+                        t.splice(edit.pos, edit.delete as isize, &edit.insert)?;
                     }
                 }
             }
         }
+        for edit in &self.mark_edits {
+            edit.apply(&mut t)?;
+        }
         Ok(())
     }
 }
@@ -267,21 +926,81 @@ impl Hydrate for Text {
         obj: &automerge::ObjId,
     ) -> Result<Self, crate::HydrateError> {
         let value = doc.text(obj)?;
-        Ok(Text(State::Rehydrated {
-            value,
-            edits: Vec::new(),
-            from_heads: doc.get_heads(),
-        }))
+        let marks = doc.marks(obj)?.into_iter().map(Mark::from).collect();
+        Ok(Text {
+            state: State::Rehydrated {
+                value,
+                edits: Vec::new(),
+                from_heads: doc.get_heads(),
+                obj: obj.clone(),
+            },
+            marks,
+            mark_edits: Vec::new(),
+        })
     }
 }
 
+/// A position in a [`Text`] which survives concurrent edits
+///
+/// A `Cursor` points at a particular character rather than an index, so as characters are
+/// inserted and deleted before it the position it refers to moves with them. Create one with
+/// [`Text::cursor`] and find where it currently points with [`Text::cursor_position`].
+///
+/// `Cursor` implements [`Reconcile`] and [`Hydrate`] (as a string) so you can store cursors in
+/// the document alongside the text they point into, e.g. to share the position of each user's
+/// caret.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cursor(automerge::Cursor);
+
+impl From<automerge::Cursor> for Cursor {
+    fn from(c: automerge::Cursor) -> Self {
+        Self(c)
+    }
+}
+
+impl From<Cursor> for automerge::Cursor {
+    fn from(c: Cursor) -> Self {
+        c.0
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Reconcile for Cursor {
+    type Key<'a> = NoKey;
+
+    fn reconcile<R: crate::Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+        self.0.to_string().reconcile(reconciler)
+    }
+}
+
+impl Hydrate for Cursor {
+    fn hydrate_string(s: &'_ str) -> Result<Self, HydrateError> {
+        automerge::Cursor::try_from(s)
+            .map(Cursor)
+            .map_err(|_| HydrateError::unexpected("a cursor", s.to_string()))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CursorError {
+    #[error(transparent)]
+    Automerge(#[from] automerge::AutomergeError),
+    #[error("the position is not in the document")]
+    NotInDocument,
+}
+
 #[cfg(test)]
 mod tests {
-    use automerge::ActorId;
+    use automerge::{marks::ExpandMark, ActorId, ReadDoc};
 
     use crate::{hydrate_prop, reconcile_prop};
 
-    use super::Text;
+    use super::{Cursor, CursorError, Mark, Text};
 
     #[test]
     fn merge_text() {
@@ -318,4 +1037,197 @@ mod tests {
         let text: Text = Text::with_value("hello");
         assert_eq!(text, text);
     }
+
+    #[test]
+    fn splice_multibyte() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "text", Text::with_value("héllo")).unwrap();
+
+        let mut text: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        // "l" is at byte 3 but char 2
+        text.splice(3, 2, "y");
+        text.splice_chars(4, -1, "o!");
+        assert_eq!(text.as_str(), "héyo!");
+        reconcile_prop(&mut doc, automerge::ROOT, "text", &text).unwrap();
+
+        let text: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        assert_eq!(text.as_str(), "héyo!");
+        assert_eq!(text.len_chars(), 5);
+    }
+
+    #[test]
+    fn splice_graphemes() {
+        let mut text = Text::with_value("e\u{301}e\u{301}");
+        assert_eq!(text.len_chars(), 4);
+        assert_eq!(text.len_graphemes(), 2);
+        text.splice_graphemes(1, 1, "a");
+        assert_eq!(text.as_str(), "e\u{301}a");
+    }
+
+    #[test]
+    fn cursor_survives_concurrent_edits() {
+        let mut doc1 = automerge::AutoCommit::new();
+        reconcile_prop(
+            &mut doc1,
+            automerge::ROOT,
+            "text",
+            Text::with_value("glitters"),
+        )
+        .unwrap();
+        let mut doc2 = doc1.fork().with_actor(ActorId::random());
+
+        // Point at the "t" in "glitters" after making an uncommitted local edit
+        let mut text1: Text = hydrate_prop(&doc1, &automerge::ROOT, "text").unwrap();
+        text1.splice_chars(0, 0, "all that ");
+        let cursor = text1.cursor(&doc1, 12).unwrap();
+        assert_eq!(text1.cursor_position(&doc1, &cursor).unwrap(), 12);
+        assert!(matches!(
+            text1.cursor(&doc1, 0),
+            Err(CursorError::NotInDocument)
+        ));
+        reconcile_prop(&mut doc1, automerge::ROOT, "text", &text1).unwrap();
+
+        let mut text2: Text = hydrate_prop(&doc2, &automerge::ROOT, "text").unwrap();
+        text2.splice_chars(0, 2, "");
+        reconcile_prop(&mut doc2, automerge::ROOT, "text", &text2).unwrap();
+
+        doc1.merge(&mut doc2).unwrap();
+        let merged: Text = hydrate_prop(&doc1, &automerge::ROOT, "text").unwrap();
+        assert_eq!(merged.as_str(), "all that itters");
+        assert_eq!(merged.cursor_position(&doc1, &cursor).unwrap(), 10);
+
+        // Cursors can be stored in the document
+        reconcile_prop(&mut doc1, automerge::ROOT, "cursor", &cursor).unwrap();
+        let stored: Cursor = hydrate_prop(&doc1, &automerge::ROOT, "cursor").unwrap();
+        assert_eq!(stored, cursor);
+    }
+
+    #[test]
+    fn rebase_onto_concurrent_changes() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "text",
+            Text::with_value("abcdef"),
+        )
+        .unwrap();
+        let mut other = doc.fork().with_actor(ActorId::random());
+
+        let mut buffer: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        buffer.splice_chars(1, 2, "X"); // aXdef
+        buffer.splice_chars(4, 0, "Y"); // aXdeYf
+        buffer.splice_chars(6, 0, "Z"); // aXdeYfZ
+        assert_eq!(buffer.edits().len(), 3);
+
+        // Concurrently delete "cd" and insert at the start
+        let mut text: Text = hydrate_prop(&other, &automerge::ROOT, "text").unwrap();
+        text.splice_chars(2, 2, "");
+        text.splice_chars(0, 0, "__");
+        reconcile_prop(&mut other, automerge::ROOT, "text", &text).unwrap();
+        doc.merge(&mut other).unwrap();
+
+        assert!(reconcile_prop(&mut doc, automerge::ROOT, "text", &buffer).is_err());
+        buffer.rebase(&doc).unwrap();
+        assert_eq!(buffer.as_str(), "__aXeYfZ");
+        reconcile_prop(&mut doc, automerge::ROOT, "text", &buffer).unwrap();
+
+        let result: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        assert_eq!(result.as_str(), "__aXeYfZ");
+        assert!(result.edits().is_empty());
+    }
+
+    #[test]
+    fn rebase_without_changes_is_a_no_op() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "text", Text::with_value("abc")).unwrap();
+        doc.commit();
+
+        let mut buffer: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        buffer.splice_chars(3, 0, "d");
+        buffer.rebase(&doc).unwrap();
+        assert_eq!(buffer.edits().len(), 1);
+
+        let mut fresh = Text::with_value("abc");
+        fresh.rebase(&doc).unwrap();
+        assert!(fresh.edits().is_empty());
+    }
+
+    #[test]
+    fn marks_round_trip() {
+        let mut doc = automerge::AutoCommit::new();
+        let mut text = Text::with_value("hello world");
+        text.add_mark(Mark::new("bold", true, 0, 5), ExpandMark::After);
+        text.add_mark(
+            Mark::new("link", "https://example.com", 6, 11),
+            ExpandMark::None,
+        );
+        reconcile_prop(&mut doc, automerge::ROOT, "text", &text).unwrap();
+
+        let mut text: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        assert_eq!(
+            text.marks(),
+            &[
+                Mark::new("bold", true, 0, 5),
+                Mark::new("link", "https://example.com", 6, 11),
+            ]
+        );
+
+        text.remove_mark("bold", 1, 3, ExpandMark::After);
+        reconcile_prop(&mut doc, automerge::ROOT, "text", &text).unwrap();
+        let mut marks = doc
+            .marks(doc.get(automerge::ROOT, "text").unwrap().unwrap().1)
+            .unwrap()
+            .into_iter()
+            .map(Mark::from)
+            .collect::<Vec<_>>();
+        marks.sort_by_key(|m| m.start);
+        assert_eq!(
+            marks,
+            vec![
+                Mark::new("bold", true, 0, 1),
+                Mark::new("bold", true, 3, 5),
+                Mark::new("link", "https://example.com", 6, 11),
+            ]
+        );
+    }
+
+    #[test]
+    fn splicing_moves_marks() {
+        let mut text = Text::with_value("hello world");
+        text.add_mark(Mark::new("bold", true, 6, 11), ExpandMark::After);
+        text.splice_chars(0, 0, "oh ");
+        assert_eq!(text.marks(), &[Mark::new("bold", true, 9, 14)]);
+        text.splice_chars(10, 2, "");
+        assert_eq!(text.marks(), &[Mark::new("bold", true, 9, 12)]);
+        text.splice_chars(8, 4, "");
+        assert!(text.marks().is_empty());
+    }
+
+    #[test]
+    fn rebase_keeps_pending_marks() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(
+            &mut doc,
+            automerge::ROOT,
+            "text",
+            Text::with_value("hello world"),
+        )
+        .unwrap();
+        let mut other = doc.fork().with_actor(ActorId::random());
+
+        let mut buffer: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        buffer.add_mark(Mark::new("bold", true, 6, 11), ExpandMark::After);
+
+        let mut text: Text = hydrate_prop(&other, &automerge::ROOT, "text").unwrap();
+        text.splice_chars(0, 0, "oh ");
+        reconcile_prop(&mut other, automerge::ROOT, "text", &text).unwrap();
+        doc.merge(&mut other).unwrap();
+
+        buffer.rebase(&doc).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "text", &buffer).unwrap();
+        let result: Text = hydrate_prop(&doc, &automerge::ROOT, "text").unwrap();
+        assert_eq!(result.as_str(), "oh hello world");
+        assert_eq!(result.marks(), &[Mark::new("bold", true, 9, 14)]);
+    }
 }
@@ -0,0 +1,125 @@
+use automerge::{ScalarValue, Value};
+
+use crate::{Hydrate, HydrateError, Prop, ReadDoc, Text};
+
+/// A read only view of a text object in a document
+///
+/// Hydrating a [`Text`] copies the whole text out of the document, which for a read only use of
+/// a very large text means holding two copies of it. A `TextRef` borrows the document instead
+/// and reads the text on demand, so iterating over its characters with [`TextRef::chars`] or
+/// writing it somewhere with its [`std::fmt::Display`] implementation never allocates a `String`
+/// of the whole text.
+///
+/// Because it borrows the document a `TextRef` can't implement [`Hydrate`], create one with
+/// [`TextRef::hydrate`] instead. Use [`TextRef::to_text`] to get a [`Text`] to edit.
+///
+/// ```rust
+/// # use autosurgeon::{reconcile_prop, Text, TextRef};
+/// let mut doc = automerge::AutoCommit::new();
+/// reconcile_prop(&mut doc, automerge::ROOT, "body", Text::with_value("a long text")).unwrap();
+///
+/// let body = TextRef::hydrate(&doc, &automerge::ROOT, "body").unwrap();
+/// assert_eq!(body.len(), 11);
+/// assert_eq!(body.chars().filter(|c| *c == 't').count(), 2);
+///
+/// let mut out = String::new();
+/// std::fmt::write(&mut out, format_args!("{}", body)).unwrap();
+/// assert_eq!(out, "a long text");
+/// ```
+pub struct TextRef<'a, D> {
+    doc: &'a D,
+    obj: automerge::ObjId,
+}
+
+// Hydrates the ID of a text object, so that we get the same errors as hydrating a `Text`
+struct TextObj(automerge::ObjId);
+
+impl Hydrate for TextObj {
+    fn hydrate_text<D: ReadDoc>(_doc: &D, obj: &automerge::ObjId) -> Result<Self, HydrateError> {
+        Ok(TextObj(obj.clone()))
+    }
+}
+
+impl<'a, D: ReadDoc> TextRef<'a, D> {
+    /// A view of the text object at `prop` of `obj`
+    ///
+    /// This fails in the same way as [`crate::hydrate_prop`] for a [`Text`] if the value at `prop`
+    /// is not a text object.
+    pub fn hydrate<'b, O: AsRef<automerge::ObjId>, P: Into<Prop<'b>>>(
+        doc: &'a D,
+        obj: O,
+        prop: P,
+    ) -> Result<Self, HydrateError> {
+        let TextObj(obj) = crate::hydrate_prop(doc, obj, prop)?;
+        Ok(Self { doc, obj })
+    }
+
+    /// The ID of the text object
+    pub fn obj(&self) -> &automerge::ObjId {
+        &self.obj
+    }
+
+    /// The length of the text in `char`s
+    pub fn len(&self) -> usize {
+        self.doc.length(&self.obj)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The characters of the text
+    pub fn chars(&self) -> impl Iterator<Item = char> + 'a {
+        self.doc
+            .list_range(&self.obj, ..)
+            .filter_map(|item| match item.value {
+                Value::Scalar(s) => match s.as_ref() {
+                    ScalarValue::Str(s) => s.chars().next(),
+                    _ => None,
+                },
+                Value::Object(_) => None,
+            })
+    }
+
+    /// Copy the text out of the document into a [`Text`]
+    pub fn to_text(&self) -> Result<Text, HydrateError> {
+        Text::hydrate_text(self.doc, &self.obj)
+    }
+}
+
+impl<'a, D: ReadDoc> std::fmt::Display for TextRef<'a, D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+        self.chars().try_for_each(|c| f.write_char(c))
+    }
+}
+
+impl<'a, D> std::fmt::Debug for TextRef<'a, D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TextRef").field("obj", &self.obj).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hydrate::Unexpected, reconcile_prop};
+
+    #[test]
+    fn text_ref() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "text", Text::with_value("héllo")).unwrap();
+        reconcile_prop(&mut doc, automerge::ROOT, "string", "hello").unwrap();
+
+        let text = TextRef::hydrate(&doc, &automerge::ROOT, "text").unwrap();
+        assert_eq!(text.len(), 5);
+        assert_eq!(text.chars().collect::<String>(), "héllo");
+        assert_eq!(text.to_text().unwrap().as_str(), "héllo");
+
+        let err = TextRef::hydrate(&doc, &automerge::ROOT, "string").unwrap_err();
+        assert!(matches!(
+            err.without_path(),
+            HydrateError::Unexpected(Unexpected::String)
+        ));
+    }
+}
@@ -0,0 +1,247 @@
+//! Spans and events for the `tracing` feature
+//!
+//! Without the feature each function here just calls its closure (or does nothing), so the
+//! instrumented code is the same as if it weren't instrumented at all.
+
+use crate::{Doc, Prop, ReadDoc};
+
+/// Run `f`, which reconciles into `prop` of `obj` (or into `obj` itself if `prop` is `None`, as
+/// when inserting into a sequence), in a span which records the path being reconciled, the type
+/// of the value currently there, and how many operations `f` made
+#[cfg(feature = "tracing")]
+pub(crate) fn reconcile<D: Doc, R>(
+    doc: &mut D,
+    obj: &automerge::ObjId,
+    prop: Option<&Prop<'_>>,
+    f: impl FnOnce(&mut D) -> R,
+) -> R {
+    let span = tracing::debug_span!(
+        "reconcile",
+        path = %path_of(doc, obj, prop),
+        obj_type = %type_at(doc, obj, prop),
+        ops = tracing::field::Empty,
+    );
+    if span.is_disabled() {
+        return f(doc);
+    }
+    let _entered = span.enter();
+    let before = doc.pending_ops();
+    let result = f(doc);
+    span.record("ops", doc.pending_ops().saturating_sub(before));
+    result
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn reconcile<D: Doc, R>(
+    doc: &mut D,
+    _obj: &automerge::ObjId,
+    _prop: Option<&Prop<'_>>,
+    f: impl FnOnce(&mut D) -> R,
+) -> R {
+    f(doc)
+}
+
+/// Run `f`, which hydrates `obj`, in a span which records the path and type of `obj`
+#[cfg(feature = "tracing")]
+pub(crate) fn hydrate<D: ReadDoc, R>(doc: &D, obj: &automerge::ObjId, f: impl FnOnce() -> R) -> R {
+    let span = tracing::debug_span!(
+        "hydrate",
+        path = %path_of(doc, obj, None),
+        obj_type = %type_at(doc, obj, None),
+    );
+    let _entered = span.enter();
+    f()
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn hydrate<D: ReadDoc, R>(
+    _doc: &D,
+    _obj: &automerge::ObjId,
+    f: impl FnOnce() -> R,
+) -> R {
+    f()
+}
+
+/// Record that the value at `prop` of `obj` is being replaced by a new object of type `expected`
+/// because it is `found`, which is not an object of that type
+#[cfg(feature = "tracing")]
+pub(crate) fn replacing<D: ReadDoc>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: &Prop<'_>,
+    expected: automerge::ObjType,
+    found: &automerge::Value<'_>,
+) {
+    tracing::debug!(
+        path = %path_of(doc, obj, Some(prop)),
+        %expected,
+        %found,
+        "replacing a value with a new object of a different type",
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn replacing<D: ReadDoc>(
+    _doc: &D,
+    _obj: &automerge::ObjId,
+    _prop: &Prop<'_>,
+    _expected: automerge::ObjType,
+    _found: &automerge::Value<'_>,
+) {
+}
+
+/// Record that the object at `prop` of `obj` is being replaced by a new object of the same type
+/// in order to clear the `losers` values it conflicts with
+#[cfg(feature = "tracing")]
+pub(crate) fn clearing_conflicts<D: ReadDoc>(
+    doc: &D,
+    obj: &automerge::ObjId,
+    prop: &Prop<'_>,
+    losers: usize,
+) {
+    tracing::debug!(
+        path = %path_of(doc, obj, Some(prop)),
+        losers,
+        "replacing a conflicted object with a new object to clear the conflict",
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn clearing_conflicts<D: ReadDoc>(
+    _doc: &D,
+    _obj: &automerge::ObjId,
+    _prop: &Prop<'_>,
+    _losers: usize,
+) {
+}
+
+/// The path from the root of `doc` to `prop` of `obj`, or to `obj` if `prop` is `None`, written
+/// as `/` for the root itself
+#[cfg(feature = "tracing")]
+fn path_of<D: ReadDoc>(doc: &D, obj: &automerge::ObjId, prop: Option<&Prop<'_>>) -> String {
+    let mut path = doc
+        .parents(obj)
+        .map(|parents| parents.map(|p| p.prop).collect::<Vec<_>>())
+        .unwrap_or_default();
+    path.reverse();
+    path.extend(prop.map(automerge::Prop::from));
+    if path.is_empty() {
+        "/".to_string()
+    } else {
+        crate::Path::from(path).to_string()
+    }
+}
+
+/// The kind of value at `prop` of `obj`, or of `obj` itself if `prop` is `None`
+#[cfg(feature = "tracing")]
+fn type_at<D: ReadDoc>(doc: &D, obj: &automerge::ObjId, prop: Option<&Prop<'_>>) -> &'static str {
+    let obj_type = match prop {
+        None => doc.object_type(obj),
+        Some(prop) => match doc.get(obj, prop) {
+            Ok(Some((automerge::Value::Object(obj_type), _))) => Some(obj_type),
+            Ok(Some((automerge::Value::Scalar(_), _))) => return "scalar",
+            _ => None,
+        },
+    };
+    match obj_type {
+        Some(automerge::ObjType::Map) => "map",
+        Some(automerge::ObjType::Table) => "table",
+        Some(automerge::ObjType::List) => "list",
+        Some(automerge::ObjType::Text) => "text",
+        None => "nothing",
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    use crate::{reconcile, reconcile_prop};
+
+    /// Records the fields of every span and event from this crate as a `name=value` string
+    #[derive(Clone, Default)]
+    struct Recorder {
+        next_id: Arc<AtomicU64>,
+        lines: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.push_str(&format!(" {}={}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            // automerge has its own instrumentation
+            metadata.target().starts_with("autosurgeon")
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            let mut fields = Fields(span.metadata().name().to_string());
+            span.record(&mut fields);
+            self.lines.lock().unwrap().push(fields.0);
+            span::Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _span: &span::Id, values: &span::Record<'_>) {
+            let mut fields = Fields("record".to_string());
+            values.record(&mut fields);
+            self.lines.lock().unwrap().push(fields.0);
+        }
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields("event".to_string());
+            event.record(&mut fields);
+            self.lines.lock().unwrap().push(fields.0);
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    #[test]
+    fn reconcile_records_paths_op_counts_and_replacements() {
+        let recorder = Recorder::default();
+        let mut doc = automerge::AutoCommit::new();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            reconcile(
+                &mut doc,
+                std::collections::HashMap::from([("numbers", vec![1, 2])]),
+            )
+            .unwrap();
+            reconcile_prop(&mut doc, automerge::ROOT, "numbers", "none").unwrap();
+            reconcile_prop(&mut doc, automerge::ROOT, "numbers", vec![3]).unwrap();
+        });
+        assert_eq!(
+            *recorder.lines.lock().unwrap(),
+            vec![
+                "reconcile path=/ obj_type=map",
+                "record ops=3",
+                "reconcile path=/numbers obj_type=list",
+                "record ops=1",
+                "reconcile path=/numbers obj_type=scalar",
+                "event message=replacing a value with a new object of a different type \
+                 path=/numbers expected=list found=\"none\"",
+                "record ops=2",
+            ]
+        );
+    }
+}
@@ -0,0 +1,640 @@
+//! Reconcile large values in several bounded size transactions
+//!
+//! [`reconcile`](crate::reconcile()) writes everything into whatever transaction the document
+//! currently has open. For very large writes (an initial import, say) this produces one enormous
+//! change, which uses a lot of memory while it is being built and is slow to sync. A
+//! [`TransactionalReconciler`] instead commits whenever the open transaction reaches a maximum
+//! number of operations, reporting progress after each commit.
+//!
+//! ```rust
+//! # use autosurgeon::{Reconcile, TransactionalReconciler};
+//! # use automerge::transaction::CommitOptions;
+//! #[derive(Reconcile)]
+//! struct Catalogue {
+//!     products: Vec<String>,
+//! }
+//!
+//! let catalogue = Catalogue {
+//!     products: (0..100).map(|i| format!("product {}", i)).collect(),
+//! };
+//! let mut doc = automerge::AutoCommit::new();
+//! let changes = TransactionalReconciler::new(&mut doc)
+//!     .max_ops_per_transaction(30)
+//!     .commit_options(CommitOptions::default().with_message("import catalogue"))
+//!     .on_progress(|progress| println!("{} ops written", progress.ops))
+//!     .reconcile(&catalogue)
+//!     .unwrap();
+//! // One op for the `products` list and one for each product
+//! assert_eq!(changes.len(), 4);
+//! ```
+//!
+//! Each transaction is a separate change, so a peer which receives only some of them will see a
+//! partially written value.
+use std::ops::RangeBounds;
+
+use automerge::{self as am, transaction::CommitOptions, AutomergeError, ObjId, Value};
+
+use crate::{Doc, ReadDoc, Reconcile, ReconcileError};
+
+/// How much of a [`TransactionalReconciler::reconcile`] has been committed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Progress {
+    /// The number of transactions committed so far
+    pub transactions: usize,
+    /// The number of operations committed so far
+    pub ops: usize,
+}
+
+type ProgressFn<'a> = dyn FnMut(&Progress) + 'a;
+
+/// Reconciles a value into an [`automerge::AutoCommit`], committing every
+/// [`max_ops_per_transaction`](Self::max_ops_per_transaction) operations
+///
+/// See the [module documentation](self) for an example.
+pub struct TransactionalReconciler<'a> {
+    doc: &'a mut am::AutoCommit,
+    max_ops: usize,
+    options: CommitOptions,
+    on_progress: Option<Box<ProgressFn<'a>>>,
+}
+
+impl<'a> TransactionalReconciler<'a> {
+    /// The default for [`Self::max_ops_per_transaction`]
+    pub const DEFAULT_MAX_OPS: usize = 10_000;
+
+    pub fn new(doc: &'a mut am::AutoCommit) -> Self {
+        Self {
+            doc,
+            max_ops: Self::DEFAULT_MAX_OPS,
+            options: CommitOptions::default(),
+            on_progress: None,
+        }
+    }
+
+    /// Commit whenever the open transaction has this many operations
+    ///
+    /// Splicing text counts as one operation per character inserted or deleted.
+    ///
+    /// # Panics
+    ///
+    /// If `max_ops` is zero
+    pub fn max_ops_per_transaction(mut self, max_ops: usize) -> Self {
+        assert!(max_ops > 0, "max_ops_per_transaction must be at least 1");
+        self.max_ops = max_ops;
+        self
+    }
+
+    /// The options (message and time) to commit every transaction with
+    pub fn commit_options(mut self, options: CommitOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Call `f` after every commit
+    pub fn on_progress<F: FnMut(&Progress) + 'a>(mut self, f: F) -> Self {
+        self.on_progress = Some(Box::new(f));
+        self
+    }
+
+    /// Reconcile `value` with the root of the document, returning the hashes of the changes which
+    /// were committed
+    ///
+    /// Any changes which were already pending in the document are committed as part of the first
+    /// transaction. If reconciling fails then the transactions which were committed before the
+    /// failure are kept and the operations written since the last commit are left pending in the
+    /// document, just as with [`reconcile`](crate::reconcile()).
+    pub fn reconcile<R: Reconcile>(
+        mut self,
+        value: R,
+    ) -> Result<Vec<am::ChangeHash>, ReconcileError> {
+        let mut chunked = Chunked {
+            doc: self.doc,
+            max_ops: self.max_ops,
+            options: &self.options,
+            on_progress: self.on_progress.as_deref_mut(),
+            ops_in_transaction: 0,
+            progress: Progress::default(),
+            hashes: Vec::new(),
+        };
+        crate::reconcile(&mut chunked, value)?;
+        if chunked.ops_in_transaction > 0 {
+            chunked.commit();
+        }
+        Ok(chunked.hashes)
+    }
+}
+
+impl<'a> std::fmt::Debug for TransactionalReconciler<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransactionalReconciler")
+            .field("max_ops", &self.max_ops)
+            .finish()
+    }
+}
+
+/// Reconcile `value` with `doc` in a single change, committed with `options`
+///
+/// Any operations which were already pending in `doc` are committed first, so the change
+/// contains exactly the operations made by the reconcile. If reconciling fails the operations it
+/// made are rolled back and nothing is committed. Returns the hash of the new change, or `None`
+/// if the document already matched `value` so there was nothing to commit.
+///
+/// ```rust
+/// # use autosurgeon::{reconcile_with_options, Reconcile};
+/// # use automerge::transaction::CommitOptions;
+/// #[derive(Reconcile)]
+/// struct Invoice {
+///     total: u64,
+/// }
+///
+/// let mut doc = automerge::AutoCommit::new();
+/// let hash = reconcile_with_options(
+///     &mut doc,
+///     &Invoice { total: 100 },
+///     CommitOptions::default()
+///         .with_message("issue invoice")
+///         .with_time(1_700_000_000),
+/// )
+/// .unwrap()
+/// .unwrap();
+/// let change = doc.get_change_by_hash(&hash).unwrap();
+/// assert_eq!(change.message().map(String::as_str), Some("issue invoice"));
+/// assert_eq!(change.timestamp(), 1_700_000_000);
+/// ```
+pub fn reconcile_with_options<R: Reconcile>(
+    doc: &mut am::AutoCommit,
+    value: R,
+    options: CommitOptions,
+) -> Result<Option<am::ChangeHash>, ReconcileError> {
+    doc.commit();
+    if let Err(e) = crate::reconcile(doc, value) {
+        doc.rollback();
+        return Err(e);
+    }
+    Ok(doc.commit_with(options))
+}
+
+/// Reconcile `value` with `doc` in a new transaction which is only committed if reconciling
+/// succeeds
+///
+/// Reconciling can fail part way through, for example because a value can't be written where the
+/// document has something of a different type, at which point some of the value has already been
+/// written. This reconciles in a transaction of its own and rolls it back if reconciling fails, so
+/// a failed reconcile leaves `doc` exactly as it was. Returns the hash of the new change, or
+/// `None` if the document already matched `value` so there was nothing to commit. For an
+/// [`automerge::AutoCommit`] use [`reconcile_with_options`], which does the same.
+///
+/// ```rust
+/// # use autosurgeon::{reconcile_atomic, Reconcile};
+/// #[derive(Reconcile)]
+/// struct Invoice {
+///     total: u64,
+/// }
+///
+/// let mut doc = automerge::Automerge::new();
+/// let hash = reconcile_atomic(&mut doc, &Invoice { total: 100 }).unwrap();
+/// assert!(hash.is_some());
+///
+/// // A number can't be reconciled into the root of a document, so nothing is written
+/// let heads = doc.get_heads();
+/// assert!(reconcile_atomic(&mut doc, 100_u64).is_err());
+/// assert_eq!(doc.get_heads(), heads);
+/// ```
+pub fn reconcile_atomic<R: Reconcile>(
+    doc: &mut am::Automerge,
+    value: R,
+) -> Result<Option<am::ChangeHash>, ReconcileError> {
+    doc.transact(|tx| crate::reconcile(tx, value))
+        .map(|success| success.hash)
+        .map_err(|failure| failure.error)
+}
+
+/// A [`Doc`] which commits the underlying document every `max_ops` operations
+struct Chunked<'a, 'p> {
+    doc: &'a mut am::AutoCommit,
+    max_ops: usize,
+    options: &'a CommitOptions,
+    on_progress: Option<&'a mut ProgressFn<'p>>,
+    ops_in_transaction: usize,
+    progress: Progress,
+    hashes: Vec<am::ChangeHash>,
+}
+
+impl<'a, 'p> Chunked<'a, 'p> {
+    fn wrote(&mut self, ops: usize) {
+        self.ops_in_transaction += ops;
+        if self.ops_in_transaction >= self.max_ops {
+            self.commit();
+        }
+    }
+
+    fn commit(&mut self) {
+        // `CommitOptions` isn't `Clone`
+        let options = CommitOptions {
+            message: self.options.message.clone(),
+            time: self.options.time,
+        };
+        if let Some(hash) = self.doc.commit_with(options) {
+            self.hashes.push(hash);
+        }
+        self.progress.transactions += 1;
+        self.progress.ops += self.ops_in_transaction;
+        self.ops_in_transaction = 0;
+        if let Some(on_progress) = self.on_progress.as_mut() {
+            on_progress(&self.progress);
+        }
+    }
+}
+
+impl<'a, 'p> ReadDoc for Chunked<'a, 'p> {
+    type Parents<'b>
+        = am::Parents<'b>
+    where
+        Self: 'b;
+
+    fn get_heads(&self) -> Vec<am::ChangeHash> {
+        ReadDoc::get_heads(&*self.doc)
+    }
+
+    fn get<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        ReadDoc::get(&*self.doc, obj, prop)
+    }
+
+    fn get_all<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        ReadDoc::get_all(&*self.doc, obj, prop)
+    }
+
+    fn object_type<O: AsRef<ObjId>>(&self, obj: O) -> Option<am::ObjType> {
+        ReadDoc::object_type(&*self.doc, obj)
+    }
+
+    fn map_range<'b, O, R>(&'b self, obj: O, range: R) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+        R: RangeBounds<String>,
+    {
+        ReadDoc::map_range(&*self.doc, obj, range)
+    }
+
+    fn list_range<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+    ) -> am::iter::ListRange<'_, R> {
+        ReadDoc::list_range(&*self.doc, obj, range)
+    }
+
+    fn length<O: AsRef<ObjId>>(&self, obj: O) -> usize {
+        ReadDoc::length(&*self.doc, obj)
+    }
+
+    fn keys<O: AsRef<ObjId>>(&self, obj: O) -> am::iter::Keys<'_> {
+        ReadDoc::keys(&*self.doc, obj)
+    }
+
+    fn text<O: AsRef<ObjId>>(&self, obj: O) -> Result<String, AutomergeError> {
+        ReadDoc::text(&*self.doc, obj)
+    }
+
+    fn parents<O: AsRef<ObjId>>(&self, obj: O) -> Result<Self::Parents<'_>, AutomergeError> {
+        ReadDoc::parents(&*self.doc, obj)
+    }
+
+    fn get_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Option<(Value<'_>, ObjId)>, AutomergeError> {
+        ReadDoc::get_at(&*self.doc, obj, prop, heads)
+    }
+
+    fn get_all_at<P: Into<am::Prop>>(
+        &self,
+        obj: &ObjId,
+        prop: P,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<(Value<'_>, ObjId)>, AutomergeError> {
+        ReadDoc::get_all_at(&*self.doc, obj, prop, heads)
+    }
+
+    fn map_range_at<'b, O, R>(
+        &'b self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::MapRange<'b, R>
+    where
+        R: RangeBounds<String> + 'b,
+        O: AsRef<ObjId>,
+    {
+        ReadDoc::map_range_at(&*self.doc, obj, range, heads)
+    }
+
+    fn list_range_at<O: AsRef<ObjId>, R: RangeBounds<usize>>(
+        &self,
+        obj: O,
+        range: R,
+        heads: &[am::ChangeHash],
+    ) -> am::iter::ListRange<'_, R> {
+        ReadDoc::list_range_at(&*self.doc, obj, range, heads)
+    }
+
+    fn length_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> usize {
+        ReadDoc::length_at(&*self.doc, obj, heads)
+    }
+
+    fn keys_at<O: AsRef<ObjId>>(&self, obj: O, heads: &[am::ChangeHash]) -> am::iter::Keys<'_> {
+        ReadDoc::keys_at(&*self.doc, obj, heads)
+    }
+
+    fn text_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<String, AutomergeError> {
+        ReadDoc::text_at(&*self.doc, obj, heads)
+    }
+
+    fn parents_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Self::Parents<'_>, AutomergeError> {
+        ReadDoc::parents_at(&*self.doc, obj, heads)
+    }
+
+    fn get_cursor<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        position: usize,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<am::Cursor, AutomergeError> {
+        ReadDoc::get_cursor(&*self.doc, obj, position, at)
+    }
+
+    fn get_cursor_position<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        cursor: &am::Cursor,
+        at: Option<&[am::ChangeHash]>,
+    ) -> Result<usize, AutomergeError> {
+        ReadDoc::get_cursor_position(&*self.doc, obj, cursor, at)
+    }
+
+    fn marks<O: AsRef<ObjId>>(&self, obj: O) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        ReadDoc::marks(&*self.doc, obj)
+    }
+
+    fn marks_at<O: AsRef<ObjId>>(
+        &self,
+        obj: O,
+        heads: &[am::ChangeHash],
+    ) -> Result<Vec<am::marks::Mark<'_>>, AutomergeError> {
+        ReadDoc::marks_at(&*self.doc, obj, heads)
+    }
+}
+
+impl<'a, 'p> Doc for Chunked<'a, 'p> {
+    fn put<O: AsRef<ObjId>, P: Into<am::Prop>, V: Into<am::ScalarValue>>(
+        &mut self,
+        obj: O,
+        prop: P,
+        value: V,
+    ) -> Result<(), AutomergeError> {
+        Doc::put(&mut *self.doc, obj, prop, value)?;
+        self.wrote(1);
+        Ok(())
+    }
+
+    fn put_object<O: AsRef<am::ObjId>, P: Into<am::Prop>>(
+        &mut self,
+        obj: O,
+        prop: P,
+        value: am::ObjType,
+    ) -> Result<ObjId, AutomergeError> {
+        let id = Doc::put_object(&mut *self.doc, obj, prop, value)?;
+        self.wrote(1);
+        Ok(id)
+    }
+
+    fn insert<O: AsRef<ObjId>, V: Into<am::ScalarValue>>(
+        &mut self,
+        obj: O,
+        index: usize,
+        value: V,
+    ) -> Result<(), AutomergeError> {
+        Doc::insert(&mut *self.doc, obj, index, value)?;
+        self.wrote(1);
+        Ok(())
+    }
+
+    fn insert_object<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        index: usize,
+        value: am::ObjType,
+    ) -> Result<ObjId, AutomergeError> {
+        let id = Doc::insert_object(&mut *self.doc, obj, index, value)?;
+        self.wrote(1);
+        Ok(id)
+    }
+
+    fn increment<O: AsRef<ObjId>, P: Into<am::Prop>>(
+        &mut self,
+        obj: O,
+        prop: P,
+        value: i64,
+    ) -> Result<(), AutomergeError> {
+        Doc::increment(&mut *self.doc, obj, prop, value)?;
+        self.wrote(1);
+        Ok(())
+    }
+
+    fn delete<O: AsRef<ObjId>, P: Into<am::Prop>>(
+        &mut self,
+        obj: O,
+        prop: P,
+    ) -> Result<(), AutomergeError> {
+        Doc::delete(&mut *self.doc, obj, prop)?;
+        self.wrote(1);
+        Ok(())
+    }
+
+    fn splice_text<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        pos: usize,
+        del: isize,
+        text: &str,
+    ) -> Result<(), AutomergeError> {
+        Doc::splice_text(&mut *self.doc, obj, pos, del, text)?;
+        self.wrote(del.unsigned_abs() + text.chars().count());
+        Ok(())
+    }
+
+    fn mark<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        mark: am::marks::Mark<'_>,
+        expand: am::marks::ExpandMark,
+    ) -> Result<(), AutomergeError> {
+        Doc::mark(&mut *self.doc, obj, mark, expand)?;
+        self.wrote(1);
+        Ok(())
+    }
+
+    fn unmark<O: AsRef<ObjId>>(
+        &mut self,
+        obj: O,
+        key: &str,
+        start: usize,
+        end: usize,
+        expand: am::marks::ExpandMark,
+    ) -> Result<(), AutomergeError> {
+        Doc::unmark(&mut *self.doc, obj, key, start, end, expand)?;
+        self.wrote(1);
+        Ok(())
+    }
+
+    fn pending_ops(&self) -> usize {
+        // Count the operations we've already committed too, so the count doesn't go backwards
+        // part way through a reconcile
+        self.progress.ops + self.ops_in_transaction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::transaction::{CommitOptions, Transactable};
+
+    use super::{reconcile_atomic, reconcile_with_options, Progress, TransactionalReconciler};
+    use crate::{hydrate_prop, reconcile::MapReconciler};
+
+    struct Numbers(Vec<u64>);
+
+    impl crate::Reconcile for Numbers {
+        type Key<'a> = crate::reconcile::NoKey;
+        fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+            let mut m = reconciler.map()?;
+            m.put("numbers", &self.0)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn commits_in_chunks() {
+        let mut doc = automerge::AutoCommit::new();
+        let numbers = Numbers((0..25).collect());
+        let mut progress = Vec::new();
+        let hashes = TransactionalReconciler::new(&mut doc)
+            .max_ops_per_transaction(10)
+            .commit_options(CommitOptions::default().with_message("numbers"))
+            .on_progress(|p| progress.push(*p))
+            .reconcile(&numbers)
+            .unwrap();
+
+        // One op for the list and one for each number
+        assert_eq!(hashes.len(), 3);
+        assert_eq!(
+            progress,
+            vec![
+                Progress {
+                    transactions: 1,
+                    ops: 10
+                },
+                Progress {
+                    transactions: 2,
+                    ops: 20
+                },
+                Progress {
+                    transactions: 3,
+                    ops: 26
+                },
+            ]
+        );
+        for hash in &hashes {
+            let change = doc.get_change_by_hash(hash).unwrap();
+            assert_eq!(change.message().map(String::as_str), Some("numbers"));
+        }
+        assert_eq!(doc.get_heads(), vec![hashes[2]]);
+
+        let hydrated: Vec<u64> = hydrate_prop(&doc, &automerge::ROOT, "numbers").unwrap();
+        assert_eq!(hydrated, numbers.0);
+    }
+
+    #[test]
+    fn reconcile_in_one_change() {
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(automerge::ROOT, "other", "pending").unwrap();
+        let hash = reconcile_with_options(
+            &mut doc,
+            Numbers(vec![1, 2, 3]),
+            CommitOptions::default().with_message("numbers"),
+        )
+        .unwrap()
+        .unwrap();
+        let change = doc.get_change_by_hash(&hash).unwrap();
+        assert_eq!(change.message().map(String::as_str), Some("numbers"));
+        // The pending put is committed separately
+        assert_eq!(change.len(), 4);
+        assert_eq!(doc.get_changes(&[]).len(), 2);
+
+        // Nothing changed so there is nothing to commit
+        let hash = reconcile_with_options(
+            &mut doc,
+            Numbers(vec![1, 2, 3]),
+            CommitOptions::default().with_message("again"),
+        )
+        .unwrap();
+        assert_eq!(hash, None);
+    }
+
+    #[test]
+    fn failed_reconcile_is_rolled_back() {
+        let mut doc = automerge::AutoCommit::new();
+        let heads = doc.get_heads();
+        let result = reconcile_with_options(&mut doc, 1_u64, CommitOptions::default());
+        assert!(result.is_err());
+        assert_eq!(doc.get_heads(), heads);
+    }
+
+    #[test]
+    fn failed_atomic_reconcile_is_rolled_back() {
+        let mut doc = automerge::Automerge::new();
+        let hash = reconcile_atomic(&mut doc, Numbers(vec![1, 2])).unwrap();
+        assert_eq!(doc.get_heads(), vec![hash.unwrap()]);
+        assert_eq!(
+            reconcile_atomic(&mut doc, Numbers(vec![1, 2])).unwrap(),
+            None
+        );
+
+        // Writes the list and then fails
+        struct Broken;
+        impl crate::Reconcile for Broken {
+            type Key<'a> = crate::reconcile::NoKey;
+            fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+                let mut m = reconciler.map()?;
+                m.put("numbers", vec![3_u64])?;
+                Err(crate::reconcile::NotUtf8 {
+                    lossy: "\u{FFFD}".to_string(),
+                }
+                .into())
+            }
+        }
+        let heads = doc.get_heads();
+        assert!(reconcile_atomic(&mut doc, Broken).is_err());
+        assert_eq!(doc.get_heads(), heads);
+        let numbers: Vec<u64> = hydrate_prop(&doc, automerge::ROOT, "numbers").unwrap();
+        assert_eq!(numbers, vec![1, 2]);
+    }
+}
@@ -0,0 +1,180 @@
+//! Derive macro adaptors for sequences with set semantics
+//!
+//! A common way to represent a set of flags in an automerge document is as a list of strings. If
+//! the element type has a key (for example a unit enum which derives [`Reconcile`], the key of
+//! which is the variant name) then this module can be used to treat a `Vec` as a set: duplicate
+//! elements (as determined by [`Reconcile::key`]) are dropped when reconciling and when hydrating,
+//! keeping the first occurrence.
+//!
+//! ```
+//! # use autosurgeon::{Reconcile, Hydrate};
+//! #[derive(Debug, Clone, PartialEq, Reconcile, Hydrate)]
+//! enum Permission {
+//!     Read,
+//!     Write,
+//! }
+//!
+//! #[derive(Reconcile, Hydrate)]
+//! struct User {
+//!     #[autosurgeon(with = "autosurgeon::unique_list")]
+//!     permissions: Vec<Permission>,
+//! }
+//! ```
+//!
+//! Elements which don't have a key (i.e. [`Reconcile::key`] returns anything other than
+//! [`LoadKey::Found`]) are never considered duplicates.
+//!
+//! If you want deterministic output regardless of the order in which elements were added, use
+//! [`sorted`] instead, which additionally sorts the elements (which must implement [`Ord`]) before
+//! writing them to the document and after hydrating them.
+use crate::{reconcile::LoadKey, Hydrate, HydrateError, Prop, ReadDoc, Reconcile, Reconciler};
+
+pub fn reconcile<'a, T, I, R>(items: I, reconciler: R) -> Result<(), R::Error>
+where
+    T: Reconcile + 'a,
+    I: IntoIterator<Item = &'a T>,
+    R: Reconciler,
+{
+    dedup(items.into_iter().collect()).reconcile(reconciler)
+}
+
+pub fn hydrate<'a, D, T, C>(
+    doc: &'a D,
+    obj: &automerge::ObjId,
+    prop: Prop<'a>,
+) -> Result<C, HydrateError>
+where
+    D: ReadDoc,
+    T: Hydrate + Reconcile,
+    C: FromIterator<T>,
+{
+    let items: Vec<T> = crate::hydrate_prop(doc, obj, prop)?;
+    let keep = dedup_mask(&items);
+    Ok(items
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(item, keep)| keep.then_some(item))
+        .collect())
+}
+
+/// Like the parent module, but elements are sorted before being written to the document and
+/// after being hydrated from it
+///
+/// ```
+/// # use autosurgeon::{Reconcile, Hydrate};
+/// #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Reconcile, Hydrate)]
+/// enum Permission {
+///     Read,
+///     Write,
+/// }
+///
+/// #[derive(Reconcile, Hydrate)]
+/// struct User {
+///     #[autosurgeon(with = "autosurgeon::unique_list::sorted")]
+///     permissions: Vec<Permission>,
+/// }
+/// ```
+pub mod sorted {
+    use crate::{Hydrate, HydrateError, Prop, ReadDoc, Reconcile, Reconciler};
+
+    pub fn reconcile<'a, T, I, R>(items: I, reconciler: R) -> Result<(), R::Error>
+    where
+        T: Reconcile + Ord + 'a,
+        I: IntoIterator<Item = &'a T>,
+        R: Reconciler,
+    {
+        let mut items = items.into_iter().collect::<Vec<_>>();
+        items.sort();
+        super::dedup(items).reconcile(reconciler)
+    }
+
+    pub fn hydrate<'a, D, T, C>(
+        doc: &'a D,
+        obj: &automerge::ObjId,
+        prop: Prop<'a>,
+    ) -> Result<C, HydrateError>
+    where
+        D: ReadDoc,
+        T: Hydrate + Reconcile + Ord,
+        C: FromIterator<T>,
+    {
+        let mut items: Vec<T> = super::hydrate(doc, obj, prop)?;
+        items.sort();
+        Ok(items.into_iter().collect())
+    }
+}
+
+fn dedup<T: Reconcile>(items: Vec<&T>) -> Vec<&T> {
+    let keep = dedup_mask(&items);
+    items
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(item, keep)| keep.then_some(item))
+        .collect()
+}
+
+// Returns a mask which is `true` for every element which is not a duplicate of an earlier element.
+// Keys are not required to be `Hash` or `Ord` so this is quadratic, but the lists this module is
+// intended for are small.
+fn dedup_mask<T: Reconcile>(items: &[T]) -> Vec<bool> {
+    let mut seen: Vec<T::Key<'_>> = Vec::new();
+    items
+        .iter()
+        .map(|item| match item.key() {
+            LoadKey::Found(k) => {
+                if seen.contains(&k) {
+                    false
+                } else {
+                    seen.push(k);
+                    true
+                }
+            }
+            _ => true,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::ActorId;
+    use automerge_test::{assert_doc, list, map};
+
+    use crate::{hydrate_prop, reconcile_prop};
+
+    #[test]
+    fn reconcile_drops_duplicates() {
+        struct Tags(Vec<String>);
+        impl crate::Reconcile for Tags {
+            type Key<'a> = crate::reconcile::NoKey;
+            fn reconcile<R: crate::Reconciler>(&self, reconciler: R) -> Result<(), R::Error> {
+                super::reconcile(&self.0, reconciler)
+            }
+        }
+
+        let mut doc = automerge::AutoCommit::new().with_actor(ActorId::random());
+        let tags = Tags(vec!["b".to_string(), "a".to_string(), "b".to_string()]);
+        reconcile_prop(&mut doc, automerge::ROOT, "tags", &tags).unwrap();
+        assert_doc!(
+            doc.document(),
+            map! {
+                "tags" => { list! { {"b"}, {"a"} } }
+            }
+        );
+    }
+
+    #[test]
+    fn hydrate_drops_duplicates() {
+        let mut doc = automerge::AutoCommit::new();
+        reconcile_prop(&mut doc, automerge::ROOT, "tags", vec!["b", "a", "b"]).unwrap();
+        let tags: Vec<String> = super::hydrate(&doc, &automerge::ROOT, "tags".into()).unwrap();
+        assert_eq!(tags, vec!["b".to_string(), "a".to_string()]);
+
+        let sorted: Vec<String> =
+            super::sorted::hydrate(&doc, &automerge::ROOT, "tags".into()).unwrap();
+        assert_eq!(sorted, vec!["a".to_string(), "b".to_string()]);
+
+        // The plain `Vec` implementation preserves duplicates
+        let all: Vec<String> = hydrate_prop(&doc, &automerge::ROOT, "tags").unwrap();
+        assert_eq!(all.len(), 3);
+    }
+}
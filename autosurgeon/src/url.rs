@@ -0,0 +1,50 @@
+use url::Url;
+
+use crate::{reconcile::NoKey, Hydrate, HydrateError, Reconcile};
+
+/// Reconciled as a string
+impl Reconcile for Url {
+    type Key<'a> = NoKey;
+
+    fn reconcile<R: crate::Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        reconciler.str(self.as_str())
+    }
+}
+
+impl Hydrate for Url {
+    fn hydrate_string(s: &'_ str) -> Result<Self, HydrateError> {
+        Url::parse(s).map_err(|_| HydrateError::unexpected("a URL", format!("{:?}", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use automerge::{transaction::Transactable, ObjId, ReadDoc};
+    use url::Url;
+
+    use crate::{hydrate_prop, reconcile_prop};
+
+    #[test]
+    fn round_trip_urls() {
+        let mut doc = automerge::AutoCommit::new();
+
+        let url = Url::parse("https://example.com/a?b=c").unwrap();
+        reconcile_prop(&mut doc, ObjId::Root, "url", &url).unwrap();
+
+        let (stored, _) = doc.get(ObjId::Root, "url").unwrap().unwrap();
+        assert_eq!(stored.into_string().unwrap(), "https://example.com/a?b=c");
+        let hydrated: Url = hydrate_prop(&doc, ObjId::Root, "url").unwrap();
+        assert_eq!(hydrated, url);
+    }
+
+    #[test]
+    fn invalid_urls_fail_to_hydrate() {
+        let mut doc = automerge::AutoCommit::new();
+        doc.put(ObjId::Root, "url", "not a url").unwrap();
+        let err = hydrate_prop::<_, Url, _, _>(&doc, ObjId::Root, "url").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at url: unexpected \"not a url\", expected a URL"
+        );
+    }
+}
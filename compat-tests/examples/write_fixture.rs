@@ -0,0 +1,12 @@
+//! Write the history from [`compat_tests::write_history`] to the path given as the first argument
+//!
+//! Run this against each new release of autosurgeon to add a fixture for it, see
+//! `fixtures/README.md`.
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: write_fixture <path>");
+    let mut doc = compat_tests::write_history();
+    std::fs::write(&path, doc.save()).unwrap();
+}
@@ -0,0 +1,76 @@
+//! Write a document with the same layout as the one `fixtures/js/write-fixture.mjs` writes
+//! using automerge-js, to the path given as the first argument.
+//!
+//! Strings are scalars, `Text` is a text object, numbers with a fractional part are `f64`s,
+//! `Date`s are timestamps and `null` is a null scalar, which is how the stable API of
+//! `@automerge/automerge` 2.x encodes them.
+use automerge::{transaction::Transactable, ObjType, ReadDoc, ScalarValue, ROOT};
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: write_js_layout <path>");
+
+    let mut doc = automerge::AutoCommit::new().with_actor(compat_tests::first_actor());
+    doc.put(ROOT, "name", "autosurgeon").unwrap();
+    let description = doc.put_object(ROOT, "description", ObjType::Text).unwrap();
+    doc.splice_text(
+        &description,
+        0,
+        0,
+        "Reconcile Rust types with automerge documents",
+    )
+    .unwrap();
+    doc.put(ROOT, "stars", ScalarValue::counter(10)).unwrap();
+    doc.put(ROOT, "created", ScalarValue::Timestamp(1_672_531_200_000))
+        .unwrap();
+    let status = doc.put_object(ROOT, "status", ObjType::Map).unwrap();
+    let active = doc.put_object(&status, "Active", ObjType::Map).unwrap();
+    doc.put(&active, "since", 3).unwrap();
+    let owner = doc.put_object(ROOT, "owner", ObjType::Map).unwrap();
+    put_member(&mut doc, &owner, "Alex", None);
+
+    let tasks = doc.put_object(ROOT, "tasks", ObjType::List).unwrap();
+    let t1 = doc.insert_object(&tasks, 0, ObjType::Map).unwrap();
+    doc.put(&t1, "id", "t1").unwrap();
+    doc.put(&t1, "title", "Write docs").unwrap();
+    doc.put(&t1, "done", false).unwrap();
+    doc.put(&t1, "estimate", 1.5).unwrap();
+    let tags = doc.put_object(&t1, "tags", ObjType::List).unwrap();
+    doc.insert(&tags, 0, "docs").unwrap();
+    let t2 = doc.insert_object(&tasks, 1, ObjType::Map).unwrap();
+    doc.put(&t2, "id", "t2").unwrap();
+    doc.put(&t2, "title", "Release").unwrap();
+    doc.put(&t2, "done", false).unwrap();
+    doc.put(&t2, "estimate", ()).unwrap();
+    doc.put_object(&t2, "tags", ObjType::List).unwrap();
+
+    let members = doc.put_object(ROOT, "members", ObjType::Map).unwrap();
+    let alex = doc.put_object(&members, "alex", ObjType::Map).unwrap();
+    put_member(&mut doc, &alex, "Alex", None);
+    let sam = doc.put_object(&members, "sam", ObjType::Map).unwrap();
+    put_member(&mut doc, &sam, "Sam", Some("sam@example.com"));
+    doc.commit();
+
+    doc.put(&t2, "done", true).unwrap();
+    doc.increment(ROOT, "stars", 5).unwrap();
+    let len = doc.length(&description);
+    doc.splice_text(&description, len, 0, " from JS").unwrap();
+    doc.put(ROOT, "status", "Draft").unwrap();
+    doc.commit();
+
+    std::fs::write(&path, doc.save()).unwrap();
+}
+
+fn put_member(
+    doc: &mut automerge::AutoCommit,
+    obj: &automerge::ObjId,
+    name: &str,
+    email: Option<&str>,
+) {
+    doc.put(obj, "name", name).unwrap();
+    match email {
+        Some(email) => doc.put(obj, "email", email).unwrap(),
+        None => doc.put(obj, "email", ()).unwrap(),
+    }
+}
@@ -0,0 +1,36 @@
+// Writes the document which `js-layout.automerge` mirrors, using the stable API of
+// @automerge/automerge 2.x. Usage:
+//
+//     npm install @automerge/automerge@2
+//     node write-fixture.mjs ../automerge-js-<version>.automerge
+import * as A from "@automerge/automerge"
+import { writeFileSync } from "node:fs"
+
+const actor = "aa".repeat(16)
+let doc = A.from(
+  {
+    name: "autosurgeon",
+    description: new A.Text("Reconcile Rust types with automerge documents"),
+    stars: new A.Counter(10),
+    created: new Date(1672531200000),
+    status: { Active: { since: 3 } },
+    owner: { name: "Alex", email: null },
+    tasks: [
+      { id: "t1", title: "Write docs", done: false, estimate: 1.5, tags: ["docs"] },
+      { id: "t2", title: "Release", done: false, estimate: null, tags: [] },
+    ],
+    members: {
+      alex: { name: "Alex", email: null },
+      sam: { name: "Sam", email: "sam@example.com" },
+    },
+  },
+  actor,
+)
+doc = A.change(doc, d => {
+  d.tasks[1].done = true
+  d.stars.increment(5)
+  d.description.insertAt(d.description.length, ..." from JS")
+  d.status = "Draft"
+})
+
+writeFileSync(process.argv[2], A.save(doc))
@@ -0,0 +1,191 @@
+//! A corpus of documents written by older versions of `autosurgeon` and by other automerge
+//! implementations, along with the types they hydrate into.
+//!
+//! Each file in `fixtures/` is checked in and never regenerated. The tests in `tests/golden.rs`
+//! load them, hydrate them into [`Project`], reconcile modifications and check that the results
+//! stay the same from release to release. Adding a fixture for a new release is a matter of
+//! running the `write_fixture` example against that release, see `fixtures/README.md`.
+//!
+//! The types in this module must only use features which are available in every version of
+//! `autosurgeon` which has a fixture, so that the example which writes the fixtures keeps
+//! compiling against all of them.
+use std::collections::BTreeMap;
+
+use automerge::ActorId;
+use autosurgeon::{Counter, Hydrate, HydrateError, Reconcile, Reconciler, Text};
+
+#[derive(Debug, Clone, Hydrate, Reconcile)]
+pub struct Project {
+    pub name: String,
+    pub description: Text,
+    pub stars: Counter,
+    pub created: Timestamp,
+    pub status: Status,
+    pub owner: Option<Member>,
+    pub tasks: Vec<Task>,
+    pub members: BTreeMap<String, Member>,
+}
+
+// `Counter` only implements `PartialEq` in newer versions of autosurgeon
+impl PartialEq for Project {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.description == other.description
+            && self.stars.value() == other.stars.value()
+            && self.created == other.created
+            && self.status == other.status
+            && self.owner == other.owner
+            && self.tasks == other.tasks
+            && self.members == other.members
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Hydrate, Reconcile)]
+pub struct Task {
+    #[key]
+    pub id: String,
+    pub title: String,
+    pub done: bool,
+    pub estimate: Option<f64>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Hydrate, Reconcile)]
+pub struct Member {
+    pub name: String,
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Hydrate, Reconcile)]
+pub enum Status {
+    Draft,
+    Active { since: i64 },
+    Archived(String),
+}
+
+/// Milliseconds since the unix epoch, stored as an automerge timestamp
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestamp(pub i64);
+
+impl Reconcile for Timestamp {
+    type Key<'a> = autosurgeon::reconcile::NoKey;
+
+    fn reconcile<R: Reconciler>(&self, mut reconciler: R) -> Result<(), R::Error> {
+        reconciler.timestamp(self.0)
+    }
+}
+
+impl Hydrate for Timestamp {
+    fn hydrate_timestamp(t: i64) -> Result<Self, HydrateError> {
+        Ok(Timestamp(t))
+    }
+}
+
+/// The actor which writes the initial state of the fixture documents
+pub fn first_actor() -> ActorId {
+    ActorId::from(&[0xaa; 16][..])
+}
+
+/// The actor which makes concurrent changes to the fixture documents
+pub fn second_actor() -> ActorId {
+    ActorId::from(&[0xbb; 16][..])
+}
+
+/// The state which every fixture starts out with
+pub fn initial_project() -> Project {
+    Project {
+        name: "autosurgeon".to_string(),
+        description: Text::with_value("Reconcile Rust types with automerge documents"),
+        stars: Counter::with_value(10),
+        created: Timestamp(1_672_531_200_000),
+        status: Status::Active { since: 3 },
+        owner: Some(Member {
+            name: "Alex".to_string(),
+            email: None,
+        }),
+        tasks: vec![
+            Task {
+                id: "t1".to_string(),
+                title: "Write docs".to_string(),
+                done: false,
+                estimate: Some(1.5),
+                tags: vec!["docs".to_string()],
+            },
+            Task {
+                id: "t2".to_string(),
+                title: "Release".to_string(),
+                done: false,
+                estimate: None,
+                tags: Vec::new(),
+            },
+        ],
+        members: BTreeMap::from([
+            (
+                "alex".to_string(),
+                Member {
+                    name: "Alex".to_string(),
+                    email: None,
+                },
+            ),
+            (
+                "sam".to_string(),
+                Member {
+                    name: "Sam".to_string(),
+                    email: Some("sam@example.com".to_string()),
+                },
+            ),
+        ]),
+    }
+}
+
+/// Write the history which the `autosurgeon-*` fixtures contain
+///
+/// The first actor reconciles [`initial_project`], then the document is forked and both actors
+/// make concurrent changes to it (including conflicting writes to `name`) which are then merged.
+pub fn write_history() -> automerge::AutoCommit {
+    let mut doc = automerge::AutoCommit::new().with_actor(first_actor());
+    autosurgeon::reconcile(&mut doc, initial_project()).unwrap();
+    doc.commit();
+
+    let mut other = doc.fork().with_actor(second_actor());
+
+    let mut project: Project = autosurgeon::hydrate(&doc).unwrap();
+    project.name = "autosurgeon-core".to_string();
+    project.tasks[0].done = true;
+    let len = project.description.as_str().len();
+    project.description.splice(len, 0, " in place");
+    project.stars.increment(1);
+    autosurgeon::reconcile(&mut doc, &project).unwrap();
+    doc.commit();
+
+    let mut project: Project = autosurgeon::hydrate(&other).unwrap();
+    project.name = "surgeon".to_string();
+    project.tasks.insert(
+        0,
+        Task {
+            id: "t3".to_string(),
+            title: "Triage issues".to_string(),
+            done: false,
+            estimate: Some(0.5),
+            tags: vec!["maintenance".to_string()],
+        },
+    );
+    project.members.remove("sam");
+    project.stars.increment(2);
+    autosurgeon::reconcile(&mut other, &project).unwrap();
+    other.commit();
+
+    doc.merge(&mut other).unwrap();
+    doc
+}
+
+/// Load a fixture from the `fixtures` directory
+pub fn load(name: &str) -> automerge::AutoCommit {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name);
+    let bytes = std::fs::read(&path)
+        .unwrap_or_else(|e| panic!("unable to read fixture {}: {}", path.display(), e));
+    automerge::AutoCommit::load(&bytes)
+        .unwrap_or_else(|e| panic!("unable to load fixture {}: {}", path.display(), e))
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use automerge::{ReadDoc, ROOT};
+use autosurgeon::{hydrate, reconcile, Counter, Text};
+use compat_tests::{load, Member, Project, Status, Task, Timestamp};
+
+/// Every fixture, along with the state it is expected to hydrate into
+fn fixtures() -> Vec<(&'static str, Project)> {
+    vec![
+        ("autosurgeon-0.8.5.automerge", autosurgeon_0_8_5()),
+        ("js-layout.automerge", js_layout()),
+    ]
+}
+
+fn alex() -> Member {
+    Member {
+        name: "Alex".to_string(),
+        email: None,
+    }
+}
+
+fn sam() -> Member {
+    Member {
+        name: "Sam".to_string(),
+        email: Some("sam@example.com".to_string()),
+    }
+}
+
+fn task(id: &str, title: &str, done: bool, estimate: Option<f64>, tags: &[&str]) -> Task {
+    Task {
+        id: id.to_string(),
+        title: title.to_string(),
+        done,
+        estimate,
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+    }
+}
+
+fn autosurgeon_0_8_5() -> Project {
+    Project {
+        // Both actors wrote `name` concurrently, the write from the greater actor ID wins
+        name: "surgeon".to_string(),
+        description: Text::with_value("Reconcile Rust types with automerge documents in place"),
+        stars: Counter::with_value(13),
+        created: Timestamp(1_672_531_200_000),
+        status: Status::Active { since: 3 },
+        owner: Some(alex()),
+        tasks: vec![
+            task("t3", "Triage issues", false, Some(0.5), &["maintenance"]),
+            task("t1", "Write docs", true, Some(1.5), &["docs"]),
+            task("t2", "Release", false, None, &[]),
+        ],
+        members: BTreeMap::from([("alex".to_string(), alex())]),
+    }
+}
+
+fn js_layout() -> Project {
+    Project {
+        name: "autosurgeon".to_string(),
+        description: Text::with_value("Reconcile Rust types with automerge documents from JS"),
+        stars: Counter::with_value(15),
+        created: Timestamp(1_672_531_200_000),
+        status: Status::Draft,
+        owner: Some(alex()),
+        tasks: vec![
+            task("t1", "Write docs", false, Some(1.5), &["docs"]),
+            task("t2", "Release", true, None, &[]),
+        ],
+        members: BTreeMap::from([("alex".to_string(), alex()), ("sam".to_string(), sam())]),
+    }
+}
+
+#[test]
+fn fixtures_hydrate() {
+    for (name, expected) in fixtures() {
+        let doc = load(name);
+        let project: Project = hydrate(&doc).unwrap();
+        assert_eq!(project, expected, "fixture {}", name);
+    }
+}
+
+#[test]
+fn hydrating_does_not_resolve_conflicts() {
+    let doc = load("autosurgeon-0.8.5.automerge");
+    assert_eq!(doc.get_all(ROOT, "name").unwrap().len(), 2);
+}
+
+#[test]
+fn reconciling_an_unchanged_value_makes_no_changes() {
+    let mut doc = load("js-layout.automerge");
+    let heads = doc.get_heads();
+    let project: Project = hydrate(&doc).unwrap();
+    reconcile(&mut doc, &project).unwrap();
+    doc.commit();
+    assert_eq!(doc.get_heads(), heads);
+}
+
+#[test]
+fn reconciling_an_unchanged_value_only_resolves_conflicts() {
+    let mut doc = load("autosurgeon-0.8.5.automerge");
+    let project: Project = hydrate(&doc).unwrap();
+    reconcile(&mut doc, &project).unwrap();
+    let hash = doc.commit().unwrap();
+    assert_eq!(doc.get_change_by_hash(&hash).unwrap().len(), 1);
+    let names = doc.get_all(ROOT, "name").unwrap();
+    assert_eq!(names.len(), 1);
+    assert_eq!(names[0].0, automerge::Value::from("surgeon"));
+}
+
+#[test]
+fn modifications_update_existing_objects() {
+    for (name, expected) in fixtures() {
+        let mut doc = load(name);
+        let (_, description) = doc.get(ROOT, "description").unwrap().unwrap();
+        let (_, tasks) = doc.get(ROOT, "tasks").unwrap().unwrap();
+        let t1_index = expected.tasks.iter().position(|t| t.id == "t1").unwrap();
+        let (_, t1) = doc.get(&tasks, t1_index).unwrap().unwrap();
+
+        let mut project: Project = hydrate(&doc).unwrap();
+        project.description.splice(0, 0, "Surgically ");
+        project.stars.increment(1);
+        project.status = Status::Archived("shipped".to_string());
+        project.owner.as_mut().unwrap().email = Some("alex@example.com".to_string());
+        project.tasks.retain(|t| t.id != "t2");
+        project
+            .tasks
+            .push(task("t4", "Celebrate", false, None, &["fun"]));
+        let t1_task = project.tasks.iter_mut().find(|t| t.id == "t1").unwrap();
+        t1_task.title = "Write more docs".to_string();
+        t1_task.tags.push("website".to_string());
+        reconcile(&mut doc, &project).unwrap();
+        doc.commit();
+
+        let rehydrated: Project = hydrate(&doc).unwrap();
+        assert_eq!(rehydrated, project, "fixture {}", name);
+        assert_eq!(rehydrated.stars.value(), expected.stars.value() + 1);
+
+        // The text and the retained task were updated in place rather than replaced
+        let (_, new_description) = doc.get(ROOT, "description").unwrap().unwrap();
+        assert_eq!(new_description, description, "fixture {}", name);
+        let t1_index = project.tasks.iter().position(|t| t.id == "t1").unwrap();
+        let (_, new_t1) = doc.get(&tasks, t1_index).unwrap().unwrap();
+        assert_eq!(new_t1, t1, "fixture {}", name);
+    }
+}
+
+#[test]
+fn concurrent_modifications_merge() {
+    for (name, expected) in fixtures() {
+        let mut doc = load(name);
+        let mut other = doc.fork();
+
+        let mut project: Project = hydrate(&doc).unwrap();
+        project.stars.increment(1);
+        project
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == "t1")
+            .unwrap()
+            .title = "Docs".to_string();
+        reconcile(&mut doc, &project).unwrap();
+
+        let mut project: Project = hydrate(&other).unwrap();
+        project.stars.increment(2);
+        project.description.splice(0, 0, "Surgically ");
+        reconcile(&mut other, &project).unwrap();
+
+        doc.merge(&mut other).unwrap();
+        let merged: Project = hydrate(&doc).unwrap();
+        assert_eq!(
+            merged.stars.value(),
+            expected.stars.value() + 3,
+            "fixture {}",
+            name
+        );
+        assert_eq!(
+            merged.description.as_str(),
+            format!("Surgically {}", expected.description.as_str()),
+            "fixture {}",
+            name
+        );
+        let t1 = merged.tasks.iter().find(|t| t.id == "t1").unwrap();
+        assert_eq!(t1.title, "Docs", "fixture {}", name);
+    }
+}
@@ -0,0 +1,4 @@
+//! Tests for the integrations in `autosurgeon::contrib` with other crates
+//!
+//! These live in their own crate so that `autosurgeon` doesn't need a web framework, an async
+//! runtime, and a bundled copy of sqlite as dev-dependencies. The tests are in `tests/`.
@@ -0,0 +1,64 @@
+use axum::{
+    body::Body,
+    extract::FromRequest,
+    http::{header, Request, StatusCode},
+    response::IntoResponse,
+};
+
+use autosurgeon::{
+    contrib::axum::{Automerge, CONTENT_TYPE},
+    hydrate_from_bytes, to_new_document, Hydrate, Reconcile,
+};
+
+#[derive(Debug, Clone, PartialEq, Hydrate, Reconcile)]
+struct Todo {
+    title: String,
+    done: bool,
+}
+
+fn todo() -> Todo {
+    Todo {
+        title: "write tests".to_string(),
+        done: false,
+    }
+}
+
+fn request(body: Vec<u8>) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn extracts_a_value_from_a_posted_document() {
+    let body = to_new_document(todo()).unwrap().save();
+    let Automerge(extracted) = Automerge::<Todo>::from_request(request(body), &())
+        .await
+        .unwrap();
+    assert_eq!(extracted, todo());
+}
+
+#[tokio::test]
+async fn rejects_a_body_which_is_not_a_document() {
+    let rejection = Automerge::<Todo>::from_request(request(b"not a document".to_vec()), &())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        rejection.into_response().status(),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+}
+
+#[tokio::test]
+async fn responds_with_a_document() {
+    let response = Automerge(todo()).into_response();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let hydrated: Todo = hydrate_from_bytes(&body).unwrap();
+    assert_eq!(hydrated, todo());
+}
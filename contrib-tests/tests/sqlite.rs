@@ -0,0 +1,65 @@
+use autosurgeon::{contrib::sqlite::DocumentStore, hydrate, to_new_document, Hydrate, Reconcile};
+
+#[derive(Debug, Clone, PartialEq, Hydrate, Reconcile)]
+struct Profile {
+    names: Vec<String>,
+    tags: Vec<String>,
+}
+
+fn store() -> DocumentStore {
+    DocumentStore::new(rusqlite::Connection::open_in_memory().unwrap()).unwrap()
+}
+
+fn alex() -> Profile {
+    Profile {
+        names: Vec::new(),
+        tags: vec!["admin".to_string()],
+    }
+}
+
+#[test]
+fn values_round_trip() {
+    let mut store = store();
+    assert_eq!(store.get::<Profile>("alex").unwrap(), None);
+    store.put("alex", &alex()).unwrap();
+    assert_eq!(store.get::<Profile>("alex").unwrap(), Some(alex()));
+    assert!(store.delete("alex").unwrap());
+    assert!(!store.delete("alex").unwrap());
+    assert_eq!(store.get::<Profile>("alex").unwrap(), None);
+}
+
+#[test]
+fn writes_are_reconciled_into_the_stored_document() {
+    let mut store = store();
+    store.put("alex", &alex()).unwrap();
+    let mut renamed = alex();
+    renamed.names.push("Alexandra".to_string());
+    store.put("alex", &renamed).unwrap();
+    let tags = store
+        .update("alex", |p: &mut Profile| {
+            p.tags.push("owner".to_string());
+            p.tags.len()
+        })
+        .unwrap();
+    assert_eq!(tags, Some(2));
+    assert_eq!(
+        store
+            .update("sam", |_: &mut Profile| unreachable!())
+            .unwrap(),
+        None
+    );
+
+    let mut doc = store.load("alex").unwrap().unwrap();
+    assert_eq!(doc.get_changes(&[]).len(), 3);
+    let profile: Profile = hydrate(&doc).unwrap();
+    assert_eq!(profile.names, vec!["Alexandra"]);
+    assert_eq!(profile.tags, vec!["admin", "owner"]);
+}
+
+#[test]
+fn documents_from_other_peers_can_be_saved() {
+    let store = store();
+    let mut doc = to_new_document(alex()).unwrap();
+    store.save("alex", &mut doc).unwrap();
+    assert_eq!(store.get::<Profile>("alex").unwrap(), Some(alex()));
+}